@@ -0,0 +1,61 @@
+// Build metadata for `ecce version`: the git commit and the build date
+use std::path::Path;
+use std::process::Command;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+fn main() {
+    let hash = git(&["rev-parse", "--short", "HEAD"]).unwrap_or_else(|| "unknown".to_string());
+    println!("cargo:rustc-env=ECCE_GIT_HASH={}", hash);
+
+    // Reproducible builds pin the date with SOURCE_DATE_EPOCH
+    let seconds = std::env::var("SOURCE_DATE_EPOCH")
+        .ok()
+        .and_then(|epoch| epoch.parse::<u64>().ok())
+        .unwrap_or_else(|| {
+            SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map_or(0, |d| d.as_secs())
+        });
+    println!("cargo:rustc-env=ECCE_BUILD_DATE={}", date(seconds / 86_400));
+
+    // Rebuild when the checked-out commit changes, not on every source edit
+    println!("cargo:rerun-if-env-changed=SOURCE_DATE_EPOCH");
+    println!("cargo:rerun-if-changed=build.rs");
+    // Asked of git, since in a worktree `.git` is a file pointing elsewhere
+    if let Some(head) = git(&["rev-parse", "--git-path", "HEAD"]) {
+        println!("cargo:rerun-if-changed={}", head);
+        if let Some(reference) = git(&["symbolic-ref", "-q", "HEAD"]) {
+            // A branch ref lives in its own file until git packs it
+            let loose = git(&["rev-parse", "--git-path", &reference])
+                .filter(|path| Path::new(path).exists());
+            if let Some(path) = loose.or_else(|| git(&["rev-parse", "--git-path", "packed-refs"])) {
+                println!("cargo:rerun-if-changed={}", path);
+            }
+        }
+    }
+}
+
+/// Trimmed output of a git command, if it succeeds with any
+fn git(args: &[&str]) -> Option<String> {
+    Command::new("git")
+        .args(args)
+        .output()
+        .ok()
+        .filter(|output| output.status.success())
+        .map(|output| String::from_utf8_lossy(&output.stdout).trim().to_string())
+        .filter(|output| !output.is_empty())
+}
+
+/// `YYYY-MM-DD` for a count of days since 1970-01-01 (Howard Hinnant's civil_from_days)
+fn date(days: u64) -> String {
+    let z = days as i64 + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1_460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    format!("{:04}-{:02}-{:02}", year, month, day)
+}
@@ -1,102 +1,908 @@
 use anyhow::{Context, Result};
+use std::collections::BTreeMap;
 use std::fs;
-use std::process::Command;
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
+use tokio::process::{Child, Command};
 use tempfile::NamedTempFile;
 use std::io::Write;
 
-use crate::config::{Agent, Task};
+use serde::Deserialize;
 
-#[derive(Clone)]
-struct Message {
-    role: String,
-    content: String,
+use crate::capability::{Capabilities, Capability, SystemPromptDelivery};
+use crate::citation;
+use crate::context_command;
+use crate::context_env;
+use crate::config::{self, Agent, Config, ModelAlias, Profile, Task, TaskConstraints, TaskOutput, CLAUDE_CODE_SERVICE};
+use crate::history::{estimate_tokens, ContextFile, ContextSnapshot};
+use crate::pattern::PatternOptions;
+use crate::provenance::content_hash;
+use crate::slots;
+use crate::template::{self, language_instruction, Message};
+
+/// Thinking budget used when an agent enables extended thinking
+const DEFAULT_THINKING_TOKENS: u32 = 10_000;
+
+/// Default prompt budget, leaving headroom below Claude's 200k-token context window
+pub const DEFAULT_MAX_PROMPT_TOKENS: u64 = 150_000;
+
+/// Corrective turns sent when a response fails its task's output checks
+const MAX_FIX_ATTEMPTS: usize = 2;
+
+/// Permission modes accepted by the claude CLI's `--permission-mode` flag
+pub const PERMISSION_MODES: &[&str] = &["default", "acceptEdits", "plan", "bypassPermissions"];
+
+/// Whether claude runs apart from the terminal's Ctrl+C (see `shield_from_interrupt`)
+static SHIELDED: AtomicBool = AtomicBool::new(false);
+
+/// Process ids of shielded claude processes still running
+static SHIELDED_CHILDREN: Mutex<Vec<u32>> = Mutex::new(Vec::new());
+
+/// Start claude processes in a process group of their own, so a Ctrl+C at the
+/// terminal reaches only ecce, which decides whether generations finish.
+/// Whoever calls this must end them with `kill_shielded` before exiting.
+pub fn shield_from_interrupt() {
+    SHIELDED.store(true, Ordering::Relaxed);
+}
+
+/// End every running shielded claude process and its children
+pub fn kill_shielded() {
+    for pid in SHIELDED_CHILDREN.lock().unwrap().drain(..) {
+        // The group id is the leader's pid; `-- -pid` signals the whole group
+        let _ = std::process::Command::new("kill")
+            .args(["-TERM", "--", &format!("-{}", pid)])
+            .stderr(Stdio::null())
+            .status();
+    }
+}
+
+/// Keeps a shielded process in `SHIELDED_CHILDREN` while it runs
+struct ShieldedChild(Option<u32>);
+
+impl ShieldedChild {
+    fn register(pid: Option<u32>) -> Self {
+        if let Some(pid) = pid {
+            SHIELDED_CHILDREN.lock().unwrap().push(pid);
+        }
+        Self(pid)
+    }
+}
+
+impl Drop for ShieldedChild {
+    fn drop(&mut self) {
+        if let Some(pid) = self.0 {
+            SHIELDED_CHILDREN.lock().unwrap().retain(|p| *p != pid);
+        }
+    }
+}
+
+/// Runtime options for claude invocations that come from the command line
+/// rather than from the agent configuration
+#[derive(Debug, Clone, Default)]
+pub struct RunOptions {
+    /// Forward `--dangerously-skip-permissions` so unattended runs never stall on a prompt
+    pub skip_permissions: bool,
+    /// Replay conversation history as text instead of resuming the claude session
+    pub no_resume: bool,
+    /// Estimated prompt token budget (defaults to `DEFAULT_MAX_PROMPT_TOKENS`)
+    pub max_prompt_tokens: Option<u64>,
+    /// Model to use instead of the agent's own (e.g. from a preset)
+    pub model: Option<String>,
+    /// Directory for the system prompt temp file (defaults to the system temp dir)
+    pub temp_dir: Option<PathBuf>,
+    /// Send the system prompt on stdin ahead of the user prompt instead of in a temp file
+    pub prompts_via_stdin: bool,
+    /// Model aliases from the config, expanded for `profile`
+    pub model_aliases: BTreeMap<String, ModelAlias>,
+    /// Profile generations run against (the active claude-code one unless switched)
+    pub profile: Option<Profile>,
+    /// Ask the agent to tag what it takes from context files with `[source: <path>]`
+    pub cite: bool,
+    /// Most claude processes running at once on this machine (see `slots`)
+    pub max_concurrent: Option<usize>,
+    /// Programs `cmd://` context entries may run (see `context_command`)
+    pub context_commands: Vec<String>,
+    /// Environment variables `env://` context entries and `{{env.VAR}}` may read (see `context_env`)
+    pub context_env: Vec<String>,
+}
+
+impl RunOptions {
+    /// Options set in the config file; command-line options are left at their defaults
+    pub fn from_config(config: &Config) -> Self {
+        Self {
+            max_prompt_tokens: config.max_prompt_tokens,
+            temp_dir: config.temp_dir.as_ref().map(PathBuf::from),
+            prompts_via_stdin: config.prompts_via_stdin,
+            model_aliases: config.model_aliases.clone(),
+            profile: config.get_active_profile(CLAUDE_CODE_SERVICE),
+            max_concurrent: config.max_concurrent,
+            context_commands: config.context_commands.clone(),
+            context_env: config.context_env.clone(),
+            ..Default::default()
+        }
+    }
 }
 
+/// Create a temp file readable only by the current user, in `dir` if given.
+/// The directory is created private to the user if it doesn't exist.
+fn private_temp_file(dir: Option<&Path>) -> Result<NamedTempFile> {
+    let mut builder = tempfile::Builder::new();
+    builder.prefix("ecce-prompt-");
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        builder.permissions(fs::Permissions::from_mode(0o600));
+    }
+
+    match dir {
+        Some(dir) => {
+            if !dir.is_dir() {
+                fs::create_dir_all(dir)
+                    .with_context(|| format!("Failed to create temp directory {}", dir.display()))?;
+                #[cfg(unix)]
+                {
+                    use std::os::unix::fs::PermissionsExt;
+                    fs::set_permissions(dir, fs::Permissions::from_mode(0o700))?;
+                }
+            }
+            builder.tempfile_in(dir)
+        }
+        None => builder.tempfile(),
+    }
+    .context("Failed to create temporary file for system prompt")
+}
+
+/// The subset of `claude --output-format json` output that ecce uses
+#[derive(Debug, Deserialize)]
+struct CliResult {
+    #[serde(default)]
+    result: String,
+    #[serde(default)]
+    session_id: Option<String>,
+    #[serde(default)]
+    is_error: bool,
+}
+
+/// Parse claude's stdout into the response text and session id.
+/// Falls back to plain text for executables that do not emit JSON.
+fn parse_cli_output(stdout: &str) -> Result<(String, Option<String>)> {
+    match serde_json::from_str::<CliResult>(stdout.trim()) {
+        Ok(parsed) if parsed.is_error => {
+            Err(anyhow::anyhow!("Claude Code returned an error: {}", parsed.result))
+        }
+        Ok(parsed) => Ok((parsed.result.trim().to_string(), parsed.session_id)),
+        Err(_) => Ok((stdout.trim().to_string(), None)),
+    }
+}
+
+/// Text streamed by a `stream-json` event line, if it carries any
+fn stream_delta(line: &str) -> Option<String> {
+    let event: serde_json::Value = serde_json::from_str(line).ok()?;
+    if event["type"] != "stream_event" || event["event"]["type"] != "content_block_delta" {
+        return None;
+    }
+    let delta = &event["event"]["delta"];
+    match delta["type"] == "text_delta" {
+        true => delta["text"].as_str().map(str::to_string),
+        false => None,
+    }
+}
+
+/// Parse claude's `stream-json` stdout into the response text, session id,
+/// and whether any text was streamed. Falls back to `parse_cli_output` for
+/// executables that don't stream.
+fn parse_stream_output(stdout: &str) -> Result<(String, Option<String>, bool)> {
+    let streamed = stdout.lines().any(|line| stream_delta(line).is_some());
+    let result = stdout.lines().rev().find(|line| {
+        serde_json::from_str::<serde_json::Value>(line).is_ok_and(|event| event["type"] == "result")
+    });
+    let (response, session_id) = parse_cli_output(result.unwrap_or(stdout))?;
+    Ok((response, session_id, streamed))
+}
+
+/// Wait for claude while passing each streamed text delta to `on_text`
+async fn stream_output(mut child: Child, on_text: &mut (dyn FnMut(&str) + Send)) -> std::io::Result<std::process::Output> {
+    let stdout = child.stdout.take().expect("stdout is piped");
+    let mut stderr = child.stderr.take().expect("stderr is piped");
+    let read_stdout = async {
+        let mut lines = BufReader::new(stdout).lines();
+        let mut all = String::new();
+        while let Some(line) = lines.next_line().await? {
+            if let Some(text) = stream_delta(&line) {
+                on_text(&text);
+            }
+            all.push_str(&line);
+            all.push('\n');
+        }
+        Ok::<_, std::io::Error>(all)
+    };
+    let read_stderr = async {
+        let mut buf = Vec::new();
+        stderr.read_to_end(&mut buf).await.map(|_| buf)
+    };
+    let (stdout, stderr) = tokio::join!(read_stdout, read_stderr);
+    Ok(std::process::Output {
+        status: child.wait().await?,
+        stdout: stdout?.into_bytes(),
+        stderr: stderr?,
+    })
+}
+
+/// Whether a response is exactly one fenced Mermaid or PlantUML block
+fn is_single_diagram(response: &str) -> bool {
+    let response = response.trim();
+    let Some(rest) = response
+        .strip_prefix("```mermaid")
+        .or_else(|| response.strip_prefix("```plantuml"))
+    else {
+        return false;
+    };
+    let Some(body) = rest.strip_suffix("```") else {
+        return false;
+    };
+
+    body.starts_with('\n') && !body.trim().is_empty() && !body.contains("```")
+}
+
+/// Descriptions of each constraint the response violates
+fn constraint_violations(constraints: &TaskConstraints, response: &str) -> Vec<String> {
+    let mut violations = Vec::new();
+
+    if let Some(max) = constraints.max_words {
+        // Count tokens with letters or digits so list markers aren't words
+        let words = response
+            .split_whitespace()
+            .filter(|w| w.chars().any(char::is_alphanumeric))
+            .count();
+        if words > max {
+            violations.push(format!("{} words (max {})", words, max));
+        }
+    }
+
+    if let Some(max) = constraints.max_bullets {
+        let bullets = response.lines().filter(|l| is_bullet(l)).count();
+        if bullets > max {
+            violations.push(format!("{} bullet points (max {})", bullets, max));
+        }
+    }
+
+    let lower = response.to_lowercase();
+    for phrase in &constraints.must_not_contain {
+        if lower.contains(&phrase.to_lowercase()) {
+            violations.push(format!("contains \"{}\"", phrase));
+        }
+    }
+
+    violations
+}
+
+/// Markdown list item: `- `, `* `, `+ `, or `1. `
+fn is_bullet(line: &str) -> bool {
+    let line = line.trim_start();
+    if line.starts_with("- ") || line.starts_with("* ") || line.starts_with("+ ") {
+        return true;
+    }
+    let digits = line.chars().take_while(|c| c.is_ascii_digit()).count();
+    digits > 0 && line[digits..].starts_with(". ")
+}
+
+/// Whether a context entry names a file, rather than a `cmd://` command or an `env://` variable
+pub fn is_context_file(entry: &str) -> bool {
+    !context_command::is_command(entry) && !context_env::is_env(entry)
+}
+
+#[derive(Clone)]
 pub struct ClaudeAgent {
     claude_executable: String,
     agent: Agent,
     task: Option<Task>,
+    options: RunOptions,
     conversation_history: Vec<Message>,
+    session_id: Option<String>,
+    /// API endpoint and key from a profile switched to mid-session
+    profile_env: Vec<(&'static str, String)>,
+    /// Inputs behind the latest generation
+    last_context: Option<ContextSnapshot>,
+    /// Style instruction sampled from the answered document (see `style::DocumentStyle`)
+    style: Option<String>,
+    /// Length instruction for the room left on the answer's slide (see `fit`)
+    fit: Option<String>,
 }
 
 impl ClaudeAgent {
     pub fn new(claude_executable: String, agent: Agent, task: Option<Task>) -> Self {
+        Self::with_options(claude_executable, agent, task, RunOptions::default())
+    }
+
+    pub fn with_options(
+        claude_executable: String,
+        agent: Agent,
+        task: Option<Task>,
+        options: RunOptions,
+    ) -> Self {
         Self {
             claude_executable,
             agent,
             task,
+            options,
             conversation_history: Vec::new(),
+            session_id: None,
+            profile_env: Vec::new(),
+            last_context: None,
+            style: None,
+            fit: None,
         }
     }
 
-    /// Load context files specified in the agent configuration
-    fn load_context(&self) -> Result<String> {
-        let mut context = String::new();
+    pub fn agent(&self) -> &Agent {
+        &self.agent
+    }
 
-        for file_path in &self.agent.context_files {
-            let content = fs::read_to_string(file_path)
-                .with_context(|| format!("Failed to read context file: {}", file_path))?;
+    pub fn task(&self) -> Option<&Task> {
+        self.task.as_ref()
+    }
+
+    /// Swap in an updated agent/task definition for subsequent generations.
+    /// The claude session is dropped so the new system prompt takes effect;
+    /// conversation history is kept and replayed instead.
+    pub fn reconfigure(&mut self, agent: Agent, task: Option<Task>) {
+        self.agent = agent;
+        self.task = task;
+        self.session_id = None;
+    }
+
+    /// Point subsequent generations at a profile's endpoint and key
+    pub fn use_profile(&mut self, profile: &Profile) {
+        self.profile_env = profile.env_vars().unwrap_or_default();
+        self.options.profile = Some(profile.clone());
+        self.session_id = None;
+    }
+
+    /// Close subsequent prompts with this style instruction, or with none
+    pub fn set_style(&mut self, instruction: Option<String>) {
+        self.style = instruction;
+    }
+
+    /// Close subsequent prompts with this length instruction, or with none
+    pub fn set_fit(&mut self, instruction: Option<String>) {
+        self.fit = instruction;
+    }
+
+    /// The value of an environment variable as the claude subprocess will see it
+    pub fn env_var(&self, name: &str) -> Option<String> {
+        if let Some(value) = self.agent.env.get(name) {
+            return Some(value.clone());
+        }
+        if let Some((_, value)) = self.profile_env.iter().find(|(key, _)| *key == name) {
+            return Some(value.clone());
+        }
+        std::env::var(name).ok().filter(|value| !value.is_empty())
+    }
+
+    /// What went into the latest generation's prompt, for the history
+    pub fn last_context(&self) -> Option<&ContextSnapshot> {
+        self.last_context.as_ref()
+    }
+
+    /// The conversation so far, replayed when the claude session can't be resumed
+    pub fn conversation(&self) -> &[Message] {
+        &self.conversation_history
+    }
+
+    /// The claude session subsequent generations resume, if any
+    pub fn session_id(&self) -> Option<&str> {
+        self.session_id.as_deref()
+    }
+
+    /// Continue a saved conversation: its history and, when still valid, its claude session
+    pub fn restore_conversation(&mut self, history: Vec<Message>, session_id: Option<String>) {
+        self.conversation_history = history;
+        self.session_id = session_id;
+    }
+
+    pub fn agent_name(&self) -> &str {
+        &self.agent.name
+    }
+
+    /// The model in effect: a run override, else the agent's own, with aliases expanded
+    pub fn model(&self) -> Option<String> {
+        self.options
+            .model
+            .as_deref()
+            .or(self.agent.model.as_deref())
+            .map(|model| self.resolve_model(model))
+    }
+
+    /// The model for one pattern, which may name its own with `model=`
+    fn model_for(&self, pattern: &PatternOptions) -> Option<String> {
+        match pattern.model {
+            Some(ref model) => Some(self.resolve_model(model)),
+            None => self.model(),
+        }
+    }
+
+    fn resolve_model(&self, model: &str) -> String {
+        config::resolve_model(&self.options.model_aliases, model, self.options.profile.as_ref())
+    }
+
+    pub fn task_name(&self) -> Option<&str> {
+        self.task.as_ref().map(|t| t.name.as_str())
+    }
+
+    /// Extra CLI arguments derived from the agent's model and permission settings
+    fn cli_args(&self, pattern: &PatternOptions) -> Vec<String> {
+        let mut args = Vec::new();
+
+        if let Some(model) = self.model_for(pattern) {
+            if model != "inherit" {
+                args.push("--model".to_string());
+                args.push(model);
+            }
+        }
+
+        if let Some(ref mode) = self.agent.permission_mode {
+            args.push("--permission-mode".to_string());
+            args.push(mode.clone());
+        }
+
+        if self.options.skip_permissions {
+            args.push("--dangerously-skip-permissions".to_string());
+        }
+
+        args
+    }
+
+    /// Environment variables carrying the agent's generation parameters.
+    /// The claude CLI reads output and thinking limits from the environment.
+    fn generation_env(&self) -> Vec<(&'static str, String)> {
+        let mut env = Vec::new();
+
+        if let Some(max_output_tokens) = self.agent.max_output_tokens {
+            env.push(("CLAUDE_CODE_MAX_OUTPUT_TOKENS", max_output_tokens.to_string()));
+        }
+
+        match self.agent.thinking {
+            Some(true) => env.push(("MAX_THINKING_TOKENS", DEFAULT_THINKING_TOKENS.to_string())),
+            Some(false) => env.push(("MAX_THINKING_TOKENS", "0".to_string())),
+            None => {}
+        }
 
+        env
+    }
+
+    /// The agent's system prompt with each `{{include "file"}}` replaced by the
+    /// file's current text and each `{{env.VAR}}` by the variable's value.
+    /// Relative paths start from the agent's working directory (else
+    /// `~/.config/ecce`), or from the including file's directory.
+    pub fn system_prompt(&self) -> Result<String> {
+        let prompt = &self.agent.system_prompt;
+        if !prompt.contains("{{") {
+            return Ok(prompt.clone());
+        }
+        let base = match self.agent.working_dir {
+            Some(ref dir) => PathBuf::from(dir),
+            None => Config::config_dir()?,
+        };
+        let prompt = template::expand_includes(prompt, &mut |path, parent| {
+            let path = match path.strip_prefix("~/") {
+                Some(rest) => dirs::home_dir().context("Could not find home directory")?.join(rest),
+                None => PathBuf::from(path),
+            };
+            let dir = parent.and_then(|p| Path::new(p).parent()).unwrap_or(&base);
+            let path = dir.join(path);
+            let content = fs::read_to_string(&path)
+                .with_context(|| format!("Failed to read included file: {}", path.display()))?;
+            Ok((path.display().to_string(), content))
+        })
+        .and_then(|prompt| self.expand_env(&prompt))
+        .with_context(|| format!("Failed to build the system prompt of agent '{}'", self.agent.name))?;
+        Ok(prompt)
+    }
+
+    /// `text` with each `{{env.VAR}}` replaced by the variable's value
+    fn expand_env(&self, text: &str) -> Result<String> {
+        template::expand_env(text, &mut |name| {
+            context_env::lookup(name, &self.options.context_env, &self.agent.env)
+        })
+    }
+
+    /// Read the context files, run the `cmd://` context commands, and look up
+    /// the `env://` variables, in the agent's order
+    async fn load_context_files(&self) -> Result<Vec<(String, String)>> {
+        let dir = self.agent.working_dir.as_deref().map(Path::new);
+        let mut files = Vec::new();
+        for entry in &self.agent.context_files {
+            let content = if context_command::is_command(entry) {
+                context_command::run(entry, &self.options.context_commands, dir).await?
+            } else if let Some(name) = entry.strip_prefix(context_env::PREFIX) {
+                context_env::lookup(name, &self.options.context_env, &self.agent.env)?
+            } else {
+                fs::read_to_string(entry).with_context(|| format!("Failed to read context file: {}", entry))?
+            };
+            files.push((entry.clone(), content));
+        }
+        Ok(files)
+    }
+
+    /// Context entries that are files, which citations may point to
+    fn citable_files(&self) -> Vec<String> {
+        self.agent
+            .context_files
+            .iter()
+            .filter(|entry| is_context_file(entry))
+            .cloned()
+            .collect()
+    }
+
+    /// Join loaded context files into the prompt's context section
+    fn format_context(files: &[(String, String)]) -> String {
+        let mut context = String::new();
+
+        for (file_path, content) in files {
             context.push_str(&format!("\n\n--- Context from {} ---\n", file_path));
-            context.push_str(&content);
+            context.push_str(content);
         }
 
-        Ok(context)
+        context
     }
 
-    /// Build the prompt using the task template and question
-    fn build_prompt(&self, question: &str, context: &str) -> String {
-        let template = self
-            .task
+    /// Drop the largest context files and the oldest conversation exchanges until
+    /// the estimated prompt fits the token budget. Returns what was dropped.
+    fn reduce_to_fit(&mut self, template: &str, question: &str, files: &mut Vec<(String, String)>) -> Vec<String> {
+        let limit = self
+            .options
+            .max_prompt_tokens
+            .unwrap_or(DEFAULT_MAX_PROMPT_TOKENS);
+        let mut dropped = Vec::new();
+
+        loop {
+            let prompt = self.build_prompt(template, question, &Self::format_context(files));
+            if estimate_tokens(&prompt) <= limit {
+                break;
+            }
+
+            let largest_file = files
+                .iter()
+                .enumerate()
+                .max_by_key(|(_, (_, content))| content.len())
+                .map(|(idx, (_, content))| (idx, content.len()));
+            let oldest_exchange: usize = self
+                .conversation_history
+                .iter()
+                .take(2)
+                .map(|m| m.content.len())
+                .sum();
+
+            match largest_file {
+                Some((idx, len)) if len >= oldest_exchange => {
+                    let (path, _) = files.remove(idx);
+                    dropped.push(format!("context file {}", path));
+                }
+                _ if !self.conversation_history.is_empty() => {
+                    let n = self.conversation_history.len().min(2);
+                    self.conversation_history.drain(..n);
+                    dropped.push("oldest conversation exchange".to_string());
+                }
+                _ => break,
+            }
+        }
+
+        dropped
+    }
+
+    /// The task's template, or the default instruction without a task
+    fn template(&self) -> &str {
+        self.task
             .as_ref()
             .map(|t| t.template.as_str())
-            .unwrap_or("Answer the following question by creating new slides that explain and elaborate on the concept.");
+            .unwrap_or(template::DEFAULT_TEMPLATE)
+    }
 
-        // Include conversation history
-        let mut prompt = String::new();
+    /// Build the prompt from the rendered task template and the question
+    fn build_prompt(&self, template: &str, question: &str, context: &str) -> String {
+        template::render_prompt(
+            template,
+            &self.conversation_history,
+            context,
+            question,
+            self.output_instruction(),
+        )
+    }
 
-        if !self.conversation_history.is_empty() {
-            prompt.push_str("## Previous Conversation:\n\n");
-            for msg in &self.conversation_history {
-                prompt.push_str(&format!("{}: {}\n\n", msg.role, msg.content));
-            }
-            prompt.push_str("---\n\n");
+    /// Build a follow-up prompt for a resumed session, which already holds
+    /// the context and previous conversation
+    fn build_followup_prompt(&self, question: &str) -> String {
+        template::render_followup(question, self.output_instruction())
+    }
+
+    fn output(&self) -> TaskOutput {
+        self.task.as_ref().map(|t| t.output).unwrap_or_default()
+    }
+
+    /// Closing instruction describing the answer format the task expects
+    fn output_instruction(&self) -> &'static str {
+        self.output().instruction()
+    }
+
+    /// A corrective follow-up if the response breaks the task's output rules
+    fn check_response(&self, response: &str) -> Option<String> {
+        if self.output() == TaskOutput::Diagram && !is_single_diagram(response) {
+            return Some(
+                "Your previous answer was not a single fenced diagram. Reply again with only one ```mermaid or ```plantuml code block and nothing before or after it."
+                    .to_string(),
+            );
         }
 
-        prompt.push_str(&format!(
-            "{}\n\nContext:\n{}\n\nQuestion: {}\n\nPlease provide slide content in Markdown format.",
-            template, context, question
-        ));
+        let violations = self
+            .task
+            .as_ref()
+            .map(|t| constraint_violations(&t.constraints, response))
+            .unwrap_or_default();
+        if violations.is_empty() {
+            None
+        } else {
+            Some(format!(
+                "Your previous answer broke these limits: {}. Rewrite it to fix them, shortening where needed, and reply with only the revised content.",
+                violations.join("; ")
+            ))
+        }
+    }
 
-        prompt
+    /// Detect the claude version and fail clearly if it rejects a flag this agent
+    /// needs. The system prompt never fails: it falls back to whatever the
+    /// version supports.
+    pub async fn check_capabilities(&self) -> Result<Capabilities> {
+        let executable = &self.claude_executable;
+        let capabilities = Capabilities::detect(executable).await;
+
+        if self.agent.permission_mode.is_some() {
+            capabilities.require(Capability::PermissionMode, executable)?;
+        }
+        if self.options.skip_permissions {
+            capabilities.require(Capability::SkipPermissions, executable)?;
+        }
+
+        Ok(capabilities)
     }
 
     /// Call Claude Code executable to generate response
     pub async fn generate_response(&mut self, question: &str) -> Result<String> {
-        // Load context files
-        let context = self.load_context()?;
+        self.generate(question, &PatternOptions::default()).await
+    }
+
+    /// Generate a response honoring per-pattern options such as the answer language
+    pub async fn generate(&mut self, question: &str, options: &PatternOptions) -> Result<String> {
+        let result = self.generate_checked(question, options).await;
+
+        // A failed resume usually means the session is gone; start fresh next time
+        if result.is_err() {
+            self.session_id = None;
+        }
+
+        result
+    }
+
+    /// Generate a reply in a conversation, passing text to `on_text` as it
+    /// streams in. Claude versions that can't stream pass the whole reply at the end.
+    /// Replies aren't held to the task's output checks.
+    pub async fn generate_streamed(
+        &mut self,
+        question: &str,
+        on_text: &mut (dyn FnMut(&str) + Send),
+    ) -> Result<String> {
+        self.last_context = None;
+        let result = self.run_generation(question, &PatternOptions::default(), Some(on_text)).await;
+        if result.is_err() {
+            self.session_id = None;
+        }
+        result
+    }
+
+    /// Generate, then send corrective turns while the response fails the task's checks
+    async fn generate_checked(&mut self, question: &str, options: &PatternOptions) -> Result<String> {
+        self.last_context = None;
+        let mut response = self.run_generation(question, options, None).await?;
+        // Corrective turns are follow-ups; the question's own context is what matters
+        let mut context = self.last_context.take();
 
-        // Build prompt with conversation history
-        let user_prompt = self.build_prompt(question, &context);
+        for corrections in 0..MAX_FIX_ATTEMPTS {
+            match self.check_response(&response) {
+                Some(fix) => {
+                    let result = self.run_generation(&fix, options, None).await;
+                    if let Some(ref mut context) = context {
+                        context.corrections = corrections + 1;
+                    }
+                    self.last_context = context.clone();
+                    response = result?;
+                }
+                None => {
+                    self.last_context = context;
+                    return Ok(response);
+                }
+            }
+        }
+        self.last_context = context;
 
-        // Create a temporary file for the system prompt
-        let mut system_file = NamedTempFile::new()
-            .context("Failed to create temporary file for system prompt")?;
-        writeln!(system_file, "{}", self.agent.system_prompt)
-            .context("Failed to write system prompt to temp file")?;
-        let system_path = system_file.path().to_string_lossy().to_string();
+        if self.check_response(&response).is_some() {
+            eprintln!(
+                "Warning: Response from agent '{}' still fails the task's output checks after {} corrections",
+                self.agent.name, MAX_FIX_ATTEMPTS
+            );
+        }
+        Ok(response)
+    }
+
+    async fn run_generation(
+        &mut self,
+        question: &str,
+        options: &PatternOptions,
+        on_text: Option<&mut (dyn FnMut(&str) + Send)>,
+    ) -> Result<String> {
+        let capabilities = self.check_capabilities().await?;
+        // Without JSON output there is no session id to resume
+        let json_output = !self.options.no_resume
+            && capabilities.supports(Capability::JsonOutput)
+            && capabilities.supports(Capability::Resume);
+
+        let resume_id = if json_output {
+            self.session_id.clone()
+        } else {
+            None
+        };
+        let system_prompt = self.system_prompt()?;
+
+        let mut context = ContextSnapshot {
+            model: self.model_for(options),
+            system_prompt_hash: content_hash(&system_prompt),
+            template: self.template().to_string(),
+            resumed: resume_id.is_some(),
+            lang: options.lang.clone(),
+            ..Default::default()
+        };
+
+        // A resumed session already has the context and history, so only send the question
+        let mut user_prompt = match resume_id {
+            Some(_) => self.build_followup_prompt(question),
+            None => {
+                let mut files = self.load_context_files().await?;
+                let template = self.expand_env(self.template())?;
+                let dropped = self.reduce_to_fit(&template, question, &mut files);
+                if !dropped.is_empty() {
+                    eprintln!(
+                        "Warning: Prompt for agent '{}' exceeded the token budget; dropped {}",
+                        self.agent.name,
+                        dropped.join(", ")
+                    );
+                }
+                context.context_files = files
+                    .iter()
+                    .map(|(path, content)| ContextFile {
+                        path: path.clone(),
+                        hash: content_hash(content),
+                    })
+                    .collect();
+                context.dropped = dropped;
+                context.replayed_messages = self.conversation_history.len();
+                self.build_prompt(&template, question, &Self::format_context(&files))
+            }
+        };
+        self.last_context = Some(context);
+
+        if let Some(ref lang) = options.lang {
+            user_prompt.push_str(&language_instruction(lang));
+        }
+        let citable = self.citable_files();
+        if self.options.cite && !citable.is_empty() {
+            user_prompt.push_str(&citation::instruction(&citable));
+        }
+        if let Some(ref style) = self.style {
+            user_prompt.push_str(style);
+        }
+        if let Some(ref fit) = self.fit {
+            user_prompt.push_str(fit);
+        }
 
         // Call Claude Code executable
-        let output = Command::new(&self.claude_executable)
-            .arg("--system-prompt-file")
-            .arg(&system_path)
-            .arg("--")
-            .arg(&user_prompt)
-            .output()
-            .context(format!(
-                "Failed to execute Claude Code at '{}'",
-                self.claude_executable
-            ))?;
+        let mut command = Command::new(&self.claude_executable);
+        command.arg("--print");
+
+        // Kept alive until claude exits, which removes the file
+        let mut system_file = None;
+        let delivery = if self.options.prompts_via_stdin {
+            SystemPromptDelivery::Prepend
+        } else {
+            capabilities.system_prompt_delivery()
+        };
+        match delivery {
+            SystemPromptDelivery::File => {
+                let mut file = private_temp_file(self.options.temp_dir.as_deref())?;
+                writeln!(file, "{}", system_prompt)
+                    .context("Failed to write system prompt to temp file")?;
+                command.arg("--system-prompt-file").arg(file.path());
+                system_file = Some(file);
+            }
+            SystemPromptDelivery::Inline => {
+                command.arg("--system-prompt").arg(&system_prompt);
+            }
+            SystemPromptDelivery::Append => {
+                command.arg("--append-system-prompt").arg(&system_prompt);
+            }
+            // A resumed session already saw the prepended prompt
+            SystemPromptDelivery::Prepend if resume_id.is_none() => {
+                user_prompt = format!("{}\n\n---\n\n{}", system_prompt, user_prompt);
+            }
+            SystemPromptDelivery::Prepend => {}
+        }
+
+        command
+            .args(self.cli_args(options))
+            .envs(self.generation_env())
+            .envs(self.profile_env.clone())
+            .envs(&self.agent.env);
+
+        let streaming = on_text.is_some() && capabilities.supports(Capability::StreamOutput);
+        if streaming {
+            command.args(["--output-format", "stream-json", "--verbose", "--include-partial-messages"]);
+        } else if json_output {
+            command.arg("--output-format").arg("json");
+        }
+        if let Some(ref id) = resume_id {
+            command.arg("--resume").arg(id);
+        }
+
+        // Run inside the agent's working directory so its tools see the right files
+        if let Some(ref dir) = self.agent.working_dir {
+            if !Path::new(dir).is_dir() {
+                return Err(anyhow::anyhow!(
+                    "Working directory for agent '{}' not found: {}",
+                    self.agent.name,
+                    dir
+                ));
+            }
+            command.current_dir(dir);
+        }
+
+        command
+            // The prompt goes over stdin: argv has a length limit and shows up in `ps`
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            // If this generation is cancelled, take claude down with it; the temp
+            // file is removed when `system_file` drops either way
+            .kill_on_drop(true);
+        let shielded = cfg!(unix) && SHIELDED.load(Ordering::Relaxed);
+        #[cfg(unix)]
+        if shielded {
+            command.process_group(0);
+        }
+        // Held until claude exits, so other generations on this machine wait their turn
+        let _slot = match self.options.max_concurrent {
+            Some(limit) => Some(slots::acquire(&slots::slots_dir()?, limit).await?),
+            None => None,
+        };
+        let mut child = command.spawn().context(format!(
+            "Failed to execute Claude Code at '{}'",
+            self.claude_executable
+        ))?;
+        let _registered = shielded.then(|| ShieldedChild::register(child.id()));
+
+        // Write while reading output, so a large prompt can't fill both pipes
+        let mut stdin = child.stdin.take().context("Claude Code stdin unavailable")?;
+        let send = async move {
+            let sent = stdin.write_all(user_prompt.as_bytes()).await;
+            drop(stdin);
+            sent
+        };
+        let mut on_text = on_text;
+        let (sent, output) = match on_text.as_deref_mut() {
+            Some(on_text) if streaming => tokio::join!(send, stream_output(child, on_text)),
+            _ => tokio::join!(send, child.wait_with_output()),
+        };
+        let output = output.context("Failed to wait for Claude Code")?;
+        drop(system_file);
 
         if !output.status.success() {
             let stderr = String::from_utf8_lossy(&output.stderr);
@@ -105,11 +911,21 @@ impl ClaudeAgent {
                 stderr
             ));
         }
+        sent.context("Failed to send prompt to Claude Code")?;
 
-        let response = String::from_utf8(output.stdout)
-            .context("Failed to parse Claude Code output as UTF-8")?
-            .trim()
-            .to_string();
+        let stdout = String::from_utf8(output.stdout)
+            .context("Failed to parse Claude Code output as UTF-8")?;
+        let (response, session_id, streamed) = match streaming {
+            true => parse_stream_output(&stdout)?,
+            false => parse_cli_output(&stdout).map(|(response, id)| (response, id, false))?,
+        };
+        if let Some(on_text) = on_text.filter(|_| !streamed) {
+            on_text(&response);
+        }
+
+        if json_output && session_id.is_some() {
+            self.session_id = session_id;
+        }
 
         // Save to conversation history
         self.conversation_history.push(Message {
@@ -124,3 +940,499 @@ impl ClaudeAgent {
         Ok(response)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn agent_with(
+        model: Option<&str>,
+        max_output_tokens: Option<u32>,
+        thinking: Option<bool>,
+    ) -> ClaudeAgent {
+        let agent = Agent {
+            name: "test".to_string(),
+            system_prompt: "Test".to_string(),
+            model: model.map(|m| m.to_string()),
+            max_output_tokens,
+            thinking,
+            ..Default::default()
+        };
+        ClaudeAgent::new("claude".to_string(), agent, None)
+    }
+
+    #[test]
+    fn test_cli_args() {
+        let none = PatternOptions::default();
+        assert_eq!(agent_with(Some("opus"), None, None).cli_args(&none), vec!["--model", "opus"]);
+        assert!(agent_with(Some("inherit"), None, None).cli_args(&none).is_empty());
+        assert!(agent_with(None, None, None).cli_args(&none).is_empty());
+    }
+
+    #[test]
+    fn test_is_single_diagram() {
+        assert!(is_single_diagram("```mermaid\ngraph TD\n  A --> B\n```"));
+        assert!(is_single_diagram("\n```plantuml\n@startuml\nA -> B\n@enduml\n```\n"));
+        assert!(!is_single_diagram("Here it is:\n```mermaid\ngraph TD\n```"));
+        assert!(!is_single_diagram("```mermaid\ngraph TD\n```\n```mermaid\ngraph LR\n```"));
+        assert!(!is_single_diagram("```rust\nfn main() {}\n```"));
+        assert!(!is_single_diagram("```mermaid\n```"));
+    }
+
+    #[test]
+    fn test_check_response_for_diagram_task() {
+        let task = Task {
+            name: "diagram".to_string(),
+            output: TaskOutput::Diagram,
+            ..Default::default()
+        };
+        let claude = ClaudeAgent::new("claude".to_string(), Agent::default(), Some(task));
+
+        assert!(claude.check_response("```mermaid\ngraph TD\n```").is_none());
+        assert!(claude.check_response("A diagram would help").is_some());
+        assert!(agent_with(None, None, None).check_response("prose").is_none());
+    }
+
+    #[test]
+    fn test_constraint_violations() {
+        let constraints = TaskConstraints {
+            max_words: Some(6),
+            max_bullets: Some(2),
+            must_not_contain: vec!["As an AI".to_string()],
+        };
+
+        assert!(constraint_violations(&constraints, "- one\n- two").is_empty());
+
+        let violations =
+            constraint_violations(&constraints, "as an ai model:\n- one\n* two\n1. three");
+        assert_eq!(
+            violations,
+            vec![
+                "8 words (max 6)".to_string(),
+                "3 bullet points (max 2)".to_string(),
+                "contains \"As an AI\"".to_string(),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_model_override() {
+        let options = RunOptions {
+            model: Some("haiku".to_string()),
+            ..Default::default()
+        };
+        let agent = agent_with(Some("opus"), None, None).agent;
+        let claude = ClaudeAgent::with_options("claude".to_string(), agent, None, options);
+
+        assert_eq!(claude.model().as_deref(), Some("haiku"));
+        assert_eq!(claude.cli_args(&PatternOptions::default()), vec!["--model", "haiku"]);
+    }
+
+    #[test]
+    fn test_model_aliases() {
+        let aliases: BTreeMap<String, ModelAlias> = serde_json::from_str(
+            r#"{"fast": "claude-haiku-4-5", "smart": {"default": "claude-opus-4-1", "profiles": {"relay": "opus"}}}"#,
+        )
+        .unwrap();
+        let options = RunOptions {
+            model_aliases: aliases,
+            ..Default::default()
+        };
+        let agent = agent_with(Some("smart"), None, None).agent;
+        let mut claude = ClaudeAgent::with_options("claude".to_string(), agent, None, options);
+        assert_eq!(claude.model().as_deref(), Some("claude-opus-4-1"));
+
+        // A pattern's own model goes through the same aliases
+        let pattern = PatternOptions::parse("model=fast");
+        assert_eq!(claude.cli_args(&pattern), vec!["--model", "claude-haiku-4-5"]);
+
+        claude.use_profile(&Profile {
+            name: "relay".to_string(),
+            ..Default::default()
+        });
+        assert_eq!(claude.model().as_deref(), Some("opus"));
+    }
+
+    #[test]
+    fn test_cli_args_permissions() {
+        let agent = Agent {
+            name: "test".to_string(),
+            permission_mode: Some("acceptEdits".to_string()),
+            ..Default::default()
+        };
+        let options = RunOptions {
+            skip_permissions: true,
+            ..Default::default()
+        };
+        let claude = ClaudeAgent::with_options("claude".to_string(), agent, None, options);
+
+        assert_eq!(
+            claude.cli_args(&PatternOptions::default()),
+            vec!["--permission-mode", "acceptEdits", "--dangerously-skip-permissions"]
+        );
+    }
+
+    #[test]
+    fn test_parse_cli_output_json() {
+        let stdout = r#"{"type":"result","result":"  # Slide  ","session_id":"abc-123","is_error":false}"#;
+        let (response, session_id) = parse_cli_output(stdout).unwrap();
+
+        assert_eq!(response, "# Slide");
+        assert_eq!(session_id.as_deref(), Some("abc-123"));
+    }
+
+    #[test]
+    fn test_parse_cli_output_plain_text_fallback() {
+        let (response, session_id) = parse_cli_output("plain answer\n").unwrap();
+
+        assert_eq!(response, "plain answer");
+        assert!(session_id.is_none());
+    }
+
+    #[test]
+    fn test_parse_cli_output_error() {
+        let stdout = r#"{"result":"rate limited","is_error":true}"#;
+        assert!(parse_cli_output(stdout).is_err());
+    }
+
+    #[test]
+    fn test_reduce_to_fit_drops_largest_contributors() {
+        let agent = Agent {
+            name: "test".to_string(),
+            ..Default::default()
+        };
+        let options = RunOptions {
+            max_prompt_tokens: Some(200),
+            ..Default::default()
+        };
+        let mut claude = ClaudeAgent::with_options("claude".to_string(), agent, None, options);
+        claude.conversation_history.push(Message {
+            role: "User".to_string(),
+            content: "q".repeat(300),
+        });
+        claude.conversation_history.push(Message {
+            role: "Assistant".to_string(),
+            content: "a".repeat(300),
+        });
+
+        let mut files = vec![
+            ("small.md".to_string(), "s".repeat(100)),
+            ("huge.md".to_string(), "h".repeat(2000)),
+        ];
+        let dropped = claude.reduce_to_fit(template::DEFAULT_TEMPLATE, "question?", &mut files);
+
+        assert_eq!(dropped, vec!["context file huge.md", "oldest conversation exchange"]);
+        assert_eq!(files.len(), 1);
+        assert!(claude.conversation_history.is_empty());
+    }
+
+    #[test]
+    fn test_reduce_to_fit_within_budget() {
+        let mut claude = agent_with(None, None, None);
+        let mut files = vec![("notes.md".to_string(), "short".to_string())];
+
+        assert!(claude.reduce_to_fit(template::DEFAULT_TEMPLATE, "question?", &mut files).is_empty());
+        assert_eq!(files.len(), 1);
+    }
+
+    #[test]
+    fn test_generation_env() {
+        let env = agent_with(None, Some(4096), Some(false)).generation_env();
+        assert_eq!(
+            env,
+            vec![
+                ("CLAUDE_CODE_MAX_OUTPUT_TOKENS", "4096".to_string()),
+                ("MAX_THINKING_TOKENS", "0".to_string()),
+            ]
+        );
+        assert!(agent_with(None, None, None).generation_env().is_empty());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_private_temp_file() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let root = tempfile::tempdir().unwrap();
+        let dir = root.path().join("prompts");
+        let file = private_temp_file(Some(&dir)).unwrap();
+        let path = file.path().to_path_buf();
+
+        assert!(path.starts_with(&dir));
+        assert_eq!(fs::metadata(&path).unwrap().permissions().mode() & 0o777, 0o600);
+        assert_eq!(fs::metadata(&dir).unwrap().permissions().mode() & 0o777, 0o700);
+        drop(file);
+        assert!(!path.exists());
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_prompts_via_stdin() {
+        let fake = crate::testing::FakeClaude::answering("Answer").unwrap();
+
+        let agent = Agent {
+            name: "test".to_string(),
+            system_prompt: "Be brief".to_string(),
+            ..Default::default()
+        };
+        let options = RunOptions {
+            no_resume: true,
+            prompts_via_stdin: true,
+            ..Default::default()
+        };
+        let mut claude = ClaudeAgent::with_options(fake.executable(), agent, None, options);
+
+        claude.generate_response("What is Rust?").await.unwrap();
+        let stdin = &fake.calls()[0].stdin;
+        assert!(stdin.starts_with("Be brief"));
+        assert!(stdin.contains("Question: What is Rust?"));
+    }
+
+    #[test]
+    fn test_system_prompt_includes() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::create_dir(dir.path().join("shared")).unwrap();
+        fs::write(dir.path().join("shared/style.md"), "Use short bullets.\n{{include \"tone.md\"}}\n").unwrap();
+        fs::write(dir.path().join("shared/tone.md"), "Be friendly.\n").unwrap();
+
+        let mut agent = Agent {
+            name: "test".to_string(),
+            system_prompt: "You write slides.\n{{include \"shared/style.md\"}}".to_string(),
+            working_dir: Some(dir.path().to_string_lossy().to_string()),
+            ..Default::default()
+        };
+        let claude = ClaudeAgent::new("claude".to_string(), agent.clone(), None);
+        assert_eq!(
+            claude.system_prompt().unwrap(),
+            "You write slides.\nUse short bullets.\nBe friendly."
+        );
+
+        // Edits to an included file show up in the next generation
+        fs::write(dir.path().join("shared/tone.md"), "Be formal.").unwrap();
+        assert!(claude.system_prompt().unwrap().ends_with("Be formal."));
+
+        agent.system_prompt = "{{include \"missing.md\"}}".to_string();
+        let err = ClaudeAgent::new("claude".to_string(), agent, None).system_prompt().unwrap_err();
+        assert!(format!("{:#}", err).contains("missing.md"));
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_context_snapshot() {
+        let fake = crate::testing::FakeClaude::answering("Answer").unwrap();
+        let dir = tempfile::tempdir().unwrap();
+        let notes = dir.path().join("notes.md");
+        fs::write(&notes, "Rust notes").unwrap();
+
+        let agent = Agent {
+            name: "test".to_string(),
+            system_prompt: "Be brief".to_string(),
+            context_files: vec![notes.to_string_lossy().to_string()],
+            model: Some("haiku".to_string()),
+            ..Default::default()
+        };
+        let options = RunOptions {
+            no_resume: true,
+            ..Default::default()
+        };
+        let mut claude = ClaudeAgent::with_options(fake.executable(), agent, None, options);
+        assert!(claude.last_context().is_none());
+
+        claude.generate_response("What is Rust?").await.unwrap();
+        let context = claude.last_context().unwrap();
+        assert_eq!(context.model.as_deref(), Some("haiku"));
+        assert_eq!(context.system_prompt_hash, content_hash("Be brief"));
+        assert_eq!(context.template, claude.template());
+        assert_eq!(context.context_files[0].hash, content_hash("Rust notes"));
+        assert_eq!(context.replayed_messages, 0);
+
+        // The second question replays the first exchange
+        claude.generate_response("And Cargo?").await.unwrap();
+        assert_eq!(claude.last_context().unwrap().replayed_messages, 2);
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_cite_asks_for_sources() {
+        let claude = crate::testing::FakeClaude::answering("Answer").unwrap();
+        let dir = tempfile::tempdir().unwrap();
+        let notes = dir.path().join("notes.md").to_string_lossy().to_string();
+        fs::write(&notes, "Rust notes").unwrap();
+        let agent = |context_files: Vec<String>| Agent {
+            name: "test".to_string(),
+            context_files,
+            ..Default::default()
+        };
+        let options = RunOptions {
+            cite: true,
+            ..Default::default()
+        };
+
+        let mut cited = ClaudeAgent::with_options(claude.executable(), agent(vec![notes.clone()]), None, options.clone());
+        cited.generate_response("What is Rust?").await.unwrap();
+        let mut plain = ClaudeAgent::with_options(claude.executable(), agent(Vec::new()), None, options);
+        plain.generate_response("What is Rust?").await.unwrap();
+
+        let calls = claude.calls();
+        assert!(calls[0].stdin.contains(&format!("[source: <path>], using the path exactly as given: {}", notes)));
+        assert!(!calls[1].stdin.contains("[source:"));
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_style_closes_the_prompt() {
+        let claude = crate::testing::FakeClaude::answering("Answer").unwrap();
+        let mut agent = ClaudeAgent::new(claude.executable(), Agent::default(), None);
+        let style = crate::style::DocumentStyle {
+            bullet: Some('*'),
+            ..Default::default()
+        };
+        agent.set_style(style.instruction());
+        agent.generate_response("What is Rust?").await.unwrap();
+        agent.set_style(None);
+        agent.generate_response("And Cargo?").await.unwrap();
+
+        let calls = claude.calls();
+        assert!(calls[0].stdin.trim_end().ends_with("Match the style of the document the answer goes into:\n- Bullets: `*`"));
+        assert!(!calls[1].stdin.contains("Match the style"));
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_fit_closes_the_prompt() {
+        let claude = crate::testing::FakeClaude::answering("Answer").unwrap();
+        let mut agent = ClaudeAgent::new(claude.executable(), Agent::default(), None);
+        agent.set_fit(Some(crate::fit::instruction(6)));
+        agent.generate_response("What is Rust?").await.unwrap();
+
+        let calls = claude.calls();
+        assert!(calls[0].stdin.trim_end().ends_with("room for about 6 more lines; keep it that short."));
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_generate_streamed() {
+        let claude = crate::testing::FakeClaude::answering("Ownership has rules.").unwrap();
+        let mut agent = ClaudeAgent::new(claude.executable(), Agent::default(), None);
+        let mut chunks = Vec::new();
+        let reply = agent
+            .generate_streamed("What is ownership?", &mut |text| chunks.push(text.to_string()))
+            .await
+            .unwrap();
+        assert_eq!(reply, "Ownership has rules.");
+        assert_eq!(chunks, vec!["Ownership ", "has ", "rules."]);
+        assert_eq!(agent.session_id(), Some("fake-session"));
+        assert!(claude.calls()[0].args.contains(&"--include-partial-messages".to_string()));
+
+        // A CLI too old to stream hands over the whole reply at the end
+        let old = crate::testing::FakeClaude::builder()
+            .answer("Ownership has rules.")
+            .without(Capability::StreamOutput)
+            .build()
+            .unwrap();
+        let mut agent = ClaudeAgent::new(old.executable(), Agent::default(), None);
+        let mut chunks = Vec::new();
+        agent
+            .generate_streamed("What is ownership?", &mut |text| chunks.push(text.to_string()))
+            .await
+            .unwrap();
+        assert_eq!(chunks, vec!["Ownership has rules."]);
+    }
+
+    #[test]
+    fn test_parse_stream_output() {
+        let stdout = concat!(
+            r#"{"type":"system","subtype":"init","session_id":"s1"}"#,
+            "\n",
+            r#"{"type":"stream_event","event":{"type":"content_block_delta","index":0,"delta":{"type":"text_delta","text":"Hi"}}}"#,
+            "\n",
+            r#"{"type":"result","subtype":"success","is_error":false,"result":"Hi","session_id":"s1"}"#,
+            "\n"
+        );
+        assert_eq!(parse_stream_output(stdout).unwrap(), ("Hi".to_string(), Some("s1".to_string()), true));
+        assert_eq!(parse_stream_output("plain text\n").unwrap(), ("plain text".to_string(), None, false));
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_context_commands_run_in_working_dir() {
+        let claude = crate::testing::FakeClaude::answering("Answer").unwrap();
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("pods.txt"), "web-1 Running").unwrap();
+        let agent = Agent {
+            context_files: vec!["cmd://cat pods.txt".to_string()],
+            working_dir: Some(dir.path().to_string_lossy().to_string()),
+            ..Default::default()
+        };
+        let options = RunOptions {
+            cite: true,
+            no_resume: true,
+            context_commands: vec!["cat".to_string()],
+            ..Default::default()
+        };
+        let mut agent = ClaudeAgent::with_options(claude.executable(), agent, None, options);
+        agent.generate_response("What is running?").await.unwrap();
+
+        let stdin = &claude.calls()[0].stdin;
+        assert!(stdin.contains("--- Context from cmd://cat pods.txt ---\nweb-1 Running"));
+        // Command output has no path to cite
+        assert!(!stdin.contains("[source: <path>]"));
+
+        // Programs off the allowlist never run
+        agent.options.context_commands.clear();
+        let err = agent.generate_response("And now?").await.unwrap_err();
+        assert!(format!("{:#}", err).contains("isn't in context_commands"));
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_env_in_context_and_templates() {
+        let claude = crate::testing::FakeClaude::answering("Answer").unwrap();
+        let agent = Agent {
+            system_prompt: "You present {{env.PROJECT}}.".to_string(),
+            context_files: vec!["env://TICKET".to_string()],
+            env: std::collections::HashMap::from([
+                ("PROJECT".to_string(), "ecce".to_string()),
+                ("TICKET".to_string(), "OPS-42".to_string()),
+            ]),
+            ..Default::default()
+        };
+        let task = Task {
+            template: "Explain the fix for {{ env.TICKET }}.".to_string(),
+            ..Default::default()
+        };
+        let options = RunOptions {
+            no_resume: true,
+            context_env: vec!["PROJECT".to_string(), "TICKET".to_string()],
+            ..Default::default()
+        };
+        let mut agent = ClaudeAgent::with_options(claude.executable(), agent, Some(task), options);
+        assert_eq!(agent.system_prompt().unwrap(), "You present ecce.");
+        agent.generate_response("What changed?").await.unwrap();
+
+        let stdin = &claude.calls()[0].stdin;
+        assert!(stdin.contains("Explain the fix for OPS-42."));
+        assert!(stdin.contains("--- Context from env://TICKET ---\nOPS-42"));
+
+        agent.options.context_env = vec!["PROJECT".to_string()];
+        let err = agent.generate_response("And now?").await.unwrap_err();
+        assert!(format!("{:#}", err).contains("'TICKET' isn't in context_env"));
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_user_prompt_not_in_argv() {
+        let fake = crate::testing::FakeClaude::answering("Answer").unwrap();
+        let options = RunOptions {
+            no_resume: true,
+            ..Default::default()
+        };
+        let mut claude = ClaudeAgent::with_options(fake.executable(), Agent::default(), None, options);
+
+        claude.generate_response("What is Rust?").await.unwrap();
+        let call = &fake.calls()[0];
+        assert_eq!(call.args[..2], ["--print", "--system-prompt-file"]);
+        assert!(!call.args.iter().any(|arg| arg.contains("What is Rust?")));
+        assert!(call.stdin.contains("Question: What is Rust?"));
+    }
+}
@@ -0,0 +1,259 @@
+use anyhow::Result;
+use std::collections::HashMap;
+use std::fmt;
+use std::process::Stdio;
+use std::sync::{Mutex, OnceLock};
+use std::time::Duration;
+use tokio::process::Command;
+
+/// A claude CLI release, as reported by `claude --version`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Version {
+    pub major: u32,
+    pub minor: u32,
+    pub patch: u32,
+}
+
+impl Version {
+    pub const fn new(major: u32, minor: u32, patch: u32) -> Self {
+        Self { major, minor, patch }
+    }
+
+    /// The first `major.minor.patch` token in `text`, e.g. `1.0.54 (Claude Code)`
+    pub fn parse(text: &str) -> Option<Self> {
+        text.split_whitespace().find_map(|token| {
+            let token = token.trim_start_matches('v');
+            let core = token.split(['-', '+']).next()?;
+            let mut parts = core.split('.').map(|p| p.parse::<u32>().ok());
+            let version = Version::new(parts.next()??, parts.next()??, parts.next()??);
+            parts.next().is_none().then_some(version)
+        })
+    }
+}
+
+impl fmt::Display for Version {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}.{}.{}", self.major, self.minor, self.patch)
+    }
+}
+
+/// A claude CLI flag that older releases reject
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Capability {
+    SystemPromptFile,
+    SystemPrompt,
+    AppendSystemPrompt,
+    JsonOutput,
+    Resume,
+    PermissionMode,
+    SkipPermissions,
+    McpConfig,
+    /// Token-by-token output: `--output-format stream-json --verbose --include-partial-messages`
+    StreamOutput,
+}
+
+impl Capability {
+    pub const ALL: [Capability; 9] = [
+        Capability::SystemPromptFile,
+        Capability::SystemPrompt,
+        Capability::AppendSystemPrompt,
+        Capability::JsonOutput,
+        Capability::Resume,
+        Capability::PermissionMode,
+        Capability::SkipPermissions,
+        Capability::McpConfig,
+        Capability::StreamOutput,
+    ];
+
+    pub fn flag(self) -> &'static str {
+        match self {
+            Capability::SystemPromptFile => "--system-prompt-file",
+            Capability::SystemPrompt => "--system-prompt",
+            Capability::AppendSystemPrompt => "--append-system-prompt",
+            Capability::JsonOutput => "--output-format json",
+            Capability::Resume => "--resume",
+            Capability::PermissionMode => "--permission-mode",
+            Capability::SkipPermissions => "--dangerously-skip-permissions",
+            Capability::McpConfig => "--mcp-config",
+            Capability::StreamOutput => "--include-partial-messages",
+        }
+    }
+
+    /// Whether `claude --help` lists the flag. Current releases mention
+    /// `--system-prompt-file` only as `--system-prompt[-file]`.
+    fn listed_in(self, help: &str) -> bool {
+        let option = self.flag().split(' ').next().unwrap_or_default();
+        let mentions = |name: &str| {
+            help.match_indices(name).any(|(at, _)| {
+                !help[at + name.len()..]
+                    .starts_with(|c: char| c.is_ascii_alphanumeric() || c == '-')
+            })
+        };
+        match self {
+            Capability::SystemPromptFile => mentions(option) || help.contains("--system-prompt[-file]"),
+            _ => mentions(option),
+        }
+    }
+}
+
+/// How the agent's system prompt reaches claude, best first
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SystemPromptDelivery {
+    /// `--system-prompt-file <path>`
+    File,
+    /// `--system-prompt <text>`
+    Inline,
+    /// `--append-system-prompt <text>`, added to claude's default system prompt
+    Append,
+    /// Prepended to the user prompt
+    Prepend,
+}
+
+/// What an installed claude executable supports, as its own `--help` lists
+/// it, so no table of release numbers has to be kept. An executable whose
+/// help can't be read or lists none of the flags (a wrapper script, a custom
+/// build) is assumed to support everything, so detection never blocks a
+/// working setup.
+#[derive(Debug, Clone, Default)]
+pub struct Capabilities {
+    pub version: Option<Version>,
+    /// Flags listed in `claude --help`; `None` when it couldn't be read
+    pub listed: Option<Vec<Capability>>,
+}
+
+/// How long `claude --version` or `--help` may take before it is treated as unknown
+const VERSION_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Capabilities detected so far this run, by executable
+static DETECTED: OnceLock<Mutex<HashMap<String, Capabilities>>> = OnceLock::new();
+
+impl Capabilities {
+    /// Detect the executable's version and flags, running `--version` and
+    /// `--help` once per executable per run
+    pub async fn detect(executable: &str) -> Self {
+        let cache = DETECTED.get_or_init(Default::default);
+        if let Some(capabilities) = cache.lock().unwrap().get(executable) {
+            return capabilities.clone();
+        }
+
+        let (version, help) = tokio::join!(query(executable, "--version"), query(executable, "--help"));
+        let capabilities = Self {
+            version: version.as_deref().and_then(Version::parse),
+            // Help that lists none of them isn't claude's
+            listed: help.as_deref().map(Self::from_help).filter(|listed| !listed.is_empty()),
+        };
+        cache.lock().unwrap().insert(executable.to_string(), capabilities.clone());
+        capabilities
+    }
+
+    /// The capabilities whose flags a `claude --help` text lists
+    pub fn from_help(help: &str) -> Vec<Capability> {
+        Capability::ALL.into_iter().filter(|c| c.listed_in(help)).collect()
+    }
+
+    pub fn supports(&self, capability: Capability) -> bool {
+        self.listed.as_ref().is_none_or(|listed| listed.contains(&capability))
+    }
+
+    /// The best way this executable accepts a system prompt
+    pub fn system_prompt_delivery(&self) -> SystemPromptDelivery {
+        if self.supports(Capability::SystemPromptFile) {
+            SystemPromptDelivery::File
+        } else if self.supports(Capability::SystemPrompt) {
+            SystemPromptDelivery::Inline
+        } else if self.supports(Capability::AppendSystemPrompt) {
+            SystemPromptDelivery::Append
+        } else {
+            SystemPromptDelivery::Prepend
+        }
+    }
+
+    /// Fail with an upgrade hint if the executable doesn't support `capability`
+    pub fn require(&self, capability: Capability, executable: &str) -> Result<()> {
+        if self.supports(capability) {
+            return Ok(());
+        }
+        let version = self.version.map(|v| format!(" {}", v)).unwrap_or_default();
+        Err(anyhow::anyhow!(
+            "Claude Code{} at '{}' does not support {} (it isn't in its --help); upgrade with 'claude update'",
+            version,
+            executable,
+            capability.flag()
+        ))
+    }
+}
+
+/// Stdout of `executable <arg>`, if it succeeds in time
+async fn query(executable: &str, arg: &str) -> Option<String> {
+    let query = Command::new(executable)
+        .arg(arg)
+        .stdin(Stdio::null())
+        .kill_on_drop(true)
+        .output();
+    tokio::time::timeout(VERSION_TIMEOUT, query)
+        .await
+        .ok()
+        .and_then(Result::ok)
+        .filter(|output| output.status.success())
+        .map(|output| String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_version_parse() {
+        assert_eq!(Version::parse("1.0.54 (Claude Code)"), Some(Version::new(1, 0, 54)));
+        assert_eq!(Version::parse("claude v2.1.3-beta.1"), Some(Version::new(2, 1, 3)));
+        assert_eq!(Version::parse("Fake answer"), None);
+        assert_eq!(Version::parse("1.0"), None);
+        assert!(Version::new(1, 0, 54) > Version::new(0, 2, 125));
+    }
+
+    /// Excerpts of `claude --help` from Claude Code 2.1.280
+    const HELP: &str = "\
+  --append-system-prompt <prompt>       Append a system prompt to the default
+                                        via: --system-prompt[-file],
+  --dangerously-skip-permissions        Bypass all permission checks.
+  --include-partial-messages            Include partial message chunks as they
+  --mcp-config <configs...>             Load MCP servers from JSON files or
+  --output-format <format>              Output format (only works with --print):
+  --permission-mode <mode>              Permission mode to use for the session
+  -r, --resume [value]                  Resume a conversation by session ID, or
+  --system-prompt <prompt>              System prompt to use for the session
+  --system-prompt-snapshot <on|off>     Record the system prompt once per
+";
+
+    #[test]
+    fn test_flags_from_help() {
+        assert_eq!(Capabilities::from_help(HELP), Capability::ALL.to_vec());
+        // Longer flags that start the same don't count
+        let help = "  --system-prompt-snapshot <on|off>\n  --append-system-prompt <prompt>\n";
+        assert_eq!(Capabilities::from_help(help), vec![Capability::AppendSystemPrompt]);
+    }
+
+    #[test]
+    fn test_capability_gating() {
+        let listing = |listed: &[Capability]| Capabilities {
+            version: Some(Version::new(1, 0, 0)),
+            listed: Some(listed.to_vec()),
+        };
+        let old = listing(&[Capability::JsonOutput, Capability::SystemPrompt]);
+        assert!(old.supports(Capability::JsonOutput));
+        assert!(!old.supports(Capability::SystemPromptFile));
+        let err = old.require(Capability::McpConfig, "claude").unwrap_err();
+        assert!(err.to_string().starts_with("Claude Code 1.0.0 at 'claude' does not support --mcp-config"));
+
+        let delivery = |listed: &[Capability]| listing(listed).system_prompt_delivery();
+        assert_eq!(delivery(&Capability::ALL), SystemPromptDelivery::File);
+        assert_eq!(delivery(&[Capability::SystemPrompt]), SystemPromptDelivery::Inline);
+        assert_eq!(delivery(&[Capability::AppendSystemPrompt]), SystemPromptDelivery::Append);
+        assert_eq!(delivery(&[]), SystemPromptDelivery::Prepend);
+
+        let unknown = Capabilities::default();
+        assert_eq!(unknown.system_prompt_delivery(), SystemPromptDelivery::File);
+        assert!(unknown.supports(Capability::SystemPromptFile));
+        assert!(unknown.require(Capability::McpConfig, "claude").is_ok());
+    }
+}
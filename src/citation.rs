@@ -0,0 +1,158 @@
+//! Citations of context files: the prompt asks the agent to tag what it takes
+//! from a context file with `[source: <path>]`, and those tags become Markdown
+//! footnotes linking to the files, relative to the answered document.
+
+use regex::Regex;
+use std::path::{Component, Path, PathBuf};
+
+use crate::environment::claude_project_root;
+
+/// Closing instruction for a prompt whose context files should be cited
+pub fn instruction(files: &[String]) -> String {
+    format!(
+        "\n\nAfter each statement that relies on a context file, cite it as [source: <path>], \
+         using the path exactly as given: {}. Cite only these files.",
+        files.join(", ")
+    )
+}
+
+/// A response with its citations turned into footnotes
+#[derive(Debug, Clone, PartialEq)]
+pub struct Cited {
+    pub text: String,
+    /// Cited paths that aren't context files; their tags are dropped
+    pub unknown: Vec<String>,
+}
+
+/// Replace `[source: path]` tags naming one of `files` with footnote references,
+/// and append one footnote per cited file linking to it from `document`'s
+/// directory. Labels come from the file's path in its project, so answers
+/// citing the same file share a footnote.
+pub fn footnotes(response: &str, files: &[String], document: &Path) -> Cited {
+    let tag = Regex::new(r" ?\[source:\s*([^\]\n]+)\]").unwrap();
+    let from = document.parent().map(absolute).unwrap_or_default();
+    let root = claude_project_root(&from, dirs::home_dir().as_deref());
+
+    let mut cited: Vec<(String, String)> = Vec::new();
+    let mut unknown = Vec::new();
+    let text = tag.replace_all(response, |caps: &regex::Captures| {
+        let mut refs = String::new();
+        for source in caps[1].split([',', ';']).map(str::trim).filter(|s| !s.is_empty()) {
+            let Some(file) = find_file(source, files) else {
+                if !unknown.iter().any(|u| u == source) {
+                    unknown.push(source.to_string());
+                }
+                continue;
+            };
+            let path = absolute(Path::new(file));
+            let shown = path.strip_prefix(&root).unwrap_or(&path).to_string_lossy().to_string();
+            let label = slug(&shown);
+            if !cited.iter().any(|(l, _)| *l == label) {
+                let link = relative_path(&from, &path).to_string_lossy().replace(' ', "%20");
+                cited.push((label.clone(), format!("[{}]({})", shown, link)));
+            }
+            refs.push_str(&format!("[^{}]", label));
+        }
+        refs
+    });
+
+    let mut text = text.into_owned();
+    if !cited.is_empty() {
+        text = text.trim_end().to_string();
+        text.push_str("\n\n");
+        for (label, link) in &cited {
+            text.push_str(&format!("[^{}]: {}\n", label, link));
+        }
+    }
+    Cited { text, unknown }
+}
+
+/// The context file a citation names: the same path, or the only file with that name
+fn find_file<'a>(source: &str, files: &'a [String]) -> Option<&'a String> {
+    let source = source.trim_matches(['`', '"', '\'']);
+    if let Some(file) = files.iter().find(|f| *f == source || Path::new(f) == Path::new(source)) {
+        return Some(file);
+    }
+    let mut named = files.iter().filter(|f| Path::new(f).ends_with(source));
+    match (named.next(), named.next()) {
+        (Some(file), None) => Some(file),
+        _ => None,
+    }
+}
+
+fn absolute(path: &Path) -> PathBuf {
+    path.canonicalize()
+        .or_else(|_| std::path::absolute(path))
+        .unwrap_or_else(|_| path.to_path_buf())
+}
+
+/// A footnote label: letters, digits, and dashes
+fn slug(path: &str) -> String {
+    let mut slug = String::new();
+    for c in path.chars() {
+        if c.is_alphanumeric() {
+            slug.extend(c.to_lowercase());
+        } else if !slug.is_empty() && !slug.ends_with('-') {
+            slug.push('-');
+        }
+    }
+    slug.trim_end_matches('-').to_string()
+}
+
+/// `to` relative to the directory `from`; both absolute
+fn relative_path(from: &Path, to: &Path) -> PathBuf {
+    let from: Vec<Component> = from.components().collect();
+    let to: Vec<Component> = to.components().collect();
+    let common = from.iter().zip(&to).take_while(|(a, b)| a == b).count();
+    let mut path = PathBuf::new();
+    for _ in common..from.len() {
+        path.push("..");
+    }
+    for component in &to[common..] {
+        path.push(component);
+    }
+    path
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_footnotes() {
+        let temp = TempDir::new().unwrap();
+        let project = temp.path().canonicalize().unwrap();
+        std::fs::create_dir_all(project.join(".git")).unwrap();
+        std::fs::create_dir_all(project.join("notes")).unwrap();
+        std::fs::create_dir_all(project.join("talks")).unwrap();
+        let rust = project.join("notes/rust book.md");
+        let errors = project.join("notes/errors.md");
+        std::fs::write(&rust, "").unwrap();
+        std::fs::write(&errors, "").unwrap();
+        let files = vec![rust.to_string_lossy().to_string(), errors.to_string_lossy().to_string()];
+
+        let response = format!(
+            "Values have one owner [source: {}]. Errors are values [source: errors.md; {}].\nMoves are cheap [source: made-up.md].",
+            files[0], files[0]
+        );
+        let cited = footnotes(&response, &files, &project.join("talks/slides.md"));
+        assert_eq!(
+            cited.text,
+            "Values have one owner[^notes-rust-book-md]. Errors are values[^notes-errors-md][^notes-rust-book-md].\nMoves are cheap.\n\n\
+             [^notes-rust-book-md]: [notes/rust book.md](../notes/rust%20book.md)\n\
+             [^notes-errors-md]: [notes/errors.md](../notes/errors.md)\n"
+        );
+        assert_eq!(cited.unknown, vec!["made-up.md"]);
+
+        // Nothing cited: untouched
+        let plain = footnotes("No sources here.", &files, &project.join("slides.md"));
+        assert_eq!(plain.text, "No sources here.");
+    }
+
+    #[test]
+    fn test_relative_path() {
+        assert_eq!(relative_path(Path::new("/a/b"), Path::new("/a/c/d.md")), PathBuf::from("../c/d.md"));
+        assert_eq!(relative_path(Path::new("/a"), Path::new("/a/d.md")), PathBuf::from("d.md"));
+    }
+}
@@ -0,0 +1,152 @@
+use anyhow::Result;
+
+use crate::agent::{ClaudeAgent, RunOptions};
+use crate::config::Config;
+use crate::pattern::{self, EccePattern, PatternDetector};
+
+/// High-level entry point for embedding ecce: loads the config, resolves agents
+/// and tasks, and runs generations the way `ecce homo` does
+///
+/// ```no_run
+/// # async fn example() -> anyhow::Result<()> {
+/// let client = ecce::EcceClient::new()?;
+/// let answer = client.generate("slide-generator", Some("explain-concept"), "What is ownership?").await?;
+/// # Ok(())
+/// # }
+/// ```
+pub struct EcceClient {
+    config: Config,
+    options: RunOptions,
+}
+
+impl EcceClient {
+    /// Client for the user's config at `~/.config/ecce/config.json`
+    pub fn new() -> Result<Self> {
+        Ok(Self::from_config(Config::load()?))
+    }
+
+    pub fn from_config(config: Config) -> Self {
+        let options = RunOptions::from_config(&config);
+        Self { config, options }
+    }
+
+    /// Replace the run options (skip permissions, model override, ...)
+    pub fn with_options(mut self, options: RunOptions) -> Self {
+        self.options = options;
+        self
+    }
+
+    pub fn config(&self) -> &Config {
+        &self.config
+    }
+
+    /// An agent ready to generate; without a name, the config's default agent
+    pub fn agent(&self, agent: Option<&str>, task: Option<&str>) -> Result<ClaudeAgent> {
+        let agent = match agent {
+            Some(name) => self.config.resolve_agent(name)?,
+            None => self
+                .config
+                .resolve_default_agent()?
+                .ok_or_else(|| anyhow::anyhow!("No agent given and no default agent set"))?,
+        };
+        let task = task.map(|name| self.config.resolve_task(name)).transpose()?;
+        Ok(ClaudeAgent::with_options(
+            self.config.get_claude_executable(),
+            agent,
+            task,
+            self.options.clone(),
+        ))
+    }
+
+    /// Answer one prompt with an agent and optional task template
+    pub async fn generate(&self, agent: &str, task: Option<&str>, prompt: &str) -> Result<String> {
+        self.agent(Some(agent), task)?.generate_response(prompt).await
+    }
+
+    /// A pattern detector using the config's trigger keywords
+    pub fn detector(&self) -> Result<PatternDetector> {
+        let mut detector = PatternDetector::new();
+        detector.set_keywords(&self.config.keywords)?;
+        Ok(detector)
+    }
+
+    /// Answer every pattern in `text` in queue order with one agent, which
+    /// keeps its conversation across patterns as in `ecce homo`
+    pub async fn answer_patterns(
+        &self,
+        agent: Option<&str>,
+        task: Option<&str>,
+        text: &str,
+    ) -> Result<Vec<(EccePattern, Result<String>)>> {
+        let mut patterns = self.detector()?.detect_patterns(text);
+        pattern::queue_order(&mut patterns);
+
+        let mut claude = self.agent(agent, task)?;
+        let mut answers = Vec::new();
+        for pattern in patterns {
+            let answer = claude.generate(pattern.question(), &pattern.options).await;
+            answers.push((pattern, answer));
+        }
+        Ok(answers)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Agent;
+
+    fn client(executable: &str) -> EcceClient {
+        let mut config = Config {
+            claude_executable: Some(executable.to_string()),
+            keywords: vec!["ask".to_string()],
+            ..Default::default()
+        };
+        config.agents.insert(
+            "writer".to_string(),
+            Agent {
+                name: "writer".to_string(),
+                system_prompt: "Be brief".to_string(),
+                ..Default::default()
+            },
+        );
+        EcceClient::from_config(config).with_options(RunOptions {
+            no_resume: true,
+            prompts_via_stdin: true,
+            ..Default::default()
+        })
+    }
+
+    #[test]
+    fn test_agent_resolution() {
+        let client = client("claude");
+        assert_eq!(client.agent(Some("writer"), None).unwrap().agent_name(), "writer");
+        assert!(client.agent(Some("nobody"), None).is_err());
+        assert!(client.agent(None, None).is_err(), "no default agent is set");
+        assert!(client.agent(Some("writer"), Some("missing-task")).is_err());
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_answer_patterns() {
+        let claude = crate::testing::FakeClaude::builder()
+            .answer_on_call(1, "Answer one")
+            .answer_on_call(2, "Answer two")
+            .build()
+            .unwrap();
+
+        let client = client(&claude.executable());
+        let answers = client
+            .answer_patterns(Some("writer"), None, "ask first? ask\nask [priority=high] second? ask")
+            .await
+            .unwrap();
+
+        assert_eq!(answers.len(), 2);
+        assert_eq!(answers[0].0.question(), "second?");
+        assert_eq!(answers[0].1.as_ref().unwrap(), "Answer one");
+        assert_eq!(answers[1].1.as_ref().unwrap(), "Answer two");
+        let calls = claude.calls();
+        assert!(calls[0].stdin.contains("second?") && !calls[0].stdin.contains("first?"));
+        assert!(calls[1].stdin.contains("first?"));
+    }
+}
@@ -1,10 +1,15 @@
 use anyhow::{Context, Result};
 use clap::Subcommand;
 use colored::*;
+use ecce::{ui_eprintln, ui_println, ui_result};
 
-use crate::config::{Agent, Config};
+use crate::agent::PERMISSION_MODES;
+use crate::config::{Agent, Config, CLAUDE_CODE_SERVICE};
+use crate::models::{self, ModelCheck};
+use crate::ui;
 
 #[derive(Subcommand)]
+#[allow(clippy::large_enum_variant)] // parsed once per run; size is irrelevant
 pub enum AgentCommand {
     /// Add a new agent
     Add {
@@ -28,6 +33,28 @@ pub enum AgentCommand {
         /// Model to use (sonnet, opus, haiku, or inherit)
         #[arg(short, long)]
         model: Option<String>,
+        /// Sampling temperature (ignored by the claude CLI)
+        #[arg(long)]
+        temperature: Option<f32>,
+        /// Maximum output tokens per response
+        #[arg(long)]
+        max_output_tokens: Option<u32>,
+        /// Enable (true) or disable (false) extended thinking
+        #[arg(long)]
+        thinking: Option<bool>,
+        /// Permission mode for claude runs (default, acceptEdits, plan, bypassPermissions)
+        #[arg(long)]
+        permission_mode: Option<String>,
+        /// Working directory for the agent's claude process
+        #[arg(long)]
+        cwd: Option<String>,
+        /// Extra environment variable for the agent's claude process (KEY=VALUE, repeatable)
+        #[arg(short, long = "env", value_parser = parse_env_var)]
+        env: Vec<(String, String)>,
+        /// Base agent to extend: its system prompt comes first (or replaces {{base}}),
+        /// and tools, model, and other unset settings are inherited
+        #[arg(long)]
+        extends: Option<String>,
     },
     /// List all agents
     #[command(alias = "ls")]
@@ -62,6 +89,46 @@ pub enum AgentCommand {
     },
 }
 
+/// Parse a KEY=VALUE environment variable argument
+fn parse_env_var(s: &str) -> Result<(String, String)> {
+    let (key, value) = s
+        .split_once('=')
+        .ok_or_else(|| anyhow::anyhow!("Expected KEY=VALUE, got '{}'", s))?;
+    if key.is_empty() {
+        return Err(anyhow::anyhow!("Environment variable name cannot be empty"));
+    }
+    Ok((key.to_string(), value.to_string()))
+}
+
+/// A model name, followed by what it expands to when it is an alias for the active profile
+pub fn describe_model(config: &Config, model: &str) -> String {
+    let resolved = config.resolve_model(model, config.get_active_profile(CLAUDE_CODE_SERVICE).as_ref());
+    if resolved == model {
+        model.to_string()
+    } else {
+        format!("{} -> {}", model, resolved)
+    }
+}
+
+/// Warn when the agent's model isn't in the active profile's cached model list;
+/// only a warning, since the list may be out of date
+fn warn_unknown_model(agent: &Agent, config: &Config) {
+    let (Some(model), Some(profile)) = (&agent.model, config.get_active_profile(CLAUDE_CODE_SERVICE)) else {
+        return;
+    };
+    let Some(catalog) = models::cached(&profile) else {
+        return;
+    };
+    let model = config.resolve_model(model, Some(&profile));
+    if let ModelCheck::Unknown { suggestion } = catalog.check(&model) {
+        let hint = suggestion.map(|s| format!("; did you mean '{}'?", s)).unwrap_or_default();
+        ui_println!(
+            "{}",
+            format!("⚠ Model '{}' is not offered by profile '{}'{}", model, profile.name, hint).yellow()
+        );
+    }
+}
+
 pub fn handle_agent_command(command: AgentCommand, config: &mut Config) -> Result<()> {
     match command {
         AgentCommand::Add {
@@ -72,7 +139,29 @@ pub fn handle_agent_command(command: AgentCommand, config: &mut Config) -> Resul
             context,
             tools,
             model,
+            temperature,
+            max_output_tokens,
+            thinking,
+            permission_mode,
+            cwd,
+            env,
+            extends,
         } => {
+            if let Some(ref base) = extends {
+                if config.get_agent(base).is_none() {
+                    return Err(anyhow::anyhow!("Base agent '{}' not found", base));
+                }
+            }
+            if let Some(ref mode) = permission_mode {
+                if !PERMISSION_MODES.contains(&mode.as_str()) {
+                    return Err(anyhow::anyhow!(
+                        "Invalid permission mode '{}'. Use one of: {}",
+                        mode,
+                        PERMISSION_MODES.join(", ")
+                    ));
+                }
+            }
+
             // Get prompt from either direct input or file
             let system_prompt = match (prompt, prompt_file) {
                 (Some(p), None) => p,
@@ -80,6 +169,8 @@ pub fn handle_agent_command(command: AgentCommand, config: &mut Config) -> Resul
                     std::fs::read_to_string(&f)
                         .with_context(|| format!("Failed to read prompt file: {}", f))?
                 }
+                // An extending agent may reuse its base prompt unchanged
+                (None, None) if extends.is_some() => String::new(),
                 (None, None) => {
                     return Err(anyhow::anyhow!(
                         "Either --prompt or --prompt-file must be provided"
@@ -106,91 +197,112 @@ pub fn handle_agent_command(command: AgentCommand, config: &mut Config) -> Resul
                 context_files,
                 tools: tools_list,
                 model,
+                temperature,
+                max_output_tokens,
+                thinking,
+                permission_mode,
+                working_dir: cwd,
+                env: env.into_iter().collect(),
+                extends,
+            };
+
+            // Reject the agent if it would close an inheritance cycle
+            let previous = config.agents.insert(name.clone(), agent.clone());
+            let resolved = config.resolve_agent(&name);
+            match previous {
+                Some(previous) => config.agents.insert(name.clone(), previous),
+                None => config.agents.remove(&name),
             };
+            resolved?;
 
+            warn_unknown_model(&agent, config);
+            if agent.temperature.is_some() {
+                ui_println!(
+                    "{}",
+                    "⚠ The claude CLI does not support a temperature; it is stored but ignored".yellow()
+                );
+            }
             config.add_agent(agent)?;
-            println!(
+            ui_println!(
                 "{}",
                 format!("✓ Agent '{}' added successfully", name).green()
             );
         }
         AgentCommand::List => {
             if config.agents.is_empty() {
-                println!("{}", "No agents configured".yellow());
+                ui_result!("{}", "No agents configured".yellow());
             } else {
-                println!("{}", "Available agents:".bold());
+                ui_result!("{}", "Available agents:".bold());
                 for (name, agent) in &config.agents {
-                    println!("  {}", name.cyan());
+                    match agent.extends {
+                        Some(ref base) => ui_result!("  {} {}", name.cyan(), format!("(extends {})", base).dimmed()),
+                        None => ui_result!("  {}", name.cyan()),
+                    }
 
                     // Show truncated description
                     if let Some(ref desc) = agent.description {
-                        let desc_preview = desc
-                            .lines()
-                            .next()
-                            .unwrap_or("")
-                            .chars()
-                            .take(100)
-                            .collect::<String>();
-                        let desc_display = if desc.len() > 100 {
-                            format!("{}...", desc_preview)
-                        } else {
-                            desc_preview
-                        };
-                        println!("    Description: {}", desc_display.dimmed());
+                        ui_result!("    Description: {}", ui::preview(desc, 100).dimmed());
                     }
 
                     // Show only first line of prompt (truncated)
-                    let prompt_preview = agent
-                        .system_prompt
-                        .lines()
-                        .next()
-                        .unwrap_or("")
-                        .chars()
-                        .take(80)
-                        .collect::<String>();
-                    let prompt_display = if agent.system_prompt.len() > 80 {
-                        format!("{}...", prompt_preview)
-                    } else {
-                        prompt_preview
-                    };
-                    println!("    Prompt: {}", prompt_display.dimmed());
+                    ui_result!("    Prompt: {}", ui::preview(&agent.system_prompt, 80).dimmed());
 
                     if !agent.context_files.is_empty() {
-                        println!("    Context: {}", agent.context_files.join(", "));
+                        ui_result!("    Context: {}", agent.context_files.join(", "));
                     }
                     if let Some(ref tools) = agent.tools {
-                        println!("    Tools: {}", tools.join(", "));
+                        ui_result!("    Tools: {}", tools.join(", "));
                     }
                     if let Some(ref model) = agent.model {
-                        println!("    Model: {}", model);
+                        ui_result!("    Model: {}", describe_model(config, model));
+                    }
+                    if let Some(temperature) = agent.temperature {
+                        ui_result!("    Temperature: {} {}", temperature, "(ignored by the claude CLI)".dimmed());
+                    }
+                    if let Some(max_output_tokens) = agent.max_output_tokens {
+                        ui_result!("    Max output tokens: {}", max_output_tokens);
+                    }
+                    if let Some(thinking) = agent.thinking {
+                        ui_result!("    Thinking: {}", if thinking { "on" } else { "off" });
+                    }
+                    if let Some(ref mode) = agent.permission_mode {
+                        ui_result!("    Permission mode: {}", mode);
+                    }
+                    if let Some(ref dir) = agent.working_dir {
+                        ui_result!("    Working dir: {}", dir);
+                    }
+                    if !agent.env.is_empty() {
+                        let mut keys: Vec<_> = agent.env.keys().cloned().collect();
+                        keys.sort();
+                        ui_result!("    Env: {}", keys.join(", "));
                     }
                 }
             }
         }
         AgentCommand::Delete { name } => {
             if config.delete_agent(&name)? {
-                println!("{}", format!("✓ Agent '{}' deleted", name).green());
+                ui_println!("{}", format!("✓ Agent '{}' deleted", name).green());
             } else {
-                println!("{}", format!("✗ Agent '{}' not found", name).red());
+                ui_eprintln!("{}", format!("✗ Agent '{}' not found", name).red());
             }
         }
         AgentCommand::Export { name, user } => {
             if let Some(agent_name) = name {
                 config.export_agent_to_file(&agent_name, user)?;
                 let location = if user { "~/.claude/agents/" } else { ".claude/agents/" };
-                println!(
+                ui_println!(
                     "{}",
                     format!("✓ Agent '{}' exported to {}", agent_name, location).green()
                 );
             } else {
                 let exported = config.export_all_agents(user)?;
                 let location = if user { "~/.claude/agents/" } else { ".claude/agents/" };
-                println!(
+                ui_println!(
                     "{}",
                     format!("✓ Exported {} agent(s) to {}", exported.len(), location).green()
                 );
                 for name in exported {
-                    println!("  - {}", name.cyan());
+                    ui_println!("  - {}", name.cyan());
                 }
             }
         }
@@ -198,14 +310,14 @@ pub fn handle_agent_command(command: AgentCommand, config: &mut Config) -> Resul
             let imported = config.sync_agents_from_files(user)?;
             if imported.is_empty() {
                 let location = if user { "~/.claude/agents/" } else { ".claude/agents/" };
-                println!("{}", format!("No agents found in {}", location).yellow());
+                ui_println!("{}", format!("No agents found in {}", location).yellow());
             } else {
-                println!(
+                ui_println!(
                     "{}",
                     format!("✓ Imported {} agent(s)", imported.len()).green()
                 );
                 for name in imported {
-                    println!("  - {}", name.cyan());
+                    ui_println!("  - {}", name.cyan());
                 }
             }
         }
@@ -215,30 +327,30 @@ pub fn handle_agent_command(command: AgentCommand, config: &mut Config) -> Resul
                     let imported = config.sync_agents_from_files(user)?;
                     if imported.is_empty() {
                         let location = if user { "~/.claude/agents/" } else { ".claude/agents/" };
-                        println!("{}", format!("No agents found in {}", location).yellow());
+                        ui_println!("{}", format!("No agents found in {}", location).yellow());
                     } else {
-                        println!(
+                        ui_println!(
                             "{}",
                             format!("✓ Synced {} agent(s) from files", imported.len()).green()
                         );
                         for name in imported {
-                            println!("  - {}", name.cyan());
+                            ui_println!("  - {}", name.cyan());
                         }
                     }
                 }
                 "export" => {
                     let exported = config.export_all_agents(user)?;
                     let location = if user { "~/.claude/agents/" } else { ".claude/agents/" };
-                    println!(
+                    ui_println!(
                         "{}",
                         format!("✓ Synced {} agent(s) to {}", exported.len(), location).green()
                     );
                     for name in exported {
-                        println!("  - {}", name.cyan());
+                        ui_println!("  - {}", name.cyan());
                     }
                 }
                 _ => {
-                    println!(
+                    ui_eprintln!(
                         "{}",
                         format!("✗ Invalid direction '{}'. Use 'import' or 'export'", direction).red()
                     );
@@ -1,10 +1,22 @@
-use anyhow::Result;
+use anyhow::{Context, Result};
+use chrono::Utc;
 use clap::Subcommand;
 use colored::*;
+use ecce::{ui_eprintln, ui_println, ui_prompt, ui_result};
+use std::io::{self, Write};
+use std::path::PathBuf;
+use std::time::Duration;
 
-use crate::config::{Config, Profile};
+use crate::commands::exec;
+use crate::config::{Config, Profile, ProjectConfig, CLAUDE_CODE_SERVICE, PROJECT_CONFIG_FILE};
+use crate::exit::Failure;
+use crate::history;
+use crate::models;
+use crate::ui::{self, Spinner};
 use crate::utils::{apply_profile, check_url_status, interactive_pickup, ConnectionStatus};
 
+mod watch;
+
 #[derive(Subcommand)]
 pub enum ApiCommand {
     /// Add a new profile
@@ -12,31 +24,64 @@ pub enum ApiCommand {
         /// Profile name
         name: String,
         /// API URL
-        #[arg(short, long)]
-        url: String,
+        #[arg(short, long, required_unless_present = "base")]
+        url: Option<String>,
         /// API Key
         #[arg(short, long)]
         key: String,
-        /// Service type (claude-code or codex)
-        #[arg(short, long, default_value = "claude-code")]
-        service: String,
+        /// Service type (claude-code or codex; default: the base's, or claude-code)
+        #[arg(short, long)]
+        service: Option<String>,
+        /// Profile to inherit the URL and service from
+        #[arg(short, long)]
+        base: Option<String>,
+        /// Last day the key is valid (YYYY-MM-DD)
+        #[arg(long)]
+        expires: Option<String>,
     },
     /// List all profiles
     List,
-    /// Switch to a profile (or default if no name provided)
+    /// Switch to a profile (or default if no name provided); each service keeps its own active profile
     Switch {
         /// Profile name to switch to (optional, uses default if not specified)
         name: Option<String>,
+        /// Service whose active profile changes (default: the profile's own service)
+        #[arg(short, long)]
+        service: Option<String>,
     },
     /// Delete a profile
     Delete {
         /// Profile name to delete
         name: String,
     },
-    /// Show current active profile
-    Current,
+    /// Show the active profile of each service
+    Current {
+        /// Only show the active profile for this service
+        #[arg(short, long)]
+        service: Option<String>,
+    },
     /// Check connection status of all profiles
     Status,
+    /// Keep checking a profile's endpoint, one status line per check
+    Watch {
+        /// Profile to check (default: the project's pinned profile, else the active claude-code one)
+        #[arg(short, long)]
+        profile: Option<String>,
+        /// Seconds between checks
+        #[arg(short, long, default_value_t = watch::DEFAULT_INTERVAL_SECS)]
+        interval: u64,
+        /// Also write the latest status line to this file, for tmux or starship
+        #[arg(long)]
+        status_file: Option<PathBuf>,
+    },
+    /// List the models a profile's endpoint offers (cached for a day)
+    Models {
+        /// Profile to ask (default: the project's pinned profile, else the active claude-code one)
+        profile: Option<String>,
+        /// Fetch the list again even if the cached one is recent
+        #[arg(long)]
+        refresh: bool,
+    },
     /// Set default profile
     SetDefault {
         /// Profile name to set as default
@@ -44,6 +89,24 @@ pub enum ApiCommand {
     },
     /// Clear default profile
     ClearDefault,
+    /// Replace a profile's key, keeping the old one in the profile's key history
+    Rotate {
+        /// Profile whose key to replace
+        name: String,
+        /// New key (prompted for if omitted)
+        #[arg(short, long)]
+        key: Option<String>,
+        /// Last day the new key is valid (YYYY-MM-DD; default: same lifetime as the old key)
+        #[arg(long)]
+        expires: Option<String>,
+    },
+    /// Pin a profile to the current project, recorded in its .ecce.json
+    Pin {
+        /// Profile to use for this project
+        name: String,
+    },
+    /// Remove the current project's pinned profile
+    Unpin,
     /// Interactively pick a profile to switch to
     #[command(hide = true)]
     Pickup,
@@ -56,28 +119,48 @@ pub async fn handle_api_command(command: ApiCommand, config: &mut Config) -> Res
             url,
             key,
             service,
+            base,
+            expires,
         } => {
+            let expires_at = expires.as_deref().map(history::parse_since).transpose()?;
+            let created_at = (!key.is_empty()).then(Utc::now);
+            if let Some(ref base) = base {
+                if !config.profiles.iter().any(|p| &p.name == base) {
+                    return Err(profile_not_found(base));
+                }
+            }
             let profile = Profile {
                 name: name.clone(),
-                url,
+                url: url.unwrap_or_default(),
                 key,
-                service,
+                service: service.unwrap_or_else(|| {
+                    if base.is_some() { String::new() } else { CLAUDE_CODE_SERVICE.to_string() }
+                }),
+                base,
+                created_at,
+                expires_at,
+                key_history: Vec::new(),
             };
             config.add_profile(profile)?;
-            println!(
+            ui_println!(
                 "{}",
                 format!("✓ Profile '{}' added successfully", name).green()
             );
         }
         ApiCommand::List => {
             if config.profiles.is_empty() {
-                println!("{}", "No profiles configured".yellow());
+                ui_result!("{}", "No profiles configured".yellow());
             } else {
-                println!("{}", "Available profiles:".bold());
-                for profile in &config.profiles {
+                let today = Utc::now().date_naive();
+                ui_result!("{}", "Available profiles:".bold());
+                for profile in &config.resolved_profiles() {
                     let mut markers = Vec::new();
 
-                    if config.active_profile.as_deref() == Some(&profile.name) {
+                    if let Some(ref base) = profile.base {
+                        markers.push(format!("base: {}", base).dimmed().to_string());
+                    }
+
+                    if config.is_active(profile) {
                         markers.push("active".green().to_string());
                     }
 
@@ -91,26 +174,44 @@ pub async fn handle_api_command(command: ApiCommand, config: &mut Config) -> Res
                         format!(" ({})", markers.join(", "))
                     };
 
-                    println!(
+                    ui_result!(
                         "  {} - {} [{}]{}",
                         profile.name.cyan(),
                         profile.url,
                         profile.service,
                         marker_text
                     );
+                    if let Some(warning) = profile.expiry_warning(today) {
+                        ui_result!("    {}", format!("⚠ {}", warning).yellow());
+                    }
                 }
             }
         }
-        ApiCommand::Switch { name } => {
+        ApiCommand::Switch { name, service } => {
+            let pinned = current_dir_pin(config, service.as_deref())?;
+            if let (Some(name), Some(pinned)) = (&name, &pinned) {
+                if name != pinned {
+                    ui_println!(
+                        "{}",
+                        format!("⚠ This project is pinned to '{}'; ecce homo here keeps using it", pinned).yellow()
+                    );
+                }
+            }
+            let default = pinned.or(config
+                .default_profile
+                .as_deref()
+                .and_then(|name| config.resolve_profile(name).ok())
+                .filter(|p| service.as_deref().is_none_or(|s| p.service == s))
+                .map(|p| p.name.clone()));
             let target_name = match name {
                 Some(n) => n,
                 None => {
-                    // Use default profile if available, otherwise trigger pickup
-                    match &config.default_profile {
-                        Some(default) => default.clone(),
+                    // Use the project's pinned profile or the default (for the service), otherwise trigger pickup
+                    match default {
+                        Some(default) => default,
                         None => {
                             // No default set, trigger interactive pickup
-                            match interactive_pickup(config)? {
+                            match interactive_pickup(config, service.as_deref())? {
                                 Some(selected) => selected,
                                 None => return Ok(()), // User cancelled or invalid selection
                             }
@@ -119,93 +220,263 @@ pub async fn handle_api_command(command: ApiCommand, config: &mut Config) -> Res
                 }
             };
 
+            if let (Some(service), Ok(profile)) = (&service, config.resolve_profile(&target_name)) {
+                if &profile.service != service {
+                    return Err(anyhow::anyhow!(
+                        "Profile '{}' is for service '{}', not '{}'",
+                        profile.name,
+                        profile.service,
+                        service
+                    ));
+                }
+            }
+
             match config.switch_profile(&target_name)? {
                 Some(profile) => {
                     apply_profile(&profile)?;
                 }
                 None => {
-                    eprintln!("{}", format!("✗ Profile '{}' not found", target_name).red());
+                    return Err(profile_not_found(&target_name));
                 }
             }
         }
         ApiCommand::Delete { name } => {
             if config.delete_profile(&name)? {
-                println!("{}", format!("✓ Profile '{}' deleted", name).green());
+                ui_println!("{}", format!("✓ Profile '{}' deleted", name).green());
             } else {
-                println!("{}", format!("✗ Profile '{}' not found", name).red());
+                return Err(profile_not_found(&name));
+            }
+        }
+        ApiCommand::Current { service } => {
+            let active: Vec<Profile> = match service {
+                Some(ref service) => config.get_active_profile(service).into_iter().collect(),
+                None => config
+                    .active_profiles
+                    .keys()
+                    .filter_map(|service| config.get_active_profile(service))
+                    .collect(),
+            };
+            if let Some(pinned) = current_dir_pin(config, service.as_deref())? {
+                ui_result!("{}", format!("📌 Pinned to this project: {}", pinned).cyan());
+            }
+            if active.is_empty() {
+                match service {
+                    Some(service) => {
+                        ui_result!("{}", format!("No active profile for {}", service).yellow())
+                    }
+                    None => ui_result!("{}", "No active profile".yellow()),
+                }
+            }
+            for (idx, profile) in active.iter().enumerate() {
+                if idx > 0 {
+                    ui_result!();
+                }
+                ui_result!("{}", format!("Current active profile ({}):", profile.service).bold());
+                ui_result!("  Name:    {}", profile.name.cyan());
+                ui_result!("  URL:     {}", profile.url);
+                ui_result!("  Service: {}", profile.service);
+                ui_result!("  Key:     {}***", ui::prefix(&profile.key, 8));
             }
         }
-        ApiCommand::Current => match config.get_active_profile() {
-            Some(profile) => {
-                println!("{}", "Current active profile:".bold());
-                println!("  Name:    {}", profile.name.cyan());
-                println!("  URL:     {}", profile.url);
-                println!("  Service: {}", profile.service);
-                println!("  Key:     {}***", &profile.key[..profile.key.len().min(8)]);
-            }
-            None => {
-                println!("{}", "No active profile".yellow());
-            }
-        },
         ApiCommand::Status => {
             if config.profiles.is_empty() {
-                println!("{}", "No profiles configured".yellow());
+                ui_result!("{}", "No profiles configured".yellow());
             } else {
-                println!(
+                ui_println!(
                     "{}",
                     "Checking connection status for all profiles...".bold()
                 );
-                println!();
+                ui_println!();
 
-                for profile in &config.profiles {
-                    let active = if config.active_profile.as_deref() == Some(&profile.name) {
+                let today = Utc::now().date_naive();
+                let mut unreachable = 0;
+                for profile in &config.resolved_profiles() {
+                    let active = if config.is_active(profile) {
                         " (active)".green()
                     } else {
                         "".normal()
                     };
 
-                    print!(
-                        "  {}{} [{}] - ",
-                        profile.name.cyan(),
-                        active,
-                        profile.service
-                    );
-
-                    let status = check_url_status(&profile.url, &profile.key).await;
-
-                    match status {
+                    let status = match check_url_status(&profile.url, &profile.key).await {
                         ConnectionStatus::Success(duration) => {
-                            println!("{} ({}ms)", "✓ Connected".green(), duration.as_millis());
+                            format!("{} ({}ms)", "✓ Connected".green(), duration.as_millis())
                         }
                         ConnectionStatus::Failed(reason) => {
-                            println!("{}: {}", "✗ Failed".red(), reason);
+                            unreachable += 1;
+                            format!("{}: {}", "✗ Failed".red(), reason)
                         }
                         ConnectionStatus::Timeout => {
-                            println!("{}", "✗ Timeout".red());
+                            unreachable += 1;
+                            "✗ Timeout".red().to_string()
+                        }
+                    };
+
+                    ui_result!(
+                        "  {}{} [{}] - {}",
+                        profile.name.cyan(),
+                        active,
+                        profile.service,
+                        status
+                    );
+                    if let Some(warning) = profile.expiry_warning(today) {
+                        ui_result!("    {}", format!("⚠ {}", warning).yellow());
+                    }
+                }
+
+                if unreachable > 0 {
+                    return Err(Failure::Connectivity.error(format!(
+                        "{} of {} profiles unreachable",
+                        unreachable,
+                        config.profiles.len()
+                    )));
+                }
+            }
+        }
+        ApiCommand::Watch {
+            profile,
+            interval,
+            status_file,
+        } => {
+            let profile = exec::select_profile(profile.as_deref(), config)?;
+            watch::watch(&profile, Duration::from_secs(interval.max(1)), status_file).await?;
+        }
+        ApiCommand::Models { profile, refresh } => {
+            let profile = exec::select_profile(profile.as_deref(), config)?;
+            let cached = models::cached(&profile);
+            let catalog = match cached {
+                Some(catalog) if !refresh && catalog.is_fresh(Utc::now()) => catalog,
+                cached => {
+                    let spinner = Spinner::start(format!("Asking {} for its models", profile.url));
+                    let fetched = models::fetch(&profile).await;
+                    spinner.stop();
+                    match (fetched, cached) {
+                        (Ok(list), _) => models::store(&profile, list)?,
+                        (Err(e), Some(catalog)) => {
+                            ui_eprintln!(
+                                "{}",
+                                format!("⚠ Could not refresh the model list, showing the cached one: {:#}", e).yellow()
+                            );
+                            catalog
+                        }
+                        (Err(e), None) => {
+                            return Err(Failure::Connectivity.error(format!(
+                                "Could not list models for '{}': {:#}",
+                                profile.name, e
+                            )));
                         }
                     }
                 }
+            };
+
+            ui_println!(
+                "{}",
+                format!(
+                    "Models offered by '{}' ({}, fetched {}):",
+                    profile.name,
+                    profile.url,
+                    catalog.fetched_at.with_timezone(&chrono::Local).format("%Y-%m-%d %H:%M")
+                )
+                .bold()
+            );
+            for model in &catalog.models {
+                ui_result!("{}", model);
             }
         }
         ApiCommand::SetDefault { name } => {
             if config.set_default_profile(&name)? {
-                println!("{}", format!("✓ Default profile set to '{}'", name).green());
+                ui_println!("{}", format!("✓ Default profile set to '{}'", name).green());
             } else {
-                println!("{}", format!("✗ Profile '{}' not found", name).red());
+                return Err(profile_not_found(&name));
             }
         }
         ApiCommand::ClearDefault => {
             config.clear_default_profile()?;
-            println!("{}", "✓ Default profile cleared".green());
+            ui_println!("{}", "✓ Default profile cleared".green());
+        }
+        ApiCommand::Rotate { name, key, expires } => {
+            let expires_at = expires.as_deref().map(history::parse_since).transpose()?;
+            let key = match key {
+                Some(key) => key,
+                None => {
+                    ui_prompt!("{} ", format!("New key for '{}':", name).yellow());
+                    io::stdout().flush()?;
+                    let mut input = String::new();
+                    io::stdin().read_line(&mut input)?;
+                    input.trim().to_string()
+                }
+            };
+            if key.is_empty() {
+                return Err(anyhow::anyhow!("No key given; '{}' is unchanged", name));
+            }
+
+            let Some(profile) = config.rotate_key(&name, key, expires_at)? else {
+                return Err(profile_not_found(&name));
+            };
+            ui_println!(
+                "{}",
+                format!(
+                    "✓ Rotated key for '{}' ({} old key(s) archived)",
+                    name,
+                    profile.key_history.len()
+                )
+                .green()
+            );
+            if let Some(date) = profile.expires_at {
+                ui_println!("  Expires: {}", date);
+            }
+            if config.profiles.iter().any(|p| p.base.as_deref() == Some(name.as_str()) && p.key.is_empty()) {
+                ui_println!("  {}", "Profiles based on it use the new key too.".dimmed());
+            }
+            ui_println!(
+                "  {}",
+                "Run `ecce api switch` again in projects whose .mise.toml uses this profile.".dimmed()
+            );
+        }
+        ApiCommand::Pin { name } => {
+            if !config.profiles.iter().any(|p| p.name == name) {
+                return Err(profile_not_found(&name));
+            }
+            let cwd = std::env::current_dir()?;
+            let path = ProjectConfig::locate(&cwd).unwrap_or_else(|| cwd.join(PROJECT_CONFIG_FILE));
+            let mut project = if path.exists() {
+                ProjectConfig::load_from(&path).context(Failure::Config)?
+            } else {
+                ProjectConfig::default()
+            };
+            project.profile = Some(name.clone());
+            project.save(&path)?;
+            ui_println!(
+                "{}",
+                format!("📌 Pinned '{}' in {}", name, path.display()).green()
+            );
+            ui_println!(
+                "  {}",
+                "ecce homo and ecce api switch use it for files in this project.".dimmed()
+            );
+        }
+        ApiCommand::Unpin => {
+            let cwd = std::env::current_dir()?;
+            let Some(path) = ProjectConfig::locate(&cwd) else {
+                ui_result!("{}", "No project profile pinned here".yellow());
+                return Ok(());
+            };
+            let mut project = ProjectConfig::load_from(&path).context(Failure::Config)?;
+            match project.profile.take() {
+                Some(name) => {
+                    project.save(&path)?;
+                    ui_println!("{}", format!("✓ Unpinned '{}' from {}", name, path.display()).green());
+                }
+                None => ui_result!("{}", "No project profile pinned here".yellow()),
+            }
         }
         ApiCommand::Pickup => {
-            match interactive_pickup(config)? {
+            match interactive_pickup(config, None)? {
                 Some(profile_name) => match config.switch_profile(&profile_name)? {
                     Some(profile) => {
                         apply_profile(&profile)?;
                     }
                     None => {
-                        eprintln!("{}", "✗ Failed to switch profile".red());
+                        ui_eprintln!("{}", "✗ Failed to switch profile".red());
                     }
                 },
                 None => {
@@ -217,3 +488,24 @@ pub async fn handle_api_command(command: ApiCommand, config: &mut Config) -> Res
 
     Ok(())
 }
+
+/// The profile pinned to the project around the current directory, if it is for `service` (when given)
+fn current_dir_pin(config: &Config, service: Option<&str>) -> Result<Option<String>> {
+    let Some(name) = ProjectConfig::find(&std::env::current_dir()?)
+        .context(Failure::Config)?
+        .profile
+    else {
+        return Ok(None);
+    };
+    let profile = config.resolve_profile(&name).map_err(|_| {
+        Failure::ProfileNotFound.error(format!(
+            "Profile '{}' is pinned in {} but not configured",
+            name, PROJECT_CONFIG_FILE
+        ))
+    })?;
+    Ok(service.is_none_or(|s| profile.service == s).then_some(name))
+}
+
+fn profile_not_found(name: &str) -> anyhow::Error {
+    Failure::ProfileNotFound.error(format!("Profile '{}' not found", name))
+}
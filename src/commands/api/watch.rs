@@ -0,0 +1,167 @@
+use anyhow::{Context, Result};
+use chrono::Local;
+use colored::*;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+use ecce::{ui_eprintln, ui_println, ui_result};
+
+use crate::config::Profile;
+use crate::ui;
+use crate::utils::{check_url_status, ConnectionStatus};
+
+pub const DEFAULT_INTERVAL_SECS: u64 = 30;
+
+/// One-line status for prompts and status bars, e.g. `✓ relay 230ms`
+fn status_line(profile: &str, status: &ConnectionStatus) -> String {
+    match status {
+        ConnectionStatus::Success(duration) => format!("✓ {} {}ms", profile, duration.as_millis()),
+        ConnectionStatus::Failed(reason) => format!("✗ {} {}", profile, ui::truncate(reason, 40)),
+        ConnectionStatus::Timeout => format!("✗ {} timeout", profile),
+    }
+}
+
+/// A change worth alerting about
+#[derive(Debug, PartialEq)]
+enum Transition {
+    Down,
+    Recovered(Duration),
+}
+
+/// Remembers whether the endpoint was up, to alert on changes only
+#[derive(Debug, Default)]
+struct Tracker {
+    up: Option<bool>,
+    down_since: Option<Instant>,
+}
+
+impl Tracker {
+    fn update(&mut self, up: bool, now: Instant) -> Option<Transition> {
+        let previous = self.up.replace(up);
+        match (previous, up) {
+            // Starting out down is worth an alert too
+            (Some(true) | None, false) => {
+                self.down_since = Some(now);
+                Some(Transition::Down)
+            }
+            (Some(false), true) => self
+                .down_since
+                .take()
+                .map(|since| Transition::Recovered(now - since)),
+            _ => None,
+        }
+    }
+}
+
+/// Replace the status file in one step, so readers never see it half-written
+fn write_status_file(path: &Path, line: &str) -> Result<()> {
+    let tmp = path.with_extension("tmp");
+    fs::write(&tmp, format!("{}\n", line))?;
+    fs::rename(&tmp, path).with_context(|| format!("Failed to write {}", path.display()))
+}
+
+/// Check a profile's endpoint every `interval` until interrupted, printing one
+/// line per check and alerting when it goes down or comes back
+pub async fn watch(profile: &Profile, interval: Duration, status_file: Option<PathBuf>) -> Result<()> {
+    ui_println!(
+        "{}",
+        format!(
+            "📡 Checking '{}' ({}) every {}s. Press Ctrl+C to stop.",
+            profile.name,
+            profile.url,
+            interval.as_secs()
+        )
+        .green()
+    );
+
+    // Listened for from the start, so a Ctrl+C pressed during a check isn't lost
+    let (interrupt, mut stop) = tokio::sync::watch::channel(false);
+    tokio::spawn(async move {
+        if tokio::signal::ctrl_c().await.is_ok() {
+            let _ = interrupt.send(true);
+        }
+    });
+
+    let mut tracker = Tracker::default();
+    loop {
+        let status = check_url_status(&profile.url, &profile.key).await;
+        let up = matches!(status, ConnectionStatus::Success(_));
+        let line = status_line(&profile.name, &status);
+
+        let time = Local::now().format("%H:%M:%S").to_string();
+        ui_result!("{} {}", time.dimmed(), if up { line.green() } else { line.red() });
+        match tracker.update(up, Instant::now()) {
+            Some(Transition::Down) => {
+                // Bell so a terminal in the background flags it
+                ui_eprintln!("\x07{}", format!("🚨 '{}' is unreachable", profile.name).red().bold());
+            }
+            Some(Transition::Recovered(after)) => ui_eprintln!(
+                "{}",
+                format!("✅ '{}' is back after {}s", profile.name, after.as_secs()).green().bold()
+            ),
+            None => {}
+        }
+        if let Some(ref path) = status_file {
+            write_status_file(path, &line)?;
+        }
+
+        if *stop.borrow() {
+            break;
+        }
+        tokio::select! {
+            _ = tokio::time::sleep(interval) => {}
+            _ = stop.changed() => break,
+        }
+    }
+
+    // A stale "✓" would be worse than nothing
+    if let Some(ref path) = status_file {
+        let _ = fs::remove_file(path);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_status_line() {
+        assert_eq!(
+            status_line("relay", &ConnectionStatus::Success(Duration::from_millis(230))),
+            "✓ relay 230ms"
+        );
+        assert_eq!(status_line("relay", &ConnectionStatus::Timeout), "✗ relay timeout");
+        assert_eq!(
+            status_line("relay", &ConnectionStatus::Failed("HTTP 502".to_string())),
+            "✗ relay HTTP 502"
+        );
+    }
+
+    #[test]
+    fn test_tracker_alerts_on_changes_only() {
+        let start = Instant::now();
+        let mut tracker = Tracker::default();
+        assert_eq!(tracker.update(true, start), None);
+        assert_eq!(tracker.update(true, start), None);
+        assert_eq!(tracker.update(false, start), Some(Transition::Down));
+        assert_eq!(tracker.update(false, start), None);
+        assert_eq!(
+            tracker.update(true, start + Duration::from_secs(90)),
+            Some(Transition::Recovered(Duration::from_secs(90)))
+        );
+
+        let mut down_at_start = Tracker::default();
+        assert_eq!(down_at_start.update(false, start), Some(Transition::Down));
+    }
+
+    #[test]
+    fn test_write_status_file() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let path = dir.path().join("ecce-status");
+        write_status_file(&path, "✓ relay 12ms").unwrap();
+        write_status_file(&path, "✗ relay timeout").unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "✗ relay timeout\n");
+        assert!(!dir.path().join("ecce-status.tmp").exists());
+    }
+}
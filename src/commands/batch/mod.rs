@@ -0,0 +1,136 @@
+use anyhow::{Context, Result};
+use clap::Args;
+use colored::*;
+use std::collections::BTreeSet;
+use std::path::PathBuf;
+use std::sync::Arc;
+use tokio::sync::Semaphore;
+use tokio::task::JoinSet;
+use ecce::{ui_eprintln, ui_result};
+
+use crate::commands::homo;
+use crate::config::Config;
+use crate::exit::Failure;
+use crate::ui;
+
+/// Files processed at once unless `--jobs` says otherwise
+const DEFAULT_JOBS: usize = 4;
+
+#[derive(Args)]
+pub struct BatchArgs {
+    /// Glob patterns of files to process (e.g. 'lectures/**/*.md'); folders use their slides.md
+    #[arg(required = true)]
+    pub patterns: Vec<String>,
+
+    /// Agent to use (default: the default agent)
+    #[arg(short, long)]
+    pub agent: Option<String>,
+
+    /// Task to use
+    #[arg(short, long)]
+    pub task: Option<String>,
+
+    /// Number of files to process in parallel
+    #[arg(short, long, default_value_t = DEFAULT_JOBS)]
+    pub jobs: usize,
+}
+
+/// Expand glob patterns into a sorted, de-duplicated list of paths
+pub fn expand_globs(patterns: &[String]) -> Result<Vec<PathBuf>> {
+    let mut files = BTreeSet::new();
+    for pattern in patterns {
+        let mut matched = false;
+        for entry in glob::glob(pattern).with_context(|| format!("Invalid glob pattern '{}'", pattern))? {
+            files.insert(entry?);
+            matched = true;
+        }
+        if !matched {
+            ui_eprintln!("{}", format!("⚠ No files match '{}'", pattern).yellow());
+        }
+    }
+    Ok(files.into_iter().collect())
+}
+
+/// Answer every pattern in many files without watching, a few files at a
+/// time, then report per file. Fails if any file had errors.
+pub async fn handle_batch_command(args: BatchArgs, config: &Config) -> Result<()> {
+    let files = expand_globs(&args.patterns)?;
+    if files.is_empty() {
+        return Err(anyhow::anyhow!("No files to process"));
+    }
+
+    // Per-pattern progress from parallel files would interleave; report per file instead
+    let quiet = ui::quiet_until_dropped();
+
+    let config = Arc::new(config.clone());
+    let permits = Arc::new(Semaphore::new(args.jobs.max(1)));
+    let mut set = JoinSet::new();
+    for file in files {
+        let config = config.clone();
+        let permits = permits.clone();
+        let (agent, task) = (args.agent.clone(), args.task.clone());
+        set.spawn(async move {
+            let _permit = permits.acquire_owned().await.expect("semaphore is never closed");
+            let result = homo::process_once(&file, &config, agent, task).await;
+            (file, result)
+        });
+    }
+
+    let (mut processed, mut answered, mut failed) = (0, 0, 0);
+    while let Some(joined) = set.join_next().await {
+        let (file, result) = joined.context("Batch task panicked")?;
+        processed += 1;
+        match result {
+            Ok(0) => ui_result!("  {} {}", "·".dimmed(), file.display().to_string().dimmed()),
+            Ok(count) => {
+                answered += count;
+                ui_result!(
+                    "  {} {} ({} answered)",
+                    "✓".green(),
+                    file.display(),
+                    count
+                );
+            }
+            Err(e) => {
+                failed += 1;
+                ui_result!("  {} {}: {:#}", "✗".red(), file.display(), e);
+            }
+        }
+    }
+    drop(quiet);
+
+    ui_result!(
+        "\n{} files, {} patterns answered, {} failed",
+        processed,
+        answered,
+        failed
+    );
+    if failed > 0 {
+        return Err(Failure::Generation.error(format!(
+            "{} of {} files had errors",
+            failed, processed
+        )));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn test_expand_globs() {
+        let temp = tempfile::TempDir::new().unwrap();
+        let dir = temp.path();
+        fs::create_dir(dir.join("week1")).unwrap();
+        fs::write(dir.join("week1/a.md"), "").unwrap();
+        fs::write(dir.join("week1/b.md"), "").unwrap();
+        fs::write(dir.join("notes.txt"), "").unwrap();
+
+        let md = format!("{}/**/*.md", dir.display());
+        let a = format!("{}/week1/a.md", dir.display());
+        let files = expand_globs(&[md, a]).unwrap();
+        assert_eq!(files, vec![dir.join("week1/a.md"), dir.join("week1/b.md")]);
+    }
+}
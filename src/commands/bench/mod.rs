@@ -0,0 +1,355 @@
+use anyhow::{Context, Result};
+use clap::Args;
+use colored::*;
+use regex::Regex;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::path::PathBuf;
+use ecce::{ui_println, ui_result};
+
+use crate::agent::{ClaudeAgent, RunOptions};
+use crate::commands::homo;
+use crate::config::{Agent, Config};
+use crate::history;
+use crate::pattern::PatternOptions;
+use crate::ui::{self, Spinner};
+
+#[derive(Args)]
+pub struct BenchArgs {
+    /// Agents to compare (comma-separated)
+    #[arg(long, required = true)]
+    pub agents: String,
+
+    /// File with one prompt per line; blank lines and lines starting with # are skipped
+    #[arg(long)]
+    pub prompts: PathBuf,
+
+    /// Task template applied to every agent
+    #[arg(short, long)]
+    pub task: Option<String>,
+
+    /// Agent that scores each answer from 1 to 10
+    #[arg(long)]
+    pub judge: Option<String>,
+
+    /// Output results as JSON, including every answer
+    #[arg(long)]
+    pub json: bool,
+}
+
+/// One agent's answer to one prompt
+#[derive(Debug, Serialize, Clone)]
+struct Run {
+    agent: String,
+    prompt: String,
+    response: Option<String>,
+    error: Option<String>,
+    latency_ms: u64,
+    /// Estimated (see `history::estimate_tokens`)
+    tokens: u64,
+    score: Option<f64>,
+}
+
+/// Per-agent totals for the comparison table
+#[derive(Debug, Serialize, Default, PartialEq)]
+struct Summary {
+    agent: String,
+    runs: usize,
+    errors: usize,
+    avg_latency_ms: u64,
+    avg_tokens: u64,
+    avg_score: Option<f64>,
+}
+
+#[derive(Serialize)]
+struct Report {
+    summary: Vec<Summary>,
+    runs: Vec<Run>,
+}
+
+/// Prompts from a prompts file, one per line
+fn parse_prompts(content: &str) -> Vec<String> {
+    content
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(str::to_string)
+        .collect()
+}
+
+/// Ask the judge to score answers it sees only by number, so it can't favor an agent by name
+fn judge_prompt(prompt: &str, responses: &[&str]) -> String {
+    let mut text = format!(
+        "Score each answer to the question below from 1 (useless) to 10 (excellent) for \
+         correctness, clarity, and fit for a presentation slide.\n\nQuestion:\n{}\n",
+        prompt
+    );
+    for (idx, response) in responses.iter().enumerate() {
+        text.push_str(&format!("\n--- Answer {} ---\n{}\n", idx + 1, response));
+    }
+    text.push_str(
+        "\nReply with only a JSON object mapping each answer number to its score, e.g. {\"1\": 7, \"2\": 4}.",
+    );
+    text
+}
+
+/// Scores by answer number (1-based) from the judge's reply; out-of-range scores are dropped
+fn parse_scores(reply: &str) -> HashMap<usize, f64> {
+    let object = Regex::new(r"\{[^{}]*\}").expect("valid regex");
+    let Some(json) = object.find(reply) else {
+        return HashMap::new();
+    };
+    let Ok(map) = serde_json::from_str::<HashMap<String, serde_json::Value>>(json.as_str()) else {
+        return HashMap::new();
+    };
+    map.into_iter()
+        .filter_map(|(key, value)| {
+            let number = key.trim().trim_start_matches("Answer").trim().parse().ok()?;
+            let score = value.as_f64()?;
+            (1.0..=10.0).contains(&score).then_some((number, score))
+        })
+        .collect()
+}
+
+fn summarize(agent_names: &[String], runs: &[Run]) -> Vec<Summary> {
+    agent_names
+        .iter()
+        .map(|name| {
+            let mine: Vec<&Run> = runs.iter().filter(|r| &r.agent == name).collect();
+            let ok: Vec<&&Run> = mine.iter().filter(|r| r.error.is_none()).collect();
+            let scores: Vec<f64> = mine.iter().filter_map(|r| r.score).collect();
+            let avg = |total: u64, count: usize| total.checked_div(count as u64).unwrap_or(0);
+            Summary {
+                agent: name.clone(),
+                runs: mine.len(),
+                errors: mine.len() - ok.len(),
+                avg_latency_ms: avg(ok.iter().map(|r| r.latency_ms).sum(), ok.len()),
+                avg_tokens: avg(ok.iter().map(|r| r.tokens).sum(), ok.len()),
+                avg_score: (!scores.is_empty())
+                    .then(|| scores.iter().sum::<f64>() / scores.len() as f64),
+            }
+        })
+        .collect()
+}
+
+/// Run every prompt against every agent (agents concurrently, prompts in turn),
+/// optionally have a judge score the answers, and print a comparison table
+pub async fn handle_bench_command(args: BenchArgs, config: &Config) -> Result<()> {
+    let prompts = parse_prompts(
+        &std::fs::read_to_string(&args.prompts)
+            .with_context(|| format!("Failed to read prompts file: {}", args.prompts.display()))?,
+    );
+    if prompts.is_empty() {
+        return Err(anyhow::anyhow!("No prompts in {}", args.prompts.display()));
+    }
+
+    let agent_configs: Vec<Agent> = args
+        .agents
+        .split(',')
+        .map(str::trim)
+        .filter(|name| !name.is_empty())
+        .map(|name| config.resolve_agent(name))
+        .collect::<Result<_>>()?;
+    if agent_configs.is_empty() {
+        return Err(anyhow::anyhow!("--agents requires at least one agent name"));
+    }
+    for (idx, agent) in agent_configs.iter().enumerate() {
+        if agent_configs[..idx].iter().any(|a| a.name == agent.name) {
+            return Err(anyhow::anyhow!("Agent '{}' is listed twice in --agents", agent.name));
+        }
+    }
+    let task = args.task.as_deref().map(|name| config.resolve_task(name)).transpose()?;
+    let judge = args
+        .judge
+        .as_deref()
+        .map(|name| {
+            config
+                .resolve_agent(name)
+                .with_context(|| format!("Judge agent '{}' can't be used", name))
+        })
+        .transpose()?;
+
+    let executable = config.get_claude_executable();
+    let options = RunOptions::from_config(config);
+    let agent_names: Vec<String> = agent_configs.iter().map(|a| a.name.clone()).collect();
+    if args.json {
+        ui::status_to_stderr();
+    }
+
+    let mut runs = Vec::new();
+    for (idx, prompt) in prompts.iter().enumerate() {
+        // Fresh agents per prompt, so no answer sees an earlier one
+        let mut agents: Vec<ClaudeAgent> = agent_configs
+            .iter()
+            .map(|agent| {
+                ClaudeAgent::with_options(executable.clone(), agent.clone(), task.clone(), options.clone())
+            })
+            .collect();
+
+        let spinner = Spinner::start(format!(
+            "Prompt {}/{}: {}",
+            idx + 1,
+            prompts.len(),
+            ui::truncate(prompt, 50).dimmed()
+        ));
+        let results = homo::generate_all(&mut agents, prompt, &PatternOptions::default()).await?;
+        spinner.stop();
+
+        let mut prompt_runs: Vec<Run> = agent_names
+            .iter()
+            .zip(results)
+            .map(|(agent, (result, elapsed))| {
+                let (response, error) = match result {
+                    Ok(response) => (Some(response), None),
+                    Err(e) => (None, Some(format!("{:#}", e))),
+                };
+                Run {
+                    agent: agent.clone(),
+                    prompt: prompt.clone(),
+                    tokens: response.as_deref().map_or(0, history::estimate_tokens),
+                    response,
+                    error,
+                    latency_ms: elapsed.as_millis() as u64,
+                    score: None,
+                }
+            })
+            .collect();
+
+        if let Some(ref judge) = judge {
+            score(&executable, &options, judge, prompt, &mut prompt_runs).await;
+        }
+        for run in &prompt_runs {
+            if let Some(ref error) = run.error {
+                ui_println!("  {} {}: {}", "✗".red(), run.agent, error);
+            }
+        }
+        runs.extend(prompt_runs);
+    }
+
+    let summary = summarize(&agent_names, &runs);
+    if args.json {
+        println!("{}", serde_json::to_string_pretty(&Report { summary, runs })?);
+        return Ok(());
+    }
+
+    ui_result!(
+        "\n{:<20} {:>5} {:>7} {:>12} {:>10} {:>7}",
+        "Agent".bold(),
+        "runs",
+        "errors",
+        "avg latency",
+        "avg tokens",
+        "score"
+    );
+    for row in &summary {
+        ui_result!(
+            "{:<20} {:>5} {:>7} {:>10}ms {:>10} {:>7}",
+            row.agent.cyan(),
+            row.runs,
+            row.errors,
+            row.avg_latency_ms,
+            format!("~{}", row.avg_tokens),
+            row.avg_score.map_or("-".to_string(), |s| format!("{:.1}", s))
+        );
+    }
+    ui_result!();
+    ui_result!("{}", "Token counts are estimates (~4 characters per token).".dimmed());
+    Ok(())
+}
+
+/// Have the judge score the successful answers in place; a failed judgment only warns
+async fn score(executable: &str, options: &RunOptions, judge: &Agent, prompt: &str, runs: &mut [Run]) {
+    let answered: Vec<usize> = (0..runs.len()).filter(|&i| runs[i].response.is_some()).collect();
+    if answered.is_empty() {
+        return;
+    }
+    let responses: Vec<&str> = answered
+        .iter()
+        .filter_map(|&i| runs[i].response.as_deref())
+        .collect();
+
+    let mut judge = ClaudeAgent::with_options(
+        executable.to_string(),
+        judge.clone(),
+        None,
+        options.clone(),
+    );
+    let spinner = Spinner::start(format!("Judging with {}", judge.agent_name()));
+    let reply = judge.generate_response(&judge_prompt(prompt, &responses)).await;
+    spinner.stop();
+
+    match reply {
+        Ok(reply) => {
+            let scores = parse_scores(&reply);
+            if scores.is_empty() {
+                ui_println!("  {}", "⚠ Judge reply had no scores".yellow());
+            }
+            for (number, &idx) in answered.iter().enumerate() {
+                runs[idx].score = scores.get(&(number + 1)).copied();
+            }
+        }
+        Err(e) => ui_println!("  {}", format!("⚠ Judge failed: {:#}", e).yellow()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_prompts() {
+        let prompts = parse_prompts("# warmup\nWhat is Rust?\n\n  Explain borrowing  \n");
+        assert_eq!(prompts, vec!["What is Rust?", "Explain borrowing"]);
+    }
+
+    #[test]
+    fn test_parse_scores() {
+        let scores = parse_scores("Here you go:\n```json\n{\"1\": 7, \"Answer 2\": 9.5, \"3\": 42}\n```");
+        assert_eq!(scores.len(), 2);
+        assert_eq!(scores[&1], 7.0);
+        assert_eq!(scores[&2], 9.5);
+        assert!(parse_scores("no idea").is_empty());
+    }
+
+    #[test]
+    fn test_judge_prompt_hides_agent_names() {
+        let prompt = judge_prompt("What is Rust?", &["A language", "A fungus"]);
+        assert!(prompt.contains("--- Answer 1 ---\nA language"));
+        assert!(prompt.contains("--- Answer 2 ---\nA fungus"));
+    }
+
+    #[test]
+    fn test_summarize() {
+        let run = |agent: &str, ok: bool, latency_ms, score| Run {
+            agent: agent.to_string(),
+            prompt: "q".to_string(),
+            response: ok.then(|| "answer".to_string()),
+            error: (!ok).then(|| "failed".to_string()),
+            latency_ms,
+            tokens: if ok { 40 } else { 0 },
+            score,
+        };
+        let runs = vec![
+            run("a", true, 1000, Some(6.0)),
+            run("a", true, 3000, Some(8.0)),
+            run("b", false, 50, None),
+            run("b", true, 500, None),
+        ];
+
+        let summary = summarize(&["a".to_string(), "b".to_string()], &runs);
+        assert_eq!(
+            summary[0],
+            Summary {
+                agent: "a".to_string(),
+                runs: 2,
+                errors: 0,
+                avg_latency_ms: 2000,
+                avg_tokens: 40,
+                avg_score: Some(7.0),
+            }
+        );
+        assert_eq!(summary[1].errors, 1);
+        assert_eq!(summary[1].avg_latency_ms, 500);
+        assert_eq!(summary[1].avg_score, None);
+    }
+}
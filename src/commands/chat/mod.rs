@@ -0,0 +1,359 @@
+use anyhow::{Context, Result};
+use chrono::Utc;
+use clap::Args;
+use colored::*;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::time::Instant;
+use tokio::io::{AsyncBufReadExt, BufReader, Lines, Stdin};
+use ecce::{ui_eprintln, ui_println, ui_prompt};
+
+use crate::agent::{ClaudeAgent, RunOptions};
+use crate::commands::homo::{enforce_guards_asking, load_guard, DEFAULT_WATCH_INTERVAL_MS, GUARD_OVERRIDE_QUESTION};
+use crate::config::Config;
+use crate::document;
+use crate::history::{self, Feedback, HistoryEntry, Vote};
+use crate::redact::Redactor;
+use crate::remote::FileTarget;
+use crate::session::{self, SavedAgent, SavedSession, SessionSettings};
+
+#[derive(Args)]
+pub struct ChatArgs {
+    /// Document the conversation is about: the chat continues its `ecce homo`
+    /// session's conversation, and `/save-to` writes to it by default
+    pub file: Option<PathBuf>,
+
+    /// Agent to talk to (default: the document's session agent, then the default agent)
+    #[arg(short, long)]
+    pub agent: Option<String>,
+
+    /// Task whose template frames the conversation
+    #[arg(short, long)]
+    pub task: Option<String>,
+}
+
+/// Recorded as the file of chats that aren't about a document
+const NO_FILE: &str = "<chat>";
+
+const HELP: &str = "\
+  /save-to [file]  Append the last question and reply to a document (default: the chat's file)
+  /good [note]     Rate the last reply up, noted in the history
+  /bad [note]      Rate the last reply down
+  /clear           Start the conversation over
+  /exit            Leave (also /quit or Ctrl-D)";
+
+/// A terminal conversation with one agent
+struct Chat<'a> {
+    config: &'a Config,
+    claude: ClaudeAgent,
+    /// The document, and its session name (see `session_file`)
+    file: Option<(PathBuf, String)>,
+    /// The document's `ecce homo` session, kept up to date with the conversation
+    saved: Option<SavedSession>,
+    /// Whether the conversation is written back to the session
+    shared: bool,
+    /// The last question, its reply, and its history id, for `/save-to` and ratings
+    last: Option<(String, String, String)>,
+    redactor: Redactor,
+}
+
+/// Talk to an agent at the terminal, with replies streamed as they're written
+pub async fn handle_chat_command(args: ChatArgs, config: &Config) -> Result<()> {
+    let file = match args.file {
+        Some(path) => {
+            let name = session_file(&path)?;
+            Some((path, name))
+        }
+        None => None,
+    };
+    let saved = match file {
+        Some((_, ref name)) => session::latest_autosave(Some(name))?,
+        None => None,
+    };
+
+    let agent_name = args
+        .agent
+        .or_else(|| saved.as_ref().and_then(|s| s.agents.first()).map(|a| a.name.clone()));
+    let agent = match agent_name {
+        Some(name) => config.resolve_agent(&name)?,
+        None => config
+            .resolve_default_agent()?
+            .ok_or_else(|| anyhow::anyhow!("No default agent set; pass --agent"))?,
+    };
+    let task_name = args.task.or_else(|| saved.as_ref().and_then(|s| s.task.clone()));
+    let task = task_name.map(|name| config.resolve_task(&name)).transpose()?;
+    let mut claude = ClaudeAgent::with_options(
+        config.get_claude_executable(),
+        agent,
+        task,
+        RunOptions::from_config(config),
+    );
+
+    // Sharing a compare session's conversation would add an agent to it
+    let shared = match saved {
+        Some(ref saved) => {
+            let earlier = saved.agents.iter().find(|a| a.name == claude.agent_name());
+            if let Some(earlier) = earlier {
+                claude.restore_conversation(earlier.history.clone(), earlier.session_id.clone());
+                ui_println!(
+                    "{}",
+                    format!("↻ Continuing the conversation from {} ({} message(s))", saved.file, earlier.history.len())
+                        .dimmed()
+                );
+            } else {
+                ui_println!(
+                    "{}",
+                    format!("⚠ Agent '{}' isn't in the session on {}; this chat stays separate", claude.agent_name(), saved.file)
+                        .yellow()
+                );
+            }
+            earlier.is_some()
+        }
+        None => file.is_some(),
+    };
+
+    ui_println!(
+        "{} {} {}",
+        "💬 Chatting with".green(),
+        claude.agent_name().cyan(),
+        "(/help for commands, /exit to leave)".dimmed()
+    );
+    let mut chat = Chat {
+        config,
+        claude,
+        file,
+        saved,
+        shared,
+        last: None,
+        redactor: Redactor::from_config(config),
+    };
+
+    let mut lines = BufReader::new(tokio::io::stdin()).lines();
+    loop {
+        ui_prompt!("{} ", "you›".bold());
+        let _ = io::stdout().flush();
+        let Some(line) = lines.next_line().await? else {
+            break;
+        };
+        let line = line.trim();
+        let (command, arg) = line.split_once(' ').unwrap_or((line, ""));
+        match command {
+            "" => {}
+            "/exit" | "/quit" => break,
+            "/help" => ui_println!("{}", HELP),
+            "/clear" => chat.clear(),
+            "/save-to" => match chat.save_to(arg.trim(), &mut lines).await {
+                Ok(path) => ui_println!("{}", format!("✓ Appended the exchange to {}", path.display()).green()),
+                Err(e) => ui_eprintln!("{}", format!("✗ {:#}", e).red()),
+            },
+            "/good" | "/bad" => {
+                let vote = if command == "/good" { Vote::Up } else { Vote::Down };
+                match chat.rate(vote, arg.trim()) {
+                    Ok(()) => ui_println!("{}", format!("{} Noted in the history", vote.emoji()).dimmed()),
+                    Err(e) => ui_eprintln!("{}", format!("✗ {:#}", e).red()),
+                }
+            }
+            _ if command.starts_with('/') => {
+                ui_eprintln!("{}", format!("Unknown command {}; /help lists them", command).yellow())
+            }
+            _ => chat.ask(line).await,
+        }
+    }
+    Ok(())
+}
+
+impl Chat<'_> {
+    /// Send a question and stream the reply to stdout
+    async fn ask(&mut self, question: &str) {
+        ui_prompt!("{} ", format!("{}›", self.claude.agent_name()).cyan());
+        let start = Instant::now();
+        let redactor = &self.redactor;
+        // Shown a line at a time, so a secret split across deltas is still redacted
+        let mut partial = String::new();
+        let result = self
+            .claude
+            .generate_streamed(question, &mut |text| {
+                partial.push_str(text);
+                if let Some(end) = partial.rfind('\n') {
+                    let lines: String = partial.drain(..=end).collect();
+                    print!("{}", redactor.redact(&lines));
+                    let _ = io::stdout().flush();
+                }
+            })
+            .await;
+        println!("{}", redactor.redact(&partial));
+        let id = self.record(question, &result, start);
+
+        match result {
+            Ok(reply) => {
+                self.last = Some((question.to_string(), reply, id));
+                self.autosave();
+            }
+            Err(e) => ui_eprintln!("{}", format!("✗ {:#}", e).red()),
+        }
+    }
+
+    fn clear(&mut self) {
+        self.claude.restore_conversation(Vec::new(), None);
+        self.last = None;
+        self.autosave();
+        ui_println!("{}", "✓ Conversation cleared".green());
+    }
+
+    /// Append the last exchange to `arg`, or to the chat's document, if the
+    /// document's guards let the reply through or the user overrides them
+    async fn save_to(&self, arg: &str, lines: &mut Lines<BufReader<Stdin>>) -> Result<PathBuf> {
+        let (question, reply, _) = self
+            .last
+            .as_ref()
+            .ok_or_else(|| anyhow::anyhow!("Nothing to save yet; ask something first"))?;
+        let path = match (arg, &self.file) {
+            ("", Some((path, _))) => path.clone(),
+            ("", None) => return Err(anyhow::anyhow!("Name a file: /save-to <file>")),
+            (arg, _) => PathBuf::from(arg),
+        };
+        // Never write secrets into a file
+        let reply = self.redactor.redact(reply);
+        let guard = load_guard(self.config, &FileTarget::from(path.clone()))?;
+        let overrides = async {
+            ui_prompt!("  {} ", GUARD_OVERRIDE_QUESTION.yellow());
+            let _ = io::stdout().flush();
+            let answer = lines.next_line().await.ok().flatten().unwrap_or_default();
+            matches!(answer.trim().to_lowercase().as_str(), "y" | "yes")
+        };
+        enforce_guards_asking(&guard.check(&reply), overrides).await?;
+        append_exchange(&path, &self.redactor.redact(question), &reply)?;
+        Ok(path)
+    }
+
+    /// Rate the last reply
+    fn rate(&self, vote: Vote, note: &str) -> Result<()> {
+        let (_, _, id) = self
+            .last
+            .as_ref()
+            .ok_or_else(|| anyhow::anyhow!("Nothing to rate yet; ask something first"))?;
+        let feedback = Feedback {
+            vote,
+            note: (!note.is_empty()).then(|| self.redactor.redact(note)),
+            timestamp: Utc::now(),
+        };
+        history::rate(id, feedback)
+    }
+
+    /// Append the exchange to the history log and return its id
+    fn record(&self, question: &str, result: &Result<String>, start: Instant) -> String {
+        let response = result.as_ref().ok().map(|r| self.redactor.redact(r));
+        let id = history::new_id();
+        let entry = HistoryEntry {
+            id: Some(id.clone()),
+            timestamp: Utc::now(),
+            file: self.file.as_ref().map_or(NO_FILE.to_string(), |(_, name)| name.clone()),
+            agent: self.claude.agent_name().to_string(),
+            task: self.claude.task_name().map(str::to_string),
+            profile: None,
+            prompt_tokens: Some(history::estimate_tokens(question)),
+            response_tokens: response.as_deref().map(history::estimate_tokens),
+            prompt: self.redactor.redact(question),
+            response,
+            error: result.as_ref().err().map(|e| self.redactor.redact(&e.to_string())),
+            duration_ms: start.elapsed().as_millis() as u64,
+            context: self.claude.last_context().cloned(),
+            feedback: None,
+        };
+        if let Err(e) = history::record(&entry) {
+            ui_eprintln!("Warning: Failed to record history: {}", e);
+        }
+        id
+    }
+
+    /// Write the conversation into the document's session, so `ecce homo`
+    /// (through `ecce session save` and `restore`) and later chats pick it up
+    fn autosave(&mut self) {
+        let (Some((_, file)), true) = (&self.file, self.shared) else {
+            return;
+        };
+        let saved = self.saved.get_or_insert_with(|| SavedSession {
+            file: file.clone(),
+            agents: Vec::new(),
+            task: self.claude.task_name().map(str::to_string),
+            preset: None,
+            settings: SessionSettings {
+                watch_interval: DEFAULT_WATCH_INTERVAL_MS,
+                ..Default::default()
+            },
+            processed: Vec::new(),
+            saved_at: Utc::now(),
+        });
+        let agent = SavedAgent {
+            name: self.claude.agent_name().to_string(),
+            history: self.claude.conversation().to_vec(),
+            session_id: self.claude.session_id().map(str::to_string),
+        };
+        match saved.agents.iter_mut().find(|a| a.name == agent.name) {
+            Some(existing) => *existing = agent,
+            None => saved.agents.push(agent),
+        }
+        saved.saved_at = Utc::now();
+        if let Err(e) = session::autosave(saved) {
+            ui_eprintln!("Warning: Failed to save the conversation: {}", e);
+        }
+    }
+}
+
+/// How sessions name a local file: its absolute path
+fn session_file(path: &Path) -> Result<String> {
+    let path = path
+        .canonicalize()
+        .with_context(|| format!("File not found: {}", path.display()))?;
+    Ok(path.display().to_string())
+}
+
+/// Append a question and its reply to a document, as a new slide in a Slidev deck
+fn append_exchange(path: &Path, question: &str, reply: &str) -> Result<()> {
+    let content = match path.exists() {
+        true => std::fs::read_to_string(path).with_context(|| format!("Failed to read {}", path.display()))?,
+        false => String::new(),
+    };
+    let format = document::handler_for(path, &content);
+    let heading = match format.name() {
+        "notebook" => return Err(anyhow::anyhow!("Can't append to a notebook; save to a Markdown file instead")),
+        "org" => format!("* {}", question),
+        _ => format!("### {}", question),
+    };
+    let exchange = format.lay_out(&[format!("{}\n\n{}", heading, reply.trim())]);
+    let separator = match (content.trim().is_empty(), format.name()) {
+        (true, _) => "",
+        (false, "slidev") => "\n\n---\n\n",
+        (false, _) => "\n\n",
+    };
+    std::fs::write(path, format!("{}{}{}\n", content.trim_end(), separator, exchange))
+        .with_context(|| format!("Failed to write {}", path.display()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_append_exchange() {
+        let dir = tempfile::tempdir().unwrap();
+        let notes = dir.path().join("notes.md");
+        append_exchange(&notes, "What is a trait?", "A set of methods.\n").unwrap();
+        append_exchange(&notes, "Why borrow?", "To lend.").unwrap();
+        assert_eq!(
+            std::fs::read_to_string(&notes).unwrap(),
+            "### What is a trait?\n\nA set of methods.\n\n### Why borrow?\n\nTo lend.\n"
+        );
+
+        // In a deck, the exchange is a slide of its own
+        let slides = dir.path().join("slides.md");
+        std::fs::write(&slides, "# Intro\n").unwrap();
+        append_exchange(&slides, "Why?", "Speed.\n---\nSafety.").unwrap();
+        assert_eq!(
+            std::fs::read_to_string(&slides).unwrap(),
+            "# Intro\n\n---\n\n### Why?\n\nSpeed.\n***\nSafety.\n"
+        );
+
+        assert!(append_exchange(&dir.path().join("a.ipynb"), "Why?", "Because.").is_err());
+    }
+}
@@ -0,0 +1,42 @@
+use anyhow::Result;
+use clap::Args;
+use colored::*;
+use std::path::PathBuf;
+use ecce::ui_println;
+
+use crate::commands::homo;
+use crate::config::Config;
+use crate::exit::Failure;
+
+#[derive(Args)]
+pub struct CheckArgs {
+    /// Files or folders (folders use their slides.md) to check for unanswered patterns
+    #[arg(required = true)]
+    pub files: Vec<PathBuf>,
+}
+
+/// List unanswered patterns as `file:line: question`; fails with the
+/// patterns-remaining exit code if there are any
+pub fn handle_check_command(args: CheckArgs, config: &Config) -> Result<()> {
+    let mut remaining = 0;
+
+    for path in &args.files {
+        let (target, patterns) = homo::unanswered_patterns(path, config)?;
+        for (line, pattern) in &patterns {
+            let question = pattern.question();
+            println!("{}:{}: {}", target, line, question.lines().next().unwrap_or(question));
+        }
+        remaining += patterns.len();
+    }
+
+    if remaining > 0 {
+        return Err(Failure::PatternsRemaining.error(format!(
+            "{} unanswered pattern{}",
+            remaining,
+            if remaining == 1 { "" } else { "s" }
+        )));
+    }
+
+    ui_println!("{}", "✓ No unanswered patterns".green());
+    Ok(())
+}
@@ -0,0 +1,656 @@
+use anyhow::{Context, Result};
+use chrono::Local;
+use clap::Subcommand;
+use colored::*;
+use serde_json::{json, Value};
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use ecce::{ui_println, ui_result};
+
+use crate::commands::hooks::{load_settings, settings_path};
+use crate::config::Config;
+use crate::environment::claude_project_root;
+use crate::history::line_diff;
+use crate::redact::Redactor;
+use crate::ui;
+
+/// Suffix of the copies kept next to a file before ecce changes it
+const BACKUP_SUFFIX: &str = ".ecce-backup-";
+
+/// Backups kept per file; older ones are deleted
+const MAX_BACKUPS: usize = 10;
+
+/// Changes spanning more lines than this are summarized by size; ~/.claude.json grows large
+const MAX_DIFF_LINES: usize = 2000;
+
+/// Unchanged lines shown around a change, so it's clear where it is
+const DIFF_CONTEXT: usize = 2;
+
+#[derive(Subcommand)]
+pub enum ClaudeCommand {
+    /// Show the model, env, permissions, hooks, and MCP servers Claude Code uses here
+    Show {
+        /// Read ~/.claude/settings.json instead of the project settings
+        #[arg(long)]
+        global: bool,
+    },
+    /// Show, set, or clear the default model
+    Model {
+        /// Model name or alias, e.g. sonnet or claude-opus-4-1
+        model: Option<String>,
+        /// Remove the model setting
+        #[arg(long, conflicts_with = "model")]
+        unset: bool,
+        /// Edit ~/.claude/settings.json instead of the project settings
+        #[arg(long)]
+        global: bool,
+        /// Show the change without writing it
+        #[arg(long)]
+        dry_run: bool,
+    },
+    /// Show, set, or remove environment variables Claude Code starts with
+    Env {
+        /// Variables to set, as KEY=VALUE
+        assignments: Vec<String>,
+        /// Variable to remove (repeatable)
+        #[arg(long, value_name = "KEY")]
+        unset: Vec<String>,
+        /// Edit ~/.claude/settings.json instead of the project settings
+        #[arg(long)]
+        global: bool,
+        /// Show the change without writing it
+        #[arg(long)]
+        dry_run: bool,
+    },
+    /// Allow tools without a permission prompt, e.g. "Bash(npm run test:*)" or WebFetch
+    Allow {
+        /// Permission rules to add
+        #[arg(required = true)]
+        rules: Vec<String>,
+        /// Remove the rules instead
+        #[arg(long)]
+        remove: bool,
+        /// Edit ~/.claude/settings.json instead of the project settings
+        #[arg(long)]
+        global: bool,
+        /// Show the change without writing it
+        #[arg(long)]
+        dry_run: bool,
+    },
+    /// Add or remove a command hook (for ecce's own hooks, see `ecce hooks`)
+    Hook {
+        /// Hook event, e.g. PreToolUse, PostToolUse, Stop, or UserPromptSubmit
+        event: String,
+        /// Shell command to run
+        command: String,
+        /// Tool name pattern the hook applies to, e.g. "Edit|Write"
+        #[arg(short, long)]
+        matcher: Option<String>,
+        /// Remove the hook instead
+        #[arg(long)]
+        remove: bool,
+        /// Edit ~/.claude/settings.json instead of the project settings
+        #[arg(long)]
+        global: bool,
+        /// Show the change without writing it
+        #[arg(long)]
+        dry_run: bool,
+    },
+    /// List the backups ecce made before changing a Claude Code file
+    Backups {
+        /// Backups of ~/.claude/settings.json instead of the project settings
+        #[arg(long)]
+        global: bool,
+        /// Backups of ~/.claude.json (written by `ecce mcp install`)
+        #[arg(long, conflicts_with = "global")]
+        claude_json: bool,
+    },
+    /// Restore the latest backup, or the one named
+    Restore {
+        /// Backup file name or timestamp, as listed by `ecce claude backups`
+        backup: Option<String>,
+        /// Restore ~/.claude/settings.json instead of the project settings
+        #[arg(long)]
+        global: bool,
+        /// Restore ~/.claude.json
+        #[arg(long, conflicts_with = "global")]
+        claude_json: bool,
+    },
+}
+
+pub fn handle_claude_command(command: ClaudeCommand, config: &Config) -> Result<()> {
+    // Diffs can show env values, so mask profile keys and anything that looks like a secret
+    let redactor = Redactor::from_config(config);
+    match command {
+        ClaudeCommand::Show { global } => show(&settings_path(global)?),
+        ClaudeCommand::Model { model: None, unset: false, global, .. } => {
+            let settings = load_settings(&settings_path(global)?)?;
+            match settings["model"].as_str() {
+                Some(model) => ui_result!("{}", model),
+                None => ui_result!("{}", "No model set; Claude Code uses its default".yellow()),
+            }
+            Ok(())
+        }
+        ClaudeCommand::Model { model, global, dry_run, .. } => edit(&settings_path(global)?, dry_run, &redactor, |settings| {
+            set_model(settings, model.as_deref());
+            Ok(())
+        }),
+        ClaudeCommand::Env { assignments, unset, global, .. } if assignments.is_empty() && unset.is_empty() => {
+            let settings = load_settings(&settings_path(global)?)?;
+            let env = env_lines(&settings);
+            if env.is_empty() {
+                ui_result!("{}", "No environment variables set".yellow());
+            }
+            for line in env {
+                ui_result!("{}", line);
+            }
+            Ok(())
+        }
+        ClaudeCommand::Env { assignments, unset, global, dry_run } => {
+            let assignments = assignments
+                .iter()
+                .map(|a| {
+                    a.split_once('=')
+                        .filter(|(key, _)| !key.is_empty())
+                        .ok_or_else(|| anyhow::anyhow!("Expected KEY=VALUE, got '{}'", a))
+                })
+                .collect::<Result<Vec<_>>>()?;
+            edit(&settings_path(global)?, dry_run, &redactor, |settings| {
+                set_env(settings, &assignments, &unset);
+                Ok(())
+            })
+        }
+        ClaudeCommand::Allow { rules, remove, global, dry_run } => edit(&settings_path(global)?, dry_run, &redactor, |settings| {
+            set_allowed(settings, &rules, remove);
+            Ok(())
+        }),
+        ClaudeCommand::Hook { event, command, matcher, remove, global, dry_run } => {
+            edit(&settings_path(global)?, dry_run, &redactor, |settings| {
+                if remove {
+                    if !remove_hook(settings, &event, &command, matcher.as_deref()) {
+                        return Err(anyhow::anyhow!("No {} hook runs '{}'", event, command));
+                    }
+                } else {
+                    add_hook(settings, &event, &command, matcher.as_deref());
+                }
+                Ok(())
+            })
+        }
+        ClaudeCommand::Backups { global, claude_json } => {
+            let path = target_path(global, claude_json)?;
+            let backups = backups(&path)?;
+            if backups.is_empty() {
+                ui_result!("{}", format!("No backups of {}", path.display()).yellow());
+            }
+            for backup in backups {
+                ui_result!("{}", backup.display());
+            }
+            Ok(())
+        }
+        ClaudeCommand::Restore { backup, global, claude_json } => {
+            restore(&target_path(global, claude_json)?, backup.as_deref(), &redactor)
+        }
+    }
+}
+
+fn target_path(global: bool, claude_json: bool) -> Result<PathBuf> {
+    if claude_json {
+        let home = dirs::home_dir().context("Could not find home directory")?;
+        Ok(home.join(".claude.json"))
+    } else {
+        settings_path(global)
+    }
+}
+
+/// Apply a change to a settings file, show it as a diff, and write it after
+/// backing up the previous version
+fn edit<F>(path: &Path, dry_run: bool, redactor: &Redactor, change: F) -> Result<()>
+where
+    F: FnOnce(&mut Value) -> Result<()>,
+{
+    let before = load_settings(path)?;
+    let mut after = before.clone();
+    change(&mut after)?;
+    write_changes(path, &before, &after, dry_run, redactor)?;
+    Ok(())
+}
+
+/// Show the diff from `before` to `after` and, unless it's a dry run, save
+/// `after` with a backup; returns whether the file was written
+pub fn write_changes(path: &Path, before: &Value, after: &Value, dry_run: bool, redactor: &Redactor) -> Result<bool> {
+    if before == after {
+        ui_println!("{}", format!("No changes to {}", path.display()).yellow());
+        return Ok(false);
+    }
+
+    ui_println!("{}", path.display().to_string().bold());
+    show_diff(before, after, redactor);
+    if dry_run {
+        ui_println!("{}", "Dry run: nothing written".yellow());
+        return Ok(false);
+    }
+    let backup = save_with_backup(path, after)?;
+    ui_println!("{}", format!("✓ Updated {}", path.display()).green());
+    if let Some(backup) = backup {
+        ui_println!("  Backup: {}", backup.display().to_string().dimmed());
+    }
+    Ok(true)
+}
+
+fn show_diff(before: &Value, after: &Value, redactor: &Redactor) {
+    let Some(lines) = json_diff(before, after) else {
+        ui_println!("  {}", "(change too large to diff)".dimmed());
+        return;
+    };
+    for line in lines {
+        let line = redactor.redact(&line);
+        if line.starts_with('-') {
+            ui_println!("{}", line.red());
+        } else if line.starts_with('+') {
+            ui_println!("{}", line.green());
+        } else {
+            ui_println!("{}", line.dimmed());
+        }
+    }
+}
+
+/// Changed lines of the pretty-printed JSON (`- `/`+ `) with a little unchanged
+/// context (`  `); unchanged leading and trailing lines are skipped first, so
+/// a small edit to a large file still diffs. None if the change is too large.
+fn json_diff(before: &Value, after: &Value) -> Option<Vec<String>> {
+    let before = serde_json::to_string_pretty(before).unwrap_or_default();
+    let after = serde_json::to_string_pretty(after).unwrap_or_default();
+    let a: Vec<&str> = before.lines().collect();
+    let b: Vec<&str> = after.lines().collect();
+    let prefix = a.iter().zip(&b).take_while(|(x, y)| x == y).count();
+    let suffix = a[prefix..]
+        .iter()
+        .rev()
+        .zip(b[prefix..].iter().rev())
+        .take_while(|(x, y)| x == y)
+        .count();
+    let (old, new) = (&a[prefix..a.len() - suffix], &b[prefix..b.len() - suffix]);
+    if old.len().max(new.len()) > MAX_DIFF_LINES {
+        return None;
+    }
+
+    let context = |lines: &[&str]| lines.iter().map(|line| format!("  {}", line)).collect::<Vec<_>>();
+    let mut out = context(&a[prefix.saturating_sub(DIFF_CONTEXT)..prefix]);
+    out.extend(line_diff(&old.join("\n"), &new.join("\n")));
+    out.extend(context(&a[a.len() - suffix..][..suffix.min(DIFF_CONTEXT)]));
+    Some(out)
+}
+
+/// Write JSON to `path`, first copying any existing file to a timestamped
+/// backup next to it; returns the backup
+pub fn save_with_backup(path: &Path, value: &Value) -> Result<Option<PathBuf>> {
+    let backup = if path.exists() {
+        let name = path.file_name().and_then(|n| n.to_str()).unwrap_or("settings");
+        let stamp = Local::now().format("%Y%m%d-%H%M%S%.3f").to_string();
+        let mut backup = path.with_file_name(format!("{}{}{}", name, BACKUP_SUFFIX, stamp));
+        for n in 1.. {
+            if !backup.exists() {
+                break;
+            }
+            backup = path.with_file_name(format!("{}{}{}-{}", name, BACKUP_SUFFIX, stamp, n));
+        }
+        fs::copy(path, &backup).with_context(|| format!("Failed to back up {}", path.display()))?;
+        for old in backups(path)?.iter().rev().skip(MAX_BACKUPS) {
+            let _ = fs::remove_file(old);
+        }
+        Some(backup)
+    } else {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        None
+    };
+
+    // Written to a temporary file first so Claude Code never reads a torn file.
+    // It takes the original's permissions before any content, since
+    // ~/.claude.json holds credentials and is usually private.
+    let tmp = path.with_extension("json.ecce-tmp");
+    let mut file = fs::File::create(&tmp).with_context(|| format!("Failed to write {}", tmp.display()))?;
+    if let Ok(metadata) = fs::metadata(path) {
+        fs::set_permissions(&tmp, metadata.permissions())?;
+    }
+    file.write_all(serde_json::to_string_pretty(value)?.as_bytes())
+        .with_context(|| format!("Failed to write {}", tmp.display()))?;
+    drop(file);
+    fs::rename(&tmp, path).with_context(|| format!("Failed to write {}", path.display()))?;
+    Ok(backup)
+}
+
+/// Backups of `path`, oldest first
+pub fn backups(path: &Path) -> Result<Vec<PathBuf>> {
+    let (Some(dir), Some(name)) = (path.parent(), path.file_name().and_then(|n| n.to_str())) else {
+        return Ok(Vec::new());
+    };
+    if !dir.exists() {
+        return Ok(Vec::new());
+    }
+    let prefix = format!("{}{}", name, BACKUP_SUFFIX);
+    let mut found: Vec<PathBuf> = fs::read_dir(dir)?
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .filter(|p| p.file_name().and_then(|n| n.to_str()).is_some_and(|n| n.starts_with(&prefix)))
+        .collect();
+    // Timestamps sort chronologically
+    found.sort();
+    Ok(found)
+}
+
+/// Put a backup back in place, backing up the current file first so the restore can be undone
+fn restore(path: &Path, name: Option<&str>, redactor: &Redactor) -> Result<()> {
+    let backups = backups(path)?;
+    let backup = match name {
+        Some(name) => backups
+            .iter()
+            .rev()
+            .find(|b| b.file_name().and_then(|n| n.to_str()).is_some_and(|n| n.ends_with(name)))
+            .ok_or_else(|| anyhow::anyhow!("No backup of {} matches '{}'", path.display(), name))?,
+        None => backups
+            .last()
+            .ok_or_else(|| anyhow::anyhow!("No backups of {}", path.display()))?,
+    };
+
+    let restored = load_settings(backup)?;
+    let current = load_settings(path)?;
+    ui_println!("{}", path.display().to_string().bold());
+    show_diff(&current, &restored, redactor);
+    save_with_backup(path, &restored)?;
+    ui_println!("{}", format!("✓ Restored {}", backup.display()).green());
+    Ok(())
+}
+
+fn set_model(settings: &mut Value, model: Option<&str>) {
+    let Some(object) = settings.as_object_mut() else {
+        return;
+    };
+    match model {
+        Some(model) => {
+            object.insert("model".to_string(), json!(model));
+        }
+        None => {
+            object.remove("model");
+        }
+    }
+}
+
+fn set_env(settings: &mut Value, assignments: &[(&str, &str)], unset: &[String]) {
+    if settings.get("env").is_none_or(|env| !env.is_object()) {
+        settings["env"] = json!({});
+    }
+    let env = settings["env"].as_object_mut().expect("env is an object");
+    for (key, value) in assignments {
+        env.insert(key.to_string(), json!(value));
+    }
+    for key in unset {
+        env.remove(key);
+    }
+    if env.is_empty() {
+        settings.as_object_mut().map(|s| s.remove("env"));
+    }
+}
+
+fn set_allowed(settings: &mut Value, rules: &[String], remove: bool) {
+    if settings.get("permissions").is_none_or(|p| !p.is_object()) {
+        settings["permissions"] = json!({});
+    }
+    let permissions = settings["permissions"].as_object_mut().expect("permissions is an object");
+    let allow = permissions.entry("allow").or_insert_with(|| json!([]));
+    if !allow.is_array() {
+        *allow = json!([]);
+    }
+    let list = allow.as_array_mut().expect("allow is an array");
+    for rule in rules {
+        let present = list.iter().any(|r| r == rule);
+        if remove {
+            list.retain(|r| r != rule);
+        } else if !present {
+            list.push(json!(rule));
+        }
+    }
+    if list.is_empty() {
+        permissions.remove("allow");
+    }
+    if permissions.is_empty() {
+        settings.as_object_mut().map(|s| s.remove("permissions"));
+    }
+}
+
+/// Add a command hook to the group for `matcher`, creating the group if needed
+fn add_hook(settings: &mut Value, event: &str, command: &str, matcher: Option<&str>) {
+    if settings.get("hooks").is_none_or(|h| !h.is_object()) {
+        settings["hooks"] = json!({});
+    }
+    let groups = settings["hooks"]
+        .as_object_mut()
+        .expect("hooks is an object")
+        .entry(event)
+        .or_insert_with(|| json!([]));
+    if !groups.is_array() {
+        *groups = json!([]);
+    }
+    let groups = groups.as_array_mut().expect("hook groups are an array");
+    let entry = json!({ "type": "command", "command": command });
+    match groups.iter_mut().find(|g| g["matcher"].as_str() == matcher) {
+        Some(group) => {
+            if group["hooks"].as_array().is_none_or(|hooks| !hooks.iter().any(|h| h["command"] == command)) {
+                match group["hooks"].as_array_mut() {
+                    Some(hooks) => hooks.push(entry),
+                    None => group["hooks"] = json!([entry]),
+                }
+            }
+        }
+        None => {
+            let mut group = json!({ "hooks": [entry] });
+            if let Some(matcher) = matcher {
+                group["matcher"] = json!(matcher);
+            }
+            groups.push(group);
+        }
+    }
+}
+
+/// Remove a command hook (only from `matcher`'s group, if given), dropping
+/// groups and events left empty; returns whether anything was removed
+fn remove_hook(settings: &mut Value, event: &str, command: &str, matcher: Option<&str>) -> bool {
+    let Some(hooks) = settings.get_mut("hooks").and_then(Value::as_object_mut) else {
+        return false;
+    };
+    let Some(groups) = hooks.get_mut(event).and_then(Value::as_array_mut) else {
+        return false;
+    };
+    let mut removed = false;
+    for group in groups.iter_mut() {
+        if matcher.is_some() && group["matcher"].as_str() != matcher {
+            continue;
+        }
+        if let Some(entries) = group["hooks"].as_array_mut() {
+            let before = entries.len();
+            entries.retain(|h| h["command"] != command);
+            removed |= entries.len() != before;
+        }
+    }
+    groups.retain(|g| g["hooks"].as_array().is_some_and(|h| !h.is_empty()));
+    if groups.is_empty() {
+        hooks.remove(event);
+    }
+    if hooks.is_empty() {
+        settings.as_object_mut().map(|s| s.remove("hooks"));
+    }
+    removed
+}
+
+/// `KEY=value` per env entry, with values of secret-looking names shortened
+fn env_lines(settings: &Value) -> Vec<String> {
+    let Some(env) = settings["env"].as_object() else {
+        return Vec::new();
+    };
+    env.iter()
+        .map(|(key, value)| {
+            let value = value.as_str().map(str::to_string).unwrap_or_else(|| value.to_string());
+            let secret = ["KEY", "TOKEN", "SECRET", "PASSWORD"]
+                .iter()
+                .any(|s| key.to_uppercase().contains(s));
+            if secret {
+                format!("{}={}***", key, ui::prefix(&value, 8))
+            } else {
+                format!("{}={}", key, value)
+            }
+        })
+        .collect()
+}
+
+fn show(path: &Path) -> Result<()> {
+    let settings = load_settings(path)?;
+    ui_result!("{} {}", "Claude Code settings:".bold(), path.display());
+    ui_result!("  {:<10}{}", "Model:", settings["model"].as_str().unwrap_or("(default)").cyan());
+
+    let env = env_lines(&settings);
+    ui_result!("  {:<10}{}", "Env:", if env.is_empty() { "(none)".dimmed() } else { "".normal() });
+    for line in env {
+        ui_result!("    {}", line);
+    }
+
+    for (label, key) in [("Allowed:", "allow"), ("Denied:", "deny")] {
+        let rules: Vec<&str> = settings["permissions"][key]
+            .as_array()
+            .map(|rules| rules.iter().filter_map(Value::as_str).collect())
+            .unwrap_or_default();
+        if !rules.is_empty() || key == "allow" {
+            let shown = if rules.is_empty() { "(none)".to_string() } else { rules.join(", ") };
+            ui_result!("  {:<10}{}", label, shown.cyan());
+        }
+    }
+
+    let hooks = settings["hooks"].as_object();
+    ui_result!("  {:<10}{}", "Hooks:", if hooks.is_none() { "(none)".dimmed() } else { "".normal() });
+    for (event, groups) in hooks.into_iter().flatten() {
+        for group in groups.as_array().into_iter().flatten() {
+            let matcher = group["matcher"].as_str().map(|m| format!(" [{}]", m)).unwrap_or_default();
+            for hook in group["hooks"].as_array().into_iter().flatten() {
+                let command = hook["command"].as_str().unwrap_or("?");
+                ui_result!("    {}{}: {}", event.cyan(), matcher.dimmed(), command);
+            }
+        }
+    }
+
+    // MCP servers live in ~/.claude.json, globally and per project
+    let claude_json = load_settings(&target_path(false, true)?)?;
+    let project = claude_project_root(&std::env::current_dir()?, dirs::home_dir().as_deref())
+        .to_string_lossy()
+        .to_string();
+    let names = |servers: &Value| -> String {
+        let names: Vec<&str> = servers.as_object().map(|s| s.keys().map(String::as_str).collect()).unwrap_or_default();
+        if names.is_empty() { "(none)".to_string() } else { names.join(", ") }
+    };
+    ui_result!("  {:<10}{}", "MCP:", format!("{} (global)", names(&claude_json["mcpServers"])).cyan());
+    ui_result!(
+        "  {:<10}{}",
+        "",
+        format!("{} (this project)", names(&claude_json["projects"][&project]["mcpServers"])).cyan()
+    );
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_model_env_and_allow() {
+        let mut settings = json!({ "includeCoAuthoredBy": false });
+        set_model(&mut settings, Some("sonnet"));
+        set_env(&mut settings, &[("DISABLE_TELEMETRY", "1"), ("ANTHROPIC_API_KEY", "sk-ant-secret-value")], &[]);
+        set_allowed(&mut settings, &["WebFetch".to_string(), "WebFetch".to_string()], false);
+        assert_eq!(settings["model"], "sonnet");
+        assert_eq!(settings["permissions"]["allow"], json!(["WebFetch"]));
+        assert_eq!(
+            env_lines(&settings),
+            vec!["ANTHROPIC_API_KEY=sk-ant-s***".to_string(), "DISABLE_TELEMETRY=1".to_string()]
+        );
+
+        set_model(&mut settings, None);
+        set_env(&mut settings, &[], &["DISABLE_TELEMETRY".to_string(), "ANTHROPIC_API_KEY".to_string()]);
+        set_allowed(&mut settings, &["WebFetch".to_string()], true);
+        // Emptied sections are dropped; unrelated settings are untouched
+        assert_eq!(settings, json!({ "includeCoAuthoredBy": false }));
+    }
+
+    #[test]
+    fn test_json_diff() {
+        let mut before = json!({ "a": 1, "m": { "x": "old" }, "z": 3 });
+        for n in 0..3000 {
+            before[format!("k{:04}", n)] = json!(n);
+        }
+        let mut after = before.clone();
+        after["m"]["x"] = json!("new");
+
+        // Only the change and its context, however large the file
+        let lines = json_diff(&before, &after).unwrap();
+        assert_eq!(
+            lines,
+            vec!["    \"k2999\": 2999,", "    \"m\": {", "-     \"x\": \"old\"", "+     \"x\": \"new\"", "    },", "    \"z\": 3"]
+        );
+
+        for n in 0..3000 {
+            after[format!("k{:04}", n)] = json!(-n);
+        }
+        assert!(json_diff(&before, &after).is_none());
+    }
+
+    #[test]
+    fn test_add_and_remove_hook() {
+        let mut settings = json!({});
+        add_hook(&mut settings, "PostToolUse", "cargo fmt", Some("Edit|Write"));
+        add_hook(&mut settings, "PostToolUse", "cargo fmt", Some("Edit|Write"));
+        add_hook(&mut settings, "PostToolUse", "ecce hooks run", Some("Edit|Write"));
+        add_hook(&mut settings, "Stop", "say done", None);
+        assert_eq!(settings["hooks"]["PostToolUse"][0]["hooks"].as_array().unwrap().len(), 2);
+        assert!(settings["hooks"]["Stop"][0].get("matcher").is_none());
+
+        assert!(remove_hook(&mut settings, "PostToolUse", "cargo fmt", None));
+        assert!(!remove_hook(&mut settings, "PostToolUse", "cargo fmt", None));
+        assert!(remove_hook(&mut settings, "Stop", "say done", None));
+        assert_eq!(
+            settings,
+            json!({ "hooks": { "PostToolUse": [
+                { "matcher": "Edit|Write", "hooks": [{ "type": "command", "command": "ecce hooks run" }] }
+            ] } })
+        );
+    }
+
+    #[test]
+    fn test_backups_and_restore() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("settings.json");
+
+        assert!(save_with_backup(&path, &json!({ "model": "opus" })).unwrap().is_none());
+        let backup = save_with_backup(&path, &json!({ "model": "sonnet" })).unwrap().unwrap();
+        assert_eq!(load_settings(&backup).unwrap(), json!({ "model": "opus" }));
+        assert_eq!(backups(&path).unwrap(), vec![backup]);
+
+        restore(&path, None, &Redactor::default()).unwrap();
+        assert_eq!(load_settings(&path).unwrap(), json!({ "model": "opus" }));
+        // The restore itself was backed up
+        assert_eq!(backups(&path).unwrap().len(), 2);
+        assert!(restore(&path, Some("no-such-backup"), &Redactor::default()).is_err());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_save_keeps_permissions() {
+        use std::os::unix::fs::PermissionsExt;
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join(".claude.json");
+        fs::write(&path, "{}").unwrap();
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o600)).unwrap();
+
+        let backup = save_with_backup(&path, &json!({ "model": "opus" })).unwrap().unwrap();
+        let mode = |path: &Path| fs::metadata(path).unwrap().permissions().mode() & 0o777;
+        assert_eq!(mode(&path), 0o600);
+        assert_eq!(mode(&backup), 0o600);
+    }
+}
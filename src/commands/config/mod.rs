@@ -0,0 +1,70 @@
+use anyhow::{Context, Result};
+use clap::Subcommand;
+use colored::*;
+use ecce::{ui_println, ui_result};
+
+use crate::config::Config;
+use crate::redact::Redactor;
+
+#[derive(Subcommand)]
+pub enum ConfigCommand {
+    /// Print a setting by dot path, e.g. `claude_executable` or `agents.writer.model`
+    Get {
+        /// Setting key
+        key: String,
+        /// Print profile keys and other secrets instead of masking them
+        #[arg(long)]
+        show_secrets: bool,
+    },
+    /// Change a setting; the value is read as JSON when it parses, else as a string
+    Set {
+        /// Setting key
+        key: String,
+        /// New value, e.g. `/opt/claude`, `8000`, `true`, or `["ecce","ask"]`
+        value: String,
+    },
+    /// Remove a setting so its default applies
+    Unset {
+        /// Setting key
+        key: String,
+    },
+    /// Print the path of the config file
+    Path,
+}
+
+pub fn handle_config_command(command: ConfigCommand, config: &mut Config) -> Result<()> {
+    match command {
+        ConfigCommand::Get { key, show_secrets } => {
+            let value = config
+                .get_setting(&key)?
+                .with_context(|| format!("'{}' is not set", key))?;
+            let text = match value {
+                serde_json::Value::String(s) => s,
+                other => serde_json::to_string_pretty(&other)?,
+            };
+            if show_secrets {
+                ui_result!("{}", text);
+            } else {
+                ui_result!("{}", Redactor::from_config(config).redact(&text));
+            }
+        }
+        ConfigCommand::Set { key, value } => {
+            config.set_setting(&key, &value)?;
+            config.save()?;
+            let value = config.get_setting(&key)?.unwrap_or(serde_json::Value::Null);
+            ui_println!(
+                "{}",
+                format!("✓ Set {} = {}", key, Redactor::from_config(config).redact(&value.to_string())).green()
+            );
+        }
+        ConfigCommand::Unset { key } => {
+            config.unset_setting(&key)?;
+            config.save()?;
+            ui_println!("{}", format!("✓ Unset {}", key).green());
+        }
+        ConfigCommand::Path => {
+            ui_result!("{}", Config::config_path()?.display());
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,96 @@
+use anyhow::{Context, Result};
+use clap::Args;
+use std::process::Command;
+
+use crate::config::{Config, Profile, CLAUDE_CODE_SERVICE};
+use crate::exit::{Failure, GENERIC_FAILURE};
+
+#[derive(Args)]
+pub struct ExecArgs {
+    /// Profile whose endpoint and key to inject (default: the project's pinned profile, else the active claude-code one)
+    #[arg(short, long)]
+    pub profile: Option<String>,
+
+    /// Command to run, after `--`
+    #[arg(required = true, trailing_var_arg = true, allow_hyphen_values = true)]
+    pub command: Vec<String>,
+}
+
+/// The named profile, else the claude-code profile pinned to the current project, else the active one
+pub fn select_profile(name: Option<&str>, config: &Config) -> Result<Profile> {
+    match name {
+        Some(name) => {
+            if !config.profiles.iter().any(|p| p.name == name) {
+                return Err(Failure::ProfileNotFound.error(format!("Profile '{}' not found", name)));
+            }
+            config.resolve_profile(name).context(Failure::Config)
+        }
+        None => {
+            let pinned = config
+                .pinned_profile(&std::env::current_dir()?, CLAUDE_CODE_SERVICE)
+                .context(Failure::Config)?;
+            pinned
+                .or_else(|| config.get_active_profile(CLAUDE_CODE_SERVICE))
+                .ok_or_else(|| {
+                    Failure::ProfileNotFound.error("No --profile given and no active claude-code profile")
+                })
+        }
+    }
+}
+
+/// The command with the profile's variables set on it alone
+pub fn command(profile: &Profile, argv: &[String]) -> Result<Command> {
+    let (program, args) = argv.split_first().context("No command given")?;
+    let vars = profile.env_vars().ok_or_else(|| {
+        Failure::Config.error(format!(
+            "Profile '{}' has unknown service '{}'",
+            profile.name, profile.service
+        ))
+    })?;
+
+    let mut command = Command::new(program);
+    command.args(args).envs(vars);
+    Ok(command)
+}
+
+/// Run a command with a profile applied, returning its exit code
+pub fn handle_exec_command(args: ExecArgs, config: &Config) -> Result<i32> {
+    let profile = select_profile(args.profile.as_deref(), config)?;
+
+    let status = command(&profile, &args.command)?
+        .status()
+        .with_context(|| format!("Failed to run '{}'", args.command[0]))?;
+    Ok(status.code().unwrap_or(GENERIC_FAILURE))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_command_injects_profile_env() {
+        let profile = Profile {
+            name: "ci".to_string(),
+            url: "https://relay.example".to_string(),
+            key: "sk-ci".to_string(),
+            service: "codex".to_string(),
+            ..Default::default()
+        };
+        let argv = vec!["codex".to_string(), "--version".to_string()];
+        let command = super::command(&profile, &argv).unwrap();
+
+        assert_eq!(command.get_program(), "codex");
+        let envs: Vec<_> = command
+            .get_envs()
+            .map(|(k, v)| (k.to_str().unwrap(), v.unwrap().to_str().unwrap()))
+            .collect();
+        assert!(envs.contains(&("OPENAI_BASE_URL", "https://relay.example")));
+        assert!(envs.contains(&("OPENAI_API_KEY", "sk-ci")));
+
+        let unknown = Profile {
+            service: "gemini".to_string(),
+            ..profile
+        };
+        assert!(super::command(&unknown, &argv).is_err());
+    }
+}
@@ -0,0 +1,289 @@
+use anyhow::{Context, Result};
+use clap::Args;
+use colored::*;
+use serde::Serialize;
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use ecce::ui_println;
+
+use crate::commands::{batch, homo};
+use crate::config::Config;
+use crate::history::{self, HistoryEntry};
+use crate::provenance;
+use crate::remote::FileTarget;
+use crate::ui;
+
+#[derive(Args)]
+pub struct ExtractArgs {
+    /// Glob patterns of files to scan (e.g. 'lectures/**/*.md'); folders use their slides.md
+    #[arg(required = true)]
+    pub patterns: Vec<String>,
+
+    /// Output format: 'csv' or 'json'
+    #[arg(short, long, default_value = "csv")]
+    pub format: String,
+
+    /// Write to a file instead of stdout
+    #[arg(short, long)]
+    pub output: Option<PathBuf>,
+}
+
+/// Whether a question has been answered, as far as the file shows
+#[derive(Debug, Serialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum QuestionStatus {
+    /// Still an `ecce ... ecce` pattern
+    Unanswered,
+    /// Answered, and the answer is unchanged
+    Answered,
+    /// Answered with provenance markers, then edited by hand
+    Edited,
+}
+
+impl QuestionStatus {
+    fn as_str(self) -> &'static str {
+        match self {
+            QuestionStatus::Unanswered => "unanswered",
+            QuestionStatus::Answered => "answered",
+            QuestionStatus::Edited => "edited",
+        }
+    }
+}
+
+/// One question a file asks, with where it is
+#[derive(Debug, Serialize, Clone, PartialEq)]
+pub struct Question {
+    pub file: String,
+    pub line: usize,
+    pub status: QuestionStatus,
+    pub question: String,
+    /// The agent that answered, if known
+    pub agent: Option<String>,
+}
+
+/// Every question in a file: unanswered patterns, answers with provenance
+/// markers, and answers from the generation history still present verbatim
+fn questions_in(path: &Path, config: &Config, history: &[HistoryEntry]) -> Result<Vec<Question>> {
+    let (target, patterns) = homo::unanswered_patterns(path, config)?;
+    let content = target.read_to_string()?;
+    let file = target.to_string();
+
+    let mut questions: Vec<Question> = patterns
+        .into_iter()
+        .map(|(line, pattern)| Question {
+            file: file.clone(),
+            line,
+            status: QuestionStatus::Unanswered,
+            question: pattern.question().to_string(),
+            agent: None,
+        })
+        .collect();
+
+    let regions = provenance::find_regions(&content);
+    for region in &regions {
+        questions.push(Question {
+            file: file.clone(),
+            line: homo::line_number(&content, region.body.start),
+            status: if region.is_edited(&content) {
+                QuestionStatus::Edited
+            } else {
+                QuestionStatus::Answered
+            },
+            question: region.provenance.prompt.clone(),
+            agent: Some(region.provenance.agent.clone()),
+        });
+    }
+
+    // Answers written without markers can only be found by their text; each
+    // occurrence counts once, so identical answers map to distinct places
+    let mut claimed = Vec::new();
+    for entry in history.iter().filter(|e| is_same_file(&e.file, &target)) {
+        let Some(response) = entry.response.as_deref().filter(|r| !r.trim().is_empty()) else {
+            continue;
+        };
+        let offset = content.match_indices(response).map(|(offset, _)| offset).find(|offset| {
+            !claimed.contains(offset) && !regions.iter().any(|r| r.range.contains(offset))
+        });
+        let Some(offset) = offset else {
+            continue;
+        };
+        claimed.push(offset);
+        let line = homo::line_number(&content, offset);
+        if questions.iter().any(|q| q.line == line && q.question == entry.prompt) {
+            continue;
+        }
+        questions.push(Question {
+            file: file.clone(),
+            line,
+            status: QuestionStatus::Answered,
+            question: entry.prompt.clone(),
+            agent: Some(entry.agent.clone()),
+        });
+    }
+
+    questions.sort_by_key(|q| q.line);
+    Ok(questions)
+}
+
+/// Whether a file recorded in the history is `target`
+fn is_same_file(recorded: &str, target: &FileTarget) -> bool {
+    if recorded == target.to_string() {
+        return true;
+    }
+    match target {
+        FileTarget::Local(path) => match (Path::new(recorded).canonicalize(), path.canonicalize()) {
+            (Ok(a), Ok(b)) => a == b,
+            _ => false,
+        },
+        FileTarget::Remote(_) => false,
+    }
+}
+
+/// Quote a CSV field if it needs it
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+fn to_csv(questions: &[Question]) -> String {
+    let mut out = String::from("file,line,status,agent,question\n");
+    for q in questions {
+        out.push_str(&format!(
+            "{},{},{},{},{}\n",
+            csv_field(&q.file),
+            q.line,
+            q.status.as_str(),
+            csv_field(q.agent.as_deref().unwrap_or("")),
+            csv_field(&q.question)
+        ));
+    }
+    out
+}
+
+/// Collect every question asked across files into a question bank
+pub fn handle_extract_command(args: ExtractArgs, config: &Config) -> Result<()> {
+    if !matches!(args.format.as_str(), "csv" | "json") {
+        return Err(anyhow::anyhow!(
+            "Invalid format '{}'. Use 'csv' or 'json'",
+            args.format
+        ));
+    }
+
+    // Keep stdout for the question bank
+    if args.output.is_none() {
+        ui::status_to_stderr();
+    }
+
+    let files = batch::expand_globs(&args.patterns)?;
+    if files.is_empty() {
+        return Err(anyhow::anyhow!("No files to scan"));
+    }
+
+    let history = history::load()?;
+    let mut questions = Vec::new();
+    let mut seen = HashSet::new();
+    for file in &files {
+        let found = questions_in(file, config, &history)?;
+        // A folder and a glob can name the same slides.md
+        if found.first().is_some_and(|q| !seen.insert(q.file.clone())) {
+            continue;
+        }
+        questions.extend(found);
+    }
+
+    let rendered = match args.format.as_str() {
+        "json" => format!("{}\n", serde_json::to_string_pretty(&questions)?),
+        _ => to_csv(&questions),
+    };
+
+    match args.output {
+        Some(path) => {
+            std::fs::write(&path, rendered)
+                .with_context(|| format!("Failed to write question bank: {}", path.display()))?;
+            ui_println!(
+                "{}",
+                format!(
+                    "✓ Extracted {} question(s) from {} file(s) to {}",
+                    questions.len(),
+                    files.len(),
+                    path.display()
+                )
+                .green()
+            );
+        }
+        None => print!("{}", rendered),
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::provenance::Provenance;
+    use chrono::Utc;
+    use std::fs;
+
+    #[test]
+    fn test_questions_in_file() {
+        let temp = tempfile::TempDir::new().unwrap();
+        let path = temp.path().join("slides.md");
+        let marked = Provenance::new(
+            "a1".to_string(),
+            "writer".to_string(),
+            None,
+            "What is Rust?".to_string(),
+            "A language",
+        );
+        fs::write(
+            &path,
+            format!(
+                "# Deck\n{}\n\nPlain answer\n\necce What is Cargo? ecce\n",
+                marked.wrap("A language")
+            ),
+        )
+        .unwrap();
+
+        let history = vec![HistoryEntry {
+            timestamp: Utc::now(),
+            file: path.display().to_string(),
+            agent: "helper".to_string(),
+            prompt: "Say something plain".to_string(),
+            response: Some("Plain answer".to_string()),
+            duration_ms: 10,
+            ..Default::default()
+        }];
+
+        let questions = questions_in(&path, &Config::default(), &history).unwrap();
+        let summary: Vec<_> = questions
+            .iter()
+            .map(|q| (q.line, q.status, q.question.as_str(), q.agent.as_deref()))
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                (3, QuestionStatus::Answered, "What is Rust?", Some("writer")),
+                (6, QuestionStatus::Answered, "Say something plain", Some("helper")),
+                (8, QuestionStatus::Unanswered, "What is Cargo?", None),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_csv_quoting() {
+        let questions = vec![Question {
+            file: "a.md".to_string(),
+            line: 2,
+            status: QuestionStatus::Edited,
+            question: "Why \"this\", and\nthat?".to_string(),
+            agent: Some("w".to_string()),
+        }];
+        assert_eq!(
+            to_csv(&questions),
+            "file,line,status,agent,question\na.md,2,edited,w,\"Why \"\"this\"\", and\nthat?\"\n"
+        );
+    }
+}
@@ -0,0 +1,504 @@
+use anyhow::{Context, Result};
+use clap::Args;
+use colored::*;
+use crossterm::{
+    cursor,
+    event::{self, Event, KeyCode, KeyEvent, KeyModifiers},
+    execute,
+    terminal::{self, ClearType},
+};
+use serde::{de::DeserializeOwned, Serialize};
+use std::io::{self, IsTerminal, Write};
+use std::process::Command;
+use ecce::{ui_println, ui_prompt, ui_result};
+
+use crate::config::Config;
+use crate::history::{self, HistoryEntry};
+use crate::ui;
+use crate::utils::apply_profile;
+
+#[derive(Args)]
+pub struct FindArgs {
+    /// Initial search text
+    pub query: Option<String>,
+}
+
+/// A named thing ecce knows about
+#[derive(Debug, Clone, PartialEq)]
+enum Target {
+    Profile(String),
+    Agent(String),
+    Task(String),
+    Mcp(String),
+    /// Index into the loaded history
+    History(usize),
+}
+
+impl Target {
+    fn kind(&self) -> &'static str {
+        match self {
+            Target::Profile(_) => "profile",
+            Target::Agent(_) => "agent",
+            Target::Task(_) => "task",
+            Target::Mcp(_) => "mcp",
+            Target::History(_) => "history",
+        }
+    }
+
+    fn actions(&self) -> &'static [Action] {
+        match self {
+            Target::Profile(_) | Target::Agent(_) => {
+                &[Action::Switch, Action::Show, Action::Edit, Action::Delete]
+            }
+            Target::Task(_) | Target::Mcp(_) => &[Action::Show, Action::Edit, Action::Delete],
+            Target::History(_) => &[Action::Show],
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Action {
+    Switch,
+    Show,
+    Edit,
+    Delete,
+}
+
+impl Action {
+    fn label(&self, target: &Target) -> &'static str {
+        match (self, target) {
+            (Action::Switch, Target::Agent(_)) => "Make default agent",
+            (Action::Switch, _) => "Switch to this profile",
+            (Action::Show, _) => "Show",
+            (Action::Edit, _) => "Edit in $EDITOR",
+            (Action::Delete, _) => "Delete",
+        }
+    }
+}
+
+/// A picker row: what it refers to and the text shown and searched
+struct Entry {
+    target: Target,
+    text: String,
+}
+
+pub fn handle_find_command(args: FindArgs, config: &mut Config) -> Result<()> {
+    if !io::stdin().is_terminal() || !io::stdout().is_terminal() {
+        return Err(anyhow::anyhow!("ecce find needs an interactive terminal"));
+    }
+
+    // A broken history log shouldn't make config entries unreachable
+    let history = history::load().unwrap_or_default();
+    let entries = collect_entries(config, &history);
+    if entries.is_empty() {
+        ui_println!("{}", "Nothing to find: no profiles, agents, tasks, MCP servers, or history".yellow());
+        return Ok(());
+    }
+
+    let texts: Vec<String> = entries.iter().map(|e| e.text.clone()).collect();
+    let Some(idx) = pick("Find (type to filter)", &texts, args.query.unwrap_or_default())? else {
+        return Ok(());
+    };
+    let target = &entries[idx].target;
+
+    let actions = target.actions();
+    let action = if actions.len() == 1 {
+        actions[0]
+    } else {
+        let labels: Vec<String> = actions.iter().map(|a| a.label(target).to_string()).collect();
+        let title = format!("{} {}", target.kind(), describe(target, &history));
+        match pick(&title, &labels, String::new())? {
+            Some(i) => actions[i],
+            None => return Ok(()),
+        }
+    };
+
+    run_action(action, target, config, &history)
+}
+
+/// Every profile, agent, task, MCP server, and history entry, grouped by kind
+fn collect_entries(config: &Config, history: &[HistoryEntry]) -> Vec<Entry> {
+    let mut entries = Vec::new();
+
+    for profile in config.resolved_profiles() {
+        entries.push(Entry {
+            target: Target::Profile(profile.name.clone()),
+            text: format!("{:<8} {}  {}", "profile", profile.name, profile.url),
+        });
+    }
+
+    let mut agents: Vec<_> = config.agents.values().collect();
+    agents.sort_by(|a, b| a.name.cmp(&b.name));
+    for agent in agents {
+        let detail = agent.description.as_deref().unwrap_or(&agent.system_prompt);
+        entries.push(Entry {
+            target: Target::Agent(agent.name.clone()),
+            text: format!("{:<8} {}  {}", "agent", agent.name, ui::preview(detail, 60)),
+        });
+    }
+
+    let mut tasks: Vec<_> = config.tasks.values().collect();
+    tasks.sort_by(|a, b| a.name.cmp(&b.name));
+    for task in tasks {
+        entries.push(Entry {
+            target: Target::Task(task.name.clone()),
+            text: format!("{:<8} {}  {}", "task", task.name, ui::preview(&task.template, 60)),
+        });
+    }
+
+    let mut servers: Vec<_> = config.mcp_servers.keys().collect();
+    servers.sort();
+    for name in servers {
+        entries.push(Entry {
+            target: Target::Mcp(name.clone()),
+            text: format!("{:<8} {}", "mcp", name),
+        });
+    }
+
+    // Newest generations first
+    for (idx, entry) in history.iter().enumerate().rev() {
+        entries.push(Entry {
+            target: Target::History(idx),
+            text: format!(
+                "{:<8} {} {}  {}",
+                "history",
+                entry.timestamp.format("%Y-%m-%d %H:%M"),
+                entry.agent,
+                ui::preview(&entry.prompt, 60)
+            ),
+        });
+    }
+
+    entries
+}
+
+/// Fuzzy match score of `query` against `text`, or None if it doesn't match.
+/// Every whitespace-separated term must appear in order (case-insensitive);
+/// consecutive and word-start matches score higher.
+fn fuzzy_score(query: &str, text: &str) -> Option<i64> {
+    let haystack: Vec<char> = text.to_lowercase().chars().collect();
+    let mut total = 0;
+
+    for term in query.split_whitespace() {
+        let mut score = 0;
+        let mut pos = 0;
+        let mut previous: Option<usize> = None;
+        for c in term.to_lowercase().chars() {
+            let found = pos + haystack[pos..].iter().position(|&h| h == c)?;
+            score += 1;
+            if previous.is_some_and(|p| p + 1 == found) {
+                score += 5;
+            }
+            if found == 0 || !haystack[found - 1].is_alphanumeric() {
+                score += 3;
+            }
+            previous = Some(found);
+            pos = found + 1;
+        }
+        total += score;
+    }
+
+    Some(total)
+}
+
+/// Indices of the items matching `query`, best first (ties keep their order)
+fn filter(items: &[String], query: &str) -> Vec<usize> {
+    let mut matches: Vec<(usize, i64)> = items
+        .iter()
+        .enumerate()
+        .filter_map(|(idx, item)| fuzzy_score(query, item).map(|score| (idx, score)))
+        .collect();
+    matches.sort_by_key(|&(idx, score)| (std::cmp::Reverse(score), idx));
+    matches.into_iter().map(|(idx, _)| idx).collect()
+}
+
+/// Full-screen type-ahead picker; returns the chosen item's index
+fn pick(title: &str, items: &[String], initial_query: String) -> Result<Option<usize>> {
+    let mut query = initial_query;
+    let mut selected = 0;
+
+    terminal::enable_raw_mode()?;
+
+    let result = (|| -> Result<Option<usize>> {
+        loop {
+            let matches = filter(items, &query);
+            selected = selected.min(matches.len().saturating_sub(1));
+
+            execute!(io::stdout(), terminal::Clear(ClearType::All), cursor::MoveTo(0, 0))?;
+            let (width, height) = terminal::size().unwrap_or((80, 24));
+            let visible = (height as usize).saturating_sub(4).max(1);
+            let offset = selected.saturating_sub(visible - 1);
+
+            ui_result!("\r{}", title.bold());
+            ui_result!("\r{} {}", ">".green().bold(), query);
+            ui_result!(
+                "\r{}",
+                format!("{}/{}  (↑/↓: navigate, Enter: select, Esc: cancel)", matches.len(), items.len())
+                    .dimmed()
+            );
+            for (row, &idx) in matches.iter().enumerate().skip(offset).take(visible) {
+                let text = ui::truncate(&items[idx], (width as usize).saturating_sub(3));
+                if row == selected {
+                    ui_result!("\r{} {}", "→".green().bold(), text.cyan());
+                } else {
+                    ui_result!("\r  {}", text);
+                }
+            }
+            io::stdout().flush()?;
+
+            // Drawn again only once a key or a resize changes what is shown
+            let (code, modifiers) = loop {
+                match event::read()? {
+                    Event::Key(KeyEvent { code, modifiers, .. }) => break (code, modifiers),
+                    Event::Resize(..) => break (KeyCode::Null, KeyModifiers::NONE),
+                    _ => {}
+                }
+            };
+            let ctrl = modifiers.contains(KeyModifiers::CONTROL);
+            match code {
+                KeyCode::Esc => return Ok(None),
+                KeyCode::Char('c') if ctrl => return Ok(None),
+                KeyCode::Enter => return Ok(matches.get(selected).copied()),
+                KeyCode::Up => selected = selected.saturating_sub(1),
+                KeyCode::Char('p') if ctrl => selected = selected.saturating_sub(1),
+                KeyCode::Down => selected += 1,
+                KeyCode::Char('n') if ctrl => selected += 1,
+                KeyCode::Backspace => {
+                    query.pop();
+                    selected = 0;
+                }
+                KeyCode::Char(c) if !ctrl => {
+                    query.push(c);
+                    selected = 0;
+                }
+                _ => {}
+            }
+        }
+    })();
+
+    terminal::disable_raw_mode()?;
+    execute!(io::stdout(), terminal::Clear(ClearType::All), cursor::MoveTo(0, 0))?;
+
+    result
+}
+
+/// Name of the target for messages, e.g. "'writer'"
+fn describe(target: &Target, history: &[HistoryEntry]) -> String {
+    match target {
+        Target::Profile(name) | Target::Agent(name) | Target::Task(name) | Target::Mcp(name) => {
+            format!("'{}'", name)
+        }
+        Target::History(idx) => format!("from {}", history[*idx].timestamp.format("%Y-%m-%d %H:%M")),
+    }
+}
+
+fn run_action(action: Action, target: &Target, config: &mut Config, history: &[HistoryEntry]) -> Result<()> {
+    match action {
+        Action::Switch => match target {
+            Target::Profile(name) => {
+                if let Some(profile) = config.switch_profile(name)? {
+                    apply_profile(&profile)?;
+                }
+            }
+            Target::Agent(name) => {
+                config.set_default_agent(name)?;
+                ui_println!("{}", format!("✓ Default agent set to '{}'", name).green());
+            }
+            _ => {}
+        },
+        Action::Show => show(target, config, history)?,
+        Action::Edit => edit(target, config)?,
+        Action::Delete => {
+            let what = format!("{} {}", target.kind(), describe(target, history));
+            if !confirm(&format!("Delete {}?", what))? {
+                return Ok(());
+            }
+            let deleted = match target {
+                Target::Profile(name) => config.delete_profile(name)?,
+                Target::Agent(name) => config.delete_agent(name)?,
+                Target::Task(name) => config.delete_task(name)?,
+                Target::Mcp(name) => config.delete_mcp_server(name)?,
+                Target::History(_) => false,
+            };
+            if deleted {
+                ui_println!("{}", format!("✓ Deleted {}", what).green());
+            }
+        }
+    }
+    Ok(())
+}
+
+fn show(target: &Target, config: &Config, history: &[HistoryEntry]) -> Result<()> {
+    let value = match target {
+        Target::Profile(name) => {
+            let profile = config
+                .profiles
+                .iter()
+                .find(|p| &p.name == name)
+                .context("Profile not found")?;
+            let mut value = serde_json::to_value(profile)?;
+            value["key"] = format!("{}***", ui::prefix(&profile.key, 8)).into();
+            value
+        }
+        Target::Agent(name) => serde_json::to_value(config.get_agent(name).context("Agent not found")?)?,
+        Target::Task(name) => serde_json::to_value(config.get_task(name).context("Task not found")?)?,
+        Target::Mcp(name) => serde_json::to_value(config.get_mcp_server(name).context("MCP server not found")?)?,
+        Target::History(idx) => serde_json::to_value(&history[*idx])?,
+    };
+    println!("{}", serde_json::to_string_pretty(&value)?);
+    Ok(())
+}
+
+fn edit(target: &Target, config: &mut Config) -> Result<()> {
+    match target {
+        Target::Profile(name) => {
+            let idx = config
+                .profiles
+                .iter()
+                .position(|p| &p.name == name)
+                .context("Profile not found")?;
+            if let Some(profile) = edit_json(&config.profiles[idx])? {
+                config.profiles[idx] = profile;
+                config.save()?;
+            }
+        }
+        Target::Agent(name) => {
+            let agent = config.get_agent(name).context("Agent not found")?;
+            if let Some(agent) = edit_json(agent)? {
+                config.agents.remove(name);
+                config.add_agent(agent)?;
+            }
+        }
+        Target::Task(name) => {
+            let task = config.get_task(name).context("Task not found")?;
+            if let Some(task) = edit_json(task)? {
+                let previous = config.tasks.remove(name);
+                config.tasks.insert(task.name.clone(), task.clone());
+                if let Err(e) = config.resolve_task(&task.name) {
+                    config.tasks.remove(&task.name);
+                    if let Some(previous) = previous {
+                        config.tasks.insert(name.clone(), previous);
+                    }
+                    return Err(e);
+                }
+                config.save()?;
+            }
+        }
+        Target::Mcp(name) => {
+            let server = config.get_mcp_server(name).context("MCP server not found")?;
+            if let Some(server) = edit_json(server)? {
+                config.mcp_servers.remove(name);
+                config.add_mcp_server(server)?;
+            }
+        }
+        Target::History(_) => return Ok(()),
+    }
+    ui_println!("{}", format!("✓ Saved {} {}", target.kind(), describe(target, &[])).green());
+    Ok(())
+}
+
+/// Open `value` as JSON in $VISUAL/$EDITOR; None if it was left unchanged
+fn edit_json<T: Serialize + DeserializeOwned>(value: &T) -> Result<Option<T>> {
+    let original = serde_json::to_string_pretty(value)?;
+    let file = tempfile::Builder::new().prefix("ecce-").suffix(".json").tempfile()?;
+    std::fs::write(file.path(), &original)?;
+
+    let editor = std::env::var("VISUAL")
+        .or_else(|_| std::env::var("EDITOR"))
+        .unwrap_or_else(|_| "vi".to_string());
+    let status = Command::new("sh")
+        .arg("-c")
+        .arg(format!("{} \"$1\"", editor))
+        .arg("sh")
+        .arg(file.path())
+        .status()
+        .with_context(|| format!("Failed to run editor '{}'", editor))?;
+    if !status.success() {
+        return Err(anyhow::anyhow!("Editor exited with {}; nothing saved", status));
+    }
+
+    let edited = std::fs::read_to_string(file.path())?;
+    if edited.trim() == original.trim() {
+        ui_println!("{}", "No changes".yellow());
+        return Ok(None);
+    }
+    serde_json::from_str(&edited).context("Edited JSON is invalid; nothing saved").map(Some)
+}
+
+fn confirm(question: &str) -> Result<bool> {
+    ui_prompt!("{} ", format!("{} [y/N]", question).yellow());
+    io::stdout().flush()?;
+
+    let mut input = String::new();
+    io::stdin().read_line(&mut input)?;
+    Ok(matches!(input.trim().to_lowercase().as_str(), "y" | "yes"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{Agent, Profile, Task};
+
+    #[test]
+    fn test_fuzzy_score() {
+        assert!(fuzzy_score("wrt", "agent writer").is_some());
+        assert!(fuzzy_score("agent wri", "agent   writer  Write slides").is_some());
+        assert!(fuzzy_score("xyz", "agent writer").is_none());
+        assert!(fuzzy_score("", "anything").is_some());
+        // Contiguous, word-start matches beat scattered ones
+        assert!(fuzzy_score("lec", "task lecture").unwrap() > fuzzy_score("lec", "task lo-fi echo").unwrap());
+    }
+
+    #[test]
+    fn test_filter_orders_by_score() {
+        let items = vec![
+            "task     lo-fi echo".to_string(),
+            "task     lecture".to_string(),
+            "agent    writer".to_string(),
+        ];
+        assert_eq!(filter(&items, "lec"), vec![1, 0]);
+        assert_eq!(filter(&items, ""), vec![0, 1, 2]);
+    }
+
+    #[test]
+    fn test_collect_entries() {
+        let mut config = Config::default();
+        config.profiles.push(Profile {
+            name: "work".to_string(),
+            url: "https://api.example.com".to_string(),
+            key: "sk-secret".to_string(),
+            service: "claude-code".to_string(),
+            ..Default::default()
+        });
+        for name in ["zeta", "alpha"] {
+            config.agents.insert(
+                name.to_string(),
+                Agent {
+                    name: name.to_string(),
+                    system_prompt: "Write slides".to_string(),
+                    ..Default::default()
+                },
+            );
+        }
+        config.tasks.insert(
+            "lecture".to_string(),
+            Task {
+                name: "lecture".to_string(),
+                ..Default::default()
+            },
+        );
+
+        let entries = collect_entries(&config, &[]);
+        let targets: Vec<Target> = entries.iter().map(|e| e.target.clone()).collect();
+        assert_eq!(
+            targets,
+            vec![
+                Target::Profile("work".to_string()),
+                Target::Agent("alpha".to_string()),
+                Target::Agent("zeta".to_string()),
+                Target::Task("lecture".to_string()),
+            ]
+        );
+        assert!(!entries[0].text.contains("sk-secret"));
+    }
+}
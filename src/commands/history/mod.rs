@@ -0,0 +1,187 @@
+use anyhow::{Context, Result};
+use clap::Subcommand;
+use colored::*;
+use std::path::PathBuf;
+use ecce::{ui_println, ui_result};
+
+use crate::search_index::{self, Index};
+use crate::history::{self, Vote};
+use crate::ui;
+
+#[derive(Subcommand)]
+pub enum HistoryCommand {
+    /// Export recorded generations (prompt/response pairs)
+    Export {
+        /// Output format: 'jsonl' or 'markdown'
+        #[arg(short, long, default_value = "jsonl")]
+        format: String,
+        /// Only include generations on or after this date (YYYY-MM-DD)
+        #[arg(long)]
+        since: Option<String>,
+        /// Write to a file instead of stdout
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+        /// Only include rated generations: 'up', 'down', or 'any'
+        #[arg(long, value_parser = ["up", "down", "any"])]
+        feedback: Option<String>,
+    },
+    /// List recent generations with their ids
+    List {
+        /// How many of the most recent generations to show
+        #[arg(short = 'n', long, default_value = "20")]
+        limit: usize,
+    },
+    /// Show what differed between two generations' inputs (ids or id prefixes)
+    Diff {
+        first: String,
+        second: String,
+    },
+    /// Find past generations that share words with a query (a lexical search, not by meaning)
+    Search {
+        /// What to look for, e.g. "transformer attention"
+        query: String,
+        /// How many generations to show
+        #[arg(short = 'n', long, default_value = "10")]
+        limit: usize,
+        /// Only generations for this file (as recorded, e.g. an absolute path)
+        #[arg(long)]
+        file: Option<String>,
+        /// Recompute every vector instead of only those for new generations
+        #[arg(long)]
+        rebuild: bool,
+    },
+}
+
+pub fn handle_history_command(command: HistoryCommand) -> Result<()> {
+    match command {
+        HistoryCommand::Export {
+            format,
+            since,
+            output,
+            feedback,
+        } => {
+            let since = since.as_deref().map(history::parse_since).transpose()?;
+            let mut entries = history::filter_since(history::load()?, since);
+            if let Some(wanted) = feedback {
+                // 'any' is no vote, which keeps every rating
+                let wanted = Vote::parse(&wanted);
+                entries.retain(|e| e.feedback.as_ref().is_some_and(|f| wanted.is_none_or(|v| v == f.vote)));
+            }
+
+            let rendered = match format.as_str() {
+                "jsonl" => history::to_jsonl(&entries)?,
+                "markdown" | "md" => history::to_markdown(&entries),
+                _ => {
+                    return Err(anyhow::anyhow!(
+                        "Invalid format '{}'. Use 'jsonl' or 'markdown'",
+                        format
+                    ));
+                }
+            };
+
+            match output {
+                Some(path) => {
+                    std::fs::write(&path, rendered)
+                        .with_context(|| format!("Failed to write export: {}", path.display()))?;
+                    ui_println!(
+                        "{}",
+                        format!(
+                            "✓ Exported {} generation(s) to {}",
+                            entries.len(),
+                            path.display()
+                        )
+                        .green()
+                    );
+                }
+                None => print!("{}", rendered),
+            }
+        }
+        HistoryCommand::List { limit } => {
+            let entries = history::load()?;
+            if entries.is_empty() {
+                ui_result!("{}", "No generations recorded yet".yellow());
+            }
+            for entry in entries.iter().skip(entries.len().saturating_sub(limit)) {
+                let status = if entry.is_success() { "✓".green() } else { "✗".red() };
+                let vote = entry.feedback.as_ref().map_or(String::new(), |f| format!(" {}", f.vote.emoji()));
+                ui_result!(
+                    "{} {} {} {:<16} {}{}",
+                    status,
+                    entry.id.as_deref().unwrap_or("-").dimmed(),
+                    entry.timestamp.format("%Y-%m-%d %H:%M"),
+                    entry.agent.cyan(),
+                    ui::preview(&entry.prompt, 50),
+                    vote
+                );
+            }
+        }
+        HistoryCommand::Diff { first, second } => {
+            let entries = history::load()?;
+            let a = history::find(&entries, &first)?;
+            let b = history::find(&entries, &second)?;
+            if a.context.is_none() && b.context.is_none() {
+                ui_println!(
+                    "{}",
+                    "⚠ Neither generation has a context snapshot (recorded by older versions)".yellow()
+                );
+            }
+
+            let changes = history::diff(a, b);
+            if changes.is_empty() {
+                ui_result!("{}", "✓ Same inputs; differences in output come from the model itself".green());
+            }
+            for change in changes {
+                let line = match change.trim_start().chars().next() {
+                    Some('-') => change.red(),
+                    Some('+') => change.green(),
+                    _ => change.normal(),
+                };
+                ui_result!("{}", line);
+            }
+        }
+        HistoryCommand::Search {
+            query,
+            limit,
+            file,
+            rebuild,
+        } => {
+            let mut entries = history::load()?;
+            if let Some(ref file) = file {
+                entries.retain(|e| &e.file == file);
+            }
+            let path = search_index::index_path()?;
+            if rebuild && path.exists() {
+                std::fs::remove_file(&path)
+                    .with_context(|| format!("Failed to remove search index: {}", path.display()))?;
+            }
+            let vectorizer = search_index::default_vectorizer();
+            let mut index = Index::open(&path, vectorizer)?;
+            let added = index.update(&entries, vectorizer)?;
+            if added > 0 {
+                ui_println!("{}", format!("Indexed {} new generation(s)", added).dimmed());
+            }
+
+            let hits = index.search(&entries, &query, vectorizer, limit);
+            if hits.is_empty() {
+                ui_result!("{}", "No matching generations".yellow());
+            }
+            for hit in hits {
+                let entry = hit.entry;
+                ui_result!(
+                    "{} {} {} {:<16} {}",
+                    format!("{:.2}", hit.score).cyan(),
+                    entry.id.as_deref().unwrap_or("-").dimmed(),
+                    entry.timestamp.format("%Y-%m-%d %H:%M"),
+                    entry.agent.cyan(),
+                    ui::preview(&entry.prompt, 50)
+                );
+                ui_result!(
+                    "     {}",
+                    ui::preview(entry.response.as_deref().unwrap_or_default(), 70).dimmed()
+                );
+            }
+        }
+    }
+
+    Ok(())
+}
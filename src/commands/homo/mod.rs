@@ -1,19 +1,53 @@
 use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
 use clap::Args;
 use colored::*;
-use std::io::{self, Write};
-use std::path::PathBuf;
+use std::collections::VecDeque;
+use std::io::{self, BufRead, IsTerminal, Write};
+use std::path::{Component, Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant, SystemTime};
 use tokio::signal;
-
-use crate::agent::ClaudeAgent;
-use crate::config::{Agent, Config, Task};
-use crate::pattern::EccePattern;
+use tokio::sync::mpsc;
+use tokio::task::JoinSet;
+use ecce::{ui_eprintln, ui_println, ui_prompt};
+
+use crate::agent::{self, ClaudeAgent, RunOptions};
+use crate::citation;
+use crate::delivery::{self, Delivery, DeliveryTarget, Outcome};
+use crate::config::{Agent, Config, Preset, Profile, ProjectConfig, Task, WatchDefaults, WatchMode, CLAUDE_CODE_SERVICE, PROJECT_CONFIG_FILE};
+use crate::environment::GenerationEnv;
+use crate::exit::Failure;
+use crate::guard::{self, Guard, GuardAction, Violation};
+use crate::history::{self, Feedback, HistoryEntry, Vote};
+use crate::pending::{self, PendingGeneration};
+use crate::document::{self, DocumentHandler};
+use crate::fit;
+use crate::line_endings;
+use crate::provenance::{self, Provenance};
+use crate::pattern::{queue_order, DedupStrategy, EccePattern, Priority, PatternDetector, PatternOptions, PatternType, DEFAULT_KEYWORD};
+use crate::redact::Redactor;
+use crate::reuse;
+use crate::remote::{FileTarget, RemoteFile, REMOTE_MIN_INTERVAL_MS};
+use crate::router::Router;
+use crate::session::{self, SavedAgent, SavedSession, SessionSettings};
+use crate::style::DocumentStyle;
+use crate::translate::Translator;
+use crate::polish::Polisher;
+use crate::ui::{self, format_elapsed, tr, trf, Msg, Spinner};
 use crate::watcher::FileWatcher;
 
 #[derive(Args)]
 pub struct HomoArgs {
-    /// File or folder to watch (if folder, looks for slides.md)
-    pub file_path: PathBuf,
+    /// File or folder to watch (if folder, looks for slides.md); may be a
+    /// remote `user@host:/path` reached over ssh, or a name from `ecce target add`
+    #[arg(required_unless_present_any = ["stdin", "target"])]
+    pub file_path: Option<PathBuf>,
+
+    /// Watch this target from `ecce target add`, never a file of the same name
+    #[arg(long, value_name = "NAME", conflicts_with_all = ["file_path", "stdin"])]
+    pub target: Option<String>,
 
     /// Agent to use (optional, uses default or prompts)
     #[arg(short, long)]
@@ -23,20 +57,536 @@ pub struct HomoArgs {
     #[arg(short, long)]
     pub task: Option<String>,
 
-    /// Watch interval in milliseconds
-    #[arg(long, default_value = "100")]
-    pub watch_interval: u64,
+    /// Preset bundling agent, task, model, and output mode; --agent and --task override it
+    #[arg(short, long)]
+    pub preset: Option<String>,
+
+    /// Watch interval in milliseconds [default: watch.interval in config, else 100]
+    #[arg(long)]
+    pub watch_interval: Option<u64>,
+
+    /// Wait until the file has been unchanged this many milliseconds before
+    /// answering its patterns (also watch.debounce in config)
+    #[arg(long, value_name = "MS")]
+    pub debounce: Option<u64>,
+
+    /// Show each answer and ask before writing it, when running at a terminal
+    /// (also watch.confirm in config)
+    #[arg(long, overrides_with = "no_confirm")]
+    pub confirm: bool,
+
+    /// Write answers without asking, even if watch.confirm is set in config
+    #[arg(long, overrides_with = "confirm")]
+    pub no_confirm: bool,
+
+    /// Compare mode: send each prompt to these agents (comma-separated) concurrently
+    /// and write every response under a labeled heading
+    #[arg(long, conflicts_with = "agent")]
+    pub compare: Option<String>,
+
+    /// Let a quick model call pick the agent for each pattern from the agents'
+    /// descriptions (automatic when `router` is in config and no agent is given)
+    #[arg(long, conflicts_with_all = ["agent", "compare"])]
+    pub route: bool,
+
+    /// Pass --dangerously-skip-permissions to claude so unattended runs never
+    /// stall on a tool permission prompt
+    #[arg(long)]
+    pub dangerously_skip_permissions: bool,
+
+    /// Replay conversation history in every prompt instead of resuming the claude session
+    #[arg(long)]
+    pub no_resume: bool,
+
+    /// Estimated prompt token budget; larger prompts drop context to fit
+    /// (overrides max_prompt_tokens in config)
+    #[arg(long)]
+    pub max_prompt_tokens: Option<u64>,
+
+    /// Read-only: never modify the watched file; append answers to
+    /// <name>.ecce-answers.md next to it instead
+    #[arg(long)]
+    pub observe: bool,
+
+    /// Append responses to this answers file (local or user@host:/path) instead
+    /// of replacing patterns in the watched file
+    #[arg(short, long, conflicts_with = "observe")]
+    pub output: Option<PathBuf>,
+
+    /// Copy each generated response to the system clipboard
+    #[arg(long)]
+    pub copy: bool,
+
+    /// Wrap answers in HTML comments recording their id, agent, time, and prompt
+    /// (also `"provenance": true` in config)
+    #[arg(long)]
+    pub provenance: bool,
+
+    /// Print responses to stdout (after their question) instead of editing the
+    /// file; status messages move to stderr
+    #[arg(long, conflicts_with_all = ["observe", "output"])]
+    pub stdout: bool,
+
+    /// Replace patterns in the watched file, even if watch.mode in config says
+    /// to observe or print to stdout
+    #[arg(long, conflicts_with_all = ["observe", "output", "stdout"])]
+    pub in_place: bool,
+
+    /// Only trigger on these pattern types: inline, block, comment, or item (repeatable)
+    #[arg(long, value_name = "TYPE")]
+    pub only: Vec<PatternType>,
+
+    /// How answered patterns are recognized: content (same question anywhere),
+    /// location (same question on the same slide), or marker (same `id=` option)
+    #[arg(long, default_value = "content")]
+    pub dedup: DedupStrategy,
+
+    /// Pattern trigger keyword instead of `ecce` (repeatable); overrides the
+    /// project's .ecce.json and the global config
+    #[arg(short, long)]
+    pub keyword: Vec<String>,
+
+    /// Refuse to start if claude would run without an API key or against an
+    /// endpoint other than the active profile's
+    #[arg(long)]
+    pub strict: bool,
+
+    /// Have the agent cite its context files, written as footnotes linking to
+    /// them relative to the document
+    #[arg(long)]
+    pub cite: bool,
+
+    /// Proofread each answer before it is written, with the task's polish
+    /// settings or the built-in proofreader
+    #[arg(long)]
+    pub polish: bool,
+
+    /// Sample how the document styles headings, lists, bold, and emoji, and
+    /// ask the agents to answer the same way (also watch.match_style in config)
+    #[arg(long, overrides_with = "no_match_style")]
+    pub match_style: bool,
+
+    /// Don't match the document's style, even if watch.match_style is set in config
+    #[arg(long, overrides_with = "match_style")]
+    pub no_match_style: bool,
+
+    /// Ask for answers that fit in the room left on their slide, out of LINES
+    /// per slide; in a Slidev deck, what doesn't fit goes on continuation slides
+    #[arg(long, value_name = "LINES")]
+    pub fit_slide: Option<usize>,
+
+    /// Treat the file as a question queue: each unchecked top-level `- [ ]`
+    /// item is a question, answered beneath it (or in --output's file) and ticked
+    #[arg(long, conflicts_with = "observe")]
+    pub queue: bool,
+
+    /// Before generating, look in the history for a nearly identical question
+    /// already answered in this file and offer its answer (or a link to it) instead
+    #[arg(long, conflicts_with_all = ["compare", "route"])]
+    pub reuse: bool,
+
+    /// Read prompts from stdin instead of watching a file, one per line (or per
+    /// --delimiter block), and print each answer to stdout as it completes
+    #[arg(long, conflicts_with_all = ["file_path", "observe", "output", "queue", "compare", "route"])]
+    pub stdin: bool,
+
+    /// With --stdin, prompts are blocks of lines separated by this line, e.g. `---`
+    #[arg(long, value_name = "LINE", requires = "stdin", allow_hyphen_values = true)]
+    pub delimiter: Option<String>,
+}
+
+/// Stand-in file name for --stdin, so project settings come from the working directory
+const STDIN_NAME: &str = "<stdin>";
+
+/// Poll interval when neither --watch-interval nor watch.interval is set
+pub const DEFAULT_WATCH_INTERVAL_MS: u64 = 100;
+
+/// Suffix of the answers file written next to the watched file in observe mode
+const ANSWERS_SUFFIX: &str = ".ecce-answers.md";
+
+/// Where generated responses are written
+enum ResponseOutput {
+    /// Replace the pattern in the watched file
+    InPlace,
+    /// Append to a separate answers file, leaving the watched file untouched
+    Sidecar(FileTarget),
+    /// Print to stdout, leaving the watched file untouched
+    Stdout,
+}
+
+/// Per-run state shared by the watch loop
+struct WatchSession {
+    target: FileTarget,
+    output: ResponseOutput,
+    profile: Option<String>,
+    watch_interval: u64,
+    /// Milliseconds the file must stay unchanged before it is scanned
+    debounce: u64,
+    /// Ask at the terminal before writing each answer
+    confirm: bool,
+    /// Pattern types that trigger generation; empty means all
+    pattern_types: Vec<PatternType>,
+    /// Trigger keywords; empty means `ecce`
+    keywords: Vec<String>,
+    dedup: DedupStrategy,
+    redactor: Redactor,
+    reloader: ConfigReloader,
+    /// Held for the whole session: on X11 and Wayland, copied text is only
+    /// available while the clipboard that set it is alive
+    clipboard: Option<arboard::Clipboard>,
+    /// Wrap in-place answers in provenance markers
+    provenance: bool,
+    /// Turn `[source: ...]` tags into footnotes
+    cite: bool,
+    /// Close each prompt with the document's style, sampled as it is answered
+    match_style: bool,
+    /// Lines per slide that in-place answers are fitted to
+    fit_slide: Option<usize>,
+    /// The file is a question queue (see `document::Queue`)
+    queue: bool,
+    /// Offer earlier answers to nearly identical questions (see `reuse`)
+    reuse: bool,
+    /// The history --reuse searches, read on first use and kept up to date
+    /// with the session's own answers
+    history: Option<Vec<HistoryEntry>>,
+    /// Proofreading pass run on each answer before it is translated and written
+    polisher: Option<Polisher>,
+    /// Second pass that translates each answer before it is written
+    translator: Option<Translator>,
+    /// Where finished answers and failures are sent
+    deliver: Vec<DeliveryTarget>,
+    /// Content rules checked before a response is written
+    guard: Guard,
+    /// Picks one of the agents per pattern; without it every agent answers
+    router: Option<Router>,
+    /// Typed lines, when the session runs at a terminal
+    input: Option<TerminalInput>,
+    /// Setup and progress kept in the autosave that `ecce session save` copies;
+    /// none for one-shot runs
+    saved: Option<SavedSession>,
+    shutdown: Arc<Shutdown>,
+}
+
+impl WatchSession {
+    /// A session that writes answers in place, with the config's guard,
+    /// redaction, delivery, and provenance, and nothing interactive; each
+    /// mode switches on the rest of what it needs
+    fn new(
+        config: &Config,
+        target: FileTarget,
+        profile: Option<String>,
+        polisher: Option<Polisher>,
+        translator: Option<Translator>,
+    ) -> Result<Self> {
+        Ok(Self {
+            guard: load_guard(config, &target)?,
+            target,
+            output: ResponseOutput::InPlace,
+            clipboard: None,
+            provenance: config.provenance,
+            cite: false,
+            match_style: false,
+            fit_slide: None,
+            queue: false,
+            reuse: false,
+            history: None,
+            polisher,
+            translator,
+            deliver: config.deliver.clone(),
+            router: None,
+            input: None,
+            profile,
+            watch_interval: 0,
+            debounce: 0,
+            confirm: false,
+            pattern_types: Vec::new(),
+            keywords: Vec::new(),
+            dedup: DedupStrategy::default(),
+            redactor: Redactor::from_config(config),
+            reloader: ConfigReloader::new(Config::config_path()?),
+            saved: None,
+            shutdown: Arc::default(),
+        })
+    }
+}
+
+/// Ctrl+C state shared between the signal listener and the watch loop
+#[derive(Default)]
+struct Shutdown {
+    requested: AtomicBool,
+    /// The file, how it is written, and the generation whose placeholder is currently in it
+    in_flight: Mutex<Option<InFlight>>,
+}
+
+type InFlight = (FileTarget, &'static dyn DocumentHandler, PendingGeneration);
+
+impl Shutdown {
+    fn is_requested(&self) -> bool {
+        self.requested.load(Ordering::Relaxed)
+    }
+
+    fn set_in_flight(&self, generation: Option<InFlight>) {
+        *self.in_flight.lock().unwrap() = generation;
+    }
+
+    /// Put the in-flight pattern back in place of its placeholder
+    fn roll_back(&self) {
+        if let Some((target, format, generation)) = self.in_flight.lock().unwrap().take() {
+            match replace_pattern_in_file(&target, format, &generation.placeholder(), &generation.raw) {
+                Ok(()) => forget_pending(&generation),
+                Err(e) => ui_eprintln!("Failed to restore pattern in {}: {}", target, e),
+            }
+        }
+    }
+}
+
+/// Tracks the config file so edits made during a session are picked up between patterns
+struct ConfigReloader {
+    path: PathBuf,
+    modified: Option<SystemTime>,
+}
+
+impl ConfigReloader {
+    fn new(path: PathBuf) -> Self {
+        let modified = modified_time(&path);
+        Self { path, modified }
+    }
+
+    /// The freshly loaded config if the file changed since the last check
+    fn poll(&mut self) -> Option<Result<Config>> {
+        let modified = modified_time(&self.path);
+        if modified == self.modified {
+            return None;
+        }
+        self.modified = modified;
+        Some(Config::load_from(&self.path))
+    }
+}
+
+fn modified_time(path: &Path) -> Option<SystemTime> {
+    std::fs::metadata(path).and_then(|m| m.modified()).ok()
 }
 
 pub async fn handle_homo_command(args: HomoArgs, config: &Config) -> Result<()> {
-    // Resolve file path (handle both files and folders)
-    let file_path = resolve_file_path(&args.file_path)?;
+    if args.stdin {
+        return answer_stdin(args, config).await;
+    }
+    watch(args, config, None).await
+}
+
+/// Resume a saved session: same file, agents, task, and flags, with the agents'
+/// conversations and the answered patterns carried over
+pub async fn resume_session(saved: SavedSession, config: &Config) -> Result<()> {
+    watch(restored_args(&saved), config, Some(saved)).await
+}
+
+/// The homo arguments a saved session was started with
+fn restored_args(saved: &SavedSession) -> HomoArgs {
+    let settings = saved.settings.clone();
+    // A routed session's agents are the router's candidates, chosen again on resume
+    let names: Vec<&str> = match settings.route {
+        true => Vec::new(),
+        false => saved.agents.iter().map(|a| a.name.as_str()).collect(),
+    };
+    // The negations pin the saved choices against watch defaults changed since
+    let in_place = !settings.observe && !settings.stdout && settings.output.is_none();
+    HomoArgs {
+        file_path: Some(PathBuf::from(&saved.file)),
+        target: None,
+        agent: (names.len() == 1).then(|| names[0].to_string()),
+        task: saved.task.clone(),
+        preset: saved.preset.clone(),
+        watch_interval: Some(settings.watch_interval),
+        debounce: settings.debounce,
+        confirm: settings.confirm,
+        no_confirm: !settings.confirm,
+        compare: (names.len() > 1).then(|| names.join(",")),
+        route: settings.route,
+        dangerously_skip_permissions: settings.skip_permissions,
+        no_resume: settings.no_resume,
+        max_prompt_tokens: settings.max_prompt_tokens,
+        observe: settings.observe,
+        output: settings.output,
+        copy: settings.copy,
+        provenance: settings.provenance,
+        stdout: settings.stdout,
+        in_place,
+        only: settings.only,
+        dedup: settings.dedup,
+        keyword: settings.keywords,
+        strict: settings.strict,
+        cite: settings.cite,
+        polish: settings.polish,
+        match_style: settings.match_style,
+        no_match_style: !settings.match_style,
+        fit_slide: settings.fit_slide,
+        queue: settings.queue,
+        reuse: settings.reuse,
+        // A stdin stream has no session to save
+        stdin: false,
+        delimiter: None,
+    }
+}
+
+fn session_settings(args: &HomoArgs) -> SessionSettings {
+    SessionSettings {
+        watch_interval: args.watch_interval.unwrap_or(DEFAULT_WATCH_INTERVAL_MS),
+        debounce: args.debounce,
+        confirm: args.confirm,
+        route: args.route,
+        skip_permissions: args.dangerously_skip_permissions,
+        no_resume: args.no_resume,
+        max_prompt_tokens: args.max_prompt_tokens,
+        observe: args.observe,
+        output: args.output.clone(),
+        copy: args.copy,
+        provenance: args.provenance,
+        stdout: args.stdout,
+        only: args.only.clone(),
+        dedup: args.dedup,
+        keywords: args.keyword.clone(),
+        strict: args.strict,
+        cite: args.cite,
+        polish: args.polish,
+        match_style: args.match_style,
+        fit_slide: args.fit_slide,
+        queue: args.queue,
+        reuse: args.reuse,
+    }
+}
+
+/// How a saved session names its file, so it resumes from any directory
+fn session_file(target: &FileTarget) -> String {
+    match target {
+        FileTarget::Local(path) => path.canonicalize().unwrap_or_else(|_| path.clone()).display().to_string(),
+        FileTarget::Remote(_) => target.to_string(),
+    }
+}
+
+/// Fill in options not given on the command line from the config's `watch` section
+fn apply_watch_defaults(mut args: HomoArgs, defaults: &WatchDefaults) -> HomoArgs {
+    args.watch_interval = args.watch_interval.or(defaults.interval);
+    args.debounce = args.debounce.or(defaults.debounce);
+    args.confirm = !args.no_confirm && (args.confirm || defaults.confirm);
+    args.match_style = !args.no_match_style && (args.match_style || defaults.match_style);
+    args.fit_slide = args.fit_slide.or(defaults.fit_slide);
+    if args.output.is_none() && !args.observe && !args.stdout && !args.in_place {
+        match defaults.mode {
+            Some(WatchMode::Observe) => args.observe = true,
+            Some(WatchMode::Stdout) => args.stdout = true,
+            Some(WatchMode::InPlace) | None => {}
+        }
+    }
+    args
+}
+
+/// Swap a target name for its path, taking the target's preset, agent, and
+/// task where none were given. A registered name wins over a file or folder
+/// of the same name, which can be watched as `./name`; `--target` names one
+/// explicitly.
+fn apply_target(mut args: HomoArgs, config: &Config) -> Result<HomoArgs> {
+    let target = match (args.target.take(), &args.file_path) {
+        (Some(name), _) => config
+            .get_target(&name)
+            .ok_or_else(|| anyhow::anyhow!("Target '{}' not found; add it with `ecce target add`", name))?,
+        // Anything written as a path (`./lecture`, `talks/rust`) is one
+        (None, Some(path))
+            if path.components().count() == 1 && matches!(path.components().next(), Some(Component::Normal(_))) =>
+        {
+            let Some(target) = path.to_str().and_then(|name| config.get_target(name)) else {
+                return Ok(args);
+            };
+            if path.exists() {
+                ui_println!(
+                    "{}",
+                    format!("⚠ '{}' is a target; to watch the local one instead, use ./{}", target.name, target.name)
+                        .yellow()
+                );
+            }
+            target
+        }
+        _ => return Ok(args),
+    };
+    ui_println!("{}", format!("🎯 Target '{}': {}", target.name, target.path).green());
+    args.file_path = Some(PathBuf::from(&target.path));
+    args.preset = args.preset.or(target.preset.clone());
+    args.task = args.task.or(target.task.clone());
+    if args.compare.is_none() && !args.route {
+        args.agent = args.agent.or(target.agent.clone());
+    }
+    Ok(args)
+}
+
+fn load_preset(config: &Config, name: Option<&str>) -> Result<Preset> {
+    match name {
+        Some(name) => config
+            .get_preset(name)
+            .cloned()
+            .ok_or_else(|| anyhow::anyhow!("Preset '{}' not found", name)),
+        None => Ok(Preset::default()),
+    }
+}
+
+/// How the agents run claude, from the flags, the preset, and the config
+fn run_options(args: &HomoArgs, config: &Config, preset: &Preset) -> RunOptions {
+    RunOptions {
+        skip_permissions: args.dangerously_skip_permissions,
+        no_resume: args.no_resume,
+        max_prompt_tokens: args.max_prompt_tokens.or(config.max_prompt_tokens),
+        // A preset's model is for its one agent; compared or routed agents keep their own
+        model: preset
+            .model
+            .clone()
+            .filter(|_| args.compare.is_none() && !routes(args, config, preset)),
+        cite: args.cite,
+        ..RunOptions::from_config(config)
+    }
+}
+
+/// Whether an agent is picked per pattern: with --route, or with a router
+/// configured and no agent given
+fn routes(args: &HomoArgs, config: &Config, preset: &Preset) -> bool {
+    args.route || (config.router.is_some() && args.agent.is_none() && args.compare.is_none() && preset.agent.is_none())
+}
+
+async fn watch(args: HomoArgs, config: &Config, restored: Option<SavedSession>) -> Result<()> {
+    let args = apply_watch_defaults(apply_target(args, config)?, &config.watch);
+    if args.fit_slide == Some(0) {
+        return Err(anyhow::anyhow!("--fit-slide needs at least one line per slide"));
+    }
+    let settings = session_settings(&args);
+    let preset = load_preset(config, args.preset.as_deref())?;
+    if preset.plain {
+        ui::init(true);
+    }
+    if args.stdout {
+        ui::status_to_stderr();
+    }
+
+    // Resolve file path (handle both files and folders, local or remote)
+    let target = resolve_target(args.file_path.as_deref().context("No file to watch")?)?;
+    let run_options = run_options(&args, config, &preset);
+    let keywords = resolve_keywords(args.keyword.clone(), config, &target)?;
 
-    // Select agent
-    let agent_config = select_agent(config, args.agent.clone())?;
+    let mut router = if routes(&args, config, &preset) {
+        let settings = config.router.clone().unwrap_or_default();
+        Some(Router::new(config, &settings, run_options.clone()).context(Failure::Config)?)
+    } else {
+        None
+    };
+
+    // Select agent(s): a router's candidates, compare mode's several, or one
+    let agent_configs = match (router.as_ref(), args.compare.as_ref()) {
+        (Some(router), _) => router.candidates().to_vec(),
+        (None, Some(names)) => select_compare_agents(config, names)?,
+        (None, None) => vec![select_agent(config, args.agent.clone().or(preset.agent))?],
+    };
 
-    // Select task (interactive if not specified)
-    let task_config = select_task(config, args.task.clone())?;
+    // Select task (interactive if not specified); a restored session without one stays without
+    let task_config = match restored {
+        Some(ref saved) => saved.task.as_deref().map(|name| config.resolve_task(name)).transpose()?,
+        None => select_task(config, args.task.clone().or(preset.task))?,
+    };
 
     // Get Claude Code executable path from config
     let claude_executable = config.get_claude_executable();
@@ -48,27 +598,738 @@ pub async fn handle_homo_command(args: HomoArgs, config: &Config) -> Result<()>
         "(none)".to_string()
     };
 
-    // Create agent
-    let claude_agent = ClaudeAgent::new(claude_executable, agent_config.clone(), task_config);
+    let agent_display = agent_configs
+        .iter()
+        .map(|a| a.name.as_str())
+        .collect::<Vec<_>>()
+        .join(", ");
+
+    for agent in agent_configs.iter().filter(|a| a.temperature.is_some()) {
+        ui_println!(
+            "{}",
+            format!(
+                "⚠ Agent '{}' sets a temperature, which the claude CLI does not support; ignoring it",
+                agent.name
+            )
+            .yellow()
+        );
+    }
+
+    // Create agents
+    let mut claude_agents: Vec<ClaudeAgent> = agent_configs
+        .into_iter()
+        .map(|agent| {
+            ClaudeAgent::with_options(
+                claude_executable.clone(),
+                agent,
+                task_config.clone(),
+                run_options.clone(),
+            )
+        })
+        .collect();
+
+    // A profile pinned to the file's project overrides whatever the shell has loaded
+    let pinned = pinned_profile(config, &target)?;
+    if let Some(ref profile) = pinned {
+        for agent in claude_agents.iter_mut() {
+            agent.use_profile(profile);
+        }
+        if let Some(ref mut router) = router {
+            router.use_profile(profile);
+        }
+    }
+    let profile = pinned.clone().or_else(|| config.get_active_profile(CLAUDE_CODE_SERVICE));
+    let polisher = load_polisher(config, &target, task_config.as_ref(), &run_options, args.polish)?;
+    let translator = load_translator(config, &target, task_config.as_ref(), &run_options)?;
+    let mut session = WatchSession::new(
+        config,
+        target,
+        profile.as_ref().map(|p| p.name.clone()),
+        polisher,
+        translator,
+    )?;
+
+    // Pick up the saved conversations after the profile, which resets claude sessions
+    if let Some(ref saved) = restored {
+        for agent in claude_agents.iter_mut() {
+            if let Some(previous) = saved.agents.iter().find(|a| a.name == agent.agent_name()) {
+                agent.restore_conversation(previous.history.clone(), previous.session_id.clone());
+            }
+        }
+    }
+
+    // Fail before watching if the installed claude rejects a flag an agent needs
+    let mut claude_version = None;
+    for agent in &claude_agents {
+        claude_version = agent.check_capabilities().await?.version;
+    }
+
+    // What claude will actually run against, which may not be what the config says
+    let environment = GenerationEnv::for_agent(&claude_agents[0], profile.clone());
+    let problems: Vec<String> = claude_agents
+        .iter()
+        .flat_map(|agent| GenerationEnv::for_agent(agent, profile.clone()).problems())
+        .fold(Vec::new(), |mut unique, problem| {
+            if !unique.contains(&problem) {
+                unique.push(problem);
+            }
+            unique
+        });
+    if args.strict && !problems.is_empty() {
+        return Err(Failure::Config.error(format!(
+            "Refusing to start (--strict):\n  {}",
+            problems.join("\n  ")
+        )));
+    }
+
+    ui_println!("\n{}", tr(Msg::WatcherStarted).bold().green());
+    ui_println!("{}", "═".repeat(60).dimmed());
+    ui_println!("  📄 {:<10}{}", label(Msg::File), session.target.to_string().cyan());
+    if let Some(ref router) = router {
+        ui_println!(
+            "  🧭 {:<10}{} {}",
+            "Router:",
+            agent_display.cyan(),
+            format!("(picked per pattern by {})", router.model()).dimmed()
+        );
+    } else if claude_agents.len() > 1 {
+        ui_println!("  ⚖️  {:<10}{}", label(Msg::Compare), agent_display.cyan());
+    } else {
+        ui_println!("  🤖 {:<10}{}", label(Msg::Agent), agent_display.cyan());
+    }
+    ui_println!("  📋 {:<10}{}", label(Msg::Task), task_display.cyan());
+    if let Some(version) = claude_version {
+        ui_println!("  🧩 {:<10}{}", "Claude:", version.to_string().cyan());
+    }
+    show_environment(&environment, pinned.is_some());
+    if let Some(ref name) = args.preset {
+        ui_println!("  🎛️  {:<10}{}", "Preset:", name.cyan());
+    }
+    if let Some(ref saved) = restored {
+        ui_println!(
+            "  💾 {:<10}{}",
+            "Restored:",
+            format!(
+                "{} answered pattern(s), {} message(s) from {}",
+                saved.processed.len(),
+                saved.message_count(),
+                saved.saved_at.with_timezone(&chrono::Local).format("%Y-%m-%d %H:%M")
+            )
+            .cyan()
+        );
+    }
+    let answers = match args.output {
+        Some(ref path) => Some(output_target(path)),
+        None if args.observe => Some(session.target.sibling_with_suffix(ANSWERS_SUFFIX)),
+        None => None,
+    };
+    let output = match answers {
+        Some(answers) => {
+            ui_println!(
+                "  👁️  {:<10}{} {}",
+                "Output:",
+                answers.to_string().cyan(),
+                "(watched file is never modified)".dimmed()
+            );
+            ResponseOutput::Sidecar(answers)
+        }
+        None if args.stdout => ResponseOutput::Stdout,
+        None => ResponseOutput::InPlace,
+    };
+    if args.dangerously_skip_permissions {
+        ui_println!("  ⚠️  Permissions: {}", "skipped (--dangerously-skip-permissions)".red());
+    }
+    for problem in &problems {
+        ui_println!("  {}", format!("⚠ {}", problem).yellow());
+    }
+    ui_println!("{}", "═".repeat(60).dimmed());
+    ui_println!("\n{}", tr(Msg::WatchingForPatterns).yellow());
+    let keyword = keywords.first().map_or(DEFAULT_KEYWORD, |k| k.as_str());
+    ui_println!("   Pattern 1: {}", format!("{k} <prompt> {k}", k = keyword).cyan());
+    ui_println!("   Pattern 2: {}", format!("```{}\\n<prompt>\\n```", keyword).cyan());
+    ui_println!("   Pattern 3: {}", format!("<!-- {} <prompt> -->", keyword).cyan());
+    if keywords.len() > 1 {
+        ui_println!("   Keywords:  {}", keywords.join(", ").cyan());
+    }
+    if !args.only.is_empty() {
+        let types: Vec<String> = args.only.iter().map(|t| format!("{:?}", t)).collect();
+        ui_println!("   Only:      {}", types.join(", ").cyan());
+    }
+    // Every remote poll is an ssh round trip, so don't hammer the host
+    let watch_interval = args.watch_interval.unwrap_or(DEFAULT_WATCH_INTERVAL_MS);
+    let watch_interval = if session.target.is_remote() {
+        watch_interval.max(REMOTE_MIN_INTERVAL_MS)
+    } else {
+        watch_interval
+    };
+    ui_println!("   Interval:  {}ms", watch_interval.to_string().cyan());
+    if let Some(debounce) = args.debounce.filter(|&d| d > 0) {
+        ui_println!("   Debounce:  {}ms", debounce.to_string().cyan());
+    }
+    if args.confirm {
+        ui_println!("   Confirm:   {}", "ask before writing each answer".cyan());
+    }
+    if let Some(ref polisher) = session.polisher {
+        ui_println!("   Polish:    {}", polisher.name().cyan());
+    }
+    if let Some(ref translator) = session.translator {
+        let layout = if translator.settings().bilingual { ", bilingual" } else { "" };
+        ui_println!("   Translate: {}", format!("{}{}", translator.language(), layout).cyan());
+    }
+    if args.cite {
+        if claude_agents
+            .iter()
+            .all(|a| !a.agent().context_files.iter().any(|f| agent::is_context_file(f)))
+        {
+            ui_println!("   {}", "⚠ --cite: no agent has context files to cite".yellow());
+        } else {
+            ui_println!("   Cite:      {}", "context files, as footnotes".cyan());
+        }
+    }
+    if args.match_style {
+        ui_println!("   Style:     {}", "matching the document's headings and lists".cyan());
+    }
+    if args.reuse {
+        ui_println!("   Reuse:     {}", "earlier answers to nearly identical questions".cyan());
+    }
+    if args.queue {
+        ui_println!("   Queue:     {}", "each unchecked `- [ ]` item, ticked once answered".cyan());
+    }
+    if let Some(lines) = args.fit_slide {
+        ui_println!("   Fit:       {}", format!("{} lines per slide", lines).cyan());
+    }
+    if let Some(limit) = config.max_concurrent {
+        ui_println!("   Limit:     {}", format!("{} claude processes at once, machine-wide", limit).cyan());
+    }
+    ui_println!("\n   {}\n", trf(Msg::PressToStop, &[&"Ctrl+C".bold().to_string()]));
+
+    let clipboard = if args.copy {
+        match arboard::Clipboard::new() {
+            Ok(clipboard) => Some(clipboard),
+            Err(e) => {
+                ui_println!("{}", format!("⚠ Clipboard unavailable, --copy disabled: {}", e).yellow());
+                None
+            }
+        }
+    } else {
+        None
+    };
+
+    let saved = SavedSession {
+        file: session_file(&session.target),
+        agents: Vec::new(),
+        task: task_config.as_ref().map(|t| t.name.clone()),
+        preset: args.preset.clone(),
+        settings,
+        processed: restored.map(|s| s.processed).unwrap_or_default(),
+        saved_at: Utc::now(),
+    };
 
-    println!("{}", "\n🎭 Ecce Homo - File Watcher Started".bold().green());
-    println!("{}", "═".repeat(60).dimmed());
-    println!("  📄 File:     {}", file_path.display().to_string().cyan());
-    println!("  🤖 Agent:    {}", agent_config.name.cyan());
-    println!("  📋 Task:     {}", task_display.cyan());
-    println!("{}", "═".repeat(60).dimmed());
-    println!("{}", "\n👀 Watching for patterns...".yellow());
-    println!("   Pattern 1: {}", "ecce <prompt> ecce".cyan());
-    println!("   Pattern 2: {}", "```ecce\\n<prompt>\\n```".cyan());
-    println!("   Interval:  {}ms", args.watch_interval.to_string().cyan());
-    println!("\n   Press {} to stop\n", "Ctrl+C".bold());
+    session.output = output;
+    session.clipboard = clipboard;
+    session.provenance |= args.provenance;
+    session.cite = args.cite;
+    session.match_style = args.match_style;
+    session.fit_slide = args.fit_slide;
+    session.queue = args.queue;
+    session.reuse = args.reuse;
+    session.router = router;
+    session.saved = Some(saved);
+    session.watch_interval = watch_interval;
+    session.debounce = args.debounce.unwrap_or(0);
+    session.confirm = args.confirm;
+    session.pattern_types = args.only;
+    session.keywords = keywords;
+    session.dedup = args.dedup;
 
     // Start watching with signal handling
-    watch_and_process_with_signals(&file_path, claude_agent, args.watch_interval).await
+    watch_and_process_with_signals(&mut session, claude_agents).await
+}
+
+/// Answer the patterns currently in a file once, without watching or prompting.
+/// Used by `ecce hooks run`; returns how many patterns were answered, or a
+/// generation failure if any pattern could not be answered.
+pub async fn process_once(
+    path: &Path,
+    config: &Config,
+    agent_name: Option<String>,
+    task_name: Option<String>,
+) -> Result<usize> {
+    let target = resolve_target(path)?;
+    let keywords = resolve_keywords(Vec::new(), config, &target)?;
+    let mut watcher = FileWatcher::new(&target)?;
+    watcher.set_keywords(&keywords)?;
+    let mut detector = PatternDetector::new();
+    detector.set_keywords(&keywords)?;
+    let format = document::handler_for(target.path(), watcher.current_content());
+    detector.set_pattern_types(document::pattern_types(format, &[]));
+    let mut patterns = detector.detect_patterns(watcher.current_content());
+    queue_order(&mut patterns);
+    if patterns.is_empty() {
+        return Ok(0);
+    }
+
+    let agent = unattended_agent(config, agent_name)?;
+    let task = task_name.map(|name| config.resolve_task(&name)).transpose()?;
+    let run_options = RunOptions::from_config(config);
+    let polisher = load_polisher(config, &target, task.as_ref(), &run_options, false)?;
+    let translator = load_translator(config, &target, task.as_ref(), &run_options)?;
+    let mut agents = vec![ClaudeAgent::with_options(
+        config.get_claude_executable(),
+        agent,
+        task,
+        run_options,
+    )];
+    let profile = use_pinned_profile(config, &target, &mut agents)?;
+    let mut session = WatchSession {
+        keywords,
+        ..WatchSession::new(config, target, profile, polisher, translator)?
+    };
+
+    let mut answered = 0;
+    for pattern in &patterns {
+        match process_pattern(pattern, &mut agents, &mut session, &mut watcher).await {
+            Ok(_) => answered += 1,
+            Err(e) => ui_eprintln!(
+                "Failed to process pattern in {}: {}",
+                session.target,
+                session.redactor.redact(&format!("{:#}", e))
+            ),
+        }
+    }
+    if answered < patterns.len() {
+        return Err(Failure::Generation.error(format!(
+            "{} of {} patterns in {} failed",
+            patterns.len() - answered,
+            patterns.len(),
+            session.target
+        )));
+    }
+    Ok(answered)
+}
+
+/// Answer prompts read from stdin (`--stdin`), printing each answer as it
+/// completes. Fails if any prompt could not be answered.
+async fn answer_stdin(args: HomoArgs, config: &Config) -> Result<()> {
+    use tokio::io::{AsyncBufReadExt, BufReader};
+
+    let args = apply_watch_defaults(args, &config.watch);
+    ui::status_to_stderr();
+    let preset = load_preset(config, args.preset.as_deref())?;
+    if preset.plain {
+        ui::init(true);
+    }
+    let target = FileTarget::Local(std::env::current_dir()?.join(STDIN_NAME));
+    // Stdin carries the prompts, so nothing can be picked interactively
+    let agent = unattended_agent(config, args.agent.clone().or(preset.agent.clone()))?;
+    let task = args.task.clone().or(preset.task.clone()).map(|name| config.resolve_task(&name)).transpose()?;
+    let run_options = run_options(&args, config, &preset);
+    let polisher = load_polisher(config, &target, task.as_ref(), &run_options, args.polish)?;
+    let translator = load_translator(config, &target, task.as_ref(), &run_options)?;
+    let mut agents = vec![ClaudeAgent::with_options(
+        config.get_claude_executable(),
+        agent,
+        task,
+        run_options,
+    )];
+    let profile = use_pinned_profile(config, &target, &mut agents)?;
+    ui_println!("{}", format!("📥 Answering prompts from stdin with {}", describe_agents(&agents)).cyan());
+
+    let session = WatchSession {
+        output: ResponseOutput::Stdout,
+        provenance: false,
+        cite: args.cite,
+        deliver: Vec::new(),
+        ..WatchSession::new(config, target, profile, polisher, translator)?
+    };
+
+    let mut reader = PromptReader::new(args.delimiter);
+    let mut lines = BufReader::new(tokio::io::stdin()).lines();
+    let (mut answered, mut failed) = (0, 0);
+    loop {
+        let prompt = match lines.next_line().await.context("Failed to read stdin")? {
+            Some(line) => match reader.push(line) {
+                Some(prompt) => prompt,
+                None => continue,
+            },
+            None => match reader.finish() {
+                Some(prompt) => prompt,
+                None => break,
+            },
+        };
+        let question = session.redactor.redact(&prompt);
+        match answer_streamed(&prompt, &mut agents, &session).await {
+            Ok(response) => {
+                print!("{}", format_stdout_entry(&question, &response));
+                io::stdout().flush()?;
+                answered += 1;
+            }
+            Err(e) => {
+                ui_eprintln!(
+                    "Failed to answer \"{}\": {}",
+                    ui::truncate(question.lines().next().unwrap_or_default(), 60),
+                    session.redactor.redact(&format!("{:#}", e))
+                );
+                failed += 1;
+            }
+        }
+    }
+
+    ui_println!("{}", format!("✓ Answered {} prompt(s)", answered).green());
+    if failed > 0 {
+        return Err(Failure::Generation.error(format!(
+            "{} of {} prompts from stdin failed",
+            failed,
+            answered + failed
+        )));
+    }
+    Ok(())
+}
+
+/// One prompt from stdin through generation, proofreading, translation, and the guards
+async fn answer_streamed(prompt: &str, agents: &mut [ClaudeAgent], session: &WatchSession) -> Result<String> {
+    let spinner = Spinner::start(format!("{} {}", tr(Msg::Generating).yellow(), describe_agents(agents).dimmed()));
+    let results = generate_all(agents, prompt, &PatternOptions::default()).await;
+    spinner.stop();
+    let (result, elapsed) = results?.into_iter().next().expect("one result per agent");
+    record_generation(&agents[0], session, prompt, &result, elapsed);
+    let response = result.map_err(|e| Failure::Generation.error(format!("{:#}", e)))?;
+    let response = polish_answer(session, response).await;
+    let mut response = session.redactor.redact(&translate_answer(session, response).await);
+    if session.cite {
+        response = cite_sources(response, agents, session);
+    }
+    enforce_guards(&session.guard.check(&response), &mut None).await?;
+    Ok(response)
+}
+
+/// Splits stdin into prompts: each non-blank line, or with a delimiter, the
+/// blocks of lines between delimiter lines
+struct PromptReader {
+    delimiter: Option<String>,
+    block: Vec<String>,
+}
+
+impl PromptReader {
+    fn new(delimiter: Option<String>) -> Self {
+        Self {
+            delimiter: delimiter.map(|d| d.trim().to_string()),
+            block: Vec::new(),
+        }
+    }
+
+    /// Take one line; returns a prompt once one is complete
+    fn push(&mut self, line: String) -> Option<String> {
+        match self.delimiter {
+            None => Some(line.trim().to_string()).filter(|prompt| !prompt.is_empty()),
+            Some(ref delimiter) if line.trim() == delimiter => self.finish(),
+            Some(_) => {
+                self.block.push(line);
+                None
+            }
+        }
+    }
+
+    /// The last block, at the end of the input
+    fn finish(&mut self) -> Option<String> {
+        let block = std::mem::take(&mut self.block).join("\n");
+        Some(block.trim().to_string()).filter(|prompt| !prompt.is_empty())
+    }
+}
+
+/// Re-run the prompt behind a generated answer and replace the answer in place.
+/// Picks the answer with the given provenance id, or the most recent one; uses
+/// the given agent and task, falling back to the ones that wrote the answer.
+/// Returns the answer's id, which is kept.
+pub async fn regenerate(
+    path: &Path,
+    config: &Config,
+    id: Option<&str>,
+    agent_name: Option<String>,
+    task_name: Option<String>,
+    force: bool,
+) -> Result<String> {
+    let target = resolve_target(path)?;
+    let content = target.read_to_string()?;
+    let regions = provenance::find_regions(&content);
+    let region = match id {
+        Some(id) => regions
+            .iter()
+            .find(|r| r.provenance.id == id)
+            .ok_or_else(|| anyhow::anyhow!("No generated answer with id '{}' in {}", id, target))?,
+        None => regions.iter().max_by_key(|r| r.provenance.at).ok_or_else(|| {
+            anyhow::anyhow!(
+                "No generated answers in {}; answers are only marked when written with --provenance",
+                target
+            )
+        })?,
+    };
+    if region.is_edited(&content) && !force {
+        return Err(anyhow::anyhow!(
+            "Answer {} was edited after it was generated; pass --force to replace it anyway",
+            region.provenance.id
+        ));
+    }
+    let previous = region.provenance.clone();
+
+    let original_agent = previous
+        .agent
+        .split(',')
+        .next()
+        .filter(|name| config.get_agent(name).is_some());
+    let agent = match (agent_name, original_agent) {
+        (None, Some(name)) => config.resolve_agent(name)?,
+        (name, _) => select_agent(config, name)?,
+    };
+    let task = match task_name {
+        Some(name) => Some(config.resolve_task(&name)?),
+        None => previous.task.as_ref().and_then(|name| config.resolve_task(name).ok()),
+    };
+    let run_options = RunOptions::from_config(config);
+    let polisher = load_polisher(config, &target, task.as_ref(), &run_options, false)?;
+    let translator = load_translator(config, &target, task.as_ref(), &run_options)?;
+    let mut agents = vec![ClaudeAgent::with_options(
+        config.get_claude_executable(),
+        agent,
+        task,
+        run_options,
+    )];
+    let profile = use_pinned_profile(config, &target, &mut agents)?;
+    let session = WatchSession {
+        provenance: true,
+        deliver: Vec::new(),
+        ..WatchSession::new(config, target, profile, polisher, translator)?
+    };
+
+    let spinner = Spinner::start(format!(
+        "{} {}",
+        tr(Msg::Generating).yellow(),
+        describe_agents(&agents).dimmed()
+    ));
+    let results = generate_all(&mut agents, &previous.prompt, &PatternOptions::default()).await;
+    spinner.stop();
+    let (result, elapsed) = results?.into_iter().next().expect("one result per agent");
+    record_generation(&agents[0], &session, &previous.prompt, &result, elapsed);
+    let response = result.map_err(|e| Failure::Generation.error(format!("{:#}", e)))?;
+    let response = polish_answer(&session, response).await;
+    let response = session.redactor.redact(&translate_answer(&session, response).await);
+    enforce_guards(&session.guard.check(&response), &mut None).await?;
+
+    // The file may have changed while generating, so find the answer again
+    let raw = session.target.read_raw()?;
+    let content = line_endings::normalize(&raw);
+    let region = provenance::find_regions(&content)
+        .into_iter()
+        .find(|r| r.provenance.id == previous.id)
+        .ok_or_else(|| anyhow::anyhow!("Answer {} was removed while regenerating", previous.id))?;
+    let replacement = Provenance::new(
+        previous.id.clone(),
+        agents[0].agent_name().to_string(),
+        agents[0].task_name().map(str::to_string),
+        previous.prompt.clone(),
+        &response,
+    )
+    .wrap(&response);
+
+    let mut updated = content;
+    updated.replace_range(region.range, &replacement);
+    session
+        .target
+        .write(&raw, &updated)
+        .context("Failed to write regenerated answer")?;
+    Ok(previous.id)
+}
+
+/// Patterns still waiting for an answer in a file, with their line numbers
+pub fn unanswered_patterns(path: &Path, config: &Config) -> Result<(FileTarget, Vec<(usize, EccePattern)>)> {
+    let target = resolve_target(path)?;
+    let content = target.read_to_string()?;
+    let mut detector = PatternDetector::new();
+    detector.set_keywords(&resolve_keywords(Vec::new(), config, &target)?)?;
+    detector.set_pattern_types(document::pattern_types(document::handler_for(target.path(), &content), &[]));
+    let patterns = detector
+        .detect_patterns(&content)
+        .into_iter()
+        .map(|p| (line_number(&content, p.start_pos), p))
+        .collect();
+    Ok((target, patterns))
+}
+
+/// Trigger keywords: `--keyword`, else the nearest project `.ecce.json`, else the global config
+fn resolve_keywords(cli: Vec<String>, config: &Config, target: &FileTarget) -> Result<Vec<String>> {
+    if !cli.is_empty() {
+        return Ok(cli);
+    }
+    let project = project_config(target)?;
+    if !project.keywords.is_empty() {
+        return Ok(project.keywords);
+    }
+    Ok(config.keywords.clone())
+}
+
+/// The `.ecce.json` nearest a local target; defaults for remote targets
+fn project_config(target: &FileTarget) -> Result<ProjectConfig> {
+    let FileTarget::Local(path) = target else {
+        return Ok(ProjectConfig::default());
+    };
+    match project_dir(path) {
+        Some(dir) => ProjectConfig::find(&dir).context(Failure::Config),
+        None => Ok(ProjectConfig::default()),
+    }
+}
+
+/// The directory whose project settings apply to a local file: the file's own
+/// (symlinks resolved), or for a file that doesn't exist, like the --stdin
+/// stand-in, its parent (the working directory for a bare file name)
+fn project_dir(path: &Path) -> Option<PathBuf> {
+    match path.canonicalize() {
+        Ok(path) => path.parent().map(Path::to_path_buf),
+        Err(_) => match path.parent()? {
+            parent if parent.as_os_str().is_empty() => std::env::current_dir().ok(),
+            parent => parent.canonicalize().ok(),
+        },
+    }
+}
+
+/// The proofreading pass: the task's polish settings, or the built-in
+/// proofreader when `--polish` asks for one and the task has none
+fn load_polisher(
+    config: &Config,
+    target: &FileTarget,
+    task: Option<&Task>,
+    options: &RunOptions,
+    requested: bool,
+) -> Result<Option<Polisher>> {
+    let settings = match task.and_then(|t| t.polish.clone()) {
+        Some(settings) => settings,
+        None if requested => Default::default(),
+        None => return Ok(None),
+    };
+    let mut polisher = Polisher::new(config, &settings, options.clone()).context(Failure::Config)?;
+    if let Some(profile) = pinned_profile(config, target)? {
+        polisher.use_profile(&profile);
+    }
+    Ok(Some(polisher))
+}
+
+/// Run the session's proofreading pass; on failure the answer is kept as generated
+async fn polish_answer(session: &WatchSession, response: String) -> String {
+    let Some(ref polisher) = session.polisher else {
+        return response;
+    };
+    let status = format!("{} {}", "✏️  Proofreading with".yellow(), polisher.name());
+    let polished = side_pass(session, status, polisher.polish(&response), "writing the answer as generated").await;
+    polished.unwrap_or(response)
+}
+
+/// The translation pass for answers written to `target`: its project's
+/// `translate_to`, else the task's
+fn load_translator(
+    config: &Config,
+    target: &FileTarget,
+    task: Option<&Task>,
+    options: &RunOptions,
+) -> Result<Option<Translator>> {
+    let Some(settings) = project_config(target)?
+        .translate_to
+        .or_else(|| task.and_then(|t| t.translate_to.clone()))
+    else {
+        return Ok(None);
+    };
+    let mut translator = Translator::new(config, settings, options.clone());
+    if let Some(profile) = pinned_profile(config, target)? {
+        translator.use_profile(&profile);
+    }
+    Ok(Some(translator))
+}
+
+/// Run the session's translation pass; on failure the untranslated answer is kept
+async fn translate_answer(session: &WatchSession, response: String) -> String {
+    let Some(ref translator) = session.translator else {
+        return response;
+    };
+    let status = format!("{} {}", "🌐 Translating into".yellow(), translator.language());
+    let translated = side_pass(session, status, translator.translate(&response), "writing the untranslated answer").await;
+    translated.unwrap_or(response)
+}
+
+/// Wait on a pass over the answer behind a spinner; on failure, warn with
+/// what is written instead and return none
+async fn side_pass(
+    session: &WatchSession,
+    status: String,
+    pass: impl std::future::Future<Output = Result<String>>,
+    instead: &str,
+) -> Option<String> {
+    let spinner = Spinner::start(status);
+    let result = pass.await;
+    spinner.stop();
+    result
+        .map_err(|e| {
+            let message = session.redactor.redact(&format!("{:#}", e));
+            ui_println!("  {}", format!("⚠ {}; {}", message, instead).yellow());
+        })
+        .ok()
+}
+
+/// Guard rules from the global config plus the target's project
+pub(crate) fn load_guard(config: &Config, target: &FileTarget) -> Result<Guard> {
+    let mut rules = config.guards.clone();
+    rules.extend(project_config(target)?.guards);
+    Guard::new(&rules).context(Failure::Config)
+}
+
+/// Banner label with trailing colon, e.g. "File:"
+fn label(msg: Msg) -> String {
+    format!("{}:", tr(msg))
+}
+
+/// Resolve the watch target; `host:/path` is remote unless a local file by that name exists
+fn resolve_target(path: &Path) -> Result<FileTarget> {
+    if !path.exists() {
+        if let Some(remote) = path.to_str().and_then(RemoteFile::parse) {
+            return resolve_remote_file(remote).map(FileTarget::Remote);
+        }
+    }
+    resolve_file_path(path).map(FileTarget::Local)
+}
+
+/// Remote counterpart of `resolve_file_path`, checked over ssh
+fn resolve_remote_file(remote: RemoteFile) -> Result<RemoteFile> {
+    if remote.test("-d")? {
+        let slides = remote.with_path(format!("{}/slides.md", remote.path.trim_end_matches('/')));
+        if slides.test("-f")? {
+            ui_println!("{}", format!("📁 Found slides.md in directory: {}", remote).green());
+            Ok(slides)
+        } else {
+            Err(anyhow::anyhow!(
+                "Directory provided but slides.md not found in: {}",
+                remote
+            ))
+        }
+    } else if remote.test("-f")? {
+        Ok(remote)
+    } else {
+        Err(anyhow::anyhow!(
+            "Remote path not found or not reachable over ssh: {}",
+            remote
+        ))
+    }
+}
+
+/// The answers file for `--output`; like the watched file, `host:/path` is remote
+/// unless a local file by that name exists
+fn output_target(path: &Path) -> FileTarget {
+    if !path.exists() {
+        if let Some(remote) = path.to_str().and_then(RemoteFile::parse) {
+            return FileTarget::Remote(remote);
+        }
+    }
+    FileTarget::Local(path.to_path_buf())
 }
 
 /// Resolve file path - if it's a directory, look for slides.md
-fn resolve_file_path(path: &PathBuf) -> Result<PathBuf> {
+fn resolve_file_path(path: &Path) -> Result<PathBuf> {
     if !path.exists() {
         return Err(anyhow::anyhow!(
             "Path not found: {}",
@@ -80,7 +1341,7 @@ fn resolve_file_path(path: &PathBuf) -> Result<PathBuf> {
         // Look for slides.md in the directory
         let slides_path = path.join("slides.md");
         if slides_path.exists() {
-            println!(
+            ui_println!(
                 "{}",
                 format!("📁 Found slides.md in directory: {}", path.display())
                     .green()
@@ -93,7 +1354,7 @@ fn resolve_file_path(path: &PathBuf) -> Result<PathBuf> {
             ))
         }
     } else if path.is_file() {
-        Ok(path.clone())
+        Ok(path.to_path_buf())
     } else {
         Err(anyhow::anyhow!(
             "Invalid path (not a file or directory): {}",
@@ -102,17 +1363,24 @@ fn resolve_file_path(path: &PathBuf) -> Result<PathBuf> {
     }
 }
 
+/// The named agent, else the default one, for runs that can't ask which
+fn unattended_agent(config: &Config, agent_name: Option<String>) -> Result<Agent> {
+    match agent_name {
+        Some(name) => config.resolve_agent(&name),
+        None => config
+            .resolve_default_agent()?
+            .ok_or_else(|| anyhow::anyhow!("No default agent set; pass --agent")),
+    }
+}
+
 /// Select agent from config, with fallback to interactive selection
 fn select_agent(config: &Config, agent_name: Option<String>) -> Result<Agent> {
     match agent_name {
-        Some(name) => config
-            .get_agent(&name)
-            .cloned()
-            .ok_or_else(|| anyhow::anyhow!("Agent '{}' not found", name)),
+        Some(name) => config.resolve_agent(&name),
         None => {
             // Try default agent first
-            if let Some(agent) = config.get_default_agent() {
-                return Ok(agent.clone());
+            if let Some(agent) = config.resolve_default_agent()? {
+                return Ok(agent);
             }
 
             // Fall back to interactive selection
@@ -121,16 +1389,26 @@ fn select_agent(config: &Config, agent_name: Option<String>) -> Result<Agent> {
     }
 }
 
+/// Resolve a comma-separated list of agent names for compare mode
+fn select_compare_agents(config: &Config, names: &str) -> Result<Vec<Agent>> {
+    let agents = names
+        .split(',')
+        .map(|s| s.trim())
+        .filter(|s| !s.is_empty())
+        .map(|name| config.resolve_agent(name))
+        .collect::<Result<Vec<_>>>()?;
+
+    if agents.is_empty() {
+        return Err(anyhow::anyhow!("--compare requires at least one agent name"));
+    }
+
+    Ok(agents)
+}
+
 /// Select task from config, with fallback to interactive selection
 fn select_task(config: &Config, task_name: Option<String>) -> Result<Option<Task>> {
     match task_name {
-        Some(name) => {
-            config
-                .get_task(&name)
-                .cloned()
-                .map(Some)
-                .ok_or_else(|| anyhow::anyhow!("Task '{}' not found", name))
-        }
+        Some(name) => config.resolve_task(&name).map(Some),
         None => {
             // If no tasks configured, return None (no task)
             if config.tasks.is_empty() {
@@ -151,29 +1429,21 @@ fn interactive_agent_selection(config: &Config) -> Result<Agent> {
         ));
     }
 
-    println!("{}", "\n🤖 Available agents:".cyan().bold());
+    ui_prompt!("{}\n", "\n🤖 Available agents:".cyan().bold());
     let agent_names: Vec<_> = config.agents.keys().cloned().collect();
 
     for (i, name) in agent_names.iter().enumerate() {
         if let Some(agent) = config.get_agent(name) {
-            println!(
-                "  {}. {} - {}",
+            ui_prompt!(
+                "  {}. {} - {}\n",
                 (i + 1).to_string().yellow(),
                 name.cyan(),
-                agent
-                    .system_prompt
-                    .lines()
-                    .next()
-                    .unwrap_or("")
-                    .chars()
-                    .take(50)
-                    .collect::<String>()
-                    .dimmed()
+                ui::preview(&agent.system_prompt, 50).dimmed()
             );
         }
     }
 
-    print!(
+    ui_prompt!(
         "\n{} ",
         format!("Select agent (1-{}):", agent_names.len()).yellow()
     );
@@ -194,33 +1464,22 @@ fn interactive_agent_selection(config: &Config) -> Result<Agent> {
         ));
     }
 
-    let agent_name = &agent_names[choice - 1];
-    config
-        .get_agent(agent_name)
-        .cloned()
-        .ok_or_else(|| anyhow::anyhow!("Agent not found"))
+    config.resolve_agent(&agent_names[choice - 1])
 }
 
 /// Interactive task selection
 fn interactive_task_selection(config: &Config) -> Result<Option<Task>> {
-    println!("{}", "\n📋 Available tasks:".cyan().bold());
+    ui_prompt!("{}\n", "\n📋 Available tasks:".cyan().bold());
     let task_names: Vec<_> = config.tasks.keys().cloned().collect();
 
     // Option 0: No task
-    println!("  {}. {}", "0".yellow(), "(No task - use default)".dimmed());
+    ui_prompt!("  {}. {}\n", "0".yellow(), "(No task - use default)".dimmed());
 
     for (i, name) in task_names.iter().enumerate() {
         if let Some(task) = config.get_task(name) {
-            let template_preview = task
-                .template
-                .lines()
-                .next()
-                .unwrap_or("")
-                .chars()
-                .take(50)
-                .collect::<String>();
-            println!(
-                "  {}. {} - {}",
+            let template_preview = ui::preview(&task.template, 50);
+            ui_prompt!(
+                "  {}. {} - {}\n",
                 (i + 1).to_string().yellow(),
                 name.cyan(),
                 template_preview.dimmed()
@@ -228,7 +1487,7 @@ fn interactive_task_selection(config: &Config) -> Result<Option<Task>> {
         }
     }
 
-    print!(
+    ui_prompt!(
         "\n{} ",
         format!("Select task (0-{}):", task_names.len()).yellow()
     );
@@ -254,181 +1513,1104 @@ fn interactive_task_selection(config: &Config) -> Result<Option<Task>> {
     }
 
     let task_name = &task_names[choice - 1];
-    config
-        .get_task(task_name)
-        .cloned()
-        .map(Some)
-        .ok_or_else(|| anyhow::anyhow!("Task not found"))
+    config.resolve_task(task_name).map(Some)
 }
 
-/// Watch file with signal handling for graceful shutdown
+/// Watch file with signal handling for graceful shutdown: the first Ctrl+C
+/// stops taking new patterns and lets the current generation finish, a
+/// second one restores the pattern being answered and quits at once
 async fn watch_and_process_with_signals(
-    file_path: &PathBuf,
-    claude_agent: ClaudeAgent,
-    watch_interval: u64,
+    session: &mut WatchSession,
+    claude_agents: Vec<ClaudeAgent>,
 ) -> Result<()> {
-    tokio::select! {
-        result = watch_and_process(file_path, claude_agent, watch_interval) => result,
-        _ = signal::ctrl_c() => {
-            println!("\n\n{}", "👋 Stopped watching file. Goodbye!".yellow().bold());
-            Ok(())
-        }
+    agent::shield_from_interrupt();
+    let listener = tokio::spawn(listen_for_shutdown(session.shutdown.clone()));
+    let result = watch_and_process(session, claude_agents).await;
+    listener.abort();
+
+    if session.shutdown.is_requested() {
+        ui_println!("\n{}", tr(Msg::Goodbye).yellow().bold());
     }
+    result
 }
 
-/// Main file watching loop
-async fn watch_and_process(file_path: &PathBuf, mut claude_agent: ClaudeAgent, watch_interval: u64) -> Result<()> {
-    let mut watcher = FileWatcher::with_interval(file_path, watch_interval)?;
-    watcher.watch(file_path)?;
+async fn listen_for_shutdown(shutdown: Arc<Shutdown>) {
+    if signal::ctrl_c().await.is_err() {
+        return;
+    }
+    shutdown.requested.store(true, Ordering::Relaxed);
+    if shutdown.in_flight.lock().unwrap().is_some() {
+        ui_println!(
+            "\n\n{}",
+            "⏳ Finishing the current generation; press Ctrl+C again to force quit".yellow()
+        );
+    }
 
-    loop {
-        // Wait for new patterns
-        let patterns = watcher.wait_for_changes(file_path)?;
+    if signal::ctrl_c().await.is_err() {
+        return;
+    }
+    shutdown.roll_back();
+    // Generations are shielded from the terminal's Ctrl+C, so end them here
+    agent::kill_shielded();
+    ui_println!("\n\n{}", tr(Msg::Goodbye).yellow().bold());
+    std::process::exit(130);
+}
 
-        if !patterns.is_empty() {
-            println!(
-                "\n{}",
-                format!("🔍 Found {} new pattern(s)", patterns.len())
-                    .green()
-                    .bold()
+/// Header lines for the profile, endpoint, key, model, and MCP servers claude will use
+fn show_environment(environment: &GenerationEnv, pinned: bool) {
+    if let Some(ref profile) = environment.profile {
+        let note = if pinned { format!(" (pinned in {})", PROJECT_CONFIG_FILE) } else { String::new() };
+        ui_println!("  🪪 {:<10}{}{}", "Profile:", profile.name.cyan(), note.dimmed());
+    }
+    let endpoint = environment.base_url.as_deref().unwrap_or("Anthropic API (default)");
+    ui_println!("  🌐 {:<10}{}", "Endpoint:", endpoint.cyan());
+    let key = match &environment.credential {
+        Some((name, value)) => format!("{}={}***", name, ui::prefix(value, 8)).cyan(),
+        None => "not set".red(),
+    };
+    ui_println!("  🔑 {:<10}{}", "Key:", key);
+    ui_println!(
+        "  🧠 {:<10}{}",
+        "Model:",
+        environment.model.as_deref().unwrap_or("default").cyan()
+    );
+    if !environment.mcp_servers.is_empty() {
+        ui_println!("  🔌 {:<10}{}", "MCP:", environment.mcp_servers.join(", ").cyan());
+    }
+}
+
+/// Lines typed at the terminal during a session: queue numbers (`3` + Enter)
+/// to answer that pattern next, and answers to prompts. Read on a plain thread
+/// so a pending read never holds up exit.
+pub(crate) struct TerminalInput {
+    lines: mpsc::UnboundedReceiver<String>,
+    /// Queue numbers typed before a prompt, applied after it
+    bumps: Vec<usize>,
+}
+
+impl TerminalInput {
+    fn listen() -> Option<Self> {
+        if !io::stdin().is_terminal() {
+            return None;
+        }
+        let (sender, lines) = mpsc::unbounded_channel();
+        std::thread::spawn(move || {
+            for line in io::stdin().lock().lines() {
+                let Ok(line) = line else { return };
+                if sender.send(line).is_err() {
+                    return;
+                }
+            }
+        });
+        Some(Self { lines, bumps: Vec::new() })
+    }
+
+    /// Keep queue numbers typed so far for `bumps`, dropping anything else
+    fn stash(&mut self) {
+        while let Ok(line) = self.lines.try_recv() {
+            if let Ok(number) = line.trim().trim_start_matches('#').parse() {
+                self.bumps.push(number);
+            }
+        }
+    }
+
+    /// Queue numbers typed since the last call
+    fn bumps(&mut self) -> Vec<usize> {
+        self.stash();
+        std::mem::take(&mut self.bumps)
+    }
+
+    /// Ask a question; the answer trimmed and lowercased, empty if input ended
+    async fn ask(&mut self, question: &str) -> String {
+        self.read_line(question).await.to_lowercase()
+    }
+
+    /// Ask for free text; the answer trimmed, empty if input ended
+    async fn read_line(&mut self, question: &str) -> String {
+        self.stash();
+        ui_prompt!("  {} ", question.yellow());
+        let _ = io::stdout().flush();
+        match self.lines.recv().await {
+            Some(answer) => answer.trim().to_string(),
+            None => String::new(),
+        }
+    }
+
+    /// Ask a yes/no question; anything but `y`/`yes` is no
+    async fn confirm(&mut self, question: &str) -> bool {
+        matches!(self.ask(question).await.as_str(), "y" | "yes")
+    }
+}
+
+/// Move the pattern with queue number `number` to the front; false if it isn't queued
+fn bump(queue: &mut VecDeque<(usize, EccePattern)>, number: usize) -> bool {
+    let Some(idx) = queue.iter().position(|(n, _)| *n == number) else {
+        return false;
+    };
+    let job = queue.remove(idx).expect("index is in range");
+    queue.push_front(job);
+    true
+}
+
+fn show_queue(patterns: &[EccePattern], redactor: &Redactor) {
+    for (number, pattern) in (1..).zip(patterns) {
+        let question = pattern.question();
+        let priority = match pattern.options.priority.unwrap_or_default() {
+            Priority::High => " [high]",
+            Priority::Low => " [low]",
+            Priority::Normal => "",
+        };
+        ui_println!(
+            "  #{}{} {}",
+            number,
+            priority.yellow(),
+            ui::truncate(&redactor.redact(question.lines().next().unwrap_or(question)), 60).dimmed()
+        );
+    }
+    ui_println!("{}", "Type a number and press Enter to answer that pattern next".dimmed());
+}
+
+/// Main file watching loop
+async fn watch_and_process(
+    session: &mut WatchSession,
+    mut claude_agents: Vec<ClaudeAgent>,
+) -> Result<()> {
+    let target = session.target.clone();
+    let mut watcher = FileWatcher::with_interval(&target, session.watch_interval)?;
+    watcher.set_debounce(Duration::from_millis(session.debounce));
+    let format = document_format(session, watcher.current_content());
+    if format.name() != document::Markdown.name() {
+        ui_println!("{}", format!("📄 Reading {} as {}", target, format.name()).dimmed());
+    }
+    watcher.set_pattern_types(document::pattern_types(format, &session.pattern_types));
+    if session.fit_slide.is_some() && !format.fits_slides() {
+        ui_println!("{}", format!("⚠ --fit-slide: {} files have no slides; ignored", format.name()).yellow());
+        session.fit_slide = None;
+    }
+    watcher.set_keywords(&session.keywords)?;
+    watcher.set_dedup(session.dedup);
+    if let Some(ref saved) = session.saved {
+        watcher.restore_processed(saved.processed.clone());
+    }
+    watcher.watch(&target)?;
+    recover_placeholders(&target)?;
+    autosave(session, &claude_agents, &watcher);
+    session.input = TerminalInput::listen();
+
+    // A queue's open items are answered right away; elsewhere only patterns added from now on
+    let mut backlog = match session.queue {
+        true => watcher.unanswered_patterns(),
+        false => Vec::new(),
+    };
+
+    loop {
+        // Wait for new patterns
+        let mut patterns = match std::mem::take(&mut backlog) {
+            backlog if !backlog.is_empty() => backlog,
+            _ => watcher.wait_for_changes_until(&target, &session.shutdown.requested)?,
+        };
+        if session.shutdown.is_requested() {
+            return Ok(());
+        }
+        queue_order(&mut patterns);
+
+        // Safe point to pick up config edits: nothing is generating
+        reload_config(session, &mut claude_agents);
+
+        if !patterns.is_empty() {
+            ui_println!(
+                "\n{}",
+                trf(Msg::FoundPatterns, &[&patterns.len().to_string()])
+                    .green()
+                    .bold()
             );
-            println!("{}", "─".repeat(60).dimmed());
+            ui_println!("{}", "─".repeat(60).dimmed());
+        }
+        let total = patterns.len();
+        if total > 1 && session.input.is_some() {
+            show_queue(&patterns, &session.redactor);
         }
 
-        // Process each pattern
-        for (idx, pattern) in patterns.iter().enumerate() {
-            println!(
-                "\n{} Pattern {}/{}",
+        // Process each pattern, numbered by queue position so they can be bumped
+        let mut queue: VecDeque<(usize, EccePattern)> = (1..).zip(patterns).collect();
+        let mut started = 0;
+        while !queue.is_empty() {
+            if let Some(input) = session.input.as_mut() {
+                for number in input.bumps() {
+                    if bump(&mut queue, number) {
+                        ui_println!("  {}", format!("⏫ #{} is next", number).cyan());
+                    }
+                }
+            }
+            let (number, pattern) = queue.pop_front().expect("queue is not empty");
+            started += 1;
+
+            // Patterns not yet started stay in the file untouched
+            if session.shutdown.is_requested() {
+                return Ok(());
+            }
+            ui_println!(
+                "\n{} Pattern {}/{} (#{})",
                 "▶".cyan(),
-                idx + 1,
-                patterns.len()
+                started,
+                total,
+                number
             );
-            println!("  Type:    {:?}", pattern.pattern_type);
-            println!(
+            ui_println!("  Type:    {:?}", pattern.pattern_type);
+            ui_println!(
                 "  Content: {}",
-                pattern
-                    .content
-                    .lines()
-                    .next()
-                    .unwrap_or(&pattern.content)
-                    .chars()
-                    .take(60)
-                    .collect::<String>()
-                    .cyan()
+                ui::truncate(
+                    &session.redactor.redact(pattern.content.lines().next().unwrap_or(&pattern.content)),
+                    60
+                )
+                .cyan()
             );
 
             // Process the pattern
-            match process_pattern(pattern, &mut claude_agent, file_path, &mut watcher).await {
-                Ok(_) => {
-                    println!("  {}", "✅ Success".green().bold());
+            match process_pattern(&pattern, &mut claude_agents, session, &mut watcher).await {
+                Ok(elapsed) => {
+                    ui_println!(
+                        "  {} ({})",
+                        tr(Msg::Success).green().bold(),
+                        format_elapsed(elapsed)
+                    );
                 }
                 Err(e) => {
-                    println!("  {} {}", "❌ Error:".red().bold(), e);
-                    eprintln!("Failed to process pattern: {}", e);
+                    let message = session.redactor.redact(&format!("{:#}", e));
+                    ui_println!("  {} {}", tr(Msg::Error).red().bold(), message);
+                    ui_eprintln!("Failed to process pattern: {}", message);
                 }
             }
+            session.shutdown.set_in_flight(None);
+            autosave(session, &claude_agents, &watcher);
         }
 
-        if !patterns.is_empty() {
-            println!("\n{}", "─".repeat(60).dimmed());
-            println!("{}", "👀 Continuing to watch...".yellow());
+        if total > 0 {
+            ui_println!("\n{}", "─".repeat(60).dimmed());
+            ui_println!("{}", tr(Msg::ContinueWatching).yellow());
+        }
+    }
+}
+
+/// Record the agents' conversations and the answered patterns for `ecce session save`
+fn autosave(session: &mut WatchSession, agents: &[ClaudeAgent], watcher: &FileWatcher) {
+    let Some(ref mut saved) = session.saved else {
+        return;
+    };
+    saved.agents = agents
+        .iter()
+        .map(|agent| SavedAgent {
+            name: agent.agent_name().to_string(),
+            history: agent.conversation().to_vec(),
+            session_id: agent.session_id().map(str::to_string),
+        })
+        .collect();
+    saved.processed = watcher.processed_keys();
+    saved.saved_at = Utc::now();
+    if let Err(e) = session::autosave(saved) {
+        ui_eprintln!("Warning: Failed to autosave session: {}", e);
+    }
+}
+
+/// Apply config changes made since the last check to the session and agents
+fn reload_config(session: &mut WatchSession, agents: &mut [ClaudeAgent]) {
+    match session.reloader.poll() {
+        None => {}
+        Some(Ok(config)) => apply_config(session, agents, &config),
+        Some(Err(e)) => ui_println!(
+            "{}",
+            format!("⚠ Config changed but failed to load, keeping previous settings: {}", e).yellow()
+        ),
+    }
+}
+
+/// The claude-code profile pinned to a local target's project (`ecce api pin`)
+fn pinned_profile(config: &Config, target: &FileTarget) -> Result<Option<Profile>> {
+    let FileTarget::Local(path) = target else {
+        return Ok(None);
+    };
+    match project_dir(path) {
+        Some(dir) => config.pinned_profile(&dir, CLAUDE_CODE_SERVICE).context(Failure::Config),
+        None => Ok(None),
+    }
+}
+
+/// Name of the claude-code profile generations for a target run against, recorded
+/// in history: the project's pinned profile, else the active one
+fn session_profile(config: &Config, target: &FileTarget) -> Option<Profile> {
+    pinned_profile(config, target)
+        .ok()
+        .flatten()
+        .or_else(|| config.get_active_profile(CLAUDE_CODE_SERVICE))
+}
+
+/// Point agents at the target's pinned profile, if any; returns the session's profile name
+fn use_pinned_profile(config: &Config, target: &FileTarget, agents: &mut [ClaudeAgent]) -> Result<Option<String>> {
+    if let Some(profile) = pinned_profile(config, target)? {
+        for agent in agents.iter_mut() {
+            agent.use_profile(&profile);
         }
     }
+    Ok(session_profile(config, target).map(|p| p.name))
 }
 
-/// Process a single pattern: generate response and replace in file
+fn apply_config(session: &mut WatchSession, agents: &mut [ClaudeAgent], config: &Config) {
+    for agent in agents.iter_mut() {
+        let name = agent.agent_name().to_string();
+        if config.get_agent(&name).is_none() {
+            ui_println!(
+                "{}",
+                format!("⚠ Agent '{}' is no longer in config; keeping its previous definition", name)
+                    .yellow()
+            );
+            continue;
+        }
+        let updated = match config.resolve_agent(&name) {
+            Ok(updated) => updated,
+            Err(e) => {
+                ui_println!(
+                    "{}",
+                    format!("⚠ Agent '{}' can't be loaded ({}); keeping its previous definition", name, e)
+                        .yellow()
+                );
+                continue;
+            }
+        };
+        let task = agent
+            .task()
+            .map(|t| config.resolve_task(&t.name).unwrap_or_else(|_| t.clone()));
+
+        if &updated != agent.agent() || task.as_ref() != agent.task() {
+            agent.reconfigure(updated, task);
+            ui_println!("{}", format!("🔄 Reloaded agent '{}' from config", name).cyan());
+        }
+    }
+
+    let profile = session_profile(config, &session.target);
+    let name = profile.as_ref().map(|p| p.name.clone());
+    if name != session.profile {
+        if let Some(profile) = profile {
+            for agent in agents.iter_mut() {
+                agent.use_profile(&profile);
+            }
+            ui_println!("{}", format!("🔄 Switched to profile '{}'", profile.name).cyan());
+        }
+        session.profile = name;
+    }
+
+    session.redactor = Redactor::from_config(config);
+    session.deliver = config.deliver.clone();
+    match load_guard(config, &session.target) {
+        Ok(guard) => session.guard = guard,
+        Err(e) => ui_println!(
+            "{}",
+            format!("⚠ Invalid guard rules in config, keeping previous ones: {:#}", e).yellow()
+        ),
+    }
+}
+
+/// Process a single pattern, then send the answer or failure to the configured
+/// delivery targets
 async fn process_pattern(
     pattern: &EccePattern,
-    agent: &mut ClaudeAgent,
-    file_path: &PathBuf,
+    agents: &mut Vec<ClaudeAgent>,
+    session: &mut WatchSession,
     watcher: &mut FileWatcher,
-) -> Result<()> {
-    println!("  {}", "🤖 Generating response...".yellow());
+) -> Result<Duration> {
+    // A notebook holds the question JSON-escaped; the agent gets it as written
+    let format = document_format(session, watcher.current_content());
+    let decoded = EccePattern {
+        content: format.decode(&pattern.content).into_owned(),
+        ..pattern.clone()
+    };
+    let pattern = &decoded;
+    let routed = match session.router {
+        Some(ref router) => Some(route_pattern(router, pattern.question(), agents).await),
+        None => None,
+    };
+    let result = match routed {
+        // Only the chosen agent answers, keeping its own conversation
+        Some(index) => {
+            let mut chosen = vec![agents.remove(index)];
+            let result = answer_pattern(pattern, &mut chosen, session, watcher).await;
+            agents.insert(index, chosen.remove(0));
+            result
+        }
+        None => answer_pattern(pattern, agents, session, watcher).await,
+    };
+    if session.deliver.is_empty() {
+        return result.map(|(elapsed, _)| elapsed);
+    }
 
-    // Immediately replace pattern with "generating" message
-    replace_pattern_in_file(file_path, &pattern.content, "🤖 Generating response...")?;
+    let outcome = match result {
+        Ok((_, ref response)) => Outcome::Answered(response.clone()),
+        Err(ref e) => Outcome::Failed(session.redactor.redact(&format!("{:#}", e))),
+    };
+    let delivery = Delivery {
+        file: session.target.to_string(),
+        question: session.redactor.redact(pattern.question()),
+        agent: match routed {
+            Some(index) => agents[index].agent_name().to_string(),
+            None => agents.iter().map(|a| a.agent_name()).collect::<Vec<_>>().join(","),
+        },
+        outcome,
+    };
+    let errors = delivery::deliver(&session.deliver, &delivery).await;
+    for e in &errors {
+        ui_eprintln!("Warning: {:#}", e);
+    }
+    let sent = session.deliver.iter().filter(|t| t.wants(&delivery.outcome)).count() - errors.len();
+    if sent > 0 {
+        ui_println!("  {}", format!("📨 Delivered to {} target(s)", sent).dimmed());
+    }
+    result.map(|(elapsed, _)| elapsed)
+}
 
-    // Update watcher's content to avoid detecting our own change
-    watcher.update_content(file_path)?;
+/// Index of the agent the router picks for a question; if routing fails, the
+/// first agent answers rather than leaving the pattern unanswered
+async fn route_pattern(router: &Router, question: &str, agents: &[ClaudeAgent]) -> usize {
+    let spinner = Spinner::start(format!("{}", "🧭 Routing...".yellow()));
+    let choice = router.route(question).await;
+    spinner.stop();
+    let index = match choice {
+        Ok(name) => agents.iter().position(|a| a.agent_name() == name),
+        Err(e) => {
+            ui_println!("{}", format!("⚠ Routing failed: {:#}", e).yellow());
+            None
+        }
+    };
+    let index = index.unwrap_or(0);
+    ui_println!("{}", format!("🧭 Routed to {}", agents[index].agent_name()).cyan());
+    index
+}
 
-    // Call agent to generate response
-    let response = agent
-        .generate_response(&pattern.content)
-        .await
-        .context("Failed to generate response from Claude API")?;
+/// How the session's file is read and written: a queue if asked for, else by its name and content
+fn document_format(session: &WatchSession, content: &str) -> &'static dyn DocumentHandler {
+    match session.queue {
+        true => &document::Queue,
+        false => document::handler_for(session.target.path(), content),
+    }
+}
 
-    println!("  {}", "📝 Replacing with response...".yellow());
+/// Generate a response and write it where the session's output goes; returns
+/// how long generation took and the (redacted) response
+async fn answer_pattern(
+    pattern: &EccePattern,
+    agents: &mut [ClaudeAgent],
+    session: &mut WatchSession,
+    watcher: &mut FileWatcher,
+) -> Result<(Duration, String)> {
+    // Looked up before the placeholder goes in, so an earlier answer can still be located
+    let reused = match session.reuse {
+        true => earlier_answer(pattern, session, watcher.current_content()).await,
+        false => None,
+    };
+    let target = &session.target;
+    let line = line_number(watcher.current_content(), pattern.start_pos);
+    let format = document_format(session, watcher.current_content());
+    let raw = format.decode(&pattern.raw).into_owned();
+    // Measured before the placeholder goes in; answers written elsewhere aren't on the slide
+    let room = match (session.fit_slide, &session.output) {
+        (Some(per_slide), ResponseOutput::InPlace) => Some((
+            fit::remaining_lines(watcher.current_content(), pattern.start_pos..pattern.end_pos, per_slide),
+            per_slide,
+        )),
+        _ => None,
+    };
 
-    // Replace "generating" message with actual response
-    replace_pattern_in_file(file_path, "🤖 Generating response...", &response)?;
+    let in_place = match session.output {
+        ResponseOutput::InPlace => {
+            // Saved before touching the file, so a crash can always be undone
+            let generation = PendingGeneration::new(target.to_string(), raw.clone());
+            if let Err(e) = pending::add(&generation) {
+                ui_eprintln!("Warning: Failed to record pending generation: {}", e);
+            }
 
-    // Update watcher's content again
-    watcher.update_content(file_path)?;
+            // Immediately replace pattern with "generating" message
+            replace_pattern_in_file(target, format, &raw, &generation.placeholder())?;
+            session
+                .shutdown
+                .set_in_flight(Some((target.clone(), format, generation.clone())));
+
+            // Update watcher's content to avoid detecting our own change
+            watcher.update_content(target)?;
+            Some(generation)
+        }
+        // The pattern stays in the file, so never pick it up again even if generation fails
+        ResponseOutput::Sidecar(_) | ResponseOutput::Stdout => {
+            watcher.mark_processed(pattern);
+            None
+        }
+    };
+
+    let is_reused = reused.is_some();
+    let (elapsed, response, recorded) = match reused {
+        Some(reused) => (Duration::ZERO, reused, Vec::new()),
+        None => {
+            // Call agent(s) to generate response, with a live elapsed-time spinner
+            let spinner = Spinner::start(format!(
+                "{} {}",
+                tr(Msg::Generating).yellow(),
+                describe_agents(agents).dimmed()
+            ));
+            if session.match_style {
+                // Sampled per pattern, so slides written during the session count too
+                let style = DocumentStyle::sample(watcher.current_content()).instruction();
+                for agent in agents.iter_mut() {
+                    agent.set_style(style.clone());
+                }
+            }
+            for agent in agents.iter_mut() {
+                agent.set_fit(room.map(|(lines, _)| fit::instruction(lines)));
+            }
+            let results = generate_all(agents, pattern.question(), &pattern.options).await;
+            let elapsed = spinner.stop();
+            let results = results?;
+            let entries: Vec<HistoryEntry> = agents
+                .iter()
+                .zip(&results)
+                .map(|(agent, (result, elapsed))| record_generation(agent, session, pattern.question(), result, *elapsed))
+                .collect();
+            let recorded: Vec<String> = entries.iter().filter_map(|e| e.id.clone()).collect();
+            if let Some(ref mut history) = session.history {
+                history.extend(entries);
+            }
+
+            let response = if agents.len() == 1 {
+                let (result, _) = results.into_iter().next().expect("one result per agent");
+                result.context("Failed to generate response from Claude API")?
+            } else {
+                let labeled: Vec<(&str, Result<String>)> = agents
+                    .iter()
+                    .map(|a| a.agent_name())
+                    .zip(results.into_iter().map(|(result, _)| result))
+                    .collect();
+                format_comparison(&labeled)?
+            };
+            (elapsed, response, recorded)
+        }
+    };
+
+    // A reused answer was proofread and translated when it was first written
+    let response = match is_reused {
+        true => response,
+        false => translate_answer(session, polish_answer(session, response).await).await,
+    };
+    // Never write secrets into a file
+    let mut response = session.redactor.redact(&response);
+    if session.cite {
+        response = cite_sources(response, agents, session);
+    }
+
+    let violations = session.guard.check(&response);
+    let mut rejected = if violations.is_empty() {
+        Ok(())
+    } else {
+        enforce_guards(&violations, &mut session.input).await
+    };
+    if rejected.is_ok() && session.confirm {
+        rejected = confirm_response(&response, &mut session.input).await;
+    }
+    // A comparison's rating wouldn't say which agent it's for
+    if let (true, [id]) = (session.confirm, recorded.as_slice()) {
+        rate_response(id, &mut session.input, &session.redactor).await;
+    }
+    if let Err(e) = rejected {
+        // Put the question back so it can be rephrased, but don't ask it again as is
+        if let Some(ref generation) = in_place {
+            replace_pattern_in_file(target, format, &generation.placeholder(), &raw)?;
+            session.shutdown.set_in_flight(None);
+            forget_pending(generation);
+            watcher.update_content(target)?;
+        }
+        watcher.mark_processed(pattern);
+        return Err(e);
+    }
+
+    match session.output {
+        ResponseOutput::InPlace => {
+            ui_println!("  {}", tr(Msg::Replacing).yellow());
+
+            // Replace "generating" message with actual response
+            let generation = in_place.as_ref().expect("placeholder written for in-place output");
+            let parts = match room {
+                Some((lines, per_slide)) => fit::split(&response, lines, per_slide),
+                None => vec![response.clone()],
+            };
+            let laid_out = format.lay_out(&parts);
+            if parts.len() > 1 && format.name() == document::Slidev.name() {
+                ui_println!("  {}", format!("📐 Continued on {} more slide(s)", parts.len() - 1).dimmed());
+            }
+            let written = if session.provenance {
+                let agent_names: Vec<&str> = agents.iter().map(|a| a.agent_name()).collect();
+                Provenance::new(
+                    generation.id.clone(),
+                    agent_names.join(","),
+                    agents[0].task_name().map(str::to_string),
+                    pattern.question().to_string(),
+                    &laid_out,
+                )
+                .wrap(&laid_out)
+            } else {
+                laid_out
+            };
+            let written = match pattern.pattern_type {
+                PatternType::Item => document::Queue::answered(&raw, &written),
+                _ => written,
+            };
+            replace_pattern_in_file(target, format, &generation.placeholder(), &written)?;
+            session.shutdown.set_in_flight(None);
+            forget_pending(generation);
+
+            // Update watcher's content again
+            watcher.update_content(target)?;
+        }
+        ResponseOutput::Sidecar(ref answers) => {
+            ui_println!("  {} {}", "📝 Appending answer to".yellow(), answers.to_string().cyan());
+            let entry = format_sidecar_entry(
+                target,
+                line,
+                &session.redactor.redact(pattern.question()),
+                &response,
+                Utc::now(),
+            );
+            answers.append(&entry)?;
+        }
+        ResponseOutput::Stdout => {
+            let question = session.redactor.redact(pattern.question());
+            print!("{}", format_stdout_entry(&question, &response));
+            io::stdout().flush()?;
+        }
+    }
+    // A queue shows what's been answered even when the answers live elsewhere
+    if pattern.pattern_type == PatternType::Item && in_place.is_none() {
+        replace_pattern_in_file(target, format, &raw, &document::Queue::checked(&raw))?;
+        watcher.update_content(target)?;
+    }
+
+    if let Some(ref mut clipboard) = session.clipboard {
+        match clipboard.set_text(response.as_str()) {
+            Ok(()) => ui_println!("  {}", "📋 Copied response to clipboard".dimmed()),
+            Err(e) => ui_eprintln!("Warning: Failed to copy response to clipboard: {}", e),
+        }
+    }
 
     // Mark pattern as processed to avoid reprocessing
-    watcher.mark_processed(&pattern.content);
+    watcher.mark_processed(pattern);
 
-    Ok(())
+    Ok((elapsed, response))
 }
 
-/// Replace a pattern in the file with new content
-fn replace_pattern_in_file(
-    file_path: &PathBuf,
-    old_text: &str,
-    new_text: &str,
+/// With --reuse, the earlier answer to a nearly identical question asked in
+/// this file, or a link to it, to write instead of generating. At a terminal
+/// the user chooses; without one the answer is reused.
+async fn earlier_answer(pattern: &EccePattern, session: &mut WatchSession, content: &str) -> Option<String> {
+    if session.history.is_none() {
+        match history::load() {
+            Ok(entries) => session.history = Some(entries),
+            Err(e) => {
+                ui_eprintln!("Warning: Failed to read history: {}", e);
+                return None;
+            }
+        }
+    }
+    let entries = session.history.as_deref().unwrap_or_default();
+    // History holds questions as redacted when they were asked
+    let question = session.redactor.redact(pattern.question());
+    let earlier = reuse::find(entries, &session.target.to_string(), &question, reuse::THRESHOLD)?;
+    ui_println!(
+        "  {}",
+        format!(
+            "♻ Answered before ({:.0}% alike): \"{}\"",
+            earlier.score * 100.0,
+            ui::truncate(earlier.question(), 60)
+        )
+        .cyan()
+    );
+    let line = reuse::locate(content, earlier.answer());
+    let Some(input) = session.input.as_mut() else {
+        return Some(earlier.answer().to_string());
+    };
+    let choice = match line {
+        Some(_) => "Reuse that answer, link to it, or generate a new one? [R/l/g]",
+        None => "Reuse that answer or generate a new one? [R/g]",
+    };
+    match (input.ask(choice).await.as_str(), line) {
+        ("" | "r" | "reuse", _) => Some(earlier.answer().to_string()),
+        ("l" | "link", Some(line)) => Some(reuse::link(earlier.question(), line)),
+        _ => None,
+    }
+}
+
+/// Turn the response's `[source: ...]` tags into footnotes linking from the
+/// local file it is written to; remote files keep the tags
+fn cite_sources(response: String, agents: &[ClaudeAgent], session: &WatchSession) -> String {
+    let document = match (&session.output, &session.target) {
+        (ResponseOutput::Sidecar(FileTarget::Local(path)), _) => path,
+        (ResponseOutput::Sidecar(_), _) => return response,
+        (_, FileTarget::Local(path)) => path,
+        (_, FileTarget::Remote(_)) => return response,
+    };
+    let files: Vec<String> = agents
+        .iter()
+        .flat_map(|a| a.agent().context_files.iter().cloned())
+        .filter(|f| agent::is_context_file(f))
+        .collect();
+    let cited = citation::footnotes(&response, &files, document);
+    if !cited.unknown.is_empty() {
+        ui_println!(
+            "  {}",
+            format!("⚠ Dropped citations of files not in context: {}", cited.unknown.join(", ")).yellow()
+        );
+    }
+    cited.text
+}
+
+/// Asked before writing a response a guard blocked
+pub(crate) const GUARD_OVERRIDE_QUESTION: &str = "Write this response anyway? [y/N]";
+
+/// Report guard matches; an error if one blocks the response and it isn't
+/// overridden at the terminal
+pub(crate) async fn enforce_guards(violations: &[Violation], input: &mut Option<TerminalInput>) -> Result<()> {
+    let overrides = async {
+        match input.as_mut() {
+            Some(input) => input.confirm(GUARD_OVERRIDE_QUESTION).await,
+            None => false,
+        }
+    };
+    enforce_guards_asking(violations, overrides).await
+}
+
+/// `enforce_guards` for a caller that reads its own input: `overrides` asks
+/// whether to write a blocked response anyway, and only runs if a guard blocks
+pub(crate) async fn enforce_guards_asking(
+    violations: &[Violation],
+    overrides: impl std::future::Future<Output = bool>,
 ) -> Result<()> {
-    // Read the entire file
-    let content = std::fs::read_to_string(file_path)
-        .context("Failed to read file for pattern replacement")?;
+    for violation in violations {
+        let (icon, verb) = match violation.action {
+            GuardAction::Block => ("⛔", "blocked"),
+            GuardAction::Flag => ("⚠", "flagged"),
+        };
+        let message = format!(
+            "{} Guard '{}' {} \"{}\" (response line {})",
+            icon,
+            violation.rule,
+            verb,
+            ui::truncate(&violation.matched, 40),
+            violation.line
+        );
+        match violation.action {
+            GuardAction::Block => ui_println!("  {}", message.red()),
+            GuardAction::Flag => ui_println!("  {}", message.yellow()),
+        }
+    }
+    if !guard::blocks(violations) {
+        return Ok(());
+    }
 
-    let mut new_content = content.clone();
-    let mut replaced = false;
-
-    // Try to find and replace inline pattern: ecce <prompt> ecce
-    let patterns_to_try = vec![
-        format!("ecce {} ecce", old_text),
-        format!("ecce  {}  ecce", old_text),
-        format!("ecce\n{}\necce", old_text),
-        format!("ecce {} ecce", old_text.trim()),
-        format!("ecce  {}  ecce", old_text.trim()),
-        // Also try direct replacement (for replacing "generating" message)
-        old_text.to_string(),
-    ];
-
-    for pattern in &patterns_to_try {
-        if content.contains(pattern) {
-            new_content = content.replace(pattern, new_text);
-            replaced = true;
-            break;
-        }
-    }
-
-    // If inline pattern not found, try code block pattern
-    if !replaced {
-        let block_patterns = vec![
-            format!("```ecce\n{}\n```", old_text),
-            format!("```ecce\n{}\n```", old_text.trim()),
-            format!("```ecce\n  {}\n```", old_text.trim()),
-        ];
+    if overrides.await {
+        ui_println!("  {}", "Overridden; writing the response".yellow());
+        return Ok(());
+    }
+    let rules: Vec<&str> = violations
+        .iter()
+        .filter(|v| v.action == GuardAction::Block)
+        .map(|v| v.rule.as_str())
+        .collect();
+    Err(Failure::Generation.error(format!("Response blocked by guard: {}", rules.join(", "))))
+}
+
+/// Lines of a response shown when asking whether to write it
+const CONFIRM_PREVIEW_LINES: usize = 12;
+
+/// With --confirm at a terminal, show the response and ask before it is written;
+/// an error if it is declined. Without a terminal there's no one to ask.
+async fn confirm_response(response: &str, input: &mut Option<TerminalInput>) -> Result<()> {
+    let Some(input) = input.as_mut() else {
+        return Ok(());
+    };
+    ui_println!("{}", "  ┌─ Response".dimmed());
+    for line in response.lines().take(CONFIRM_PREVIEW_LINES) {
+        ui_println!("  {} {}", "│".dimmed(), line);
+    }
+    let hidden = response.lines().count().saturating_sub(CONFIRM_PREVIEW_LINES);
+    if hidden > 0 {
+        ui_println!("  {} {}", "│".dimmed(), format!("... {} more line(s)", hidden).dimmed());
+    }
+    if input.confirm("Write this response? [y/N]").await {
+        return Ok(());
+    }
+    Err(anyhow::anyhow!("Response discarded; the pattern was left in place"))
+}
 
-        for pattern in &block_patterns {
-            if content.contains(pattern) {
-                new_content = content.replace(pattern, new_text);
-                replaced = true;
-                break;
+/// With --confirm at a terminal, offer a quick rating of the answer, written or
+/// not, kept with its history entry (see `ecce history export --feedback`)
+async fn rate_response(id: &str, input: &mut Option<TerminalInput>, redactor: &Redactor) {
+    let Some(input) = input.as_mut() else {
+        return;
+    };
+    let Some(vote) = Vote::parse(&input.ask("Rate this answer? [+/-, Enter to skip]").await) else {
+        return;
+    };
+    let note = input.read_line("Note (Enter to skip):").await;
+    let feedback = Feedback {
+        vote,
+        note: (!note.is_empty()).then(|| redactor.redact(&note)),
+        timestamp: Utc::now(),
+    };
+    match history::rate(id, feedback) {
+        Ok(()) => ui_println!("  {}", format!("{} Noted in the history", vote.emoji()).dimmed()),
+        Err(e) => ui_eprintln!("Warning: Failed to record feedback: {}", e),
+    }
+}
+
+/// 1-based line number of a byte offset in `content`
+pub fn line_number(content: &str, offset: usize) -> usize {
+    content
+        .get(..offset)
+        .map_or(1, |before| before.matches('\n').count() + 1)
+}
+
+/// An answers-file entry referring back to the pattern's file and line
+fn format_sidecar_entry(
+    source: &FileTarget,
+    line: usize,
+    question: &str,
+    response: &str,
+    timestamp: DateTime<Utc>,
+) -> String {
+    format!(
+        "## {}\n\n> From `{}` line {} · {}\n\n{}\n\n---\n\n",
+        question.lines().next().unwrap_or(question),
+        source,
+        line,
+        timestamp.format("%Y-%m-%d %H:%M UTC"),
+        response.trim()
+    )
+}
+
+/// A response as printed by `--stdout`: the question as a heading, then the answer
+fn format_stdout_entry(question: &str, response: &str) -> String {
+    format!(
+        "## {}\n\n{}\n\n",
+        question.lines().next().unwrap_or(question),
+        response.trim()
+    )
+}
+
+/// Agent names (and models, when set) for the progress line, e.g. "[writer (opus)]"
+fn describe_agents(agents: &[ClaudeAgent]) -> String {
+    let names: Vec<String> = agents
+        .iter()
+        .map(|a| match a.model() {
+            Some(model) => format!("{} ({})", a.agent_name(), model),
+            None => a.agent_name().to_string(),
+        })
+        .collect();
+    format!("[{}]", names.join(", "))
+}
+
+/// Run the prompt through every agent concurrently, returning results in agent order
+pub async fn generate_all(
+    agents: &mut [ClaudeAgent],
+    prompt: &str,
+    options: &PatternOptions,
+) -> Result<Vec<(Result<String>, Duration)>> {
+    let mut set = JoinSet::new();
+    // Each task works on a copy, so an agent whose task panics is left as it was
+    for (idx, agent) in agents.iter().enumerate() {
+        let mut agent = agent.clone();
+        let prompt = prompt.to_string();
+        let options = options.clone();
+        set.spawn(async move {
+            let started = Instant::now();
+            let result = agent.generate(&prompt, &options).await;
+            (idx, agent, result, started.elapsed())
+        });
+    }
+
+    let mut results: Vec<Option<(Result<String>, Duration)>> = agents.iter().map(|_| None).collect();
+    let mut panicked = None;
+    while let Some(joined) = set.join_next().await {
+        match joined {
+            Ok((idx, agent, result, elapsed)) => {
+                agents[idx] = agent;
+                results[idx] = Some((result, elapsed));
+            }
+            Err(e) => {
+                panicked.get_or_insert(e);
             }
         }
     }
+    if let Some(e) = panicked {
+        return Err(anyhow::Error::new(e).context("Generation task panicked"));
+    }
+    Ok(results.into_iter().map(|r| r.expect("every task finished")).collect())
+}
 
-    if !replaced {
-        return Err(anyhow::anyhow!(
-            "Pattern not found in file: '{}'",
-            old_text
-        ));
+/// Combine compare-mode responses under one heading per agent.
+/// Fails only if every agent failed.
+fn format_comparison(results: &[(&str, Result<String>)]) -> Result<String> {
+    if results.iter().all(|(_, r)| r.is_err()) {
+        let errors: Vec<String> = results
+            .iter()
+            .filter_map(|(name, r)| r.as_ref().err().map(|e| format!("{}: {}", name, e)))
+            .collect();
+        return Err(anyhow::anyhow!("All agents failed: {}", errors.join("; ")));
+    }
+
+    let sections: Vec<String> = results
+        .iter()
+        .map(|(name, result)| match result {
+            Ok(response) => format!("### {}\n\n{}", name, response),
+            Err(e) => format!("### {}\n\n_Error: {}_", name, e),
+        })
+        .collect();
+
+    Ok(sections.join("\n\n"))
+}
+
+/// Append a generation to the history log and return the entry; failures only warn
+fn record_generation(
+    agent: &ClaudeAgent,
+    session: &WatchSession,
+    prompt: &str,
+    result: &Result<String>,
+    elapsed: Duration,
+) -> HistoryEntry {
+    let redactor = &session.redactor;
+    let response = result.as_ref().ok().map(|r| redactor.redact(r));
+    let entry = HistoryEntry {
+        id: Some(history::new_id()),
+        timestamp: Utc::now(),
+        file: session.target.to_string(),
+        agent: agent.agent_name().to_string(),
+        task: agent.task_name().map(|t| t.to_string()),
+        profile: session.profile.clone(),
+        prompt_tokens: Some(history::estimate_tokens(prompt)),
+        response_tokens: response.as_deref().map(history::estimate_tokens),
+        prompt: redactor.redact(prompt),
+        response,
+        error: result.as_ref().err().map(|e| redactor.redact(&e.to_string())),
+        duration_ms: elapsed.as_millis() as u64,
+        context: agent.last_context().cloned(),
+        feedback: None,
+    };
+
+    if let Err(e) = history::record(&entry) {
+        ui_eprintln!("Warning: Failed to record history: {}", e);
+    }
+    entry
+}
+
+/// Drop a generation's crash-recovery record once its placeholder is gone
+fn forget_pending(generation: &PendingGeneration) {
+    if let Err(e) = pending::remove(&generation.id) {
+        ui_eprintln!("Warning: Failed to clear pending generation: {}", e);
+    }
+}
+
+/// Offer to put back the patterns behind placeholders left by a session that
+/// died mid-generation. Restored patterns are answered on the next poll.
+fn recover_placeholders(target: &FileTarget) -> Result<()> {
+    let raw = target.read_raw()?;
+    let content = line_endings::normalize(&raw);
+    let placeholders = pending::find_placeholders(&content);
+    if placeholders.is_empty() {
+        return Ok(());
+    }
+
+    let saved = pending::load().unwrap_or_else(|e| {
+        ui_eprintln!("Warning: Failed to load pending generations: {}", e);
+        Vec::new()
+    });
+    let restorable: Vec<(String, PendingGeneration)> = placeholders
+        .iter()
+        .filter_map(|placeholder| {
+            let id = placeholder.id.as_deref()?;
+            let generation = saved.iter().find(|g| g.id == id)?;
+            Some((placeholder.text.clone(), generation.clone()))
+        })
+        .collect();
+
+    ui_prompt!(
+        "\n{}\n",
+        format!(
+            "⚠ {} contains {} unfinished generation{} from a previous session",
+            target,
+            placeholders.len(),
+            if placeholders.len() == 1 { "" } else { "s" }
+        )
+        .yellow()
+    );
+    let unrecoverable = placeholders.len() - restorable.len();
+    if unrecoverable > 0 {
+        ui_prompt!(
+            "  {}\n",
+            format!(
+                "{} placeholder{} ha{} no saved pattern; remove {} by hand",
+                unrecoverable,
+                if unrecoverable == 1 { "" } else { "s" },
+                if unrecoverable == 1 { "s" } else { "ve" },
+                if unrecoverable == 1 { "it" } else { "them" }
+            )
+            .dimmed()
+        );
+    }
+    if restorable.is_empty() {
+        return Ok(());
+    }
+
+    // Without a terminal to ask, restoring is the safe choice
+    if io::stdin().is_terminal() {
+        ui_prompt!("{} ", "Restore the original patterns and answer them again? [Y/n]".yellow());
+        io::stdout().flush()?;
+        let mut input = String::new();
+        io::stdin().read_line(&mut input)?;
+        if matches!(input.trim().to_lowercase().as_str(), "n" | "no") {
+            return Ok(());
+        }
+    }
+
+    let document = document::handler_for(target.path(), &content);
+    let mut restored = content;
+    for (placeholder, generation) in &restorable {
+        restored = restored.replacen(placeholder, &document.encode(&generation.raw), 1);
     }
+    target
+        .write(&raw, &restored)
+        .context("Failed to write file after restoring patterns")?;
+    for (_, generation) in &restorable {
+        forget_pending(generation);
+    }
+    ui_println!(
+        "{}",
+        format!("✓ Restored {} pattern{}", restorable.len(), if restorable.len() == 1 { "" } else { "s" }).green()
+    );
+    Ok(())
+}
+
+/// Replace a pattern in the file with new content, written the way `format` writes it
+fn replace_pattern_in_file(
+    target: &FileTarget,
+    format: &dyn DocumentHandler,
+    old_text: &str,
+    new_text: &str,
+) -> Result<()> {
+    // Read the entire file
+    let raw = target
+        .read_raw()
+        .context("Failed to read file for pattern replacement")?;
+    let content = line_endings::normalize(&raw);
+
+    let new_content = format
+        .replace(&content, old_text, new_text)
+        .ok_or_else(|| anyhow::anyhow!("Pattern not found in file: '{}'", old_text))?;
 
     // Write the modified content back
-    std::fs::write(file_path, new_content)
+    target
+        .write(&raw, &new_content)
         .context("Failed to write file after pattern replacement")?;
 
     Ok(())
@@ -442,14 +2624,360 @@ mod tests {
 
     #[test]
     fn test_replace_pattern_in_file() {
-        let mut temp = NamedTempFile::new().unwrap();
+        let temp = NamedTempFile::new().unwrap();
         let path = PathBuf::from(temp.path());
 
         fs::write(&path, "ecce test prompt ecce").unwrap();
 
-        replace_pattern_in_file(&path, "test prompt", "Generated response").unwrap();
+        replace_pattern_in_file(&path.clone().into(), &document::Markdown, "test prompt", "Generated response").unwrap();
 
         let content = fs::read_to_string(&path).unwrap();
         assert_eq!(content, "Generated response");
     }
+
+    #[test]
+    fn test_replace_pattern_in_notebook() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let path = dir.path().join("analysis.ipynb");
+        fs::write(&path, r#"{"cells": [{"cell_type": "markdown", "source": ["ecce why? ecce"]}]}"#).unwrap();
+
+        replace_pattern_in_file(&path.clone().into(), &document::Notebook, "why?", "Because:\n- \"reasons\"").unwrap();
+
+        let notebook: serde_json::Value = serde_json::from_str(&fs::read_to_string(&path).unwrap()).unwrap();
+        assert_eq!(notebook["cells"][0]["source"][0], "Because:\n- \"reasons\"");
+    }
+
+    #[test]
+    fn test_prompt_reader() {
+        let mut lines = PromptReader::new(None);
+        assert_eq!(lines.push("  What is Rust?  ".to_string()).as_deref(), Some("What is Rust?"));
+        assert_eq!(lines.push("".to_string()), None);
+        assert_eq!(lines.finish(), None);
+
+        let mut blocks = PromptReader::new(Some("---".to_string()));
+        assert_eq!(blocks.push("Explain:".to_string()), None);
+        assert_eq!(blocks.push("  ownership".to_string()), None);
+        assert_eq!(blocks.push("--- ".to_string()).as_deref(), Some("Explain:\n  ownership"));
+        assert_eq!(blocks.push("---".to_string()), None);
+        assert_eq!(blocks.push("Last one".to_string()), None);
+        assert_eq!(blocks.finish().as_deref(), Some("Last one"));
+    }
+
+    #[tokio::test]
+    async fn test_enforce_guards_without_terminal() {
+        let violation = |action| Violation {
+            rule: "codenames".to_string(),
+            action,
+            matched: "bluebird".to_string(),
+            line: 1,
+        };
+        assert!(enforce_guards(&[violation(GuardAction::Flag)], &mut None).await.is_ok());
+
+        let err = enforce_guards(&[violation(GuardAction::Flag), violation(GuardAction::Block)], &mut None)
+            .await
+            .unwrap_err();
+        assert_eq!(err.to_string(), "Response blocked by guard: codenames");
+    }
+
+    #[test]
+    fn test_restored_args_match_original() {
+        #[derive(clap::Parser)]
+        struct Cli {
+            #[command(flatten)]
+            args: HomoArgs,
+        }
+        let args = <Cli as clap::Parser>::parse_from([
+            "homo", "slides.md", "--compare", "a,b", "--observe", "--only", "block", "--dedup", "id",
+            "-k", "ask", "--watch-interval", "250", "--debounce", "400", "--confirm", "--cite", "--polish",
+            "--match-style", "--fit-slide", "16",
+        ])
+        .args;
+
+        let saved = SavedSession {
+            file: "/talks/slides.md".to_string(),
+            agents: ["a", "b"]
+                .map(|name| SavedAgent {
+                    name: name.to_string(),
+                    history: Vec::new(),
+                    session_id: None,
+                })
+                .to_vec(),
+            task: None,
+            preset: None,
+            settings: session_settings(&args),
+            processed: Vec::new(),
+            saved_at: Utc::now(),
+        };
+        let restored = restored_args(&saved);
+        assert_eq!(restored.file_path, Some(PathBuf::from("/talks/slides.md")));
+        assert_eq!((restored.agent.as_deref(), restored.compare.as_deref()), (None, Some("a,b")));
+        assert_eq!(session_settings(&restored), saved.settings);
+        assert_eq!(restored.dedup, DedupStrategy::Marker);
+
+        // A routed session routes again instead of comparing its candidates
+        let routed = SavedSession {
+            settings: SessionSettings { route: true, ..saved.settings.clone() },
+            ..saved
+        };
+        let restored = restored_args(&routed);
+        assert!(restored.route);
+        assert_eq!((restored.agent, restored.compare), (None, None));
+    }
+
+    #[test]
+    fn test_apply_watch_defaults() {
+        #[derive(clap::Parser)]
+        struct Cli {
+            #[command(flatten)]
+            args: HomoArgs,
+        }
+        let parse = |extra: &[&str]| {
+            let argv = ["homo", "slides.md"].iter().chain(extra);
+            <Cli as clap::Parser>::parse_from(argv).args
+        };
+        let defaults = WatchDefaults {
+            interval: Some(500),
+            debounce: Some(300),
+            mode: Some(WatchMode::Stdout),
+            confirm: true,
+            match_style: true,
+            fit_slide: Some(18),
+        };
+
+        let args = apply_watch_defaults(parse(&[]), &defaults);
+        assert_eq!((args.watch_interval, args.debounce), (Some(500), Some(300)));
+        assert!(args.stdout && !args.observe && args.confirm && args.match_style);
+        assert_eq!(args.fit_slide, Some(18));
+
+        // Flags win, and any output flag replaces the default mode
+        let args = apply_watch_defaults(parse(&["--watch-interval", "50", "--debounce", "0", "--observe"]), &defaults);
+        assert_eq!((args.watch_interval, args.debounce), (Some(50), Some(0)));
+        assert!(args.observe && !args.stdout);
+
+        // The command line can turn off what the config turns on
+        let args = apply_watch_defaults(parse(&["--no-confirm", "--no-match-style", "--in-place"]), &defaults);
+        assert!(!args.confirm && !args.match_style);
+        assert!(!args.stdout && !args.observe && args.output.is_none());
+
+        // The last of a flag and its negation wins
+        let args = apply_watch_defaults(parse(&["--no-confirm", "--confirm"]), &WatchDefaults::default());
+        assert!(args.confirm);
+
+        let args = apply_watch_defaults(parse(&[]), &WatchDefaults::default());
+        assert_eq!(session_settings(&args).watch_interval, DEFAULT_WATCH_INTERVAL_MS);
+        assert!(!args.stdout && !args.observe && !args.confirm && !args.match_style);
+    }
+
+    #[test]
+    fn test_preset_model_is_for_one_agent() {
+        #[derive(clap::Parser)]
+        struct Cli {
+            #[command(flatten)]
+            args: HomoArgs,
+        }
+        let parse = |argv: &[&str]| <Cli as clap::Parser>::parse_from(argv).args;
+        let preset = Preset {
+            model: Some("sonnet".to_string()),
+            ..Default::default()
+        };
+        let mut config = Config::default();
+        let model = |argv: &[&str], config: &Config| run_options(&parse(argv), config, &preset).model;
+
+        assert_eq!(model(&["homo", "a.md"], &config).as_deref(), Some("sonnet"));
+        assert_eq!(model(&["homo", "a.md", "--compare", "a,b"], &config), None);
+        assert_eq!(model(&["homo", "a.md", "--route"], &config), None);
+        // A configured router picks per pattern unless an agent is named
+        config.router = Some(Default::default());
+        assert_eq!(model(&["homo", "a.md"], &config), None);
+        assert_eq!(model(&["homo", "a.md", "--agent", "writer"], &config).as_deref(), Some("sonnet"));
+    }
+
+    #[test]
+    fn test_apply_target() {
+        #[derive(clap::Parser)]
+        struct Cli {
+            #[command(flatten)]
+            args: HomoArgs,
+        }
+        let parse = |argv: &[&str]| <Cli as clap::Parser>::parse_from(argv).args;
+        let mut config = Config::default();
+        config.targets.insert(
+            "lecture".to_string(),
+            crate::config::WatchTarget {
+                name: "lecture".to_string(),
+                path: "/talks/rust/slides.md".to_string(),
+                preset: Some("lecture-notes".to_string()),
+                agent: Some("writer".to_string()),
+                task: None,
+            },
+        );
+
+        let args = apply_target(parse(&["homo", "lecture", "--task", "explain"]), &config).unwrap();
+        assert_eq!(args.file_path, Some(PathBuf::from("/talks/rust/slides.md")));
+        assert_eq!(args.preset.as_deref(), Some("lecture-notes"));
+        assert_eq!((args.agent.as_deref(), args.task.as_deref()), (Some("writer"), Some("explain")));
+
+        // Flags win, and compare mode doesn't get the target's single agent
+        let args = apply_target(parse(&["homo", "lecture", "--compare", "a,b", "-p", "other"]), &config).unwrap();
+        assert_eq!((args.agent, args.preset.as_deref()), (None, Some("other")));
+
+        // Anything else, and names written as paths, are left alone
+        let args = apply_target(parse(&["homo", "notes.md"]), &config).unwrap();
+        assert_eq!(args.file_path, Some(PathBuf::from("notes.md")));
+        let args = apply_target(parse(&["homo", "./lecture"]), &config).unwrap();
+        assert_eq!(args.file_path, Some(PathBuf::from("./lecture")));
+        let dir = tempfile::TempDir::new().unwrap();
+        let local = dir.path().join("lecture");
+        std::fs::write(&local, "").unwrap();
+        let args = apply_target(parse(&["homo", local.to_str().unwrap()]), &config).unwrap();
+        assert_eq!(args.file_path, Some(local));
+
+        // An explicit --target never falls back to a file
+        let args = apply_target(parse(&["homo", "--target", "lecture"]), &config).unwrap();
+        assert_eq!(args.file_path, Some(PathBuf::from("/talks/rust/slides.md")));
+        assert!(apply_target(parse(&["homo", "--target", "slides"]), &config).is_err());
+    }
+
+    fn session_with_config(dir: &Path) -> WatchSession {
+        WatchSession {
+            target: FileTarget::from(dir.join("slides.md")),
+            output: ResponseOutput::InPlace,
+            clipboard: None,
+            provenance: false,
+            cite: false,
+            match_style: false,
+            fit_slide: None,
+            queue: false,
+            reuse: false,
+            history: None,
+            polisher: None,
+            translator: None,
+            deliver: Vec::new(),
+            guard: Guard::default(),
+            router: None,
+            input: None,
+            profile: None,
+            watch_interval: 100,
+            debounce: 0,
+            confirm: false,
+            pattern_types: Vec::new(),
+            keywords: Vec::new(),
+            dedup: DedupStrategy::default(),
+            redactor: Redactor::default(),
+            reloader: ConfigReloader::new(dir.join("config.json")),
+            saved: None,
+            shutdown: Arc::default(),
+        }
+    }
+
+    #[test]
+    fn test_config_reloader_detects_changes() {
+        let temp = tempfile::TempDir::new().unwrap();
+        let path = temp.path().join("config.json");
+        fs::write(&path, r#"{"profiles": [], "active_profile": null}"#).unwrap();
+
+        let mut reloader = ConfigReloader::new(path.clone());
+        assert!(reloader.poll().is_none());
+
+        // Force a different mtime regardless of filesystem timestamp granularity
+        let file = fs::File::options().write(true).open(&path).unwrap();
+        file.set_modified(SystemTime::now() + Duration::from_secs(5)).unwrap();
+
+        assert!(reloader.poll().unwrap().is_ok());
+        assert!(reloader.poll().is_none());
+    }
+
+    #[test]
+    fn test_apply_config_updates_agents_and_profile() {
+        let temp = tempfile::TempDir::new().unwrap();
+        let mut session = session_with_config(temp.path());
+        let agent = Agent {
+            name: "writer".to_string(),
+            system_prompt: "old".to_string(),
+            ..Default::default()
+        };
+        let mut agents = vec![ClaudeAgent::new("claude".to_string(), agent.clone(), None)];
+
+        let mut config = Config::default();
+        config.agents.insert(
+            "writer".to_string(),
+            Agent {
+                system_prompt: "new".to_string(),
+                ..agent
+            },
+        );
+        config.profiles.push(crate::config::Profile {
+            name: "relay".to_string(),
+            url: "https://relay.example".to_string(),
+            key: "relay-key-1234567".to_string(),
+            service: "claude-code".to_string(),
+            ..Default::default()
+        });
+        config
+            .active_profiles
+            .insert("claude-code".to_string(), "relay".to_string());
+
+        apply_config(&mut session, &mut agents, &config);
+
+        assert_eq!(agents[0].agent().system_prompt, "new");
+        assert_eq!(session.profile.as_deref(), Some("relay"));
+        assert_eq!(session.redactor.redact("relay-key-1234567"), crate::redact::REDACTED);
+    }
+
+    #[test]
+    fn test_line_number() {
+        let content = "# Title\n\necce q ecce\n";
+        assert_eq!(line_number(content, 0), 1);
+        assert_eq!(line_number(content, content.find("ecce").unwrap()), 3);
+    }
+
+    #[test]
+    fn test_bump_moves_pattern_to_front() {
+        let patterns = PatternDetector::new().detect_patterns("ecce a ecce ecce b ecce ecce c ecce");
+        let mut queue: VecDeque<(usize, EccePattern)> = (1..).zip(patterns).collect();
+
+        assert!(bump(&mut queue, 3));
+        assert!(!bump(&mut queue, 7));
+        let order: Vec<(usize, &str)> = queue.iter().map(|(n, p)| (*n, p.question())).collect();
+        assert_eq!(order, vec![(3, "c"), (1, "a"), (2, "b")]);
+    }
+
+    #[test]
+    fn test_format_sidecar_entry() {
+        use chrono::TimeZone;
+
+        let source = FileTarget::from(PathBuf::from("talks/slides.md"));
+        let timestamp = Utc.with_ymd_and_hms(2025, 5, 1, 9, 30, 0).unwrap();
+        let entry = format_sidecar_entry(&source, 12, "what is rust?", "A language.\n", timestamp);
+
+        assert_eq!(
+            entry,
+            "## what is rust?\n\n> From `talks/slides.md` line 12 · 2025-05-01 09:30 UTC\n\nA language.\n\n---\n\n"
+        );
+    }
+
+    #[test]
+    fn test_format_stdout_entry() {
+        assert_eq!(
+            format_stdout_entry("what is rust?\nand why?", "A language.\n"),
+            "## what is rust?\n\nA language.\n\n"
+        );
+    }
+
+    #[test]
+    fn test_format_comparison() {
+        let results = vec![
+            ("terse", Ok("Short answer".to_string())),
+            ("verbose", Err(anyhow::anyhow!("timed out"))),
+        ];
+
+        let combined = format_comparison(&results).unwrap();
+        assert_eq!(
+            combined,
+            "### terse\n\nShort answer\n\n### verbose\n\n_Error: timed out_"
+        );
+
+        let all_failed = vec![("terse", Err(anyhow::anyhow!("boom")))];
+        assert!(format_comparison(&all_failed).is_err());
+    }
 }
@@ -0,0 +1,400 @@
+use anyhow::{Context, Result};
+use clap::Subcommand;
+use colored::*;
+use serde_json::{json, Value};
+use std::fs;
+use std::io::{self, IsTerminal, Read};
+use std::path::{Path, PathBuf};
+use ecce::{ui_println, ui_result};
+
+use crate::commands::{claude, homo};
+use crate::config::Config;
+use crate::remote::shell_quote;
+use crate::ui;
+
+/// Command Claude Code runs for ecce hook entries; also how they are recognized
+const HOOK_COMMAND: &str = "ecce hooks run";
+
+/// Tools whose edits trigger the PostToolUse hook
+const EDIT_TOOLS_MATCHER: &str = "Edit|Write|MultiEdit";
+
+#[derive(Subcommand)]
+pub enum HooksCommand {
+    /// Register ecce as a Claude Code hook so files Claude edits get their patterns answered
+    Install {
+        /// Hook event: PostToolUse (after each file edit) or Stop (when Claude finishes a turn)
+        #[arg(short, long, default_value = "PostToolUse")]
+        event: HookEvent,
+        /// Agent to answer with (default: the default agent)
+        #[arg(short, long)]
+        agent: Option<String>,
+        /// Task template to use
+        #[arg(short, long)]
+        task: Option<String>,
+        /// Files to process on each run (required for Stop, which doesn't name edited files)
+        #[arg(short, long)]
+        file: Vec<PathBuf>,
+        /// Write to ~/.claude/settings.json instead of .claude/settings.json in this project
+        #[arg(long)]
+        global: bool,
+    },
+    /// Remove ecce hook entries
+    Remove {
+        /// Only remove hooks for this event
+        #[arg(short, long)]
+        event: Option<HookEvent>,
+        /// Edit ~/.claude/settings.json instead of the project settings
+        #[arg(long)]
+        global: bool,
+    },
+    /// List ecce hook entries
+    #[command(alias = "ls")]
+    List {
+        /// Read ~/.claude/settings.json instead of the project settings
+        #[arg(long)]
+        global: bool,
+    },
+    /// Process patterns once; invoked by Claude Code with the hook payload on stdin
+    #[command(hide = true)]
+    Run {
+        #[arg(short, long)]
+        agent: Option<String>,
+        #[arg(short, long)]
+        task: Option<String>,
+        #[arg(short, long)]
+        file: Vec<PathBuf>,
+    },
+}
+
+/// Claude Code hook events ecce can register for
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum HookEvent {
+    PostToolUse,
+    Stop,
+}
+
+impl HookEvent {
+    fn as_str(&self) -> &'static str {
+        match self {
+            HookEvent::PostToolUse => "PostToolUse",
+            HookEvent::Stop => "Stop",
+        }
+    }
+}
+
+impl std::str::FromStr for HookEvent {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_lowercase().replace(['-', '_'], "").as_str() {
+            "posttooluse" => Ok(HookEvent::PostToolUse),
+            "stop" => Ok(HookEvent::Stop),
+            _ => Err(anyhow::anyhow!("Unknown hook event '{}'. Expected PostToolUse or Stop", s)),
+        }
+    }
+}
+
+pub async fn handle_hooks_command(command: HooksCommand, config: &Config) -> Result<()> {
+    match command {
+        HooksCommand::Install {
+            event,
+            agent,
+            task,
+            file,
+            global,
+        } => {
+            if event == HookEvent::Stop && file.is_empty() {
+                return Err(anyhow::anyhow!(
+                    "Stop hooks don't know which files changed; pass the files to process with --file"
+                ));
+            }
+            if let Some(ref name) = agent {
+                config
+                    .get_agent(name)
+                    .ok_or_else(|| anyhow::anyhow!("Agent '{}' not found", name))?;
+            }
+            if let Some(ref name) = task {
+                config.resolve_task(name)?;
+            }
+
+            let path = settings_path(global)?;
+            let mut settings = load_settings(&path)?;
+            let command = hook_command(agent.as_deref(), task.as_deref(), &file);
+            add_hook(&mut settings, event, &command);
+            save_settings(&path, &settings)?;
+
+            ui_println!("{} Installed {} hook in {}", "✓".green(), event.as_str(), path.display());
+            ui_println!("  {}", command.dimmed());
+            ui_println!("\n{}", "Restart Claude Code to load the hook.".cyan());
+        }
+        HooksCommand::Remove { event, global } => {
+            let path = settings_path(global)?;
+            let mut settings = load_settings(&path)?;
+            let removed = remove_hooks(&mut settings, event);
+            if removed == 0 {
+                ui_println!("{} No ecce hooks found in {}", "!".yellow(), path.display());
+            } else {
+                save_settings(&path, &settings)?;
+                ui_println!("{} Removed {} ecce hook(s) from {}", "✓".green(), removed, path.display());
+            }
+        }
+        HooksCommand::List { global } => {
+            let path = settings_path(global)?;
+            let hooks = ecce_hooks(&load_settings(&path)?);
+            if hooks.is_empty() {
+                ui_result!("{}", format!("No ecce hooks in {}", path.display()).yellow());
+                ui_result!("Use 'ecce hooks install' to add one.");
+            } else {
+                ui_result!("{}", format!("ecce hooks in {}:", path.display()).bold());
+                for (event, command) in hooks {
+                    ui_result!("  {:<12} {}", event.cyan(), command);
+                }
+            }
+        }
+        HooksCommand::Run { agent, task, file } => {
+            // Claude Code may parse hook stdout, so keep it clean
+            ui::status_to_stderr();
+
+            let files = if file.is_empty() {
+                let mut payload = String::new();
+                if !io::stdin().is_terminal() {
+                    io::stdin().read_to_string(&mut payload)?;
+                }
+                edited_files(&payload)?
+            } else {
+                file
+            };
+
+            for path in files.iter().filter(|p| p.is_file()) {
+                homo::process_once(path, config, agent.clone(), task.clone()).await?;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// `.claude/settings.json` in the current project, or `~/.claude/settings.json` with `global`
+pub fn settings_path(global: bool) -> Result<PathBuf> {
+    let base = if global {
+        dirs::home_dir().context("Could not find home directory")?
+    } else {
+        std::env::current_dir().context("Could not get current directory")?
+    };
+    Ok(base.join(".claude").join("settings.json"))
+}
+
+pub fn load_settings(path: &Path) -> Result<Value> {
+    if path.exists() {
+        let content = fs::read_to_string(path)
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+        serde_json::from_str(&content).with_context(|| format!("Failed to parse {}", path.display()))
+    } else {
+        Ok(json!({}))
+    }
+}
+
+fn save_settings(path: &Path, settings: &Value) -> Result<()> {
+    claude::save_with_backup(path, settings).map(|_| ())
+}
+
+/// The `ecce hooks run ...` command line for a hook entry
+fn hook_command(agent: Option<&str>, task: Option<&str>, files: &[PathBuf]) -> String {
+    let mut command = HOOK_COMMAND.to_string();
+    if let Some(agent) = agent {
+        command.push_str(&format!(" --agent {}", shell_quote(agent)));
+    }
+    if let Some(task) = task {
+        command.push_str(&format!(" --task {}", shell_quote(task)));
+    }
+    for file in files {
+        command.push_str(&format!(" --file {}", shell_quote(&file.to_string_lossy())));
+    }
+    command
+}
+
+fn is_ecce_hook(hook: &Value) -> bool {
+    hook.get("command")
+        .and_then(|c| c.as_str())
+        .is_some_and(|c| c.starts_with(HOOK_COMMAND))
+}
+
+/// Add a hook entry for `event`, replacing any ecce entry already registered for it
+fn add_hook(settings: &mut Value, event: HookEvent, command: &str) {
+    remove_hooks(settings, Some(event));
+
+    let mut group = json!({
+        "hooks": [{ "type": "command", "command": command }]
+    });
+    if event == HookEvent::PostToolUse {
+        group["matcher"] = json!(EDIT_TOOLS_MATCHER);
+    }
+
+    if !settings["hooks"].is_object() {
+        settings["hooks"] = json!({});
+    }
+    if !settings["hooks"][event.as_str()].is_array() {
+        settings["hooks"][event.as_str()] = json!([]);
+    }
+    if let Some(groups) = settings["hooks"][event.as_str()].as_array_mut() {
+        groups.push(group);
+    }
+}
+
+/// Remove ecce hook entries (for one event or all), dropping groups and events
+/// left empty. Other hooks are untouched. Returns how many entries were removed.
+fn remove_hooks(settings: &mut Value, event: Option<HookEvent>) -> usize {
+    let Some(events) = settings.get_mut("hooks").and_then(|h| h.as_object_mut()) else {
+        return 0;
+    };
+
+    let mut removed = 0;
+    for (name, groups) in events.iter_mut() {
+        if event.is_some_and(|e| e.as_str() != name) {
+            continue;
+        }
+        let Some(groups) = groups.as_array_mut() else {
+            continue;
+        };
+        for group in groups.iter_mut() {
+            if let Some(hooks) = group.get_mut("hooks").and_then(|h| h.as_array_mut()) {
+                let before = hooks.len();
+                hooks.retain(|hook| !is_ecce_hook(hook));
+                removed += before - hooks.len();
+            }
+        }
+        groups.retain(|group| {
+            group
+                .get("hooks")
+                .and_then(|h| h.as_array())
+                .is_none_or(|hooks| !hooks.is_empty())
+        });
+    }
+    events.retain(|_, groups| groups.as_array().is_none_or(|g| !g.is_empty()));
+
+    if events.is_empty() {
+        if let Some(obj) = settings.as_object_mut() {
+            obj.remove("hooks");
+        }
+    }
+    removed
+}
+
+/// (event, command) for every ecce hook entry
+fn ecce_hooks(settings: &Value) -> Vec<(String, String)> {
+    let mut found = Vec::new();
+    let Some(events) = settings.get("hooks").and_then(|h| h.as_object()) else {
+        return found;
+    };
+    for (event, groups) in events {
+        for group in groups.as_array().into_iter().flatten() {
+            for hook in group["hooks"].as_array().into_iter().flatten() {
+                if is_ecce_hook(hook) {
+                    found.push((event.clone(), hook["command"].as_str().unwrap_or_default().to_string()));
+                }
+            }
+        }
+    }
+    found
+}
+
+/// Files named in a Claude Code hook payload: the edited file of a PostToolUse
+/// event, resolved against the session's working directory
+fn edited_files(payload: &str) -> Result<Vec<PathBuf>> {
+    if payload.trim().is_empty() {
+        return Ok(Vec::new());
+    }
+    let payload: Value = serde_json::from_str(payload).context("Invalid hook payload")?;
+
+    let Some(file) = payload["tool_input"]["file_path"].as_str() else {
+        return Ok(Vec::new());
+    };
+    let path = PathBuf::from(file);
+    match payload["cwd"].as_str() {
+        Some(cwd) if path.is_relative() => Ok(vec![Path::new(cwd).join(path)]),
+        _ => Ok(vec![path]),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_add_and_remove_hooks_keeps_other_entries() {
+        let mut settings = json!({
+            "model": "opus",
+            "hooks": {
+                "PostToolUse": [{
+                    "matcher": "Bash",
+                    "hooks": [{ "type": "command", "command": "./lint.sh" }]
+                }]
+            }
+        });
+
+        add_hook(&mut settings, HookEvent::PostToolUse, "ecce hooks run");
+        add_hook(&mut settings, HookEvent::PostToolUse, "ecce hooks run --agent 'w'");
+        add_hook(&mut settings, HookEvent::Stop, "ecce hooks run --file 'slides.md'");
+
+        let hooks = ecce_hooks(&settings);
+        assert_eq!(hooks.len(), 2);
+        assert!(hooks.contains(&("PostToolUse".to_string(), "ecce hooks run --agent 'w'".to_string())));
+        assert_eq!(settings["hooks"]["PostToolUse"][1]["matcher"], EDIT_TOOLS_MATCHER);
+        assert!(settings["hooks"]["Stop"][0].get("matcher").is_none());
+
+        assert_eq!(remove_hooks(&mut settings, Some(HookEvent::Stop)), 1);
+        assert!(settings["hooks"].get("Stop").is_none());
+        assert_eq!(remove_hooks(&mut settings, None), 1);
+        assert_eq!(
+            settings,
+            json!({
+                "model": "opus",
+                "hooks": {
+                    "PostToolUse": [{
+                        "matcher": "Bash",
+                        "hooks": [{ "type": "command", "command": "./lint.sh" }]
+                    }]
+                }
+            })
+        );
+    }
+
+    #[test]
+    fn test_remove_last_hook_drops_hooks_key() {
+        let mut settings = json!({});
+        add_hook(&mut settings, HookEvent::PostToolUse, HOOK_COMMAND);
+        assert_eq!(remove_hooks(&mut settings, None), 1);
+        assert_eq!(settings, json!({}));
+        assert_eq!(remove_hooks(&mut settings, None), 0);
+    }
+
+    #[test]
+    fn test_hook_command() {
+        assert_eq!(hook_command(None, None, &[]), "ecce hooks run");
+        assert_eq!(
+            hook_command(Some("writer"), Some("lecture"), &[PathBuf::from("my slides.md")]),
+            "ecce hooks run --agent 'writer' --task 'lecture' --file 'my slides.md'"
+        );
+    }
+
+    #[test]
+    fn test_edited_files_from_payload() {
+        let payload = r#"{"cwd": "/talks", "hook_event_name": "PostToolUse",
+            "tool_name": "Edit", "tool_input": {"file_path": "slides.md"}}"#;
+        assert_eq!(edited_files(payload).unwrap(), vec![PathBuf::from("/talks/slides.md")]);
+
+        let absolute = r#"{"tool_input": {"file_path": "/tmp/a.md"}}"#;
+        assert_eq!(edited_files(absolute).unwrap(), vec![PathBuf::from("/tmp/a.md")]);
+
+        assert!(edited_files(r#"{"hook_event_name": "Stop"}"#).unwrap().is_empty());
+        assert!(edited_files("").unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_parse_hook_event() {
+        assert_eq!("PostToolUse".parse::<HookEvent>().unwrap(), HookEvent::PostToolUse);
+        assert_eq!("post-tool-use".parse::<HookEvent>().unwrap(), HookEvent::PostToolUse);
+        assert_eq!("stop".parse::<HookEvent>().unwrap(), HookEvent::Stop);
+        assert!("PreToolUse".parse::<HookEvent>().is_err());
+    }
+}
@@ -0,0 +1,651 @@
+use anyhow::{Context, Result};
+use clap::Subcommand;
+use colored::Colorize;
+use serde_json::{json, Value};
+use std::fs;
+use std::io::{self, IsTerminal, Write};
+use std::path::{Path, PathBuf};
+use ecce::{ui_println, ui_prompt, ui_result};
+
+use crate::commands::claude;
+use crate::config::{mcp_transport, Config, McpServer};
+use crate::environment::claude_project_root;
+use crate::redact::Redactor;
+
+mod run;
+mod serve;
+
+#[derive(Subcommand)]
+pub enum McpCommand {
+    /// Add an MCP server to ecce config
+    Add {
+        /// Server name
+        name: String,
+        /// Server configuration as JSON (e.g., '{"command": "bun", "args": ["run", "server.ts"]}')
+        #[arg(required_unless_present = "url", conflicts_with_all = ["url", "transport", "headers"])]
+        json: Option<String>,
+        /// Transport of a remote server given with --url (default: http)
+        #[arg(long, requires = "url", value_parser = ["sse", "http"])]
+        transport: Option<String>,
+        /// URL of a remote server, instead of JSON
+        #[arg(long)]
+        url: Option<String>,
+        /// HTTP header sent to a remote server (KEY=VALUE, repeatable)
+        #[arg(long = "header", requires = "url", value_parser = parse_header)]
+        headers: Vec<(String, String)>,
+    },
+    /// Remove an MCP server from ecce config
+    Remove {
+        /// Server name to remove
+        name: String,
+    },
+    /// List all MCP servers in ecce config
+    List,
+    /// Install an MCP server to ~/.claude.json (local project or --global)
+    Install {
+        /// Server name to install
+        name: String,
+        /// Install globally to ~/.claude.json mcpServers instead of project-specific
+        #[arg(long, short)]
+        global: bool,
+        /// Show the change to ~/.claude.json without writing it
+        #[arg(long)]
+        dry_run: bool,
+    },
+    /// Uninstall an MCP server from ~/.claude.json (local project or --global)
+    Uninstall {
+        /// Server name to uninstall
+        name: String,
+        /// Uninstall from global ~/.claude.json mcpServers instead of project-specific
+        #[arg(long, short)]
+        global: bool,
+        /// Show the change to ~/.claude.json without writing it
+        #[arg(long)]
+        dry_run: bool,
+    },
+    /// Show MCP servers status
+    Status,
+    /// Merge ~/.claude.json entries made from this project's subdirectories
+    /// into the entry for the project root
+    MergeProjects {
+        /// Merge without asking
+        #[arg(long, short)]
+        yes: bool,
+    },
+    /// Build ecce's MCP server
+    Build,
+    /// Launch a stdio server locally, go through Claude Code's handshake, and
+    /// print its JSON-RPC traffic and stderr, to see why it fails
+    Run {
+        /// Server name, from ecce config or ~/.claude.json
+        name: String,
+        /// Keep running after the handshake: send JSON-RPC lines typed on stdin
+        /// and show everything the server writes until Ctrl+C
+        #[arg(long)]
+        tail: bool,
+    },
+    /// Run ecce's native MCP server on stdio, exposing ecce_run_agent,
+    /// ecce_list_agents, and ecce_apply_profile to Claude Code
+    Serve,
+}
+
+fn get_mcp_server_path() -> Result<PathBuf> {
+    let cwd = std::env::current_dir()?;
+    let workspace_mcp = cwd.join("mcp-server");
+    if workspace_mcp.exists() {
+        return Ok(workspace_mcp);
+    }
+
+    let exe_path = std::env::current_exe().context("Could not get executable path")?;
+    let mut possible_paths = vec![];
+
+    let mut current = exe_path.parent();
+    while let Some(dir) = current {
+        let mcp_path = dir.join("mcp-server");
+        if mcp_path.exists() {
+            possible_paths.push(mcp_path);
+        }
+        current = dir.parent();
+    }
+
+    if let Some(home) = dirs::home_dir() {
+        possible_paths.push(home.join(".ecce").join("mcp-server"));
+    }
+
+    for path in &possible_paths {
+        if path.join("dist").join("index.js").exists() {
+            return Ok(path.clone());
+        }
+    }
+
+    for path in &possible_paths {
+        if path.exists() {
+            return Ok(path.clone());
+        }
+    }
+
+    Ok(workspace_mcp)
+}
+
+pub async fn handle_mcp_command(command: McpCommand, config: &mut Config) -> Result<()> {
+    match command {
+        McpCommand::Add { name, json, transport, url, headers } => {
+            let server_config = match (json, url) {
+                (Some(json), _) => serde_json::from_str(&json).context(
+                    "Invalid JSON. Example: '{\"command\": \"bun\", \"args\": [\"run\", \"server.ts\"]}'",
+                )?,
+                (None, Some(url)) => remote_config(transport.as_deref().unwrap_or("http"), &url, &headers),
+                (None, None) => unreachable!("clap requires json or --url"),
+            };
+            add_mcp(config, name, server_config)
+        }
+        McpCommand::Remove { name } => remove_mcp(config, name),
+        McpCommand::List => list_mcp(config),
+        McpCommand::Install { name, global, dry_run } => install_mcp(config, name, global, dry_run),
+        McpCommand::Uninstall { name, global, dry_run } => uninstall_mcp(config, name, global, dry_run),
+        McpCommand::Status => show_status(config),
+        McpCommand::MergeProjects { yes } => merge_projects_command(config, yes),
+        McpCommand::Build => build_mcp(),
+        McpCommand::Run { name, tail } => {
+            let server_config = find_server_config(config, &name)?;
+            match run::run(&name, &server_config, tail).await? {
+                Some(code) if code != 0 => Err(anyhow::anyhow!("MCP server '{}' exited with code {}", name, code)),
+                _ => Ok(()),
+            }
+        }
+        McpCommand::Serve => serve::serve(config).await,
+    }
+}
+
+/// Parse a KEY=VALUE header argument
+fn parse_header(s: &str) -> Result<(String, String)> {
+    let (key, value) = s
+        .split_once('=')
+        .ok_or_else(|| anyhow::anyhow!("Expected KEY=VALUE, got '{}'", s))?;
+    if key.trim().is_empty() {
+        return Err(anyhow::anyhow!("Header name cannot be empty"));
+    }
+    Ok((key.trim().to_string(), value.trim().to_string()))
+}
+
+/// Config for a remote server, as Claude Code expects it
+fn remote_config(transport: &str, url: &str, headers: &[(String, String)]) -> Value {
+    let mut config = json!({ "type": transport, "url": url });
+    if !headers.is_empty() {
+        config["headers"] = headers.iter().map(|(k, v)| (k.clone(), json!(v))).collect();
+    }
+    config
+}
+
+fn add_mcp(config: &mut Config, name: String, server_config: Value) -> Result<()> {
+    let server = McpServer {
+        name: name.clone(),
+        config: server_config,
+    };
+    let warnings = server
+        .validate()
+        .with_context(|| format!("Invalid config for MCP server '{}'", name))?;
+    for warning in warnings {
+        ui_println!("{} {}", "!".yellow(), warning);
+    }
+
+    config.add_mcp_server(server)?;
+    ui_println!("{} Added MCP server '{}'", "✓".green(), name);
+    ui_println!("  Run 'ecce mcp install {}' to install it to Claude Code", name);
+
+    Ok(())
+}
+
+fn remove_mcp(config: &mut Config, name: String) -> Result<()> {
+    if config.delete_mcp_server(&name)? {
+        ui_println!("{} Removed MCP server '{}'", "✓".green(), name);
+    } else {
+        ui_println!("{} MCP server '{}' not found", "!".yellow(), name);
+    }
+    Ok(())
+}
+
+fn list_mcp(config: &Config) -> Result<()> {
+    if config.mcp_servers.is_empty() {
+        ui_result!("{}", "No MCP servers configured.".yellow());
+        ui_result!("Use 'ecce mcp add <name> <json>' to add one.");
+        return Ok(());
+    }
+
+    ui_result!("{}", "MCP Servers in ecce config:".bold());
+    let mut servers: Vec<&McpServer> = config.mcp_servers.values().collect();
+    servers.sort_by(|a, b| a.name.cmp(&b.name));
+    for server in servers {
+        ui_result!("\n  {} {}", server.name.cyan(), format!("({})", server.transport()).dimmed());
+        ui_result!("    {}", serde_json::to_string_pretty(&server.config)?
+            .lines()
+            .collect::<Vec<_>>()
+            .join("\n    "));
+    }
+
+    Ok(())
+}
+
+fn get_claude_json_path() -> Result<PathBuf> {
+    let home = dirs::home_dir().context("Could not find home directory")?;
+    Ok(home.join(".claude.json"))
+}
+
+fn load_claude_json() -> Result<Value> {
+    let path = get_claude_json_path()?;
+    if path.exists() {
+        let content = fs::read_to_string(&path).context("Failed to read ~/.claude.json")?;
+        serde_json::from_str(&content).context("Failed to parse ~/.claude.json")
+    } else {
+        Ok(json!({ "projects": {} }))
+    }
+}
+
+/// Show the diff against the file as loaded and write it unless `dry_run`;
+/// real writes are backed up first (`ecce claude restore --claude-json` undoes them)
+fn save_claude_json(config: &Config, before: &Value, after: &Value, dry_run: bool) -> Result<bool> {
+    // Diffs can show MCP server env values, so mask anything that looks like a secret
+    let redactor = Redactor::from_config(config);
+    claude::write_changes(&get_claude_json_path()?, before, after, dry_run, &redactor)
+}
+
+/// The `projects` key for the current directory: its project root, so running
+/// from a subdirectory doesn't create a second entry
+fn get_current_project_path() -> Result<String> {
+    let cwd = std::env::current_dir().context("Could not get current directory")?;
+    let root = claude_project_root(&cwd, dirs::home_dir().as_deref());
+    Ok(root.to_string_lossy().to_string())
+}
+
+/// Other `projects` keys with MCP servers that resolve to `root`: entries made
+/// from a subdirectory, with a trailing slash, or through a symlink
+fn duplicate_projects(claude_json: &Value, root: &str, home: Option<&Path>) -> Vec<String> {
+    let Some(projects) = claude_json.get("projects").and_then(Value::as_object) else {
+        return Vec::new();
+    };
+    projects
+        .iter()
+        .filter(|(key, project)| {
+            key.as_str() != root
+                && project["mcpServers"].as_object().is_some_and(|servers| !servers.is_empty())
+                && claude_project_root(Path::new(key), home) == Path::new(root)
+        })
+        .map(|(key, _)| key.clone())
+        .collect()
+}
+
+/// Move the duplicates' MCP servers into the root's entry, leaving the rest of
+/// their Claude Code state alone; entries left empty are removed. Where the same
+/// server name differs, the root's (or the first duplicate's) version is kept
+/// and the name is returned.
+fn merge_projects(claude_json: &mut Value, root: &str, duplicates: &[String]) -> Vec<String> {
+    if claude_json.get("projects").is_none() {
+        claude_json["projects"] = json!({});
+    }
+    let mut conflicts = Vec::new();
+    for key in duplicates {
+        let Some(project) = claude_json["projects"].get_mut(key).and_then(Value::as_object_mut) else {
+            continue;
+        };
+        let servers = project.remove("mcpServers");
+        if project.is_empty() {
+            claude_json["projects"].as_object_mut().expect("projects is an object").remove(key);
+        }
+        let Some(Value::Object(servers)) = servers else {
+            continue;
+        };
+
+        let target = &mut claude_json["projects"][root]["mcpServers"];
+        if !target.is_object() {
+            *target = json!({});
+        }
+        let target = target.as_object_mut().expect("just made an object");
+        for (name, server) in servers {
+            match target.get(&name) {
+                Some(existing) if *existing != server => conflicts.push(format!("{} (from {})", name, key)),
+                Some(_) => {}
+                None => {
+                    target.insert(name, server);
+                }
+            }
+        }
+    }
+    conflicts
+}
+
+/// Report duplicated entries for `root` and merge them if the user agrees
+/// (`yes`), or asks when attached to a terminal. Returns whether anything
+/// was merged; the caller saves.
+fn offer_merge(claude_json: &mut Value, root: &str, yes: bool) -> Result<bool> {
+    let duplicates = duplicate_projects(claude_json, root, dirs::home_dir().as_deref());
+    if duplicates.is_empty() {
+        return Ok(false);
+    }
+    ui_println!(
+        "{} ~/.claude.json has MCP servers for {} under other paths of the same project:",
+        "!".yellow(),
+        root
+    );
+    for key in &duplicates {
+        ui_println!("  {} ({})", key, servers_list(&claude_json["projects"][key]["mcpServers"]));
+    }
+
+    if !yes {
+        if !io::stdin().is_terminal() {
+            ui_println!("  Run 'ecce mcp merge-projects' from the project to merge them");
+            return Ok(false);
+        }
+        ui_prompt!("{} ", "Merge them into the project root entry? [y/N]".yellow());
+        io::stdout().flush()?;
+        let mut input = String::new();
+        io::stdin().read_line(&mut input)?;
+        if !matches!(input.trim().to_lowercase().as_str(), "y" | "yes") {
+            return Ok(false);
+        }
+    }
+
+    for conflict in merge_projects(claude_json, root, &duplicates) {
+        ui_println!("  {} Kept the project root's '{}'; the other differs", "!".yellow(), conflict);
+    }
+    ui_println!("{} Merged {} entr{} into {}", "✓".green(), duplicates.len(), if duplicates.len() == 1 { "y" } else { "ies" }, root);
+    Ok(true)
+}
+
+fn servers_list(servers: &Value) -> String {
+    servers
+        .as_object()
+        .map(|s| s.keys().cloned().collect::<Vec<_>>().join(", "))
+        .unwrap_or_default()
+}
+
+fn merge_projects_command(config: &Config, yes: bool) -> Result<()> {
+    let before = load_claude_json()?;
+    let mut claude_json = before.clone();
+    let project_path = get_current_project_path()?;
+    if offer_merge(&mut claude_json, &project_path, yes)? {
+        if save_claude_json(config, &before, &claude_json, false)? {
+            ui_println!("\n{}", "Restart Claude Code to apply changes.".cyan());
+        }
+    } else if duplicate_projects(&claude_json, &project_path, dirs::home_dir().as_deref()).is_empty() {
+        ui_println!("{} No duplicated entries for {}", "✓".green(), project_path);
+    }
+    Ok(())
+}
+
+/// A server's config from ecce config, else as installed in ~/.claude.json
+/// for this project or globally
+fn find_server_config(config: &Config, name: &str) -> Result<Value> {
+    if let Some(server) = config.get_mcp_server(name) {
+        return Ok(server.config.clone());
+    }
+    let claude_json = load_claude_json()?;
+    let project = &claude_json["projects"][get_current_project_path()?]["mcpServers"][name];
+    let global = &claude_json["mcpServers"][name];
+    let found = [project, global].into_iter().find(|server| server.is_object()).cloned();
+    found.with_context(|| format!("MCP server '{}' not found in ecce config or ~/.claude.json", name))
+}
+
+fn install_mcp(config: &Config, name: String, global: bool, dry_run: bool) -> Result<()> {
+    let server = config.get_mcp_server(&name)
+        .context(format!("MCP server '{}' not found in ecce config", name))?;
+
+    let before = load_claude_json()?;
+    let mut claude_json = before.clone();
+
+    if global {
+        // Install to root-level mcpServers
+        if claude_json.get("mcpServers").is_none() {
+            claude_json["mcpServers"] = json!({});
+        }
+        claude_json["mcpServers"][&name] = server.config.clone();
+        if !save_claude_json(config, &before, &claude_json, dry_run)? {
+            return Ok(());
+        }
+        ui_println!("{} Installed '{}' globally to ~/.claude.json", "✓".green(), name);
+    } else {
+        let project_path = get_current_project_path()?;
+
+        // Ensure projects object exists
+        if claude_json.get("projects").is_none() {
+            claude_json["projects"] = json!({});
+        }
+
+        // Ensure project entry exists
+        if claude_json["projects"].get(&project_path).is_none() {
+            claude_json["projects"][&project_path] = json!({});
+        }
+
+        // Ensure mcpServers object exists for this project
+        if claude_json["projects"][&project_path].get("mcpServers").is_none() {
+            claude_json["projects"][&project_path]["mcpServers"] = json!({});
+        }
+
+        claude_json["projects"][&project_path]["mcpServers"][&name] = server.config.clone();
+        offer_merge(&mut claude_json, &project_path, false)?;
+        if !save_claude_json(config, &before, &claude_json, dry_run)? {
+            return Ok(());
+        }
+        ui_println!("{} Installed '{}' to ~/.claude.json for project:", "✓".green(), name);
+        ui_println!("  {}", project_path);
+    }
+
+    ui_println!("\n{}", "Restart Claude Code to load the MCP server.".cyan());
+    Ok(())
+}
+
+fn uninstall_mcp(config: &Config, name: String, global: bool, dry_run: bool) -> Result<()> {
+    let before = load_claude_json()?;
+    let mut claude_json = before.clone();
+
+    if global {
+        // Uninstall from root-level mcpServers
+        if let Some(servers) = claude_json.get_mut("mcpServers") {
+            if let Some(obj) = servers.as_object_mut() {
+                if obj.remove(&name).is_some() {
+                    if save_claude_json(config, &before, &claude_json, dry_run)? {
+                        ui_println!("{} Uninstalled '{}' globally from ~/.claude.json", "✓".green(), name);
+                        ui_println!("\n{}", "Restart Claude Code to apply changes.".cyan());
+                    }
+                    return Ok(());
+                }
+            }
+        }
+        ui_println!("{} '{}' not found in global ~/.claude.json mcpServers", "!".yellow(), name);
+    } else {
+        let project_path = get_current_project_path()?;
+
+        if let Some(projects) = claude_json.get_mut("projects") {
+            if let Some(project) = projects.get_mut(&project_path) {
+                if let Some(servers) = project.get_mut("mcpServers") {
+                    if let Some(obj) = servers.as_object_mut() {
+                        if obj.remove(&name).is_some() {
+                            if save_claude_json(config, &before, &claude_json, dry_run)? {
+                                ui_println!("{} Uninstalled '{}' from ~/.claude.json for project:", "✓".green(), name);
+                                ui_println!("  {}", project_path);
+                                ui_println!("\n{}", "Restart Claude Code to apply changes.".cyan());
+                            }
+                            return Ok(());
+                        }
+                    }
+                }
+            }
+        }
+        ui_println!("{} '{}' not found in ~/.claude.json for project:", "!".yellow(), name);
+        ui_println!("  {}", project_path);
+        if !duplicate_projects(&claude_json, &project_path, dirs::home_dir().as_deref()).is_empty() {
+            ui_println!("  Other paths of this project have MCP servers; see 'ecce mcp merge-projects'");
+        }
+    }
+    Ok(())
+}
+
+fn show_status(_config: &Config) -> Result<()> {
+    let mcp_server_path = get_mcp_server_path()?;
+    let dist_path = mcp_server_path.join("dist").join("index.js");
+
+    ui_result!("{}", "Ecce MCP Status".bold());
+    ui_result!();
+
+    // Ecce's own MCP server
+    if dist_path.exists() {
+        ui_result!("Ecce MCP Server Built: {}", "Yes".green());
+        ui_result!("  Path: {}", dist_path.display());
+    } else {
+        ui_result!("Ecce MCP Server Built: {}", "No".red());
+        ui_result!("  Run 'ecce mcp build' to build");
+    }
+
+    let claude_json = load_claude_json()?;
+
+    ui_result!();
+
+    // Global MCP servers from ~/.claude.json root mcpServers
+    ui_result!("{}", "Global MCP Servers in ~/.claude.json:".bold());
+    let mut global_found = false;
+    if let Some(servers) = claude_json.get("mcpServers") {
+        if let Some(obj) = servers.as_object() {
+            if !obj.is_empty() {
+                global_found = true;
+                for (name, server) in obj {
+                    ui_result!("  - {} {}", name, format!("({})", mcp_transport(server)).dimmed());
+                }
+            }
+        }
+    }
+    if !global_found {
+        ui_result!("  {}", "None".yellow());
+    }
+
+    ui_result!();
+
+    // Local project MCP servers from ~/.claude.json
+    let project_path = get_current_project_path()?;
+    ui_result!("{}", "Project MCP Servers in ~/.claude.json:".bold());
+    ui_result!("  {}", project_path);
+
+    let mut found = false;
+    if let Some(projects) = claude_json.get("projects") {
+        if let Some(project) = projects.get(&project_path) {
+            if let Some(servers) = project.get("mcpServers") {
+                if let Some(obj) = servers.as_object() {
+                    if !obj.is_empty() {
+                        found = true;
+                        for (name, server) in obj {
+                            ui_result!("  - {} {}", name, format!("({})", mcp_transport(server)).dimmed());
+                        }
+                    }
+                }
+            }
+        }
+    }
+    if !found {
+        ui_result!("  {}", "None".yellow());
+    }
+
+    let duplicates = duplicate_projects(&claude_json, &project_path, dirs::home_dir().as_deref());
+    if !duplicates.is_empty() {
+        ui_result!();
+        ui_result!("{}", "Duplicated entries for this project:".bold());
+        for key in &duplicates {
+            ui_result!("  - {} ({})", key, servers_list(&claude_json["projects"][key]["mcpServers"]));
+        }
+        ui_result!("  Run 'ecce mcp merge-projects' to merge them");
+    }
+
+    Ok(())
+}
+
+fn build_mcp() -> Result<()> {
+    let mcp_path = get_mcp_server_path()?;
+
+    if !mcp_path.exists() {
+        anyhow::bail!("MCP server directory not found at: {}", mcp_path.display());
+    }
+
+    ui_println!("{}", "Building MCP server...".cyan());
+
+    let install_status = std::process::Command::new("bun")
+        .arg("install")
+        .current_dir(&mcp_path)
+        .status()
+        .context("Failed to run 'bun install'")?;
+
+    if !install_status.success() {
+        anyhow::bail!("'bun install' failed");
+    }
+
+    let build_status = std::process::Command::new("bun")
+        .arg("run")
+        .arg("build")
+        .current_dir(&mcp_path)
+        .status()
+        .context("Failed to run 'bun run build'")?;
+
+    if !build_status.success() {
+        anyhow::bail!("'bun run build' failed");
+    }
+
+    ui_println!("{} MCP server built successfully!", "✓".green());
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_merge_projects() {
+        let home = tempfile::TempDir::new().unwrap();
+        let root = home.path().canonicalize().unwrap().join("talk");
+        fs::create_dir_all(root.join(".git")).unwrap();
+        fs::create_dir_all(root.join("slides")).unwrap();
+        let root_key = root.to_string_lossy().to_string();
+        let sub_key = root.join("slides").to_string_lossy().to_string();
+        let slash_key = format!("{}/", root_key);
+
+        let mut claude_json = json!({
+            "projects": {
+                &root_key: { "mcpServers": { "ecce": { "command": "ecce" } } },
+                &sub_key: {
+                    "allowedTools": ["Bash"],
+                    "mcpServers": { "ecce": { "command": "other" }, "search": { "command": "s" } }
+                },
+                &slash_key: { "mcpServers": { "notes": { "command": "n" } } },
+                "/elsewhere": { "mcpServers": { "x": {} } }
+            }
+        });
+
+        let duplicates = duplicate_projects(&claude_json, &root_key, Some(home.path()));
+        assert_eq!(duplicates, vec![slash_key.clone(), sub_key.clone()]);
+
+        let conflicts = merge_projects(&mut claude_json, &root_key, &duplicates);
+        assert_eq!(conflicts, vec![format!("ecce (from {})", sub_key)]);
+        let projects = &claude_json["projects"];
+        assert_eq!(servers_list(&projects[&root_key]["mcpServers"]), "ecce, notes, search");
+        assert_eq!(projects[&root_key]["mcpServers"]["ecce"]["command"], "ecce");
+        // Other state stays; emptied entries go
+        assert_eq!(projects[&sub_key], json!({ "allowedTools": ["Bash"] }));
+        assert!(projects.get(&slash_key).is_none());
+        assert!(projects.get("/elsewhere").is_some());
+        assert!(duplicate_projects(&claude_json, &root_key, Some(home.path())).is_empty());
+    }
+
+    #[test]
+    fn test_remote_config() {
+        let headers = vec![parse_header("Authorization=Bearer abc").unwrap()];
+        let config = remote_config("sse", "https://mcp.example.com/sse", &headers);
+        assert_eq!(
+            config,
+            json!({
+                "type": "sse",
+                "url": "https://mcp.example.com/sse",
+                "headers": { "Authorization": "Bearer abc" }
+            })
+        );
+        assert_eq!(mcp_transport(&config), "sse");
+        assert_eq!(remote_config("http", "https://x", &[]), json!({ "type": "http", "url": "https://x" }));
+        assert!(parse_header("no-equals").is_err());
+        assert!(parse_header("=value").is_err());
+    }
+}
@@ -0,0 +1,341 @@
+use anyhow::{Context, Result};
+use colored::Colorize;
+use serde_json::{json, Value};
+use std::process::Stdio;
+use std::time::Duration;
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::process::{Child, ChildStdin, Command};
+use tokio::sync::mpsc;
+use ecce::{ui_eprintln, ui_println};
+
+use crate::config::mcp_transport;
+
+/// How long to wait for the server to answer one request
+const RESPONSE_TIMEOUT: Duration = Duration::from_secs(15);
+
+/// How long a server gets to exit after its stdin closes
+const EXIT_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// A line the server wrote to stdout, decoded as far as it goes
+#[derive(Debug, PartialEq)]
+pub enum Frame {
+    Request { id: Value, method: String, params: Value },
+    Notification { method: String, params: Value },
+    Response { id: Value, result: Value },
+    Error { id: Value, code: i64, message: String },
+    /// Not JSON-RPC; Claude Code drops the connection on these
+    Garbage(String),
+}
+
+impl Frame {
+    pub fn parse(line: &str) -> Self {
+        let Ok(message) = serde_json::from_str::<Value>(line) else {
+            return Frame::Garbage(line.to_string());
+        };
+        if message.get("jsonrpc").and_then(Value::as_str) != Some("2.0") {
+            return Frame::Garbage(line.to_string());
+        }
+        let id = message.get("id").cloned();
+        match (message["method"].as_str(), id) {
+            (Some(method), Some(id)) => Frame::Request {
+                id,
+                method: method.to_string(),
+                params: message["params"].clone(),
+            },
+            (Some(method), None) => Frame::Notification {
+                method: method.to_string(),
+                params: message["params"].clone(),
+            },
+            (None, Some(id)) if message.get("error").is_some() => Frame::Error {
+                id,
+                code: message["error"]["code"].as_i64().unwrap_or_default(),
+                message: message["error"]["message"].as_str().unwrap_or_default().to_string(),
+            },
+            (None, Some(id)) if message.get("result").is_some() => Frame::Response {
+                id,
+                result: message["result"].clone(),
+            },
+            _ => Frame::Garbage(line.to_string()),
+        }
+    }
+
+    /// Id of a response or error, to match it with its request
+    fn response_id(&self) -> Option<&Value> {
+        match self {
+            Frame::Response { id, .. } | Frame::Error { id, .. } => Some(id),
+            _ => None,
+        }
+    }
+
+    /// Headline and pretty-printed body
+    pub fn describe(&self) -> (String, Option<String>) {
+        let pretty = |value: &Value| (!value.is_null()).then(|| serde_json::to_string_pretty(value).unwrap_or_default());
+        match self {
+            Frame::Request { id, method, params } => (format!("request #{} {}", id, method), pretty(params)),
+            Frame::Notification { method, params } => (format!("notification {}", method), pretty(params)),
+            Frame::Response { id, result } => (format!("response #{}", id), pretty(result)),
+            Frame::Error { id, code, message } => (format!("error #{} ({}): {}", id, code, message), None),
+            Frame::Garbage(line) => (
+                "not JSON-RPC; stdout must carry only protocol messages".to_string(),
+                Some(line.clone()),
+            ),
+        }
+    }
+}
+
+fn show(arrow: &str, frame: &Frame) {
+    let (headline, body) = frame.describe();
+    let headline = match frame {
+        Frame::Error { .. } | Frame::Garbage(_) => headline.red(),
+        _ => headline.cyan(),
+    };
+    ui_println!("{} {}", arrow.bold(), headline);
+    if let Some(body) = body {
+        for line in body.lines() {
+            ui_println!("    {}", line.dimmed());
+        }
+    }
+}
+
+/// A running server: its stdin, and its stdout lines as they arrive
+struct Session {
+    child: Child,
+    stdin: Option<ChildStdin>,
+    frames: mpsc::UnboundedReceiver<String>,
+    next_id: u64,
+}
+
+impl Session {
+    async fn send(&mut self, message: Value) -> Result<()> {
+        show("→", &Frame::parse(&message.to_string()));
+        let stdin = self.stdin.as_mut().context("Server stdin is closed")?;
+        stdin.write_all(format!("{}\n", message).as_bytes()).await?;
+        stdin.flush().await.context("Server closed its stdin")
+    }
+
+    async fn request(&mut self, method: &str, params: Value) -> Result<Option<Frame>> {
+        self.next_id += 1;
+        let id = json!(self.next_id);
+        self.send(json!({ "jsonrpc": "2.0", "id": id, "method": method, "params": params }))
+            .await?;
+
+        let wait = async {
+            while let Some(line) = self.frames.recv().await {
+                let frame = Frame::parse(&line);
+                show("←", &frame);
+                if frame.response_id() == Some(&id) {
+                    return Some(frame);
+                }
+            }
+            None
+        };
+        match tokio::time::timeout(RESPONSE_TIMEOUT, wait).await {
+            Ok(frame) => Ok(frame),
+            Err(_) => {
+                ui_println!("{} No response to {} after {}s", "!".yellow(), method, RESPONSE_TIMEOUT.as_secs());
+                Ok(None)
+            }
+        }
+    }
+}
+
+/// How to start a stdio server
+#[derive(Debug, PartialEq)]
+pub struct Launch {
+    pub command: String,
+    pub args: Vec<String>,
+    pub env: Vec<(String, String)>,
+}
+
+/// The command line and environment of a stdio server config
+pub fn command_of(config: &Value) -> Result<Launch> {
+    if mcp_transport(config) != "stdio" {
+        return Err(anyhow::anyhow!(
+            "Only stdio servers can be run locally; this is a {} server at {}",
+            mcp_transport(config),
+            config["url"].as_str().unwrap_or("?")
+        ));
+    }
+    let command = config["command"]
+        .as_str()
+        .context("Server config has no \"command\"")?
+        .to_string();
+    let args = config["args"]
+        .as_array()
+        .map(|args| args.iter().filter_map(Value::as_str).map(str::to_string).collect())
+        .unwrap_or_default();
+    let env = config["env"]
+        .as_object()
+        .map(|env| {
+            env.iter()
+                .filter_map(|(k, v)| Some((k.clone(), v.as_str()?.to_string())))
+                .collect()
+        })
+        .unwrap_or_default();
+    Ok(Launch { command, args, env })
+}
+
+/// Launch the server, go through the handshake Claude Code does (initialize,
+/// then tools/list), and print every frame and stderr line. With `tail`, keep
+/// running: lines typed on stdin are sent to the server until it exits or
+/// Ctrl+C. Returns the server's exit code, if it exited on its own.
+pub async fn run(name: &str, config: &Value, tail: bool) -> Result<Option<i32>> {
+    let Launch { command, args, env } = command_of(config)?;
+    ui_println!("{} {} {}", "▶".green(), command.bold(), args.join(" "));
+
+    let mut child = Command::new(&command)
+        .args(&args)
+        .envs(env)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true)
+        .spawn()
+        .with_context(|| format!("Failed to start MCP server '{}' ({})", name, command))?;
+
+    let stderr = child.stderr.take().context("No stderr")?;
+    tokio::spawn(async move {
+        let mut lines = BufReader::new(stderr).lines();
+        while let Ok(Some(line)) = lines.next_line().await {
+            ui_println!("{} {}", "stderr │".yellow(), line);
+        }
+    });
+    let stdout = child.stdout.take().context("No stdout")?;
+    let (tx, frames) = mpsc::unbounded_channel();
+    tokio::spawn(async move {
+        let mut lines = BufReader::new(stdout).lines();
+        while let Ok(Some(line)) = lines.next_line().await {
+            if !line.trim().is_empty() && tx.send(line).is_err() {
+                break;
+            }
+        }
+    });
+
+    let mut session = Session {
+        stdin: child.stdin.take(),
+        child,
+        frames,
+        next_id: 0,
+    };
+    let initialized = session
+        .request(
+            "initialize",
+            json!({
+                "protocolVersion": "2025-03-26",
+                "capabilities": {},
+                "clientInfo": { "name": "ecce", "version": env!("CARGO_PKG_VERSION") }
+            }),
+        )
+        .await;
+    if let Ok(Some(Frame::Response { .. })) = initialized {
+        session
+            .send(json!({ "jsonrpc": "2.0", "method": "notifications/initialized" }))
+            .await
+            .ok();
+        session.request("tools/list", json!({})).await.ok();
+    } else if let Err(e) = initialized {
+        ui_eprintln!("{} {:#}", "!".yellow(), e);
+    }
+
+    if tail {
+        ui_println!("{}", "Type JSON-RPC messages to send them; Ctrl+C stops the server.".dimmed());
+        let mut input = BufReader::new(tokio::io::stdin()).lines();
+        let mut typing = true;
+        loop {
+            tokio::select! {
+                line = session.frames.recv() => match line {
+                    Some(line) => show("←", &Frame::parse(&line)),
+                    None => break,
+                },
+                line = input.next_line(), if typing => match line {
+                    Ok(Some(line)) if !line.trim().is_empty() => match serde_json::from_str::<Value>(&line) {
+                        Ok(message) => {
+                            if let Err(e) = session.send(message).await {
+                                ui_eprintln!("{} {:#}", "!".yellow(), e);
+                            }
+                        }
+                        Err(e) => ui_eprintln!("{} Not JSON: {}", "!".yellow(), e),
+                    },
+                    Ok(Some(_)) => {}
+                    // End of input: close the server's stdin and show what it writes until it exits
+                    _ => {
+                        typing = false;
+                        session.stdin = None;
+                    }
+                },
+                _ = tokio::signal::ctrl_c() => break,
+            }
+        }
+    }
+
+    // Closing stdin is how a client asks a stdio server to stop
+    session.stdin = None;
+    match tokio::time::timeout(EXIT_TIMEOUT, session.child.wait()).await {
+        Ok(status) => {
+            let status = status?;
+            // Whatever it wrote before exiting, such as a banner that broke the handshake
+            let drain = async {
+                while let Some(line) = session.frames.recv().await {
+                    show("←", &Frame::parse(&line));
+                }
+            };
+            tokio::time::timeout(Duration::from_secs(1), drain).await.ok();
+            let color = if status.success() { "green" } else { "red" };
+            ui_println!("{}", format!("Server exited with {}", status).color(color));
+            Ok(status.code())
+        }
+        Err(_) => {
+            ui_println!("{}", "Server didn't exit after its stdin closed; stopping it".yellow());
+            session.child.kill().await.ok();
+            Ok(None)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_frames() {
+        let request = Frame::parse(r#"{"jsonrpc":"2.0","id":1,"method":"tools/list","params":{}}"#);
+        assert_eq!(request.describe().0, "request #1 tools/list");
+        let response = Frame::parse(r#"{"jsonrpc":"2.0","id":1,"result":{"tools":[]}}"#);
+        assert_eq!(response.response_id(), Some(&json!(1)));
+        assert!(response.describe().1.unwrap().contains("\"tools\": []"));
+        let error = Frame::parse(r#"{"jsonrpc":"2.0","id":"a","error":{"code":-32601,"message":"nope"}}"#);
+        assert_eq!(error.describe().0, "error #\"a\" (-32601): nope");
+        let note = Frame::parse(r#"{"jsonrpc":"2.0","method":"notifications/message","params":{"level":"info"}}"#);
+        assert!(matches!(note, Frame::Notification { .. }));
+        assert_eq!(Frame::parse("Server started on stdio"), Frame::Garbage("Server started on stdio".to_string()));
+        assert!(matches!(Frame::parse(r#"{"id":1,"result":{}}"#), Frame::Garbage(_)));
+    }
+
+    #[test]
+    fn test_command_of() {
+        let launch = command_of(&json!({"command": "npx", "args": ["-y", "srv"], "env": {"K": "v"}})).unwrap();
+        assert_eq!(
+            launch,
+            Launch {
+                command: "npx".to_string(),
+                args: vec!["-y".to_string(), "srv".to_string()],
+                env: vec![("K".to_string(), "v".to_string())],
+            }
+        );
+        let err = command_of(&json!({"type": "http", "url": "https://x"})).unwrap_err();
+        assert!(err.to_string().contains("http server at https://x"));
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_run_fake_server() {
+        // Answers every request with an empty result, then exits with 3 when stdin closes
+        let script = r#"while read -r line; do
+            id=$(printf '%s' "$line" | sed -n 's/.*"id":\([0-9]*\).*/\1/p')
+            [ -n "$id" ] && printf '{"jsonrpc":"2.0","id":%s,"result":{}}\n' "$id"
+            echo "got a line" >&2
+        done; exit 3"#;
+        let config = json!({"command": "sh", "args": ["-c", script]});
+        assert_eq!(run("fake", &config, false).await.unwrap(), Some(3));
+    }
+}
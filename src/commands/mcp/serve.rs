@@ -0,0 +1,333 @@
+use anyhow::{Context, Result};
+use serde_json::{json, Value};
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+
+use crate::agent::{ClaudeAgent, RunOptions};
+use crate::commands::exec::select_profile;
+use crate::config::{Config, Profile, CLAUDE_CODE_SERVICE};
+use crate::ui;
+
+/// Protocol version answered when the client doesn't name one
+const PROTOCOL_VERSION: &str = "2024-11-05";
+
+// JSON-RPC error codes
+const PARSE_ERROR: i64 = -32700;
+const METHOD_NOT_FOUND: i64 = -32601;
+const INVALID_PARAMS: i64 = -32602;
+
+/// Tools ecce offers to an MCP client such as Claude Code
+fn tools() -> Value {
+    json!([
+        {
+            "name": "ecce_run_agent",
+            "description": "Send a prompt to one of ecce's configured agents and return its response. \
+                Each agent keeps its conversation across calls until new_conversation is set.",
+            "inputSchema": {
+                "type": "object",
+                "properties": {
+                    "agent": { "type": "string", "description": "Agent name (see ecce_list_agents)" },
+                    "prompt": { "type": "string", "description": "Prompt to send" },
+                    "task": { "type": "string", "description": "Task template to apply (optional)" },
+                    "new_conversation": {
+                        "type": "boolean",
+                        "description": "Start a fresh conversation with the agent"
+                    }
+                },
+                "required": ["agent", "prompt"]
+            }
+        },
+        {
+            "name": "ecce_list_agents",
+            "description": "List ecce's configured agents with their descriptions and models",
+            "inputSchema": { "type": "object", "properties": {}, "required": [] }
+        },
+        {
+            "name": "ecce_apply_profile",
+            "description": "Run later ecce_run_agent calls against this claude-code API profile's endpoint and key",
+            "inputSchema": {
+                "type": "object",
+                "properties": {
+                    "name": { "type": "string", "description": "Profile name" }
+                },
+                "required": ["name"]
+            }
+        }
+    ])
+}
+
+/// State kept across requests: the agents' conversations and the applied profile
+pub struct McpServer {
+    /// Reloaded before each tool call so config edits apply without a restart
+    config_path: Option<PathBuf>,
+    config: Config,
+    profile: Option<Profile>,
+    agents: BTreeMap<String, ClaudeAgent>,
+}
+
+impl McpServer {
+    pub fn new(config: Config, config_path: Option<PathBuf>) -> Self {
+        Self {
+            config_path,
+            config,
+            profile: None,
+            agents: BTreeMap::new(),
+        }
+    }
+
+    /// The response to one JSON-RPC message; none for notifications
+    pub async fn handle_message(&mut self, line: &str) -> Option<Value> {
+        let message: Value = match serde_json::from_str(line) {
+            Ok(message) => message,
+            Err(e) => return Some(error_response(Value::Null, PARSE_ERROR, &format!("Parse error: {}", e))),
+        };
+        // Notifications (no id) never get a response
+        let id = message.get("id")?.clone();
+        let method = message["method"].as_str().unwrap_or_default();
+        let params = &message["params"];
+
+        let result = match method {
+            "initialize" => Ok(json!({
+                "protocolVersion": params["protocolVersion"].as_str().unwrap_or(PROTOCOL_VERSION),
+                "capabilities": { "tools": {} },
+                "serverInfo": { "name": "ecce", "version": env!("CARGO_PKG_VERSION") }
+            })),
+            "ping" => Ok(json!({})),
+            "tools/list" => Ok(json!({ "tools": tools() })),
+            "tools/call" => self.call_tool(params).await,
+            _ => Err((METHOD_NOT_FOUND, format!("Method not found: {}", method))),
+        };
+        Some(match result {
+            Ok(result) => json!({ "jsonrpc": "2.0", "id": id, "result": result }),
+            Err((code, message)) => error_response(id, code, &message),
+        })
+    }
+
+    /// A tool's result; failures inside a tool are reported to the model as `isError`
+    async fn call_tool(&mut self, params: &Value) -> std::result::Result<Value, (i64, String)> {
+        let name = params["name"].as_str().unwrap_or_default();
+        let args = &params["arguments"];
+        let result = match name {
+            "ecce_run_agent" => self.run_agent(args).await,
+            "ecce_list_agents" => self.list_agents(),
+            "ecce_apply_profile" => self.apply_profile(args),
+            _ => return Err((INVALID_PARAMS, format!("Unknown tool: {}", name))),
+        };
+        Ok(match result {
+            Ok(text) => json!({ "content": [{ "type": "text", "text": text }] }),
+            Err(e) => json!({ "content": [{ "type": "text", "text": format!("Error: {:#}", e) }], "isError": true }),
+        })
+    }
+
+    fn reload(&mut self) -> Result<()> {
+        if let Some(ref path) = self.config_path {
+            self.config = Config::load_from(path)?;
+        }
+        Ok(())
+    }
+
+    async fn run_agent(&mut self, args: &Value) -> Result<String> {
+        let name = required(args, "agent")?;
+        let prompt = required(args, "prompt")?;
+        self.reload()?;
+        if self.config.get_agent(name).is_none() {
+            return Err(anyhow::anyhow!("Agent '{}' not found", name));
+        }
+        let agent = self.config.resolve_agent(name)?;
+        let task = args["task"]
+            .as_str()
+            .map(|task| self.config.resolve_task(task))
+            .transpose()?;
+
+        let fresh = args["new_conversation"].as_bool().unwrap_or(false);
+        let claude = match self.agents.get_mut(name) {
+            Some(claude) if !fresh => {
+                // Follow config edits; a changed prompt or task needs a new claude session
+                if claude.agent() != &agent || claude.task() != task.as_ref() {
+                    claude.reconfigure(agent, task);
+                }
+                claude
+            }
+            _ => {
+                let mut claude = ClaudeAgent::with_options(
+                    self.config.get_claude_executable(),
+                    agent,
+                    task,
+                    RunOptions::from_config(&self.config),
+                );
+                if let Some(ref profile) = self.profile {
+                    claude.use_profile(profile);
+                }
+                self.agents.insert(name.to_string(), claude);
+                self.agents.get_mut(name).expect("just inserted")
+            }
+        };
+        claude.generate_response(prompt).await
+    }
+
+    fn list_agents(&mut self) -> Result<String> {
+        self.reload()?;
+        let agents: Vec<Value> = self
+            .config
+            .agents
+            .values()
+            .map(|agent| {
+                json!({
+                    "name": agent.name,
+                    "description": agent.description,
+                    "model": agent.model,
+                    "extends": agent.extends,
+                    "default": self.config.default_agent.as_deref() == Some(agent.name.as_str()),
+                })
+            })
+            .collect();
+        Ok(serde_json::to_string_pretty(&agents)?)
+    }
+
+    fn apply_profile(&mut self, args: &Value) -> Result<String> {
+        let name = required(args, "name")?;
+        self.reload()?;
+        let profile = select_profile(Some(name), &self.config)?;
+        if profile.service != CLAUDE_CODE_SERVICE {
+            return Err(anyhow::anyhow!(
+                "Profile '{}' is for {}, not {}",
+                name,
+                profile.service,
+                CLAUDE_CODE_SERVICE
+            ));
+        }
+        for claude in self.agents.values_mut() {
+            claude.use_profile(&profile);
+        }
+        let text = format!("ecce_run_agent now uses profile '{}' ({})", profile.name, profile.url);
+        self.profile = Some(profile);
+        Ok(text)
+    }
+}
+
+fn required<'a>(args: &'a Value, key: &str) -> Result<&'a str> {
+    args[key]
+        .as_str()
+        .ok_or_else(|| anyhow::anyhow!("Missing string argument '{}'", key))
+}
+
+fn error_response(id: Value, code: i64, message: &str) -> Value {
+    json!({ "jsonrpc": "2.0", "id": id, "error": { "code": code, "message": message } })
+}
+
+/// Serve MCP over stdio: one JSON-RPC message per line. Stdout carries only
+/// protocol messages, so status output goes to stderr.
+pub async fn serve(config: &Config) -> Result<()> {
+    ui::status_to_stderr();
+    let mut server = McpServer::new(config.clone(), Some(Config::config_path()?));
+    let mut lines = BufReader::new(tokio::io::stdin()).lines();
+    let mut stdout = tokio::io::stdout();
+    eprintln!("ecce MCP server running on stdio");
+
+    while let Some(line) = lines.next_line().await.context("Failed to read from stdin")? {
+        if line.trim().is_empty() {
+            continue;
+        }
+        if let Some(response) = server.handle_message(&line).await {
+            stdout.write_all(format!("{}\n", response).as_bytes()).await?;
+            stdout.flush().await?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Agent;
+
+    fn server(executable: Option<String>) -> McpServer {
+        let mut config = Config {
+            claude_executable: executable,
+            ..Default::default()
+        };
+        config.agents.insert(
+            "writer".to_string(),
+            Agent {
+                name: "writer".to_string(),
+                description: Some("Writes slides".to_string()),
+                system_prompt: "You write slides.".to_string(),
+                ..Default::default()
+            },
+        );
+        McpServer::new(config, None)
+    }
+
+    async fn call(server: &mut McpServer, tool: &str, arguments: Value) -> Value {
+        let request = json!({
+            "jsonrpc": "2.0", "id": 7, "method": "tools/call",
+            "params": { "name": tool, "arguments": arguments }
+        });
+        server.handle_message(&request.to_string()).await.unwrap()["result"].clone()
+    }
+
+    #[tokio::test]
+    async fn test_protocol() {
+        let mut server = server(None);
+        let init = server
+            .handle_message(r#"{"jsonrpc":"2.0","id":1,"method":"initialize","params":{"protocolVersion":"2025-03-26"}}"#)
+            .await
+            .unwrap();
+        assert_eq!(init["result"]["protocolVersion"], "2025-03-26");
+        assert_eq!(init["result"]["serverInfo"]["name"], "ecce");
+
+        assert!(server
+            .handle_message(r#"{"jsonrpc":"2.0","method":"notifications/initialized"}"#)
+            .await
+            .is_none());
+
+        let list = server
+            .handle_message(r#"{"jsonrpc":"2.0","id":2,"method":"tools/list"}"#)
+            .await
+            .unwrap();
+        let names: Vec<&str> = list["result"]["tools"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|t| t["name"].as_str().unwrap())
+            .collect();
+        assert_eq!(names, vec!["ecce_run_agent", "ecce_list_agents", "ecce_apply_profile"]);
+
+        let unknown = server
+            .handle_message(r#"{"jsonrpc":"2.0","id":3,"method":"resources/list"}"#)
+            .await
+            .unwrap();
+        assert_eq!(unknown["error"]["code"], METHOD_NOT_FOUND);
+        let garbage = server.handle_message("{not json").await.unwrap();
+        assert_eq!(garbage["error"]["code"], PARSE_ERROR);
+    }
+
+    #[tokio::test]
+    async fn test_list_agents_and_errors() {
+        let mut server = server(None);
+        let result = call(&mut server, "ecce_list_agents", json!({})).await;
+        let agents: Value = serde_json::from_str(result["content"][0]["text"].as_str().unwrap()).unwrap();
+        assert_eq!(agents[0]["name"], "writer");
+        assert_eq!(agents[0]["description"], "Writes slides");
+
+        let result = call(&mut server, "ecce_run_agent", json!({"agent": "nobody", "prompt": "Hi"})).await;
+        assert_eq!(result["isError"], true);
+        assert_eq!(result["content"][0]["text"], "Error: Agent 'nobody' not found");
+
+        let result = call(&mut server, "ecce_apply_profile", json!({"name": "missing"})).await;
+        assert_eq!(result["isError"], true);
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_run_agent_with_fake_claude() {
+        let claude = ecce::testing::FakeClaude::answering("Ownership moves values.").unwrap();
+        let mut server = server(Some(claude.executable()));
+        let result = call(&mut server, "ecce_run_agent", json!({"agent": "writer", "prompt": "What is ownership?"})).await;
+        assert_eq!(result["content"][0]["text"], "Ownership moves values.");
+        assert!(result.get("isError").is_none());
+        // The conversation is kept for follow-ups
+        assert_eq!(server.agents["writer"].conversation().len(), 2);
+    }
+}
@@ -1,5 +1,27 @@
 pub mod api;
 pub mod agent;
+pub mod batch;
+pub mod bench;
+pub mod chat;
+pub mod check;
+pub mod claude;
+pub mod config;
+pub mod exec;
+pub mod extract;
+pub mod find;
+pub mod history;
+pub mod hooks;
 pub mod homo;
 pub mod mcp;
+pub mod pack;
+pub mod preset;
+pub mod regen;
+pub mod report;
+pub mod service;
+pub mod session;
+pub mod shell;
+pub mod stats;
+pub mod target;
 pub mod task;
+pub mod upgrade;
+pub mod version;
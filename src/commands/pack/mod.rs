@@ -0,0 +1,228 @@
+use anyhow::Result;
+use clap::Subcommand;
+use colored::*;
+use std::io::{self, IsTerminal, Write};
+use std::path::PathBuf;
+use ecce::{ui_println, ui_prompt};
+
+use crate::config::Config;
+use crate::pack::{self, Pack, PackSelection};
+
+#[derive(Subcommand)]
+pub enum PackCommand {
+    /// Bundle agents, tasks, and MCP server templates into a pack file
+    Create {
+        /// Pack name
+        name: String,
+        /// Agents to include (comma-separated or repeated)
+        #[arg(short, long, value_delimiter = ',')]
+        agent: Vec<String>,
+        /// Tasks to include; base tasks they extend are added automatically
+        #[arg(short, long, value_delimiter = ',')]
+        task: Vec<String>,
+        /// MCP servers to include
+        #[arg(short, long, value_delimiter = ',')]
+        mcp: Vec<String>,
+        /// Include every agent, task, and MCP server
+        #[arg(long)]
+        all: bool,
+        /// Output file (default: <name>.ecce-pack.json)
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
+    /// Add a pack's agents, tasks, and MCP servers to config
+    Install {
+        /// Pack file path or http(s) URL
+        source: String,
+        /// Overwrite existing entries without asking
+        #[arg(long, conflicts_with = "keep_existing")]
+        force: bool,
+        /// Keep existing entries without asking
+        #[arg(long)]
+        keep_existing: bool,
+    },
+}
+
+/// How to handle pack entries whose names already exist in config
+#[derive(Clone, Copy)]
+enum ConflictPolicy {
+    Ask,
+    Overwrite,
+    Keep,
+}
+
+pub async fn handle_pack_command(command: PackCommand, config: &mut Config) -> Result<()> {
+    match command {
+        PackCommand::Create {
+            name,
+            agent,
+            task,
+            mcp,
+            all,
+            output,
+        } => {
+            let selection = if all {
+                PackSelection {
+                    agents: sorted(config.agents.keys()),
+                    tasks: sorted(config.tasks.keys()),
+                    mcp_servers: sorted(config.mcp_servers.keys()),
+                }
+            } else {
+                PackSelection {
+                    agents: agent,
+                    tasks: task,
+                    mcp_servers: mcp,
+                }
+            };
+
+            let pack = Pack::from_config(config, &name, &selection)?;
+            if pack.is_empty() {
+                return Err(anyhow::anyhow!(
+                    "Nothing to pack. Select entries with --agent, --task, --mcp, or use --all"
+                ));
+            }
+
+            let skipped_env: Vec<&str> = selection
+                .agents
+                .iter()
+                .filter(|a| {
+                    config
+                        .get_agent(a)
+                        .is_some_and(|a| !a.env.is_empty() || a.working_dir.is_some())
+                })
+                .map(|a| a.as_str())
+                .collect();
+            if !skipped_env.is_empty() {
+                ui_println!(
+                    "{}",
+                    format!(
+                        "⚠ Left out env vars and working directories of: {}",
+                        skipped_env.join(", ")
+                    )
+                    .yellow()
+                );
+            }
+
+            if !pack.placeholders.is_empty() {
+                ui_println!(
+                    "{}",
+                    format!(
+                        "⚠ Replaced MCP server secrets with placeholders; fill them in after installing: {}",
+                        pack.placeholders.join(", ")
+                    )
+                    .yellow()
+                );
+            }
+
+            let path = output.unwrap_or_else(|| PathBuf::from(format!("{}.ecce-pack.json", name)));
+            std::fs::write(&path, pack.to_json()?)?;
+            ui_println!(
+                "{}",
+                format!(
+                    "✓ Pack '{}' written to {} ({} agents, {} tasks, {} MCP servers)",
+                    name,
+                    path.display(),
+                    pack.agents.len(),
+                    pack.tasks.len(),
+                    pack.mcp_servers.len()
+                )
+                .green()
+            );
+        }
+        PackCommand::Install {
+            source,
+            force,
+            keep_existing,
+        } => {
+            let pack = Pack::load(&source).await?;
+            pack.check_extends(config)?;
+            let policy = if force {
+                ConflictPolicy::Overwrite
+            } else if keep_existing || !io::stdin().is_terminal() {
+                ConflictPolicy::Keep
+            } else {
+                ConflictPolicy::Ask
+            };
+
+            ui_println!("{}", format!("📦 Installing pack '{}'", pack.name).bold());
+            let mut installed = 0;
+
+            for agent in pack.agents {
+                let existing = config.get_agent(&agent.name);
+                if should_install("Agent", &agent.name, existing.map(|e| e == &agent), policy)? {
+                    ui_println!("  {} agent {}", "+".green(), agent.name.cyan());
+                    config.agents.insert(agent.name.clone(), agent);
+                    installed += 1;
+                }
+            }
+            for task in pack.tasks {
+                let existing = config.get_task(&task.name);
+                if should_install("Task", &task.name, existing.map(|e| e == &task), policy)? {
+                    ui_println!("  {} task {}", "+".green(), task.name.cyan());
+                    config.tasks.insert(task.name.clone(), task);
+                    installed += 1;
+                }
+            }
+            for server in pack.mcp_servers {
+                let existing = config.get_mcp_server(&server.name);
+                if should_install("MCP server", &server.name, existing.map(|e| e == &server), policy)? {
+                    ui_println!("  {} MCP server {}", "+".green(), server.name.cyan());
+                    let unfilled = pack::unfilled(&server);
+                    if !unfilled.is_empty() {
+                        ui_println!(
+                            "    {}",
+                            format!(
+                                "⚠ Fill in {} before using it, e.g. `ecce config set mcp_servers.{}.config.{} <value>`",
+                                unfilled.join(", "),
+                                server.name,
+                                unfilled[0]
+                            )
+                            .yellow()
+                        );
+                    }
+                    config.mcp_servers.insert(server.name.clone(), server);
+                    installed += 1;
+                }
+            }
+
+            config.save()?;
+            ui_println!(
+                "{}",
+                format!("✓ Installed {} entries from pack '{}'", installed, pack.name).green()
+            );
+        }
+    }
+
+    Ok(())
+}
+
+fn sorted<'a>(names: impl Iterator<Item = &'a String>) -> Vec<String> {
+    let mut names: Vec<String> = names.cloned().collect();
+    names.sort();
+    names
+}
+
+/// Decide whether to write a pack entry. `same` is None when the name is new,
+/// otherwise whether the existing entry is identical.
+fn should_install(kind: &str, name: &str, same: Option<bool>, policy: ConflictPolicy) -> Result<bool> {
+    match (same, policy) {
+        (None, _) => Ok(true),
+        (Some(true), _) => Ok(false),
+        (Some(false), ConflictPolicy::Overwrite) => Ok(true),
+        (Some(false), ConflictPolicy::Keep) => {
+            ui_println!("  {} {} '{}' exists; keeping it", "=".yellow(), kind, name);
+            Ok(false)
+        }
+        (Some(false), ConflictPolicy::Ask) => {
+            ui_prompt!(
+                "{} ",
+                format!("  {} '{}' already exists and differs. Overwrite? [y/N]", kind, name).yellow()
+            );
+            io::stdout().flush()?;
+
+            let mut input = String::new();
+            io::stdin().read_line(&mut input)?;
+            Ok(matches!(input.trim().to_lowercase().as_str(), "y" | "yes"))
+        }
+    }
+}
@@ -0,0 +1,106 @@
+use anyhow::Result;
+use clap::Subcommand;
+use colored::*;
+use ecce::{ui_eprintln, ui_println, ui_result};
+
+use crate::commands::agent::describe_model;
+use crate::config::{Config, Preset};
+
+#[derive(Subcommand)]
+pub enum PresetCommand {
+    /// Add a preset bundling agent, task, model, and output mode
+    Add {
+        /// Preset name (e.g., lecture-notes)
+        name: String,
+        /// Agent to use
+        #[arg(short, long)]
+        agent: Option<String>,
+        /// Task template to use
+        #[arg(short, long)]
+        task: Option<String>,
+        /// Model to use instead of the agent's own
+        #[arg(short, long)]
+        model: Option<String>,
+        /// Use plain output (no color, emoji, or box-drawing)
+        #[arg(long)]
+        plain: bool,
+    },
+    /// List all presets
+    #[command(alias = "ls")]
+    List,
+    /// Delete a preset
+    Delete {
+        /// Preset name to delete
+        name: String,
+    },
+}
+
+pub fn handle_preset_command(command: PresetCommand, config: &mut Config) -> Result<()> {
+    match command {
+        PresetCommand::Add {
+            name,
+            agent,
+            task,
+            model,
+            plain,
+        } => {
+            if let Some(ref agent) = agent {
+                if config.get_agent(agent).is_none() {
+                    return Err(anyhow::anyhow!("Agent '{}' not found", agent));
+                }
+            }
+            if let Some(ref task) = task {
+                if config.get_task(task).is_none() {
+                    return Err(anyhow::anyhow!("Task '{}' not found", task));
+                }
+            }
+
+            config.add_preset(Preset {
+                name: name.clone(),
+                agent,
+                task,
+                model,
+                plain,
+            })?;
+            ui_println!(
+                "{}",
+                format!("✓ Preset '{}' added successfully", name).green()
+            );
+        }
+        PresetCommand::List => {
+            if config.presets.is_empty() {
+                ui_result!("{}", "No presets configured".yellow());
+            } else {
+                ui_result!("{}", "Available presets:".bold());
+                let mut presets: Vec<&Preset> = config.presets.values().collect();
+                presets.sort_by(|a, b| a.name.cmp(&b.name));
+                for preset in presets {
+                    ui_result!("  {}", preset.name.cyan());
+                    ui_result!(
+                        "    Agent:  {}",
+                        preset.agent.as_deref().unwrap_or("(default)").dimmed()
+                    );
+                    ui_result!(
+                        "    Task:   {}",
+                        preset.task.as_deref().unwrap_or("(none)").dimmed()
+                    );
+                    if let Some(ref model) = preset.model {
+                        ui_result!("    Model:  {}", describe_model(config, model).dimmed());
+                    }
+                    if preset.plain {
+                        ui_result!("    Output: {}", "plain".dimmed());
+                    }
+                }
+            }
+        }
+        PresetCommand::Delete { name } => {
+            if config.delete_preset(&name)? {
+                ui_println!("{}", format!("✓ Preset '{}' deleted", name).green());
+            } else {
+                ui_eprintln!("{}", format!("✗ Preset '{}' not found", name).red());
+            }
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,54 @@
+use anyhow::Result;
+use clap::Args;
+use colored::*;
+use std::path::PathBuf;
+use ecce::ui_result;
+
+use crate::commands::homo;
+use crate::config::Config;
+
+#[derive(Args)]
+pub struct RegenArgs {
+    /// File or folder (uses its slides.md) containing the answer
+    pub file: PathBuf,
+
+    /// Provenance id of the answer to regenerate
+    #[arg(long, conflicts_with = "last")]
+    pub id: Option<String>,
+
+    /// Regenerate the most recently generated answer (the default)
+    #[arg(long)]
+    pub last: bool,
+
+    /// Agent to use instead of the one that wrote the answer
+    #[arg(short, long)]
+    pub agent: Option<String>,
+
+    /// Task to use instead of the one the answer was written with
+    #[arg(short, long)]
+    pub task: Option<String>,
+
+    /// Replace the answer even if it was edited by hand
+    #[arg(long)]
+    pub force: bool,
+}
+
+/// Re-run the prompt behind an answer written with provenance markers and
+/// replace the answer in place
+pub async fn handle_regen_command(args: RegenArgs, config: &Config) -> Result<()> {
+    let id = homo::regenerate(
+        &args.file,
+        config,
+        args.id.as_deref(),
+        args.agent,
+        args.task,
+        args.force,
+    )
+    .await?;
+
+    ui_result!(
+        "{}",
+        format!("✓ Regenerated answer {} in {}", id, args.file.display()).green()
+    );
+    Ok(())
+}
@@ -0,0 +1,287 @@
+use anyhow::{Context, Result};
+use chrono::Utc;
+use clap::Args;
+use ecce::ui_result;
+use std::backtrace::{Backtrace, BacktraceStatus};
+use std::fmt::Write as _;
+use std::fs::{self, OpenOptions};
+use std::io::Write as _;
+use std::path::{Path, PathBuf};
+
+use crate::commands::version::{claude_version, report};
+use crate::config::Config;
+use crate::history;
+use crate::redact::Redactor;
+
+/// Name prefix of diagnostic bundle directories
+const BUNDLE_PREFIX: &str = "ecce-report-";
+
+/// Bundles written on failure kept in the temp directory; older ones are removed
+const KEPT_BUNDLES: usize = 5;
+
+/// Recent generations listed in a bundle
+const HISTORY_ENTRIES: usize = 20;
+
+#[derive(Args)]
+pub struct ReportArgs {
+    /// Directory to create the bundle in (default: the system temp directory)
+    #[arg(short, long)]
+    pub output: Option<PathBuf>,
+}
+
+/// What went wrong, for a bundle written on failure
+pub struct Incident {
+    /// `panic` or `error`
+    pub kind: &'static str,
+    pub message: String,
+    pub backtrace: String,
+}
+
+impl Incident {
+    pub fn panic(info: &std::panic::PanicHookInfo) -> Self {
+        Self {
+            kind: "panic",
+            message: info.to_string(),
+            backtrace: Backtrace::force_capture().to_string(),
+        }
+    }
+
+    pub fn error(error: &anyhow::Error) -> Self {
+        let backtrace = match error.backtrace().status() {
+            BacktraceStatus::Captured => error.backtrace().to_string(),
+            _ => "not captured; run again with RUST_BACKTRACE=1 to include one".to_string(),
+        };
+        Self {
+            kind: "error",
+            message: format!("{:#}", error),
+            backtrace,
+        }
+    }
+}
+
+/// Assemble a bundle on demand and print its path
+pub async fn handle_report_command(args: ReportArgs) -> Result<()> {
+    let config = Config::load();
+    let claude = match config {
+        Ok(ref config) => Some(claude_version(config).await),
+        Err(_) => None,
+    };
+    let parent = match args.output {
+        Some(dir) => dir,
+        None => std::env::temp_dir(),
+    };
+    let bundle = write_bundle(&parent, None, config.as_ref(), claude)?;
+    ui_result!("{}", bundle.display());
+    Ok(())
+}
+
+/// Write a bundle for a panic or unexpected error, and say where it is.
+/// Never fails: a broken report must not hide the original problem.
+pub fn report_incident(incident: &Incident) {
+    let parent = std::env::temp_dir();
+    let config = Config::load();
+    match write_bundle(&parent, Some(incident), config.as_ref(), None) {
+        Ok(bundle) => {
+            prune_bundles(&parent, KEPT_BUNDLES);
+            eprintln!("Diagnostic report: {} (attach it to a bug report)", bundle.display());
+        }
+        Err(e) => eprintln!("Failed to write a diagnostic report: {:#}", e),
+    }
+}
+
+/// Create `ecce-report-<time>-<pid>` in `parent` with the report, the config
+/// with its secrets masked, and a summary of recent generations
+fn write_bundle(
+    parent: &Path,
+    incident: Option<&Incident>,
+    config: Result<&Config, &anyhow::Error>,
+    claude: Option<String>,
+) -> Result<PathBuf> {
+    let now = Utc::now();
+    let dir = parent.join(format!(
+        "{}{}-{}",
+        BUNDLE_PREFIX,
+        now.format("%Y%m%d-%H%M%S"),
+        std::process::id()
+    ));
+    // The bundle holds paths and settings, so only the user may read it
+    let mut builder = fs::DirBuilder::new();
+    builder.recursive(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::DirBuilderExt;
+        builder.mode(0o700);
+    }
+    builder.create(&dir).with_context(|| format!("Failed to create {}", dir.display()))?;
+    let redactor = match config {
+        Ok(config) => Redactor::from_config(config),
+        Err(_) => Redactor::default(),
+    };
+
+    let mut text = format!("ecce diagnostic report, {}\n\n", now.to_rfc3339());
+    let args: Vec<String> = std::env::args().collect();
+    writeln!(text, "{:<15} {}", "command:", command_shape(&args))?;
+    for (label, value) in report(config, claude) {
+        writeln!(text, "{:<15} {}", format!("{}:", label), value)?;
+    }
+    if let Some(incident) = incident {
+        writeln!(text, "\n## {}\n\n{}\n\n## Backtrace\n\n{}", incident.kind, incident.message, incident.backtrace)?;
+    }
+    write_private(&dir.join("report.txt"), &redactor.redact(&text))?;
+
+    if let Ok(config) = config {
+        let json = serde_json::to_string_pretty(config)?;
+        write_private(&dir.join("config.json"), &redactor.redact(&json))?;
+    }
+    // Only outcomes and timings: prompts and answers are the user's content
+    let entries = history::load().unwrap_or_default();
+    let recent = &entries[entries.len().saturating_sub(HISTORY_ENTRIES)..];
+    write_private(&dir.join("history.txt"), &redactor.redact(&history_summary(recent)))?;
+    Ok(dir)
+}
+
+/// Write a file only the current user can read
+fn write_private(path: &Path, content: &str) -> Result<()> {
+    let mut options = OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    let mut file = options
+        .open(path)
+        .with_context(|| format!("Failed to create {}", path.display()))?;
+    file.write_all(content.as_bytes())?;
+    Ok(())
+}
+
+/// The command line with its values left out, since flags like `--key` and
+/// `--header` carry secrets: flag names, and the leading plain words
+/// (subcommands and entry names) only
+fn command_shape(args: &[String]) -> String {
+    let mut shape = vec!["ecce".to_string()];
+    let mut in_subcommands = true;
+    for arg in args.iter().skip(1) {
+        if arg.starts_with('-') {
+            in_subcommands = false;
+            shape.push(arg.split('=').next().unwrap_or(arg).to_string());
+        } else if in_subcommands && arg.chars().all(|c| c.is_ascii_lowercase() || c == '-') {
+            shape.push(arg.clone());
+        } else {
+            in_subcommands = false;
+            shape.push("<value>".to_string());
+        }
+    }
+    shape.join(" ")
+}
+
+/// One line per generation: when, who, how long, and how it ended
+fn history_summary(entries: &[history::HistoryEntry]) -> String {
+    let mut summary = String::new();
+    for entry in entries {
+        let model = entry
+            .context
+            .as_ref()
+            .and_then(|c| c.model.as_deref())
+            .unwrap_or("-");
+        let outcome = match entry.error {
+            Some(ref error) => format!("error: {}", error.lines().next().unwrap_or_default()),
+            None => "ok".to_string(),
+        };
+        summary.push_str(&format!(
+            "{} {} model={} {}ms {}\n",
+            entry.timestamp.format("%Y-%m-%d %H:%M:%S"),
+            entry.agent,
+            model,
+            entry.duration_ms,
+            outcome
+        ));
+    }
+    summary
+}
+
+/// Remove all but the newest `keep` bundles in `parent`
+fn prune_bundles(parent: &Path, keep: usize) {
+    let Ok(entries) = fs::read_dir(parent) else {
+        return;
+    };
+    let mut bundles: Vec<PathBuf> = entries
+        .flatten()
+        .filter(|e| e.file_name().to_string_lossy().starts_with(BUNDLE_PREFIX))
+        .map(|e| e.path())
+        .collect();
+    // Names start with the time they were written
+    bundles.sort();
+    for old in &bundles[..bundles.len().saturating_sub(keep)] {
+        let _ = fs::remove_dir_all(old);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Profile;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_write_bundle() {
+        let dir = TempDir::new().unwrap();
+        let mut config = Config::default();
+        config.profiles.push(Profile {
+            name: "work".to_string(),
+            url: "https://relay.example.com".to_string(),
+            key: "sk-ant-REDACTED".to_string(),
+            service: "claude-code".to_string(),
+            ..Default::default()
+        });
+        let incident = Incident {
+            kind: "panic",
+            message: "index out of bounds".to_string(),
+            backtrace: "0: ecce::main".to_string(),
+        };
+
+        let bundle = write_bundle(dir.path(), Some(&incident), Ok(&config), None).unwrap();
+        assert!(bundle.file_name().unwrap().to_string_lossy().starts_with(BUNDLE_PREFIX));
+        let report = fs::read_to_string(bundle.join("report.txt")).unwrap();
+        assert!(report.contains("## panic\n\nindex out of bounds\n\n## Backtrace\n\n0: ecce::main"));
+        let config = fs::read_to_string(bundle.join("config.json")).unwrap();
+        assert!(config.contains("relay.example.com"));
+        assert!(!config.contains("secretsecret"));
+        assert!(bundle.join("history.txt").exists());
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = |path: &Path| fs::metadata(path).unwrap().permissions().mode() & 0o777;
+            assert_eq!(mode(&bundle), 0o700);
+            assert_eq!(mode(&bundle.join("report.txt")), 0o600);
+            assert_eq!(mode(&bundle.join("config.json")), 0o600);
+        }
+    }
+
+    #[test]
+    fn test_command_shape() {
+        let args = |line: &str| line.split(' ').map(str::to_string).collect::<Vec<_>>();
+        assert_eq!(
+            command_shape(&args("/usr/bin/ecce api add work --url https://x --key=sk-ant-secret")),
+            "ecce api add work --url <value> --key"
+        );
+        assert_eq!(command_shape(&args("ecce mcp add s --header Authorization:Bearer")), "ecce mcp add s --header <value>");
+    }
+
+    #[test]
+    fn test_prune_bundles() {
+        let dir = TempDir::new().unwrap();
+        for name in ["ecce-report-20260101-000000-1", "ecce-report-20260102-000000-1", "ecce-report-20260103-000000-1", "other"] {
+            fs::create_dir(dir.path().join(name)).unwrap();
+        }
+        prune_bundles(dir.path(), 2);
+        let mut left: Vec<String> = fs::read_dir(dir.path())
+            .unwrap()
+            .map(|e| e.unwrap().file_name().to_string_lossy().to_string())
+            .collect();
+        left.sort();
+        assert_eq!(left, vec!["ecce-report-20260102-000000-1", "ecce-report-20260103-000000-1", "other"]);
+    }
+}
@@ -0,0 +1,474 @@
+use anyhow::{Context, Result};
+use clap::Subcommand;
+use colored::*;
+use ecce::{ui_println, ui_result};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use crate::config::{Config, WatchTarget};
+use crate::provenance::content_hash;
+use crate::remote::RemoteFile;
+
+/// Prefix of launchd labels; the target name follows
+const LAUNCHD_LABEL_PREFIX: &str = "io.github.hmyuuu.ecce.";
+
+#[derive(Subcommand)]
+pub enum ServiceCommand {
+    /// Run `ecce homo --target <target>` at login, as a systemd user unit (Linux) or launchd agent (macOS)
+    Install {
+        /// Target to watch (default: the only configured target)
+        #[arg(short, long)]
+        target: Option<String>,
+    },
+    /// Show whether target services are installed and running
+    Status {
+        /// Only this target (default: every target with a service)
+        #[arg(short, long)]
+        target: Option<String>,
+    },
+    /// Stop a target's service and remove it
+    Uninstall {
+        /// Target whose service to remove (default: the only configured target)
+        #[arg(short, long)]
+        target: Option<String>,
+    },
+}
+
+/// The service manager that starts user services at login on this OS
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Manager {
+    Systemd,
+    Launchd,
+}
+
+impl Manager {
+    fn current() -> Result<Self> {
+        if cfg!(target_os = "macos") {
+            Ok(Manager::Launchd)
+        } else if cfg!(target_os = "linux") {
+            Ok(Manager::Systemd)
+        } else {
+            Err(anyhow::anyhow!("ecce service supports systemd (Linux) and launchd (macOS) only"))
+        }
+    }
+
+    /// The unit name or launchd label for a target
+    fn service_name(self, target: &str) -> String {
+        match self {
+            Manager::Systemd => format!("ecce-{}.service", unit_slug(target)),
+            Manager::Launchd => format!("{}{}", LAUNCHD_LABEL_PREFIX, unit_slug(target)),
+        }
+    }
+
+    fn service_path(self, home: &Path, target: &str) -> PathBuf {
+        match self {
+            Manager::Systemd => home.join(".config/systemd/user").join(self.service_name(target)),
+            Manager::Launchd => home
+                .join("Library/LaunchAgents")
+                .join(format!("{}.plist", self.service_name(target))),
+        }
+    }
+}
+
+pub fn handle_service_command(command: ServiceCommand, config: &Config) -> Result<()> {
+    let manager = Manager::current()?;
+    let home = dirs::home_dir().context("Could not find home directory")?;
+
+    match command {
+        ServiceCommand::Install { target } => {
+            let target = pick_target(config, target.as_deref())?;
+            check_unattended(config, target)?;
+            let exe = std::env::current_exe().context("Failed to locate the ecce executable")?;
+            let path_env = std::env::var("PATH").unwrap_or_default();
+            let path = manager.service_path(&home, &target.name);
+            let name = manager.service_name(&target.name);
+            let dir = working_dir(target, &home);
+            if let Some(dir) = path.parent() {
+                fs::create_dir_all(dir).with_context(|| format!("Failed to create {}", dir.display()))?;
+            }
+
+            match manager {
+                Manager::Systemd => {
+                    fs::write(&path, systemd_unit(&target.name, &exe, &dir, &path_env))
+                        .with_context(|| format!("Failed to write {}", path.display()))?;
+                    run("systemctl", &["--user", "daemon-reload"])?;
+                    run("systemctl", &["--user", "enable", &name])?;
+                    // Picks up a rewritten unit when the service was already running
+                    run("systemctl", &["--user", "restart", &name])?;
+                }
+                Manager::Launchd => {
+                    let log = launchd_log(&target.name)?;
+                    if let Some(dir) = log.parent() {
+                        fs::create_dir_all(dir)?;
+                    }
+                    if path.exists() {
+                        run("launchctl", &["unload", &path.to_string_lossy()]).ok();
+                    }
+                    fs::write(&path, launchd_plist(&target.name, &exe, &dir, &path_env, &log))
+                        .with_context(|| format!("Failed to write {}", path.display()))?;
+                    run("launchctl", &["load", "-w", &path.to_string_lossy()])?;
+                }
+            }
+            ui_println!(
+                "{}",
+                format!("✓ Service for target '{}' installed: {}", target.name, path.display()).green()
+            );
+            ui_println!("  It runs {} at login", format!("ecce homo --target {}", target.name).cyan());
+            ui_println!("  Check it with: {}", format!("ecce service status --target {}", target.name).cyan());
+        }
+        ServiceCommand::Status { target } => {
+            let targets: Vec<&WatchTarget> = match target {
+                Some(ref name) => vec![pick_target(config, Some(name))?],
+                None => {
+                    let mut targets: Vec<&WatchTarget> = config
+                        .targets
+                        .values()
+                        .filter(|t| manager.service_path(&home, &t.name).exists())
+                        .collect();
+                    targets.sort_by(|a, b| a.name.cmp(&b.name));
+                    targets
+                }
+            };
+            if targets.is_empty() {
+                ui_result!("{}", "No target services installed".yellow());
+            }
+            for target in targets {
+                let path = manager.service_path(&home, &target.name);
+                ui_result!("  {}", target.name.cyan());
+                if !path.exists() {
+                    ui_result!("    State:   {}", "not installed".yellow());
+                    continue;
+                }
+                ui_result!("    State:   {}", service_state(manager, &manager.service_name(&target.name)));
+                ui_result!("    File:    {}", path.display().to_string().dimmed());
+                let logs = match manager {
+                    Manager::Systemd => format!("journalctl --user -u {}", manager.service_name(&target.name)),
+                    Manager::Launchd => launchd_log(&target.name)?.display().to_string(),
+                };
+                ui_result!("    Logs:    {}", logs.dimmed());
+            }
+        }
+        ServiceCommand::Uninstall { target } => {
+            let name = match target {
+                Some(name) => name,
+                None => pick_target(config, None)?.name.clone(),
+            };
+            let path = manager.service_path(&home, &name);
+            if !path.exists() {
+                return Err(anyhow::anyhow!("No service installed for target '{}'", name));
+            }
+            match manager {
+                Manager::Systemd => {
+                    // Already stopped or never loaded is fine: the unit is removed either way
+                    run("systemctl", &["--user", "disable", "--now", &manager.service_name(&name)]).ok();
+                    fs::remove_file(&path).with_context(|| format!("Failed to remove {}", path.display()))?;
+                    run("systemctl", &["--user", "daemon-reload"])?;
+                }
+                Manager::Launchd => {
+                    run("launchctl", &["unload", "-w", &path.to_string_lossy()]).ok();
+                    fs::remove_file(&path).with_context(|| format!("Failed to remove {}", path.display()))?;
+                }
+            }
+            ui_println!("{}", format!("✓ Service for target '{}' removed", name).green());
+        }
+    }
+
+    Ok(())
+}
+
+/// The named target, or the only one when no name is given
+fn pick_target<'a>(config: &'a Config, name: Option<&str>) -> Result<&'a WatchTarget> {
+    if let Some(name) = name {
+        return config
+            .get_target(name)
+            .ok_or_else(|| anyhow::anyhow!("Target '{}' not found; add it with `ecce target add`", name));
+    }
+    let mut targets = config.targets.values();
+    match (targets.next(), targets.next()) {
+        (Some(target), None) => Ok(target),
+        (None, _) => Err(anyhow::anyhow!(
+            "No targets configured; add one with `ecce target add <name> <path>`"
+        )),
+        _ => {
+            let mut names: Vec<&str> = config.targets.keys().map(String::as_str).collect();
+            names.sort_unstable();
+            Err(anyhow::anyhow!("Several targets configured; pass --target ({})", names.join(", ")))
+        }
+    }
+}
+
+/// A service has no terminal to pick an agent at, so one must be settled in advance
+fn check_unattended(config: &Config, target: &WatchTarget) -> Result<()> {
+    let preset_agent = target
+        .preset
+        .as_deref()
+        .and_then(|name| config.get_preset(name))
+        .and_then(|preset| preset.agent.as_ref());
+    if target.agent.is_some() || preset_agent.is_some() || config.default_agent.is_some() {
+        return Ok(());
+    }
+    Err(anyhow::anyhow!(
+        "Target '{}' has no agent and there is no default agent; a service can't ask for one. \
+         Set one with `ecce target add {} {} --agent <name>` or `ecce config set default_agent <name>`",
+        target.name,
+        target.name,
+        target.path
+    ))
+}
+
+/// Run a service manager command, failing with its stderr
+fn run(program: &str, args: &[&str]) -> Result<()> {
+    let output = Command::new(program)
+        .args(args)
+        .output()
+        .with_context(|| format!("Failed to run {}", program))?;
+    if !output.status.success() {
+        return Err(anyhow::anyhow!(
+            "`{} {}` failed: {}",
+            program,
+            args.join(" "),
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    Ok(())
+}
+
+/// What the service manager says about a service
+fn service_state(manager: Manager, name: &str) -> ColoredString {
+    match manager {
+        Manager::Systemd => {
+            let state = Command::new("systemctl")
+                .args(["--user", "is-active", name])
+                .output()
+                .map(|o| String::from_utf8_lossy(&o.stdout).trim().to_string())
+                .unwrap_or_default();
+            match state.as_str() {
+                "active" => "running".green(),
+                "" => "unknown".yellow(),
+                other => other.to_string().yellow(),
+            }
+        }
+        Manager::Launchd => match Command::new("launchctl").args(["list", name]).output() {
+            Ok(output) if output.status.success() => {
+                match String::from_utf8_lossy(&output.stdout).contains("\"PID\" =") {
+                    true => "running".green(),
+                    false => "loaded, not running".yellow(),
+                }
+            }
+            _ => "not loaded".yellow(),
+        },
+    }
+}
+
+/// Where a launchd service's output goes; systemd's goes to the journal
+fn launchd_log(target: &str) -> Result<PathBuf> {
+    Ok(Config::config_dir()?.join("logs").join(format!("{}.log", unit_slug(target))))
+}
+
+/// A target name made safe for unit names and labels. Names that needed
+/// changing get a hash of the original, so `a b` and `a-b` don't share a unit.
+fn unit_slug(target: &str) -> String {
+    let slug: String = target
+        .chars()
+        .map(|c| match c.is_ascii_alphanumeric() || matches!(c, '-' | '_') {
+            true => c,
+            false => '-',
+        })
+        .collect();
+    match slug == target {
+        true => slug,
+        false => format!("{}-{}", slug, &content_hash(target)[..8]),
+    }
+}
+
+/// Where the service runs: the target's folder, or home for a remote target
+/// (and for a relative path, which is then read from home as before)
+fn working_dir(target: &WatchTarget, home: &Path) -> PathBuf {
+    let path = Path::new(&target.path);
+    if RemoteFile::parse(&target.path).is_some() || path.is_relative() {
+        return home.to_path_buf();
+    }
+    match path.is_dir() {
+        true => path.to_path_buf(),
+        false => path.parent().unwrap_or(path).to_path_buf(),
+    }
+}
+
+fn systemd_unit(target: &str, exe: &Path, dir: &Path, path_env: &str) -> String {
+    format!(
+        "[Unit]\n\
+         Description=ecce watcher for target {target}\n\
+         \n\
+         [Service]\n\
+         ExecStart={exe} homo --target {name}\n\
+         WorkingDirectory={dir}\n\
+         Restart=on-failure\n\
+         RestartSec=10\n\
+         Environment={path}\n\
+         Environment=ECCE_PLAIN=1\n\
+         \n\
+         [Install]\n\
+         WantedBy=default.target\n",
+        target = target,
+        exe = systemd_quote(&exe.to_string_lossy()),
+        name = systemd_quote(target),
+        dir = systemd_quote(&dir.to_string_lossy()),
+        path = systemd_quote(&format!("PATH={}", path_env)),
+    )
+}
+
+/// A double-quoted systemd word, with `%` and `$` kept literal
+fn systemd_quote(word: &str) -> String {
+    let escaped = word
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('%', "%%")
+        .replace('$', "$$");
+    format!("\"{}\"", escaped)
+}
+
+fn launchd_plist(target: &str, exe: &Path, dir: &Path, path_env: &str, log: &Path) -> String {
+    let string = |s: &str| format!("<string>{}</string>", xml_escape(s));
+    format!(
+        r#"<?xml version="1.0" encoding="UTF-8"?>
+<!DOCTYPE plist PUBLIC "-//Apple//DTD PLIST 1.0//EN" "http://www.apple.com/DTDs/PropertyList-1.0.dtd">
+<plist version="1.0">
+<dict>
+    <key>Label</key>
+    {label}
+    <key>ProgramArguments</key>
+    <array>
+        {exe}
+        <string>homo</string>
+        <string>--target</string>
+        {target}
+    </array>
+    <key>WorkingDirectory</key>
+    {dir}
+    <key>EnvironmentVariables</key>
+    <dict>
+        <key>PATH</key>
+        {path}
+        <key>ECCE_PLAIN</key>
+        <string>1</string>
+    </dict>
+    <key>RunAtLoad</key>
+    <true/>
+    <key>KeepAlive</key>
+    <dict>
+        <key>SuccessfulExit</key>
+        <false/>
+    </dict>
+    <key>StandardOutPath</key>
+    {log}
+    <key>StandardErrorPath</key>
+    {log}
+</dict>
+</plist>
+"#,
+        label = string(&Manager::Launchd.service_name(target)),
+        exe = string(&exe.to_string_lossy()),
+        target = string(target),
+        dir = string(&dir.to_string_lossy()),
+        path = string(path_env),
+        log = string(&log.to_string_lossy()),
+    )
+}
+
+fn xml_escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_service_names() {
+        let home = Path::new("/home/ada");
+        assert_eq!(Manager::Systemd.service_name("notes"), "ecce-notes.service");
+        assert_eq!(
+            Manager::Systemd.service_path(home, "rust talk"),
+            PathBuf::from(format!(
+                "/home/ada/.config/systemd/user/ecce-rust-talk-{}.service",
+                &content_hash("rust talk")[..8]
+            ))
+        );
+        assert_ne!(unit_slug("rust talk"), unit_slug("rust/talk"));
+        assert_ne!(unit_slug("rust talk"), "rust-talk");
+        assert_eq!(
+            Manager::Launchd.service_path(home, "notes"),
+            PathBuf::from("/home/ada/Library/LaunchAgents/io.github.hmyuuu.ecce.notes.plist")
+        );
+    }
+
+    #[test]
+    fn test_systemd_unit() {
+        let unit = systemd_unit("notes", Path::new("/opt/my tools/ecce"), Path::new("/docs"), "/usr/bin:$HOME/bin");
+        assert!(unit.contains("ExecStart=\"/opt/my tools/ecce\" homo --target \"notes\"\n"));
+        assert!(unit.contains("WorkingDirectory=\"/docs\"\n"));
+        assert!(unit.contains("Environment=\"PATH=/usr/bin:$$HOME/bin\"\n"));
+        assert!(unit.contains("Restart=on-failure\n"));
+        assert!(unit.ends_with("WantedBy=default.target\n"));
+    }
+
+    #[test]
+    fn test_launchd_plist() {
+        let plist = launchd_plist(
+            "a&b",
+            Path::new("/usr/local/bin/ecce"),
+            Path::new("/docs"),
+            "/usr/bin",
+            Path::new("/logs/a-b.log"),
+        );
+        assert!(plist.contains(&format!("<string>io.github.hmyuuu.ecce.a-b-{}</string>", &content_hash("a&b")[..8])));
+        assert!(plist.contains("<string>--target</string>\n        <string>a&amp;b</string>"));
+        assert!(plist.contains("<key>WorkingDirectory</key>\n    <string>/docs</string>"));
+        assert!(plist.contains("<key>StandardOutPath</key>\n    <string>/logs/a-b.log</string>"));
+    }
+
+    #[test]
+    fn test_pick_target() {
+        let mut config = Config::default();
+        assert!(pick_target(&config, None).is_err());
+        for name in ["notes", "talk"] {
+            config.targets.insert(
+                name.to_string(),
+                WatchTarget {
+                    name: name.to_string(),
+                    path: format!("/docs/{}.md", name),
+                    ..Default::default()
+                },
+            );
+        }
+        let err = pick_target(&config, None).unwrap_err();
+        assert!(err.to_string().contains("pass --target (notes, talk)"));
+        assert_eq!(pick_target(&config, Some("talk")).unwrap().path, "/docs/talk.md");
+        assert!(pick_target(&config, Some("slides")).is_err());
+
+        // Nothing to answer with: a service can't prompt
+        let notes = config.get_target("notes").unwrap().clone();
+        assert!(check_unattended(&config, &notes).is_err());
+        config.default_agent = Some("writer".to_string());
+        assert!(check_unattended(&config, &notes).is_ok());
+    }
+
+    #[test]
+    fn test_working_dir() {
+        let home = Path::new("/home/ada");
+        let dir = tempfile::tempdir().unwrap();
+        let target = |path: &str| WatchTarget {
+            name: "notes".to_string(),
+            path: path.to_string(),
+            ..Default::default()
+        };
+        assert_eq!(working_dir(&target("/docs/notes.md"), home), PathBuf::from("/docs"));
+        assert_eq!(working_dir(&target(&dir.path().to_string_lossy()), home), dir.path());
+        assert_eq!(working_dir(&target("host:/docs/notes.md"), home), home);
+        assert_eq!(working_dir(&target("docs/notes.md"), home), home);
+    }
+}
@@ -0,0 +1,84 @@
+use anyhow::Result;
+use clap::Subcommand;
+use colored::*;
+use std::path::PathBuf;
+use ecce::{ui_println, ui_result};
+
+use crate::commands::homo;
+use crate::config::Config;
+use crate::session;
+
+#[derive(Subcommand)]
+pub enum SessionCommand {
+    /// Save the latest `ecce homo` session (files, agents, task, flags,
+    /// conversations, and answered patterns) under a name
+    Save {
+        name: String,
+        /// Save the session watching this file instead of the most recent one
+        #[arg(short, long)]
+        file: Option<PathBuf>,
+    },
+    /// Resume a saved session where it left off
+    Restore { name: String },
+    /// List saved sessions
+    List,
+    /// Delete a saved session
+    Delete { name: String },
+}
+
+pub async fn handle_session_command(command: SessionCommand, config: &Config) -> Result<()> {
+    match command {
+        SessionCommand::Save { name, file } => {
+            // Sessions record local files by absolute path
+            let file = file.map(|path| {
+                path.canonicalize()
+                    .unwrap_or(path)
+                    .display()
+                    .to_string()
+            });
+            let saved = session::latest_autosave(file.as_deref())?.ok_or_else(|| match file {
+                Some(ref file) => anyhow::anyhow!("No `ecce homo` session has watched {}", file),
+                None => anyhow::anyhow!("No `ecce homo` session to save yet; start one with `ecce homo <file>`"),
+            })?;
+            session::save(&name, &saved)?;
+            ui_println!(
+                "{}",
+                format!(
+                    "✓ Saved session '{}' ({}, {} answered pattern(s))",
+                    name,
+                    saved.file,
+                    saved.processed.len()
+                )
+                .green()
+            );
+        }
+        SessionCommand::Restore { name } => {
+            let saved = session::load(&name)?;
+            ui_println!("{}", format!("💾 Restoring session '{}'", name).green());
+            homo::resume_session(saved, config).await?;
+        }
+        SessionCommand::List => {
+            let sessions = session::list()?;
+            if sessions.is_empty() {
+                ui_result!("{}", "No saved sessions. Save one with `ecce session save <name>`".yellow());
+            }
+            for (name, saved) in sessions {
+                let agents: Vec<&str> = saved.agents.iter().map(|a| a.name.as_str()).collect();
+                ui_result!(
+                    "{:<16} {} {} {} {}",
+                    name.cyan(),
+                    saved.saved_at.with_timezone(&chrono::Local).format("%Y-%m-%d %H:%M"),
+                    saved.file,
+                    agents.join(",").dimmed(),
+                    format!("({} answered)", saved.processed.len()).dimmed()
+                );
+            }
+        }
+        SessionCommand::Delete { name } => {
+            session::delete(&name)?;
+            ui_println!("{}", format!("✓ Deleted session '{}'", name).green());
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,155 @@
+use anyhow::{Context, Result};
+use clap::Args;
+use colored::*;
+use std::fs;
+use std::path::Path;
+use ecce::{ui_eprintln, ui_println};
+
+use crate::commands::exec;
+use crate::config::Config;
+use crate::exit::GENERIC_FAILURE;
+use crate::remote::shell_quote;
+use crate::ui;
+
+/// Set in the subshell so prompts and scripts can tell which profile is applied
+pub const PROFILE_VAR: &str = "ECCE_PROFILE";
+
+#[derive(Args)]
+pub struct ShellArgs {
+    /// Profile whose endpoint and key to export (default: the project's pinned profile, else the active claude-code one)
+    #[arg(short, long)]
+    pub profile: Option<String>,
+}
+
+/// Extra arguments and variables for the subshell
+#[derive(Debug, Default)]
+struct PromptSetup {
+    args: Vec<String>,
+    env: Vec<(String, String)>,
+}
+
+/// What makes `shell` show `label` before its usual prompt, with any startup
+/// files it needs written to `dir`
+fn prompt_setup(shell: &str, label: &str, dir: &Path) -> Result<PromptSetup> {
+    let indicator = shell_quote(&format!("({}) ", label));
+    let name = Path::new(shell)
+        .file_name()
+        .and_then(|n| n.to_str())
+        .unwrap_or(shell);
+
+    match name {
+        "bash" => {
+            let rcfile = dir.join("bashrc");
+            fs::write(
+                &rcfile,
+                format!("[ -f ~/.bashrc ] && . ~/.bashrc\nPS1={}\"$PS1\"\n", indicator),
+            )?;
+            Ok(PromptSetup {
+                args: vec!["--rcfile".to_string(), rcfile.to_string_lossy().into_owned()],
+                ..Default::default()
+            })
+        }
+        "zsh" => {
+            // zsh reads .zshrc from ZDOTDIR; ours loads the user's own first
+            let user_dir = std::env::var("ZDOTDIR")
+                .ok()
+                .or_else(|| dirs::home_dir().map(|h| h.to_string_lossy().into_owned()))
+                .unwrap_or_default();
+            fs::write(
+                dir.join(".zshrc"),
+                format!(
+                    "ZDOTDIR={dir}\n[ -f {dir}/.zshrc ] && . {dir}/.zshrc\nPROMPT={}\"$PROMPT\"\n",
+                    indicator,
+                    dir = shell_quote(&user_dir)
+                ),
+            )?;
+            Ok(PromptSetup {
+                env: vec![("ZDOTDIR".to_string(), dir.to_string_lossy().into_owned())],
+                ..Default::default()
+            })
+        }
+        "fish" => Ok(PromptSetup {
+            args: vec![
+                "--init-command".to_string(),
+                format!(
+                    "functions -c fish_prompt _ecce_fish_prompt; function fish_prompt; echo -n {}; _ecce_fish_prompt; end",
+                    indicator
+                ),
+            ],
+            ..Default::default()
+        }),
+        // Other shells read PS1 from the environment
+        _ => Ok(PromptSetup {
+            env: vec![(
+                "PS1".to_string(),
+                format!("({}) {}", label, std::env::var("PS1").unwrap_or_else(|_| "$ ".to_string())),
+            )],
+            ..Default::default()
+        }),
+    }
+}
+
+/// Start $SHELL with a profile exported, returning its exit code once the user leaves it
+pub fn handle_shell_command(args: ShellArgs, config: &Config) -> Result<i32> {
+    let profile = exec::select_profile(args.profile.as_deref(), config)?;
+    let shell = std::env::var("SHELL")
+        .ok()
+        .filter(|s| !s.is_empty())
+        .unwrap_or_else(|| "/bin/sh".to_string());
+
+    if let Ok(outer) = std::env::var(PROFILE_VAR) {
+        ui_eprintln!(
+            "{}",
+            format!("⚠ Already in an ecce shell for '{}'; nesting another", outer).yellow()
+        );
+    }
+
+    let startup = tempfile::TempDir::new()?;
+    let setup = prompt_setup(&shell, &format!("ecce:{}", profile.name), startup.path())?;
+    let mut command = exec::command(&profile, std::slice::from_ref(&shell))?;
+    command
+        .args(setup.args)
+        .envs(setup.env)
+        .env(PROFILE_VAR, &profile.name);
+
+    ui::status_to_stderr();
+    ui_println!(
+        "{}",
+        format!("🐚 Shell with profile '{}' ({}). Exit to return.", profile.name, profile.url).green()
+    );
+    let status = command
+        .status()
+        .with_context(|| format!("Failed to start shell '{}'", shell))?;
+    ui_println!("{}", format!("Left profile '{}'", profile.name).dimmed());
+
+    Ok(status.code().unwrap_or(GENERIC_FAILURE))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_prompt_setup_per_shell() {
+        let dir = tempfile::TempDir::new().unwrap();
+
+        let bash = prompt_setup("/bin/bash", "ecce:work", dir.path()).unwrap();
+        assert_eq!(bash.args[0], "--rcfile");
+        assert!(bash.env.is_empty());
+        let rc = fs::read_to_string(&bash.args[1]).unwrap();
+        assert!(rc.contains(". ~/.bashrc"));
+        assert!(rc.contains("PS1='(ecce:work) '\"$PS1\""));
+
+        let zsh = prompt_setup("/usr/bin/zsh", "ecce:work", dir.path()).unwrap();
+        assert!(zsh.args.is_empty());
+        assert_eq!(zsh.env[0].0, "ZDOTDIR");
+        assert!(fs::read_to_string(dir.path().join(".zshrc")).unwrap().contains("PROMPT='(ecce:work) '"));
+
+        let fish = prompt_setup("fish", "ecce:work", dir.path()).unwrap();
+        assert!(fish.args[1].contains("echo -n '(ecce:work) '"));
+
+        let dash = prompt_setup("/bin/dash", "ecce:work", dir.path()).unwrap();
+        assert!(dash.args.is_empty());
+        assert!(dash.env[0].1.starts_with("(ecce:work) "));
+    }
+}
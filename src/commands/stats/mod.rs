@@ -0,0 +1,84 @@
+use anyhow::Result;
+use clap::Args;
+use colored::*;
+use std::collections::BTreeMap;
+use ecce::ui_result;
+
+use crate::history;
+use crate::stats::{Bucket, UsageStats};
+
+mod serve;
+
+/// Port the dashboard listens on unless `--port` says otherwise
+const DEFAULT_PORT: u16 = 7878;
+
+#[derive(Args)]
+pub struct StatsArgs {
+    /// Only include generations on or after this date (YYYY-MM-DD)
+    #[arg(long)]
+    pub since: Option<String>,
+
+    /// Output as JSON
+    #[arg(long, conflicts_with = "serve")]
+    pub json: bool,
+
+    /// Serve a local dashboard with charts of the history (localhost only, no external requests)
+    #[arg(long)]
+    pub serve: bool,
+
+    /// Port for --serve (0 picks a free one)
+    #[arg(long, default_value_t = DEFAULT_PORT, requires = "serve")]
+    pub port: u16,
+}
+
+pub async fn handle_stats_command(args: StatsArgs) -> Result<()> {
+    let since = args.since.as_deref().map(history::parse_since).transpose()?;
+    if args.serve {
+        return serve::serve(args.port, since).await;
+    }
+    let entries = history::filter_since(history::load()?, since);
+    let stats = UsageStats::from_entries(&entries);
+
+    if args.json {
+        println!("{}", serde_json::to_string_pretty(&stats)?);
+        return Ok(());
+    }
+
+    if stats.total.count == 0 {
+        ui_result!("{}", "No generations recorded yet".yellow());
+        return Ok(());
+    }
+
+    ui_result!("{}", "Usage statistics:".bold());
+    ui_result!(
+        "  {} generation(s), {} error(s), avg {}ms, ~{} tokens",
+        stats.total.count,
+        stats.total.errors,
+        stats.total.avg_latency_ms(),
+        stats.total.total_tokens()
+    );
+
+    print_group("By agent", &stats.by_agent);
+    print_group("By profile", &stats.by_profile);
+    print_group("By day", &stats.by_day);
+
+    ui_result!();
+    ui_result!("{}", "Token counts are estimates (~4 characters per token).".dimmed());
+
+    Ok(())
+}
+
+fn print_group(title: &str, group: &BTreeMap<String, Bucket>) {
+    ui_result!();
+    ui_result!("{}", format!("{}:", title).bold());
+    for (name, bucket) in group {
+        ui_result!(
+            "  {:<24} {:>5} runs  {:>7}ms avg  {:>5.1}% errors  ~{} tokens",
+            name.cyan(),
+            bucket.count,
+            bucket.avg_latency_ms(),
+            bucket.error_rate() * 100.0,
+            bucket.total_tokens()
+        );
+    }
+}
@@ -0,0 +1,158 @@
+use anyhow::{Context, Result};
+use chrono::{DateTime, NaiveDate, Utc};
+use colored::*;
+use serde::Serialize;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+use ecce::{ui_eprintln, ui_println};
+
+use crate::history::{self, HistoryEntry};
+use crate::stats::UsageStats;
+
+const DASHBOARD: &str = include_str!("dashboard.html");
+
+/// Generations plotted in the latency chart
+const RECENT_LIMIT: usize = 500;
+
+/// Request heads larger than this are rejected
+const MAX_REQUEST_BYTES: usize = 8192;
+
+/// One generation in the latency chart
+#[derive(Debug, Serialize, PartialEq)]
+struct Recent {
+    timestamp: DateTime<Utc>,
+    agent: String,
+    duration_ms: u64,
+    ok: bool,
+}
+
+#[derive(Debug, Serialize)]
+struct Dashboard {
+    stats: UsageStats,
+    recent: Vec<Recent>,
+}
+
+fn dashboard_data(entries: &[HistoryEntry]) -> Dashboard {
+    let recent = entries
+        .iter()
+        .skip(entries.len().saturating_sub(RECENT_LIMIT))
+        .map(|e| Recent {
+            timestamp: e.timestamp,
+            agent: e.agent.clone(),
+            duration_ms: e.duration_ms,
+            ok: e.is_success(),
+        })
+        .collect();
+    Dashboard {
+        stats: UsageStats::from_entries(entries),
+        recent,
+    }
+}
+
+/// Status line, content type, and body for a request path
+fn route(path: &str, since: Option<NaiveDate>) -> Result<(&'static str, &'static str, String)> {
+    match path.split('?').next().unwrap_or("") {
+        "/" | "/index.html" => Ok(("200 OK", "text/html; charset=utf-8", DASHBOARD.to_string())),
+        "/stats.json" => {
+            // Read on every request so the page follows a running watcher
+            let entries = history::filter_since(history::load()?, since);
+            let body = serde_json::to_string(&dashboard_data(&entries))?;
+            Ok(("200 OK", "application/json", body))
+        }
+        _ => Ok(("404 Not Found", "text/plain", "Not found".to_string())),
+    }
+}
+
+async fn handle(mut stream: TcpStream, since: Option<NaiveDate>) -> Result<()> {
+    let mut request = Vec::new();
+    let mut buf = [0u8; 1024];
+    while !request.windows(4).any(|w| w == b"\r\n\r\n") {
+        let read = stream.read(&mut buf).await?;
+        if read == 0 || request.len() > MAX_REQUEST_BYTES {
+            return Ok(());
+        }
+        request.extend_from_slice(&buf[..read]);
+    }
+
+    let head = String::from_utf8_lossy(&request);
+    let mut parts = head.split_whitespace();
+    let (status, content_type, body) = match (parts.next(), parts.next()) {
+        (Some("GET"), Some(path)) => route(path, since)?,
+        _ => ("405 Method Not Allowed", "text/plain", "Method not allowed".to_string()),
+    };
+
+    let response = format!(
+        "HTTP/1.1 {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nCache-Control: no-store\r\nConnection: close\r\n\r\n{}",
+        status,
+        content_type,
+        body.len(),
+        body
+    );
+    stream.write_all(response.as_bytes()).await?;
+    Ok(())
+}
+
+/// Serve the dashboard on localhost until interrupted
+pub async fn serve(port: u16, since: Option<NaiveDate>) -> Result<()> {
+    let listener = TcpListener::bind(("127.0.0.1", port))
+        .await
+        .with_context(|| format!("Failed to listen on 127.0.0.1:{}", port))?;
+    let address = listener.local_addr()?;
+
+    ui_println!("{}", format!("📊 Dashboard at http://{}/", address).green().bold());
+    ui_println!("   {}", "Only reachable from this machine. Press Ctrl+C to stop.".dimmed());
+
+    loop {
+        tokio::select! {
+            accepted = listener.accept() => {
+                let (stream, _) = accepted?;
+                tokio::spawn(async move {
+                    if let Err(e) = handle(stream, since).await {
+                        ui_eprintln!("Dashboard request failed: {:#}", e);
+                    }
+                });
+            }
+            _ = tokio::signal::ctrl_c() => return Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn entry(minute: u32, ok: bool) -> HistoryEntry {
+        HistoryEntry {
+            timestamp: Utc.with_ymd_and_hms(2025, 3, 1, 9, minute, 0).unwrap(),
+            file: "slides.md".to_string(),
+            agent: "writer".to_string(),
+            prompt: "q".to_string(),
+            response: ok.then(|| "a".to_string()),
+            error: (!ok).then(|| "failed".to_string()),
+            duration_ms: 100 * minute as u64,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_dashboard_data() {
+        let entries = vec![entry(1, true), entry(2, false)];
+        let data = dashboard_data(&entries);
+        assert_eq!(data.stats.total.count, 2);
+        assert_eq!(data.recent.len(), 2);
+        assert_eq!(data.recent[1].duration_ms, 200);
+        assert!(!data.recent[1].ok);
+    }
+
+    #[test]
+    fn test_routes() {
+        let (status, content_type, body) = route("/", None).unwrap();
+        assert_eq!(status, "200 OK");
+        assert!(content_type.starts_with("text/html"));
+        assert!(body.contains("stats.json"));
+        assert!(!body.contains("https://"), "the page must not load anything external");
+
+        assert_eq!(route("/nope", None).unwrap().0, "404 Not Found");
+    }
+}
@@ -0,0 +1,108 @@
+use anyhow::{Context, Result};
+use clap::Subcommand;
+use colored::*;
+use ecce::{ui_eprintln, ui_println, ui_result};
+use std::path::{Path, PathBuf};
+
+use crate::config::{Config, WatchTarget};
+use crate::remote::RemoteFile;
+
+#[derive(Subcommand)]
+pub enum TargetCommand {
+    /// Name a file or folder so `ecce homo <name>` watches it
+    Add {
+        /// Target name (e.g., lecture)
+        name: String,
+        /// File or folder to watch; may be a remote `user@host:/path`
+        path: PathBuf,
+        /// Preset to watch it with
+        #[arg(short, long)]
+        preset: Option<String>,
+        /// Agent to use
+        #[arg(short, long)]
+        agent: Option<String>,
+        /// Task template to use
+        #[arg(short, long)]
+        task: Option<String>,
+    },
+    /// List all targets
+    #[command(alias = "ls")]
+    List,
+    /// Delete a target
+    Delete {
+        /// Target name to delete
+        name: String,
+    },
+}
+
+pub fn handle_target_command(command: TargetCommand, config: &mut Config) -> Result<()> {
+    match command {
+        TargetCommand::Add {
+            name,
+            path,
+            preset,
+            agent,
+            task,
+        } => {
+            let target = WatchTarget {
+                name: name.clone(),
+                path: target_path(&path)?,
+                preset,
+                agent,
+                task,
+            };
+            config.check_target(&target)?;
+            let path = target.path.clone();
+            config.add_target(target)?;
+            ui_println!(
+                "{}",
+                format!("✓ Target '{}' added: {}", name, path).green()
+            );
+            ui_println!("  Watch it with: {}", format!("ecce homo {}", name).cyan());
+        }
+        TargetCommand::List => {
+            if config.targets.is_empty() {
+                ui_result!("{}", "No targets configured".yellow());
+            } else {
+                ui_result!("{}", "Available targets:".bold());
+                let mut targets: Vec<&WatchTarget> = config.targets.values().collect();
+                targets.sort_by(|a, b| a.name.cmp(&b.name));
+                for target in targets {
+                    ui_result!("  {}", target.name.cyan());
+                    ui_result!("    Path:   {}", target.path.dimmed());
+                    if let Some(ref preset) = target.preset {
+                        ui_result!("    Preset: {}", preset.dimmed());
+                    }
+                    if let Some(ref agent) = target.agent {
+                        ui_result!("    Agent:  {}", agent.dimmed());
+                    }
+                    if let Some(ref task) = target.task {
+                        ui_result!("    Task:   {}", task.dimmed());
+                    }
+                }
+            }
+        }
+        TargetCommand::Delete { name } => {
+            if config.delete_target(&name)? {
+                ui_println!("{}", format!("✓ Target '{}' deleted", name).green());
+            } else {
+                ui_eprintln!("{}", format!("✗ Target '{}' not found", name).red());
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// A local path made absolute, so the target works from any directory; remote paths are kept as given
+fn target_path(path: &Path) -> Result<String> {
+    if !path.exists() {
+        if let Some(spec) = path.to_str().filter(|spec| RemoteFile::parse(spec).is_some()) {
+            return Ok(spec.to_string());
+        }
+    }
+    let path = path
+        .canonicalize()
+        .with_context(|| format!("File or folder not found: {}", path.display()))?;
+    Ok(path.to_string_lossy().to_string())
+}
@@ -1,10 +1,13 @@
 use anyhow::{Context, Result};
 use clap::Subcommand;
 use colored::*;
+use ecce::{ui_eprintln, ui_println, ui_result};
 
-use crate::config::{Config, Task};
+use crate::config::{Config, Polish, Task, TaskConstraints, TaskOutput, Translation};
+use crate::ui;
 
 #[derive(Subcommand)]
+#[allow(clippy::large_enum_variant)] // parsed once per run; size is irrelevant
 pub enum TaskCommand {
     /// Add a new task
     Add {
@@ -16,6 +19,39 @@ pub enum TaskCommand {
         /// File containing the task prompt
         #[arg(short = 'f', long, conflicts_with = "prompt")]
         prompt_file: Option<String>,
+        /// Expected answer: text (Markdown slides) or diagram (a single Mermaid/PlantUML block)
+        #[arg(short, long, default_value = "text")]
+        output: TaskOutput,
+        /// Maximum words per answer
+        #[arg(long)]
+        max_words: Option<usize>,
+        /// Maximum bullet points per answer
+        #[arg(long)]
+        max_bullets: Option<usize>,
+        /// Phrase the answer must not contain (repeatable)
+        #[arg(long = "must-not-contain", value_name = "PHRASE")]
+        must_not_contain: Vec<String>,
+        /// Base task to extend; use {{base}} in the prompt to place its template
+        #[arg(long)]
+        extends: Option<String>,
+        /// Translate each answer into this language (e.g. ja) with a second, quick generation
+        #[arg(long, value_name = "LANG")]
+        translate_to: Option<String>,
+        /// With --translate-to, write the original answer followed by its translation
+        #[arg(long, requires = "translate_to")]
+        bilingual: bool,
+        /// With --translate-to, the model for the translation (default: haiku)
+        #[arg(long, requires = "translate_to")]
+        translate_model: Option<String>,
+        /// Proofread each answer for spelling and grammar before it is written
+        #[arg(long)]
+        polish: bool,
+        /// Agent that proofreads instead of the built-in proofreader (implies --polish)
+        #[arg(long, value_name = "AGENT")]
+        polish_agent: Option<String>,
+        /// Model for proofreading (implies --polish)
+        #[arg(long, value_name = "MODEL")]
+        polish_model: Option<String>,
     },
     /// List all tasks
     #[command(alias = "ls")]
@@ -25,6 +61,17 @@ pub enum TaskCommand {
         /// Task name to delete
         name: String,
     },
+    /// Export task(s) as Claude Code slash commands in .claude/commands/
+    Export {
+        /// Task name to export (exports all if not specified)
+        name: Option<String>,
+        /// Write each task as a custom slash command (/<task> <question>)
+        #[arg(long, required = true)]
+        as_commands: bool,
+        /// Export to user-level directory (~/.claude/commands/)
+        #[arg(short, long)]
+        user: bool,
+    },
 }
 
 pub fn handle_task_command(command: TaskCommand, config: &mut Config) -> Result<()> {
@@ -33,7 +80,29 @@ pub fn handle_task_command(command: TaskCommand, config: &mut Config) -> Result<
             name,
             prompt,
             prompt_file,
+            output,
+            max_words,
+            max_bullets,
+            must_not_contain,
+            extends,
+            translate_to,
+            bilingual,
+            translate_model,
+            polish,
+            polish_agent,
+            polish_model,
         } => {
+            if let Some(ref base) = extends {
+                if config.get_task(base).is_none() {
+                    return Err(anyhow::anyhow!("Base task '{}' not found", base));
+                }
+            }
+            if let Some(ref agent) = polish_agent {
+                if config.get_agent(agent).is_none() {
+                    return Err(anyhow::anyhow!("Agent '{}' not found", agent));
+                }
+            }
+
             // Get prompt from either direct input or file
             let task_prompt = match (prompt, prompt_file) {
                 (Some(p), None) => p,
@@ -41,6 +110,8 @@ pub fn handle_task_command(command: TaskCommand, config: &mut Config) -> Result<
                     std::fs::read_to_string(&f)
                         .with_context(|| format!("Failed to read prompt file: {}", f))?
                 }
+                // An extending task may reuse its base template unchanged
+                (None, None) if extends.is_some() => String::new(),
                 (None, None) => {
                     return Err(anyhow::anyhow!(
                         "Either --prompt or --prompt-file must be provided"
@@ -56,48 +127,110 @@ pub fn handle_task_command(command: TaskCommand, config: &mut Config) -> Result<
             let task = Task {
                 name: name.clone(),
                 template: task_prompt,
+                output,
+                constraints: TaskConstraints {
+                    max_words,
+                    max_bullets,
+                    must_not_contain,
+                },
+                extends,
+                translate_to: translate_to.map(|lang| Translation {
+                    lang,
+                    bilingual,
+                    model: translate_model,
+                }),
+                polish: (polish || polish_agent.is_some() || polish_model.is_some()).then_some(Polish {
+                    agent: polish_agent,
+                    model: polish_model,
+                }),
+            };
+
+            // Reject the task if it would close an inheritance cycle
+            let previous = config.tasks.insert(name.clone(), task.clone());
+            let resolved = config.resolve_task(&name);
+            match previous {
+                Some(previous) => config.tasks.insert(name.clone(), previous),
+                None => config.tasks.remove(&name),
             };
+            resolved?;
 
             config.add_task(task)?;
-            println!(
+            ui_println!(
                 "{}",
                 format!("✓ Task '{}' added successfully", name).green()
             );
         }
         TaskCommand::List => {
             if config.tasks.is_empty() {
-                println!("{}", "No tasks configured".yellow());
+                ui_result!("{}", "No tasks configured".yellow());
             } else {
-                println!("{}", "Available tasks:".bold());
+                ui_result!("{}", "Available tasks:".bold());
                 for (name, task) in &config.tasks {
-                    println!("  {}", name.cyan());
+                    match task.extends {
+                        Some(ref base) => ui_result!("  {} {}", name.cyan(), format!("(extends {})", base).dimmed()),
+                        None => ui_result!("  {}", name.cyan()),
+                    }
 
                     // Show truncated prompt
-                    let prompt_preview = task
-                        .template
-                        .lines()
-                        .next()
-                        .unwrap_or("")
-                        .chars()
-                        .take(100)
-                        .collect::<String>();
-                    let prompt_display = if task.template.len() > 100 {
-                        format!("{}...", prompt_preview)
-                    } else {
-                        prompt_preview
-                    };
-                    println!("    Prompt: {}", prompt_display.dimmed());
+                    ui_result!("    Prompt: {}", ui::preview(&task.template, 100).dimmed());
+                    if task.output == TaskOutput::Diagram {
+                        ui_result!("    Output: {}", "diagram".dimmed());
+                    }
+                    if !task.constraints.is_empty() {
+                        ui_result!("    Limits: {}", describe_constraints(&task.constraints).dimmed());
+                    }
+                    if let Some(ref translation) = task.translate_to {
+                        let layout = if translation.bilingual { " (bilingual)" } else { "" };
+                        ui_result!("    Translate: {}{}", translation.lang, layout.dimmed());
+                    }
+                    if let Some(ref polish) = task.polish {
+                        ui_result!("    Polish: {}", polish.agent.as_deref().unwrap_or("built-in proofreader").dimmed());
+                    }
                 }
             }
         }
         TaskCommand::Delete { name } => {
             if config.delete_task(&name)? {
-                println!("{}", format!("✓ Task '{}' deleted", name).green());
+                ui_println!("{}", format!("✓ Task '{}' deleted", name).green());
             } else {
-                println!("{}", format!("✗ Task '{}' not found", name).red());
+                ui_eprintln!("{}", format!("✗ Task '{}' not found", name).red());
+            }
+        }
+        TaskCommand::Export { name, as_commands: _, user } => {
+            let location = if user { "~/.claude/commands/" } else { ".claude/commands/" };
+            if let Some(task_name) = name {
+                config.export_task_as_command(&task_name, user)?;
+                ui_println!(
+                    "{}",
+                    format!("✓ Task '{}' exported to {} as /{}", task_name, location, task_name).green()
+                );
+            } else {
+                let exported = config.export_all_tasks_as_commands(user)?;
+                ui_println!(
+                    "{}",
+                    format!("✓ Exported {} task(s) to {}", exported.len(), location).green()
+                );
+                for name in exported {
+                    ui_println!("  - /{}", name.cyan());
+                }
             }
         }
     }
 
     Ok(())
 }
+
+/// One-line summary of a task's constraints, e.g. "≤120 words, ≤5 bullets"
+fn describe_constraints(constraints: &TaskConstraints) -> String {
+    let mut parts = Vec::new();
+    if let Some(words) = constraints.max_words {
+        parts.push(format!("≤{} words", words));
+    }
+    if let Some(bullets) = constraints.max_bullets {
+        parts.push(format!("≤{} bullets", bullets));
+    }
+    if !constraints.must_not_contain.is_empty() {
+        parts.push(format!("avoid {}", constraints.must_not_contain.join(", ")));
+    }
+    parts.join(", ")
+}
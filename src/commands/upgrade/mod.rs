@@ -0,0 +1,253 @@
+use anyhow::{Context, Result};
+use clap::Args;
+use colored::*;
+use ecce::{ui_println, ui_result};
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use crate::capability::Version;
+use crate::exit::Failure;
+
+/// Where the latest release is looked up
+const LATEST_RELEASE_URL: &str = "https://api.github.com/repos/hmyuuu/ecce/releases/latest";
+
+/// Version of the running binary
+const CURRENT_VERSION: &str = env!("CARGO_PKG_VERSION");
+
+#[derive(Args)]
+pub struct UpgradeArgs {
+    /// Only report whether a newer release exists; exits with code 8 if one does
+    #[arg(long)]
+    pub check: bool,
+
+    /// Reinstall the latest release even if this version is as new
+    #[arg(long)]
+    pub force: bool,
+}
+
+/// The parts of a GitHub release that matter here
+#[derive(Debug, Deserialize)]
+struct Release {
+    tag_name: String,
+    #[serde(default)]
+    assets: Vec<Asset>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Asset {
+    name: String,
+    browser_download_url: String,
+}
+
+pub async fn handle_upgrade_command(args: UpgradeArgs) -> Result<()> {
+    let client = reqwest::Client::builder()
+        .timeout(Duration::from_secs(120))
+        .user_agent(format!("ecce/{}", CURRENT_VERSION))
+        .build()?;
+    let release: Release = async {
+        client
+            .get(LATEST_RELEASE_URL)
+            .header("Accept", "application/vnd.github+json")
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await
+    }
+    .await
+    .context(Failure::Connectivity)
+    .context("Failed to look up the latest ecce release")?;
+
+    let latest = release.tag_name.trim_start_matches('v');
+    let newer = is_newer(latest, CURRENT_VERSION);
+    if args.check {
+        if newer {
+            return Err(Failure::UpdateAvailable.error(format!(
+                "ecce {} is available (this is {}); run `ecce upgrade`",
+                latest, CURRENT_VERSION
+            )));
+        }
+        ui_result!("{}", format!("✓ ecce {} is the latest release", CURRENT_VERSION).green());
+        return Ok(());
+    }
+    if !newer && !args.force {
+        ui_result!("{}", format!("✓ ecce {} is the latest release", CURRENT_VERSION).green());
+        return Ok(());
+    }
+
+    let name = asset_name(std::env::consts::ARCH, std::env::consts::OS)?;
+    let binary = find_asset(&release, &name)?;
+    let checksum = find_asset(&release, &format!("{}.sha256", name))?;
+
+    ui_println!("{}", format!("⬇️  Downloading ecce {} ({})", latest, name).yellow());
+    let download = |url: String| {
+        let client = client.clone();
+        async move {
+            let response = client.get(&url).send().await?.error_for_status()?;
+            anyhow::Ok(response.bytes().await?.to_vec())
+        }
+    };
+    let bytes = download(binary.browser_download_url.clone())
+        .await
+        .context(Failure::Connectivity)
+        .with_context(|| format!("Failed to download {}", binary.name))?;
+    let expected = download(checksum.browser_download_url.clone())
+        .await
+        .context(Failure::Connectivity)
+        .with_context(|| format!("Failed to download {}", checksum.name))?;
+    verify_checksum(&bytes, &String::from_utf8_lossy(&expected))?;
+
+    let exe = std::env::current_exe().context("Failed to locate the ecce executable")?;
+    let exe = exe.canonicalize().unwrap_or(exe);
+    replace_executable(&exe, &bytes)?;
+    ui_println!(
+        "{}",
+        format!("✓ Upgraded ecce {} → {} ({})", CURRENT_VERSION, latest, exe.display()).green()
+    );
+    Ok(())
+}
+
+/// Whether release `latest` is newer than `current`; a pre-release build
+/// (e.g. `1.0.0-DEV`) is older than the release of the same number
+fn is_newer(latest: &str, current: &str) -> bool {
+    match (Version::parse(latest), Version::parse(current)) {
+        (Some(l), Some(c)) => l > c || (l == c && current.contains('-') && !latest.contains('-')),
+        // An unparseable tag is never offered as an upgrade
+        _ => false,
+    }
+}
+
+/// The release asset built for this platform, e.g. `ecce-x86_64-unknown-linux-gnu`
+fn asset_name(arch: &str, os: &str) -> Result<String> {
+    let platform = match os {
+        "linux" => "unknown-linux-gnu",
+        "macos" => "apple-darwin",
+        "windows" => "pc-windows-msvc",
+        other => return Err(anyhow::anyhow!("No ecce release builds for {}; install with cargo", other)),
+    };
+    let suffix = if os == "windows" { ".exe" } else { "" };
+    Ok(format!("ecce-{}-{}{}", arch, platform, suffix))
+}
+
+fn find_asset<'a>(release: &'a Release, name: &str) -> Result<&'a Asset> {
+    release.assets.iter().find(|a| a.name == name).ok_or_else(|| {
+        anyhow::anyhow!("Release {} has no {} asset; install with cargo", release.tag_name, name)
+    })
+}
+
+/// Check the download against a `.sha256` file: the hex digest, optionally followed by the file name
+fn verify_checksum(bytes: &[u8], checksum_file: &str) -> Result<()> {
+    let expected = checksum_file
+        .split_whitespace()
+        .next()
+        .map(str::to_lowercase)
+        .filter(|hex| hex.len() == 64 && hex.chars().all(|c| c.is_ascii_hexdigit()))
+        .context("The release's checksum file holds no SHA-256 digest")?;
+    let actual = format!("{:x}", Sha256::digest(bytes));
+    if actual != expected {
+        return Err(anyhow::anyhow!(
+            "Checksum mismatch: expected {}, downloaded file has {}; nothing was replaced",
+            expected,
+            actual
+        ));
+    }
+    Ok(())
+}
+
+/// Swap in the new binary next to the old one, so the final rename is atomic
+fn replace_executable(exe: &Path, bytes: &[u8]) -> Result<()> {
+    let staged = sibling(exe, "new");
+    fs::write(&staged, bytes).with_context(|| format!("Failed to write {}", staged.display()))?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        fs::set_permissions(&staged, fs::Permissions::from_mode(0o755))?;
+    }
+    // Windows can't overwrite a running executable, but it can rename it
+    #[cfg(windows)]
+    let old = {
+        let old = sibling(exe, "old");
+        let _ = fs::remove_file(&old);
+        fs::rename(exe, &old).with_context(|| {
+            let _ = fs::remove_file(&staged);
+            format!("Failed to move {} aside", exe.display())
+        })?;
+        old
+    };
+    fs::rename(&staged, exe).with_context(|| {
+        let _ = fs::remove_file(&staged);
+        // Put the old binary back, so a failed upgrade still leaves one
+        #[cfg(windows)]
+        let _ = fs::rename(&old, exe);
+        format!("Failed to replace {}", exe.display())
+    })
+}
+
+/// `ecce` -> `ecce.<extension>` in the same directory
+fn sibling(exe: &Path, extension: &str) -> PathBuf {
+    let mut name = exe.file_name().unwrap_or_default().to_os_string();
+    name.push(format!(".{}", extension));
+    exe.with_file_name(name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_newer() {
+        assert!(is_newer("1.2.0", "1.1.9"));
+        assert!(!is_newer("1.1.0", "1.2.0"));
+        assert!(!is_newer("1.2.0", "1.2.0"));
+        // A development build is older than its release, but not than itself
+        assert!(is_newer("1.0.0", "1.0.0-DEV"));
+        assert!(!is_newer("1.0.0-rc.1", "1.0.0-DEV"));
+        assert!(!is_newer("nightly", "1.0.0"));
+    }
+
+    #[test]
+    fn test_asset_name() {
+        assert_eq!(asset_name("x86_64", "linux").unwrap(), "ecce-x86_64-unknown-linux-gnu");
+        assert_eq!(asset_name("aarch64", "macos").unwrap(), "ecce-aarch64-apple-darwin");
+        assert_eq!(asset_name("x86_64", "windows").unwrap(), "ecce-x86_64-pc-windows-msvc.exe");
+        assert!(asset_name("x86_64", "freebsd").is_err());
+
+        // The release workflow builds an asset under each of these names
+        let workflow = include_str!("../../../.github/workflows/release.yml");
+        for (arch, os) in [("x86_64", "linux"), ("aarch64", "linux"), ("x86_64", "macos"), ("aarch64", "macos")] {
+            let triple = asset_name(arch, os).unwrap().replacen("ecce-", "", 1);
+            assert!(workflow.contains(&format!("target: {},", triple)), "{}", triple);
+        }
+        assert!(workflow.contains("{ target: x86_64-pc-windows-msvc, os: windows-latest, ext: .exe }"));
+        assert!(workflow.contains("asset=\"ecce-${{ matrix.target }}${{ matrix.ext }}\""));
+        assert!(workflow.contains("${{ env.ASSET }}.sha256"));
+    }
+
+    #[test]
+    fn test_verify_checksum() {
+        let digest = format!("{:x}", Sha256::digest(b"binary"));
+        verify_checksum(b"binary", &format!("{}  ecce-x86_64-unknown-linux-gnu\n", digest)).unwrap();
+        verify_checksum(b"binary", &digest.to_uppercase()).unwrap();
+        let err = verify_checksum(b"tampered", &digest).unwrap_err();
+        assert!(err.to_string().contains("Checksum mismatch"));
+        assert!(verify_checksum(b"binary", "<html>Not Found</html>").is_err());
+    }
+
+    #[test]
+    fn test_replace_executable() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let exe = dir.path().join("ecce");
+        fs::write(&exe, "old").unwrap();
+        replace_executable(&exe, b"new").unwrap();
+        assert_eq!(fs::read_to_string(&exe).unwrap(), "new");
+        assert!(!sibling(&exe, "new").exists());
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            assert_eq!(fs::metadata(&exe).unwrap().permissions().mode() & 0o777, 0o755);
+        }
+    }
+}
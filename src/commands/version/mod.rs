@@ -0,0 +1,130 @@
+use anyhow::Result;
+use clap::Args;
+use ecce::ui_result;
+
+use crate::capability::Capabilities;
+use crate::config::Config;
+use crate::redact::{Redactor, REDACTED};
+
+/// Version of this build, with the commit and date it was built from
+pub const VERSION: &str = env!("CARGO_PKG_VERSION");
+pub const GIT_HASH: &str = env!("ECCE_GIT_HASH");
+pub const BUILD_DATE: &str = env!("ECCE_BUILD_DATE");
+
+#[derive(Args)]
+pub struct VersionArgs {
+    /// Add the platform, claude version, config path, and active profiles, for bug reports
+    #[arg(short, long)]
+    pub verbose: bool,
+}
+
+/// Print the version; verbose output works even when the config can't be loaded
+pub async fn handle_version_command(args: VersionArgs, config: Result<Config>) -> Result<()> {
+    if !args.verbose {
+        ui_result!("ecce {} ({} {})", VERSION, GIT_HASH, BUILD_DATE);
+        return Ok(());
+    }
+    let claude = match config {
+        Ok(ref config) => Some(claude_version(config).await),
+        Err(_) => None,
+    };
+    // Plain text, so it pastes cleanly into an issue
+    for (label, value) in report(config.as_ref(), claude) {
+        ui_result!("{:<15} {}", format!("{}:", label), value);
+    }
+    Ok(())
+}
+
+/// The detected claude version and the executable it came from
+pub async fn claude_version(config: &Config) -> String {
+    let executable = config.get_claude_executable();
+    match Capabilities::detect(&executable).await.version {
+        Some(version) => format!("{} ({})", version, executable),
+        None => format!("not found or unknown version ({})", executable),
+    }
+}
+
+/// Labeled lines of the bug report, secrets masked; `claude` is the result
+/// of `claude_version`, when it was checked
+pub fn report(config: Result<&Config, &anyhow::Error>, claude: Option<String>) -> Vec<(String, String)> {
+    let mut lines = vec![
+        ("ecce".to_string(), VERSION.to_string()),
+        ("commit".to_string(), GIT_HASH.to_string()),
+        ("built".to_string(), BUILD_DATE.to_string()),
+        ("os".to_string(), format!("{} {}", std::env::consts::OS, std::env::consts::ARCH)),
+    ];
+    let path = match Config::config_path() {
+        Ok(path) if path.exists() => path.display().to_string(),
+        Ok(path) => format!("{} (not created yet)", path.display()),
+        Err(e) => format!("unavailable ({})", e),
+    };
+    let config = match config {
+        Ok(config) => config,
+        Err(e) => {
+            lines.push(("config".to_string(), format!("{} (failed to load: {:#})", path, e)));
+            return lines;
+        }
+    };
+
+    if let Some(claude) = claude {
+        lines.push(("claude".to_string(), claude));
+    }
+    lines.push(("config".to_string(), path));
+
+    let redactor = Redactor::from_config(config);
+    if config.active_profiles.is_empty() {
+        lines.push(("active profile".to_string(), "none".to_string()));
+    }
+    for (service, name) in &config.active_profiles {
+        let value = match config.resolve_profile(name) {
+            Ok(profile) => format!(
+                "{} ({}, {}, key {})",
+                profile.name,
+                service,
+                redactor.redact(&profile.url),
+                if profile.key.is_empty() { "not set" } else { REDACTED }
+            ),
+            Err(e) => format!("{} ({}, {:#})", name, service, e),
+        };
+        lines.push(("active profile".to_string(), value));
+    }
+    lines
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Profile;
+
+    #[tokio::test]
+    async fn test_report_masks_keys() {
+        let mut config = Config {
+            claude_executable: Some("/nonexistent/claude".to_string()),
+            ..Default::default()
+        };
+        config.profiles.push(Profile {
+            name: "work".to_string(),
+            url: "https://relay.example.com".to_string(),
+            key: "sk-ant-REDACTED".to_string(),
+            service: "claude-code".to_string(),
+            ..Default::default()
+        });
+        config.active_profiles.insert("claude-code".to_string(), "work".to_string());
+
+        let lines = report(Ok(&config), Some(claude_version(&config).await));
+        let text = format!("{:?}", lines);
+        assert!(!text.contains("secretsecret"));
+        let value = |label: &str| lines.iter().find(|(l, _)| l == label).map(|(_, v)| v.clone()).unwrap();
+        assert_eq!(value("ecce"), VERSION);
+        assert_eq!(
+            value("active profile"),
+            format!("work (claude-code, https://relay.example.com, key {})", REDACTED)
+        );
+        assert!(value("claude").starts_with("not found"));
+
+        // A broken config still yields the build lines
+        let err = anyhow::anyhow!("expected value at line 3");
+        let lines = report(Err(&err), None);
+        assert!(lines.last().unwrap().1.contains("failed to load: expected value at line 3"));
+    }
+}
@@ -1,19 +1,112 @@
 use anyhow::{Context, Result};
+use chrono::{DateTime, NaiveDate, Utc};
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use std::fs;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::io::Write;
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
+use crate::delivery::DeliveryTarget;
+use crate::guard::{Guard, GuardRule};
+use crate::template;
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
 pub struct Profile {
     pub name: String,
+    /// Empty to inherit from `base`
+    #[serde(default)]
     pub url: String,
+    /// Empty to inherit from `base`
+    #[serde(default)]
     pub key: String,
+    /// Empty to inherit from `base`
+    #[serde(default)]
     pub service: String,
+    /// Profile to take unset fields from, e.g. a shared relay with one key per profile
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub base: Option<String>,
+    /// When the current key was added
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub created_at: Option<DateTime<Utc>>,
+    /// Last day the current key is valid
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub expires_at: Option<NaiveDate>,
+    /// Keys replaced by `ecce api rotate`, oldest first
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub key_history: Vec<ArchivedKey>,
+}
+
+/// A profile key retired by rotation
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct ArchivedKey {
+    pub key: String,
+    #[serde(default)]
+    pub created_at: Option<DateTime<Utc>>,
+    pub retired_at: DateTime<Utc>,
+}
+
+/// Keys expiring within this many days are flagged by `ecce api list` and `status`
+pub const KEY_EXPIRY_WARNING_DAYS: i64 = 7;
+
+impl Profile {
+    /// Days left before the key expires (negative once expired), if an expiry is set
+    pub fn days_until_expiry(&self, today: NaiveDate) -> Option<i64> {
+        self.expires_at.map(|date| (date - today).num_days())
+    }
+
+    /// A warning for a key that has expired or expires soon
+    pub fn expiry_warning(&self, today: NaiveDate) -> Option<String> {
+        match self.days_until_expiry(today)? {
+            days if days < 0 => Some(format!("key expired {} day(s) ago", -days)),
+            0 => Some("key expires today".to_string()),
+            days if days <= KEY_EXPIRY_WARNING_DAYS => {
+                Some(format!("key expires in {} day(s)", days))
+            }
+            _ => None,
+        }
+    }
+
+    /// Environment variables that point the profile's service at its endpoint and key;
+    /// `None` for an unknown service
+    pub fn env_vars(&self) -> Option<Vec<(&'static str, String)>> {
+        let (url, key) = match self.service.as_str() {
+            CLAUDE_CODE_SERVICE => ("ANTHROPIC_BASE_URL", "ANTHROPIC_API_KEY"),
+            "codex" => ("OPENAI_BASE_URL", "OPENAI_API_KEY"),
+            _ => return None,
+        };
+        Some(vec![(url, self.url.clone()), (key, self.key.clone())])
+    }
+
+    /// Replace the key, archiving the old one in `key_history`. Without `expires_at`,
+    /// the new key gets the same lifetime as the old one when that is known.
+    pub fn rotate(&mut self, key: String, expires_at: Option<NaiveDate>, now: DateTime<Utc>) -> Result<()> {
+        if self.key.is_empty() {
+            return Err(anyhow::anyhow!(
+                "Profile '{}' inherits its key from '{}'; rotate that profile instead",
+                self.name,
+                self.base.as_deref().unwrap_or("its base")
+            ));
+        }
+        if self.key == key {
+            return Err(anyhow::anyhow!("The new key is the same as the current one"));
+        }
+
+        let lifetime = self
+            .created_at
+            .zip(self.expires_at)
+            .map(|(created, expires)| expires - created.date_naive());
+        self.key_history.push(ArchivedKey {
+            key: std::mem::replace(&mut self.key, key),
+            created_at: self.created_at,
+            retired_at: now,
+        });
+        self.created_at = Some(now);
+        self.expires_at = expires_at.or_else(|| lifetime.map(|l| now.date_naive() + l));
+        Ok(())
+    }
 }
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
 pub struct Agent {
     pub name: String,
     pub description: Option<String>,
@@ -21,23 +114,412 @@ pub struct Agent {
     pub context_files: Vec<String>,
     pub tools: Option<Vec<String>>,
     pub model: Option<String>,
+    /// Sampling temperature (not supported by the claude CLI; kept for API backends)
+    #[serde(default)]
+    pub temperature: Option<f32>,
+    /// Maximum output tokens per response
+    #[serde(default)]
+    pub max_output_tokens: Option<u32>,
+    /// Enable or disable extended thinking
+    #[serde(default)]
+    pub thinking: Option<bool>,
+    /// claude CLI permission mode (default, acceptEdits, plan, bypassPermissions)
+    #[serde(default)]
+    pub permission_mode: Option<String>,
+    /// Working directory for the claude subprocess (defaults to ecce's own)
+    #[serde(default)]
+    pub working_dir: Option<String>,
+    /// Extra environment variables for the claude subprocess
+    #[serde(default)]
+    pub env: HashMap<String, String>,
+    /// Base agent this one extends (see `Config::resolve_agent`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub extends: Option<String>,
 }
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
 pub struct Task {
     pub name: String,
     pub template: String,
+    /// Kind of answer the task expects
+    #[serde(default)]
+    pub output: TaskOutput,
+    /// Limits checked after generation; violations trigger a fix-up turn
+    #[serde(default)]
+    pub constraints: TaskConstraints,
+    /// Base task this one extends (see `Config::resolve_task`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub extends: Option<String>,
+    /// Translate each answer after it is generated
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub translate_to: Option<Translation>,
+    /// Proofread each answer before it is written
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub polish: Option<Polish>,
+}
+
+/// A proofreading pass run on each answer: `"polish": {}` for the built-in
+/// proofreader, `"polish": "editor"` for an agent of your own, or an object
+/// with `agent` and `model`
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
+#[serde(from = "PolishSpec")]
+pub struct Polish {
+    /// Agent that proofreads (default: a built-in proofreader)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub agent: Option<String>,
+    /// Model for the proofreading call (default: the agent's, else haiku)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub model: Option<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum PolishSpec {
+    Agent(String),
+    Full {
+        #[serde(default)]
+        agent: Option<String>,
+        #[serde(default)]
+        model: Option<String>,
+    },
+}
+
+impl From<PolishSpec> for Polish {
+    fn from(spec: PolishSpec) -> Self {
+        match spec {
+            PolishSpec::Agent(agent) => Polish {
+                agent: Some(agent),
+                model: None,
+            },
+            PolishSpec::Full { agent, model } => Polish { agent, model },
+        }
+    }
+}
+
+/// A translation pass run on each answer: `"translate_to": "ja"`, or an object
+/// with `lang`, `bilingual`, and `model`
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
+#[serde(from = "TranslationSpec")]
+pub struct Translation {
+    /// Language code, e.g. `ja`
+    pub lang: String,
+    /// Keep the original answer and put the translation after it
+    #[serde(default)]
+    pub bilingual: bool,
+    /// Model for the translation call (default: haiku)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub model: Option<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum TranslationSpec {
+    Lang(String),
+    Full {
+        lang: String,
+        #[serde(default)]
+        bilingual: bool,
+        #[serde(default)]
+        model: Option<String>,
+    },
+}
+
+impl From<TranslationSpec> for Translation {
+    fn from(spec: TranslationSpec) -> Self {
+        match spec {
+            TranslationSpec::Lang(lang) => Translation {
+                lang,
+                ..Default::default()
+            },
+            TranslationSpec::Full { lang, bilingual, model } => Translation { lang, bilingual, model },
+        }
+    }
 }
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
+/// Length and style limits for a task's answers
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
+pub struct TaskConstraints {
+    #[serde(default)]
+    pub max_words: Option<usize>,
+    #[serde(default)]
+    pub max_bullets: Option<usize>,
+    /// Phrases that must not appear (case-insensitive)
+    #[serde(default)]
+    pub must_not_contain: Vec<String>,
+}
+
+impl TaskConstraints {
+    pub fn is_empty(&self) -> bool {
+        self.max_words.is_none() && self.max_bullets.is_none() && self.must_not_contain.is_empty()
+    }
+}
+
+/// Answer format a task asks for
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, Default, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum TaskOutput {
+    /// Markdown slide content
+    #[default]
+    Text,
+    /// A single fenced Mermaid or PlantUML diagram
+    Diagram,
+}
+
+impl TaskOutput {
+    /// Closing instruction describing the answer format
+    pub fn instruction(self) -> &'static str {
+        match self {
+            TaskOutput::Text => "Please provide slide content in Markdown format.",
+            TaskOutput::Diagram => {
+                "Answer with a single fenced Mermaid (```mermaid) or PlantUML (```plantuml) diagram block and no other text."
+            }
+        }
+    }
+}
+
+impl std::str::FromStr for TaskOutput {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "text" => Ok(TaskOutput::Text),
+            "diagram" => Ok(TaskOutput::Diagram),
+            _ => Err(anyhow::anyhow!("Unknown task output '{}'. Expected text or diagram", s)),
+        }
+    }
+}
+
+/// A named bundle of agent, task, model, and output settings for `ecce homo --preset`
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
+pub struct Preset {
+    pub name: String,
+    #[serde(default)]
+    pub agent: Option<String>,
+    #[serde(default)]
+    pub task: Option<String>,
+    /// Overrides the agent's model
+    #[serde(default)]
+    pub model: Option<String>,
+    /// Plain output (no color, emoji, or box-drawing)
+    #[serde(default)]
+    pub plain: bool,
+}
+
+/// A named file or folder for `ecce homo <name>`, with the options to watch it with
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
+pub struct WatchTarget {
+    pub name: String,
+    /// Absolute local path or remote `user@host:/path`
+    pub path: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub preset: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub agent: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub task: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
 pub struct McpServer {
     pub name: String,
     pub config: serde_json::Value,
 }
 
-#[derive(Debug, Serialize, Deserialize, Default)]
+/// How Claude Code reaches an MCP server: its `type`, or `stdio` when unset
+pub fn mcp_transport(config: &serde_json::Value) -> &str {
+    config["type"].as_str().unwrap_or("stdio")
+}
+
+impl McpServer {
+    pub fn transport(&self) -> &str {
+        mcp_transport(&self.config)
+    }
+
+    /// Check `config` against the shapes Claude Code launches: stdio
+    /// (`command`, `args`, `env`), sse/http (`url`, `headers`), and ws (`url`).
+    /// Returns warnings for keys ecce doesn't know, which are kept as they are.
+    pub fn validate(&self) -> Result<Vec<String>> {
+        let config = self
+            .config
+            .as_object()
+            .ok_or_else(|| anyhow::anyhow!("MCP server config must be a JSON object, e.g. {{\"command\": \"npx\"}}"))?;
+        let kind = match config.get("type") {
+            None if config.contains_key("url") => {
+                return Err(anyhow::anyhow!(
+                    "Servers with a url need \"type\": \"sse\", \"http\", or \"ws\""
+                ))
+            }
+            None => "stdio",
+            Some(serde_json::Value::String(kind)) => kind.as_str(),
+            Some(other) => return Err(anyhow::anyhow!("\"type\" must be a string, got {}", other)),
+        };
+
+        let string = |key: &str| -> Result<Option<&str>> {
+            match config.get(key) {
+                None => Ok(None),
+                Some(serde_json::Value::String(value)) if !value.trim().is_empty() => Ok(Some(value)),
+                Some(_) => Err(anyhow::anyhow!("\"{}\" must be a non-empty string", key)),
+            }
+        };
+        let string_map = |key: &str| -> Result<()> {
+            match config.get(key) {
+                None => Ok(()),
+                Some(serde_json::Value::Object(map)) => match map.iter().find(|(_, v)| !v.is_string()) {
+                    Some((name, _)) => Err(anyhow::anyhow!("\"{}\".{} must be a string", key, name)),
+                    None => Ok(()),
+                },
+                Some(_) => Err(anyhow::anyhow!("\"{}\" must be an object of strings", key)),
+            }
+        };
+        let url = |schemes: &[&str]| -> Result<()> {
+            let url = string("url")?
+                .ok_or_else(|| anyhow::anyhow!("{} servers need a \"url\"", kind))?;
+            if !schemes.iter().any(|scheme| url.starts_with(&format!("{}://", scheme))) {
+                return Err(anyhow::anyhow!(
+                    "{} server url must start with {}",
+                    kind,
+                    schemes.iter().map(|s| format!("{}://", s)).collect::<Vec<_>>().join(" or ")
+                ));
+            }
+            Ok(())
+        };
+
+        let known: &[&str] = match kind {
+            "stdio" => {
+                if config.contains_key("url") || config.contains_key("headers") {
+                    return Err(anyhow::anyhow!(
+                        "stdio servers take command/args/env; for a url set \"type\" to \"sse\", \"http\", or \"ws\""
+                    ));
+                }
+                string("command")?.ok_or_else(|| {
+                    anyhow::anyhow!("stdio servers need a \"command\", e.g. {{\"command\": \"npx\", \"args\": [\"my-server\"]}}")
+                })?;
+                match config.get("args") {
+                    None => {}
+                    Some(serde_json::Value::Array(args)) if args.iter().all(|a| a.is_string()) => {}
+                    Some(_) => return Err(anyhow::anyhow!("\"args\" must be an array of strings")),
+                }
+                string_map("env")?;
+                &["type", "command", "args", "env"]
+            }
+            "sse" | "http" => {
+                url(&["http", "https"])?;
+                string_map("headers")?;
+                &["type", "url", "headers"]
+            }
+            "ws" => {
+                url(&["ws", "wss"])?;
+                string_map("headers")?;
+                &["type", "url", "headers"]
+            }
+            other => {
+                return Err(anyhow::anyhow!(
+                    "Unknown server type '{}'; expected stdio, sse, http, or ws",
+                    other
+                ))
+            }
+        };
+        if kind != "stdio" {
+            if let Some(key) = ["command", "args", "env"].iter().find(|key| config.contains_key(**key)) {
+                return Err(anyhow::anyhow!("\"{}\" is for stdio servers; {} servers take url/headers", key, kind));
+            }
+        }
+
+        let mut unknown: Vec<&String> = config.keys().filter(|key| !known.contains(&key.as_str())).collect();
+        unknown.sort();
+        Ok(unknown
+            .into_iter()
+            .map(|key| format!("Unknown key \"{}\" for a {} server; kept as is", key, kind))
+            .collect())
+    }
+}
+
+/// What a model alias stands for: one model everywhere, or a model per profile or service
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(untagged)]
+pub enum ModelAlias {
+    Plain(String),
+    PerTarget {
+        /// Used when no profile or service entry matches
+        #[serde(default)]
+        default: Option<String>,
+        /// Model by profile name
+        #[serde(default)]
+        profiles: BTreeMap<String, String>,
+        /// Model by profile service
+        #[serde(default)]
+        services: BTreeMap<String, String>,
+    },
+}
+
+impl ModelAlias {
+    /// The model this alias means for `profile`: its name first, then its service, then the default
+    pub fn resolve(&self, profile: Option<&Profile>) -> Option<&str> {
+        match self {
+            ModelAlias::Plain(model) => Some(model),
+            ModelAlias::PerTarget {
+                default,
+                profiles,
+                services,
+            } => profile
+                .and_then(|p| profiles.get(&p.name).or_else(|| services.get(&p.service)))
+                .or(default.as_ref())
+                .map(String::as_str),
+        }
+    }
+}
+
+/// Expand a model alias for `profile`; names that aren't aliases (or have no
+/// model for this profile) are returned unchanged
+pub fn resolve_model(aliases: &BTreeMap<String, ModelAlias>, model: &str, profile: Option<&Profile>) -> String {
+    aliases
+        .get(model)
+        .and_then(|alias| alias.resolve(profile))
+        .unwrap_or(model)
+        .to_string()
+}
+
+/// A task as a Claude Code slash command: the template, the command's
+/// arguments (`$ARGUMENTS`) as the question, then the answer format and limits,
+/// which ecce checks after generation but a slash command can only ask for
+pub fn task_command(task: &Task) -> String {
+    let summary = task.template.lines().map(str::trim).find(|l| !l.is_empty()).unwrap_or_default();
+    let description = match summary.char_indices().nth(80) {
+        Some((end, _)) => format!("{}...", &summary[..end]),
+        None if summary.is_empty() => format!("ecce task {}", task.name),
+        None => summary.to_string(),
+    };
+
+    let mut rules = vec![task.output.instruction().to_string()];
+    if let Some(max) = task.constraints.max_words {
+        rules.push(format!("Use at most {} words.", max));
+    }
+    if let Some(max) = task.constraints.max_bullets {
+        rules.push(format!("Use at most {} bullet points.", max));
+    }
+    for phrase in &task.constraints.must_not_contain {
+        rules.push(format!("Do not write \"{}\".", phrase));
+    }
+
+    format!(
+        "---\ndescription: {}\nargument-hint: <question>\n---\n\n{}\n\nQuestion: $ARGUMENTS\n\n{}\n",
+        description,
+        task.template.trim(),
+        rules.join("\n")
+    )
+}
+
+/// Service of the profile that `ecce homo` and other generations run against
+pub const CLAUDE_CODE_SERVICE: &str = "claude-code";
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
 pub struct Config {
     pub profiles: Vec<Profile>,
+    /// Active profile name per service, so claude-code and codex can each have one
+    #[serde(default)]
+    pub active_profiles: BTreeMap<String, String>,
+    /// Single active profile written by older versions; moved into `active_profiles` on load
+    #[serde(default, skip_serializing)]
     pub active_profile: Option<String>,
     #[serde(default)]
     pub default_profile: Option<String>,
@@ -51,26 +533,190 @@ pub struct Config {
     pub claude_executable: Option<String>,
     #[serde(default)]
     pub mcp_servers: HashMap<String, McpServer>,
+    /// Estimated prompt token budget for generations
+    #[serde(default)]
+    pub max_prompt_tokens: Option<u64>,
+    /// Most claude processes running at once across all ecce processes
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_concurrent: Option<usize>,
+    /// Programs `cmd://` context entries may run
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub context_commands: Vec<String>,
+    /// Environment variables `env://` context entries and `{{env.VAR}}` may read
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub context_env: Vec<String>,
+    #[serde(default)]
+    pub presets: HashMap<String, Preset>,
+    /// Named files for `ecce homo <name>`
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub targets: HashMap<String, WatchTarget>,
+    /// Pattern trigger keywords (default: `ecce`)
+    #[serde(default)]
+    pub keywords: Vec<String>,
+    /// Directory for prompt temp files, e.g. a tmpfs (default: the system temp dir)
+    #[serde(default)]
+    pub temp_dir: Option<String>,
+    /// Send system prompts to claude on stdin so they never touch disk
+    #[serde(default)]
+    pub prompts_via_stdin: bool,
+    /// Wrap in-place answers in provenance markers
+    #[serde(default)]
+    pub provenance: bool,
+    /// Model names usable wherever a model is set, e.g. `fast` -> a haiku model
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub model_aliases: BTreeMap<String, ModelAlias>,
+    /// Slack webhooks and email recipients that receive answers and failures
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub deliver: Vec<DeliveryTarget>,
+    /// Rules that block or flag responses before they are written
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub guards: Vec<GuardRule>,
+    /// Pick an agent per pattern from agent descriptions when none is given
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub router: Option<RouterConfig>,
+    /// Defaults for `ecce homo` options not given on the command line
+    #[serde(default, skip_serializing_if = "WatchDefaults::is_empty")]
+    pub watch: WatchDefaults,
+}
+
+/// Where `ecce homo` writes answers when no output flag is given
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum WatchMode {
+    /// Replace patterns in the watched file
+    InPlace,
+    /// Like --observe: append to an answers file next to the watched file
+    Observe,
+    /// Like --stdout: print answers
+    Stdout,
+}
+
+/// The `watch` section of the config: fallbacks for `ecce homo` flags
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
+pub struct WatchDefaults {
+    /// Poll interval in milliseconds (--watch-interval)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub interval: Option<u64>,
+    /// Milliseconds the file must stay unchanged before its patterns are answered (--debounce)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub debounce: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mode: Option<WatchMode>,
+    /// Ask before writing each answer when running at a terminal (--confirm)
+    #[serde(default)]
+    pub confirm: bool,
+    /// Tell agents how the document styles headings and lists (--match-style)
+    #[serde(default)]
+    pub match_style: bool,
+    /// Lines per slide answers are fitted to (--fit-slide)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub fit_slide: Option<usize>,
+}
+
+impl WatchDefaults {
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+/// Settings for the `router` step in `ecce homo`
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
+pub struct RouterConfig {
+    /// Model for the routing call (default: haiku)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub model: Option<String>,
+    /// Agents to choose from (default: every agent with a description)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub agents: Vec<String>,
+}
+
+/// File name of per-project settings, found in the watched file's directory or an ancestor
+pub const PROJECT_CONFIG_FILE: &str = ".ecce.json";
+
+/// Per-project settings from the nearest `.ecce.json`, overriding the global config
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
+pub struct ProjectConfig {
+    /// Pattern trigger keywords for files in this project
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub keywords: Vec<String>,
+    /// Profile pinned to this project by `ecce api pin`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub profile: Option<String>,
+    /// Guard rules for this project, checked along with the global ones
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub guards: Vec<GuardRule>,
+    /// Translate answers written to files in this project (overrides the task's)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub translate_to: Option<Translation>,
+}
+
+impl ProjectConfig {
+    /// Load the `.ecce.json` nearest to `dir`, walking up to the filesystem root;
+    /// defaults if there is none
+    pub fn find(dir: &Path) -> Result<Self> {
+        match Self::locate(dir) {
+            Some(path) => Self::load_from(&path),
+            None => Ok(Self::default()),
+        }
+    }
+
+    /// Path of the `.ecce.json` nearest to `dir`, if any
+    pub fn locate(dir: &Path) -> Option<PathBuf> {
+        dir.ancestors()
+            .map(|ancestor| ancestor.join(PROJECT_CONFIG_FILE))
+            .find(|path| path.is_file())
+    }
+
+    pub fn load_from(path: &Path) -> Result<Self> {
+        let content = fs::read_to_string(path)
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+        serde_json::from_str(&content).with_context(|| format!("Failed to parse {}", path.display()))
+    }
+
+    pub fn save(&self, path: &Path) -> Result<()> {
+        fs::write(path, serde_json::to_string_pretty(self)? + "\n")
+            .with_context(|| format!("Failed to write {}", path.display()))
+    }
 }
 
 impl Config {
-    pub fn config_path() -> Result<PathBuf> {
+    /// Get the ecce config directory (~/.config/ecce), creating it if needed
+    pub fn config_dir() -> Result<PathBuf> {
         let home = dirs::home_dir().context("Could not find home directory")?;
         let config_dir = home.join(".config").join("ecce");
         fs::create_dir_all(&config_dir)?;
-        Ok(config_dir.join("config.json"))
+        Ok(config_dir)
+    }
+
+    pub fn config_path() -> Result<PathBuf> {
+        Ok(Self::config_dir()?.join("config.json"))
     }
 
     pub fn load() -> Result<Self> {
-        let path = Self::config_path()?;
+        Self::load_from(&Self::config_path()?)
+    }
+
+    /// Load config from the given path, or defaults if it does not exist
+    pub fn load_from(path: &Path) -> Result<Self> {
         if !path.exists() {
             return Ok(Config::default());
         }
-        let content = fs::read_to_string(&path)?;
-        let config: Config = serde_json::from_str(&content)?;
+        let content = fs::read_to_string(path)?;
+        let mut config: Config = serde_json::from_str(&content)?;
+        config.migrate_active_profile();
         Ok(config)
     }
 
+    /// Move a legacy single `active_profile` into the slot for that profile's service
+    fn migrate_active_profile(&mut self) {
+        let Some(name) = self.active_profile.take() else {
+            return;
+        };
+        if let Ok(profile) = self.resolve_profile(&name) {
+            self.active_profiles.entry(profile.service).or_insert(name);
+        }
+    }
+
     pub fn save(&self) -> Result<()> {
         let path = Self::config_path()?;
         let content = serde_json::to_string_pretty(self)?;
@@ -86,14 +732,26 @@ impl Config {
     }
 
     pub fn delete_profile(&mut self, name: &str) -> Result<bool> {
+        let dependents: Vec<&str> = self
+            .profiles
+            .iter()
+            .filter(|p| p.base.as_deref() == Some(name))
+            .map(|p| p.name.as_str())
+            .collect();
+        if !dependents.is_empty() {
+            return Err(anyhow::anyhow!(
+                "Profile '{}' is the base of {}; delete or rebase those first",
+                name,
+                dependents.join(", ")
+            ));
+        }
+
         let initial_len = self.profiles.len();
         self.profiles.retain(|p| p.name != name);
 
         if self.profiles.len() < initial_len {
-            // If deleted profile was active, clear active profile
-            if self.active_profile.as_deref() == Some(name) {
-                self.active_profile = None;
-            }
+            // If deleted profile was active for its service, clear that slot
+            self.active_profiles.retain(|_, active| active != name);
             // If deleted profile was default, clear default profile
             if self.default_profile.as_deref() == Some(name) {
                 self.default_profile = None;
@@ -105,20 +763,115 @@ impl Config {
         }
     }
 
+    /// Make a profile the active one for its service; other services keep theirs.
+    /// Returns the profile with inherited fields filled in.
     pub fn switch_profile(&mut self, name: &str) -> Result<Option<Profile>> {
-        if let Some(profile) = self.profiles.iter().find(|p| p.name == name) {
-            self.active_profile = Some(name.to_string());
-            self.save()?;
-            Ok(Some(profile.clone()))
-        } else {
-            Ok(None)
+        if !self.profiles.iter().any(|p| p.name == name) {
+            return Ok(None);
         }
+        let profile = self.resolve_profile(name)?;
+        self.active_profiles
+            .insert(profile.service.clone(), profile.name.clone());
+        self.save()?;
+        Ok(Some(profile))
     }
 
-    pub fn get_active_profile(&self) -> Option<&Profile> {
-        self.active_profile
-            .as_ref()
-            .and_then(|name| self.profiles.iter().find(|p| p.name == *name))
+    /// Active profile for a service, with inherited fields filled in
+    pub fn get_active_profile(&self, service: &str) -> Option<Profile> {
+        self.active_profiles
+            .get(service)
+            .and_then(|name| self.resolve_profile(name).ok())
+            .filter(|p| p.service == service)
+    }
+
+    /// Look up a profile and fill its empty fields from its `base` chain
+    pub fn resolve_profile(&self, name: &str) -> Result<Profile> {
+        self.resolve_profile_chain(name, &mut Vec::new())
+    }
+
+    fn resolve_profile_chain(&self, name: &str, chain: &mut Vec<String>) -> Result<Profile> {
+        if chain.iter().any(|n| n == name) {
+            chain.push(name.to_string());
+            return Err(anyhow::anyhow!(
+                "Profile inheritance cycle: {}",
+                chain.join(" -> ")
+            ));
+        }
+        chain.push(name.to_string());
+
+        let profile = self
+            .profiles
+            .iter()
+            .find(|p| p.name == name)
+            .ok_or_else(|| anyhow::anyhow!("Profile '{}' not found", name))?;
+        let mut resolved = match profile.base {
+            Some(ref base_name) => {
+                let base = self.resolve_profile_chain(base_name, chain)?;
+                let inherit = |own: &str, base: String| if own.is_empty() { base } else { own.to_string() };
+                let mut resolved = Profile {
+                    url: inherit(&profile.url, base.url),
+                    service: inherit(&profile.service, base.service),
+                    ..profile.clone()
+                };
+                // Dates describe the key, so they come along with an inherited one
+                if profile.key.is_empty() {
+                    resolved.key = base.key;
+                    resolved.created_at = base.created_at;
+                    resolved.expires_at = base.expires_at;
+                }
+                resolved
+            }
+            None => profile.clone(),
+        };
+        if resolved.service.is_empty() {
+            resolved.service = CLAUDE_CODE_SERVICE.to_string();
+        }
+        Ok(resolved)
+    }
+
+    /// Replace a profile's key and save; see [`Profile::rotate`]
+    pub fn rotate_key(
+        &mut self,
+        name: &str,
+        key: String,
+        expires_at: Option<NaiveDate>,
+    ) -> Result<Option<Profile>> {
+        let Some(profile) = self.profiles.iter_mut().find(|p| p.name == name) else {
+            return Ok(None);
+        };
+        profile.rotate(key, expires_at, Utc::now())?;
+        let profile = profile.clone();
+        self.save()?;
+        Ok(Some(profile))
+    }
+
+    /// Every profile with inherited fields filled in; a profile whose chain is broken is shown as written
+    pub fn resolved_profiles(&self) -> Vec<Profile> {
+        self.profiles
+            .iter()
+            .map(|p| self.resolve_profile(&p.name).unwrap_or_else(|_| p.clone()))
+            .collect()
+    }
+
+    /// The profile pinned to the project containing `dir`, if it is for `service`
+    pub fn pinned_profile(&self, dir: &Path, service: &str) -> Result<Option<Profile>> {
+        let Some(name) = ProjectConfig::find(dir)?.profile else {
+            return Ok(None);
+        };
+        let profile = self.resolve_profile(&name).with_context(|| {
+            format!("Profile '{}' is pinned in {} but can't be used", name, PROJECT_CONFIG_FILE)
+        })?;
+        Ok((profile.service == service).then_some(profile))
+    }
+
+    /// Expand a model alias for `profile`; see [`resolve_model`]
+    pub fn resolve_model(&self, model: &str, profile: Option<&Profile>) -> String {
+        resolve_model(&self.model_aliases, model, profile)
+    }
+
+    /// Whether a resolved profile is the active one for its service
+    pub fn is_active(&self, profile: &Profile) -> bool {
+        self.active_profiles.get(&profile.service) == Some(&profile.name)
     }
 
     pub fn set_default_profile(&mut self, name: &str) -> Result<bool> {
@@ -172,6 +925,121 @@ impl Config {
         self.tasks.get(name)
     }
 
+    /// Get a task with its `extends` chain applied. The base template replaces
+    /// `{{base}}` in the extending template, or is prepended when there is no
+    /// placeholder; unset output settings and limits are inherited.
+    pub fn resolve_task(&self, name: &str) -> Result<Task> {
+        self.resolve_task_chain(name, &mut Vec::new())
+    }
+
+    fn resolve_task_chain(&self, name: &str, chain: &mut Vec<String>) -> Result<Task> {
+        if chain.iter().any(|n| n == name) {
+            chain.push(name.to_string());
+            return Err(anyhow::anyhow!(
+                "Task inheritance cycle: {}",
+                chain.join(" -> ")
+            ));
+        }
+        chain.push(name.to_string());
+
+        let task = self
+            .get_task(name)
+            .ok_or_else(|| anyhow::anyhow!("Task '{}' not found", name))?;
+        let Some(ref base_name) = task.extends else {
+            return Ok(task.clone());
+        };
+        let base = self.resolve_task_chain(base_name, chain)?;
+
+        let template = template::extend_template(&base.template, &task.template);
+
+        let mut must_not_contain = base.constraints.must_not_contain.clone();
+        for phrase in &task.constraints.must_not_contain {
+            if !must_not_contain.contains(phrase) {
+                must_not_contain.push(phrase.clone());
+            }
+        }
+
+        Ok(Task {
+            name: task.name.clone(),
+            template,
+            output: if task.output == TaskOutput::default() {
+                base.output
+            } else {
+                task.output
+            },
+            constraints: TaskConstraints {
+                max_words: task.constraints.max_words.or(base.constraints.max_words),
+                max_bullets: task.constraints.max_bullets.or(base.constraints.max_bullets),
+                must_not_contain,
+            },
+            extends: None,
+            translate_to: task.translate_to.clone().or(base.translate_to),
+            polish: task.polish.clone().or(base.polish),
+        })
+    }
+
+    pub fn add_preset(&mut self, preset: Preset) -> Result<()> {
+        self.presets.insert(preset.name.clone(), preset);
+        self.save()
+    }
+
+    pub fn delete_preset(&mut self, name: &str) -> Result<bool> {
+        if self.presets.remove(name).is_some() {
+            self.save()?;
+            Ok(true)
+        } else {
+            Ok(false)
+        }
+    }
+
+    pub fn get_preset(&self, name: &str) -> Option<&Preset> {
+        self.presets.get(name)
+    }
+
+    pub fn add_target(&mut self, target: WatchTarget) -> Result<()> {
+        self.targets.insert(target.name.clone(), target);
+        self.save()
+    }
+
+    pub fn delete_target(&mut self, name: &str) -> Result<bool> {
+        if self.targets.remove(name).is_some() {
+            self.save()?;
+            Ok(true)
+        } else {
+            Ok(false)
+        }
+    }
+
+    pub fn get_target(&self, name: &str) -> Option<&WatchTarget> {
+        self.targets.get(name)
+    }
+
+    /// Check that a target's name can't be mistaken for a path and that its preset, agent, and task exist
+    pub fn check_target(&self, target: &WatchTarget) -> Result<()> {
+        if target.name.is_empty() || target.name.contains(['/', '\\', ':']) || target.name.starts_with('.') {
+            return Err(anyhow::anyhow!(
+                "Invalid target name '{}': names can't start with '.' or contain '/', '\\', or ':'",
+                target.name
+            ));
+        }
+        if let Some(ref preset) = target.preset {
+            if self.get_preset(preset).is_none() {
+                return Err(anyhow::anyhow!("Preset '{}' not found", preset));
+            }
+        }
+        if let Some(ref agent) = target.agent {
+            if self.get_agent(agent).is_none() {
+                return Err(anyhow::anyhow!("Agent '{}' not found", agent));
+            }
+        }
+        if let Some(ref task) = target.task {
+            if self.get_task(task).is_none() {
+                return Err(anyhow::anyhow!("Task '{}' not found", task));
+            }
+        }
+        Ok(())
+    }
+
     pub fn set_default_agent(&mut self, name: &str) -> Result<bool> {
         if self.agents.contains_key(name) {
             self.default_agent = Some(name.to_string());
@@ -193,6 +1061,66 @@ impl Config {
             .and_then(|name| self.agents.get(name))
     }
 
+    /// The default agent with its `extends` chain applied, if one is set and exists
+    pub fn resolve_default_agent(&self) -> Result<Option<Agent>> {
+        match self.get_default_agent() {
+            Some(agent) => self.resolve_agent(&agent.name).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    /// Get an agent with its `extends` chain applied. The base system prompt
+    /// replaces `{{base}}` in the extending prompt, or is prepended when there is
+    /// no placeholder; context files and env add up, and unset settings
+    /// (tools, model, ...) are inherited.
+    pub fn resolve_agent(&self, name: &str) -> Result<Agent> {
+        self.resolve_agent_chain(name, &mut Vec::new())
+    }
+
+    fn resolve_agent_chain(&self, name: &str, chain: &mut Vec<String>) -> Result<Agent> {
+        if chain.iter().any(|n| n == name) {
+            chain.push(name.to_string());
+            return Err(anyhow::anyhow!(
+                "Agent inheritance cycle: {}",
+                chain.join(" -> ")
+            ));
+        }
+        chain.push(name.to_string());
+
+        let agent = self
+            .get_agent(name)
+            .ok_or_else(|| anyhow::anyhow!("Agent '{}' not found", name))?;
+        let Some(ref base_name) = agent.extends else {
+            return Ok(agent.clone());
+        };
+        let base = self.resolve_agent_chain(base_name, chain)?;
+
+        let mut context_files = base.context_files.clone();
+        for file in &agent.context_files {
+            if !context_files.contains(file) {
+                context_files.push(file.clone());
+            }
+        }
+        let mut env = base.env.clone();
+        env.extend(agent.env.clone());
+
+        Ok(Agent {
+            name: agent.name.clone(),
+            description: agent.description.clone().or(base.description),
+            system_prompt: template::extend_template(&base.system_prompt, &agent.system_prompt),
+            context_files,
+            tools: agent.tools.clone().or(base.tools),
+            model: agent.model.clone().or(base.model),
+            temperature: agent.temperature.or(base.temperature),
+            max_output_tokens: agent.max_output_tokens.or(base.max_output_tokens),
+            thinking: agent.thinking.or(base.thinking),
+            permission_mode: agent.permission_mode.clone().or(base.permission_mode),
+            working_dir: agent.working_dir.clone().or(base.working_dir),
+            env,
+            extends: None,
+        })
+    }
+
     pub fn get_claude_executable(&self) -> String {
         self.claude_executable
             .clone()
@@ -213,10 +1141,46 @@ impl Config {
         Ok(agents_dir)
     }
 
+    /// Get the project-level slash commands directory (.claude/commands)
+    pub fn claude_commands_dir() -> Result<PathBuf> {
+        Ok(std::env::current_dir()?.join(".claude").join("commands"))
+    }
+
+    /// Get the user-level slash commands directory (~/.claude/commands)
+    pub fn user_commands_dir() -> Result<PathBuf> {
+        let home = dirs::home_dir().context("Could not find home directory")?;
+        Ok(home.join(".claude").join("commands"))
+    }
+
+    /// Export a task as a Claude Code slash command in .claude/commands/
+    pub fn export_task_as_command(&self, task_name: &str, user_level: bool) -> Result<PathBuf> {
+        // Slash commands have no inheritance either, so export the merged task
+        let task = self.resolve_task(task_name)?;
+        let commands_dir = if user_level {
+            Self::user_commands_dir()?
+        } else {
+            Self::claude_commands_dir()?
+        };
+        fs::create_dir_all(&commands_dir)?;
+
+        let file_path = commands_dir.join(format!("{}.md", task.name));
+        fs::write(&file_path, task_command(&task))?;
+        Ok(file_path)
+    }
+
+    pub fn export_all_tasks_as_commands(&self, user_level: bool) -> Result<Vec<String>> {
+        let mut names: Vec<String> = self.tasks.keys().cloned().collect();
+        names.sort();
+        for task_name in &names {
+            self.export_task_as_command(task_name, user_level)?;
+        }
+        Ok(names)
+    }
+
     /// Export an agent to a markdown file in .claude/agents/
     pub fn export_agent_to_file(&self, agent_name: &str, user_level: bool) -> Result<()> {
-        let agent = self.agents.get(agent_name)
-            .context(format!("Agent '{}' not found", agent_name))?;
+        // Claude Code has no inheritance, so export the agent as it runs
+        let agent = &self.resolve_agent(agent_name)?;
 
         let agents_dir = if user_level {
             Self::user_agents_dir()?
@@ -302,6 +1266,7 @@ impl Config {
             context_files: Vec::new(),
             tools,
             model,
+            ..Default::default()
         })
     }
 
@@ -374,6 +1339,203 @@ impl Config {
     pub fn get_mcp_server(&self, name: &str) -> Option<&McpServer> {
         self.mcp_servers.get(name)
     }
+
+    /// Look up a setting by dot path into the config JSON, e.g. `claude_executable`,
+    /// `router.model`, or `agents.writer.model`; `None` when it isn't set
+    pub fn get_setting(&self, key: &str) -> Result<Option<serde_json::Value>> {
+        let root = serde_json::to_value(self)?;
+        Ok(lookup_setting(&root, &setting_path(key)?).filter(|v| !v.is_null()).cloned())
+    }
+
+    /// Set a setting by dot path. `value` is read as JSON when it parses (numbers,
+    /// booleans, lists) and as a string otherwise; the result must fit the setting's
+    /// type and pass its checks. Not saved.
+    pub fn set_setting(&mut self, key: &str, value: &str) -> Result<()> {
+        let path = setting_path(key)?;
+        let mut candidates: Vec<serde_json::Value> = serde_json::from_str(value).into_iter().collect();
+        candidates.push(serde_json::Value::String(value.to_string()));
+
+        let mut first_error = None;
+        for candidate in candidates {
+            match self.with_setting(&path, Some(candidate.clone())) {
+                Ok(config) => {
+                    if !self.has_setting(&path, &candidate) {
+                        return Err(anyhow::anyhow!("Unknown setting '{}'", key));
+                    }
+                    config.check_setting(&path)?;
+                    *self = config;
+                    return Ok(());
+                }
+                Err(e) => {
+                    first_error.get_or_insert(e);
+                }
+            }
+        }
+        Err(first_error.unwrap()).with_context(|| format!("Invalid value for '{}'", key))
+    }
+
+    /// Remove a setting by dot path, so its default applies. Not saved.
+    pub fn unset_setting(&mut self, key: &str) -> Result<()> {
+        let path = setting_path(key)?;
+        if self.get_setting(key)?.is_none() {
+            return Err(anyhow::anyhow!("'{}' is not set", key));
+        }
+        let config = self
+            .with_setting(&path, None)
+            .with_context(|| format!("Cannot unset '{}'", key))?;
+        config.check_setting(&path)?;
+        *self = config;
+        Ok(())
+    }
+
+    /// Whether the config has a setting at `path`: keys it doesn't have are
+    /// ignored when it is read, so `value` is dropped from what is read back.
+    /// Empty lists and maps aren't written out either, so those are tried
+    /// with a null item, which any setting that reads the item rejects.
+    fn has_setting(&self, path: &[&str], value: &serde_json::Value) -> bool {
+        let probe = match value {
+            serde_json::Value::Array(items) if items.is_empty() => serde_json::json!([null]),
+            serde_json::Value::Object(map) if map.is_empty() => serde_json::json!({"": null}),
+            value => value.clone(),
+        };
+        match self.with_setting(path, Some(probe)) {
+            Ok(config) => serde_json::to_value(&config)
+                .is_ok_and(|root| lookup_setting(&root, path).is_some_and(|v| !v.is_null())),
+            Err(_) => true,
+        }
+    }
+
+    /// A copy of the config with the setting at `path` replaced, or removed when `value` is `None`
+    fn with_setting(&self, path: &[&str], value: Option<serde_json::Value>) -> Result<Config> {
+        let mut root = serde_json::to_value(self)?;
+        let (last, parents) = path.split_last().context("Empty setting key")?;
+        let mut node = &mut root;
+        for segment in parents {
+            if node.is_null() {
+                *node = serde_json::json!({});
+            }
+            node = match node {
+                serde_json::Value::Object(map) => map.entry(segment.to_string()).or_insert(serde_json::Value::Null),
+                serde_json::Value::Array(items) => segment
+                    .parse::<usize>()
+                    .ok()
+                    .and_then(|i| items.get_mut(i))
+                    .with_context(|| format!("No item '{}' in the list", segment))?,
+                _ => return Err(anyhow::anyhow!("'{}' is not a group of settings", segment)),
+            };
+        }
+        if node.is_null() {
+            *node = serde_json::json!({});
+        }
+        match (node, value) {
+            (serde_json::Value::Object(map), Some(value)) => {
+                map.insert(last.to_string(), value);
+            }
+            (serde_json::Value::Object(map), None) => {
+                map.remove(*last);
+            }
+            (serde_json::Value::Array(items), value) => {
+                let index = last
+                    .parse::<usize>()
+                    .ok()
+                    .filter(|&i| i < items.len() || (value.is_some() && i == items.len()))
+                    .with_context(|| format!("No item '{}' in the list", last))?;
+                match value {
+                    Some(value) if index == items.len() => items.push(value),
+                    Some(value) => items[index] = value,
+                    None => {
+                        items.remove(index);
+                    }
+                }
+            }
+            _ => return Err(anyhow::anyhow!("'{}' is not a group of settings", path[..path.len() - 1].join("."))),
+        }
+        let mut config: Config = serde_json::from_value(root)?;
+        config.active_profile = self.active_profile.clone();
+        Ok(config)
+    }
+
+    /// Checks beyond the type for the setting at `path`
+    fn check_setting(&self, path: &[&str]) -> Result<()> {
+        match path[0] {
+            "default_agent" => {
+                if let Some(ref name) = self.default_agent {
+                    if self.get_agent(name).is_none() {
+                        return Err(anyhow::anyhow!("Agent '{}' not found", name));
+                    }
+                }
+            }
+            "default_profile" => {
+                if let Some(ref name) = self.default_profile {
+                    self.resolve_profile(name)?;
+                }
+            }
+            "claude_executable" if self.claude_executable.as_deref().is_some_and(|e| e.trim().is_empty()) => {
+                return Err(anyhow::anyhow!("claude_executable must not be empty"));
+            }
+            "max_prompt_tokens" if self.max_prompt_tokens == Some(0) => {
+                return Err(anyhow::anyhow!("max_prompt_tokens must be greater than 0"));
+            }
+            "max_concurrent" if self.max_concurrent == Some(0) => {
+                return Err(anyhow::anyhow!("max_concurrent must be greater than 0"));
+            }
+            "keywords" => {
+                if let Some(bad) = self.keywords.iter().find(|k| k.is_empty() || k.contains(char::is_whitespace)) {
+                    return Err(anyhow::anyhow!(
+                        "Invalid pattern keyword '{}': keywords must be non-empty and contain no whitespace",
+                        bad
+                    ));
+                }
+            }
+            "temp_dir" => {
+                if let Some(ref dir) = self.temp_dir {
+                    if !Path::new(dir).is_dir() {
+                        return Err(anyhow::anyhow!("temp_dir '{}' is not a directory", dir));
+                    }
+                }
+            }
+            "agents" => {
+                if let Some(name) = path.get(1).filter(|name| self.agents.contains_key(**name)) {
+                    self.resolve_agent(name)?;
+                }
+            }
+            "guards" => {
+                Guard::new(&self.guards)?;
+            }
+            "watch" if self.watch.interval == Some(0) => {
+                return Err(anyhow::anyhow!("watch.interval must be greater than 0"));
+            }
+            "targets" => {
+                if let Some(target) = path.get(1).and_then(|name| self.targets.get(*name)) {
+                    self.check_target(target)?;
+                }
+            }
+            "mcp_servers" => {
+                if let Some(server) = path.get(1).and_then(|name| self.mcp_servers.get(*name)) {
+                    server.validate()?;
+                }
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+}
+
+/// Split a setting key into its dot-separated segments
+fn setting_path(key: &str) -> Result<Vec<&str>> {
+    let path: Vec<&str> = key.split('.').collect();
+    if path.iter().any(|segment| segment.is_empty()) {
+        return Err(anyhow::anyhow!("Invalid setting key '{}'", key));
+    }
+    Ok(path)
+}
+
+fn lookup_setting<'a>(root: &'a serde_json::Value, path: &[&str]) -> Option<&'a serde_json::Value> {
+    path.iter().try_fold(root, |node, segment| match node {
+        serde_json::Value::Object(map) => map.get(*segment),
+        serde_json::Value::Array(items) => items.get(segment.parse::<usize>().ok()?),
+        _ => None,
+    })
 }
 
 #[cfg(test)]
@@ -392,7 +1554,7 @@ mod tests {
     fn test_config_default() {
         let config = Config::default();
         assert!(config.profiles.is_empty());
-        assert!(config.active_profile.is_none());
+        assert!(config.active_profiles.is_empty());
         assert!(config.default_profile.is_none());
         assert!(config.agents.is_empty());
         assert!(config.tasks.is_empty());
@@ -407,6 +1569,7 @@ mod tests {
             url: "https://api.test.com".to_string(),
             key: "test-key".to_string(),
             service: "claude-code".to_string(),
+            ..Default::default()
         };
 
         config.profiles.push(profile.clone());
@@ -425,6 +1588,7 @@ mod tests {
             url: "https://api.test.com".to_string(),
             key: "test-key".to_string(),
             service: "claude-code".to_string(),
+            ..Default::default()
         };
 
         config.profiles.push(profile);
@@ -443,21 +1607,22 @@ mod tests {
             url: "https://api.test.com".to_string(),
             key: "test-key".to_string(),
             service: "claude-code".to_string(),
+            ..Default::default()
         };
 
         config.profiles.push(profile);
-        config.active_profile = Some("test".to_string());
+        config
+            .active_profiles
+            .insert("claude-code".to_string(), "test".to_string());
 
         let initial_len = config.profiles.len();
         config.profiles.retain(|p| p.name != "test");
 
         if config.profiles.len() < initial_len {
-            if config.active_profile.as_deref() == Some("test") {
-                config.active_profile = None;
-            }
+            config.active_profiles.retain(|_, active| active != "test");
         }
 
-        assert!(config.active_profile.is_none());
+        assert!(config.get_active_profile("claude-code").is_none());
     }
 
     #[test]
@@ -469,14 +1634,239 @@ mod tests {
             url: "https://api.test.com".to_string(),
             key: "test-key".to_string(),
             service: "claude-code".to_string(),
+            ..Default::default()
         };
 
         config.profiles.push(profile);
-        config.active_profile = Some("test".to_string());
+        config
+            .active_profiles
+            .insert("claude-code".to_string(), "test".to_string());
 
-        let active = config.get_active_profile();
+        let active = config.get_active_profile("claude-code");
         assert!(active.is_some());
         assert_eq!(active.unwrap().name, "test");
+        assert!(config.get_active_profile("codex").is_none());
+    }
+
+    #[test]
+    fn test_model_aliases() {
+        let config: Config = serde_json::from_str(
+            r#"{
+                "profiles": [],
+                "model_aliases": {
+                    "fast": "claude-haiku-4-5",
+                    "smart": {
+                        "default": "claude-opus-4-1",
+                        "profiles": {"relay": "opus"},
+                        "services": {"bedrock": "us.anthropic.claude-opus-4-1"}
+                    }
+                }
+            }"#,
+        )
+        .unwrap();
+        let profile = |name: &str, service: &str| Profile {
+            name: name.to_string(),
+            service: service.to_string(),
+            ..Default::default()
+        };
+
+        assert_eq!(config.resolve_model("fast", None), "claude-haiku-4-5");
+        assert_eq!(config.resolve_model("smart", None), "claude-opus-4-1");
+        assert_eq!(config.resolve_model("smart", Some(&profile("relay", "claude-code"))), "opus");
+        assert_eq!(
+            config.resolve_model("smart", Some(&profile("aws", "bedrock"))),
+            "us.anthropic.claude-opus-4-1"
+        );
+        // Not an alias
+        assert_eq!(config.resolve_model("sonnet", None), "sonnet");
+
+        let no_default: ModelAlias = serde_json::from_str(r#"{"profiles": {"relay": "opus"}}"#).unwrap();
+        assert_eq!(no_default.resolve(None), None);
+    }
+
+    #[test]
+    fn test_active_profile_per_service() {
+        let temp = TempDir::new().unwrap();
+        let path = temp.path().join("config.json");
+        fs::write(
+            &path,
+            r#"{
+                "profiles": [
+                    {"name": "relay", "url": "https://relay.example", "key": "k1", "service": "claude-code"},
+                    {"name": "openai", "url": "https://api.openai.com", "key": "k2", "service": "codex"}
+                ],
+                "active_profile": "relay"
+            }"#,
+        )
+        .unwrap();
+
+        // The legacy single slot lands in its profile's service
+        let mut config = Config::load_from(&path).unwrap();
+        assert!(config.active_profile.is_none());
+        assert_eq!(config.get_active_profile("claude-code").unwrap().name, "relay");
+
+        config
+            .active_profiles
+            .insert("codex".to_string(), "openai".to_string());
+        assert_eq!(config.get_active_profile("claude-code").unwrap().name, "relay");
+        assert_eq!(config.get_active_profile("codex").unwrap().name, "openai");
+        assert!(config.is_active(&config.profiles[1]));
+
+        let saved = serde_json::to_value(&config).unwrap();
+        assert!(saved.get("active_profile").is_none());
+        assert_eq!(saved["active_profiles"]["codex"], "openai");
+    }
+
+    #[test]
+    fn test_profile_inheritance() {
+        let json = r#"{
+            "profiles": [
+                {"name": "company-relay", "url": "https://relay.example", "key": "", "service": "claude-code"},
+                {"name": "alice", "key": "sk-alice", "base": "company-relay"},
+                {"name": "alice-eu", "url": "https://eu.relay.example", "base": "alice"}
+            ]
+        }"#;
+        let mut config: Config = serde_json::from_str(json).unwrap();
+
+        let alice = config.resolve_profile("alice").unwrap();
+        assert_eq!(alice.url, "https://relay.example");
+        assert_eq!(alice.key, "sk-alice");
+        assert_eq!(alice.service, "claude-code");
+
+        let eu = config.resolve_profile("alice-eu").unwrap();
+        assert_eq!(eu.url, "https://eu.relay.example");
+        assert_eq!(eu.key, "sk-alice");
+
+        // Moving the relay updates every profile built on it
+        config.profiles[0].url = "https://relay2.example".to_string();
+        assert_eq!(config.resolve_profile("alice").unwrap().url, "https://relay2.example");
+
+        // Only the definition is saved, not the inherited values
+        let saved = serde_json::to_value(&config.profiles[1]).unwrap();
+        assert_eq!(saved["url"], "");
+        assert_eq!(saved["base"], "company-relay");
+    }
+
+    #[test]
+    fn test_key_expiry_warning() {
+        let today = NaiveDate::from_ymd_opt(2025, 3, 10).unwrap();
+        let expiring = |date: Option<(i32, u32, u32)>| Profile {
+            name: "p".to_string(),
+            key: "k".to_string(),
+            expires_at: date.map(|(y, m, d)| NaiveDate::from_ymd_opt(y, m, d).unwrap()),
+            ..Default::default()
+        };
+
+        assert_eq!(expiring(None).expiry_warning(today), None);
+        assert_eq!(expiring(Some((2025, 4, 1))).expiry_warning(today), None);
+        assert_eq!(
+            expiring(Some((2025, 3, 13))).expiry_warning(today).as_deref(),
+            Some("key expires in 3 day(s)")
+        );
+        assert_eq!(
+            expiring(Some((2025, 3, 10))).expiry_warning(today).as_deref(),
+            Some("key expires today")
+        );
+        assert_eq!(
+            expiring(Some((2025, 3, 8))).expiry_warning(today).as_deref(),
+            Some("key expired 2 day(s) ago")
+        );
+    }
+
+    #[test]
+    fn test_rotate_key() {
+        use chrono::TimeZone;
+
+        let created = Utc.with_ymd_and_hms(2025, 3, 1, 9, 0, 0).unwrap();
+        let mut profile = Profile {
+            name: "monthly".to_string(),
+            key: "sk-march".to_string(),
+            created_at: Some(created),
+            expires_at: NaiveDate::from_ymd_opt(2025, 3, 31),
+            ..Default::default()
+        };
+
+        let now = Utc.with_ymd_and_hms(2025, 3, 28, 9, 0, 0).unwrap();
+        profile.rotate("sk-april".to_string(), None, now).unwrap();
+
+        assert_eq!(profile.key, "sk-april");
+        assert_eq!(profile.created_at, Some(now));
+        // Same 30-day lifetime as the key it replaced
+        assert_eq!(profile.expires_at, NaiveDate::from_ymd_opt(2025, 4, 27));
+        assert_eq!(
+            profile.key_history,
+            vec![ArchivedKey {
+                key: "sk-march".to_string(),
+                created_at: Some(created),
+                retired_at: now,
+            }]
+        );
+
+        assert!(profile.rotate("sk-april".to_string(), None, now).is_err());
+
+        let mut inherited = Profile {
+            name: "alice".to_string(),
+            base: Some("relay".to_string()),
+            ..Default::default()
+        };
+        assert!(inherited.rotate("sk-new".to_string(), None, now).is_err());
+    }
+
+    #[test]
+    fn test_pinned_profile() {
+        let temp = TempDir::new().unwrap();
+        let nested = temp.path().join("deck");
+        fs::create_dir_all(&nested).unwrap();
+        let mut config: Config = serde_json::from_str(
+            r#"{"profiles": [
+                {"name": "billing-a", "url": "https://a.example", "key": "ka", "service": "claude-code"},
+                {"name": "openai", "url": "https://api.openai.com", "key": "kb", "service": "codex"}
+            ]}"#,
+        )
+        .unwrap();
+        assert!(config.pinned_profile(&nested, "claude-code").unwrap().is_none());
+
+        let pin = |name: &str| {
+            ProjectConfig {
+                profile: Some(name.to_string()),
+                ..Default::default()
+            }
+            .save(&temp.path().join(PROJECT_CONFIG_FILE))
+            .unwrap()
+        };
+        pin("billing-a");
+        assert_eq!(
+            config.pinned_profile(&nested, "claude-code").unwrap().unwrap().name,
+            "billing-a"
+        );
+        assert_eq!(
+            fs::read_to_string(temp.path().join(PROJECT_CONFIG_FILE)).unwrap(),
+            "{\n  \"profile\": \"billing-a\"\n}\n"
+        );
+
+        // A pin for another service doesn't apply
+        pin("openai");
+        assert!(config.pinned_profile(&nested, "claude-code").unwrap().is_none());
+
+        // A pin to a profile that no longer exists is an error, not a silent fallback
+        pin("billing-a");
+        config.profiles.remove(0);
+        assert!(config.pinned_profile(&nested, "claude-code").is_err());
+    }
+
+    #[test]
+    fn test_profile_inheritance_cycle() {
+        let json = r#"{
+            "profiles": [
+                {"name": "a", "base": "b"},
+                {"name": "b", "base": "a"}
+            ]
+        }"#;
+        let config: Config = serde_json::from_str(json).unwrap();
+        let err = config.resolve_profile("a").unwrap_err().to_string();
+        assert!(err.contains("a -> b -> a"), "{}", err);
+        // A broken chain is listed as written rather than hidden
+        assert_eq!(config.resolved_profiles().len(), 2);
     }
 
     #[test]
@@ -490,6 +1880,7 @@ mod tests {
             context_files: vec![],
             tools: Some(vec!["tool1".to_string()]),
             model: Some("sonnet".to_string()),
+            ..Default::default()
         };
 
         config.agents.insert(agent.name.clone(), agent);
@@ -509,6 +1900,7 @@ mod tests {
             context_files: vec![],
             tools: None,
             model: None,
+            ..Default::default()
         };
 
         config.agents.insert(agent.name.clone(), agent);
@@ -530,6 +1922,7 @@ mod tests {
             context_files: vec![],
             tools: None,
             model: None,
+            ..Default::default()
         };
 
         config.agents.insert(agent.name.clone(), agent);
@@ -546,6 +1939,7 @@ mod tests {
         let task = Task {
             name: "test-task".to_string(),
             template: "Test template".to_string(),
+            ..Default::default()
         };
 
         config.tasks.insert(task.name.clone(), task);
@@ -561,6 +1955,7 @@ mod tests {
         let task = Task {
             name: "test-task".to_string(),
             template: "Test template".to_string(),
+            ..Default::default()
         };
 
         config.tasks.insert(task.name.clone(), task);
@@ -578,6 +1973,7 @@ mod tests {
         let task = Task {
             name: "test-task".to_string(),
             template: "Test template".to_string(),
+            ..Default::default()
         };
 
         config.tasks.insert(task.name.clone(), task);
@@ -587,6 +1983,165 @@ mod tests {
         assert_eq!(retrieved.unwrap().name, "test-task");
     }
 
+    #[test]
+    fn test_resolve_task_inheritance() {
+        let mut config = Config::default();
+        let task = |name: &str, template: &str, extends: Option<&str>| Task {
+            name: name.to_string(),
+            template: template.to_string(),
+            extends: extends.map(|e| e.to_string()),
+            ..Default::default()
+        };
+        config.tasks.insert("base".to_string(), task("base", "Make slides.", None));
+        config.tasks.insert(
+            "wrapped".to_string(),
+            task("wrapped", "Intro.\n{{base}}\nOutro.", Some("base")),
+        );
+        config.tasks.insert("appended".to_string(), task("appended", "Be brief.", Some("wrapped")));
+
+        assert_eq!(config.resolve_task("wrapped").unwrap().template, "Intro.\nMake slides.\nOutro.");
+        let appended = config.resolve_task("appended").unwrap();
+        assert_eq!(appended.template, "Intro.\nMake slides.\nOutro.\n\nBe brief.");
+        assert!(appended.extends.is_none());
+    }
+
+    #[test]
+    fn test_resolve_task_cycle_and_missing_base() {
+        let mut config = Config::default();
+        for (name, base) in [("a", "b"), ("b", "a"), ("orphan", "missing")] {
+            config.tasks.insert(
+                name.to_string(),
+                Task {
+                    name: name.to_string(),
+                    extends: Some(base.to_string()),
+                    ..Default::default()
+                },
+            );
+        }
+
+        let err = config.resolve_task("a").unwrap_err().to_string();
+        assert_eq!(err, "Task inheritance cycle: a -> b -> a");
+        assert!(config.resolve_task("orphan").is_err());
+    }
+
+    #[test]
+    fn test_task_passes() {
+        let task: Task = serde_json::from_str(r#"{"name": "base", "template": "", "translate_to": "ja"}"#).unwrap();
+        assert_eq!(
+            task.translate_to,
+            Some(Translation {
+                lang: "ja".to_string(),
+                ..Default::default()
+            })
+        );
+        let project: ProjectConfig =
+            serde_json::from_str(r#"{"translate_to": {"lang": "fr", "bilingual": true, "model": "sonnet"}}"#).unwrap();
+        let translation = project.translate_to.unwrap();
+        assert!(translation.bilingual);
+        assert_eq!((translation.lang.as_str(), translation.model.as_deref()), ("fr", Some("sonnet")));
+
+        // Extending tasks inherit the translation unless they set their own
+        let mut config = Config::default();
+        config.tasks.insert("base".to_string(), task);
+        config.tasks.insert(
+            "brief".to_string(),
+            Task {
+                name: "brief".to_string(),
+                extends: Some("base".to_string()),
+                ..Default::default()
+            },
+        );
+        assert_eq!(config.resolve_task("brief").unwrap().translate_to.unwrap().lang, "ja");
+        config.set_setting("tasks.brief.translate_to", r#"{"lang": "de", "bilingual": true}"#).unwrap();
+        assert_eq!(config.resolve_task("brief").unwrap().translate_to.unwrap().lang, "de");
+
+        // Polish: an agent name, or an object (empty for the built-in proofreader)
+        let polish = |json: &str| serde_json::from_str::<Polish>(json).unwrap();
+        assert_eq!(polish(r#""editor""#).agent.as_deref(), Some("editor"));
+        assert_eq!(polish("{}"), Polish::default());
+        assert_eq!(polish(r#"{"model": "sonnet"}"#).model.as_deref(), Some("sonnet"));
+        config.set_setting("tasks.base.polish", "{}").unwrap();
+        assert_eq!(config.resolve_task("brief").unwrap().polish, Some(Polish::default()));
+    }
+
+    #[test]
+    fn test_task_command() {
+        let mut config = Config::default();
+        config.tasks.insert(
+            "base".to_string(),
+            Task {
+                name: "base".to_string(),
+                template: "Make slides.".to_string(),
+                ..Default::default()
+            },
+        );
+        config.tasks.insert(
+            "brief".to_string(),
+            Task {
+                name: "brief".to_string(),
+                template: "\nKeep it short.\n{{base}}\n".to_string(),
+                output: TaskOutput::Diagram,
+                constraints: TaskConstraints {
+                    max_words: Some(50),
+                    max_bullets: None,
+                    must_not_contain: vec!["TODO".to_string()],
+                },
+                extends: Some("base".to_string()),
+                translate_to: None,
+                polish: None,
+            },
+        );
+
+        let command = task_command(&config.resolve_task("brief").unwrap());
+        assert!(command.starts_with("---\ndescription: Keep it short.\nargument-hint: <question>\n---\n\n"));
+        assert!(command.contains("Keep it short.\nMake slides.\n\nQuestion: $ARGUMENTS\n\n"));
+        assert!(command.contains("Answer with a single fenced Mermaid"));
+        assert!(command.ends_with("Use at most 50 words.\nDo not write \"TODO\".\n"));
+        assert!(!command.contains("{{base}}"));
+    }
+
+    #[test]
+    fn test_resolve_agent_inheritance() {
+        let mut config = Config::default();
+        config.agents.insert(
+            "base-writer".to_string(),
+            Agent {
+                name: "base-writer".to_string(),
+                system_prompt: "You write lecture slides.".to_string(),
+                context_files: vec!["style.md".to_string()],
+                tools: Some(vec!["Read".to_string()]),
+                model: Some("sonnet".to_string()),
+                env: HashMap::from([("LANG".to_string(), "en".to_string())]),
+                ..Default::default()
+            },
+        );
+        config.agents.insert(
+            "kids".to_string(),
+            Agent {
+                name: "kids".to_string(),
+                system_prompt: "The audience is ten years old.".to_string(),
+                context_files: vec!["style.md".to_string(), "kids.md".to_string()],
+                model: Some("haiku".to_string()),
+                extends: Some("base-writer".to_string()),
+                ..Default::default()
+            },
+        );
+        config.default_agent = Some("kids".to_string());
+
+        let kids = config.resolve_agent("kids").unwrap();
+        assert_eq!(kids.system_prompt, "You write lecture slides.\n\nThe audience is ten years old.");
+        assert_eq!(kids.context_files, vec!["style.md", "kids.md"]);
+        assert_eq!(kids.tools, Some(vec!["Read".to_string()]));
+        assert_eq!(kids.model.as_deref(), Some("haiku"));
+        assert_eq!(kids.env["LANG"], "en");
+        assert!(kids.extends.is_none());
+        assert_eq!(config.resolve_default_agent().unwrap(), Some(kids));
+
+        config.agents.get_mut("base-writer").unwrap().extends = Some("kids".to_string());
+        let err = config.resolve_agent("kids").unwrap_err().to_string();
+        assert_eq!(err, "Agent inheritance cycle: kids -> base-writer -> kids");
+    }
+
     #[test]
     fn test_set_default_agent() {
         let (mut config, _temp) = setup_test_config();
@@ -598,6 +2153,7 @@ mod tests {
             context_files: vec![],
             tools: None,
             model: None,
+            ..Default::default()
         };
 
         config.agents.insert(agent.name.clone(), agent);
@@ -620,6 +2176,7 @@ mod tests {
             context_files: vec![],
             tools: None,
             model: None,
+            ..Default::default()
         };
 
         config.agents.insert(agent.name.clone(), agent);
@@ -643,6 +2200,55 @@ mod tests {
         assert_eq!(config.get_claude_executable(), "/custom/path/claude");
     }
 
+    #[test]
+    fn test_agent_deserialize_without_optional_fields() {
+        let json = r#"{
+            "name": "legacy",
+            "description": null,
+            "system_prompt": "Old agent",
+            "context_files": [],
+            "tools": null,
+            "model": null
+        }"#;
+
+        let agent: Agent = serde_json::from_str(json).unwrap();
+        assert_eq!(agent.name, "legacy");
+        assert!(agent.working_dir.is_none());
+        assert!(agent.env.is_empty());
+        assert!(agent.permission_mode.is_none());
+    }
+
+    #[test]
+    fn test_project_config_find_nearest() {
+        let temp = TempDir::new().unwrap();
+        let nested = temp.path().join("talks").join("rust");
+        fs::create_dir_all(&nested).unwrap();
+        assert_eq!(ProjectConfig::find(&nested).unwrap(), ProjectConfig::default());
+
+        fs::write(temp.path().join(PROJECT_CONFIG_FILE), r#"{"keywords": ["ai!"]}"#).unwrap();
+        assert_eq!(ProjectConfig::find(&nested).unwrap().keywords, vec!["ai!"]);
+
+        fs::write(nested.join(PROJECT_CONFIG_FILE), r#"{"keywords": ["ask"]}"#).unwrap();
+        assert_eq!(ProjectConfig::find(&nested).unwrap().keywords, vec!["ask"]);
+    }
+
+    #[test]
+    fn test_preset_deserialize_partial() {
+        let json = r#"{
+            "profiles": [],
+            "active_profile": null,
+            "presets": {
+                "lecture-notes": {"name": "lecture-notes", "agent": "writer"}
+            }
+        }"#;
+
+        let config: Config = serde_json::from_str(json).unwrap();
+        let preset = config.get_preset("lecture-notes").unwrap();
+        assert_eq!(preset.agent.as_deref(), Some("writer"));
+        assert!(preset.task.is_none());
+        assert!(!preset.plain);
+    }
+
     #[test]
     fn test_import_agent_from_file() {
         let temp_dir = TempDir::new().unwrap();
@@ -712,6 +2318,39 @@ System prompt here"#;
         assert!(config.mcp_servers.contains_key("test-server"));
     }
 
+    #[test]
+    fn test_validate_mcp_server() {
+        let check = |config: serde_json::Value| {
+            McpServer {
+                name: "s".to_string(),
+                config,
+            }
+            .validate()
+        };
+        let error = |config: serde_json::Value| check(config).unwrap_err().to_string();
+
+        assert!(check(serde_json::json!({"command": "npx", "args": ["-y", "srv"], "env": {"K": "v"}}))
+            .unwrap()
+            .is_empty());
+        assert!(check(serde_json::json!({"type": "http", "url": "https://mcp.example.com", "headers": {"A": "b"}}))
+            .unwrap()
+            .is_empty());
+        assert!(check(serde_json::json!({"type": "ws", "url": "wss://mcp.example.com"})).unwrap().is_empty());
+        assert_eq!(
+            check(serde_json::json!({"command": "npx", "timeout": 5})).unwrap(),
+            vec!["Unknown key \"timeout\" for a stdio server; kept as is"]
+        );
+
+        assert!(error(serde_json::json!(["npx"])).contains("JSON object"));
+        assert!(error(serde_json::json!({"args": ["x"]})).contains("need a \"command\""));
+        assert!(error(serde_json::json!({"command": "npx", "args": "x"})).contains("array of strings"));
+        assert!(error(serde_json::json!({"command": "npx", "env": {"K": 1}})).contains("\"env\".K"));
+        assert!(error(serde_json::json!({"url": "https://x"})).contains("need \"type\""));
+        assert!(error(serde_json::json!({"type": "sse", "url": "ws://x"})).contains("http:// or https://"));
+        assert!(error(serde_json::json!({"type": "http", "url": "https://x", "command": "npx"})).contains("for stdio"));
+        assert!(error(serde_json::json!({"type": "grpc"})).contains("Unknown server type"));
+    }
+
     #[test]
     fn test_delete_mcp_server() {
         let (mut config, _temp) = setup_test_config();
@@ -744,4 +2383,80 @@ System prompt here"#;
         assert!(retrieved.is_some());
         assert_eq!(retrieved.unwrap().name, "test-server");
     }
+
+    #[test]
+    fn test_settings() {
+        let (mut config, _temp) = setup_test_config();
+        config.agents.insert(
+            "writer".to_string(),
+            Agent {
+                name: "writer".to_string(),
+                ..Default::default()
+            },
+        );
+
+        // Strings, numbers, and lists; plain words are strings
+        config.set_setting("claude_executable", "/opt/claude").unwrap();
+        assert_eq!(config.claude_executable.as_deref(), Some("/opt/claude"));
+        config.set_setting("max_prompt_tokens", "8000").unwrap();
+        assert_eq!(config.get_setting("max_prompt_tokens").unwrap(), Some(serde_json::json!(8000)));
+        config.set_setting("keywords", r#"["ecce","ask"]"#).unwrap();
+        assert_eq!(config.keywords, vec!["ecce", "ask"]);
+        config.set_setting("keywords.1", "why").unwrap();
+        assert_eq!(config.keywords, vec!["ecce", "why"]);
+
+        // Nested settings, including ones not set yet
+        config.set_setting("agents.writer.model", "haiku").unwrap();
+        assert_eq!(config.agents["writer"].model.as_deref(), Some("haiku"));
+        config.set_setting("router.model", "haiku").unwrap();
+        assert_eq!(config.router.as_ref().unwrap().model.as_deref(), Some("haiku"));
+        config.set_setting("watch.mode", "observe").unwrap();
+        assert_eq!(config.watch.mode, Some(WatchMode::Observe));
+        assert!(config.set_setting("watch.mode", "sideways").is_err());
+        assert!(config.set_setting("watch.interval", "0").is_err());
+        assert_eq!(config.get_setting("agents.writer.model").unwrap(), Some(serde_json::json!("haiku")));
+        assert_eq!(config.get_setting("default_agent").unwrap(), None);
+
+        // Wrong types, unknown keys, and failed checks leave the config alone
+        let err = config.set_setting("max_prompt_tokens", "lots").unwrap_err();
+        assert!(format!("{:#}", err).contains("Invalid value for 'max_prompt_tokens'"));
+        assert!(config.set_setting("max_prompt_tokens", "0").is_err());
+        assert!(config.set_setting("max_concurrent", "0").is_err());
+        assert!(config.set_setting("prompts_via_stdin", "maybe").is_err());
+        let err = config.set_setting("claude_exe", "/opt/claude").unwrap_err();
+        assert!(err.to_string().contains("Unknown setting 'claude_exe'"));
+        assert!(config.set_setting("agents.writer.modle", "haiku").is_err());
+        assert!(config.set_setting("default_agent", "missing").is_err());
+        assert!(config.set_setting("keywords", r#"["two words"]"#).is_err());
+        assert!(config.set_setting("agents..model", "haiku").is_err());
+        config.set_setting("guards", r#"[{"name": "codenames", "words": ["bluebird"]}]"#).unwrap();
+        config.set_setting("guards.0.patterns", r#"["blue+bird"]"#).unwrap();
+        assert!(config.set_setting("guards.0.patterns", r#"["("]"#).is_err());
+        assert_eq!(config.guards[0].patterns, vec!["blue+bird"]);
+        // Whatever the value, even an empty list or map
+        for empty in ["[]", "{}"] {
+            let err = config.set_setting("claude_exe", empty).unwrap_err();
+            assert!(err.to_string().contains("Unknown setting 'claude_exe'"));
+        }
+        for (known, empty) in [
+            ("keywords", "[]"),
+            ("guards", "[]"),
+            ("deliver", "[]"),
+            ("agents", "{}"),
+            ("mcp_servers", "{}"),
+            ("agents.writer.context_files", "[]"),
+            ("agents.writer.env", "{}"),
+        ] {
+            config.clone().set_setting(known, empty).unwrap_or_else(|e| panic!("{}: {:#}", known, e));
+        }
+        assert_eq!(config.get_setting("max_prompt_tokens").unwrap(), Some(serde_json::json!(8000)));
+        config.set_setting("default_agent", "writer").unwrap();
+
+        // Unsetting restores the default
+        config.unset_setting("claude_executable").unwrap();
+        assert_eq!(config.get_claude_executable(), "claude");
+        config.unset_setting("keywords.0").unwrap();
+        assert_eq!(config.keywords, vec!["why"]);
+        assert!(config.unset_setting("claude_executable").is_err());
+    }
 }
@@ -0,0 +1,116 @@
+//! `cmd://` context entries: commands run at generation time whose output is
+//! given to the agent like a context file, e.g. `cmd://git log -5`
+
+use anyhow::{Context, Result};
+use std::path::Path;
+use std::process::Stdio;
+use std::time::Duration;
+use tokio::process::Command;
+
+/// Marks a context entry as a command rather than a file
+pub const PREFIX: &str = "cmd://";
+
+/// How long a context command may run before generation gives up on it
+pub const TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Whether a context entry is a command
+pub fn is_command(entry: &str) -> bool {
+    entry.starts_with(PREFIX)
+}
+
+/// Run a `cmd://` entry in `dir` and return what it printed. The program must
+/// be in `allowed`; no shell is involved, so pipes and `;` are plain arguments.
+pub async fn run(entry: &str, allowed: &[String], dir: Option<&Path>) -> Result<String> {
+    let line = entry.strip_prefix(PREFIX).unwrap_or(entry);
+    let args = split_args(line)?;
+    let (program, args) = args
+        .split_first()
+        .with_context(|| format!("Context command '{}' is empty", entry))?;
+    if !allowed.iter().any(|a| a == program) {
+        return Err(anyhow::anyhow!(
+            "Context command '{}' runs '{}', which isn't in context_commands; allow it with \
+             `ecce config set context_commands '[\"{}\"]'`",
+            entry,
+            program,
+            program
+        ));
+    }
+
+    let mut command = Command::new(program);
+    command
+        .args(args)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true);
+    if let Some(dir) = dir {
+        command.current_dir(dir);
+    }
+    let output = tokio::time::timeout(TIMEOUT, command.output())
+        .await
+        .map_err(|_| anyhow::anyhow!("Context command '{}' timed out after {}s", entry, TIMEOUT.as_secs()))?
+        .with_context(|| format!("Failed to run context command '{}'", entry))?;
+
+    // A failing command's error output is still worth seeing
+    let mut text = String::from_utf8_lossy(&output.stdout).to_string();
+    text.push_str(&String::from_utf8_lossy(&output.stderr));
+    if !output.status.success() {
+        text.push_str(&format!("\n({})", output.status));
+    }
+    Ok(text)
+}
+
+/// Split a command line on whitespace, keeping single- or double-quoted words together
+fn split_args(line: &str) -> Result<Vec<String>> {
+    let mut args = Vec::new();
+    let mut current: Option<String> = None;
+    let mut quote: Option<char> = None;
+    for c in line.chars() {
+        match (quote, c) {
+            (Some(q), c) if c == q => quote = None,
+            (Some(_), c) => current.get_or_insert_with(String::new).push(c),
+            (None, '\'' | '"') => {
+                quote = Some(c);
+                current.get_or_insert_with(String::new);
+            }
+            (None, c) if c.is_whitespace() => args.extend(current.take()),
+            (None, c) => current.get_or_insert_with(String::new).push(c),
+        }
+    }
+    if quote.is_some() {
+        return Err(anyhow::anyhow!("Unclosed quote in context command '{}'", line));
+    }
+    args.extend(current);
+    Ok(args)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_split_args() {
+        assert_eq!(split_args("git log -5").unwrap(), vec!["git", "log", "-5"]);
+        assert_eq!(
+            split_args(r#"git log --format="%h %s" ''"#).unwrap(),
+            vec!["git", "log", "--format=%h %s", ""]
+        );
+        assert!(split_args("echo 'open").is_err());
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_run() {
+        let allowed = vec!["echo".to_string(), "sh".to_string()];
+        assert_eq!(run("cmd://echo hello world", &allowed, None).await.unwrap(), "hello world\n");
+
+        // No shell: the `;` is an argument to echo
+        assert_eq!(run("cmd://echo a; rm b", &allowed, None).await.unwrap(), "a; rm b\n");
+
+        let failed = run("cmd://sh -c 'echo oops >&2; exit 3'", &allowed, None).await.unwrap();
+        assert!(failed.starts_with("oops\n") && failed.contains("exit status: 3"));
+
+        let err = run("cmd://kubectl get pods", &allowed, None).await.unwrap_err();
+        assert!(err.to_string().contains("isn't in context_commands"));
+    }
+}
@@ -0,0 +1,51 @@
+//! Environment facts in prompts: `env://VAR` context entries and `{{env.VAR}}`
+//! in system prompts and task templates, limited to allowed variables
+
+use anyhow::Result;
+use std::collections::HashMap;
+
+/// Marks a context entry as an environment variable rather than a file
+pub const PREFIX: &str = "env://";
+
+/// Whether a context entry is an environment variable
+pub fn is_env(entry: &str) -> bool {
+    entry.starts_with(PREFIX)
+}
+
+/// Value of `name` for an agent: its own `env` first, then ecce's environment.
+/// The variable must be in `allowed`, so a prompt can't read arbitrary secrets.
+pub fn lookup(name: &str, allowed: &[String], agent_env: &HashMap<String, String>) -> Result<String> {
+    if !allowed.iter().any(|a| a == name) {
+        return Err(anyhow::anyhow!(
+            "Environment variable '{}' isn't in context_env; allow it with \
+             `ecce config set context_env '[\"{}\"]'`",
+            name,
+            name
+        ));
+    }
+    match agent_env.get(name) {
+        Some(value) => Ok(value.clone()),
+        None => std::env::var(name).map_err(|_| anyhow::anyhow!("Environment variable '{}' is not set", name)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lookup() {
+        let allowed = vec!["TICKET".to_string(), "ECCE_TEST_UNSET_VAR".to_string(), "HOME".to_string()];
+        let agent_env = HashMap::from([("TICKET".to_string(), "OPS-42".to_string())]);
+        assert_eq!(lookup("TICKET", &allowed, &agent_env).unwrap(), "OPS-42");
+        assert_eq!(lookup("HOME", &allowed, &agent_env).unwrap(), std::env::var("HOME").unwrap());
+        assert!(lookup("ECCE_TEST_UNSET_VAR", &allowed, &agent_env)
+            .unwrap_err()
+            .to_string()
+            .contains("is not set"));
+        assert!(lookup("PATH", &allowed, &agent_env)
+            .unwrap_err()
+            .to_string()
+            .contains("isn't in context_env"));
+    }
+}
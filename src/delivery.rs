@@ -0,0 +1,322 @@
+//! Sending finished answers and failures to a Slack channel or by email, so a
+//! team can review generated content without watching the terminal
+
+use anyhow::{Context, Result};
+use lettre::message::Mailbox;
+use lettre::transport::smtp::authentication::Credentials;
+use lettre::{AsyncSmtpTransport, AsyncTransport, Message, Tokio1Executor};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::time::Duration;
+
+/// Longest answer sent in full; longer ones are cut with a note
+const MAX_ANSWER_CHARS: usize = 3000;
+
+const TIMEOUT: Duration = Duration::from_secs(15);
+
+/// Which outcomes a target receives
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, Default, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum DeliverOn {
+    #[default]
+    All,
+    Answers,
+    Failures,
+}
+
+/// How an SMTP connection is secured
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum SmtpSecurity {
+    /// TLS from the start (usually port 465)
+    Tls,
+    /// Plain connection upgraded with STARTTLS (usually port 587)
+    Starttls,
+    /// No encryption, for a relay on the local machine
+    None,
+}
+
+/// Where answers are sent, from `deliver` in the config
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum DeliveryTarget {
+    /// A Slack incoming webhook
+    Slack {
+        webhook_url: String,
+        #[serde(default)]
+        on: DeliverOn,
+    },
+    /// An email sent through an SMTP server
+    Email {
+        smtp_host: String,
+        /// Default: 465 with `tls`, else 587
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        port: Option<u16>,
+        /// Default: `tls` on port 465, else `starttls`
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        security: Option<SmtpSecurity>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        username: Option<String>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        password: Option<String>,
+        from: String,
+        to: Vec<String>,
+        #[serde(default)]
+        on: DeliverOn,
+    },
+}
+
+/// How a pattern turned out
+#[derive(Debug, Clone, PartialEq)]
+pub enum Outcome {
+    Answered(String),
+    Failed(String),
+}
+
+/// A finished pattern to report
+#[derive(Debug, Clone, PartialEq)]
+pub struct Delivery {
+    /// The watched file, as shown to the user
+    pub file: String,
+    pub question: String,
+    /// Agent name, or several joined with commas in compare mode
+    pub agent: String,
+    pub outcome: Outcome,
+}
+
+impl DeliveryTarget {
+    fn on(&self) -> DeliverOn {
+        match self {
+            DeliveryTarget::Slack { on, .. } | DeliveryTarget::Email { on, .. } => *on,
+        }
+    }
+
+    /// Whether this target should hear about the outcome
+    pub fn wants(&self, outcome: &Outcome) -> bool {
+        matches!(
+            (self.on(), outcome),
+            (DeliverOn::All, _) | (DeliverOn::Answers, Outcome::Answered(_)) | (DeliverOn::Failures, Outcome::Failed(_))
+        )
+    }
+
+    /// The webhook URL or SMTP password, which grant access to whoever has them
+    pub fn secrets(&self) -> Vec<String> {
+        match self {
+            DeliveryTarget::Slack { webhook_url, .. } => vec![webhook_url.clone()],
+            DeliveryTarget::Email { password, .. } => password.iter().cloned().collect(),
+        }
+    }
+
+    /// Short description for messages, without credentials
+    pub fn describe(&self) -> String {
+        match self {
+            DeliveryTarget::Slack { .. } => "Slack webhook".to_string(),
+            DeliveryTarget::Email { to, .. } => format!("email to {}", to.join(", ")),
+        }
+    }
+
+    async fn send(&self, delivery: &Delivery) -> Result<()> {
+        match self {
+            DeliveryTarget::Slack { webhook_url, .. } => {
+                let client = reqwest::Client::builder().timeout(TIMEOUT).build()?;
+                client
+                    .post(webhook_url)
+                    .json(&slack_payload(delivery))
+                    .send()
+                    .await?
+                    .error_for_status()?;
+                Ok(())
+            }
+            DeliveryTarget::Email {
+                smtp_host,
+                port,
+                security,
+                username,
+                password,
+                from,
+                to,
+                ..
+            } => {
+                let message = email_message(delivery, from, to)?;
+                let security = security.unwrap_or(if *port == Some(465) {
+                    SmtpSecurity::Tls
+                } else {
+                    SmtpSecurity::Starttls
+                });
+                let mut transport = match security {
+                    SmtpSecurity::Tls => AsyncSmtpTransport::<Tokio1Executor>::relay(smtp_host)?,
+                    SmtpSecurity::Starttls => AsyncSmtpTransport::<Tokio1Executor>::starttls_relay(smtp_host)?,
+                    SmtpSecurity::None => AsyncSmtpTransport::<Tokio1Executor>::builder_dangerous(smtp_host),
+                };
+                if let Some(port) = port {
+                    transport = transport.port(*port);
+                }
+                if let (Some(username), Some(password)) = (username, password) {
+                    transport = transport.credentials(Credentials::new(username.clone(), password.clone()));
+                }
+                transport.timeout(Some(TIMEOUT)).build().send(message).await?;
+                Ok(())
+            }
+        }
+    }
+}
+
+/// The answer, cut to `MAX_ANSWER_CHARS`
+fn clip(text: &str) -> String {
+    match text.char_indices().nth(MAX_ANSWER_CHARS) {
+        Some((end, _)) => format!("{}\n… (truncated)", &text[..end]),
+        None => text.to_string(),
+    }
+}
+
+/// Subject line or headline for a delivery
+pub fn headline(delivery: &Delivery) -> String {
+    let status = match delivery.outcome {
+        Outcome::Answered(_) => "Answered",
+        Outcome::Failed(_) => "Failed",
+    };
+    let question = delivery.question.lines().next().unwrap_or_default();
+    format!("ecce: {} \"{}\" in {}", status, question, delivery.file)
+}
+
+/// Plain text body: question, agent, and the answer or error
+pub fn text_body(delivery: &Delivery) -> String {
+    let (label, text) = match delivery.outcome {
+        Outcome::Answered(ref answer) => ("Answer", clip(answer)),
+        Outcome::Failed(ref error) => ("Error", error.clone()),
+    };
+    format!(
+        "File: {}\nAgent: {}\n\nQuestion:\n{}\n\n{}:\n{}\n",
+        delivery.file, delivery.agent, delivery.question, label, text
+    )
+}
+
+/// Slack message JSON for a delivery
+pub fn slack_payload(delivery: &Delivery) -> Value {
+    let icon = match delivery.outcome {
+        Outcome::Answered(_) => "✅",
+        Outcome::Failed(_) => "❌",
+    };
+    let body = match delivery.outcome {
+        Outcome::Answered(ref answer) => clip(answer),
+        Outcome::Failed(ref error) => error.clone(),
+    };
+    let quoted: Vec<String> = delivery.question.lines().map(|line| format!("> {}", line)).collect();
+    json!({
+        "text": format!(
+            "{} *{}* (agent `{}`)\n{}\n```\n{}\n```",
+            icon,
+            headline(delivery),
+            delivery.agent,
+            quoted.join("\n"),
+            body
+        )
+    })
+}
+
+fn email_message(delivery: &Delivery, from: &str, to: &[String]) -> Result<Message> {
+    let mut builder = Message::builder()
+        .from(from.parse::<Mailbox>().with_context(|| format!("Invalid from address '{}'", from))?)
+        .subject(headline(delivery));
+    for address in to {
+        builder = builder.to(address
+            .parse::<Mailbox>()
+            .with_context(|| format!("Invalid to address '{}'", address))?);
+    }
+    Ok(builder.body(text_body(delivery))?)
+}
+
+/// Send a delivery to every target that wants it; returns the failures, each
+/// naming its target, so one unreachable target doesn't stop the others
+pub async fn deliver(targets: &[DeliveryTarget], delivery: &Delivery) -> Vec<anyhow::Error> {
+    let mut errors = Vec::new();
+    for target in targets.iter().filter(|t| t.wants(&delivery.outcome)) {
+        if let Err(e) = target.send(delivery).await {
+            errors.push(e.context(format!("Failed to deliver to {}", target.describe())));
+        }
+    }
+    errors
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn delivery(outcome: Outcome) -> Delivery {
+        Delivery {
+            file: "slides.md".to_string(),
+            question: "What is ownership?".to_string(),
+            agent: "writer".to_string(),
+            outcome,
+        }
+    }
+
+    #[test]
+    fn test_parse_targets() {
+        let targets: Vec<DeliveryTarget> = serde_json::from_str(
+            r#"[
+                {"type": "slack", "webhook_url": "https://hooks.slack.com/services/x"},
+                {"type": "email", "smtp_host": "smtp.example.com", "from": "ecce@example.com",
+                 "to": ["team@example.com"], "on": "failures"}
+            ]"#,
+        )
+        .unwrap();
+
+        let answered = Outcome::Answered("A".to_string());
+        let failed = Outcome::Failed("boom".to_string());
+        assert!(targets[0].wants(&answered) && targets[0].wants(&failed));
+        assert!(!targets[1].wants(&answered) && targets[1].wants(&failed));
+        assert_eq!(targets[1].describe(), "email to team@example.com");
+    }
+
+    #[test]
+    fn test_message_contents() {
+        let answered = delivery(Outcome::Answered("x".repeat(MAX_ANSWER_CHARS + 10)));
+        assert_eq!(headline(&answered), "ecce: Answered \"What is ownership?\" in slides.md");
+        let text = slack_payload(&answered)["text"].as_str().unwrap().to_string();
+        assert!(text.starts_with("✅ *ecce: Answered"));
+        assert!(text.contains("> What is ownership?"));
+        assert!(text.contains("… (truncated)"));
+
+        let failed = delivery(Outcome::Failed("claude exited with status 1".to_string()));
+        assert!(text_body(&failed).ends_with("Error:\nclaude exited with status 1\n"));
+
+        let email = email_message(&failed, "ecce <ecce@example.com>", &["team@example.com".to_string()]).unwrap();
+        let raw = String::from_utf8(email.formatted()).unwrap();
+        assert!(raw.contains("Subject: ecce: Failed"));
+        assert!(email_message(&failed, "not an address", &[]).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_slack_delivery() {
+        let mut server = mockito::Server::new_async().await;
+        let hook = server
+            .mock("POST", "/hook")
+            .match_body(mockito::Matcher::Regex("What is ownership".to_string()))
+            .with_status(200)
+            .create_async()
+            .await;
+        let failing = server.mock("POST", "/gone").with_status(404).create_async().await;
+
+        let targets = vec![
+            DeliveryTarget::Slack {
+                webhook_url: format!("{}/hook", server.url()),
+                on: DeliverOn::All,
+            },
+            DeliveryTarget::Slack {
+                webhook_url: format!("{}/gone", server.url()),
+                on: DeliverOn::Answers,
+            },
+            DeliveryTarget::Slack {
+                webhook_url: format!("{}/never", server.url()),
+                on: DeliverOn::Failures,
+            },
+        ];
+        let errors = deliver(&targets, &delivery(Outcome::Answered("Moves".to_string()))).await;
+
+        hook.assert_async().await;
+        failing.assert_async().await;
+        assert_eq!(errors.len(), 1);
+        assert!(format!("{:#}", errors[0]).contains("Failed to deliver to Slack webhook"));
+    }
+}
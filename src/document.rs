@@ -0,0 +1,300 @@
+//! Document formats: which patterns a format can hold and how text is written
+//! into it, chosen per file by extension or front matter
+
+use std::borrow::Cow;
+use std::path::Path;
+
+use crate::pattern::{self, PatternType};
+
+/// Pattern types a Markdown-like format can hold
+const ALL_TYPES: &[PatternType] = &[PatternType::Inline, PatternType::CodeBlock, PatternType::Comment];
+
+/// Only `ecce <prompt> ecce`: formats without fenced code or HTML comments
+const INLINE_ONLY: &[PatternType] = &[PatternType::Inline];
+
+/// Only `- [ ] <question>` items: question queues
+const ITEMS_ONLY: &[PatternType] = &[PatternType::Item];
+
+/// Front matter keys that mark a Markdown file as a Slidev deck
+const SLIDEV_KEYS: &[&str] = &["theme", "layout", "highlighter", "drawings", "transition"];
+
+/// Per-format pattern contexts and replacement mechanics. `replace` takes
+/// plain text on both sides; text found in the document goes through
+/// `decode` first.
+pub trait DocumentHandler: Sync {
+    /// Short name shown in the watch banner
+    fn name(&self) -> &'static str;
+
+    /// Pattern types the format can hold
+    fn pattern_types(&self) -> &'static [PatternType] {
+        ALL_TYPES
+    }
+
+    /// How plain text is written into the document
+    fn encode<'a>(&self, text: &'a str) -> Cow<'a, str> {
+        Cow::Borrowed(text)
+    }
+
+    /// Plain text of something found in the document, such as a pattern's `raw`
+    fn decode<'a>(&self, text: &'a str) -> Cow<'a, str> {
+        Cow::Borrowed(text)
+    }
+
+    /// Replace the first occurrence of `old` (a pattern, placeholder, or
+    /// question) with `new`; `None` if `old` isn't there
+    fn replace(&self, content: &str, old: &str, new: &str) -> Option<String> {
+        pattern::replace_pattern(content, &self.encode(old), &self.encode(new))
+    }
+
+    /// Whether `---` lines divide the document into slides an answer can be
+    /// fitted to (see `fit`)
+    fn fits_slides(&self) -> bool {
+        false
+    }
+
+    /// An answer ready to write, from parts that each belong on a slide of
+    /// their own (see `fit::split`); formats without slides run them together
+    fn lay_out(&self, parts: &[String]) -> String {
+        parts.join("\n\n")
+    }
+}
+
+/// Plain Markdown, the default
+pub struct Markdown;
+
+impl DocumentHandler for Markdown {
+    fn name(&self) -> &'static str {
+        "markdown"
+    }
+}
+
+/// A Slidev deck: Markdown where a `---` line starts a new slide
+pub struct Slidev;
+
+impl DocumentHandler for Slidev {
+    fn name(&self) -> &'static str {
+        "slidev"
+    }
+
+    fn fits_slides(&self) -> bool {
+        true
+    }
+
+    /// Parts after the first continue on new slides. A `---` rule inside a
+    /// part would split its slide in two, so it becomes `***`; inside fenced
+    /// code it is left alone.
+    fn lay_out(&self, parts: &[String]) -> String {
+        let slides: Vec<String> = parts
+            .iter()
+            .map(|part| {
+                let mut in_fence = false;
+                part.split('\n')
+                    .map(|line| {
+                        if line.trim_start().starts_with("```") {
+                            in_fence = !in_fence;
+                        }
+                        if is_slide_separator(line) && !in_fence {
+                            "***"
+                        } else {
+                            line
+                        }
+                    })
+                    .collect::<Vec<_>>()
+                    .join("\n")
+            })
+            .collect();
+        slides.join("\n\n---\n\n")
+    }
+}
+
+/// A Jupyter notebook; patterns sit in cell sources, which are JSON strings
+pub struct Notebook;
+
+impl DocumentHandler for Notebook {
+    fn name(&self) -> &'static str {
+        "notebook"
+    }
+
+    /// Escaped as JSON string contents, so answers stay inside their cell
+    fn encode<'a>(&self, text: &'a str) -> Cow<'a, str> {
+        let quoted = serde_json::to_string(text).expect("strings always serialize");
+        match &quoted[1..quoted.len() - 1] {
+            escaped if escaped == text => Cow::Borrowed(text),
+            escaped => Cow::Owned(escaped.to_string()),
+        }
+    }
+
+    fn decode<'a>(&self, text: &'a str) -> Cow<'a, str> {
+        if !text.contains('\\') {
+            return Cow::Borrowed(text);
+        }
+        match serde_json::from_str::<String>(&format!("\"{}\"", text)) {
+            Ok(decoded) => Cow::Owned(decoded),
+            Err(_) => Cow::Borrowed(text),
+        }
+    }
+
+    /// Refuses a replacement that would leave the notebook unreadable
+    fn replace(&self, content: &str, old: &str, new: &str) -> Option<String> {
+        let replaced = pattern::replace_pattern(content, &self.encode(old), &self.encode(new))?;
+        serde_json::from_str::<serde_json::Value>(&replaced).ok()?;
+        Some(replaced)
+    }
+}
+
+/// An Org mode file: no fenced code or HTML comments, so inline patterns only
+pub struct Org;
+
+impl DocumentHandler for Org {
+    fn name(&self) -> &'static str {
+        "org"
+    }
+
+    fn pattern_types(&self) -> &'static [PatternType] {
+        INLINE_ONLY
+    }
+}
+
+/// Plain text: inline patterns only
+pub struct PlainText;
+
+impl DocumentHandler for PlainText {
+    fn name(&self) -> &'static str {
+        "text"
+    }
+
+    fn pattern_types(&self) -> &'static [PatternType] {
+        INLINE_ONLY
+    }
+}
+
+/// A question queue: every unchecked top-level `- [ ]` item is a question,
+/// ticked once it is answered. Chosen with `--queue`, never by extension.
+pub struct Queue;
+
+impl Queue {
+    /// The item ticked, with `answer` indented beneath it
+    pub fn answered(item: &str, answer: &str) -> String {
+        let indented: Vec<String> = answer
+            .lines()
+            .map(|line| if line.is_empty() { String::new() } else { format!("  {}", line) })
+            .collect();
+        format!("{}\n\n{}", Self::checked(item), indented.join("\n"))
+    }
+
+    /// The item ticked
+    pub fn checked(item: &str) -> String {
+        item.replacen("[ ]", "[x]", 1)
+    }
+}
+
+impl DocumentHandler for Queue {
+    fn name(&self) -> &'static str {
+        "queue"
+    }
+
+    fn pattern_types(&self) -> &'static [PatternType] {
+        ITEMS_ONLY
+    }
+}
+
+/// The handler for a file, by extension and then, for Markdown, front matter
+pub fn handler_for(path: &Path, content: &str) -> &'static dyn DocumentHandler {
+    let extension = path
+        .extension()
+        .and_then(|e| e.to_str())
+        .map(str::to_lowercase)
+        .unwrap_or_default();
+    match extension.as_str() {
+        "ipynb" => &Notebook,
+        "org" => &Org,
+        "txt" | "text" => &PlainText,
+        _ if is_slidev(path, content) => &Slidev,
+        _ => &Markdown,
+    }
+}
+
+/// Pattern types to detect: an explicit `--only` wins, otherwise whatever the format can hold
+pub fn pattern_types(handler: &dyn DocumentHandler, only: &[PatternType]) -> Vec<PatternType> {
+    match only {
+        [] if handler.pattern_types() == ALL_TYPES => Vec::new(),
+        [] => handler.pattern_types().to_vec(),
+        only => only.to_vec(),
+    }
+}
+
+/// `slides.md`, or Markdown whose front matter sets a Slidev key
+fn is_slidev(path: &Path, content: &str) -> bool {
+    if path.file_name().is_some_and(|name| name == "slides.md") {
+        return true;
+    }
+    let Some(rest) = content.strip_prefix("---\n").or_else(|| content.strip_prefix("---\r\n")) else {
+        return false;
+    };
+    rest.lines()
+        .take_while(|line| !is_slide_separator(line))
+        .filter_map(|line| line.split_once(':'))
+        .any(|(key, _)| SLIDEV_KEYS.contains(&key.trim()))
+}
+
+fn is_slide_separator(line: &str) -> bool {
+    line.trim_end() == "---"
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_handler_for() {
+        let pick = |path: &str, content: &str| handler_for(Path::new(path), content).name();
+        assert_eq!(pick("notes.md", "# Notes"), "markdown");
+        assert_eq!(pick("talks/slides.md", "# Intro"), "slidev");
+        assert_eq!(pick("deck.md", "---\ntheme: seriph\n---\n\n# Intro"), "slidev");
+        assert_eq!(pick("post.md", "---\ntitle: Hello\n---\n\n# Hello"), "markdown");
+        assert_eq!(pick("analysis.IPYNB", "{}"), "notebook");
+        assert_eq!(pick("todo.org", "* Tasks"), "org");
+        assert_eq!(pick("scratch.txt", ""), "text");
+        assert_eq!(pick("README", ""), "markdown");
+    }
+
+    #[test]
+    fn test_pattern_types() {
+        assert!(pattern_types(&Markdown, &[]).is_empty());
+        assert_eq!(pattern_types(&Org, &[]), vec![PatternType::Inline]);
+        assert_eq!(pattern_types(&Org, &[PatternType::Comment]), vec![PatternType::Comment]);
+    }
+
+    #[test]
+    fn test_queue_answered() {
+        assert_eq!(Queue::checked("- [ ] Why [ ]?"), "- [x] Why [ ]?");
+        assert_eq!(
+            Queue::answered("- [ ] Why?", "Because.\n\n- one\n- two"),
+            "- [x] Why?\n\n  Because.\n\n  - one\n  - two"
+        );
+    }
+
+    #[test]
+    fn test_slidev_lay_out() {
+        let parts = ["A language.\n---\nFast.".to_string(), "More.".to_string()];
+        assert_eq!(Slidev.lay_out(&parts), "A language.\n***\nFast.\n\n---\n\nMore.");
+        assert_eq!(Markdown.lay_out(&parts), "A language.\n---\nFast.\n\nMore.");
+        let yaml = ["```yaml\n---\nname: ecce\n```\n---".to_string()];
+        assert_eq!(Slidev.lay_out(&yaml), "```yaml\n---\nname: ecce\n```\n***");
+        // Only a deck has slides to fit an answer to
+        assert!(Slidev.fits_slides() && !Markdown.fits_slides());
+    }
+
+    #[test]
+    fn test_notebook_replaces_inside_cell_source() {
+        let content = r##"{"cells": [{"cell_type": "markdown", "source": ["# Data\n", "ecce say \"hi\" ecce"]}]}"##;
+        let raw = r#"ecce say \"hi\" ecce"#;
+        let question = Notebook.decode(raw);
+        assert_eq!(question, "ecce say \"hi\" ecce");
+
+        let replaced = Notebook.replace(content, &question, "Line one\nLine \"two\"").unwrap();
+        let notebook: serde_json::Value = serde_json::from_str(&replaced).unwrap();
+        assert_eq!(notebook["cells"][0]["source"][1], "Line one\nLine \"two\"");
+        assert!(Notebook.replace(content, "not there", "x").is_none());
+    }
+}
@@ -0,0 +1,237 @@
+use serde_json::Value;
+use std::collections::BTreeSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::agent::ClaudeAgent;
+use crate::config::Profile;
+use crate::models::{self, Catalog, ModelCheck};
+
+/// Variables the claude CLI authenticates with, in the order it prefers them
+pub const CREDENTIAL_VARS: [&str; 2] = ["ANTHROPIC_API_KEY", "ANTHROPIC_AUTH_TOKEN"];
+
+/// What a claude subprocess started for an agent will actually run against
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct GenerationEnv {
+    /// The profile generations should use: the project's pinned one, else ecce's active one
+    pub profile: Option<Profile>,
+    /// `ANTHROPIC_BASE_URL`; unset means Anthropic's API
+    pub base_url: Option<String>,
+    /// The credential variable that is set, and its value
+    pub credential: Option<(&'static str, String)>,
+    pub model: Option<String>,
+    /// MCP servers claude will load in the agent's working directory
+    pub mcp_servers: Vec<String>,
+    /// The profile's cached model list (`ecce api models`), if fetched
+    pub catalog: Option<Catalog>,
+}
+
+impl GenerationEnv {
+    pub fn for_agent(agent: &ClaudeAgent, profile: Option<Profile>) -> Self {
+        let catalog = profile.as_ref().and_then(models::cached);
+        let credential = CREDENTIAL_VARS
+            .iter()
+            .find_map(|&name| agent.env_var(name).map(|value| (name, value)));
+        let dir = agent
+            .agent()
+            .working_dir
+            .as_ref()
+            .map(PathBuf::from)
+            .or_else(|| std::env::current_dir().ok())
+            .unwrap_or_default();
+
+        Self {
+            profile,
+            base_url: agent.env_var("ANTHROPIC_BASE_URL"),
+            credential,
+            model: agent.model(),
+            mcp_servers: dirs::home_dir()
+                .map(|home| mcp_servers(&home, &dir))
+                .unwrap_or_default(),
+            catalog,
+        }
+    }
+
+    /// Reasons generations may not go where the user expects
+    pub fn problems(&self) -> Vec<String> {
+        let mut problems = Vec::new();
+        if self.credential.is_none() {
+            problems.push(format!(
+                "Neither {} is set for claude",
+                CREDENTIAL_VARS.join(" nor ")
+            ));
+        }
+        if let (Some(model), Some(catalog), Some(profile)) = (&self.model, &self.catalog, &self.profile) {
+            if let ModelCheck::Unknown { suggestion } = catalog.check(model) {
+                let hint = suggestion.map(|s| format!(" (did you mean '{}'?)", s)).unwrap_or_default();
+                problems.push(format!(
+                    "Model '{}' is not in the models '{}' offers{}",
+                    model, profile.name, hint
+                ));
+            }
+        }
+        if let Some(ref profile) = self.profile {
+            if self.base_url.as_deref() != Some(profile.url.as_str()) {
+                problems.push(format!(
+                    "ANTHROPIC_BASE_URL is {} but the profile '{}' uses {}",
+                    self.base_url.as_deref().unwrap_or("unset"),
+                    profile.name,
+                    profile.url
+                ));
+            }
+            if self.credential.as_ref().is_some_and(|(_, key)| *key != profile.key) {
+                problems.push(format!(
+                    "The API key claude will use is not the profile '{}''s key",
+                    profile.name
+                ));
+            }
+        }
+        problems
+    }
+}
+
+/// The project Claude Code should key `dir` under in `~/.claude.json`: the
+/// nearest ancestor with a `.git` or a `.claude` directory (home's `.claude`
+/// is user-level and doesn't count), else `dir` itself; symlinks resolved
+pub fn claude_project_root(dir: &Path, home: Option<&Path>) -> PathBuf {
+    let dir = dir.canonicalize().unwrap_or_else(|_| dir.components().collect());
+    let home = home.map(|h| h.canonicalize().unwrap_or_else(|_| h.to_path_buf()));
+    dir.ancestors()
+        .take_while(|ancestor| Some(*ancestor) != home.as_deref())
+        .find(|ancestor| ancestor.join(".git").exists() || ancestor.join(".claude").is_dir())
+        .unwrap_or(&dir)
+        .to_path_buf()
+}
+
+/// MCP servers configured for claude in `dir`: global and per-project entries
+/// in `~/.claude.json`, plus the project's `.mcp.json`, both found from the
+/// project root (see [`claude_project_root`])
+pub fn mcp_servers(home: &Path, dir: &Path) -> Vec<String> {
+    let read = |path: PathBuf| -> Option<Value> {
+        serde_json::from_str(&fs::read_to_string(path).ok()?).ok()
+    };
+    let mut names = BTreeSet::new();
+    let mut add = |servers: Option<&Value>| {
+        if let Some(servers) = servers.and_then(Value::as_object) {
+            names.extend(servers.keys().cloned());
+        }
+    };
+
+    let root = claude_project_root(dir, Some(home));
+    if let Some(claude_json) = read(home.join(".claude.json")) {
+        add(claude_json.get("mcpServers"));
+        let project = claude_json.get("projects").and_then(|p| p.get(root.to_string_lossy().as_ref()));
+        add(project.and_then(|p| p.get("mcpServers")));
+    }
+    if let Some(mcp_json) = read(root.join(".mcp.json")) {
+        add(mcp_json.get("mcpServers"));
+    }
+    names.into_iter().collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn profile() -> Profile {
+        Profile {
+            name: "work".to_string(),
+            url: "https://proxy.example.com".to_string(),
+            key: "sk-work".to_string(),
+            service: "claude-code".to_string(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_problems() {
+        let env = GenerationEnv {
+            profile: Some(profile()),
+            base_url: Some("https://proxy.example.com".to_string()),
+            credential: Some(("ANTHROPIC_API_KEY", "sk-work".to_string())),
+            ..Default::default()
+        };
+        assert!(env.problems().is_empty());
+
+        let wrong = GenerationEnv {
+            base_url: None,
+            credential: Some(("ANTHROPIC_API_KEY", "sk-other".to_string())),
+            ..env.clone()
+        };
+        let problems = wrong.problems();
+        assert_eq!(problems.len(), 2);
+        assert!(problems[0].contains("is unset but the profile 'work' uses https://proxy.example.com"));
+
+        let typo = GenerationEnv {
+            model: Some("claude-sonet-4".to_string()),
+            catalog: Some(Catalog {
+                url: "https://proxy.example.com".to_string(),
+                fetched_at: chrono::Utc::now(),
+                models: vec!["claude-sonnet-4".to_string()],
+            }),
+            ..env.clone()
+        };
+        assert_eq!(
+            typo.problems(),
+            vec!["Model 'claude-sonet-4' is not in the models 'work' offers (did you mean 'claude-sonnet-4'?)"]
+        );
+
+        let missing = GenerationEnv::default();
+        assert_eq!(
+            missing.problems(),
+            vec!["Neither ANTHROPIC_API_KEY nor ANTHROPIC_AUTH_TOKEN is set for claude"]
+        );
+    }
+
+    #[test]
+    fn test_mcp_servers() {
+        let home = tempfile::TempDir::new().unwrap();
+        let project = tempfile::TempDir::new().unwrap();
+        let claude_json = serde_json::json!({
+            "mcpServers": { "global": {} },
+            "projects": {
+                project.path().to_string_lossy(): { "mcpServers": { "local": {} } },
+                "/elsewhere": { "mcpServers": { "other": {} } }
+            }
+        });
+        fs::write(home.path().join(".claude.json"), claude_json.to_string()).unwrap();
+        fs::write(
+            project.path().join(".mcp.json"),
+            r#"{"mcpServers": {"shared": {}, "global": {}}}"#,
+        )
+        .unwrap();
+
+        assert_eq!(mcp_servers(home.path(), project.path()), vec!["global", "local", "shared"]);
+
+        // A subdirectory of a git project sees the project's entry
+        fs::create_dir(project.path().join(".git")).unwrap();
+        fs::create_dir(project.path().join("slides")).unwrap();
+        assert_eq!(
+            mcp_servers(home.path(), &project.path().join("slides")),
+            vec!["global", "local", "shared"]
+        );
+    }
+
+    #[test]
+    fn test_claude_project_root() {
+        let home = tempfile::TempDir::new().unwrap();
+        let home = home.path().canonicalize().unwrap();
+        fs::create_dir(home.join(".claude")).unwrap();
+        let nested = home.join("talks/rust/slides");
+        fs::create_dir_all(&nested).unwrap();
+
+        // Home's .claude is user-level, so without a marker the directory is its own root
+        assert_eq!(claude_project_root(&nested, Some(&home)), nested);
+
+        fs::create_dir(home.join("talks/rust/.claude")).unwrap();
+        assert_eq!(claude_project_root(&nested, Some(&home)), home.join("talks/rust"));
+        // A .git file (worktrees, submodules) counts as well
+        fs::write(nested.join(".git"), "gitdir: elsewhere").unwrap();
+        assert_eq!(claude_project_root(&nested, Some(&home)), nested);
+        // Missing paths are kept as written, minus trailing slashes
+        assert_eq!(
+            claude_project_root(Path::new("/no/such/dir/"), None),
+            PathBuf::from("/no/such/dir")
+        );
+    }
+}
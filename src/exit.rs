@@ -0,0 +1,112 @@
+use std::fmt;
+
+/// Exit code for failures without a more specific category
+pub const GENERIC_FAILURE: i32 = 1;
+
+/// Why a command failed, mapped to a distinct process exit code so scripts can
+/// branch on it. Wrap an existing error with `.context(Failure::Config)`, or
+/// create one with `Failure::ProfileNotFound.error(message)`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Failure {
+    /// The config file could not be read or parsed
+    Config,
+    /// A named profile does not exist
+    ProfileNotFound,
+    /// Claude failed to answer a pattern
+    Generation,
+    /// Unanswered patterns are left in a file
+    PatternsRemaining,
+    /// An API endpoint could not be reached
+    Connectivity,
+    /// A newer ecce release exists (`ecce upgrade --check`)
+    UpdateAvailable,
+    /// Something that should never happen did: a bug in ecce, reported with a
+    /// diagnostic bundle
+    Internal,
+}
+
+impl Failure {
+    pub fn code(self) -> i32 {
+        match self {
+            Failure::Config => 3,
+            Failure::ProfileNotFound => 4,
+            Failure::Generation => 5,
+            Failure::PatternsRemaining => 6,
+            Failure::Connectivity => 7,
+            Failure::UpdateAvailable => 8,
+            // EX_SOFTWARE in sysexits.h
+            Failure::Internal => 70,
+        }
+    }
+
+    /// An error with this failure's exit code and the given message
+    pub fn error(self, message: impl fmt::Display) -> anyhow::Error {
+        anyhow::Error::new(FailureError {
+            failure: self,
+            message: message.to_string(),
+        })
+    }
+}
+
+impl fmt::Display for Failure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let text = match self {
+            Failure::Config => "Invalid configuration",
+            Failure::ProfileNotFound => "Profile not found",
+            Failure::Generation => "Generation failed",
+            Failure::PatternsRemaining => "Unanswered patterns remain",
+            Failure::Connectivity => "Connection failed",
+            Failure::UpdateAvailable => "Update available",
+            Failure::Internal => "Internal error",
+        };
+        f.write_str(text)
+    }
+}
+
+/// Error created by `Failure::error`
+#[derive(Debug)]
+struct FailureError {
+    failure: Failure,
+    message: String,
+}
+
+impl fmt::Display for FailureError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+impl std::error::Error for FailureError {}
+
+/// Process exit code for an error: its failure category's code, or 1
+pub fn exit_code(error: &anyhow::Error) -> i32 {
+    error
+        .downcast_ref::<Failure>()
+        .copied()
+        .or_else(|| {
+            error
+                .chain()
+                .find_map(|e| e.downcast_ref::<FailureError>().map(|f| f.failure))
+        })
+        .map_or(GENERIC_FAILURE, Failure::code)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::Context;
+
+    #[test]
+    fn test_exit_code_from_context_and_error() {
+        let parse: anyhow::Result<()> = Err(anyhow::anyhow!("expected value at line 1"));
+        let err = parse.context(Failure::Config).context("Loading config").unwrap_err();
+        assert_eq!(exit_code(&err), 3);
+
+        let err = Failure::ProfileNotFound.error("Profile 'work' not found");
+        assert_eq!(err.to_string(), "Profile 'work' not found");
+        assert_eq!(exit_code(&err), 4);
+        assert_eq!(exit_code(&err.context("Switching profile")), 4);
+
+        assert_eq!(exit_code(&anyhow::anyhow!("something else")), GENERIC_FAILURE);
+    }
+}
@@ -0,0 +1,156 @@
+//! Answer length budgeting: how much room is left on the slide a pattern is
+//! on, and splitting an answer that doesn't fit into slide-sized parts
+
+use std::ops::Range;
+
+/// Characters that fit on one line of a slide; longer lines wrap
+const LINE_WIDTH: usize = 80;
+
+/// Fewest lines an answer is asked to fit in, however full its slide is
+const MIN_LINES: usize = 3;
+
+/// Lines `text` takes up on a slide, counting wrapped lines
+pub fn height(text: &str) -> usize {
+    text.lines()
+        .map(|line| line.chars().count().div_ceil(LINE_WIDTH).max(1))
+        .sum()
+}
+
+/// Lines left out of `per_slide` on the slide holding `pattern`. The pattern
+/// itself, blank lines, and comments (speaker notes) take no room.
+pub fn remaining_lines(content: &str, pattern: Range<usize>, per_slide: usize) -> usize {
+    let mut start = 0;
+    let mut end = content.len();
+    let mut offset = 0;
+    for line in content.split_inclusive('\n') {
+        if is_separator(line) {
+            if offset + line.len() <= pattern.start {
+                start = offset + line.len();
+            } else if offset >= pattern.end {
+                end = offset;
+                break;
+            }
+        }
+        offset += line.len();
+    }
+    let used = used_lines(&content[start..pattern.start.max(start)]) + used_lines(&content[pattern.end.min(end)..end]);
+    per_slide.saturating_sub(used)
+}
+
+/// Ask for an answer of at most `lines` lines
+pub fn instruction(lines: usize) -> String {
+    format!(
+        "\n\nThe answer goes on a slide with room for about {} more lines; keep it that short.",
+        lines.max(MIN_LINES)
+    )
+}
+
+/// Split `answer` at paragraph breaks into parts of at most `first` lines,
+/// then `per_slide` lines each. A paragraph or code block is never split; one
+/// taller than a slide gets a part of its own. The first part is empty when
+/// nothing fits in `first`.
+pub fn split(answer: &str, first: usize, per_slide: usize) -> Vec<String> {
+    let mut parts: Vec<String> = vec![String::new()];
+    let mut budget = first;
+    for block in blocks(answer) {
+        let current = parts.last_mut().expect("always one part");
+        let needed = if current.is_empty() { height(&block) } else { height(current) + 1 + height(&block) };
+        if needed <= budget {
+            if !current.is_empty() {
+                current.push_str("\n\n");
+            }
+            current.push_str(&block);
+            continue;
+        }
+        // Doesn't fit here: it starts the next part, even if it overflows that too
+        budget = per_slide;
+        parts.push(block);
+    }
+    parts
+}
+
+/// Paragraphs of `text`, keeping blank lines inside fenced code
+fn blocks(text: &str) -> Vec<String> {
+    let mut blocks = Vec::new();
+    let mut block: Vec<&str> = Vec::new();
+    let mut in_fence = false;
+    for line in text.lines() {
+        if line.trim_start().starts_with("```") {
+            in_fence = !in_fence;
+        }
+        if line.trim().is_empty() && !in_fence {
+            if !block.is_empty() {
+                blocks.push(block.join("\n"));
+                block.clear();
+            }
+            continue;
+        }
+        block.push(line);
+    }
+    if !block.is_empty() {
+        blocks.push(block.join("\n"));
+    }
+    blocks
+}
+
+/// Lines that take room on a slide: not blank and not inside an HTML comment
+fn used_lines(text: &str) -> usize {
+    let mut used = 0;
+    let mut in_comment = false;
+    for line in text.lines() {
+        let trimmed = line.trim();
+        if in_comment || trimmed.starts_with("<!--") {
+            in_comment = !trimmed.contains("-->");
+            continue;
+        }
+        if !trimmed.is_empty() {
+            used += height(line);
+        }
+    }
+    used
+}
+
+fn is_separator(line: &str) -> bool {
+    line.trim_end() == "---"
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_height_counts_wrapped_lines() {
+        assert_eq!(height("one\ntwo"), 2);
+        assert_eq!(height(&"x".repeat(LINE_WIDTH + 1)), 2);
+        assert_eq!(height("a\n\nb"), 3);
+    }
+
+    #[test]
+    fn test_remaining_lines() {
+        let content = "# One\n\n- a\n- b\n\n---\n\n# Two\n\necce q ecce\n\n- c\n\n<!--\nSpeaker notes\n-->\n\n---\n\n# Three\n";
+        let start = content.find("ecce q").unwrap();
+        let pattern = start..start + "ecce q ecce".len();
+        // "# Two" and "- c"; the notes and the other slides don't count
+        assert_eq!(remaining_lines(content, pattern, 10), 8);
+        assert_eq!(remaining_lines("ecce q ecce", 0..11, 10), 10);
+        assert_eq!(remaining_lines(&"line\n".repeat(12), 0..0, 10), 0);
+    }
+
+    #[test]
+    fn test_instruction_has_a_floor() {
+        assert!(instruction(8).contains("about 8 more lines"));
+        assert!(instruction(0).contains("about 3 more lines"));
+    }
+
+    #[test]
+    fn test_split() {
+        let answer = "One.\n\nTwo.\n\n```rust\nfn a() {}\n\nfn b() {}\n```\n\nThree.";
+        assert_eq!(split(answer, 20, 20), vec![answer.to_string()]);
+        assert_eq!(
+            split(answer, 3, 5),
+            vec!["One.\n\nTwo.", "```rust\nfn a() {}\n\nfn b() {}\n```", "Three."]
+        );
+        // A full slide: everything moves on
+        assert_eq!(split("One.\n\nTwo.", 0, 5), vec!["", "One.\n\nTwo."]);
+    }
+}
@@ -0,0 +1,146 @@
+//! Content guards: rules that block or flag responses containing forbidden
+//! content (internal codenames, PII) before they are written anywhere
+
+use anyhow::{Context, Result};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+/// What happens when a guard matches a response
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, Default, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum GuardAction {
+    /// Don't write the response (interactive sessions may override)
+    #[default]
+    Block,
+    /// Write the response but warn about the match
+    Flag,
+}
+
+/// A rule from `guards` in the config or a project's `.ecce.json`
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct GuardRule {
+    /// Shown when the rule matches, e.g. "internal codenames"
+    pub name: String,
+    /// Regular expressions, any of which triggers the rule
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub patterns: Vec<String>,
+    /// Words or phrases matched case-insensitively as whole words
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub words: Vec<String>,
+    #[serde(default)]
+    pub action: GuardAction,
+}
+
+/// A rule that matched a response
+#[derive(Debug, Clone, PartialEq)]
+pub struct Violation {
+    pub rule: String,
+    pub action: GuardAction,
+    /// The text that matched
+    pub matched: String,
+    /// 1-based line in the response
+    pub line: usize,
+}
+
+/// Compiled guard rules
+#[derive(Default)]
+pub struct Guard {
+    rules: Vec<(String, GuardAction, Vec<Regex>)>,
+}
+
+impl Guard {
+    /// Compile rules, failing on the first invalid pattern
+    pub fn new(rules: &[GuardRule]) -> Result<Self> {
+        let rules = rules
+            .iter()
+            .map(|rule| {
+                let words = rule
+                    .words
+                    .iter()
+                    .filter(|w| !w.trim().is_empty())
+                    .map(|w| format!(r"(?i)\b{}\b", regex::escape(w.trim())));
+                let regexes = rule
+                    .patterns
+                    .iter()
+                    .cloned()
+                    .chain(words)
+                    .map(|p| {
+                        Regex::new(&p).with_context(|| format!("Invalid pattern in guard '{}': {}", rule.name, p))
+                    })
+                    .collect::<Result<Vec<_>>>()?;
+                Ok((rule.name.clone(), rule.action, regexes))
+            })
+            .collect::<Result<Vec<_>>>()?;
+        Ok(Self { rules })
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.rules.is_empty()
+    }
+
+    /// The first match of each rule in `text`
+    pub fn check(&self, text: &str) -> Vec<Violation> {
+        self.rules
+            .iter()
+            .filter_map(|(name, action, regexes)| {
+                let found = regexes.iter().filter_map(|r| r.find(text)).min_by_key(|m| m.start())?;
+                Some(Violation {
+                    rule: name.clone(),
+                    action: *action,
+                    matched: found.as_str().to_string(),
+                    line: text[..found.start()].matches('\n').count() + 1,
+                })
+            })
+            .collect()
+    }
+}
+
+/// Whether any violation blocks the response
+pub fn blocks(violations: &[Violation]) -> bool {
+    violations.iter().any(|v| v.action == GuardAction::Block)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rules() -> Vec<GuardRule> {
+        serde_json::from_str(
+            r#"[
+                {"name": "codenames", "words": ["Project Falcon", "bluebird"]},
+                {"name": "emails", "patterns": ["[\\w.+-]+@[\\w-]+\\.[\\w.]+"], "action": "flag"}
+            ]"#,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_check() {
+        let guard = Guard::new(&rules()).unwrap();
+        assert!(guard.check("Nothing to see here").is_empty());
+        // Whole words only
+        assert!(guard.check("bluebirds fly").is_empty());
+
+        let violations = guard.check("# Plan\n\nproject falcon ships soon\nMail ana@example.com or BLUEBIRD");
+        assert_eq!(violations.len(), 2);
+        assert_eq!(violations[0].rule, "codenames");
+        assert_eq!(violations[0].matched, "project falcon");
+        assert_eq!(violations[0].line, 3);
+        assert_eq!(violations[1].action, GuardAction::Flag);
+        assert_eq!(violations[1].matched, "ana@example.com");
+        assert!(blocks(&violations));
+        assert!(!blocks(&violations[1..]));
+    }
+
+    #[test]
+    fn test_invalid_pattern() {
+        let rule = GuardRule {
+            name: "broken".to_string(),
+            patterns: vec!["(unclosed".to_string()],
+            words: Vec::new(),
+            action: GuardAction::Block,
+        };
+        let err = Guard::new(&[rule]).err().unwrap();
+        assert!(err.to_string().contains("guard 'broken'"));
+    }
+}
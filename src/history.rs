@@ -0,0 +1,553 @@
+use anyhow::{Context, Result};
+use chrono::{DateTime, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use crate::config::Config;
+
+/// A single recorded generation: the prompt that was sent and what came back
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct HistoryEntry {
+    /// Identifies the entry for `ecce history diff`; missing in older logs
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
+    pub timestamp: DateTime<Utc>,
+    pub file: String,
+    pub agent: String,
+    pub task: Option<String>,
+    #[serde(default)]
+    pub profile: Option<String>,
+    pub prompt: String,
+    pub response: Option<String>,
+    pub error: Option<String>,
+    pub duration_ms: u64,
+    /// Estimated token counts (see `estimate_tokens`)
+    #[serde(default)]
+    pub prompt_tokens: Option<u64>,
+    #[serde(default)]
+    pub response_tokens: Option<u64>,
+    /// What went into the prompt besides the question
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub context: Option<ContextSnapshot>,
+    /// How the answer was rated, if it was (kept in the feedback log, see `load`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub feedback: Option<Feedback>,
+}
+
+/// A rating given to an answer after reading it
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct Feedback {
+    pub vote: Vote,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub note: Option<String>,
+    pub timestamp: DateTime<Utc>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Vote {
+    Up,
+    Down,
+}
+
+impl Vote {
+    /// `+`/`up`/`y` or `-`/`down`/`n`; anything else is no vote
+    pub fn parse(answer: &str) -> Option<Self> {
+        match answer.trim().to_lowercase().as_str() {
+            "+" | "up" | "y" | "yes" | "good" | "👍" => Some(Vote::Up),
+            "-" | "down" | "n" | "no" | "bad" | "👎" => Some(Vote::Down),
+            _ => None,
+        }
+    }
+
+    pub fn emoji(self) -> &'static str {
+        match self {
+            Vote::Up => "👍",
+            Vote::Down => "👎",
+        }
+    }
+}
+
+/// A line of the feedback log: a rating for the entry with id `id`
+#[derive(Debug, Serialize, Deserialize)]
+struct Rated {
+    id: String,
+    #[serde(flatten)]
+    feedback: Feedback,
+}
+
+/// The inputs behind a generation, recorded so two runs can be compared
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
+pub struct ContextSnapshot {
+    pub model: Option<String>,
+    /// `content_hash` of the agent's system prompt
+    pub system_prompt_hash: String,
+    /// The task template as resolved (base templates included)
+    pub template: String,
+    /// Context files sent, after any were dropped to fit the token budget
+    pub context_files: Vec<ContextFile>,
+    /// What was left out to fit the token budget
+    #[serde(default)]
+    pub dropped: Vec<String>,
+    /// Conversation messages replayed ahead of the question
+    #[serde(default)]
+    pub replayed_messages: usize,
+    /// Sent as a follow-up in a resumed claude session, which already had the context
+    #[serde(default)]
+    pub resumed: bool,
+    #[serde(default)]
+    pub lang: Option<String>,
+    /// Corrective turns needed to meet the task's output checks
+    #[serde(default)]
+    pub corrections: usize,
+}
+
+/// A context file as it was when sent
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct ContextFile {
+    pub path: String,
+    /// `content_hash` of its content
+    pub hash: String,
+}
+
+impl HistoryEntry {
+    pub fn is_success(&self) -> bool {
+        self.error.is_none()
+    }
+}
+
+/// Rough token estimate (~4 characters per token); Claude Code's text output
+/// does not report usage, so this is what stats are based on
+pub fn estimate_tokens(text: &str) -> u64 {
+    (text.chars().count() as u64).div_ceil(4)
+}
+
+/// A new entry id: hex nanoseconds since the epoch and the process id
+pub fn new_id() -> String {
+    format!(
+        "{:x}{:x}",
+        Utc::now().timestamp_nanos_opt().unwrap_or_default(),
+        std::process::id()
+    )
+}
+
+/// Get the history log path (~/.config/ecce/history.jsonl)
+pub fn history_path() -> Result<PathBuf> {
+    Ok(Config::config_dir()?.join("history.jsonl"))
+}
+
+/// Append an entry to the history log
+pub fn record(entry: &HistoryEntry) -> Result<()> {
+    append_to(&history_path()?, entry)
+}
+
+/// Append an entry to a history log at the given path
+pub fn append_to(path: &Path, entry: &HistoryEntry) -> Result<()> {
+    let mut file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .with_context(|| format!("Failed to open history log: {}", path.display()))?;
+    writeln!(file, "{}", serde_json::to_string(entry)?)?;
+    Ok(())
+}
+
+/// Get the feedback log path (~/.config/ecce/feedback.jsonl). Ratings are
+/// kept apart from the history log, which is only ever appended to.
+pub fn feedback_path() -> Result<PathBuf> {
+    Ok(Config::config_dir()?.join("feedback.jsonl"))
+}
+
+/// Rate the entry with id `id`; a later rating replaces an earlier one
+pub fn rate(id: &str, feedback: Feedback) -> Result<()> {
+    rate_to(&feedback_path()?, id, feedback)
+}
+
+/// Append a rating to a feedback log at the given path
+pub fn rate_to(path: &Path, id: &str, feedback: Feedback) -> Result<()> {
+    let mut file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .with_context(|| format!("Failed to open feedback log: {}", path.display()))?;
+    let rated = Rated {
+        id: id.to_string(),
+        feedback,
+    };
+    writeln!(file, "{}", serde_json::to_string(&rated)?)?;
+    Ok(())
+}
+
+/// Load all entries from the history log, with their ratings
+pub fn load() -> Result<Vec<HistoryEntry>> {
+    let mut entries = load_from(&history_path()?)?;
+    attach_feedback(&mut entries, &feedback_path()?)?;
+    Ok(entries)
+}
+
+/// Give entries the latest rating for their id from a feedback log at the given path
+pub fn attach_feedback(entries: &mut [HistoryEntry], path: &Path) -> Result<()> {
+    if !path.exists() {
+        return Ok(());
+    }
+    let content = fs::read_to_string(path)
+        .with_context(|| format!("Failed to read feedback log: {}", path.display()))?;
+    let mut ratings = HashMap::new();
+    for line in content.lines().filter(|l| !l.trim().is_empty()) {
+        if let Ok(rated) = serde_json::from_str::<Rated>(line) {
+            ratings.insert(rated.id, rated.feedback);
+        }
+    }
+    for entry in entries.iter_mut() {
+        if let Some(feedback) = entry.id.as_ref().and_then(|id| ratings.get(id)) {
+            entry.feedback = Some(feedback.clone());
+        }
+    }
+    Ok(())
+}
+
+/// Load all entries from a history log at the given path, skipping malformed lines
+pub fn load_from(path: &Path) -> Result<Vec<HistoryEntry>> {
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+
+    let content = fs::read_to_string(path)
+        .with_context(|| format!("Failed to read history log: {}", path.display()))?;
+
+    let mut entries = Vec::new();
+    for (idx, line) in content.lines().enumerate() {
+        if line.trim().is_empty() {
+            continue;
+        }
+        match serde_json::from_str::<HistoryEntry>(line) {
+            Ok(entry) => entries.push(entry),
+            Err(e) => eprintln!("Warning: Skipping malformed history line {}: {}", idx + 1, e),
+        }
+    }
+
+    Ok(entries)
+}
+
+/// Parse a `--since` date argument (YYYY-MM-DD)
+pub fn parse_since(value: &str) -> Result<NaiveDate> {
+    NaiveDate::parse_from_str(value, "%Y-%m-%d")
+        .with_context(|| format!("Invalid date '{}'. Expected YYYY-MM-DD", value))
+}
+
+/// Keep only entries recorded on or after the given date (UTC)
+pub fn filter_since(entries: Vec<HistoryEntry>, since: Option<NaiveDate>) -> Vec<HistoryEntry> {
+    match since {
+        Some(date) => entries
+            .into_iter()
+            .filter(|e| e.timestamp.date_naive() >= date)
+            .collect(),
+        None => entries,
+    }
+}
+
+/// The entry whose id is `id` or starts with it
+pub fn find<'a>(entries: &'a [HistoryEntry], id: &str) -> Result<&'a HistoryEntry> {
+    let matches: Vec<&HistoryEntry> = entries
+        .iter()
+        .filter(|e| e.id.as_deref().is_some_and(|entry_id| entry_id.starts_with(id)))
+        .collect();
+    match matches.as_slice() {
+        [entry] => Ok(entry),
+        [] => Err(anyhow::anyhow!("No generation with id '{}' in the history", id)),
+        _ => Err(anyhow::anyhow!("Id '{}' matches {} generations; use more characters", id, matches.len())),
+    }
+}
+
+/// Why two generations may have differed: one line per input that changed
+pub fn diff(a: &HistoryEntry, b: &HistoryEntry) -> Vec<String> {
+    let mut changes = Vec::new();
+    let or_none = |value: Option<&str>| value.unwrap_or("(none)").to_string();
+
+    changed(&mut changes, "agent", a.agent.clone(), b.agent.clone());
+    changed(&mut changes, "task", or_none(a.task.as_deref()), or_none(b.task.as_deref()));
+    changed(&mut changes, "profile", or_none(a.profile.as_deref()), or_none(b.profile.as_deref()));
+    changed(&mut changes, "file", a.file.clone(), b.file.clone());
+    if a.prompt != b.prompt {
+        changes.push("question:".to_string());
+        changes.extend(line_diff(&a.prompt, &b.prompt).into_iter().map(|l| format!("  {}", l)));
+    }
+
+    let (Some(x), Some(y)) = (&a.context, &b.context) else {
+        if a.context.is_some() != b.context.is_some() {
+            changes.push("context: only one generation has a context snapshot".to_string());
+        }
+        return changes;
+    };
+    changed(&mut changes, "model", or_none(x.model.as_deref()), or_none(y.model.as_deref()));
+    changed(&mut changes, "system prompt", x.system_prompt_hash.clone(), y.system_prompt_hash.clone());
+    if x.template != y.template {
+        changes.push("template:".to_string());
+        changes.extend(line_diff(&x.template, &y.template).into_iter().map(|l| format!("  {}", l)));
+    }
+    for file in &x.context_files {
+        match y.context_files.iter().find(|f| f.path == file.path) {
+            None => changes.push(format!("context file removed: {}", file.path)),
+            Some(other) if other.hash != file.hash => changes.push(format!(
+                "context file changed: {} ({} → {})",
+                file.path, file.hash, other.hash
+            )),
+            Some(_) => {}
+        }
+    }
+    for file in &y.context_files {
+        if !x.context_files.iter().any(|f| f.path == file.path) {
+            changes.push(format!("context file added: {}", file.path));
+        }
+    }
+    changed(&mut changes, "dropped", x.dropped.join(", "), y.dropped.join(", "));
+    changed(&mut changes, "replayed messages", x.replayed_messages.to_string(), y.replayed_messages.to_string());
+    changed(&mut changes, "resumed session", x.resumed.to_string(), y.resumed.to_string());
+    changed(&mut changes, "lang", or_none(x.lang.as_deref()), or_none(y.lang.as_deref()));
+    changed(&mut changes, "corrections", x.corrections.to_string(), y.corrections.to_string());
+    changes
+}
+
+fn changed(changes: &mut Vec<String>, name: &str, old: String, new: String) {
+    if old != new {
+        changes.push(format!("{}: {} → {}", name, old, new));
+    }
+}
+
+/// Lines only in `old` (`- `) and only in `new` (`+ `), in order, from their longest common subsequence
+pub fn line_diff(old: &str, new: &str) -> Vec<String> {
+    let a: Vec<&str> = old.lines().collect();
+    let b: Vec<&str> = new.lines().collect();
+    let mut common = vec![vec![0usize; b.len() + 1]; a.len() + 1];
+    for i in (0..a.len()).rev() {
+        for j in (0..b.len()).rev() {
+            common[i][j] = if a[i] == b[j] {
+                common[i + 1][j + 1] + 1
+            } else {
+                common[i + 1][j].max(common[i][j + 1])
+            };
+        }
+    }
+
+    let (mut i, mut j) = (0, 0);
+    let mut out = Vec::new();
+    while i < a.len() || j < b.len() {
+        if i < a.len() && j < b.len() && a[i] == b[j] {
+            i += 1;
+            j += 1;
+        } else if i < a.len() && (j == b.len() || common[i + 1][j] >= common[i][j + 1]) {
+            out.push(format!("- {}", a[i]));
+            i += 1;
+        } else {
+            out.push(format!("+ {}", b[j]));
+            j += 1;
+        }
+    }
+    out
+}
+
+/// Render entries as JSON Lines, one entry per line
+pub fn to_jsonl(entries: &[HistoryEntry]) -> Result<String> {
+    let mut out = String::new();
+    for entry in entries {
+        out.push_str(&serde_json::to_string(entry)?);
+        out.push('\n');
+    }
+    Ok(out)
+}
+
+/// Render entries as a Markdown document for human review
+pub fn to_markdown(entries: &[HistoryEntry]) -> String {
+    let mut out = String::from("# Ecce Generation History\n");
+
+    for entry in entries {
+        out.push_str(&format!(
+            "\n## {} — {}\n\n",
+            entry.timestamp.format("%Y-%m-%d %H:%M:%S UTC"),
+            entry.agent
+        ));
+        out.push_str(&format!("- File: `{}`\n", entry.file));
+        if let Some(ref task) = entry.task {
+            out.push_str(&format!("- Task: {}\n", task));
+        }
+        if let Some(ref profile) = entry.profile {
+            out.push_str(&format!("- Profile: {}\n", profile));
+        }
+        out.push_str(&format!("- Duration: {}ms\n", entry.duration_ms));
+        if let Some(ref feedback) = entry.feedback {
+            out.push_str(&format!("- Feedback: {}", feedback.vote.emoji()));
+            if let Some(ref note) = feedback.note {
+                out.push_str(&format!(" {}", note));
+            }
+            out.push('\n');
+        }
+
+        out.push_str("\n### Prompt\n\n");
+        out.push_str(&entry.prompt);
+        out.push('\n');
+
+        match (&entry.response, &entry.error) {
+            (_, Some(error)) => {
+                out.push_str("\n### Error\n\n");
+                out.push_str(error);
+                out.push('\n');
+            }
+            (Some(response), None) => {
+                out.push_str("\n### Response\n\n");
+                out.push_str(response);
+                out.push('\n');
+            }
+            (None, None) => {}
+        }
+    }
+
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+    use tempfile::TempDir;
+
+    fn entry(day: u32, error: Option<&str>) -> HistoryEntry {
+        HistoryEntry {
+            timestamp: Utc.with_ymd_and_hms(2025, 1, day, 12, 0, 0).unwrap(),
+            file: "slides.md".to_string(),
+            agent: "writer".to_string(),
+            prompt: "what is rust?".to_string(),
+            response: error.is_none().then(|| "A language".to_string()),
+            error: error.map(|e| e.to_string()),
+            duration_ms: 1200,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_append_and_load_roundtrip() {
+        let temp = TempDir::new().unwrap();
+        let path = temp.path().join("history.jsonl");
+
+        append_to(&path, &entry(1, None)).unwrap();
+        append_to(&path, &entry(2, Some("boom"))).unwrap();
+
+        let entries = load_from(&path).unwrap();
+        assert_eq!(entries.len(), 2);
+        assert!(entries[0].is_success());
+        assert_eq!(entries[1].error.as_deref(), Some("boom"));
+    }
+
+    #[test]
+    fn test_load_skips_malformed_lines() {
+        let temp = TempDir::new().unwrap();
+        let path = temp.path().join("history.jsonl");
+
+        append_to(&path, &entry(1, None)).unwrap();
+        let mut file = OpenOptions::new().append(true).open(&path).unwrap();
+        writeln!(file, "not json").unwrap();
+
+        assert_eq!(load_from(&path).unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_feedback() {
+        let temp = TempDir::new().unwrap();
+        let path = temp.path().join("feedback.jsonl");
+        let feedback = |vote, note: Option<&str>| Feedback {
+            vote,
+            note: note.map(str::to_string),
+            timestamp: Utc::now(),
+        };
+        rate_to(&path, "a1", feedback(Vote::Down, None)).unwrap();
+        rate_to(&path, "a1", feedback(Vote::Up, Some("clear"))).unwrap();
+        rate_to(&path, "zz", feedback(Vote::Down, None)).unwrap();
+
+        let mut entries = vec![HistoryEntry { id: Some("a1".to_string()), ..entry(1, None) }, entry(2, None)];
+        attach_feedback(&mut entries, &path).unwrap();
+        // The latest rating wins
+        assert_eq!(entries[0].feedback.as_ref().map(|f| f.vote), Some(Vote::Up));
+        assert!(entries[1].feedback.is_none());
+        assert!(to_markdown(&entries).contains("- Feedback: 👍 clear\n"));
+
+        assert_eq!(Vote::parse(" + "), Some(Vote::Up));
+        assert_eq!(Vote::parse("Bad"), Some(Vote::Down));
+        assert_eq!(Vote::parse(""), None);
+    }
+
+    #[test]
+    fn test_filter_since() {
+        let entries = vec![entry(1, None), entry(5, None), entry(9, None)];
+        let since = parse_since("2025-01-05").unwrap();
+
+        let filtered = filter_since(entries, Some(since));
+        assert_eq!(filtered.len(), 2);
+        assert!(parse_since("05/01/2025").is_err());
+    }
+
+    #[test]
+    fn test_to_markdown() {
+        let md = to_markdown(&[entry(1, None), entry(2, Some("boom"))]);
+
+        assert!(md.contains("### Prompt\n\nwhat is rust?"));
+        assert!(md.contains("### Response\n\nA language"));
+        assert!(md.contains("### Error\n\nboom"));
+    }
+
+    #[test]
+    fn test_line_diff() {
+        let diff = line_diff("Intro\nBe brief\nUse bullets", "Intro\nBe thorough\nUse bullets\nCite sources");
+        assert_eq!(diff, vec!["- Be brief", "+ Be thorough", "+ Cite sources"]);
+        assert!(line_diff("same", "same").is_empty());
+    }
+
+    #[test]
+    fn test_find_and_diff() {
+        let snapshot = ContextSnapshot {
+            model: Some("sonnet".to_string()),
+            system_prompt_hash: "aaaa".to_string(),
+            template: "Answer briefly".to_string(),
+            context_files: vec![
+                ContextFile { path: "a.md".to_string(), hash: "1111".to_string() },
+                ContextFile { path: "b.md".to_string(), hash: "2222".to_string() },
+            ],
+            ..Default::default()
+        };
+        let first = HistoryEntry {
+            id: Some("abc123".to_string()),
+            context: Some(snapshot.clone()),
+            ..entry(1, None)
+        };
+        let second = HistoryEntry {
+            id: Some("abd456".to_string()),
+            context: Some(ContextSnapshot {
+                model: Some("opus".to_string()),
+                context_files: vec![
+                    ContextFile { path: "a.md".to_string(), hash: "3333".to_string() },
+                    ContextFile { path: "c.md".to_string(), hash: "4444".to_string() },
+                ],
+                ..snapshot
+            }),
+            ..entry(2, None)
+        };
+        let entries = vec![first, second];
+
+        assert_eq!(find(&entries, "abc").unwrap().id.as_deref(), Some("abc123"));
+        assert!(find(&entries, "ab").is_err());
+        assert!(find(&entries, "zzz").is_err());
+
+        assert_eq!(
+            diff(&entries[0], &entries[1]),
+            vec![
+                "model: sonnet → opus",
+                "context file changed: a.md (1111 → 3333)",
+                "context file removed: b.md",
+                "context file added: c.md",
+            ]
+        );
+        assert!(diff(&entries[0], &entries[0]).is_empty());
+    }
+
+}
@@ -1,13 +1,87 @@
 // Library exports for ecce package
 // This allows integration tests and external crates to use ecce modules
+//
+// Pattern detection, document formats, answer fitting, provenance markers,
+// line ending handling, style sampling, and template rendering form a core
+// that builds without the `cli` feature (e.g. for wasm32); everything that
+// touches the filesystem, network, processes, or terminal needs `cli`.
 
-pub mod config;
+pub mod document;
+pub mod fit;
+pub mod line_endings;
 pub mod pattern;
+pub mod provenance;
+pub mod style;
+pub mod template;
+
+#[cfg(feature = "cli")]
+pub mod citation;
+#[cfg(feature = "cli")]
+pub mod config;
+#[cfg(feature = "cli")]
+pub mod context_command;
+#[cfg(feature = "cli")]
+pub mod context_env;
+#[cfg(feature = "cli")]
+pub mod delivery;
+#[cfg(feature = "cli")]
+pub mod environment;
+#[cfg(feature = "cli")]
+pub mod exit;
+#[cfg(feature = "cli")]
+pub mod guard;
+#[cfg(feature = "cli")]
+pub mod pack;
+#[cfg(feature = "cli")]
+pub mod pending;
+#[cfg(feature = "cli")]
+pub mod polish;
+#[cfg(feature = "cli")]
+pub mod redact;
+#[cfg(feature = "cli")]
+pub mod remote;
+#[cfg(feature = "cli")]
+pub mod reuse;
+#[cfg(feature = "cli")]
+pub mod router;
+#[cfg(feature = "cli")]
+pub mod search_index;
+#[cfg(feature = "cli")]
+pub mod session;
+#[cfg(feature = "cli")]
+pub mod side_pass;
+#[cfg(feature = "cli")]
 pub mod watcher;
+#[cfg(feature = "cli")]
 pub mod agent;
+#[cfg(feature = "cli")]
+pub mod capability;
+#[cfg(feature = "cli")]
+pub mod client;
+#[cfg(feature = "cli")]
+pub mod history;
+#[cfg(feature = "cli")]
+pub mod models;
+#[cfg(feature = "cli")]
+pub mod slots;
+#[cfg(feature = "cli")]
+pub mod stats;
+#[cfg(all(unix, any(test, feature = "test-support")))]
+pub mod testing;
+#[cfg(feature = "cli")]
+pub mod translate;
+#[cfg(feature = "cli")]
+pub mod ui;
+#[cfg(feature = "cli")]
 pub mod utils;
 
 // Re-export commonly used types for convenience
-pub use config::{Agent, Config, McpServer, Profile, Task};
-pub use pattern::{EccePattern, PatternDetector, PatternType};
+pub use pattern::{EccePattern, PatternDetector, PatternOptions, PatternType};
+#[cfg(feature = "cli")]
+pub use client::EcceClient;
+#[cfg(feature = "cli")]
+pub use config::{Agent, Config, McpServer, Preset, Profile, ProjectConfig, Task};
+#[cfg(feature = "cli")]
+pub use remote::FileTarget;
+#[cfg(feature = "cli")]
 pub use watcher::FileWatcher;
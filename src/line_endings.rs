@@ -0,0 +1,140 @@
+//! Line ending and byte order mark handling. ecce works on text with `\n`
+//! line endings and no BOM; files keep their own style on disk.
+
+const BOM: char = '\u{feff}';
+
+/// Text as ecce sees it: without a leading BOM and with `\r\n` turned into `\n`
+pub fn normalize(raw: &str) -> String {
+    raw.strip_prefix(BOM).unwrap_or(raw).replace("\r\n", "\n")
+}
+
+/// Whether most of the text's line endings are `\r\n`
+pub fn prefers_crlf(raw: &str) -> bool {
+    let crlf = raw.matches("\r\n").count();
+    crlf > 0 && crlf >= raw.matches('\n').count() - crlf
+}
+
+/// `\n` line endings converted to `\r\n` when `raw` prefers them
+pub fn in_style_of(raw: &str, text: &str) -> String {
+    if prefers_crlf(raw) {
+        text.replace('\n', "\r\n")
+    } else {
+        text.to_string()
+    }
+}
+
+/// Byte offset in `raw` (without its BOM) where the normalized text's byte `offset` starts
+fn raw_offset(raw: &str, offset: usize) -> usize {
+    let bytes = raw.as_bytes();
+    let (mut normalized, mut i) = (0, 0);
+    while normalized < offset && i < bytes.len() {
+        // The `\r` of a `\r\n` has no counterpart in the normalized text
+        if !(bytes[i] == b'\r' && bytes.get(i + 1) == Some(&b'\n')) {
+            normalized += 1;
+        }
+        i += 1;
+    }
+    i
+}
+
+/// The on-disk form of `text`, an edited version of `normalize(raw)`. Unchanged
+/// text at the start and end keeps its exact bytes from `raw`, so a mixed-ending
+/// file stays mixed; the edited middle uses the file's prevailing line ending.
+/// A BOM is kept if `raw` had one.
+pub fn restore(raw: &str, text: &str) -> String {
+    let (bom, body) = match raw.strip_prefix(BOM) {
+        Some(body) => (true, body),
+        None => (false, raw),
+    };
+    if !bom && !body.contains('\r') {
+        return text.to_string();
+    }
+
+    let old = body.replace("\r\n", "\n");
+    let prefix = common_prefix(&old, text);
+    let suffix = common_suffix(&old[prefix..], &text[prefix..]);
+
+    let mut restored = String::with_capacity(text.len() + text.len() / 16 + 3);
+    if bom {
+        restored.push(BOM);
+    }
+    let (start, end) = (raw_offset(body, prefix), raw_offset(body, old.len() - suffix));
+    restored.push_str(&body[..start]);
+    restored.push_str(&in_style_of(body, &text[prefix..text.len() - suffix]));
+    restored.push_str(&body[end..]);
+    restored
+}
+
+/// Length in bytes of the longest common prefix, on a char boundary
+pub(crate) fn common_prefix(a: &str, b: &str) -> usize {
+    a.char_indices()
+        .zip(b.chars())
+        .find(|((_, x), y)| x != y)
+        .map_or(a.len().min(b.len()), |((i, _), _)| i)
+}
+
+/// Length in bytes of the longest common suffix, on a char boundary
+pub(crate) fn common_suffix(a: &str, b: &str) -> usize {
+    a.chars()
+        .rev()
+        .zip(b.chars().rev())
+        .take_while(|(x, y)| x == y)
+        .map(|(x, _)| x.len_utf8())
+        .sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_normalize() {
+        assert_eq!(normalize("\u{feff}a\r\nb\nc\r"), "a\nb\nc\r");
+        assert_eq!(normalize("plain\n"), "plain\n");
+    }
+
+    #[test]
+    fn test_restore_keeps_crlf_and_bom() {
+        let raw = "\u{feff}# Title\r\n\r\necce what? ecce\r\n";
+        let text = normalize(raw).replace("ecce what? ecce", "Answer\nline two");
+        assert_eq!(restore(raw, &text), "\u{feff}# Title\r\n\r\nAnswer\r\nline two\r\n");
+        assert_eq!(restore(raw, &normalize(raw)), raw);
+    }
+
+    #[test]
+    fn test_restore_mixed_endings() {
+        // Untouched lines keep their own endings; the edit uses the majority (CRLF)
+        let raw = "one\r\ntwo\nthree\r\necce q ecce\r\nfour\n";
+        let text = normalize(raw).replace("ecce q ecce", "a\nb");
+        assert_eq!(restore(raw, &text), "one\r\ntwo\nthree\r\na\r\nb\r\nfour\n");
+    }
+
+    #[test]
+    fn test_restore_lf_file_untouched() {
+        assert_eq!(restore("a\nb\n", "a\nc\n"), "a\nc\n");
+        assert_eq!(restore("", "new\n"), "new\n");
+    }
+
+    proptest::proptest! {
+        #[test]
+        fn restore_round_trips(raw in "\u{feff}?(a|é|\r|\n|\r\n){0,30}", edit in "(x|\n){0,5}", at in 0usize..40) {
+            proptest::prop_assert_eq!(restore(&raw, &normalize(&raw)), raw.clone());
+
+            // Text outside an edit keeps its exact bytes
+            let text = normalize(&raw);
+            let at = (0..=at.min(text.len())).rev().find(|&i| text.is_char_boundary(i)).unwrap();
+            let edited = format!("{}{}{}", &text[..at], edit, &text[at..]);
+            // A newline typed right after a lone `\r` forms a `\r\n`, which has no normalized form
+            proptest::prop_assume!(!edited.contains("\r\n"));
+            proptest::prop_assert_eq!(normalize(&restore(&raw, &edited)), edited);
+        }
+    }
+
+    #[test]
+    fn test_restore_edit_at_line_boundary() {
+        let raw = "a\r\nb\r\n";
+        assert_eq!(restore(raw, "a\nX\nb\n"), "a\r\nX\r\nb\r\n");
+        assert_eq!(restore(raw, "a\n"), "a\r\n");
+        assert_eq!(restore(raw, "a\nb\nc\n"), "a\r\nb\r\nc\r\n");
+    }
+}
@@ -1,26 +1,54 @@
-use anyhow::Result;
+use anyhow::{Context, Result};
 use clap::{Parser, Subcommand};
 
 mod commands;
-mod config;
-mod utils;
-mod agent;
-mod watcher;
-mod pattern;
+
+use ecce::{agent, capability, citation, config, delivery, document, environment, exit, fit, guard, history, line_endings, models, pack, pattern, pending, polish, provenance, redact, remote, reuse, router, search_index, session, stats, style, translate, ui, utils, watcher};
 
 use commands::api::{handle_api_command, ApiCommand};
 use commands::agent::{handle_agent_command, AgentCommand};
+use commands::batch::{handle_batch_command, BatchArgs};
+use commands::bench::{handle_bench_command, BenchArgs};
+use commands::chat::{handle_chat_command, ChatArgs};
+use commands::check::{handle_check_command, CheckArgs};
+use commands::exec::{handle_exec_command, ExecArgs};
+use commands::regen::{handle_regen_command, RegenArgs};
+use commands::extract::{handle_extract_command, ExtractArgs};
+use commands::find::{handle_find_command, FindArgs};
+use commands::history::{handle_history_command, HistoryCommand};
+use commands::hooks::{handle_hooks_command, HooksCommand};
 use commands::homo::{handle_homo_command, HomoArgs};
+use commands::claude::{handle_claude_command, ClaudeCommand};
+use commands::config::{handle_config_command, ConfigCommand};
 use commands::mcp::{handle_mcp_command, McpCommand};
+use commands::pack::{handle_pack_command, PackCommand};
+use commands::preset::{handle_preset_command, PresetCommand};
+use commands::report::{handle_report_command, report_incident, Incident, ReportArgs};
+use commands::service::{handle_service_command, ServiceCommand};
+use commands::session::{handle_session_command, SessionCommand};
+use commands::shell::{handle_shell_command, ShellArgs};
+use commands::stats::{handle_stats_command, StatsArgs};
+use commands::target::{handle_target_command, TargetCommand};
 use commands::task::{handle_task_command, TaskCommand};
+use commands::upgrade::{handle_upgrade_command, UpgradeArgs};
+use commands::version::{handle_version_command, VersionArgs};
 use config::Config;
+use exit::Failure;
 
 #[derive(Parser)]
-#[command(name = "ecce")]
+#[command(name = "ecce", version)]
 #[command(about = "Ecce Claude CodE - Behold Claude Code", long_about = None)]
 struct Cli {
     #[command(subcommand)]
     command: Commands,
+
+    /// Plain output: no color, emoji, or box-drawing (also ECCE_PLAIN=1)
+    #[arg(long, global = true)]
+    plain: bool,
+
+    /// Quiet: print only results and errors, no banners or progress (also ECCE_QUIET=1)
+    #[arg(short, long, global = true)]
+    quiet: bool,
 }
 
 #[derive(Subcommand)]
@@ -45,16 +73,115 @@ enum Commands {
         #[command(subcommand)]
         command: McpCommand,
     },
+    /// Share agents, tasks, and MCP templates as a pack file
+    Pack {
+        #[command(subcommand)]
+        command: PackCommand,
+    },
+    /// Read and change settings by key: `ecce config set claude_executable /opt/claude`
+    Config {
+        #[command(subcommand)]
+        command: ConfigCommand,
+    },
+    /// Agent + task presets for `ecce homo --preset`
+    Preset {
+        #[command(subcommand)]
+        command: PresetCommand,
+    },
+    /// Named files and folders for `ecce homo <name>`
+    Target {
+        #[command(subcommand)]
+        command: TargetCommand,
+    },
     /// Watch file and trigger agents on pattern detection
+    #[command(visible_alias = "watch")]
     Homo(HomoArgs),
+    /// Keep `ecce homo <target>` running from login as a systemd or launchd service
+    Service {
+        #[command(subcommand)]
+        command: ServiceCommand,
+    },
+    /// Talk to an agent at the terminal before writing anything into a document
+    Chat(ChatArgs),
+    /// Save and resume `ecce homo` sessions
+    Session {
+        #[command(subcommand)]
+        command: SessionCommand,
+    },
+    /// List unanswered patterns; exits with code 6 if any remain
+    Check(CheckArgs),
+    /// Collect the questions asked across files into a question bank (CSV or JSON)
+    Extract(ExtractArgs),
+    /// Regenerate an answer written with --provenance, replacing it in place
+    Regen(RegenArgs),
+    /// Answer patterns across many files in parallel without watching
+    Batch(BatchArgs),
+    /// Run prompts against several agents and compare latency, tokens, and judged quality
+    Bench(BenchArgs),
+    /// Claude Code settings: model, env, allowed tools, and hooks, with diffs and backups
+    Claude {
+        #[command(subcommand)]
+        command: ClaudeCommand,
+    },
+    /// Claude Code hooks that answer patterns in files Claude edits
+    Hooks {
+        #[command(subcommand)]
+        command: HooksCommand,
+    },
+    /// Recorded generation history
+    History {
+        #[command(subcommand)]
+        command: HistoryCommand,
+    },
+    /// Show aggregate usage metrics from the generation history
+    Stats(StatsArgs),
+    /// Fuzzy-find profiles, agents, tasks, MCP servers, and history, then act on one
+    Find(FindArgs),
+    /// Run a command with a profile's endpoint and key in its environment: `ecce exec -p work -- claude`
+    Exec(ExecArgs),
+    /// Start $SHELL with a profile's endpoint and key exported; exit to get the old environment back
+    Shell(ShellArgs),
+    /// Replace this binary with the latest GitHub release, checksum-verified
+    Upgrade(UpgradeArgs),
+    /// Print the version; with --verbose, a build and environment report for bug reports
+    Version(VersionArgs),
+    /// Write a diagnostic bundle (environment, masked config, recent generations) for a bug report
+    Report(ReportArgs),
 }
 
 #[tokio::main]
-async fn main() -> Result<()> {
+async fn main() {
+    // The default hook prints the panic; the bundle adds what's needed to debug it remotely
+    let default_hook = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |info| {
+        default_hook(info);
+        report_incident(&Incident::panic(info));
+    }));
+
+    if let Err(e) = run().await {
+        eprintln!("Error: {:?}", e);
+        let code = exit::exit_code(&e);
+        // Most errors are the user's to fix; only bugs are worth a bug report
+        if code == exit::Failure::Internal.code() {
+            report_incident(&Incident::error(&e));
+        }
+        std::process::exit(code);
+    }
+}
+
+async fn run() -> Result<()> {
     let cli = Cli::parse();
-    let mut config = Config::load()?;
+    ui::init(cli.plain);
+    ui::set_quiet(cli.quiet);
+    // Reports should work even when the config doesn't load
+    let command = match cli.command {
+        Commands::Version(args) => return handle_version_command(args, Config::load()).await,
+        Commands::Report(args) => return handle_report_command(args).await,
+        command => command,
+    };
+    let mut config = Config::load().context(Failure::Config)?;
 
-    match cli.command {
+    match command {
         Commands::Api { command } => {
             handle_api_command(command, &mut config).await?;
         }
@@ -64,13 +191,101 @@ async fn main() -> Result<()> {
         Commands::Task { command } => {
             handle_task_command(command, &mut config)?;
         }
+        Commands::Claude { command } => {
+            handle_claude_command(command, &config)?;
+        }
         Commands::Mcp { command } => {
-            handle_mcp_command(command, &mut config)?;
+            handle_mcp_command(command, &mut config).await?;
+        }
+        Commands::Pack { command } => {
+            handle_pack_command(command, &mut config).await?;
+        }
+        Commands::Config { command } => {
+            handle_config_command(command, &mut config)?;
+        }
+        Commands::Preset { command } => {
+            handle_preset_command(command, &mut config)?;
+        }
+        Commands::Target { command } => {
+            handle_target_command(command, &mut config)?;
         }
         Commands::Homo(args) => {
             handle_homo_command(args, &config).await?;
         }
+        Commands::Service { command } => {
+            handle_service_command(command, &config)?;
+        }
+        Commands::Chat(args) => {
+            handle_chat_command(args, &config).await?;
+        }
+        Commands::Session { command } => {
+            handle_session_command(command, &config).await?;
+        }
+        Commands::Check(args) => {
+            handle_check_command(args, &config)?;
+        }
+        Commands::Extract(args) => {
+            handle_extract_command(args, &config)?;
+        }
+        Commands::Regen(args) => {
+            handle_regen_command(args, &config).await?;
+        }
+        Commands::Batch(args) => {
+            handle_batch_command(args, &config).await?;
+        }
+        Commands::Bench(args) => {
+            handle_bench_command(args, &config).await?;
+        }
+        Commands::Hooks { command } => {
+            handle_hooks_command(command, &config).await?;
+        }
+        Commands::History { command } => {
+            handle_history_command(command)?;
+        }
+        Commands::Stats(args) => {
+            handle_stats_command(args).await?;
+        }
+        Commands::Find(args) => {
+            handle_find_command(args, &mut config)?;
+        }
+        Commands::Exec(args) => {
+            let code = handle_exec_command(args, &config)?;
+            if code != 0 {
+                std::process::exit(code);
+            }
+        }
+        Commands::Shell(args) => {
+            let code = handle_shell_command(args, &config)?;
+            if code != 0 {
+                std::process::exit(code);
+            }
+        }
+        Commands::Upgrade(args) => {
+            handle_upgrade_command(args).await?;
+        }
+        Commands::Version(_) | Commands::Report(_) => unreachable!("handled before the config is loaded"),
     }
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_watch_is_homo() {
+        // One variant behind both names, so their flags can't drift apart
+        for name in ["homo", "watch"] {
+            let argv = ["ecce", name, "slides.md", "--cite", "--debounce", "300"];
+            match Cli::try_parse_from(argv).unwrap().command {
+                Commands::Homo(args) => {
+                    assert_eq!(args.file_path, Some(std::path::PathBuf::from("slides.md")));
+                    assert!(args.cite);
+                    assert_eq!(args.debounce, Some(300));
+                }
+                _ => panic!("`ecce {}` is not the homo command", name),
+            }
+        }
+    }
+}
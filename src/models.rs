@@ -0,0 +1,276 @@
+use anyhow::{Context, Result};
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::config::{Config, Profile, CLAUDE_CODE_SERVICE};
+
+/// Model names the claude CLI resolves itself, accepted whatever the endpoint lists
+pub const CLAUDE_ALIASES: [&str; 6] = ["default", "sonnet", "opus", "haiku", "opusplan", "sonnet[1m]"];
+
+/// `ecce api models` refetches catalogs older than this
+pub const CACHE_TTL_HOURS: i64 = 24;
+
+/// Models an endpoint offered when it was last asked
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct Catalog {
+    /// Endpoint the list came from; a profile pointed elsewhere needs a new one
+    pub url: String,
+    pub fetched_at: DateTime<Utc>,
+    pub models: Vec<String>,
+}
+
+/// Whether a model name is one the endpoint offers
+#[derive(Debug, PartialEq)]
+pub enum ModelCheck {
+    Known,
+    Unknown { suggestion: Option<String> },
+}
+
+impl Catalog {
+    pub fn is_fresh(&self, now: DateTime<Utc>) -> bool {
+        now - self.fetched_at < Duration::hours(CACHE_TTL_HOURS)
+    }
+
+    pub fn check(&self, model: &str) -> ModelCheck {
+        if CLAUDE_ALIASES.contains(&model) || self.models.iter().any(|m| m == model) {
+            return ModelCheck::Known;
+        }
+        ModelCheck::Unknown {
+            suggestion: suggest(model, &self.models).map(str::to_string),
+        }
+    }
+}
+
+/// Cached catalogs by profile name
+pub type Cache = BTreeMap<String, Catalog>;
+
+/// Get the model cache path (~/.config/ecce/models.json)
+pub fn cache_path() -> Result<PathBuf> {
+    Ok(Config::config_dir()?.join("models.json"))
+}
+
+/// Load cached catalogs from the given path (none if it doesn't exist)
+pub fn load_cache_from(path: &Path) -> Result<Cache> {
+    if !path.exists() {
+        return Ok(Cache::new());
+    }
+    let content = fs::read_to_string(path)
+        .with_context(|| format!("Failed to read {}", path.display()))?;
+    serde_json::from_str(&content).with_context(|| format!("Failed to parse {}", path.display()))
+}
+
+pub fn save_cache_to(path: &Path, cache: &Cache) -> Result<()> {
+    fs::write(path, serde_json::to_string_pretty(cache)?)
+        .with_context(|| format!("Failed to write {}", path.display()))
+}
+
+/// The cached catalog for a profile, if it was fetched from the profile's current URL
+pub fn cached(profile: &Profile) -> Option<Catalog> {
+    let cache = load_cache_from(&cache_path().ok()?).ok()?;
+    cache
+        .get(&profile.name)
+        .filter(|catalog| catalog.url == profile.url)
+        .cloned()
+}
+
+/// Remember a freshly fetched catalog for a profile
+pub fn store(profile: &Profile, models: Vec<String>) -> Result<Catalog> {
+    let path = cache_path()?;
+    let mut cache = load_cache_from(&path)?;
+    let catalog = Catalog {
+        url: profile.url.clone(),
+        fetched_at: Utc::now(),
+        models,
+    };
+    cache.insert(profile.name.clone(), catalog.clone());
+    save_cache_to(&path, &cache)?;
+    Ok(catalog)
+}
+
+/// Model ids from an Anthropic or OpenAI-compatible model list (`{"data": [{"id": ...}]}`)
+pub fn parse_model_list(body: &Value) -> Vec<String> {
+    let mut models: Vec<String> = body
+        .get("data")
+        .and_then(Value::as_array)
+        .into_iter()
+        .flatten()
+        .filter_map(|model| model.get("id").and_then(Value::as_str))
+        .map(str::to_string)
+        .collect();
+    models.sort();
+    models.dedup();
+    models
+}
+
+/// URLs to ask for the model list, most likely first
+fn list_urls(profile: &Profile) -> Vec<String> {
+    let base = profile.url.trim_end_matches('/');
+    if base.ends_with("/v1") {
+        vec![format!("{}/models", base)]
+    } else if profile.service == CLAUDE_CODE_SERVICE {
+        vec![format!("{}/v1/models", base)]
+    } else {
+        vec![format!("{}/v1/models", base), format!("{}/models", base)]
+    }
+}
+
+/// Ask a profile's endpoint which models it offers
+pub async fn fetch(profile: &Profile) -> Result<Vec<String>> {
+    let client = reqwest::Client::builder()
+        .timeout(std::time::Duration::from_secs(15))
+        .build()?;
+
+    let mut last_error = None;
+    for url in list_urls(profile) {
+        let mut request = client.get(&url).bearer_auth(&profile.key);
+        if profile.service == CLAUDE_CODE_SERVICE {
+            request = request
+                .header("x-api-key", &profile.key)
+                .header("anthropic-version", "2023-06-01");
+        }
+        let result = async {
+            let response = request.send().await?.error_for_status()?;
+            let body: Value = response.json().await?;
+            anyhow::Ok(parse_model_list(&body))
+        }
+        .await;
+        match result {
+            Ok(models) if !models.is_empty() => return Ok(models),
+            Ok(_) => last_error = Some(anyhow::anyhow!("{} listed no models", url)),
+            Err(e) => last_error = Some(e.context(format!("GET {}", url))),
+        }
+    }
+    Err(last_error.unwrap_or_else(|| anyhow::anyhow!("No model list URL for {}", profile.url)))
+}
+
+/// The closest known model to a probable typo
+pub fn suggest<'a>(model: &str, known: &'a [String]) -> Option<&'a str> {
+    let model = model.to_lowercase();
+    known
+        .iter()
+        .map(String::as_str)
+        .chain(CLAUDE_ALIASES)
+        .map(|candidate| (edit_distance(&model, &candidate.to_lowercase()), candidate))
+        .filter(|(distance, candidate)| *distance <= 3.max(candidate.len() / 5))
+        .min_by_key(|(distance, _)| *distance)
+        .map(|(_, candidate)| candidate)
+}
+
+/// Levenshtein distance between two strings, by character
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut row: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut diagonal = row[0];
+        row[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let above = row[j + 1];
+            row[j + 1] = if ca == *cb {
+                diagonal
+            } else {
+                1 + diagonal.min(above).min(row[j])
+            };
+            diagonal = above;
+        }
+    }
+    row[b.len()]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn catalog() -> Catalog {
+        Catalog {
+            url: "https://relay.example".to_string(),
+            fetched_at: Utc::now(),
+            models: vec![
+                "claude-opus-4-1-20250805".to_string(),
+                "claude-sonnet-4-20250514".to_string(),
+            ],
+        }
+    }
+
+    #[test]
+    fn test_parse_model_list() {
+        let body = serde_json::json!({
+            "data": [
+                {"id": "claude-sonnet-4-20250514", "type": "model"},
+                {"id": "claude-opus-4-1-20250805", "type": "model"}
+            ],
+            "has_more": false
+        });
+        assert_eq!(
+            parse_model_list(&body),
+            vec!["claude-opus-4-1-20250805", "claude-sonnet-4-20250514"]
+        );
+        assert!(parse_model_list(&serde_json::json!({"error": "nope"})).is_empty());
+    }
+
+    #[test]
+    fn test_check_model() {
+        let catalog = catalog();
+        assert_eq!(catalog.check("claude-sonnet-4-20250514"), ModelCheck::Known);
+        assert_eq!(catalog.check("sonnet"), ModelCheck::Known);
+        assert_eq!(
+            catalog.check("claude-sonet-4-20250514"),
+            ModelCheck::Unknown {
+                suggestion: Some("claude-sonnet-4-20250514".to_string())
+            }
+        );
+        assert_eq!(
+            catalog.check("sonet"),
+            ModelCheck::Unknown {
+                suggestion: Some("sonnet".to_string())
+            }
+        );
+        assert_eq!(catalog.check("gpt-4o"), ModelCheck::Unknown { suggestion: None });
+    }
+
+    #[test]
+    fn test_list_urls() {
+        let profile = |url: &str, service: &str| Profile {
+            name: "p".to_string(),
+            url: url.to_string(),
+            service: service.to_string(),
+            ..Default::default()
+        };
+        assert_eq!(
+            list_urls(&profile("https://api.anthropic.com/", "claude-code")),
+            vec!["https://api.anthropic.com/v1/models"]
+        );
+        assert_eq!(
+            list_urls(&profile("https://api.openai.com/v1", "codex")),
+            vec!["https://api.openai.com/v1/models"]
+        );
+        assert_eq!(list_urls(&profile("http://localhost:8000", "codex")).len(), 2);
+    }
+
+    #[test]
+    fn test_cache_roundtrip() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let path = dir.path().join("models.json");
+        assert!(load_cache_from(&path).unwrap().is_empty());
+
+        let mut cache = Cache::new();
+        cache.insert("relay".to_string(), catalog());
+        save_cache_to(&path, &cache).unwrap();
+        assert_eq!(load_cache_from(&path).unwrap(), cache);
+
+        let mut stale = catalog();
+        stale.fetched_at = Utc::now() - Duration::hours(CACHE_TTL_HOURS + 1);
+        assert!(!stale.is_fresh(Utc::now()));
+        assert!(catalog().is_fresh(Utc::now()));
+    }
+
+    #[test]
+    fn test_edit_distance() {
+        assert_eq!(edit_distance("sonnet", "sonnet"), 0);
+        assert_eq!(edit_distance("sonet", "sonnet"), 1);
+        assert_eq!(edit_distance("kitten", "sitting"), 3);
+    }
+}
@@ -0,0 +1,347 @@
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::path::Path;
+
+use crate::config::{Agent, Config, McpServer, Task};
+
+/// Pack format version written by `ecce pack create`
+pub const PACK_FORMAT: u32 = 1;
+
+/// A shareable bundle of agents, tasks, and MCP server templates
+#[derive(Debug, Serialize, Deserialize, Default)]
+pub struct Pack {
+    pub name: String,
+    #[serde(default = "default_format")]
+    pub format: u32,
+    #[serde(default)]
+    pub agents: Vec<Agent>,
+    #[serde(default)]
+    pub tasks: Vec<Task>,
+    #[serde(default)]
+    pub mcp_servers: Vec<McpServer>,
+    /// MCP server `env` and `headers` entries replaced by placeholders, as
+    /// `server.env.KEY`; not part of the pack file
+    #[serde(skip)]
+    pub placeholders: Vec<String>,
+}
+
+fn default_format() -> u32 {
+    PACK_FORMAT
+}
+
+/// Which config entries to bundle
+#[derive(Debug, Default)]
+pub struct PackSelection {
+    pub agents: Vec<String>,
+    pub tasks: Vec<String>,
+    pub mcp_servers: Vec<String>,
+}
+
+impl Pack {
+    /// Bundle the selected entries. Base tasks of selected tasks come along,
+    /// machine-specific agent settings (env, working_dir) are left out, and MCP
+    /// server env vars and headers, which often hold keys, become placeholders.
+    pub fn from_config(config: &Config, name: &str, selection: &PackSelection) -> Result<Self> {
+        let mut pack = Pack {
+            name: name.to_string(),
+            format: PACK_FORMAT,
+            ..Default::default()
+        };
+
+        // Base agents and tasks come along so the pack works on its own
+        let mut pending: Vec<String> = selection.agents.clone();
+        while let Some(agent_name) = pending.pop() {
+            if pack.agents.iter().any(|a| a.name == agent_name) {
+                continue;
+            }
+            let agent = config
+                .get_agent(&agent_name)
+                .ok_or_else(|| anyhow::anyhow!("Agent '{}' not found", agent_name))?;
+            if let Some(ref base) = agent.extends {
+                pending.push(base.clone());
+            }
+            pack.agents.push(Agent {
+                env: Default::default(),
+                working_dir: None,
+                ..agent.clone()
+            });
+        }
+        pack.agents.sort_by(|a, b| a.name.cmp(&b.name));
+
+        let mut pending: Vec<String> = selection.tasks.clone();
+        while let Some(task_name) = pending.pop() {
+            if pack.tasks.iter().any(|t| t.name == task_name) {
+                continue;
+            }
+            let task = config
+                .get_task(&task_name)
+                .ok_or_else(|| anyhow::anyhow!("Task '{}' not found", task_name))?;
+            if let Some(ref base) = task.extends {
+                pending.push(base.clone());
+            }
+            pack.tasks.push(task.clone());
+        }
+        pack.tasks.sort_by(|a, b| a.name.cmp(&b.name));
+
+        for server_name in &selection.mcp_servers {
+            let server = config
+                .get_mcp_server(server_name)
+                .ok_or_else(|| anyhow::anyhow!("MCP server '{}' not found", server_name))?;
+            let mut server = server.clone();
+            for section in ["env", "headers"] {
+                let Some(values) = server.config.get_mut(section).and_then(|v| v.as_object_mut()) else {
+                    continue;
+                };
+                for (key, value) in values.iter_mut() {
+                    *value = serde_json::Value::String(placeholder(key));
+                    pack.placeholders.push(format!("{}.{}.{}", server.name, section, key));
+                }
+            }
+            pack.mcp_servers.push(server);
+        }
+
+        Ok(pack)
+    }
+
+    /// Check that every `extends` in the pack names an agent or task in the
+    /// pack or in `config`, and that no chain loops back on itself
+    pub fn check_extends(&self, config: &Config) -> Result<()> {
+        let agent_base = |name: &str| {
+            let agent = self.agents.iter().find(|a| a.name == name).or_else(|| config.get_agent(name))?;
+            Some(agent.extends.clone())
+        };
+        for agent in &self.agents {
+            check_chain("Agent", &agent.name, agent_base)?;
+        }
+        let task_base = |name: &str| {
+            let task = self.tasks.iter().find(|t| t.name == name).or_else(|| config.get_task(name))?;
+            Some(task.extends.clone())
+        };
+        for task in &self.tasks {
+            check_chain("Task", &task.name, task_base)?;
+        }
+        Ok(())
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.agents.is_empty() && self.tasks.is_empty() && self.mcp_servers.is_empty()
+    }
+
+    pub fn to_json(&self) -> Result<String> {
+        Ok(serde_json::to_string_pretty(self)?)
+    }
+
+    pub fn from_json(content: &str) -> Result<Self> {
+        let pack: Pack = serde_json::from_str(content).context("Invalid ecce pack")?;
+        if pack.format > PACK_FORMAT {
+            return Err(anyhow::anyhow!(
+                "Pack '{}' uses format {}, but this ecce supports up to {}. Please upgrade ecce.",
+                pack.name,
+                pack.format,
+                PACK_FORMAT
+            ));
+        }
+        Ok(pack)
+    }
+
+    /// Load a pack from a local path or an http(s) URL
+    pub async fn load(source: &str) -> Result<Self> {
+        let content = if source.starts_with("http://") || source.starts_with("https://") {
+            let response = reqwest::get(source)
+                .await
+                .with_context(|| format!("Failed to download pack: {}", source))?
+                .error_for_status()
+                .with_context(|| format!("Failed to download pack: {}", source))?;
+            response.text().await?
+        } else {
+            std::fs::read_to_string(Path::new(source))
+                .with_context(|| format!("Failed to read pack: {}", source))?
+        };
+        Self::from_json(&content)
+    }
+}
+
+/// What a pack holds instead of a secret: the key's name, for the installer to fill in
+fn placeholder(key: &str) -> String {
+    format!("<{}>", key)
+}
+
+/// `env` and `headers` keys of a server still holding a pack placeholder
+pub fn unfilled(server: &McpServer) -> Vec<String> {
+    ["env", "headers"]
+        .iter()
+        .filter_map(|section| Some((section, server.config.get(*section)?.as_object()?)))
+        .flat_map(|(section, values)| {
+            values
+                .iter()
+                .filter(|(key, value)| value.as_str() == Some(placeholder(key).as_str()))
+                .map(move |(key, _)| format!("{}.{}", section, key))
+        })
+        .collect()
+}
+
+/// Follow `extends` from `name`; `base_of` gives an entry's base, or None if
+/// there is no such entry
+fn check_chain(kind: &str, name: &str, base_of: impl Fn(&str) -> Option<Option<String>>) -> Result<()> {
+    let mut seen = HashSet::from([name.to_string()]);
+    let mut current = name.to_string();
+    while let Some(base) = base_of(&current).flatten() {
+        if base_of(&base).is_none() {
+            return Err(anyhow::anyhow!(
+                "{} '{}' extends '{}', which is neither in the pack nor configured",
+                kind,
+                current,
+                base
+            ));
+        }
+        if !seen.insert(base.clone()) {
+            return Err(anyhow::anyhow!("{} '{}' extends itself through '{}'", kind, name, base));
+        }
+        current = base;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config() -> Config {
+        let mut config = Config::default();
+        let mut env = std::collections::HashMap::new();
+        env.insert("API_TOKEN".to_string(), "secret-value-123".to_string());
+        config.agents.insert(
+            "writer".to_string(),
+            Agent {
+                name: "writer".to_string(),
+                system_prompt: "Write".to_string(),
+                env,
+                working_dir: Some("/home/me/talks".to_string()),
+                ..Default::default()
+            },
+        );
+        config.agents.insert(
+            "kids-writer".to_string(),
+            Agent {
+                name: "kids-writer".to_string(),
+                system_prompt: "For kids".to_string(),
+                extends: Some("writer".to_string()),
+                ..Default::default()
+            },
+        );
+        for (name, extends) in [("base", None), ("lecture", Some("base"))] {
+            config.tasks.insert(
+                name.to_string(),
+                Task {
+                    name: name.to_string(),
+                    extends: extends.map(|e: &str| e.to_string()),
+                    ..Default::default()
+                },
+            );
+        }
+        config
+    }
+
+    #[test]
+    fn test_pack_from_config() {
+        let selection = PackSelection {
+            agents: vec!["writer".to_string()],
+            tasks: vec!["lecture".to_string()],
+            ..Default::default()
+        };
+        let pack = Pack::from_config(&config(), "team", &selection).unwrap();
+
+        assert_eq!(pack.agents.len(), 1);
+        assert!(pack.agents[0].env.is_empty());
+        assert!(pack.agents[0].working_dir.is_none());
+        let task_names: Vec<&str> = pack.tasks.iter().map(|t| t.name.as_str()).collect();
+        assert_eq!(task_names, vec!["base", "lecture"]);
+
+        let missing = PackSelection {
+            agents: vec!["nobody".to_string()],
+            ..Default::default()
+        };
+        assert!(Pack::from_config(&config(), "team", &missing).is_err());
+
+        // An extending agent brings its base along
+        let extending = PackSelection {
+            agents: vec!["kids-writer".to_string()],
+            ..Default::default()
+        };
+        let pack = Pack::from_config(&config(), "team", &extending).unwrap();
+        let agent_names: Vec<&str> = pack.agents.iter().map(|a| a.name.as_str()).collect();
+        assert_eq!(agent_names, vec!["kids-writer", "writer"]);
+    }
+
+    #[test]
+    fn test_pack_json_roundtrip() {
+        let selection = PackSelection {
+            tasks: vec!["base".to_string()],
+            ..Default::default()
+        };
+        let pack = Pack::from_config(&config(), "team", &selection).unwrap();
+        let loaded = Pack::from_json(&pack.to_json().unwrap()).unwrap();
+
+        assert_eq!(loaded.name, "team");
+        assert_eq!(loaded.tasks, pack.tasks);
+        assert!(Pack::from_json(r#"{"name": "future", "format": 99}"#).is_err());
+    }
+
+    #[test]
+    fn test_pack_replaces_mcp_secrets() {
+        let mut config = config();
+        config.mcp_servers.insert(
+            "github".to_string(),
+            McpServer {
+                name: "github".to_string(),
+                config: serde_json::json!({
+                    "command": "npx",
+                    "env": {"GITHUB_TOKEN": "ghp_secret"},
+                    "headers": {"Authorization": "Bearer abc"}
+                }),
+            },
+        );
+        let selection = PackSelection {
+            mcp_servers: vec!["github".to_string()],
+            ..Default::default()
+        };
+        let pack = Pack::from_config(&config, "team", &selection).unwrap();
+
+        let json = pack.to_json().unwrap();
+        assert!(!json.contains("ghp_secret") && !json.contains("Bearer abc"));
+        assert_eq!(pack.mcp_servers[0].config["env"]["GITHUB_TOKEN"], "<GITHUB_TOKEN>");
+        assert_eq!(pack.mcp_servers[0].config["command"], "npx");
+        assert_eq!(pack.placeholders, vec!["github.env.GITHUB_TOKEN", "github.headers.Authorization"]);
+        assert!(!json.contains("placeholders"));
+        assert_eq!(unfilled(&pack.mcp_servers[0]), vec!["env.GITHUB_TOKEN", "headers.Authorization"]);
+    }
+
+    #[test]
+    fn test_check_extends() {
+        let agent = |name: &str, extends: Option<&str>| Agent {
+            name: name.to_string(),
+            extends: extends.map(str::to_string),
+            ..Default::default()
+        };
+        let pack = |agents| Pack {
+            name: "team".to_string(),
+            agents,
+            ..Default::default()
+        };
+        let config = config();
+
+        // A base may come from the pack or from the config
+        assert!(pack(vec![agent("a", Some("b")), agent("b", None)]).check_extends(&config).is_ok());
+        assert!(pack(vec![agent("a", Some("writer"))]).check_extends(&config).is_ok());
+
+        let err = pack(vec![agent("a", Some("nobody"))]).check_extends(&config).unwrap_err();
+        assert!(err.to_string().contains("neither in the pack nor configured"));
+        let err = pack(vec![agent("a", Some("b")), agent("b", Some("a"))])
+            .check_extends(&config)
+            .unwrap_err();
+        assert!(err.to_string().contains("extends itself"));
+        // A pack entry can't loop through an installed one either
+        assert!(pack(vec![agent("writer", Some("kids-writer"))]).check_extends(&config).is_err());
+    }
+}
@@ -1,89 +1,532 @@
 use regex::Regex;
-use sha2::{Digest, Sha256};
+use serde::{Deserialize, Serialize};
 use std::collections::HashSet;
+use std::ops::Range;
+use std::sync::OnceLock;
+
+use crate::provenance;
 
 #[derive(Debug, Clone)]
 pub struct EccePattern {
     pub content: String,
+    /// The full matched text, delimiters included
+    pub raw: String,
     pub start_pos: usize,
     pub end_pos: usize,
     pub pattern_type: PatternType,
+    /// Options from the pattern itself, falling back to the file-level directive
+    pub options: PatternOptions,
+    /// Slides (`---` separator lines) before the pattern, so 0 is the first slide
+    pub slide: usize,
+}
+
+impl EccePattern {
+    /// The question with any leading `[key=value]` options removed
+    pub fn question(&self) -> &str {
+        split_inline_options(&self.content).1
+    }
+}
+
+/// Per-pattern settings, written as `key=value` pairs: in brackets at the start
+/// of a pattern (`ecce [lang=ja] question ecce`), after the code block tag
+/// (```` ```ecce lang=ja ````), or file-wide in `<!-- ecce: lang=ja -->`
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct PatternOptions {
+    /// Language code the answer should be written in, e.g. `ja`
+    pub lang: Option<String>,
+    /// Where the pattern goes in the queue: `priority=high|normal|low`
+    pub priority: Option<Priority>,
+    /// Model (or model alias) for this pattern instead of the agent's
+    pub model: Option<String>,
+    /// Stable name for the pattern, used by `DedupStrategy::Marker`
+    pub id: Option<String>,
+}
+
+/// How soon a queued pattern is answered; equal priorities keep document order
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Priority {
+    Low,
+    #[default]
+    Normal,
+    High,
+}
+
+impl Priority {
+    fn parse(value: &str) -> Option<Self> {
+        match value {
+            "high" => Some(Priority::High),
+            "normal" => Some(Priority::Normal),
+            "low" => Some(Priority::Low),
+            _ => None,
+        }
+    }
+}
+
+impl PatternOptions {
+    /// Parse whitespace-separated `key=value` pairs; unknown keys are ignored
+    pub fn parse(text: &str) -> Self {
+        let mut options = Self::default();
+        for pair in text.split_whitespace() {
+            if let Some((key, value)) = pair.split_once('=') {
+                match key {
+                    "lang" if !value.is_empty() => options.lang = Some(value.to_string()),
+                    "priority" => options.priority = Priority::parse(value).or(options.priority),
+                    "model" if !value.is_empty() => options.model = Some(value.to_string()),
+                    "id" if !value.is_empty() => options.id = Some(value.to_string()),
+                    _ => {}
+                }
+            }
+        }
+        options
+    }
+
+    /// Fill unset options from `defaults`
+    pub fn or(self, defaults: &PatternOptions) -> Self {
+        Self {
+            lang: self.lang.or_else(|| defaults.lang.clone()),
+            priority: self.priority.or(defaults.priority),
+            model: self.model.or_else(|| defaults.model.clone()),
+            id: self.id.or_else(|| defaults.id.clone()),
+        }
+    }
 }
 
-#[derive(Debug, Clone, PartialEq)]
+/// Put patterns in the order they should be answered: higher priority first,
+/// document order otherwise
+pub fn queue_order(patterns: &mut [EccePattern]) {
+    patterns.sort_by_key(|p| std::cmp::Reverse(p.options.priority.unwrap_or_default()));
+}
+
+/// Split a leading `[key=value ...]` group off pattern content
+fn split_inline_options(content: &str) -> (Option<&str>, &str) {
+    if let Some(rest) = content.strip_prefix('[') {
+        if let Some((options, question)) = rest.split_once(']') {
+            if options.contains('=') {
+                return (Some(options), question.trim_start());
+            }
+        }
+    }
+    (None, content)
+}
+
+/// Paragraphs scanned on each side of an edit, so a pattern whose keywords sit
+/// on lines around a blank-separated question is still seen whole
+const WINDOW_PARAGRAPHS: usize = 2;
+
+/// Inline and code block patterns hold a single line between their keywords,
+/// so none runs through two non-blank lines that name no keyword. A scan that
+/// starts or ends just past two such lines matches what a full scan finds there.
+fn is_plain_line(line: &str, keywords: &[&str]) -> Option<bool> {
+    (!line.trim().is_empty()).then(|| !keywords.iter().any(|k| line.contains(k)))
+}
+
+/// The nearest point at or before `start` that follows two plain lines
+fn sync_start(text: &str, start: usize, keywords: &[&str]) -> usize {
+    let (mut plain, mut after) = (0, start);
+    let mut pos = start;
+    for line in text[..start].split_inclusive('\n').rev() {
+        match is_plain_line(line, keywords) {
+            Some(true) => {
+                plain += 1;
+                if plain == 1 {
+                    after = pos;
+                } else {
+                    return after;
+                }
+            }
+            Some(false) => plain = 0,
+            None => {}
+        }
+        pos -= line.len();
+    }
+    0
+}
+
+/// The nearest point at or after `end` that follows two plain lines
+fn sync_end(text: &str, end: usize, keywords: &[&str]) -> usize {
+    let mut plain = 0;
+    let mut pos = end;
+    for line in text[end..].split_inclusive('\n') {
+        pos += line.len();
+        match is_plain_line(line, keywords) {
+            Some(true) => {
+                plain += 1;
+                if plain == 2 {
+                    return pos;
+                }
+            }
+            Some(false) => plain = 0,
+            None => {}
+        }
+    }
+    text.len()
+}
+
+/// The part of `text` to rescan after the bytes in `changed` were edited
+fn scan_window(text: &str, changed: Range<usize>, keywords: &[&str]) -> Range<usize> {
+    let mut start = changed.start.min(text.len());
+    let mut end = changed.end.clamp(start, text.len());
+
+    for _ in 0..=WINDOW_PARAGRAPHS {
+        let head = text[..start].trim_end();
+        start = head.rfind("\n\n").map_or(0, |i| i + 2);
+
+        let tail = &text[end..];
+        end += tail.len() - tail.trim_start().len();
+        end = text[end..].find("\n\n").map_or(text.len(), |i| end + i);
+    }
+
+    // Patterns that run across blank lines could start before the window or
+    // end after it; widen each side to a point none can cross
+    start = sync_start(text, start, keywords);
+    end = sync_end(text, end, keywords);
+
+    // Comments may span paragraphs; take in the whole of any the window cuts through
+    loop {
+        let window = start..end;
+        let before = &text[..start];
+        if let Some(open) = before.rfind("<!--") {
+            if !before[open..].contains("-->") {
+                start = sync_start(text, open, keywords);
+            }
+        }
+        let inside = &text[start..end];
+        if let Some(open) = inside.rfind("<!--").map(|i| start + i) {
+            if !text[open..end].contains("-->") {
+                let close = text[open..].find("-->").map_or(text.len(), |i| open + i + 3);
+                end = sync_end(text, close, keywords);
+            }
+        }
+
+        if (start..end) == window {
+            return window;
+        }
+    }
+}
+
+/// An unchecked task list item that isn't nested, so lists inside answers
+/// (indented beneath their item) are never taken for questions
+fn item_re() -> &'static Regex {
+    static RE: OnceLock<Regex> = OnceLock::new();
+    RE.get_or_init(|| Regex::new(r"(?m)^[-*+] \[ \][ \t]+([^\r\n]*\S)").expect("valid regex"))
+}
+
+/// A `---` line between slides
+fn slide_separator_re() -> &'static Regex {
+    static RE: OnceLock<Regex> = OnceLock::new();
+    RE.get_or_init(|| Regex::new(r"(?m)^---[ \t]*\r?$").expect("valid regex"))
+}
+
+/// A `<!-- ecce: key=value -->` directive
+fn directive_re() -> &'static Regex {
+    static RE: OnceLock<Regex> = OnceLock::new();
+    RE.get_or_init(|| Regex::new(r"<!--\s*ecce:\s*(.*?)\s*-->").expect("valid regex"))
+}
+
+/// File-wide defaults from a `<!-- ecce: key=value -->` directive
+fn file_options(text: &str) -> PatternOptions {
+    directive_re()
+        .captures(text)
+        .map(|cap| PatternOptions {
+            // An id names one pattern, so it can't be a file-wide default
+            id: None,
+            ..PatternOptions::parse(&cap[1])
+        })
+        .unwrap_or_default()
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
 pub enum PatternType {
     Inline,    // ecce ... ecce
     CodeBlock, // ```ecce ... ```
+    Comment,   // <!-- ecce ... -->
+    Item,      // - [ ] ... (question queues only)
+}
+
+impl std::str::FromStr for PatternType {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> anyhow::Result<Self> {
+        match s {
+            "inline" => Ok(PatternType::Inline),
+            "block" | "codeblock" => Ok(PatternType::CodeBlock),
+            "comment" => Ok(PatternType::Comment),
+            "item" => Ok(PatternType::Item),
+            _ => Err(anyhow::anyhow!(
+                "Unknown pattern type '{}'. Expected inline, block, comment, or item",
+                s
+            )),
+        }
+    }
+}
+
+/// How the watcher tells that a pattern was already answered
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DedupStrategy {
+    /// Same question text anywhere in the file; unlike `Location`, unaffected
+    /// by `---` lines added above an answered pattern
+    #[default]
+    Content,
+    /// Same question text on the same slide, so a question repeated on another slide is answered again
+    Location,
+    /// Same `id=` option; patterns without an id fall back to `Location`
+    Marker,
+}
+
+impl DedupStrategy {
+    fn key(self, pattern: &EccePattern) -> String {
+        match (self, &pattern.options.id) {
+            (DedupStrategy::Content, _) => format!("content:{}", pattern.content),
+            (DedupStrategy::Marker, Some(id)) => format!("id:{}", id),
+            _ => format!("slide {}:{}", pattern.slide, pattern.content),
+        }
+    }
+}
+
+impl std::str::FromStr for DedupStrategy {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> anyhow::Result<Self> {
+        match s {
+            "content" => Ok(DedupStrategy::Content),
+            "location" => Ok(DedupStrategy::Location),
+            "marker" | "id" => Ok(DedupStrategy::Marker),
+            _ => Err(anyhow::anyhow!(
+                "Unknown dedup strategy '{}'. Expected content, location, or marker",
+                s
+            )),
+        }
+    }
+}
+
+/// Trigger keyword used when none is configured
+pub const DEFAULT_KEYWORD: &str = "ecce";
+
+/// Regexes for one trigger keyword
+struct KeywordRegexes {
+    keyword: String,
+    inline: Regex,
+    codeblock: Regex,
+    comment: Regex,
+}
+
+impl KeywordRegexes {
+    fn new(keyword: &str) -> Self {
+        let k = regex::escape(keyword);
+        Self {
+            keyword: keyword.to_string(),
+            inline: Regex::new(&format!(r"{k}\s+(.*?)\s+{k}")).unwrap(),
+            codeblock: Regex::new(&format!(r"```{k}([ \t][^\r\n]*)?\r?\n(.*?)\r?\n```")).unwrap(),
+            // `<!-- ecce: ... -->` is a directive, not a pattern
+            comment: Regex::new(&format!(r"(?s)<!--\s*{k}\s+(.*?)\s*-->")).unwrap(),
+        }
+    }
 }
 
 pub struct PatternDetector {
-    processed_hashes: HashSet<String>,
+    /// Keys (see `DedupStrategy`) of patterns already answered
+    processed: HashSet<String>,
+    dedup: DedupStrategy,
+    /// Pattern types to detect; empty means all
+    only: Vec<PatternType>,
+    keywords: Vec<KeywordRegexes>,
+}
+
+impl Default for PatternDetector {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl PatternDetector {
     pub fn new() -> Self {
         Self {
-            processed_hashes: HashSet::new(),
+            processed: HashSet::new(),
+            dedup: DedupStrategy::default(),
+            only: Vec::new(),
+            keywords: vec![KeywordRegexes::new(DEFAULT_KEYWORD)],
+        }
+    }
+
+    /// Trigger on these keywords instead of `ecce`; an empty list keeps the default
+    pub fn set_keywords(&mut self, keywords: &[String]) -> anyhow::Result<()> {
+        if let Some(bad) = keywords.iter().find(|k| k.is_empty() || k.contains(char::is_whitespace)) {
+            return Err(anyhow::anyhow!(
+                "Invalid pattern keyword '{}': keywords must be non-empty and contain no whitespace",
+                bad
+            ));
         }
+        self.keywords = if keywords.is_empty() {
+            vec![KeywordRegexes::new(DEFAULT_KEYWORD)]
+        } else {
+            keywords.iter().map(|k| KeywordRegexes::new(k)).collect()
+        };
+        Ok(())
+    }
+
+    /// Detect only the given pattern types (all of them when empty)
+    pub fn set_pattern_types(&mut self, types: Vec<PatternType>) {
+        self.only = types;
+    }
+
+    /// Task list items are questions only where asked for, as any checklist has them
+    fn wants(&self, pattern_type: PatternType) -> bool {
+        self.only.contains(&pattern_type) || (self.only.is_empty() && pattern_type != PatternType::Item)
     }
 
-    /// Compute hash of pattern content to track what's been processed
-    fn hash_content(content: &str) -> String {
-        let mut hasher = Sha256::new();
-        hasher.update(content.as_bytes());
-        format!("{:x}", hasher.finalize())
+    /// How answered patterns are recognized; set before marking any
+    pub fn set_dedup(&mut self, dedup: DedupStrategy) {
+        self.dedup = dedup;
     }
 
     /// Check if a pattern has been processed
-    pub fn is_processed(&self, content: &str) -> bool {
-        let hash = Self::hash_content(content);
-        self.processed_hashes.contains(&hash)
+    pub fn is_processed(&self, pattern: &EccePattern) -> bool {
+        self.processed.contains(&self.dedup.key(pattern))
     }
 
     /// Mark a pattern as processed
-    pub fn mark_processed(&mut self, content: &str) {
-        let hash = Self::hash_content(content);
-        self.processed_hashes.insert(hash);
+    pub fn mark_processed(&mut self, pattern: &EccePattern) {
+        self.processed.insert(self.dedup.key(pattern));
+    }
+
+    /// Keys of the patterns marked processed, sorted so saved state is stable
+    pub fn processed_keys(&self) -> Vec<String> {
+        let mut keys: Vec<String> = self.processed.iter().cloned().collect();
+        keys.sort();
+        keys
+    }
+
+    /// Treat patterns with these keys (from `processed_keys`) as already answered
+    pub fn restore_processed(&mut self, keys: impl IntoIterator<Item = String>) {
+        self.processed.extend(keys);
     }
 
     /// Detect all ecce patterns in the given text
     pub fn detect_patterns(&self, text: &str) -> Vec<EccePattern> {
+        self.detect_in(text, 0..text.len())
+    }
+
+    /// Detect patterns around the bytes of `text` in `changed`, for rescanning only
+    /// what an edit touched. The scan covers whole paragraphs around the change
+    /// and any comment it cuts through; file-wide options still apply.
+    pub fn detect_patterns_near(&self, text: &str, changed: Range<usize>) -> Vec<EccePattern> {
+        let keywords: Vec<&str> = self.keywords.iter().map(|k| k.keyword.as_str()).collect();
+        self.detect_in(text, scan_window(text, changed, &keywords))
+    }
+
+    fn detect_in(&self, text: &str, window: Range<usize>) -> Vec<EccePattern> {
         let mut patterns = Vec::new();
+        let offset = window.start;
+        let haystack = &text[window];
 
-        // Detect inline patterns: ecce ... ecce
-        let inline_re = Regex::new(r"ecce\s+(.*?)\s+ecce").unwrap();
-        for cap in inline_re.captures_iter(text) {
-            let full_match = cap.get(0).unwrap();
-            let content = cap.get(1).unwrap().as_str().to_string();
+        for regexes in &self.keywords {
+            // Detect inline patterns: ecce ... ecce
+            for cap in regexes.inline.captures_iter(haystack) {
+                let full_match = cap.get(0).unwrap();
+                let content = cap.get(1).unwrap().as_str().to_string();
 
-            if !self.is_processed(&content) {
+                let (inline, _) = split_inline_options(&content);
+                let options = PatternOptions::parse(inline.unwrap_or(""));
                 patterns.push(EccePattern {
                     content,
-                    start_pos: full_match.start(),
-                    end_pos: full_match.end(),
+                    raw: full_match.as_str().to_string(),
+                    start_pos: offset + full_match.start(),
+                    end_pos: offset + full_match.end(),
                     pattern_type: PatternType::Inline,
+                    options,
+                    slide: 0,
                 });
             }
-        }
 
-        // Detect code block patterns: ```ecce ... ```
-        let codeblock_re = Regex::new(r"```ecce\s*\n(.*?)\n```").unwrap();
-        for cap in codeblock_re.captures_iter(text) {
-            let full_match = cap.get(0).unwrap();
-            let content = cap.get(1).unwrap().as_str().to_string();
+            // Detect code block patterns: ```ecce [options]\n...\n```
+            for cap in regexes.codeblock.captures_iter(haystack) {
+                let full_match = cap.get(0).unwrap();
+                let content = cap.get(2).unwrap().as_str().to_string();
 
-            if !self.is_processed(&content) {
+                let (inline, _) = split_inline_options(&content);
+                let options = PatternOptions::parse(inline.unwrap_or(""))
+                    .or(&PatternOptions::parse(cap.get(1).map_or("", |m| m.as_str())));
                 patterns.push(EccePattern {
                     content,
-                    start_pos: full_match.start(),
-                    end_pos: full_match.end(),
+                    raw: full_match.as_str().to_string(),
+                    start_pos: offset + full_match.start(),
+                    end_pos: offset + full_match.end(),
                     pattern_type: PatternType::CodeBlock,
+                    options,
+                    slide: 0,
+                });
+            }
+
+            // Detect comment patterns: <!-- ecce ... -->
+            for cap in regexes.comment.captures_iter(haystack) {
+                let full_match = cap.get(0).unwrap();
+                let content = cap.get(1).unwrap().as_str().to_string();
+
+                let (inline, _) = split_inline_options(&content);
+                let options = PatternOptions::parse(inline.unwrap_or(""));
+                patterns.push(EccePattern {
+                    content,
+                    raw: full_match.as_str().to_string(),
+                    start_pos: offset + full_match.start(),
+                    end_pos: offset + full_match.end(),
+                    pattern_type: PatternType::Comment,
+                    options,
+                    slide: 0,
                 });
             }
         }
 
-        // Sort by position
-        patterns.sort_by_key(|p| p.start_pos);
+        // Unchecked top-level task list items: - [ ] ...
+        if self.wants(PatternType::Item) {
+            for cap in item_re().captures_iter(haystack) {
+                let full_match = cap.get(0).unwrap();
+                let content = cap.get(1).unwrap().as_str().to_string();
+
+                let (inline, _) = split_inline_options(&content);
+                let options = PatternOptions::parse(inline.unwrap_or(""));
+                patterns.push(EccePattern {
+                    content,
+                    raw: full_match.as_str().to_string(),
+                    start_pos: offset + full_match.start(),
+                    end_pos: offset + full_match.end(),
+                    pattern_type: PatternType::Item,
+                    options,
+                    slide: 0,
+                });
+            }
+        }
+
+        // Sort by position; where matches overlap (`<!-- ecce q ecce -->`) the outer one wins
+        patterns.sort_by_key(|p| (p.start_pos, std::cmp::Reverse(p.end_pos)));
+        let mut end = 0;
+        patterns.retain(|p| {
+            let keep = p.start_pos >= end;
+            if keep {
+                end = p.end_pos;
+            }
+            keep
+        });
+        patterns.retain(|p| self.wants(p.pattern_type));
+        if patterns.is_empty() {
+            return patterns;
+        }
+
+        let separators: Vec<usize> = slide_separator_re().find_iter(text).map(|m| m.start()).collect();
+        for pattern in &mut patterns {
+            pattern.slide = separators.partition_point(|&at| at < pattern.start_pos);
+        }
+
+        // Never answer patterns that are part of a generated answer
+        let regions = provenance::find_regions(text);
+        patterns.retain(|p| !regions.iter().any(|r| r.range.contains(&p.start_pos)));
+
+        let defaults = file_options(text);
+        for pattern in &mut patterns {
+            pattern.options = std::mem::take(&mut pattern.options).or(&defaults);
+        }
+        patterns.retain(|p| !self.is_processed(p));
         patterns
     }
 
@@ -91,11 +534,40 @@ impl PatternDetector {
     pub fn detect_new_patterns(&self, text: &str) -> Vec<EccePattern> {
         self.detect_patterns(text)
             .into_iter()
-            .filter(|p| !self.is_processed(&p.content))
+            .filter(|p| !self.is_processed(p))
             .collect()
     }
 }
 
+/// Replace the first occurrence of a pattern's text in `content`. `old_text` may be
+/// a whole pattern (or placeholder) or just an inline or code block question;
+/// `None` if it isn't found in any of those forms
+pub fn replace_pattern(content: &str, old_text: &str, new_text: &str) -> Option<String> {
+    // An empty question would match the keywords of two neighboring patterns
+    if old_text.trim().is_empty() {
+        return None;
+    }
+    let candidates = [
+        // Inline pattern: ecce <prompt> ecce
+        format!("ecce {} ecce", old_text),
+        format!("ecce  {}  ecce", old_text),
+        format!("ecce\n{}\necce", old_text),
+        format!("ecce {} ecce", old_text.trim()),
+        format!("ecce  {}  ecce", old_text.trim()),
+        // Direct replacement (a raw pattern or a "generating" placeholder)
+        old_text.to_string(),
+        // Code block pattern
+        format!("```ecce\n{}\n```", old_text),
+        format!("```ecce\n{}\n```", old_text.trim()),
+        format!("```ecce\n  {}\n```", old_text.trim()),
+    ];
+
+    candidates
+        .iter()
+        .find(|candidate| content.contains(candidate.as_str()))
+        .map(|candidate| content.replacen(candidate.as_str(), new_text, 1))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -133,6 +605,189 @@ mod tests {
         assert_eq!(patterns[1].content, "second question?");
     }
 
+    #[test]
+    fn test_inline_options() {
+        let detector = PatternDetector::new();
+        let patterns = detector.detect_patterns("ecce [lang=ja] what is apple? ecce");
+
+        assert_eq!(patterns[0].content, "[lang=ja] what is apple?");
+        assert_eq!(patterns[0].question(), "what is apple?");
+        assert_eq!(patterns[0].options.lang.as_deref(), Some("ja"));
+        assert_eq!(patterns[0].raw, "ecce [lang=ja] what is apple? ecce");
+    }
+
+    #[test]
+    fn test_codeblock_options_and_file_default() {
+        let detector = PatternDetector::new();
+        let text = "<!-- ecce: lang=zh -->\n```ecce lang=ja\nfirst?\n```\necce second? ecce";
+        let patterns = detector.detect_patterns(text);
+
+        assert_eq!(patterns.len(), 2);
+        assert_eq!(patterns[0].question(), "first?");
+        assert_eq!(patterns[0].options.lang.as_deref(), Some("ja"));
+        assert_eq!(patterns[1].options.lang.as_deref(), Some("zh"));
+    }
+
+    #[test]
+    fn test_dedup_strategies() {
+        let text = "ecce What is Rust? ecce\n\n---\n\necce What is Rust? ecce\n";
+        let answered = |dedup: DedupStrategy| {
+            let mut detector = PatternDetector::new();
+            detector.set_dedup(dedup);
+            let patterns = detector.detect_patterns(text);
+            assert_eq!((patterns[0].slide, patterns[1].slide), (0, 1));
+            detector.mark_processed(&patterns[0]);
+            detector.detect_patterns(text).len()
+        };
+
+        // Content alone hides the same question on the next slide
+        assert_eq!(answered(DedupStrategy::Content), 0);
+        assert_eq!(answered(DedupStrategy::Location), 1);
+        assert_eq!(answered(DedupStrategy::Marker), 1);
+
+        // By default, a slide break inserted above an answered pattern doesn't bring it back
+        let mut detector = PatternDetector::new();
+        let patterns = detector.detect_patterns("ecce What is Rust? ecce\n");
+        detector.mark_processed(&patterns[0]);
+        assert!(detector.detect_patterns("# Intro\n\n---\n\necce What is Rust? ecce\n").is_empty());
+    }
+
+    #[test]
+    fn test_marker_dedup() {
+        let mut detector = PatternDetector::new();
+        detector.set_dedup(DedupStrategy::Marker);
+        let patterns = detector.detect_patterns("<!-- ecce: id=all -->\necce [id=intro] What is Rust? ecce");
+        assert_eq!(patterns[0].options.id.as_deref(), Some("intro"));
+        detector.mark_processed(&patterns[0]);
+
+        // Reworded, moved to another slide: still the same pattern
+        assert!(detector.detect_patterns("---\necce [id=intro] What's Rust? ecce").is_empty());
+        assert_eq!(detector.detect_patterns("ecce [id=outro] What is Rust? ecce").len(), 1);
+        assert_eq!("id".parse::<DedupStrategy>().unwrap(), DedupStrategy::Marker);
+    }
+
+    #[test]
+    fn test_crlf_codeblock() {
+        let detector = PatternDetector::new();
+        let text = "# Deck\r\n```ecce lang=ja\r\nwhat is apple?\r\n```\r\necce inline? ecce\r\n";
+        let patterns = detector.detect_patterns(text);
+
+        assert_eq!(patterns.len(), 2);
+        assert_eq!(patterns[0].content, "what is apple?");
+        assert_eq!(patterns[0].options.lang.as_deref(), Some("ja"));
+        assert_eq!(patterns[1].content, "inline?");
+    }
+
+    #[test]
+    fn test_detect_patterns_near() {
+        let detector = PatternDetector::new();
+        let text = "<!-- ecce: lang=ja -->\n\necce far ecce\n\np1\n\np2\n\np3\n\n<!-- ecce spans\n\nparagraphs -->\n\necce here ecce\n";
+        let at = text.find("here").unwrap();
+        let patterns = detector.detect_patterns_near(text, at..at + 4);
+
+        let contents: Vec<&str> = patterns.iter().map(|p| p.content.as_str()).collect();
+        assert_eq!(contents, vec!["spans\n\nparagraphs", "here"]);
+        assert_eq!(patterns[1].options.lang.as_deref(), Some("ja"));
+        assert_eq!(&text[patterns[1].start_pos..patterns[1].end_pos], "ecce here ecce");
+    }
+
+    #[test]
+    fn test_detect_patterns_near_across_blank_lines() {
+        // Inline keywords pair up across blank lines, so a window starting at
+        // the second `ecce` would pair it differently from a full scan
+        let detector = PatternDetector::new();
+        let text = "Intro\n\nmore intro\n\necce\n\nfirst\n\necce\n\necce\n\nsecond\n\necce\n\nOutro\n";
+        let at = text.find("second").unwrap();
+        let near = detector.detect_patterns_near(text, at..at + 6);
+
+        let contents: Vec<&str> = near.iter().map(|p| p.content.as_str()).collect();
+        assert_eq!(contents, vec!["first", "second"]);
+        assert_eq!(sync_start(text, at, &["ecce"]), "Intro\n\nmore intro\n".len());
+        assert_eq!(sync_end(text, at, &["ecce"]), text.len());
+    }
+
+    #[test]
+    fn test_model_option() {
+        let detector = PatternDetector::new();
+        let text = "<!-- ecce: model=fast -->
+ecce [model=smart lang=ja] hard one ecce
+ecce easy one ecce";
+        let patterns = detector.detect_patterns(text);
+
+        assert_eq!(patterns[0].question(), "hard one");
+        assert_eq!(patterns[0].options.model.as_deref(), Some("smart"));
+        assert_eq!(patterns[1].options.model.as_deref(), Some("fast"));
+    }
+
+    #[test]
+    fn test_brackets_without_options_are_kept() {
+        let detector = PatternDetector::new();
+        let patterns = detector.detect_patterns("ecce [1, 2] vs (1, 2)? ecce");
+
+        assert_eq!(patterns[0].question(), "[1, 2] vs (1, 2)?");
+        assert_eq!(patterns[0].options, PatternOptions::default());
+    }
+
+    #[test]
+    fn test_comment_pattern() {
+        let detector = PatternDetector::new();
+        let text = "<!-- ecce: lang=ja -->\n<!-- ecce what is apple? -->\n<!-- ecce q ecce -->";
+        let patterns = detector.detect_patterns(text);
+
+        assert_eq!(patterns.len(), 2);
+        assert_eq!(patterns[0].content, "what is apple?");
+        assert_eq!(patterns[0].pattern_type, PatternType::Comment);
+        assert_eq!(patterns[0].raw, "<!-- ecce what is apple? -->");
+        assert_eq!(patterns[0].options.lang.as_deref(), Some("ja"));
+        assert_eq!(patterns[1].raw, "<!-- ecce q ecce -->");
+    }
+
+    #[test]
+    fn test_pattern_type_filter() {
+        let mut detector = PatternDetector::new();
+        detector.set_pattern_types(vec![PatternType::CodeBlock, PatternType::Comment]);
+        let text = "the ecce word ecce again\n```ecce\nblock?\n```\n<!-- ecce note? -->";
+        let patterns = detector.detect_patterns(text);
+
+        let types: Vec<PatternType> = patterns.iter().map(|p| p.pattern_type).collect();
+        assert_eq!(types, vec![PatternType::CodeBlock, PatternType::Comment]);
+        assert_eq!("block".parse::<PatternType>().unwrap(), PatternType::CodeBlock);
+        assert!("heading".parse::<PatternType>().is_err());
+    }
+
+    #[test]
+    fn test_queue_items() {
+        let text = "# Questions\n\n- [ ] What is a trait?\n- [x] Done already\n* [ ] [lang=ja] Why borrow?\n  - [ ] nested, an answer's list\n";
+        assert!(PatternDetector::new().detect_patterns(text).is_empty());
+
+        let mut detector = PatternDetector::new();
+        detector.set_pattern_types(vec![PatternType::Item]);
+        let patterns = detector.detect_patterns(text);
+        assert_eq!(patterns.len(), 2);
+        assert_eq!(patterns[0].raw, "- [ ] What is a trait?");
+        assert_eq!(patterns[0].question(), "What is a trait?");
+        assert_eq!(patterns[1].question(), "Why borrow?");
+        assert_eq!(patterns[1].options.lang.as_deref(), Some("ja"));
+        assert_eq!(&text[patterns[1].start_pos..patterns[1].end_pos], patterns[1].raw);
+    }
+
+    #[test]
+    fn test_custom_keywords() {
+        let mut detector = PatternDetector::new();
+        detector.set_keywords(&["ai!".to_string(), "ask".to_string()]).unwrap();
+        let text = "Ecce homo, ecce not a prompt ecce.\nai! what is apple? ai!\n```ask\nwhy?\n```";
+        let patterns = detector.detect_patterns(text);
+
+        assert_eq!(patterns.len(), 2);
+        assert_eq!(patterns[0].content, "what is apple?");
+        assert_eq!(patterns[0].raw, "ai! what is apple? ai!");
+        assert_eq!(patterns[1].content, "why?");
+        assert_eq!(patterns[1].pattern_type, PatternType::CodeBlock);
+
+        assert!(detector.set_keywords(&["two words".to_string()]).is_err());
+        assert!(detector.set_keywords(&[String::new()]).is_err());
+    }
+
     #[test]
     fn test_processed_tracking() {
         let mut detector = PatternDetector::new();
@@ -141,9 +796,200 @@ mod tests {
         let patterns = detector.detect_patterns(text);
         assert_eq!(patterns.len(), 1);
 
-        detector.mark_processed(&patterns[0].content);
+        detector.mark_processed(&patterns[0]);
 
         let patterns_again = detector.detect_patterns(text);
         assert_eq!(patterns_again.len(), 0);
     }
+
+    #[test]
+    fn test_restore_processed() {
+        let mut detector = PatternDetector::new();
+        let text = "ecce one? ecce\n\necce two? ecce";
+        detector.mark_processed(&detector.detect_patterns(text)[1]);
+
+        let mut restored = PatternDetector::new();
+        restored.restore_processed(detector.processed_keys());
+        let remaining = restored.detect_patterns(text);
+        assert_eq!(remaining.len(), 1);
+        assert_eq!(remaining[0].question(), "one?");
+    }
+
+    #[test]
+    fn test_patterns_in_generated_answers_ignored() {
+        let answer = "Write `ecce your question ecce` to ask";
+        let wrapped = provenance::Provenance::new(
+            "a1".to_string(),
+            "writer".to_string(),
+            None,
+            "How do I ask?".to_string(),
+            answer,
+        )
+        .wrap(answer);
+        let text = format!("{}\n\necce a real one ecce", wrapped);
+
+        let patterns = PatternDetector::new().detect_patterns(&text);
+        assert_eq!(patterns.len(), 1);
+        assert_eq!(patterns[0].content, "a real one");
+    }
+
+    #[test]
+    fn test_queue_order_by_priority() {
+        let detector = PatternDetector::new();
+        let text = "ecce first ecce\necce [priority=low] second ecce\necce third ecce\necce [priority=high] fourth ecce";
+        let mut patterns = detector.detect_patterns(text);
+        queue_order(&mut patterns);
+
+        let order: Vec<&str> = patterns.iter().map(|p| p.question()).collect();
+        assert_eq!(order, vec!["fourth", "first", "third", "second"]);
+    }
+}
+
+/// Property tests pinning down detection and replacement on generated documents
+#[cfg(test)]
+mod properties {
+    use super::*;
+    use proptest::prelude::*;
+
+    /// A question: words of ASCII and non-ASCII letters joined by single spaces
+    fn question() -> impl Strategy<Value = String> {
+        prop::collection::vec("[a-zA-Z0-9?éü日本語한국😀]{1,6}", 1..5)
+            .prop_map(|words| words.join(" "))
+            .prop_filter("no keyword inside", |q| !q.contains("ecce"))
+    }
+
+    /// Plain prose between patterns
+    fn filler() -> impl Strategy<Value = String> {
+        "[a-zA-Z ,.é日]{0,20}".prop_filter("no keyword inside", |f| !f.contains("ecce"))
+    }
+
+    fn line_ending() -> impl Strategy<Value = &'static str> {
+        prop_oneof![Just("\n"), Just("\r\n")]
+    }
+
+    /// Fragments that tend to form partial, nested, or overlapping patterns
+    fn fragments() -> impl Strategy<Value = String> {
+        let fragment = prop_oneof![
+            Just("ecce"),
+            Just(" "),
+            Just("\n"),
+            Just("\r\n"),
+            Just("```"),
+            Just("```ecce\n"),
+            Just("````markdown\n"),
+            Just("<!--"),
+            Just("-->"),
+            Just("<!-- ecce: lang=ja -->"),
+            Just("[priority=high]"),
+            Just("q?"),
+            Just("日本"),
+            Just("é"),
+            Just("\u{feff}"),
+        ];
+        prop::collection::vec(fragment, 0..40).prop_map(|parts| parts.concat())
+    }
+
+    proptest! {
+        #[test]
+        fn detected_spans_match_the_text(text in fragments()) {
+            let patterns = PatternDetector::new().detect_patterns(&text);
+
+            let mut end = 0;
+            for p in &patterns {
+                prop_assert!(text.is_char_boundary(p.start_pos) && text.is_char_boundary(p.end_pos));
+                prop_assert_eq!(&text[p.start_pos..p.end_pos], p.raw.as_str());
+                prop_assert!(p.start_pos >= end, "patterns are sorted and never overlap");
+                end = p.end_pos;
+            }
+        }
+
+        #[test]
+        fn inline_pattern_found_exactly(
+            before in filler(),
+            after in filler(),
+            q in question(),
+            eol in line_ending(),
+        ) {
+            let text = format!("{before}{eol}ecce {q} ecce{eol}{after}");
+            let patterns = PatternDetector::new().detect_patterns(&text);
+
+            prop_assert_eq!(patterns.len(), 1);
+            prop_assert_eq!(patterns[0].pattern_type, PatternType::Inline);
+            prop_assert_eq!(&patterns[0].content, &q);
+        }
+
+        #[test]
+        fn adjacent_inline_patterns_stay_separate(a in question(), b in question(), gap in filler()) {
+            // Matching is lazy: the closing keyword of the first pattern never
+            // reaches past the opening keyword of the second
+            for text in [format!("ecce {a} ecce ecce {b} ecce"), format!("ecce {a} ecce {gap} ecce {b} ecce")] {
+                let patterns = PatternDetector::new().detect_patterns(&text);
+                let contents: Vec<&str> = patterns.iter().map(|p| p.content.as_str()).collect();
+                prop_assert_eq!(contents, vec![a.as_str(), b.as_str()]);
+            }
+        }
+
+        #[test]
+        fn code_block_wins_over_inline_inside_it(q in question(), fence in prop_oneof![Just(""), Just("markdown"), Just("text")]) {
+            // Inside another fence, the ecce block is still a pattern; an inline
+            // pattern inside the block is part of its question
+            let text = format!("````{fence}\n```ecce\necce {q} ecce\n```\n````\n");
+            let patterns = PatternDetector::new().detect_patterns(&text);
+
+            prop_assert_eq!(patterns.len(), 1);
+            prop_assert_eq!(patterns[0].pattern_type, PatternType::CodeBlock);
+            prop_assert_eq!(patterns[0].content.clone(), format!("ecce {q} ecce"));
+        }
+
+        #[test]
+        fn replacement_round_trips(
+            before in filler(),
+            after in filler(),
+            q in question(),
+            eol in line_ending(),
+        ) {
+            let text = format!("{before}{eol}ecce {q} ecce{eol}{after}");
+            let raw = PatternDetector::new().detect_patterns(&text)[0].raw.clone();
+            let placeholder = "<!-- ecce-generating 1 -->";
+
+            let replaced = replace_pattern(&text, &raw, placeholder).unwrap();
+            let (head, tail) = (format!("{}{}", before, eol), format!("{}{}", eol, after));
+            prop_assert_eq!(replaced.matches(placeholder).count(), 1);
+            prop_assert!(replaced.starts_with(&head));
+            prop_assert!(replaced.ends_with(&tail));
+            prop_assert_eq!(replace_pattern(&replaced, placeholder, &raw).unwrap(), text);
+        }
+    }
+
+    proptest! {
+        #[test]
+        fn rescan_near_an_edit_finds_what_a_full_scan_finds_there(
+            text in fragments(),
+            insert in fragments(),
+            at in 0usize..200,
+        ) {
+            let at = (0..=at.min(text.len())).rev().find(|&i| text.is_char_boundary(i)).unwrap();
+            let edited = format!("{}{}{}", &text[..at], insert, &text[at..]);
+            let changed = at..at + insert.len();
+            let detector = PatternDetector::new();
+
+            let full = detector.detect_patterns(&edited);
+            let near = detector.detect_patterns_near(&edited, changed.clone());
+            for p in &near {
+                prop_assert!(full.iter().any(|f| f.raw == p.raw && f.start_pos == p.start_pos));
+            }
+            for p in full.iter().filter(|p| p.start_pos < changed.end && changed.start < p.end_pos) {
+                prop_assert!(near.iter().any(|n| n.start_pos == p.start_pos), "missed {:?}", p.raw);
+            }
+        }
+    }
+
+    #[test]
+    fn replacement_touches_only_the_first_duplicate() {
+        let text = "ecce same ecce\n\necce same ecce\n";
+        let replaced = replace_pattern(text, "ecce same ecce", "answer").unwrap();
+        assert_eq!(replaced, "answer\n\necce same ecce\n");
+        assert_eq!(replace_pattern(text, "missing", "answer"), None);
+        assert_eq!(replace_pattern(text, "", "answer"), None);
+    }
 }
@@ -0,0 +1,164 @@
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, OnceLock};
+
+use crate::config::Config;
+
+/// Text shown in place of a pattern while it is being answered
+pub const PLACEHOLDER_TEXT: &str = "🤖 Generating response...";
+
+/// A pattern replaced by a placeholder whose answer hasn't been written yet.
+/// Persisted so that a session that dies mid-generation can be undone.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct PendingGeneration {
+    pub id: String,
+    /// The watched file, as shown to the user (a path or `host:path`)
+    pub file: String,
+    /// The pattern text the placeholder replaced
+    pub raw: String,
+    pub started: DateTime<Utc>,
+}
+
+impl PendingGeneration {
+    pub fn new(file: String, raw: String) -> Self {
+        let started = Utc::now();
+        let id = format!(
+            "{:x}{:x}",
+            started.timestamp_nanos_opt().unwrap_or_default(),
+            std::process::id()
+        );
+        Self { id, file, raw, started }
+    }
+
+    /// The placeholder written into the file, tagged with this generation's id
+    pub fn placeholder(&self) -> String {
+        format!("{} <!-- ecce:pending {} -->", PLACEHOLDER_TEXT, self.id)
+    }
+}
+
+/// A placeholder found in file content: its exact text and its id, if tagged
+#[derive(Debug, Clone, PartialEq)]
+pub struct Placeholder {
+    pub text: String,
+    pub id: Option<String>,
+}
+
+/// Every placeholder in `content`, including untagged ones from older versions
+pub fn find_placeholders(content: &str) -> Vec<Placeholder> {
+    static PLACEHOLDER: OnceLock<Regex> = OnceLock::new();
+    let regex = PLACEHOLDER.get_or_init(|| {
+        Regex::new(&format!(
+            r"{}(?: <!-- ecce:pending ([0-9a-f]+) -->)?",
+            regex::escape(PLACEHOLDER_TEXT)
+        ))
+        .expect("valid placeholder regex")
+    });
+
+    regex
+        .captures_iter(content)
+        .map(|caps| Placeholder {
+            text: caps[0].to_string(),
+            id: caps.get(1).map(|id| id.as_str().to_string()),
+        })
+        .collect()
+}
+
+/// Get the pending generations path (~/.config/ecce/pending.json)
+pub fn pending_path() -> Result<PathBuf> {
+    Ok(Config::config_dir()?.join("pending.json"))
+}
+
+/// Load pending generations from the given path (none if it doesn't exist)
+pub fn load_from(path: &Path) -> Result<Vec<PendingGeneration>> {
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+    let content = fs::read_to_string(path)
+        .with_context(|| format!("Failed to read {}", path.display()))?;
+    serde_json::from_str(&content).with_context(|| format!("Failed to parse {}", path.display()))
+}
+
+fn save_to(path: &Path, pending: &[PendingGeneration]) -> Result<()> {
+    if pending.is_empty() {
+        if path.exists() {
+            fs::remove_file(path)?;
+        }
+        return Ok(());
+    }
+    fs::write(path, serde_json::to_string_pretty(pending)?)
+        .with_context(|| format!("Failed to write {}", path.display()))
+}
+
+/// Serializes read-modify-write updates from concurrent generations
+static UPDATE: Mutex<()> = Mutex::new(());
+
+/// Persist a generation that is about to start
+pub fn add_to(path: &Path, generation: &PendingGeneration) -> Result<()> {
+    let _guard = UPDATE.lock().unwrap_or_else(|e| e.into_inner());
+    let mut pending = load_from(path)?;
+    pending.push(generation.clone());
+    save_to(path, &pending)
+}
+
+/// Forget a generation once its placeholder is gone from the file
+pub fn remove_from(path: &Path, id: &str) -> Result<()> {
+    let _guard = UPDATE.lock().unwrap_or_else(|e| e.into_inner());
+    let mut pending = load_from(path)?;
+    pending.retain(|p| p.id != id);
+    save_to(path, &pending)
+}
+
+pub fn add(generation: &PendingGeneration) -> Result<()> {
+    add_to(&pending_path()?, generation)
+}
+
+pub fn remove(id: &str) -> Result<()> {
+    remove_from(&pending_path()?, id)
+}
+
+pub fn load() -> Result<Vec<PendingGeneration>> {
+    load_from(&pending_path()?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_find_placeholders() {
+        let generation = PendingGeneration::new("slides.md".to_string(), "ecce q ecce".to_string());
+        let content = format!(
+            "# Title\n{}\n\n{}\n<!-- ecce:pending -->\n",
+            generation.placeholder(),
+            PLACEHOLDER_TEXT
+        );
+
+        let found = find_placeholders(&content);
+        assert_eq!(found.len(), 2);
+        assert_eq!(found[0].text, generation.placeholder());
+        assert_eq!(found[0].id.as_deref(), Some(generation.id.as_str()));
+        assert_eq!(found[1], Placeholder { text: PLACEHOLDER_TEXT.to_string(), id: None });
+    }
+
+    #[test]
+    fn test_pending_add_and_remove() {
+        let temp = tempfile::TempDir::new().unwrap();
+        let path = temp.path().join("pending.json");
+
+        let first = PendingGeneration::new("a.md".to_string(), "ecce one ecce".to_string());
+        let mut second = PendingGeneration::new("b.md".to_string(), "ecce two ecce".to_string());
+        second.id.push('0');
+        add_to(&path, &first).unwrap();
+        add_to(&path, &second).unwrap();
+        assert_eq!(load_from(&path).unwrap(), vec![first.clone(), second.clone()]);
+
+        remove_from(&path, &first.id).unwrap();
+        assert_eq!(load_from(&path).unwrap(), vec![second.clone()]);
+        remove_from(&path, &second.id).unwrap();
+        assert!(!path.exists());
+    }
+}
@@ -0,0 +1,138 @@
+//! Polish pass: before an answer is written, a proofreading agent fixes its
+//! spelling and grammar without changing what it says
+
+use anyhow::{Context, Result};
+
+use crate::agent::RunOptions;
+use crate::config::{Agent, Config, Polish, Profile};
+use crate::side_pass::SidePass;
+
+/// Model of the built-in proofreader unless the settings name another
+pub const DEFAULT_POLISH_MODEL: &str = "haiku";
+
+const PROOFREADER_SYSTEM_PROMPT: &str = "You are a meticulous proofreader. Fix spelling, grammar, \
+and punctuation and nothing else: keep the wording, meaning, tone, and Markdown structure. \
+Reply with the corrected text only.";
+
+/// A proofread answer shorter or longer than this share of the original is
+/// a rewrite, not a proofread, and is rejected
+const MAX_LENGTH_CHANGE: f64 = 0.5;
+
+/// Proofreads answers with the built-in proofreader or an agent of the user's
+pub struct Polisher {
+    pass: SidePass,
+    agent: Agent,
+}
+
+impl Polisher {
+    pub fn new(config: &Config, settings: &Polish, options: RunOptions) -> Result<Self> {
+        let mut agent = match settings.agent {
+            Some(ref name) => config.resolve_agent(name)?,
+            None => Agent {
+                name: "proofreader".to_string(),
+                system_prompt: PROOFREADER_SYSTEM_PROMPT.to_string(),
+                model: Some(DEFAULT_POLISH_MODEL.to_string()),
+                ..Default::default()
+            },
+        };
+        if settings.model.is_some() {
+            agent.model = settings.model.clone();
+        }
+        Ok(Self {
+            pass: SidePass::new(config, options),
+            agent,
+        })
+    }
+
+    /// Proofread through this profile's endpoint and key (see `ClaudeAgent::use_profile`)
+    pub fn use_profile(&mut self, profile: &Profile) {
+        self.pass.use_profile(profile);
+    }
+
+    /// Name of the proofreading agent
+    pub fn name(&self) -> &str {
+        &self.agent.name
+    }
+
+    /// The answer with its spelling and grammar fixed
+    pub async fn polish(&self, answer: &str) -> Result<String> {
+        let polished = self
+            .pass
+            .ask(&self.agent, &polish_prompt(answer))
+            .await
+            .with_context(|| format!("Failed to proofread the answer with '{}'", self.agent.name))?;
+        check_polished(answer, polished.trim()).map(str::to_string)
+    }
+}
+
+/// The proofreading request for one answer
+pub fn polish_prompt(answer: &str) -> String {
+    format!(
+        "Proofread this text. Fix only spelling, grammar, and punctuation. Leave code blocks, \
+         inline code, links, HTML comments, and [source: ...] tags exactly as they are.\n\n\
+         <document>\n{}\n</document>",
+        answer.trim()
+    )
+}
+
+/// The proofread text, unless it is empty or so different in length that it was rewritten
+fn check_polished<'a>(answer: &str, polished: &'a str) -> Result<&'a str> {
+    if polished.is_empty() {
+        return Err(anyhow::anyhow!("The proofread answer came back empty"));
+    }
+    let original = answer.trim().chars().count().max(1) as f64;
+    let change = (polished.chars().count() as f64 - original).abs() / original;
+    if change > MAX_LENGTH_CHANGE {
+        return Err(anyhow::anyhow!(
+            "The proofread answer is {:.0}% longer or shorter than the original, so it was rewritten rather than proofread",
+            change * 100.0
+        ));
+    }
+    Ok(polished)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_check_polished() {
+        assert_eq!(check_polished("Teh answer.", "The answer.").unwrap(), "The answer.");
+        assert!(check_polished("Teh answer.", "").is_err());
+        assert!(check_polished("Teh answer.", "Here is the corrected text: The answer. Let me know!").is_err());
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_polish() {
+        let claude = crate::testing::FakeClaude::answering("Ownership moves values.").unwrap();
+        let mut config = Config::default();
+        claude.install(&mut config);
+
+        let polisher = Polisher::new(&config, &Polish::default(), RunOptions::default()).unwrap();
+        assert_eq!(polisher.name(), "proofreader");
+        assert_eq!(polisher.polish("Ownership move values.").await.unwrap(), "Ownership moves values.");
+        let call = &claude.calls()[0];
+        assert!(call.stdin.contains("<document>\nOwnership move values.\n</document>"));
+        let model = call.args.iter().position(|a| a == "--model").unwrap();
+        assert_eq!(call.args[model + 1], DEFAULT_POLISH_MODEL);
+
+        // An agent of your own, with a model override
+        let settings = Polish {
+            agent: Some("writer".to_string()),
+            model: Some("sonnet".to_string()),
+        };
+        let polisher = Polisher::new(&config, &settings, RunOptions::default()).unwrap();
+        assert_eq!(polisher.name(), "writer");
+        polisher.polish("Ownership move values.").await.unwrap();
+        let call = &claude.calls()[1];
+        let model = call.args.iter().position(|a| a == "--model").unwrap();
+        assert_eq!(call.args[model + 1], "sonnet");
+
+        let missing = Polish {
+            agent: Some("editor".to_string()),
+            model: None,
+        };
+        assert!(Polisher::new(&config, &missing, RunOptions::default()).is_err());
+    }
+}
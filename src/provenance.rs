@@ -0,0 +1,159 @@
+use chrono::{DateTime, Utc};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::ops::Range;
+use std::sync::OnceLock;
+
+/// Where a generated answer came from, stored in the HTML comment that opens it:
+///
+/// ```text
+/// <!-- ecce:answer {"id":"…","agent":"writer","at":"…","hash":"…","prompt":"…"} -->
+/// …answer…
+/// <!-- /ecce:answer … -->
+/// ```
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct Provenance {
+    pub id: String,
+    pub agent: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub task: Option<String>,
+    pub at: DateTime<Utc>,
+    /// `content_hash` of the answer as written, to tell whether it was edited since
+    pub hash: String,
+    /// The question the answer responds to
+    pub prompt: String,
+}
+
+impl Provenance {
+    pub fn new(id: String, agent: String, task: Option<String>, prompt: String, response: &str) -> Self {
+        Self {
+            id,
+            agent,
+            task,
+            at: Utc::now(),
+            hash: content_hash(response),
+            prompt,
+        }
+    }
+
+    /// The response wrapped in this provenance's markers
+    pub fn wrap(&self, response: &str) -> String {
+        // `>` is escaped so nothing inside the JSON can close the comment
+        let json = serde_json::to_string(self)
+            .expect("provenance serializes")
+            .replace('>', "\\u003e");
+        format!(
+            "<!-- ecce:answer {} -->\n{}\n<!-- /ecce:answer {} -->",
+            json, response, self.id
+        )
+    }
+}
+
+/// A generated region found in file content
+#[derive(Debug, Clone, PartialEq)]
+pub struct Region {
+    pub provenance: Provenance,
+    /// Byte range of the whole region, markers included
+    pub range: Range<usize>,
+    /// Byte range of the answer between the markers
+    pub body: Range<usize>,
+}
+
+impl Region {
+    /// Whether the answer was changed by hand after it was written
+    pub fn is_edited(&self, content: &str) -> bool {
+        content_hash(&content[self.body.clone()]) != self.provenance.hash
+    }
+}
+
+/// Every well-formed generated region in `content`, in file order
+pub fn find_regions(content: &str) -> Vec<Region> {
+    static BEGIN: OnceLock<Regex> = OnceLock::new();
+    let begin = BEGIN.get_or_init(|| {
+        Regex::new(r"<!-- ecce:answer (\{.*?\}) -->\n?").expect("valid provenance regex")
+    });
+
+    let mut regions = Vec::new();
+    let mut from = 0;
+    while let Some(caps) = begin.captures_at(content, from) {
+        let open = caps.get(0).unwrap();
+        from = open.end();
+        let Ok(provenance) = serde_json::from_str::<Provenance>(&caps[1]) else {
+            continue;
+        };
+
+        let close = format!("<!-- /ecce:answer {} -->", provenance.id);
+        let Some(offset) = content[open.end()..].find(&close) else {
+            continue;
+        };
+        let close_start = open.end() + offset;
+        let body_end = if content[..close_start].ends_with('\n') && close_start > open.end() {
+            close_start - 1
+        } else {
+            close_start
+        };
+
+        from = close_start + close.len();
+        regions.push(Region {
+            provenance,
+            range: open.start()..from,
+            body: open.end()..body_end,
+        });
+    }
+
+    regions
+}
+
+/// Short hash of generated text: the first 16 hex digits of its SHA-256
+pub fn content_hash(text: &str) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(text.as_bytes());
+    format!("{:x}", hasher.finalize())[..16].to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn provenance(id: &str, prompt: &str, response: &str) -> Provenance {
+        Provenance::new(id.to_string(), "writer".to_string(), None, prompt.to_string(), response)
+    }
+
+    #[test]
+    fn test_wrap_and_find_regions() {
+        let first = provenance("a1", "What is --> this?", "Answer one");
+        let second = provenance("b2", "Next?", "Line 1\nLine 2");
+        let content = format!(
+            "# Slides\n{}\n\ntext\n{}\n",
+            first.wrap("Answer one"),
+            second.wrap("Line 1\nLine 2")
+        );
+        assert!(!content.contains("What is -->"));
+
+        let regions = find_regions(&content);
+        assert_eq!(regions.len(), 2);
+        assert_eq!(regions[0].provenance, first);
+        assert_eq!(&content[regions[0].body.clone()], "Answer one");
+        assert_eq!(&content[regions[1].body.clone()], "Line 1\nLine 2");
+        assert!(content[regions[0].range.clone()].starts_with("<!-- ecce:answer"));
+        assert!(content[regions[1].range.clone()].ends_with("<!-- /ecce:answer b2 -->"));
+        assert!(!regions[1].is_edited(&content));
+
+        let edited = content.replace("Line 2", "Line two");
+        assert!(find_regions(&edited)[1].is_edited(&edited));
+    }
+
+    #[test]
+    fn test_unclosed_region_ignored() {
+        let wrapped = provenance("c3", "Q", "A").wrap("A");
+        let content = wrapped.replace("<!-- /ecce:answer c3 -->", "");
+        assert!(find_regions(&content).is_empty());
+    }
+
+    #[test]
+    fn test_content_hash_is_stable() {
+        assert_eq!(content_hash(""), "e3b0c44298fc1c14");
+        assert_ne!(content_hash("a"), content_hash("b"));
+    }
+}
@@ -0,0 +1,336 @@
+use anyhow::{Context, Result};
+use std::fmt;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::time::SystemTime;
+
+use crate::line_endings;
+
+/// Remote files are polled no faster than this, since every poll is an ssh round trip
+pub const REMOTE_MIN_INTERVAL_MS: u64 = 1000;
+
+/// A file ecce reads and writes: either on this machine or on a host reached over ssh
+#[derive(Debug, Clone, PartialEq)]
+pub enum FileTarget {
+    Local(PathBuf),
+    Remote(RemoteFile),
+}
+
+/// Modification time and size of a file at one moment
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FileStamp {
+    pub modified: SystemTime,
+    pub len: u64,
+}
+
+/// A file addressed as `[user@]host:/path`, accessed with the system `ssh` client
+#[derive(Debug, Clone, PartialEq)]
+pub struct RemoteFile {
+    pub host: String,
+    pub path: String,
+}
+
+impl FileTarget {
+    /// The file's path, on whichever machine it lives
+    pub fn path(&self) -> &Path {
+        match self {
+            FileTarget::Local(path) => path,
+            FileTarget::Remote(remote) => Path::new(&remote.path),
+        }
+    }
+
+    pub fn is_remote(&self) -> bool {
+        matches!(self, FileTarget::Remote(_))
+    }
+
+    /// Modification time and size of a local file, to tell cheaply whether it changed;
+    /// `None` for remote files or when the metadata can't be read
+    pub fn stamp(&self) -> Option<FileStamp> {
+        match self {
+            FileTarget::Local(path) => {
+                let metadata = std::fs::metadata(path).ok()?;
+                Some(FileStamp {
+                    modified: metadata.modified().ok()?,
+                    len: metadata.len(),
+                })
+            }
+            FileTarget::Remote(_) => None,
+        }
+    }
+
+    /// The file's text with `\n` line endings and no byte order mark
+    pub fn read_to_string(&self) -> Result<String> {
+        Ok(line_endings::normalize(&self.read_raw()?))
+    }
+
+    /// The file's text exactly as stored, to pass back to `write`
+    pub fn read_raw(&self) -> Result<String> {
+        match self {
+            FileTarget::Local(path) => std::fs::read_to_string(path)
+                .with_context(|| format!("Failed to read {}", path.display())),
+            FileTarget::Remote(remote) => remote.read_to_string(),
+        }
+    }
+
+    /// Replace the file's text `raw`, as `read_raw` returned it, with `content`,
+    /// keeping its line endings and byte order mark. Taking the text already read
+    /// saves a second read, which for a remote file is another ssh round trip.
+    pub fn write(&self, raw: &str, content: &str) -> Result<()> {
+        let content = line_endings::restore(raw, content);
+        match self {
+            FileTarget::Local(path) => std::fs::write(path, content)
+                .with_context(|| format!("Failed to write {}", path.display())),
+            FileTarget::Remote(remote) => remote.write(&content),
+        }
+    }
+
+    /// Append to the file, creating it if needed; new lines follow the file's line endings
+    pub fn append(&self, content: &str) -> Result<()> {
+        let content = match self.read_raw() {
+            Ok(raw) => line_endings::in_style_of(&raw, content),
+            Err(_) => content.to_string(),
+        };
+        let content = content.as_str();
+        match self {
+            FileTarget::Local(path) => {
+                let mut file = std::fs::OpenOptions::new()
+                    .create(true)
+                    .append(true)
+                    .open(path)
+                    .with_context(|| format!("Failed to open {}", path.display()))?;
+                file.write_all(content.as_bytes())
+                    .with_context(|| format!("Failed to write {}", path.display()))
+            }
+            FileTarget::Remote(remote) => remote.append(content),
+        }
+    }
+
+    /// A file next to this one whose name replaces the extension with `suffix`,
+    /// e.g. `slides.md` -> `slides.ecce-answers.md`
+    pub fn sibling_with_suffix(&self, suffix: &str) -> FileTarget {
+        match self {
+            FileTarget::Local(path) => {
+                let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+                FileTarget::Local(path.with_file_name(format!("{}{}", stem, suffix)))
+            }
+            FileTarget::Remote(remote) => {
+                let (dir, file) = match remote.path.rsplit_once('/') {
+                    Some((dir, file)) => (format!("{}/", dir), file),
+                    None => (String::new(), remote.path.as_str()),
+                };
+                let stem = file.rsplit_once('.').map_or(file, |(stem, _)| stem);
+                FileTarget::Remote(remote.with_path(format!("{}{}{}", dir, stem, suffix)))
+            }
+        }
+    }
+}
+
+impl From<PathBuf> for FileTarget {
+    fn from(path: PathBuf) -> Self {
+        FileTarget::Local(path)
+    }
+}
+
+impl From<&Path> for FileTarget {
+    fn from(path: &Path) -> Self {
+        FileTarget::Local(path.to_path_buf())
+    }
+}
+
+impl fmt::Display for FileTarget {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FileTarget::Local(path) => write!(f, "{}", path.display()),
+            FileTarget::Remote(remote) => write!(f, "{}", remote),
+        }
+    }
+}
+
+impl RemoteFile {
+    /// Parse `[user@]host:/path`. Single-letter hosts are rejected so Windows
+    /// drive paths like `C:/slides.md` stay local.
+    pub fn parse(spec: &str) -> Option<Self> {
+        let (host, path) = spec.split_once(':')?;
+        if host.len() < 2 || host.contains('/') || host.contains('\\') || path.is_empty() {
+            return None;
+        }
+        Some(Self {
+            host: host.to_string(),
+            path: path.to_string(),
+        })
+    }
+
+    /// The same host with a different path
+    pub fn with_path(&self, path: String) -> Self {
+        Self {
+            host: self.host.clone(),
+            path,
+        }
+    }
+
+    pub fn read_to_string(&self) -> Result<String> {
+        let output = self
+            .ssh(&format!("cat -- {}", shell_quote(&self.path)))
+            .output()
+            .context("Failed to run ssh")?;
+
+        if !output.status.success() {
+            return Err(anyhow::anyhow!(
+                "Failed to read {}: {}",
+                self,
+                String::from_utf8_lossy(&output.stderr).trim()
+            ));
+        }
+
+        String::from_utf8(output.stdout).with_context(|| format!("{} is not valid UTF-8", self))
+    }
+
+    pub fn write(&self, content: &str) -> Result<()> {
+        self.send(">", content)
+    }
+
+    pub fn append(&self, content: &str) -> Result<()> {
+        self.send(">>", content)
+    }
+
+    /// Pipe content into the file with the given shell redirection
+    fn send(&self, redirect: &str, content: &str) -> Result<()> {
+        let mut child = self
+            .ssh(&format!("cat {} {}", redirect, shell_quote(&self.path)))
+            .stdin(Stdio::piped())
+            .spawn()
+            .context("Failed to run ssh")?;
+
+        child
+            .stdin
+            .take()
+            .expect("stdin is piped")
+            .write_all(content.as_bytes())
+            .with_context(|| format!("Failed to send content to {}", self))?;
+
+        let output = child.wait_with_output()?;
+        if !output.status.success() {
+            return Err(anyhow::anyhow!(
+                "Failed to write {}: {}",
+                self,
+                String::from_utf8_lossy(&output.stderr).trim()
+            ));
+        }
+        Ok(())
+    }
+
+    /// Run `test <flag> path` on the host, e.g. `-d` for "is a directory"
+    pub fn test(&self, flag: &str) -> Result<bool> {
+        let status = self
+            .ssh(&format!("test {} {}", flag, shell_quote(&self.path)))
+            .stderr(Stdio::null())
+            .status()
+            .context("Failed to run ssh")?;
+        Ok(status.success())
+    }
+
+    /// An ssh command for the host that reuses one multiplexed connection, so
+    /// frequent polls don't each pay for a new handshake
+    fn ssh(&self, remote_command: &str) -> Command {
+        let control_path = std::env::temp_dir().join("ecce-ssh-%C");
+        let mut cmd = Command::new("ssh");
+        cmd.arg("-o")
+            .arg("BatchMode=yes")
+            .arg("-o")
+            .arg("ControlMaster=auto")
+            .arg("-o")
+            .arg(format!("ControlPath={}", control_path.display()))
+            .arg("-o")
+            .arg("ControlPersist=60")
+            .arg(&self.host)
+            .arg("--")
+            .arg(remote_command)
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped());
+        cmd
+    }
+}
+
+impl fmt::Display for RemoteFile {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}", self.host, self.path)
+    }
+}
+
+/// Quote a string for a POSIX shell (and fish) as a single word
+pub fn shell_quote(s: &str) -> String {
+    format!("'{}'", s.replace('\'', r"'\''"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_parse_remote_file() {
+        let remote = RemoteFile::parse("me@studio:/talks/slides.md").unwrap();
+        assert_eq!(remote.host, "me@studio");
+        assert_eq!(remote.path, "/talks/slides.md");
+        assert_eq!(remote.to_string(), "me@studio:/talks/slides.md");
+
+        assert!(RemoteFile::parse("studio:talks").is_some());
+        assert!(RemoteFile::parse("slides.md").is_none());
+        assert!(RemoteFile::parse("C:/talks/slides.md").is_none());
+        assert!(RemoteFile::parse("./a:b.md").is_none());
+        assert!(RemoteFile::parse("host:").is_none());
+    }
+
+    #[test]
+    fn test_shell_quote() {
+        assert_eq!(shell_quote("/a b/slides.md"), "'/a b/slides.md'");
+        assert_eq!(shell_quote("it's.md"), r"'it'\''s.md'");
+        assert_eq!(shell_quote("(ecce:work) "), "'(ecce:work) '");
+    }
+
+    #[test]
+    fn test_local_target_roundtrip() {
+        let temp = TempDir::new().unwrap();
+        let target = FileTarget::from(temp.path().join("slides.md"));
+
+        target.write("", "ecce hi ecce").unwrap();
+        target.append("\nmore").unwrap();
+        assert_eq!(target.read_to_string().unwrap(), "ecce hi ecce\nmore");
+        assert!(!target.is_remote());
+    }
+
+    #[test]
+    fn test_local_target_keeps_crlf_and_bom() {
+        let temp = TempDir::new().unwrap();
+        let path = temp.path().join("slides.md");
+        std::fs::write(&path, "\u{feff}# Deck\r\n\r\necce q ecce\r\n").unwrap();
+        let target = FileTarget::from(path.clone());
+
+        let raw = target.read_raw().unwrap();
+        let content = line_endings::normalize(&raw);
+        assert_eq!(content, "# Deck\n\necce q ecce\n");
+        target.write(&raw, &content.replace("ecce q ecce", "Answer\nmore")).unwrap();
+        target.append("tail\n").unwrap();
+
+        assert_eq!(
+            std::fs::read_to_string(&path).unwrap(),
+            "\u{feff}# Deck\r\n\r\nAnswer\r\nmore\r\ntail\r\n"
+        );
+    }
+
+    #[test]
+    fn test_sibling_with_suffix() {
+        let local = FileTarget::from(PathBuf::from("/talks/slides.md"));
+        assert_eq!(
+            local.sibling_with_suffix(".ecce-answers.md"),
+            FileTarget::from(PathBuf::from("/talks/slides.ecce-answers.md"))
+        );
+
+        let remote = FileTarget::Remote(RemoteFile::parse("me@studio:/talks/slides.md").unwrap());
+        assert_eq!(
+            remote.sibling_with_suffix(".ecce-answers.md").to_string(),
+            "me@studio:/talks/slides.ecce-answers.md"
+        );
+    }
+}
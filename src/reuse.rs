@@ -0,0 +1,123 @@
+//! Answer reuse: finding a nearly identical question already answered in the
+//! same file, from the history log, so its answer can stand in for a new generation
+
+use crate::history::HistoryEntry;
+use std::collections::HashSet;
+
+/// Least similarity (see `similarity`) for a question to count as asked before
+pub const THRESHOLD: f64 = 0.85;
+
+/// An earlier answer to a similar question
+#[derive(Debug, Clone, Copy)]
+pub struct Earlier<'a> {
+    pub entry: &'a HistoryEntry,
+    /// `similarity` of the two questions
+    pub score: f64,
+}
+
+impl Earlier<'_> {
+    pub fn question(&self) -> &str {
+        &self.entry.prompt
+    }
+
+    pub fn answer(&self) -> &str {
+        self.entry.response.as_deref().unwrap_or_default()
+    }
+}
+
+/// The most similar question answered for `file`, if any reaches `threshold`;
+/// the latest answer wins a tie
+pub fn find<'a>(entries: &'a [HistoryEntry], file: &str, question: &str, threshold: f64) -> Option<Earlier<'a>> {
+    let question = trigrams(question);
+    entries
+        .iter()
+        .filter(|e| e.file == file && e.is_success() && e.response.as_deref().is_some_and(|r| !r.trim().is_empty()))
+        .map(|entry| Earlier {
+            entry,
+            score: dice(&question, &trigrams(&entry.prompt)),
+        })
+        .filter(|earlier| earlier.score >= threshold)
+        .max_by(|a, b| a.score.total_cmp(&b.score).then(a.entry.timestamp.cmp(&b.entry.timestamp)))
+}
+
+/// How alike two questions are, from 0 to 1: the overlap of their character
+/// trigrams, ignoring case, punctuation, and spacing
+pub fn similarity(a: &str, b: &str) -> f64 {
+    dice(&trigrams(a), &trigrams(b))
+}
+
+/// 1-based line in `content` where `answer` starts, if it is still there
+pub fn locate(content: &str, answer: &str) -> Option<usize> {
+    let first = answer.lines().map(str::trim).find(|line| !line.is_empty())?;
+    content.lines().position(|line| line.trim() == first).map(|idx| idx + 1)
+}
+
+/// Written in place of an answer: a pointer to the earlier one
+pub fn link(question: &str, line: usize) -> String {
+    format!("*Answered earlier: see \"{}\" (line {}).*", question, line)
+}
+
+fn trigrams(text: &str) -> HashSet<String> {
+    let words: Vec<String> = text
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|w| !w.is_empty())
+        .map(str::to_lowercase)
+        .collect();
+    let padded: Vec<char> = format!(" {} ", words.join(" ")).chars().collect();
+    padded.windows(3).map(|w| w.iter().collect()).collect()
+}
+
+fn dice(a: &HashSet<String>, b: &HashSet<String>) -> f64 {
+    if a.is_empty() && b.is_empty() {
+        return 0.0;
+    }
+    2.0 * a.intersection(b).count() as f64 / (a.len() + b.len()) as f64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{Duration, Utc};
+
+    fn entry(file: &str, prompt: &str, response: Option<&str>, age_minutes: i64) -> HistoryEntry {
+        HistoryEntry {
+            timestamp: Utc::now() - Duration::minutes(age_minutes),
+            file: file.to_string(),
+            agent: "tutor".to_string(),
+            prompt: prompt.to_string(),
+            response: response.map(str::to_string),
+            error: response.is_none().then(|| "failed".to_string()),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_similarity() {
+        assert_eq!(similarity("What is Rust?", "what is rust"), 1.0);
+        assert!(similarity("What is ownership in Rust?", "What's ownership in Rust?") > THRESHOLD);
+        assert!(similarity("What is ownership in Rust?", "What is borrowing in Rust?") < THRESHOLD);
+        assert_eq!(similarity("", ""), 0.0);
+    }
+
+    #[test]
+    fn test_find() {
+        let entries = vec![
+            entry("slides.md", "What is ownership in Rust?", Some("Old answer"), 60),
+            entry("slides.md", "What is ownership in Rust", Some("New answer"), 5),
+            entry("slides.md", "What is ownership in Rust?", None, 1),
+            entry("other.md", "What is ownership in Rust?", Some("Elsewhere"), 1),
+        ];
+        let earlier = find(&entries, "slides.md", "what is ownership in rust?", THRESHOLD).unwrap();
+        assert_eq!(earlier.answer(), "New answer");
+        assert!(find(&entries, "slides.md", "How do lifetimes work?", THRESHOLD).is_none());
+        assert!(find(&entries, "notes.md", "What is ownership in Rust?", THRESHOLD).is_none());
+    }
+
+    #[test]
+    fn test_locate_and_link() {
+        let content = "# Ownership\n\nEvery value has one owner.\nIt is dropped with it.\n";
+        assert_eq!(locate(content, "\nEvery value has one owner.\nMore."), Some(3));
+        assert_eq!(locate(content, "Not here"), None);
+        assert_eq!(link("What is ownership?", 3), "*Answered earlier: see \"What is ownership?\" (line 3).*");
+    }
+}
@@ -0,0 +1,190 @@
+//! Automatic agent selection: a quick call to a small model reads the agents'
+//! descriptions and picks the one best suited to a question, the way Claude
+//! Code picks subagents
+
+use anyhow::Result;
+
+use crate::agent::RunOptions;
+use crate::config::{Agent, Config, Profile, RouterConfig};
+use crate::side_pass::SidePass;
+
+/// Model the router uses unless the config names another
+pub const DEFAULT_ROUTER_MODEL: &str = "haiku";
+
+const ROUTER_SYSTEM_PROMPT: &str = "You route questions to the most suitable assistant. \
+Reply with the assistant's name and nothing else.";
+
+/// Picks an agent per question from a fixed set of candidates
+pub struct Router {
+    pass: SidePass,
+    model: String,
+    candidates: Vec<Agent>,
+}
+
+impl Router {
+    /// A router over the configured agents, or every agent with a description
+    pub fn new(config: &Config, settings: &RouterConfig, options: RunOptions) -> Result<Self> {
+        let mut candidates = if settings.agents.is_empty() {
+            let mut names: Vec<&String> = config
+                .agents
+                .iter()
+                .filter(|(_, agent)| agent.description.as_deref().is_some_and(|d| !d.trim().is_empty()))
+                .map(|(name, _)| name)
+                .collect();
+            names.sort();
+            names
+                .into_iter()
+                .map(|name| config.resolve_agent(name))
+                .collect::<Result<Vec<_>>>()?
+        } else {
+            settings
+                .agents
+                .iter()
+                .map(|name| config.resolve_agent(name))
+                .collect::<Result<Vec<_>>>()?
+        };
+        candidates.dedup_by(|a, b| a.name == b.name);
+        if candidates.is_empty() {
+            return Err(anyhow::anyhow!(
+                "No agents to route between; give agents a description (ecce agent add --description) \
+                 or list them under router.agents in config"
+            ));
+        }
+
+        Ok(Self {
+            pass: SidePass::new(config, options),
+            model: settings.model.clone().unwrap_or_else(|| DEFAULT_ROUTER_MODEL.to_string()),
+            candidates,
+        })
+    }
+
+    /// Route through this profile's endpoint and key (see `ClaudeAgent::use_profile`)
+    pub fn use_profile(&mut self, profile: &Profile) {
+        self.pass.use_profile(profile);
+    }
+
+    pub fn model(&self) -> &str {
+        &self.model
+    }
+
+    pub fn candidates(&self) -> &[Agent] {
+        &self.candidates
+    }
+
+    /// Name of the agent best suited to the question; with one candidate, no call is made
+    pub async fn route(&self, question: &str) -> Result<&str> {
+        if let [only] = self.candidates.as_slice() {
+            return Ok(&only.name);
+        }
+        let router = Agent {
+            name: "router".to_string(),
+            system_prompt: ROUTER_SYSTEM_PROMPT.to_string(),
+            model: Some(self.model.clone()),
+            ..Default::default()
+        };
+        let reply = self
+            .pass
+            .ask(&router, &routing_prompt(&self.candidates, question))
+            .await?;
+        parse_choice(&reply, &self.candidates)
+            .ok_or_else(|| anyhow::anyhow!("Router replied '{}', which names no agent", reply.trim()))
+    }
+}
+
+/// The routing question: each candidate with its description, then the question
+pub fn routing_prompt(candidates: &[Agent], question: &str) -> String {
+    let mut prompt = String::from("Assistants:\n");
+    for agent in candidates {
+        let description = agent.description.as_deref().unwrap_or("General purpose");
+        prompt.push_str(&format!("- {}: {}\n", agent.name, description.trim()));
+    }
+    prompt.push_str(&format!(
+        "\nQuestion:\n{}\n\nWhich assistant should answer? Reply with its name only.",
+        question
+    ));
+    prompt
+}
+
+/// The candidate named in the router's reply: an exact name, else the first
+/// name mentioned as a whole word (so `writer` never matches `writer-kids`)
+pub fn parse_choice<'a>(reply: &str, candidates: &'a [Agent]) -> Option<&'a str> {
+    let find = |word: &str| candidates.iter().find(|a| a.name.eq_ignore_ascii_case(word));
+    let reply = reply.trim().trim_matches(|c: char| c == '`' || c == '"' || c == '\'' || c == '.');
+    find(reply)
+        .or_else(|| {
+            reply
+                .split(|c: char| !(c.is_alphanumeric() || c == '-' || c == '_'))
+                .find_map(find)
+        })
+        .map(|a| a.name.as_str())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn agent(name: &str, description: Option<&str>) -> Agent {
+        Agent {
+            name: name.to_string(),
+            description: description.map(str::to_string),
+            ..Default::default()
+        }
+    }
+
+    fn config() -> Config {
+        let mut config = Config::default();
+        for agent in [
+            agent("writer", Some("Explains concepts for a general audience")),
+            agent("writer-kids", Some("Explains concepts for children")),
+            agent("helper", None),
+        ] {
+            config.agents.insert(agent.name.clone(), agent);
+        }
+        config
+    }
+
+    #[test]
+    fn test_candidates() {
+        let router = Router::new(&config(), &RouterConfig::default(), RunOptions::default()).unwrap();
+        let names: Vec<&str> = router.candidates().iter().map(|a| a.name.as_str()).collect();
+        assert_eq!(names, vec!["writer", "writer-kids"], "agents without a description are skipped");
+
+        let settings = RouterConfig {
+            agents: vec!["helper".to_string()],
+            ..Default::default()
+        };
+        let router = Router::new(&config(), &settings, RunOptions::default()).unwrap();
+        assert_eq!(router.candidates()[0].name, "helper");
+
+        assert!(Router::new(&Config::default(), &RouterConfig::default(), RunOptions::default()).is_err());
+    }
+
+    #[test]
+    fn test_parse_choice() {
+        let candidates = [agent("writer", None), agent("writer-kids", None)];
+        assert_eq!(parse_choice("writer", &candidates), Some("writer"));
+        assert_eq!(parse_choice("`Writer-Kids`.", &candidates), Some("writer-kids"));
+        assert_eq!(parse_choice("I'd pick writer-kids for this.", &candidates), Some("writer-kids"));
+        assert_eq!(parse_choice("nobody", &candidates), None);
+        assert_eq!(parse_choice("rewriters", &candidates), None);
+    }
+
+    #[test]
+    fn test_routing_prompt() {
+        let candidates = [agent("writer", Some("General audience")), agent("helper", None)];
+        let prompt = routing_prompt(&candidates, "What is a monad?");
+        assert!(prompt.starts_with("Assistants:\n- writer: General audience\n- helper: General purpose\n"));
+        assert!(prompt.contains("Question:\nWhat is a monad?"));
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_route_with_fake_claude() {
+        // A router stand-in that always picks the kids' writer
+        let claude = crate::testing::FakeClaude::answering("writer-kids").unwrap();
+        let mut config = config();
+        config.claude_executable = Some(claude.executable());
+        let router = Router::new(&config, &RouterConfig::default(), RunOptions::default()).unwrap();
+        assert_eq!(router.route("Why is the sky blue?").await.unwrap(), "writer-kids");
+    }
+}
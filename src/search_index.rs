@@ -0,0 +1,297 @@
+//! Local index over the history log for lexical search: finding past answers
+//! that share words and word forms with a query, not ones that only mean the
+//! same. Vectors are computed on this machine and stored next to the history;
+//! nothing is sent anywhere.
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use crate::config::Config;
+use crate::history::HistoryEntry;
+
+/// Turns text into a fixed-length vector of term counts; texts sharing words get nearby vectors
+pub trait Vectorizer {
+    /// Stored with each vector, so vectors from another vectorizer are recomputed
+    fn name(&self) -> &'static str;
+
+    /// A unit-length vector for `text`
+    fn vectorize(&self, text: &str) -> Vec<f32>;
+}
+
+/// Dimensions of `HashedNgrams` vectors
+const DIMS: usize = 384;
+
+/// Least similarity for a search hit; below it, texts share little beyond a few trigrams
+const MIN_SCORE: f32 = 0.1;
+
+/// Counts of words, word pairs, and character trigrams, hashed into a fixed
+/// number of buckets. Catches shared vocabulary and word forms
+/// ("transformer" and "transformers"), not synonyms.
+pub struct HashedNgrams;
+
+impl Vectorizer for HashedNgrams {
+    fn name(&self) -> &'static str {
+        "hashed-ngrams-v1"
+    }
+
+    fn vectorize(&self, text: &str) -> Vec<f32> {
+        let words: Vec<String> = text
+            .split(|c: char| !c.is_alphanumeric())
+            .filter(|w| !w.is_empty())
+            .map(str::to_lowercase)
+            .collect();
+        let mut vector = vec![0.0f32; DIMS];
+        let mut add = |feature: &str, weight: f32| {
+            let hash = fnv1a(feature);
+            let sign = if hash & (1 << 63) == 0 { 1.0 } else { -1.0 };
+            vector[(hash % DIMS as u64) as usize] += sign * weight;
+        };
+        for word in &words {
+            add(word, 1.0);
+            let padded: Vec<char> = format!(" {} ", word).chars().collect();
+            for gram in padded.windows(3) {
+                add(&gram.iter().collect::<String>(), 0.5);
+            }
+        }
+        for pair in words.windows(2) {
+            add(&format!("{} {}", pair[0], pair[1]), 0.7);
+        }
+        normalize(&mut vector);
+        vector
+    }
+}
+
+/// The vectorizer used unless another is chosen
+pub fn default_vectorizer() -> &'static dyn Vectorizer {
+    &HashedNgrams
+}
+
+/// Where vectors are kept (~/.config/ecce/search-index.jsonl); safe to delete
+pub fn index_path() -> Result<PathBuf> {
+    Ok(Config::config_dir()?.join("search-index.jsonl"))
+}
+
+/// How a history entry is known in the index: its id, or its timestamp in
+/// logs from before entries had ids
+pub fn entry_key(entry: &HistoryEntry) -> String {
+    entry.id.clone().unwrap_or_else(|| entry.timestamp.to_rfc3339())
+}
+
+/// The text indexed for an entry: the question and its answer
+fn entry_text(entry: &HistoryEntry) -> String {
+    format!("{}\n\n{}", entry.prompt, entry.response.as_deref().unwrap_or_default())
+}
+
+/// One stored vector
+#[derive(Debug, Serialize, Deserialize)]
+struct Stored {
+    key: String,
+    vectorizer: String,
+    vector: Vec<f32>,
+}
+
+/// Vectors for history entries, kept in a JSONL file and extended as the
+/// history grows
+pub struct Index {
+    path: PathBuf,
+    vectorizer: &'static str,
+    vectors: HashMap<String, Vec<f32>>,
+}
+
+/// A history entry close to a search query
+#[derive(Debug)]
+pub struct Hit<'a> {
+    pub entry: &'a HistoryEntry,
+    /// Cosine similarity, from -1 to 1
+    pub score: f32,
+}
+
+impl Index {
+    /// Load the vectors at `path` that `vectorizer` made; a missing file is an empty index
+    pub fn open(path: &Path, vectorizer: &dyn Vectorizer) -> Result<Self> {
+        let mut vectors = HashMap::new();
+        if path.exists() {
+            let content = fs::read_to_string(path)
+                .with_context(|| format!("Failed to read search index: {}", path.display()))?;
+            for line in content.lines().filter(|l| !l.trim().is_empty()) {
+                // A torn or foreign line only costs recomputing that entry
+                if let Ok(stored) = serde_json::from_str::<Stored>(line) {
+                    if stored.vectorizer == vectorizer.name() {
+                        vectors.insert(stored.key, stored.vector);
+                    }
+                }
+            }
+        }
+        Ok(Self {
+            path: path.to_path_buf(),
+            vectorizer: vectorizer.name(),
+            vectors,
+        })
+    }
+
+    /// Number of entries with a vector
+    pub fn len(&self) -> usize {
+        self.vectors.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.vectors.is_empty()
+    }
+
+    /// Vectorize the successful entries that have no vector yet and append them
+    /// to the index file. Returns how many were added.
+    pub fn update(&mut self, entries: &[HistoryEntry], vectorizer: &dyn Vectorizer) -> Result<usize> {
+        let mut lines = String::new();
+        let mut added = 0;
+        for entry in entries.iter().filter(|e| e.is_success()) {
+            let key = entry_key(entry);
+            if self.vectors.contains_key(&key) {
+                continue;
+            }
+            let stored = Stored {
+                key: key.clone(),
+                vectorizer: self.vectorizer.to_string(),
+                vector: vectorizer.vectorize(&entry_text(entry)),
+            };
+            lines.push_str(&serde_json::to_string(&stored)?);
+            lines.push('\n');
+            self.vectors.insert(key, stored.vector);
+            added += 1;
+        }
+        if added > 0 {
+            if let Some(dir) = self.path.parent() {
+                fs::create_dir_all(dir)?;
+            }
+            let mut file = OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(&self.path)
+                .with_context(|| format!("Failed to open search index: {}", self.path.display()))?;
+            file.write_all(lines.as_bytes())?;
+        }
+        Ok(added)
+    }
+
+    /// Up to `limit` entries close to `query`, best first
+    pub fn search<'a>(&self, entries: &'a [HistoryEntry], query: &str, vectorizer: &dyn Vectorizer, limit: usize) -> Vec<Hit<'a>> {
+        let query = vectorizer.vectorize(query);
+        let mut hits: Vec<Hit> = entries
+            .iter()
+            .filter_map(|entry| {
+                let vector = self.vectors.get(&entry_key(entry))?;
+                Some(Hit {
+                    entry,
+                    score: cosine(&query, vector),
+                })
+            })
+            .filter(|hit| hit.score >= MIN_SCORE)
+            .collect();
+        hits.sort_by(|a, b| b.score.total_cmp(&a.score));
+        hits.truncate(limit);
+        hits
+    }
+}
+
+/// Cosine similarity of two vectors
+pub fn cosine(a: &[f32], b: &[f32]) -> f32 {
+    let dot: f32 = a.iter().zip(b).map(|(x, y)| x * y).sum();
+    let norm = |v: &[f32]| v.iter().map(|x| x * x).sum::<f32>().sqrt();
+    let norms = norm(a) * norm(b);
+    if norms == 0.0 {
+        0.0
+    } else {
+        dot / norms
+    }
+}
+
+fn normalize(vector: &mut [f32]) {
+    let norm = vector.iter().map(|x| x * x).sum::<f32>().sqrt();
+    if norm > 0.0 {
+        vector.iter_mut().for_each(|x| *x /= norm);
+    }
+}
+
+/// 64-bit FNV-1a, stable across runs and Rust versions unlike `DefaultHasher`
+fn fnv1a(text: &str) -> u64 {
+    text.bytes().fold(0xcbf29ce484222325, |hash, byte| {
+        (hash ^ byte as u64).wrapping_mul(0x100000001b3)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Utc;
+
+    fn entry(id: Option<&str>, prompt: &str, response: Option<&str>) -> HistoryEntry {
+        HistoryEntry {
+            id: id.map(str::to_string),
+            timestamp: Utc::now(),
+            file: "slides.md".to_string(),
+            agent: "tutor".to_string(),
+            prompt: prompt.to_string(),
+            response: response.map(str::to_string),
+            error: response.is_none().then(|| "failed".to_string()),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_similar_texts_are_close() {
+        let vectorize = |text: &str| HashedNgrams.vectorize(text);
+        let query = vectorize("transformer attention");
+        let related = vectorize("How does attention work in transformers?");
+        let unrelated = vectorize("What is a Rust trait object?");
+        assert!(cosine(&query, &related) > cosine(&query, &unrelated));
+        assert!((cosine(&related, &related) - 1.0).abs() < 1e-5);
+        assert_eq!(vectorize("Same text"), vectorize("same TEXT!"));
+        assert_eq!(cosine(&vectorize(""), &related), 0.0);
+    }
+
+    #[test]
+    fn test_index_update_and_search() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("search-index.jsonl");
+        let entries = vec![
+            entry(Some("a1"), "How does attention work in transformers?", Some("Queries weigh keys.")),
+            entry(Some("b2"), "What is a trait object?", Some("Dynamic dispatch in Rust.")),
+            entry(Some("c3"), "What is self-attention?", None),
+            entry(None, "Explain multi-head attention", Some("Several attention heads run in parallel.")),
+        ];
+
+        let mut index = Index::open(&path, &HashedNgrams).unwrap();
+        assert_eq!(index.update(&entries, &HashedNgrams).unwrap(), 3);
+        // Only new entries are vectorized, and vectors survive a reload
+        let mut index = Index::open(&path, &HashedNgrams).unwrap();
+        assert_eq!(index.len(), 3);
+        assert_eq!(index.update(&entries, &HashedNgrams).unwrap(), 0);
+
+        let hits = index.search(&entries, "transformer attention", &HashedNgrams, 2);
+        let prompts: Vec<&str> = hits.iter().map(|h| h.entry.prompt.as_str()).collect();
+        assert_eq!(prompts.len(), 2);
+        assert!(prompts.iter().all(|p| p.contains("attention")));
+        assert!(hits[0].score >= hits[1].score);
+    }
+
+    #[test]
+    fn test_other_vectorizers_are_ignored() {
+        struct Other;
+        impl Vectorizer for Other {
+            fn name(&self) -> &'static str {
+                "other"
+            }
+            fn vectorize(&self, _: &str) -> Vec<f32> {
+                vec![1.0]
+            }
+        }
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("search-index.jsonl");
+        let entries = vec![entry(Some("a1"), "What is a trait?", Some("A set of methods."))];
+        Index::open(&path, &Other).unwrap().update(&entries, &Other).unwrap();
+        assert!(Index::open(&path, &HashedNgrams).unwrap().is_empty());
+    }
+}
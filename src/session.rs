@@ -0,0 +1,346 @@
+//! Saved `ecce homo` sessions. A running watch keeps its setup and progress in
+//! an autosave; `ecce session save` names a copy that `ecce session restore` resumes.
+
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use colored::Colorize;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::config::Config;
+use crate::pattern::{DedupStrategy, PatternType};
+use crate::template::Message;
+use crate::ui_eprintln;
+
+/// Subdirectory of the sessions directory holding running sessions' autosaves
+const AUTOSAVE_DIR: &str = "autosave";
+
+/// An agent's conversation in a saved session
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct SavedAgent {
+    pub name: String,
+    #[serde(default)]
+    pub history: Vec<Message>,
+    /// The claude session to resume, if claude reported one
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub session_id: Option<String>,
+}
+
+/// The homo flags a session was started with
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
+pub struct SessionSettings {
+    pub watch_interval: u64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub debounce: Option<u64>,
+    #[serde(default)]
+    pub confirm: bool,
+    /// Agents were picked per pattern by the router
+    #[serde(default)]
+    pub route: bool,
+    #[serde(default)]
+    pub skip_permissions: bool,
+    #[serde(default)]
+    pub no_resume: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_prompt_tokens: Option<u64>,
+    #[serde(default)]
+    pub observe: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub output: Option<PathBuf>,
+    #[serde(default)]
+    pub copy: bool,
+    #[serde(default)]
+    pub provenance: bool,
+    #[serde(default)]
+    pub stdout: bool,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub only: Vec<PatternType>,
+    #[serde(default)]
+    pub dedup: DedupStrategy,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub keywords: Vec<String>,
+    #[serde(default)]
+    pub strict: bool,
+    #[serde(default)]
+    pub cite: bool,
+    #[serde(default)]
+    pub polish: bool,
+    #[serde(default)]
+    pub match_style: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub fit_slide: Option<usize>,
+    #[serde(default)]
+    pub queue: bool,
+    #[serde(default)]
+    pub reuse: bool,
+}
+
+/// Everything needed to pick a homo session back up where it stopped
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct SavedSession {
+    /// The watched file: an absolute local path or `host:path`
+    pub file: String,
+    /// One agent, or several in compare mode
+    pub agents: Vec<SavedAgent>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub task: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub preset: Option<String>,
+    pub settings: SessionSettings,
+    /// Keys of the patterns already answered (see `DedupStrategy`)
+    #[serde(default)]
+    pub processed: Vec<String>,
+    pub saved_at: DateTime<Utc>,
+}
+
+impl SavedSession {
+    /// Messages across all agents' conversations
+    pub fn message_count(&self) -> usize {
+        self.agents.iter().map(|a| a.history.len()).sum()
+    }
+}
+
+/// Get the sessions directory (~/.config/ecce/sessions)
+pub fn sessions_dir() -> Result<PathBuf> {
+    Ok(Config::config_dir()?.join("sessions"))
+}
+
+/// Session names become file names, so keep them to a safe set of characters
+fn check_name(name: &str) -> Result<()> {
+    let valid = !name.is_empty()
+        && !name.starts_with('.')
+        && name.chars().all(|c| c.is_alphanumeric() || matches!(c, '-' | '_' | '.'));
+    if valid {
+        Ok(())
+    } else {
+        Err(anyhow::anyhow!(
+            "Invalid session name '{}': use letters, digits, '-', '_', or '.'",
+            name
+        ))
+    }
+}
+
+fn read(path: &Path) -> Result<SavedSession> {
+    let content = fs::read_to_string(path)
+        .with_context(|| format!("Failed to read {}", path.display()))?;
+    serde_json::from_str(&content).with_context(|| format!("Failed to parse {}", path.display()))
+}
+
+/// A session file for listing; one that can't be read is skipped with a
+/// warning, so it doesn't hide the others
+fn read_listed(path: &Path) -> Option<SavedSession> {
+    read(path)
+        .map_err(|e| ui_eprintln!("{}", format!("⚠ Skipping session: {:#}", e).yellow()))
+        .ok()
+}
+
+/// Written to a temporary file first so a crash mid-write never leaves a torn session
+fn write(path: &Path, session: &SavedSession) -> Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let tmp = path.with_extension("json.tmp");
+    fs::write(&tmp, serde_json::to_string_pretty(session)?)
+        .with_context(|| format!("Failed to write {}", tmp.display()))?;
+    fs::rename(&tmp, path).with_context(|| format!("Failed to write {}", path.display()))
+}
+
+/// Save a session under `name` in `dir`, replacing any session of that name
+pub fn save_in(dir: &Path, name: &str, session: &SavedSession) -> Result<PathBuf> {
+    check_name(name)?;
+    let path = dir.join(format!("{}.json", name));
+    write(&path, session)?;
+    Ok(path)
+}
+
+pub fn load_in(dir: &Path, name: &str) -> Result<SavedSession> {
+    check_name(name)?;
+    let path = dir.join(format!("{}.json", name));
+    if !path.exists() {
+        return Err(anyhow::anyhow!("Session '{}' not found", name));
+    }
+    read(&path)
+}
+
+pub fn delete_in(dir: &Path, name: &str) -> Result<()> {
+    check_name(name)?;
+    let path = dir.join(format!("{}.json", name));
+    if !path.exists() {
+        return Err(anyhow::anyhow!("Session '{}' not found", name));
+    }
+    fs::remove_file(&path).with_context(|| format!("Failed to delete {}", path.display()))
+}
+
+/// Named sessions in `dir`, sorted by name
+pub fn list_in(dir: &Path) -> Result<Vec<(String, SavedSession)>> {
+    if !dir.exists() {
+        return Ok(Vec::new());
+    }
+    let mut sessions = Vec::new();
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.extension().and_then(|e| e.to_str()) != Some("json") {
+            continue;
+        }
+        if let Some(name) = path.file_stem().and_then(|s| s.to_str()) {
+            if let Some(session) = read_listed(&path) {
+                sessions.push((name.to_string(), session));
+            }
+        }
+    }
+    sessions.sort_by(|a, b| a.0.cmp(&b.0));
+    Ok(sessions)
+}
+
+/// Record a running session's progress; one autosave per watched file
+pub fn autosave_in(dir: &Path, session: &SavedSession) -> Result<()> {
+    let id = format!("{:x}", Sha256::digest(session.file.as_bytes()));
+    write(&dir.join(AUTOSAVE_DIR).join(format!("{}.json", &id[..16])), session)
+}
+
+/// The most recently updated autosave, optionally only for the given file
+pub fn latest_autosave_in(dir: &Path, file: Option<&str>) -> Result<Option<SavedSession>> {
+    let dir = dir.join(AUTOSAVE_DIR);
+    if !dir.exists() {
+        return Ok(None);
+    }
+    let mut latest: Option<SavedSession> = None;
+    for entry in fs::read_dir(&dir)? {
+        let path = entry?.path();
+        if path.extension().and_then(|e| e.to_str()) != Some("json") {
+            continue;
+        }
+        let Some(session) = read_listed(&path) else {
+            continue;
+        };
+        if file.is_some_and(|file| file != session.file) {
+            continue;
+        }
+        if latest.as_ref().is_none_or(|l| session.saved_at > l.saved_at) {
+            latest = Some(session);
+        }
+    }
+    Ok(latest)
+}
+
+pub fn save(name: &str, session: &SavedSession) -> Result<PathBuf> {
+    save_in(&sessions_dir()?, name, session)
+}
+
+pub fn load(name: &str) -> Result<SavedSession> {
+    load_in(&sessions_dir()?, name)
+}
+
+pub fn delete(name: &str) -> Result<()> {
+    delete_in(&sessions_dir()?, name)
+}
+
+pub fn list() -> Result<Vec<(String, SavedSession)>> {
+    list_in(&sessions_dir()?)
+}
+
+pub fn autosave(session: &SavedSession) -> Result<()> {
+    autosave_in(&sessions_dir()?, session)
+}
+
+pub fn latest_autosave(file: Option<&str>) -> Result<Option<SavedSession>> {
+    latest_autosave_in(&sessions_dir()?, file)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn session(file: &str, saved_at: &str) -> SavedSession {
+        SavedSession {
+            file: file.to_string(),
+            agents: vec![SavedAgent {
+                name: "writer".to_string(),
+                history: vec![Message {
+                    role: "User".to_string(),
+                    content: "What is Rust?".to_string(),
+                }],
+                session_id: Some("abc".to_string()),
+            }],
+            task: Some("explain".to_string()),
+            preset: None,
+            settings: SessionSettings {
+                watch_interval: 100,
+                debounce: None,
+                confirm: false,
+                cite: false,
+                polish: false,
+                match_style: false,
+                fit_slide: None,
+                queue: false,
+                reuse: false,
+                route: false,
+                skip_permissions: false,
+                no_resume: false,
+                max_prompt_tokens: None,
+                observe: true,
+                output: None,
+                copy: false,
+                provenance: false,
+                stdout: false,
+                only: vec![PatternType::CodeBlock],
+                dedup: DedupStrategy::Marker,
+                keywords: Vec::new(),
+                strict: false,
+            },
+            processed: vec!["slide 0:What is Rust?".to_string()],
+            saved_at: saved_at.parse().unwrap(),
+        }
+    }
+
+    #[test]
+    fn test_save_load_list_delete() {
+        let dir = tempfile::tempdir().unwrap();
+        let saved = session("/talks/slides.md", "2026-10-14T18:00:00Z");
+
+        save_in(dir.path(), "lecture-3", &saved).unwrap();
+        assert_eq!(load_in(dir.path(), "lecture-3").unwrap(), saved);
+        assert_eq!(list_in(dir.path()).unwrap(), vec![("lecture-3".to_string(), saved)]);
+
+        fs::write(dir.path().join("torn.json"), "{\"file\": ").unwrap();
+        assert_eq!(list_in(dir.path()).unwrap().len(), 1);
+        fs::remove_file(dir.path().join("torn.json")).unwrap();
+
+        delete_in(dir.path(), "lecture-3").unwrap();
+        assert!(list_in(dir.path()).unwrap().is_empty());
+        assert!(load_in(dir.path(), "lecture-3").is_err());
+    }
+
+    #[test]
+    fn test_invalid_names() {
+        let dir = tempfile::tempdir().unwrap();
+        let saved = session("/talks/slides.md", "2026-10-14T18:00:00Z");
+        for name in ["", "../up", "a/b", ".hidden"] {
+            assert!(save_in(dir.path(), name, &saved).is_err(), "{}", name);
+        }
+    }
+
+    #[test]
+    fn test_latest_autosave() {
+        let dir = tempfile::tempdir().unwrap();
+        assert!(latest_autosave_in(dir.path(), None).unwrap().is_none());
+
+        autosave_in(dir.path(), &session("/a.md", "2026-10-14T18:00:00Z")).unwrap();
+        autosave_in(dir.path(), &session("/b.md", "2026-10-14T19:00:00Z")).unwrap();
+        // A later save of the same file replaces its autosave
+        autosave_in(dir.path(), &session("/a.md", "2026-10-14T17:00:00Z")).unwrap();
+
+        assert_eq!(latest_autosave_in(dir.path(), None).unwrap().unwrap().file, "/b.md");
+        let a = latest_autosave_in(dir.path(), Some("/a.md")).unwrap().unwrap();
+        assert_eq!(a.saved_at, "2026-10-14T17:00:00Z".parse::<DateTime<Utc>>().unwrap());
+        assert!(latest_autosave_in(dir.path(), Some("/c.md")).unwrap().is_none());
+        // Autosaves are not named sessions
+        assert!(list_in(dir.path()).unwrap().is_empty());
+
+        // A corrupt file is skipped rather than failing the lookup
+        fs::write(dir.path().join(AUTOSAVE_DIR).join("torn.json"), "{\"file\": ").unwrap();
+        assert_eq!(latest_autosave_in(dir.path(), None).unwrap().unwrap().file, "/b.md");
+    }
+}
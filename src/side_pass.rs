@@ -0,0 +1,46 @@
+//! Side passes: one-off calls to a small model outside the agents'
+//! conversations, which route questions and proofread and translate answers
+
+use anyhow::Result;
+
+use crate::agent::{ClaudeAgent, RunOptions};
+use crate::config::{Agent, Config, Profile};
+
+/// Runs one prompt at a time through a fresh claude agent
+pub struct SidePass {
+    executable: String,
+    options: RunOptions,
+    /// Profile pinned to the watched file's project, used like the agents use it
+    profile: Option<Profile>,
+}
+
+impl SidePass {
+    pub fn new(config: &Config, options: RunOptions) -> Self {
+        Self {
+            executable: config.get_claude_executable(),
+            // The pass's own model, whatever a preset picked for the agents
+            options: RunOptions {
+                model: None,
+                no_resume: true,
+                cite: false,
+                ..options
+            },
+            profile: None,
+        }
+    }
+
+    /// Call claude through this profile's endpoint and key (see `ClaudeAgent::use_profile`)
+    pub fn use_profile(&mut self, profile: &Profile) {
+        self.profile = Some(profile.clone());
+    }
+
+    /// The agent's reply to one prompt; a fresh agent per call, so earlier
+    /// calls never leak into the prompt
+    pub async fn ask(&self, agent: &Agent, prompt: &str) -> Result<String> {
+        let mut claude = ClaudeAgent::with_options(self.executable.clone(), agent.clone(), None, self.options.clone());
+        if let Some(ref profile) = self.profile {
+            claude.use_profile(profile);
+        }
+        claude.generate_response(prompt).await
+    }
+}
@@ -0,0 +1,83 @@
+//! Machine-wide limit on running claude processes. Each process holds a lock
+//! on one of `max_concurrent` slot files, so watchers and batch runs in
+//! separate ecce processes wait their turn. A lock dies with its process, so a
+//! crash never leaks a slot.
+
+use anyhow::{Context, Result};
+use std::fs::{self, File, OpenOptions, TryLockError};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use crate::config::Config;
+
+/// How often a waiting generation looks for a free slot
+const SLOT_POLL: Duration = Duration::from_millis(250);
+
+/// Where the slot files live
+pub fn slots_dir() -> Result<PathBuf> {
+    Ok(Config::config_dir()?.join("slots"))
+}
+
+/// One of the slots; freed when dropped
+#[derive(Debug)]
+pub struct Slot {
+    _lock: File,
+}
+
+/// Take the first free slot in `dir`, waiting for one if all `limit` are taken
+pub async fn acquire(dir: &Path, limit: usize) -> Result<Slot> {
+    fs::create_dir_all(dir).with_context(|| format!("Failed to create slot directory {}", dir.display()))?;
+    loop {
+        if let Some(slot) = try_acquire(dir, limit)? {
+            return Ok(slot);
+        }
+        tokio::time::sleep(SLOT_POLL).await;
+    }
+}
+
+/// The first free slot, if any
+pub fn try_acquire(dir: &Path, limit: usize) -> Result<Option<Slot>> {
+    for i in 0..limit.max(1) {
+        let path = dir.join(format!("{}.lock", i));
+        let file = OpenOptions::new()
+            .create(true)
+            .truncate(false)
+            .write(true)
+            .open(&path)
+            .with_context(|| format!("Failed to open slot {}", path.display()))?;
+        match file.try_lock() {
+            Ok(()) => return Ok(Some(Slot { _lock: file })),
+            Err(TryLockError::WouldBlock) => continue,
+            Err(TryLockError::Error(e)) => {
+                return Err(e).with_context(|| format!("Failed to lock slot {}", path.display()))
+            }
+        }
+    }
+    Ok(None)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[tokio::test]
+    async fn test_slots() {
+        let dir = TempDir::new().unwrap();
+        let first = acquire(dir.path(), 2).await.unwrap();
+        let second = acquire(dir.path(), 2).await.unwrap();
+        assert!(try_acquire(dir.path(), 2).unwrap().is_none());
+
+        // A third waits until one is freed
+        let waiting = tokio::spawn({
+            let dir = dir.path().to_path_buf();
+            async move { acquire(&dir, 2).await }
+        });
+        tokio::time::sleep(SLOT_POLL * 2).await;
+        assert!(!waiting.is_finished());
+        drop(first);
+        let third = tokio::time::timeout(SLOT_POLL * 8, waiting).await.unwrap().unwrap();
+        assert!(third.is_ok());
+        drop(second);
+    }
+}
@@ -0,0 +1,149 @@
+use serde::ser::{Serialize, SerializeStruct, Serializer};
+use std::collections::BTreeMap;
+
+use crate::history::HistoryEntry;
+
+/// Aggregate metrics for one group of generations
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Bucket {
+    pub count: u64,
+    pub errors: u64,
+    pub total_duration_ms: u64,
+    pub prompt_tokens: u64,
+    pub response_tokens: u64,
+}
+
+impl Bucket {
+    fn add(&mut self, entry: &HistoryEntry) {
+        self.count += 1;
+        if !entry.is_success() {
+            self.errors += 1;
+        }
+        self.total_duration_ms += entry.duration_ms;
+        self.prompt_tokens += entry.prompt_tokens.unwrap_or(0);
+        self.response_tokens += entry.response_tokens.unwrap_or(0);
+    }
+
+    pub fn avg_latency_ms(&self) -> u64 {
+        self.total_duration_ms.checked_div(self.count).unwrap_or(0)
+    }
+
+    pub fn error_rate(&self) -> f64 {
+        if self.count == 0 {
+            0.0
+        } else {
+            self.errors as f64 / self.count as f64
+        }
+    }
+
+    pub fn total_tokens(&self) -> u64 {
+        self.prompt_tokens + self.response_tokens
+    }
+}
+
+// Serialize derived metrics alongside the raw counters for `--json` consumers
+impl Serialize for Bucket {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut state = serializer.serialize_struct("Bucket", 7)?;
+        state.serialize_field("count", &self.count)?;
+        state.serialize_field("errors", &self.errors)?;
+        state.serialize_field("error_rate", &self.error_rate())?;
+        state.serialize_field("avg_latency_ms", &self.avg_latency_ms())?;
+        state.serialize_field("prompt_tokens", &self.prompt_tokens)?;
+        state.serialize_field("response_tokens", &self.response_tokens)?;
+        state.serialize_field("total_tokens", &self.total_tokens())?;
+        state.end()
+    }
+}
+
+/// Usage metrics grouped by agent, profile, and day
+#[derive(Debug, serde::Serialize, Default)]
+pub struct UsageStats {
+    pub total: Bucket,
+    pub by_agent: BTreeMap<String, Bucket>,
+    pub by_profile: BTreeMap<String, Bucket>,
+    pub by_day: BTreeMap<String, Bucket>,
+}
+
+impl UsageStats {
+    pub fn from_entries(entries: &[HistoryEntry]) -> Self {
+        let mut stats = UsageStats::default();
+
+        for entry in entries {
+            stats.total.add(entry);
+            stats
+                .by_agent
+                .entry(entry.agent.clone())
+                .or_default()
+                .add(entry);
+            stats
+                .by_profile
+                .entry(entry.profile.clone().unwrap_or_else(|| "(none)".to_string()))
+                .or_default()
+                .add(entry);
+            stats
+                .by_day
+                .entry(entry.timestamp.format("%Y-%m-%d").to_string())
+                .or_default()
+                .add(entry);
+        }
+
+        stats
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{TimeZone, Utc};
+
+    fn entry(agent: &str, profile: Option<&str>, day: u32, ok: bool, ms: u64) -> HistoryEntry {
+        HistoryEntry {
+            timestamp: Utc.with_ymd_and_hms(2025, 3, day, 9, 0, 0).unwrap(),
+            file: "slides.md".to_string(),
+            agent: agent.to_string(),
+            profile: profile.map(|p| p.to_string()),
+            prompt: "q".to_string(),
+            response: ok.then(|| "a".to_string()),
+            error: (!ok).then(|| "failed".to_string()),
+            duration_ms: ms,
+            prompt_tokens: Some(10),
+            response_tokens: ok.then_some(20),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_usage_stats_grouping() {
+        let entries = vec![
+            entry("writer", Some("prod"), 1, true, 1000),
+            entry("writer", Some("relay"), 1, false, 3000),
+            entry("coder", None, 2, true, 500),
+        ];
+
+        let stats = UsageStats::from_entries(&entries);
+
+        assert_eq!(stats.total.count, 3);
+        assert_eq!(stats.total.errors, 1);
+        assert_eq!(stats.total.total_tokens(), 70);
+
+        let writer = &stats.by_agent["writer"];
+        assert_eq!(writer.count, 2);
+        assert_eq!(writer.avg_latency_ms(), 2000);
+        assert_eq!(writer.error_rate(), 0.5);
+
+        assert_eq!(stats.by_profile["(none)"].count, 1);
+        assert_eq!(stats.by_day["2025-03-01"].count, 2);
+        assert_eq!(stats.by_day["2025-03-02"].count, 1);
+
+        let json = serde_json::to_value(&stats).unwrap();
+        assert_eq!(json["by_agent"]["writer"]["avg_latency_ms"], 2000);
+    }
+
+    #[test]
+    fn test_empty_bucket() {
+        let bucket = Bucket::default();
+        assert_eq!(bucket.avg_latency_ms(), 0);
+        assert_eq!(bucket.error_rate(), 0.0);
+    }
+}
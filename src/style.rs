@@ -0,0 +1,315 @@
+//! Style sampling: how the hand-written parts of a document use headings,
+//! lists, bold, and emoji, summed up as a prompt instruction so generated
+//! answers look like the slides around them
+
+use std::collections::HashMap;
+
+use crate::provenance::find_regions;
+
+/// Fewest examples of a habit before it counts as the document's style
+const MIN_SAMPLES: usize = 3;
+
+/// Share of the examples that must agree for a habit to count
+const MIN_AGREEMENT: f64 = 0.7;
+
+/// Words a Title Case heading leaves in lower case
+const MINOR_WORDS: &[&str] = &[
+    "a", "an", "and", "as", "at", "but", "by", "for", "in", "of", "on", "or", "the", "to", "vs", "with",
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum HeadingCase {
+    /// Every significant word capitalized
+    Title,
+    /// Only the first word capitalized
+    Sentence,
+}
+
+/// The habits found in a document; `None` where there were too few examples
+/// or they disagreed
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct DocumentStyle {
+    /// Marker of unordered list items: `-`, `*`, or `+`
+    pub bullet: Option<char>,
+    /// Spaces per nesting level of list items
+    pub indent: Option<usize>,
+    /// Delimiter after the number of ordered list items: `.` or `)`
+    pub numbering: Option<char>,
+    /// Heading levels in use, shallowest first
+    pub heading_levels: Vec<usize>,
+    pub heading_case: Option<HeadingCase>,
+    /// Whether headings carry an emoji
+    pub heading_emoji: Option<bool>,
+    /// Whether list items carry an emoji
+    pub bullet_emoji: Option<bool>,
+    /// Bold markup: `**` or `__`
+    pub bold: Option<&'static str>,
+}
+
+impl DocumentStyle {
+    /// Sample the hand-written parts of `document`: generated answers, code
+    /// blocks, and front matter are skipped
+    pub fn sample(document: &str) -> Self {
+        let mut bullets = Vec::new();
+        let mut indents = Vec::new();
+        let mut numberings = Vec::new();
+        let mut headings = Vec::new();
+        let mut bold = Vec::new();
+
+        for line in hand_written_lines(document) {
+            let indent = line.len() - line.trim_start_matches(' ').len();
+            let text = line.trim_start();
+            if let Some((level, title)) = heading(text) {
+                headings.push((level, title.to_string()));
+            } else if let Some((marker, item)) = bullet(text) {
+                bullets.push((marker, item.to_string()));
+                if indent > 0 {
+                    indents.push(indent);
+                }
+            } else if let Some(delimiter) = numbered(text) {
+                numberings.push(delimiter);
+                if indent > 0 {
+                    indents.push(indent);
+                }
+            }
+            bold.extend(text.match_indices("**").step_by(2).map(|_| "**"));
+            bold.extend(text.match_indices("__").step_by(2).map(|_| "__"));
+        }
+
+        let mut levels: Vec<usize> = headings.iter().map(|(level, _)| *level).collect();
+        levels.sort_unstable();
+        levels.dedup();
+        let cases: Vec<HeadingCase> = headings.iter().filter_map(|(_, title)| heading_case(title)).collect();
+        Self {
+            bullet: consensus(&bullets.iter().map(|(marker, _)| *marker).collect::<Vec<_>>()),
+            // Deeper items are indented by multiples of one level
+            indent: (indents.len() >= MIN_SAMPLES).then(|| indents.iter().copied().min()).flatten(),
+            numbering: consensus(&numberings),
+            heading_levels: if headings.len() >= MIN_SAMPLES { levels } else { Vec::new() },
+            heading_case: consensus(&cases),
+            heading_emoji: consensus(&headings.iter().map(|(_, t)| has_emoji(t)).collect::<Vec<_>>()),
+            bullet_emoji: consensus(&bullets.iter().map(|(_, t)| has_emoji(t)).collect::<Vec<_>>()),
+            bold: consensus(&bold),
+        }
+    }
+
+    /// Closing instruction describing the style, if anything was found
+    pub fn instruction(&self) -> Option<String> {
+        let mut rules = Vec::new();
+        if !self.heading_levels.is_empty() {
+            let levels: Vec<String> = self.heading_levels.iter().map(|l| "#".repeat(*l)).collect();
+            let mut rule = format!("Headings: only levels {}", levels.join(", "));
+            match self.heading_case {
+                Some(HeadingCase::Title) => rule.push_str(", in Title Case"),
+                Some(HeadingCase::Sentence) => rule.push_str(", in sentence case"),
+                None => {}
+            }
+            match self.heading_emoji {
+                Some(true) => rule.push_str(", each with an emoji"),
+                Some(false) => rule.push_str(", without emoji"),
+                None => {}
+            }
+            rules.push(rule);
+        }
+        if let Some(marker) = self.bullet {
+            let mut rule = format!("Bullets: `{}`", marker);
+            if let Some(indent) = self.indent {
+                rule.push_str(&format!(", nested items indented {} spaces", indent));
+            }
+            match self.bullet_emoji {
+                Some(true) => rule.push_str(", each starting with an emoji"),
+                Some(false) => rule.push_str(", without emoji"),
+                None => {}
+            }
+            rules.push(rule);
+        }
+        if let Some(delimiter) = self.numbering {
+            rules.push(format!("Numbered lists: `1{}`", delimiter));
+        }
+        if let Some(bold) = self.bold {
+            rules.push(format!("Bold: `{b}text{b}`", b = bold));
+        }
+        if rules.is_empty() {
+            return None;
+        }
+        Some(format!(
+            "\n\nMatch the style of the document the answer goes into:\n- {}",
+            rules.join("\n- ")
+        ))
+    }
+}
+
+/// Lines outside generated answers, fenced code, and front matter
+fn hand_written_lines(document: &str) -> Vec<&str> {
+    let mut segments = Vec::new();
+    let mut last = 0;
+    for region in find_regions(document) {
+        segments.push(&document[last..region.range.start]);
+        last = region.range.end;
+    }
+    segments.push(&document[last..]);
+
+    let mut lines = Vec::new();
+    let mut fence: Option<&str> = None;
+    let mut front_matter = document.starts_with("---\n");
+    for (i, line) in segments.iter().flat_map(|s| s.lines()).enumerate() {
+        let trimmed = line.trim();
+        if front_matter {
+            front_matter = i == 0 || trimmed != "---";
+            continue;
+        }
+        match fence {
+            Some(open) if trimmed.starts_with(open) => fence = None,
+            Some(_) => {}
+            None if trimmed.starts_with("```") => fence = Some("```"),
+            None if trimmed.starts_with("~~~") => fence = Some("~~~"),
+            None => lines.push(line),
+        }
+    }
+    lines
+}
+
+/// An ATX heading's level and text
+fn heading(line: &str) -> Option<(usize, &str)> {
+    let level = line.len() - line.trim_start_matches('#').len();
+    let title = &line[level..];
+    match (1..=6).contains(&level) && title.starts_with(' ') {
+        true => Some((level, title.trim().trim_end_matches('#').trim_end())),
+        false => None,
+    }
+}
+
+/// An unordered list item's marker and text
+fn bullet(line: &str) -> Option<(char, &str)> {
+    let marker = line.chars().next().filter(|c| matches!(c, '-' | '*' | '+'))?;
+    let item = line[1..].strip_prefix(' ')?;
+    // A rule (`---`, `* * *`) or bold text (`**x**`) isn't a list item
+    match item.trim().chars().all(|c| c == marker || c == ' ') || line.starts_with("**") {
+        true => None,
+        false => Some((marker, item.trim())),
+    }
+}
+
+/// An ordered list item's delimiter
+fn numbered(line: &str) -> Option<char> {
+    let digits = line.len() - line.trim_start_matches(|c: char| c.is_ascii_digit()).len();
+    let rest = &line[digits..];
+    let delimiter = rest.chars().next().filter(|c| matches!(c, '.' | ')'))?;
+    (digits > 0 && rest[1..].starts_with(' ')).then_some(delimiter)
+}
+
+/// Title or sentence case, for headings with at least two significant words
+fn heading_case(title: &str) -> Option<HeadingCase> {
+    let words: Vec<&str> = title
+        .split_whitespace()
+        .filter(|w| w.chars().next().is_some_and(char::is_alphabetic))
+        .collect();
+    let (first, rest) = words.split_first()?;
+    let significant: Vec<&&str> = rest
+        .iter()
+        .filter(|w| !MINOR_WORDS.contains(&w.to_lowercase().as_str()))
+        .collect();
+    if significant.is_empty() || !first.chars().next().is_some_and(char::is_uppercase) {
+        return None;
+    }
+    let capitalized = significant
+        .iter()
+        .filter(|w| w.chars().next().is_some_and(char::is_uppercase))
+        .count();
+    match capitalized {
+        n if n == significant.len() => Some(HeadingCase::Title),
+        0 => Some(HeadingCase::Sentence),
+        _ => None,
+    }
+}
+
+fn has_emoji(text: &str) -> bool {
+    text.chars().any(|c| {
+        matches!(c as u32, 0x1F300..=0x1FAFF | 0x2600..=0x27BF | 0x2B50 | 0x2B55)
+    })
+}
+
+/// The value most examples agree on, if there are enough and they agree enough
+fn consensus<T: Copy + Eq + std::hash::Hash>(samples: &[T]) -> Option<T> {
+    if samples.len() < MIN_SAMPLES {
+        return None;
+    }
+    let mut counts: HashMap<T, usize> = HashMap::new();
+    for sample in samples {
+        *counts.entry(*sample).or_default() += 1;
+    }
+    let (value, count) = counts.into_iter().max_by_key(|(_, count)| *count)?;
+    (count as f64 / samples.len() as f64 >= MIN_AGREEMENT).then_some(value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SLIDES: &str = "---\ntheme: default\n# Not A Heading\n---\n\n\
+# 🦀 Rust Ownership Basics\n\n\
+* 📦 Values have one owner\n  * moved on assignment\n* 🔁 Borrowing lends access\n* 🧹 Dropped at scope end\n\n\
+## 📏 Rules of the Borrow Checker\n\n\
+1) One **mutable** reference\n2) Or many **shared** ones\n3) Never dangling\n\n\
+```rust\n# [derive(Debug)]\n- not a bullet\n```\n\n\
+## 🧪 Testing Your Code\n\n---\n";
+
+    #[test]
+    fn test_sample() {
+        let style = DocumentStyle::sample(SLIDES);
+        assert_eq!(style.bullet, Some('*'));
+        assert_eq!(style.indent, None);
+        assert_eq!(style.numbering, Some(')'));
+        assert_eq!(style.heading_levels, vec![1, 2]);
+        assert_eq!(style.heading_case, Some(HeadingCase::Title));
+        assert_eq!(style.heading_emoji, Some(true));
+        assert_eq!(style.bullet_emoji, Some(true));
+        assert_eq!(style.bold, None);
+
+        assert_eq!(
+            style.instruction().unwrap(),
+            "\n\nMatch the style of the document the answer goes into:\n\
+             - Headings: only levels #, ##, in Title Case, each with an emoji\n\
+             - Bullets: `*`, each starting with an emoji\n\
+             - Numbered lists: `1)`"
+        );
+    }
+
+    #[test]
+    fn test_sample_skips_generated_answers() {
+        let answer = crate::provenance::Provenance::new(
+            "a1".to_string(),
+            "writer".to_string(),
+            None,
+            "What is borrowing?".to_string(),
+            "- one\n- two\n- three\n- four\n",
+        )
+        .wrap("- one\n- two\n- three\n- four\n");
+        let document = format!("+ hand\n+ written\n+ items\n\n{}\n+ more\n", answer);
+        assert_eq!(DocumentStyle::sample(&document).bullet, Some('+'));
+    }
+
+    #[test]
+    fn test_no_style_without_examples() {
+        assert_eq!(DocumentStyle::sample("Just prose.\n\n- one item\n"), DocumentStyle::default());
+        assert_eq!(DocumentStyle::default().instruction(), None);
+        // Mixed markers: no consensus
+        assert_eq!(DocumentStyle::sample("- a\n* b\n+ c\n- d\n").bullet, None);
+    }
+
+    #[test]
+    fn test_line_kinds() {
+        assert_eq!(heading("## Title ##"), Some((2, "Title")));
+        assert_eq!(heading("#hashtag"), None);
+        assert_eq!(bullet("- item"), Some(('-', "item")));
+        assert_eq!(bullet("---"), None);
+        assert_eq!(bullet("* * *"), None);
+        assert_eq!(bullet("**bold** text"), None);
+        assert_eq!(numbered("12. item"), Some('.'));
+        assert_eq!(numbered("2024) was"), Some(')'));
+        assert_eq!(numbered("3.14 is pi"), None);
+        assert_eq!(heading_case("Ownership and Borrowing"), Some(HeadingCase::Title));
+        assert_eq!(heading_case("Ownership and borrowing"), Some(HeadingCase::Sentence));
+        assert_eq!(heading_case("Ownership"), None);
+    }
+}
@@ -0,0 +1,248 @@
+//! Prompt and task template rendering, free of filesystem and process access
+//! so it builds for wasm32 along with pattern detection
+
+/// Instruction used when no task is selected
+pub const DEFAULT_TEMPLATE: &str =
+    "Answer the following question by creating new slides that explain and elaborate on the concept.";
+
+/// Marks where a base task's template goes in an extending template
+pub const BASE_TEMPLATE_PLACEHOLDER: &str = "{{base}}";
+
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::sync::OnceLock;
+
+/// Deepest nesting of `{{include}}` directives
+const MAX_INCLUDE_DEPTH: usize = 8;
+
+/// One turn of an agent's conversation
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Message {
+    pub role: String,
+    pub content: String,
+}
+
+/// Combine a base task's template with an extending one: the base replaces
+/// `{{base}}`, or is prepended when there is no placeholder
+pub fn extend_template(base: &str, template: &str) -> String {
+    if template.contains(BASE_TEMPLATE_PLACEHOLDER) {
+        template.replace(BASE_TEMPLATE_PLACEHOLDER, base)
+    } else if template.trim().is_empty() {
+        base.to_string()
+    } else {
+        format!("{}\n\n{}", base, template)
+    }
+}
+
+/// `{{include "path"}}`, capturing the path
+fn include_re() -> &'static Regex {
+    static INCLUDE: OnceLock<Regex> = OnceLock::new();
+    INCLUDE.get_or_init(|| Regex::new(r#"\{\{\s*include\s+"([^"]+)"\s*\}\}"#).expect("valid include regex"))
+}
+
+/// `{{env.VAR}}`, capturing the variable name
+fn env_re() -> &'static Regex {
+    static ENV: OnceLock<Regex> = OnceLock::new();
+    ENV.get_or_init(|| Regex::new(r"\{\{\s*env\.([A-Za-z_][A-Za-z0-9_]*)\s*\}\}").expect("valid env regex"))
+}
+
+/// Replace every `{{env.VAR}}` in `text` with `lookup(VAR)`
+pub fn expand_env<F>(text: &str, lookup: &mut F) -> anyhow::Result<String>
+where
+    F: FnMut(&str) -> anyhow::Result<String>,
+{
+    let mut expanded = String::with_capacity(text.len());
+    let mut last = 0;
+    for caps in env_re().captures_iter(text) {
+        let directive = caps.get(0).expect("whole match");
+        expanded.push_str(&text[last..directive.start()]);
+        expanded.push_str(&lookup(&caps[1])?);
+        last = directive.end();
+    }
+    expanded.push_str(&text[last..]);
+    Ok(expanded)
+}
+
+/// Replace every `{{include "path"}}` in `text` with the included file's text,
+/// expanding includes inside included files too. `load` gets the path as written
+/// and the resolved name of the including file (none at the top level), and
+/// returns the included file's resolved name and text.
+pub fn expand_includes<F>(text: &str, load: &mut F) -> anyhow::Result<String>
+where
+    F: FnMut(&str, Option<&str>) -> anyhow::Result<(String, String)>,
+{
+    expand_nested(text, load, &mut Vec::new())
+}
+
+fn expand_nested<F>(text: &str, load: &mut F, stack: &mut Vec<String>) -> anyhow::Result<String>
+where
+    F: FnMut(&str, Option<&str>) -> anyhow::Result<(String, String)>,
+{
+    let mut expanded = String::with_capacity(text.len());
+    let mut last = 0;
+    for caps in include_re().captures_iter(text) {
+        let directive = caps.get(0).expect("whole match");
+        let (name, content) = load(&caps[1], stack.last().map(String::as_str))?;
+        if stack.contains(&name) {
+            stack.push(name);
+            return Err(anyhow::anyhow!("Include cycle: {}", stack.join(" -> ")));
+        }
+        if stack.len() >= MAX_INCLUDE_DEPTH {
+            return Err(anyhow::anyhow!(
+                "Includes nested more than {} deep at {}",
+                MAX_INCLUDE_DEPTH,
+                name
+            ));
+        }
+
+        stack.push(name);
+        let content = expand_nested(&content, load, stack)?;
+        stack.pop();
+
+        expanded.push_str(&text[last..directive.start()]);
+        expanded.push_str(content.trim_end_matches('\n'));
+        last = directive.end();
+    }
+    expanded.push_str(&text[last..]);
+    Ok(expanded)
+}
+
+/// The prompt for a question: earlier conversation, the task template, context
+/// files, the question, and the answer format instruction
+pub fn render_prompt(
+    template: &str,
+    history: &[Message],
+    context: &str,
+    question: &str,
+    instruction: &str,
+) -> String {
+    let mut prompt = String::new();
+
+    if !history.is_empty() {
+        prompt.push_str("## Previous Conversation:\n\n");
+        for msg in history {
+            prompt.push_str(&format!("{}: {}\n\n", msg.role, msg.content));
+        }
+        prompt.push_str("---\n\n");
+    }
+
+    prompt.push_str(&format!(
+        "{}\n\nContext:\n{}\n\nQuestion: {}\n\n{}",
+        template, context, question, instruction
+    ));
+
+    prompt
+}
+
+/// The prompt for a resumed session, which already holds the context and conversation
+pub fn render_followup(question: &str, instruction: &str) -> String {
+    format!("Question: {}\n\n{}", question, instruction)
+}
+
+/// Instruction appended to the prompt so the answer is written in `lang`
+pub fn language_instruction(lang: &str) -> String {
+    match language_name(lang) {
+        Some(name) => format!("\n\nWrite the entire answer in {} ({}).", name, lang),
+        None => format!("\n\nWrite the entire answer in the language with code '{}'.", lang),
+    }
+}
+
+/// English name of a common language code, e.g. `ja` -> Japanese
+pub fn language_name(lang: &str) -> Option<&'static str> {
+    let name = match lang.to_lowercase().as_str() {
+        "en" => "English",
+        "ja" => "Japanese",
+        "zh" | "zh-cn" | "zh-hans" => "Simplified Chinese",
+        "zh-tw" | "zh-hant" => "Traditional Chinese",
+        "ko" => "Korean",
+        "fr" => "French",
+        "de" => "German",
+        "es" => "Spanish",
+        "it" => "Italian",
+        "pt" => "Portuguese",
+        "ru" => "Russian",
+        _ => return None,
+    };
+    Some(name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_extend_template() {
+        assert_eq!(extend_template("Base.", "Intro.\n{{base}}\nOutro."), "Intro.\nBase.\nOutro.");
+        assert_eq!(extend_template("Base.", "More."), "Base.\n\nMore.");
+        assert_eq!(extend_template("Base.", "  "), "Base.");
+    }
+
+    #[test]
+    fn test_render_prompt() {
+        let history = vec![
+            Message {
+                role: "User".to_string(),
+                content: "What is Rust?".to_string(),
+            },
+            Message {
+                role: "Assistant".to_string(),
+                content: "A language".to_string(),
+            },
+        ];
+        let prompt = render_prompt("Explain.", &history, "notes", "And Go?", "Use Markdown.");
+
+        assert!(prompt.starts_with("## Previous Conversation:\n\nUser: What is Rust?"));
+        assert!(prompt.ends_with("Explain.\n\nContext:\nnotes\n\nQuestion: And Go?\n\nUse Markdown."));
+        assert_eq!(render_followup("And Go?", "Use Markdown."), "Question: And Go?\n\nUse Markdown.");
+    }
+
+    #[test]
+    fn test_expand_includes() {
+        let files = [
+            ("style.md", "Be brief.\n{{ include \"tone.md\" }}\n"),
+            ("tone.md", "Stay friendly."),
+            ("loop.md", "{{include \"loop.md\"}}"),
+        ];
+        let mut load = |path: &str, _: Option<&str>| {
+            files
+                .iter()
+                .find(|(name, _)| *name == path)
+                .map(|(name, text)| (name.to_string(), text.to_string()))
+                .ok_or_else(|| anyhow::anyhow!("no such file: {}", path))
+        };
+
+        let prompt = "You write slides.\n{{include \"style.md\"}}\nEnd.";
+        assert_eq!(
+            expand_includes(prompt, &mut load).unwrap(),
+            "You write slides.\nBe brief.\nStay friendly.\nEnd."
+        );
+        assert_eq!(expand_includes("No includes {{base}}", &mut load).unwrap(), "No includes {{base}}");
+
+        let err = expand_includes("{{include \"loop.md\"}}", &mut load).unwrap_err();
+        assert_eq!(err.to_string(), "Include cycle: loop.md -> loop.md");
+        assert!(expand_includes("{{include \"gone.md\"}}", &mut load).is_err());
+    }
+
+    #[test]
+    fn test_expand_env() {
+        let mut lookup = |name: &str| match name {
+            "PROJECT" => Ok("ecce".to_string()),
+            other => Err(anyhow::anyhow!("{} is not set", other)),
+        };
+        assert_eq!(
+            expand_env("Slides for {{env.PROJECT}} ({{ env.PROJECT }}).", &mut lookup).unwrap(),
+            "Slides for ecce (ecce)."
+        );
+        assert_eq!(expand_env("{{base}} {{env.}}", &mut lookup).unwrap(), "{{base}} {{env.}}");
+        assert!(expand_env("{{env.TICKET}}", &mut lookup).is_err());
+    }
+
+    #[test]
+    fn test_language_instruction() {
+        assert_eq!(
+            language_instruction("ja"),
+            "\n\nWrite the entire answer in Japanese (ja)."
+        );
+        assert!(language_instruction("tlh").contains("code 'tlh'"));
+    }
+}
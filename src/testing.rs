@@ -0,0 +1,301 @@
+//! Test support: a scripted stand-in for the `claude` executable, so tests can
+//! run the whole pipeline (detect, placeholder, generate, replace) without
+//! Claude Code or a network. Built with the `test-support` feature; Unix only,
+//! since the stand-in is a shell script.
+//!
+//! ```no_run
+//! # fn main() -> anyhow::Result<()> {
+//! use ecce::testing::FakeClaude;
+//!
+//! let claude = FakeClaude::builder()
+//!     .answer("Ownership moves values.")
+//!     .answer_when("borrow", "References borrow values.")
+//!     .build()?;
+//! let mut config = ecce::Config::default();
+//! claude.install(&mut config);
+//! // ... run ecce against `config` ...
+//! assert!(claude.calls()[0].stdin.contains("What is ownership?"));
+//! # Ok(())
+//! # }
+//! ```
+
+use anyhow::{Context, Result};
+use std::fs;
+use std::os::unix::fs::PermissionsExt;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tempfile::TempDir;
+
+use crate::capability::Capability;
+use crate::config::{Agent, Config};
+
+/// Which calls a reply is for
+#[derive(Debug, Clone)]
+enum When {
+    /// The prompt on stdin contains this text
+    Contains(String),
+    /// The nth call, from 1
+    Call(usize),
+}
+
+#[derive(Debug, Clone)]
+struct Reply {
+    text: String,
+    /// Non-zero: print `text` to stderr and exit with this code
+    exit_code: i32,
+}
+
+/// Builds a [`FakeClaude`]; the first matching rule answers a call, else the default
+#[derive(Debug, Clone)]
+pub struct FakeClaudeBuilder {
+    rules: Vec<(When, Reply)>,
+    default: Reply,
+    delay: Option<Duration>,
+    version: String,
+    /// Flags left out of `claude --help`
+    unlisted: Vec<Capability>,
+}
+
+impl FakeClaudeBuilder {
+    /// Answer for calls no rule matches (default: "Fake answer")
+    pub fn answer(mut self, text: &str) -> Self {
+        self.default = Reply {
+            text: text.to_string(),
+            exit_code: 0,
+        };
+        self
+    }
+
+    /// Answer prompts containing `needle` with `text`
+    pub fn answer_when(mut self, needle: &str, text: &str) -> Self {
+        self.rules.push((When::Contains(needle.to_string()), Reply { text: text.to_string(), exit_code: 0 }));
+        self
+    }
+
+    /// Answer the nth call (from 1) with `text`
+    pub fn answer_on_call(mut self, call: usize, text: &str) -> Self {
+        self.rules.push((When::Call(call), Reply { text: text.to_string(), exit_code: 0 }));
+        self
+    }
+
+    /// Fail the nth call (from 1): `stderr` is printed and the process exits with `exit_code`
+    pub fn fail_on_call(mut self, call: usize, exit_code: i32, stderr: &str) -> Self {
+        self.rules.push((When::Call(call), Reply { text: stderr.to_string(), exit_code }));
+        self
+    }
+
+    /// Fail prompts containing `needle`
+    pub fn fail_when(mut self, needle: &str, exit_code: i32, stderr: &str) -> Self {
+        self.rules.push((When::Contains(needle.to_string()), Reply { text: stderr.to_string(), exit_code }));
+        self
+    }
+
+    /// Version reported to `claude --version` (default: 2.0.0); version
+    /// queries aren't recorded as calls
+    pub fn version(mut self, version: &str) -> Self {
+        self.version = version.to_string();
+        self
+    }
+
+    /// Leave a flag out of `claude --help`, to emulate an older CLI that rejects it
+    pub fn without(mut self, capability: Capability) -> Self {
+        self.unlisted.push(capability);
+        self
+    }
+
+    /// Wait this long before answering, e.g. to see a placeholder in the file
+    pub fn delay(mut self, delay: Duration) -> Self {
+        self.delay = Some(delay);
+        self
+    }
+
+    /// Write the script and its replies to a temporary directory
+    pub fn build(self) -> Result<FakeClaude> {
+        let dir = TempDir::new().context("Failed to create a directory for the fake claude")?;
+        let root = dir.path();
+        let quoted = |path: &Path| format!("'{}'", path.to_string_lossy().replace('\'', r"'\''"));
+
+        let replies: Vec<&Reply> = self.rules.iter().map(|(_, r)| r).chain([&self.default]).collect();
+        for (i, reply) in replies.iter().enumerate() {
+            fs::write(root.join(format!("reply-{}.txt", i)), &reply.text)?;
+            let json = serde_json::json!({
+                "result": reply.text,
+                "session_id": "fake-session",
+                "is_error": false,
+            });
+            fs::write(root.join(format!("reply-{}.json", i)), json.to_string())?;
+            // Streamed a word at a time, then the same result line as plain JSON output
+            let mut stream = String::new();
+            for word in reply.text.split_inclusive(' ') {
+                let delta = serde_json::json!({
+                    "type": "stream_event",
+                    "event": {"type": "content_block_delta", "index": 0, "delta": {"type": "text_delta", "text": word}},
+                });
+                stream.push_str(&format!("{}\n", delta));
+            }
+            let mut result = json.clone();
+            result["type"] = "result".into();
+            stream.push_str(&format!("{}\n", result));
+            fs::write(root.join(format!("reply-{}.stream", i)), stream)?;
+            fs::write(root.join(format!("reply-{}.exit", i)), reply.exit_code.to_string())?;
+        }
+
+        let help: String = Capability::ALL
+            .into_iter()
+            .filter(|c| !self.unlisted.contains(c))
+            .map(|c| format!("  {}\n", c.flag()))
+            .collect();
+        fs::write(root.join("help"), help)?;
+
+        let mut pick = String::new();
+        for (i, (when, _)) in self.rules.iter().enumerate() {
+            let test = match when {
+                When::Contains(needle) => {
+                    let file = root.join(format!("needle-{}.txt", i));
+                    fs::write(&file, needle)?;
+                    format!("grep -qF -f {} \"$calls/$n.stdin\"", quoted(&file))
+                }
+                When::Call(call) => format!("[ \"$n\" = {} ]", call),
+            };
+            pick.push_str(&format!("if [ -z \"$reply\" ] && {}; then reply={}; fi\n", test, i));
+        }
+        let delay = self
+            .delay
+            .map(|d| format!("sleep {}\n", d.as_secs_f64()))
+            .unwrap_or_default();
+
+        let script = format!(
+            r#"#!/bin/sh
+# Fake claude for ecce tests: records each call, then answers from the replies next to it
+root={root}
+if [ "$1" = --version ]; then echo '{version} (Claude Code)'; exit 0; fi
+if [ "$1" = --help ]; then cat "$root/help"; exit 0; fi
+calls="$root/calls"
+mkdir -p "$calls"
+n=$(( $(cat "$root/count" 2>/dev/null || echo 0) + 1 ))
+echo "$n" > "$root/count"
+for arg in "$@"; do printf '%s\n' "$arg"; done > "$calls/$n.args"
+cat > "$calls/$n.stdin"
+json=
+stream=
+for arg in "$@"; do [ "$arg" = json ] && json=1; [ "$arg" = stream-json ] && stream=1; done
+reply=
+{pick}[ -z "$reply" ] && reply={default}
+{delay}code=$(cat "$root/reply-$reply.exit")
+if [ "$code" != 0 ]; then cat "$root/reply-$reply.txt" >&2; exit "$code"; fi
+if [ -n "$stream" ]; then cat "$root/reply-$reply.stream"
+elif [ -n "$json" ]; then cat "$root/reply-$reply.json"; else cat "$root/reply-$reply.txt"; echo; fi
+"#,
+            root = quoted(root),
+            pick = pick,
+            default = self.rules.len(),
+            delay = delay,
+            version = self.version.replace('\'', ""),
+        );
+        let path = root.join("claude");
+        fs::write(&path, script)?;
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o755))?;
+        Ok(FakeClaude { dir, path })
+    }
+}
+
+/// One invocation of the fake claude
+#[derive(Debug, Clone, PartialEq)]
+pub struct Call {
+    pub args: Vec<String>,
+    /// The prompt, as sent on stdin
+    pub stdin: String,
+}
+
+/// A fake `claude` executable; removed when dropped
+pub struct FakeClaude {
+    dir: TempDir,
+    path: PathBuf,
+}
+
+impl FakeClaude {
+    pub fn builder() -> FakeClaudeBuilder {
+        FakeClaudeBuilder {
+            rules: Vec::new(),
+            default: Reply {
+                text: "Fake answer".to_string(),
+                exit_code: 0,
+            },
+            delay: None,
+            version: "2.0.0".to_string(),
+            unlisted: Vec::new(),
+        }
+    }
+
+    /// A fake that answers every call with `text`
+    pub fn answering(text: &str) -> Result<Self> {
+        Self::builder().answer(text).build()
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Path to put in `claude_executable`
+    pub fn executable(&self) -> String {
+        self.path.to_string_lossy().to_string()
+    }
+
+    /// Point `config` at this fake and give it a default agent `writer` if it has none
+    pub fn install(&self, config: &mut Config) {
+        config.claude_executable = Some(self.executable());
+        if config.default_agent.is_none() {
+            config.agents.entry("writer".to_string()).or_insert_with(|| Agent {
+                name: "writer".to_string(),
+                system_prompt: "You write slides.".to_string(),
+                ..Default::default()
+            });
+            config.default_agent = Some("writer".to_string());
+        }
+    }
+
+    /// Every call so far, in order
+    pub fn calls(&self) -> Vec<Call> {
+        let calls = self.dir.path().join("calls");
+        (1..)
+            .map_while(|n| {
+                let stdin = fs::read_to_string(calls.join(format!("{}.stdin", n))).ok()?;
+                let args = fs::read_to_string(calls.join(format!("{}.args", n))).unwrap_or_default();
+                Some(Call {
+                    args: args.lines().map(str::to_string).collect(),
+                    stdin,
+                })
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::agent::ClaudeAgent;
+
+    #[tokio::test]
+    async fn test_fake_claude() {
+        let claude = FakeClaude::builder()
+            .answer("Default")
+            .answer_when("borrow", "It's about \"borrowing\"")
+            .fail_on_call(3, 2, "rate limited")
+            .build()
+            .unwrap();
+        let mut config = Config::default();
+        claude.install(&mut config);
+        let agent = config.resolve_agent("writer").unwrap();
+        let mut agent = ClaudeAgent::new(config.get_claude_executable(), agent, None);
+
+        assert_eq!(agent.generate_response("What is ownership?").await.unwrap(), "Default");
+        assert_eq!(agent.generate_response("What is borrowing?").await.unwrap(), "It's about \"borrowing\"");
+        let err = agent.generate_response("And lifetimes?").await.unwrap_err();
+        assert!(err.to_string().contains("rate limited"));
+
+        let calls = claude.calls();
+        assert_eq!(calls.len(), 3);
+        assert!(calls[1].stdin.contains("What is borrowing?"));
+        assert!(calls[1].args.ends_with(&["--resume".to_string(), "fake-session".to_string()]));
+    }
+}
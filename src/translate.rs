@@ -0,0 +1,142 @@
+//! Translation pass: after an answer is generated, a quick call to a small
+//! model translates it, so the same deck can be kept in two languages
+
+use anyhow::{Context, Result};
+
+use crate::agent::RunOptions;
+use crate::config::{Agent, Config, Profile, Translation};
+use crate::side_pass::SidePass;
+use crate::template::language_name;
+
+/// Model used for translations unless the settings name another
+pub const DEFAULT_TRANSLATION_MODEL: &str = "haiku";
+
+const TRANSLATOR_SYSTEM_PROMPT: &str = "You translate documents. Keep the Markdown structure, \
+code blocks, inline code, links, HTML comments, and [source: <path>] tags exactly as they are; \
+translate only the prose. Reply with the translation and nothing else.";
+
+/// Label between the original and its translation; not an `<!-- ecce: -->`
+/// directive, which would set options for the whole file
+const BILINGUAL_LABEL: &str = "ecce-translation";
+
+/// Translates answers into one language
+pub struct Translator {
+    pass: SidePass,
+    settings: Translation,
+}
+
+impl Translator {
+    pub fn new(config: &Config, settings: Translation, options: RunOptions) -> Self {
+        Self {
+            pass: SidePass::new(config, options),
+            settings,
+        }
+    }
+
+    /// Translate through this profile's endpoint and key (see `ClaudeAgent::use_profile`)
+    pub fn use_profile(&mut self, profile: &Profile) {
+        self.pass.use_profile(profile);
+    }
+
+    pub fn settings(&self) -> &Translation {
+        &self.settings
+    }
+
+    /// The language's name, or its code when it isn't a common one
+    pub fn language(&self) -> &str {
+        language_name(&self.settings.lang).unwrap_or(&self.settings.lang)
+    }
+
+    /// The answer as it should be written: its translation, or both for a bilingual layout
+    pub async fn translate(&self, answer: &str) -> Result<String> {
+        let translator = Agent {
+            name: "translator".to_string(),
+            system_prompt: TRANSLATOR_SYSTEM_PROMPT.to_string(),
+            model: Some(
+                self.settings
+                    .model
+                    .clone()
+                    .unwrap_or_else(|| DEFAULT_TRANSLATION_MODEL.to_string()),
+            ),
+            ..Default::default()
+        };
+        let translation = self
+            .pass
+            .ask(&translator, &translation_prompt(self.language(), answer))
+            .await
+            .with_context(|| format!("Failed to translate the answer into {}", self.language()))?;
+        let translation = translation.trim();
+        if translation.is_empty() {
+            return Err(anyhow::anyhow!("The translation into {} came back empty", self.language()));
+        }
+        Ok(match self.settings.bilingual {
+            true => bilingual(answer, translation, &self.settings.lang),
+            false => translation.to_string(),
+        })
+    }
+}
+
+/// The translation request for one answer
+pub fn translation_prompt(language: &str, answer: &str) -> String {
+    format!(
+        "Translate this into {}:\n\n<document>\n{}\n</document>",
+        language,
+        answer.trim()
+    )
+}
+
+/// The original answer, then its translation under an invisible label
+pub fn bilingual(answer: &str, translation: &str, lang: &str) -> String {
+    format!("{}\n\n<!-- {} {} -->\n\n{}", answer.trim_end(), BILINGUAL_LABEL, lang, translation)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_translation_layouts() {
+        assert_eq!(
+            translation_prompt("Japanese", "# Ownership\n"),
+            "Translate this into Japanese:\n\n<document>\n# Ownership\n</document>"
+        );
+        assert_eq!(
+            bilingual("# Ownership\n", "# 所有権", "ja"),
+            "# Ownership\n\n<!-- ecce-translation ja -->\n\n# 所有権"
+        );
+        // The label doesn't shadow the file's own `<!-- ecce: -->` options
+        let text = format!("{}\n\n<!-- ecce: lang=fr -->\n\necce why? ecce", bilingual("A.", "あ", "ja"));
+        let patterns = crate::pattern::PatternDetector::new().detect_patterns(&text);
+        assert_eq!(patterns[0].options.lang.as_deref(), Some("fr"));
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_translate() {
+        let claude = crate::testing::FakeClaude::answering("# 所有権\n").unwrap();
+        let mut config = Config::default();
+        claude.install(&mut config);
+        let settings = Translation {
+            lang: "ja".to_string(),
+            ..Default::default()
+        };
+
+        let translator = Translator::new(&config, settings.clone(), RunOptions::default());
+        assert_eq!(translator.language(), "Japanese");
+        assert_eq!(translator.translate("# Ownership").await.unwrap(), "# 所有権");
+        let call = &claude.calls()[0];
+        assert!(call.stdin.contains("Translate this into Japanese:"));
+        let model = call.args.iter().position(|a| a == "--model").unwrap();
+        assert_eq!(call.args[model + 1], DEFAULT_TRANSLATION_MODEL);
+
+        let settings = Translation {
+            bilingual: true,
+            ..settings
+        };
+        let translator = Translator::new(&config, settings, RunOptions::default());
+        assert_eq!(
+            translator.translate("# Ownership").await.unwrap(),
+            "# Ownership\n\n<!-- ecce-translation ja -->\n\n# 所有権"
+        );
+    }
+}
@@ -0,0 +1,500 @@
+use std::borrow::Cow;
+use std::io::{IsTerminal, Write};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::OnceLock;
+use std::time::{Duration, Instant};
+use tokio::task::JoinHandle;
+use unicode_segmentation::UnicodeSegmentation;
+use unicode_width::UnicodeWidthStr;
+
+static PLAIN: AtomicBool = AtomicBool::new(false);
+static STATUS_TO_STDERR: AtomicBool = AtomicBool::new(false);
+static QUIET: AtomicBool = AtomicBool::new(false);
+static LANG: OnceLock<Lang> = OnceLock::new();
+
+/// Set up output mode for the process. Plain mode (from `--plain` or
+/// `ECCE_PLAIN=1`) disables color, emoji, and box-drawing; `NO_COLOR`
+/// disables color only.
+pub fn init(plain: bool) {
+    let plain = plain || env_flag("ECCE_PLAIN");
+    PLAIN.store(plain, Ordering::Relaxed);
+
+    if plain || std::env::var_os("NO_COLOR").is_some_and(|v| !v.is_empty()) {
+        colored::control::set_override(false);
+    }
+}
+
+fn env_flag(name: &str) -> bool {
+    matches!(
+        std::env::var(name).as_deref(),
+        Ok("1") | Ok("true") | Ok("yes")
+    )
+}
+
+pub fn is_plain() -> bool {
+    PLAIN.load(Ordering::Relaxed)
+}
+
+/// Send `ui_println!`/`ui_print!` output to stderr, keeping stdout for data
+/// (e.g. `ecce homo --stdout`)
+pub fn status_to_stderr() {
+    STATUS_TO_STDERR.store(true, Ordering::Relaxed);
+}
+
+pub fn is_status_on_stderr() -> bool {
+    STATUS_TO_STDERR.load(Ordering::Relaxed)
+}
+
+/// Quiet mode (from `-q/--quiet` or `ECCE_QUIET=1`): only results and errors are printed
+pub fn set_quiet(quiet: bool) {
+    QUIET.store(quiet || env_flag("ECCE_QUIET"), Ordering::Relaxed);
+}
+
+pub fn is_quiet() -> bool {
+    QUIET.load(Ordering::Relaxed)
+}
+
+/// Quiet mode until the guard is dropped, then whatever it was before, even
+/// when the caller returns early with an error
+pub fn quiet_until_dropped() -> QuietGuard {
+    QuietGuard {
+        was: QUIET.swap(true, Ordering::Relaxed),
+    }
+}
+
+pub struct QuietGuard {
+    was: bool,
+}
+
+impl Drop for QuietGuard {
+    fn drop(&mut self) {
+        QUIET.store(self.was, Ordering::Relaxed);
+    }
+}
+
+/// How important a piece of output is
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Severity {
+    /// Banners, progress, and confirmations (`ui_println!`, `ui_print!`); dropped in quiet mode
+    Info,
+    /// What the user asked for: listings, reports, interactive prompts (`ui_result!`, `ui_prompt!`)
+    Result,
+    /// Errors and warnings (`ui_eprintln!`)
+    Error,
+}
+
+/// Whether output of this severity is printed at the current verbosity
+pub fn shows(severity: Severity) -> bool {
+    shown_when(severity, is_quiet())
+}
+
+fn shown_when(severity: Severity, quiet: bool) -> bool {
+    severity != Severity::Info || !quiet
+}
+
+/// Adapt a line of user-facing output to the current mode
+pub fn text(s: &str) -> Cow<'_, str> {
+    if is_plain() {
+        Cow::Owned(to_plain(s))
+    } else {
+        Cow::Borrowed(s)
+    }
+}
+
+/// ASCII stand-ins for the symbols ecce uses; other emoji are dropped
+fn plain_symbol(c: char) -> Option<&'static str> {
+    match c {
+        '✓' | '✅' => Some("[ok]"),
+        '✗' | '❌' => Some("[error]"),
+        '⚠' => Some("[warn]"),
+        '→' | '▶' => Some(">"),
+        '★' => Some("*"),
+        '↑' => Some("Up"),
+        '↓' => Some("Down"),
+        '═' => Some("="),
+        '─' => Some("-"),
+        '…' => Some("..."),
+        _ => None,
+    }
+}
+
+fn is_decoration(c: char) -> bool {
+    matches!(c as u32,
+        0x1F000..=0x1FAFF // emoji and pictographs
+        | 0x2600..=0x27BF // misc symbols and dingbats
+        | 0x2B00..=0x2BFF // misc symbols and arrows
+        | 0xFE0F          // emoji variation selector
+        | 0x200D          // zero-width joiner
+    )
+}
+
+/// Replace known symbols with ASCII and strip remaining emoji (with the
+/// space that followed them at the start of a word)
+pub fn to_plain(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    let mut skip_space = false;
+
+    for c in s.chars() {
+        if let Some(replacement) = plain_symbol(c) {
+            out.push_str(replacement);
+            skip_space = false;
+        } else if is_decoration(c) {
+            skip_space = out.is_empty() || out.ends_with(char::is_whitespace);
+        } else if c == ' ' && skip_space {
+            skip_space = false;
+        } else {
+            out.push(c);
+            skip_space = false;
+        }
+    }
+
+    out
+}
+
+/// Status `println!`: respects plain mode and status redirection, silent in quiet mode
+#[macro_export]
+macro_rules! ui_println {
+    () => {
+        if $crate::ui::shows($crate::ui::Severity::Info) {
+            if $crate::ui::is_status_on_stderr() { eprintln!() } else { println!() }
+        }
+    };
+    ($($arg:tt)*) => {{
+        if $crate::ui::shows($crate::ui::Severity::Info) {
+            let line = $crate::ui::text(&format!($($arg)*)).into_owned();
+            if $crate::ui::is_status_on_stderr() { eprintln!("{}", line) } else { println!("{}", line) }
+        }
+    }};
+}
+
+/// Result `println!`: always printed (to stdout), respects plain mode
+#[macro_export]
+macro_rules! ui_result {
+    () => { println!() };
+    ($($arg:tt)*) => { println!("{}", $crate::ui::text(&format!($($arg)*))) };
+}
+
+/// `eprintln!` that respects plain mode
+#[macro_export]
+macro_rules! ui_eprintln {
+    () => { eprintln!() };
+    ($($arg:tt)*) => { eprintln!("{}", $crate::ui::text(&format!($($arg)*))) };
+}
+
+/// Status `print!`: respects plain mode and status redirection, silent in quiet mode
+#[macro_export]
+macro_rules! ui_print {
+    ($($arg:tt)*) => {{
+        if $crate::ui::shows($crate::ui::Severity::Info) {
+            let text = $crate::ui::text(&format!($($arg)*)).into_owned();
+            if $crate::ui::is_status_on_stderr() { eprint!("{}", text) } else { print!("{}", text) }
+        }
+    }};
+}
+
+/// Interactive `print!` (a question awaiting input): always printed, respects
+/// plain mode and status redirection
+#[macro_export]
+macro_rules! ui_prompt {
+    ($($arg:tt)*) => {{
+        let text = $crate::ui::text(&format!($($arg)*)).into_owned();
+        if $crate::ui::is_status_on_stderr() { eprint!("{}", text) } else { print!("{}", text) }
+    }};
+}
+
+const SPINNER_FRAMES: &[char] = &['⠋', '⠙', '⠹', '⠸', '⠼', '⠴', '⠦', '⠧', '⠇', '⠏'];
+
+/// The stream status output currently goes to
+fn status_stream() -> Box<dyn Write> {
+    if is_status_on_stderr() {
+        Box::new(std::io::stderr())
+    } else {
+        Box::new(std::io::stdout())
+    }
+}
+
+fn status_is_terminal() -> bool {
+    if is_status_on_stderr() {
+        std::io::stderr().is_terminal()
+    } else {
+        std::io::stdout().is_terminal()
+    }
+}
+
+/// In-place progress line with elapsed seconds. Falls back to a single static
+/// line in plain mode or when the status stream is not a terminal.
+pub struct Spinner {
+    started: Instant,
+    ticker: Option<JoinHandle<()>>,
+}
+
+impl Spinner {
+    /// Start showing `message`; must be called inside a tokio runtime
+    pub fn start(message: String) -> Self {
+        let started = Instant::now();
+
+        if !shows(Severity::Info) {
+            return Self {
+                started,
+                ticker: None,
+            };
+        }
+
+        if is_plain() || !status_is_terminal() {
+            let _ = writeln!(status_stream(), "  {}", text(&message));
+            return Self {
+                started,
+                ticker: None,
+            };
+        }
+
+        let ticker = tokio::spawn(async move {
+            let mut interval = tokio::time::interval(Duration::from_millis(100));
+            let mut frame = 0;
+            loop {
+                interval.tick().await;
+                let mut stream = status_stream();
+                let _ = write!(
+                    stream,
+                    "\r\x1b[2K  {} {} {}",
+                    SPINNER_FRAMES[frame % SPINNER_FRAMES.len()],
+                    message,
+                    format_elapsed(started.elapsed())
+                );
+                let _ = stream.flush();
+                frame += 1;
+            }
+        });
+
+        Self {
+            started,
+            ticker: Some(ticker),
+        }
+    }
+
+    /// Stop the spinner, clear its line, and return the elapsed time
+    pub fn stop(self) -> Duration {
+        if let Some(ticker) = self.ticker {
+            ticker.abort();
+            let mut stream = status_stream();
+            let _ = write!(stream, "\r\x1b[2K");
+            let _ = stream.flush();
+        }
+        self.started.elapsed()
+    }
+}
+
+/// Format a duration as seconds with one decimal, e.g. "4.2s"
+pub fn format_elapsed(elapsed: Duration) -> String {
+    format!("{:.1}s", elapsed.as_secs_f64())
+}
+
+/// Terminal columns `s` occupies (wide CJK characters count as two)
+pub fn display_width(s: &str) -> usize {
+    s.width()
+}
+
+/// Cut `s` to at most `max_width` columns without splitting a character,
+/// ending with "..." when anything was removed
+pub fn truncate(s: &str, max_width: usize) -> String {
+    if s.width() <= max_width {
+        return s.to_string();
+    }
+    let budget = max_width.saturating_sub(3);
+    let mut out = String::new();
+    let mut width = 0;
+    for grapheme in s.graphemes(true) {
+        width += grapheme.width();
+        if width > budget {
+            break;
+        }
+        out.push_str(grapheme);
+    }
+    out.push_str("...");
+    out
+}
+
+/// The first `count` characters (graphemes) of `s`, e.g. the visible start of an API key
+pub fn prefix(s: &str, count: usize) -> &str {
+    match s.grapheme_indices(true).nth(count) {
+        Some((end, _)) => &s[..end],
+        None => s,
+    }
+}
+
+/// First line of `text` within `max_width` columns, with "..." if anything was left out
+pub fn preview(text: &str, max_width: usize) -> String {
+    let first_line = text.lines().next().unwrap_or("");
+    let shown = truncate(first_line, max_width);
+    if shown == first_line && first_line.len() < text.trim_end().len() {
+        format!("{}...", first_line)
+    } else {
+        shown
+    }
+}
+
+/// Languages with a message catalog
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Lang {
+    En,
+    Zh,
+}
+
+impl Lang {
+    /// Pick the language from `ECCE_LANG`, then `LANG`, defaulting to English
+    pub fn detect() -> Self {
+        let value = std::env::var("ECCE_LANG")
+            .or_else(|_| std::env::var("LANG"))
+            .unwrap_or_default()
+            .to_lowercase();
+
+        if value.starts_with("zh") {
+            Lang::Zh
+        } else {
+            Lang::En
+        }
+    }
+}
+
+pub fn lang() -> Lang {
+    *LANG.get_or_init(Lang::detect)
+}
+
+/// Catalog keys for localizable output strings
+#[derive(Debug, Clone, Copy)]
+pub enum Msg {
+    WatcherStarted,
+    File,
+    Agent,
+    Compare,
+    Task,
+    WatchingForPatterns,
+    PressToStop,
+    FoundPatterns,
+    Generating,
+    Replacing,
+    Success,
+    Error,
+    ContinueWatching,
+    Goodbye,
+}
+
+/// Look up a message in the given language. `{}` marks an argument slot for `trf`.
+pub fn message(lang: Lang, msg: Msg) -> &'static str {
+    match (lang, msg) {
+        (Lang::En, Msg::WatcherStarted) => "🎭 Ecce Homo - File Watcher Started",
+        (Lang::En, Msg::File) => "File",
+        (Lang::En, Msg::Agent) => "Agent",
+        (Lang::En, Msg::Compare) => "Compare",
+        (Lang::En, Msg::Task) => "Task",
+        (Lang::En, Msg::WatchingForPatterns) => "👀 Watching for patterns...",
+        (Lang::En, Msg::PressToStop) => "Press {} to stop",
+        (Lang::En, Msg::FoundPatterns) => "🔍 Found {} new pattern(s)",
+        (Lang::En, Msg::Generating) => "🤖 Generating response...",
+        (Lang::En, Msg::Replacing) => "📝 Replacing with response...",
+        (Lang::En, Msg::Success) => "✅ Success",
+        (Lang::En, Msg::Error) => "❌ Error:",
+        (Lang::En, Msg::ContinueWatching) => "👀 Continuing to watch...",
+        (Lang::En, Msg::Goodbye) => "👋 Stopped watching file. Goodbye!",
+
+        (Lang::Zh, Msg::WatcherStarted) => "🎭 Ecce Homo - 文件监视已启动",
+        (Lang::Zh, Msg::File) => "文件",
+        (Lang::Zh, Msg::Agent) => "智能体",
+        (Lang::Zh, Msg::Compare) => "对比",
+        (Lang::Zh, Msg::Task) => "任务",
+        (Lang::Zh, Msg::WatchingForPatterns) => "👀 正在监视模式...",
+        (Lang::Zh, Msg::PressToStop) => "按 {} 停止",
+        (Lang::Zh, Msg::FoundPatterns) => "🔍 发现 {} 个新模式",
+        (Lang::Zh, Msg::Generating) => "🤖 正在生成回复...",
+        (Lang::Zh, Msg::Replacing) => "📝 正在写入回复...",
+        (Lang::Zh, Msg::Success) => "✅ 成功",
+        (Lang::Zh, Msg::Error) => "❌ 错误:",
+        (Lang::Zh, Msg::ContinueWatching) => "👀 继续监视...",
+        (Lang::Zh, Msg::Goodbye) => "👋 已停止监视文件。再见！",
+    }
+}
+
+/// Translate a message into the detected language
+pub fn tr(msg: Msg) -> &'static str {
+    message(lang(), msg)
+}
+
+/// Translate a message and fill its `{}` slots in order
+pub fn trf(msg: Msg, args: &[&str]) -> String {
+    fill(tr(msg), args)
+}
+
+fn fill(template: &str, args: &[&str]) -> String {
+    let mut out = template.to_string();
+    for arg in args {
+        out = out.replacen("{}", arg, 1);
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_truncate_respects_width_and_graphemes() {
+        assert_eq!(truncate("short", 10), "short");
+        assert_eq!(truncate("abcdefghij", 8), "abcde...");
+        // Wide characters take two columns each
+        assert_eq!(truncate("日本語のスライド", 9), "日本語...");
+        assert_eq!(display_width("日本語"), 6);
+        // Never split a combined emoji
+        assert_eq!(truncate("👩‍💻👩‍💻👩‍💻x", 6), "👩‍💻...");
+    }
+
+    #[test]
+    fn test_prefix_and_preview() {
+        assert_eq!(prefix("sk-ant-api03-xyz", 8), "sk-ant-a");
+        assert_eq!(prefix("ключ", 8), "ключ");
+        assert_eq!(prefix("ключ-секрет", 4), "ключ");
+
+        assert_eq!(preview("one line", 20), "one line");
+        assert_eq!(preview("first\nsecond", 20), "first...");
+        assert_eq!(preview("説明文です\n", 6), "説...");
+        assert_eq!(preview("", 10), "");
+    }
+
+    #[test]
+    fn test_to_plain_replaces_symbols() {
+        assert_eq!(to_plain("✓ Profile 'dev' added"), "[ok] Profile 'dev' added");
+        assert_eq!(to_plain("═══"), "===");
+        assert_eq!(to_plain("(↑/↓: navigate)"), "(Up/Down: navigate)");
+    }
+
+    #[test]
+    fn test_to_plain_strips_emoji() {
+        assert_eq!(to_plain("🎭 Ecce Homo"), "Ecce Homo");
+        assert_eq!(to_plain("  📄 File:     slides.md"), "  File:     slides.md");
+        assert_eq!(to_plain("  ⚖️  Compare"), "   Compare");
+        assert_eq!(to_plain("plain text"), "plain text");
+    }
+
+    #[test]
+    fn test_format_elapsed() {
+        assert_eq!(format_elapsed(Duration::from_millis(4230)), "4.2s");
+        assert_eq!(format_elapsed(Duration::from_millis(0)), "0.0s");
+    }
+
+    #[test]
+    fn test_quiet_keeps_results_and_errors() {
+        assert!(!shown_when(Severity::Info, true));
+        assert!(shown_when(Severity::Result, true));
+        assert!(shown_when(Severity::Error, true));
+        assert!(shown_when(Severity::Info, false));
+    }
+
+    #[test]
+    fn test_message_catalog() {
+        assert_eq!(message(Lang::En, Msg::Task), "Task");
+        assert_eq!(message(Lang::Zh, Msg::Task), "任务");
+        assert_eq!(
+            fill(message(Lang::En, Msg::FoundPatterns), &["3"]),
+            "🔍 Found 3 new pattern(s)"
+        );
+    }
+}
@@ -8,15 +8,23 @@ use crossterm::{
 };
 use std::fs;
 use std::io::{self, Write as IoWrite};
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::process::Command;
 use std::time::Duration;
 
 use crate::config::{Config, Profile};
-
-pub fn interactive_pickup(config: &mut Config) -> Result<Option<String>> {
-    if config.profiles.is_empty() {
-        println!("{}", "No profiles configured".yellow());
+use crate::ui;
+use crate::{ui_eprintln, ui_println, ui_result};
+
+/// Pick a profile with the arrow keys, limited to one service if given
+pub fn interactive_pickup(config: &mut Config, service: Option<&str>) -> Result<Option<String>> {
+    let profiles: Vec<Profile> = config
+        .resolved_profiles()
+        .into_iter()
+        .filter(|p| service.is_none_or(|s| p.service == s))
+        .collect();
+    if profiles.is_empty() {
+        ui_result!("{}", "No profiles configured".yellow());
         return Ok(None);
     }
 
@@ -40,17 +48,17 @@ pub fn interactive_pickup(config: &mut Config) -> Result<Option<String>> {
             // Reserve space for prefix (2 chars) + right-side content (20 chars) + padding
             let max_display_width = (terminal_width as usize).saturating_sub(25);
 
-            println!("\r{}", "Available profiles:".bold());
-            println!(
+            ui_result!("\r{}", "Available profiles:".bold());
+            ui_result!(
                 "\r{}",
                 "(↑/↓: navigate, Enter: select, Esc/q: cancel)".dimmed()
             );
-            println!();
+            ui_result!();
 
-            for (idx, profile) in config.profiles.iter().enumerate() {
+            for (idx, profile) in profiles.iter().enumerate() {
                 let mut markers = Vec::new();
 
-                if config.active_profile.as_deref() == Some(&profile.name) {
+                if config.is_active(profile) {
                     markers.push("→".green().to_string());
                 }
 
@@ -72,19 +80,16 @@ pub fn interactive_pickup(config: &mut Config) -> Result<Option<String>> {
 
                 // Show compact single-line format with URL
                 // Calculate available space for name and URL
-                let marker_len = marker_text.as_ref().map_or(0, |m| m.len() + 1);
+                // Markers are one column each, e.g. "[→ ★]" plus a separating space
+                let marker_len = if markers.is_empty() { 0 } else { 2 * markers.len() + 2 };
                 let available_for_content = max_display_width.saturating_sub(marker_len + 5); // 5 for " - "
 
                 let name_and_url = format!("{} - {}", profile.name, profile.url);
-                let display_text = if name_and_url.len() > available_for_content {
-                    format!("{}...", &name_and_url[..available_for_content.saturating_sub(3)])
-                } else {
-                    name_and_url
-                };
+                let display_text = ui::truncate(&name_and_url, available_for_content);
 
                 match &marker_text {
-                    Some(marker) => println!("\r{} {} {}", prefix, display_text.cyan(), marker),
-                    None => println!("\r{} {}", prefix, display_text.cyan()),
+                    Some(marker) => ui_result!("\r{} {} {}", prefix, display_text.cyan(), marker),
+                    None => ui_result!("\r{} {}", prefix, display_text.cyan()),
                 }
             }
 
@@ -99,22 +104,15 @@ pub fn interactive_pickup(config: &mut Config) -> Result<Option<String>> {
                     {
                         match code {
                             KeyCode::Up | KeyCode::Char('k') => {
-                                if selected_idx > 0 {
-                                    selected_idx -= 1;
-                                }
+                                selected_idx = selected_idx.saturating_sub(1);
                             }
-                            KeyCode::Down | KeyCode::Char('j') => {
-                                if selected_idx < config.profiles.len() - 1 {
-                                    selected_idx += 1;
-                                }
+                            KeyCode::Down | KeyCode::Char('j')
+                                if selected_idx < profiles.len() - 1 =>
+                            {
+                                selected_idx += 1;
                             }
                             KeyCode::Enter => {
-                                if !config.profiles.is_empty() {
-                                    let selected_profile = &config.profiles[selected_idx];
-                                    return Ok(Some(selected_profile.name.clone()));
-                                } else {
-                                    return Ok(None);
-                                }
+                                return Ok(Some(profiles[selected_idx].name.clone()));
                             }
                             KeyCode::Esc | KeyCode::Char('q') => {
                                 return Ok(None);
@@ -174,60 +172,175 @@ pub fn check_mise_installation() -> (bool, bool) {
 
 pub fn show_mise_warning(mise_installed: bool, mise_activated: bool) {
     if !mise_installed {
-        println!();
-        println!(
+        ui_println!();
+        ui_println!(
             "{}",
             "⚠ Warning: mise is not installed or not in PATH"
                 .yellow()
                 .bold()
         );
-        println!();
-        println!("{}", "The .mise.toml file has been created, but mise is required to load the environment variables.".yellow());
-        println!();
-        println!("{}", "To install mise, run one of:".bold());
-        println!("  curl https://mise.run | sh");
-        println!("  brew install mise");
-        println!("  cargo install mise");
-        println!();
-        println!(
+        ui_println!();
+        ui_println!("{}", "The .mise.toml file has been created, but mise is required to load the environment variables.".yellow());
+        ui_println!();
+        ui_println!("{}", "To install mise, run one of:".bold());
+        ui_println!("  curl https://mise.run | sh");
+        ui_println!("  brew install mise");
+        ui_println!("  cargo install mise");
+        ui_println!();
+        ui_println!(
             "{}",
             "After installation, activate mise in your shell:".bold()
         );
-        println!("  eval \"$(mise activate bash)\"  # for bash");
-        println!("  eval \"$(mise activate zsh)\"   # for zsh");
-        println!("  mise activate fish | source    # for fish");
-        println!();
-        println!(
+        ui_println!("  eval \"$(mise activate bash)\"  # for bash");
+        ui_println!("  eval \"$(mise activate zsh)\"   # for zsh");
+        ui_println!("  mise activate fish | source    # for fish");
+        ui_println!();
+        ui_println!(
             "{}",
             "See https://mise.jdx.dev/ for more information.".dimmed()
         );
-        println!();
+        ui_println!();
     } else if !mise_activated {
-        println!();
-        println!(
+        ui_println!();
+        ui_println!(
             "{}",
             "⚠ Warning: mise is installed but may not be activated in your shell"
                 .yellow()
                 .bold()
         );
-        println!();
-        println!("{}", "The .mise.toml file has been created, but mise needs to be activated to load environment variables.".yellow());
-        println!();
-        println!(
+        ui_println!();
+        ui_println!("{}", "The .mise.toml file has been created, but mise needs to be activated to load environment variables.".yellow());
+        ui_println!();
+        ui_println!(
             "{}",
             "To activate mise, add this to your shell configuration (~/.bashrc, ~/.zshrc, etc.):"
                 .bold()
         );
-        println!("  eval \"$(mise activate bash)\"  # for bash");
-        println!("  eval \"$(mise activate zsh)\"   # for zsh");
-        println!("  mise activate fish | source    # for fish");
-        println!();
-        println!(
+        ui_println!("  eval \"$(mise activate bash)\"  # for bash");
+        ui_println!("  eval \"$(mise activate zsh)\"   # for zsh");
+        ui_println!("  mise activate fish | source    # for fish");
+        ui_println!();
+        ui_println!(
             "{}",
             "Then restart your shell or run: source ~/.bashrc (or equivalent)".dimmed()
         );
-        println!();
+        ui_println!();
+    }
+}
+
+/// First and last line of the `[env]` entries ecce owns in `.mise.toml`
+const MISE_BLOCK_START: &str = "# >>> ecce managed >>>";
+const MISE_BLOCK_END: &str = "# <<< ecce managed <<<";
+
+/// Whether a TOML line opens a table (`[env]`, `[[tools]]`, ...)
+fn is_table_header(line: &str) -> bool {
+    line.trim_start().starts_with('[')
+}
+
+/// Whether a TOML line opens the `[env]` table
+fn is_env_header(line: &str) -> bool {
+    let line = line.split('#').next().unwrap_or("").trim();
+    line.strip_prefix('[')
+        .and_then(|l| l.strip_suffix(']'))
+        .is_some_and(|name| name.trim() == "env")
+}
+
+/// `content` with `vars` set in its `[env]` table inside an ecce-managed block.
+/// Everything outside the block is kept, except earlier definitions of the same
+/// variables in `[env]`, which would otherwise clash with the block.
+pub fn upsert_mise_env(content: &str, vars: &[(&str, String)]) -> Result<String> {
+    let mut block = vec![MISE_BLOCK_START.to_string()];
+    for (name, value) in vars {
+        block.push(format!("{} = {}", name, toml::Value::String(value.clone())));
+    }
+    block.push(MISE_BLOCK_END.to_string());
+
+    let has_block = content.lines().any(|l| l.trim() == MISE_BLOCK_START)
+        && content.lines().any(|l| l.trim() == MISE_BLOCK_END);
+    let mut lines: Vec<String> = Vec::new();
+    let mut in_block = false;
+    let mut in_env = false;
+    let mut placed = false;
+    for line in content.lines() {
+        let trimmed = line.trim();
+        if has_block && trimmed == MISE_BLOCK_END {
+            in_block = false;
+            continue;
+        }
+        if in_block {
+            continue;
+        }
+        if has_block && trimmed == MISE_BLOCK_START {
+            in_block = true;
+            if !placed {
+                lines.extend(block.iter().cloned());
+                placed = true;
+            }
+            continue;
+        }
+        if is_table_header(line) {
+            in_env = is_env_header(line);
+            lines.push(line.to_string());
+            if in_env && !placed && !has_block {
+                lines.extend(block.iter().cloned());
+                placed = true;
+            }
+            continue;
+        }
+        let key = trimmed.split('=').next().unwrap_or("").trim().trim_matches('"');
+        if in_env && vars.iter().any(|(name, _)| *name == key) {
+            continue;
+        }
+        lines.push(line.to_string());
+    }
+    if !placed {
+        if lines.last().is_some_and(|l| !l.trim().is_empty()) {
+            lines.push(String::new());
+        }
+        lines.push("[env]".to_string());
+        lines.extend(block);
+    }
+
+    let updated = lines.join("\n") + "\n";
+
+    // Refuse to write a file mise can't read, or one where the values didn't land
+    let parsed: toml::Table = updated
+        .parse()
+        .context("Updating .mise.toml would make it invalid TOML; left it unchanged")?;
+    for (name, value) in vars {
+        if parsed
+            .get("env")
+            .and_then(|env| env.get(*name))
+            .and_then(|v| v.as_str())
+            != Some(value.as_str())
+        {
+            return Err(anyhow::anyhow!(
+                "Could not set {} in .mise.toml (is `env` defined in an unusual form?); left it unchanged",
+                name
+            ));
+        }
+    }
+    Ok(updated)
+}
+
+/// Set a profile's variables in `.mise.toml`, keeping the rest of the file and a
+/// `.mise.toml.bak` copy of the previous version
+fn write_mise_env(path: &Path, vars: &[(&str, String)]) -> Result<()> {
+    let existing = match fs::read_to_string(path) {
+        Ok(content) => Some(content),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => None,
+        Err(e) => return Err(e).with_context(|| format!("Failed to read {}", path.display())),
+    };
+    let updated = upsert_mise_env(existing.as_deref().unwrap_or(""), vars)?;
+    if let Some(ref existing) = existing {
+        if *existing == updated {
+            return Ok(());
+        }
+        // Copied rather than written, so the backup is as private as the file it holds
+        let backup = path.with_extension("toml.bak");
+        fs::copy(path, &backup).with_context(|| format!("Failed to write backup {}", backup.display()))?;
     }
+    fs::write(path, updated).context("Failed to write .mise.toml file")
 }
 
 pub fn apply_profile(profile: &Profile) -> Result<()> {
@@ -238,56 +351,44 @@ pub fn apply_profile(profile: &Profile) -> Result<()> {
 
             // Update .mise.toml with environment variables
             let mise_path = PathBuf::from(".mise.toml");
+            write_mise_env(&mise_path, &profile.env_vars().unwrap_or_default())?;
 
-            let mise_content = format!(
-                r#"# mise configuration for ecce project
-# Environment variables set by ecce tool
-
-[env]
-ANTHROPIC_BASE_URL = "{}"
-ANTHROPIC_API_KEY = "{}"
-"#,
-                profile.url, profile.key
-            );
-
-            fs::write(&mise_path, mise_content).context("Failed to write .mise.toml file")?;
-
-            println!(
+            ui_println!(
                 "{}",
                 "✓ Environment variables updated in .mise.toml".green()
             );
-            println!();
-            println!("{}", "Profile applied:".bold());
-            println!("  ANTHROPIC_BASE_URL = {}", profile.url.cyan());
-            println!(
+            ui_println!();
+            ui_println!("{}", "Profile applied:".bold());
+            ui_println!("  ANTHROPIC_BASE_URL = {}", profile.url.cyan());
+            ui_println!(
                 "  ANTHROPIC_API_KEY = {}***",
-                profile.key[..profile.key.len().min(8)].cyan()
+                ui::prefix(&profile.key, 8).cyan()
             );
 
             // Show warning if mise is not properly set up
             if !mise_installed || !mise_activated {
                 show_mise_warning(mise_installed, mise_activated);
             } else {
-                println!();
-                println!("{}", "✓ mise is installed and activated".green());
-                println!(
+                ui_println!();
+                ui_println!("{}", "✓ mise is installed and activated".green());
+                ui_println!(
                     "{}",
                     "  Environment variables will be loaded automatically in this directory."
                         .dimmed()
                 );
-                println!();
+                ui_println!();
             }
         }
         "codex" => {
             // Placeholder for Codex configuration
-            eprintln!(
+            ui_eprintln!(
                 "{}",
                 "✓ Codex configuration (placeholder - implement based on Codex config location)"
                     .yellow()
             );
         }
         _ => {
-            eprintln!(
+            ui_eprintln!(
                 "{}",
                 format!("⚠ Unknown service type: {}", profile.service).yellow()
             );
@@ -344,3 +445,88 @@ pub async fn check_url_status(url: &str, api_key: &str) -> ConnectionStatus {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn vars() -> Vec<(&'static str, String)> {
+        vec![
+            ("ANTHROPIC_BASE_URL", "https://relay.example".to_string()),
+            ("ANTHROPIC_API_KEY", "sk-\"new\"".to_string()),
+        ]
+    }
+
+    #[test]
+    fn test_upsert_mise_env_keeps_other_settings() {
+        let content = "# my project\n[tools]\nnode = \"20\"\n\n[env]\nNODE_ENV = \"dev\"\nANTHROPIC_API_KEY = \"sk-old\"\n\n[tasks.build]\nrun = \"make\"\n";
+        let updated = upsert_mise_env(content, &vars()).unwrap();
+
+        assert!(updated.starts_with("# my project\n[tools]\nnode = \"20\"\n"));
+        assert!(updated.contains("[env]\n# >>> ecce managed >>>\nANTHROPIC_BASE_URL = \"https://relay.example\"\n"));
+        assert!(updated.contains("NODE_ENV = \"dev\""));
+        assert!(updated.contains("[tasks.build]\nrun = \"make\""));
+        assert!(!updated.contains("sk-old"));
+
+        let parsed: toml::Table = updated.parse().unwrap();
+        assert_eq!(parsed["env"]["ANTHROPIC_API_KEY"].as_str(), Some("sk-\"new\""));
+    }
+
+    #[test]
+    fn test_upsert_mise_env_replaces_block() {
+        let first = upsert_mise_env("[tools]\npython = \"3.12\"\n", &vars()).unwrap();
+        assert!(first.ends_with("[env]\n# >>> ecce managed >>>\nANTHROPIC_BASE_URL = \"https://relay.example\"\nANTHROPIC_API_KEY = 'sk-\"new\"'\n# <<< ecce managed <<<\n"));
+
+        let second = upsert_mise_env(
+            &first,
+            &[
+                ("ANTHROPIC_BASE_URL", "https://other.example".to_string()),
+                ("ANTHROPIC_API_KEY", "sk-2".to_string()),
+            ],
+        )
+        .unwrap();
+        assert_eq!(second.matches(MISE_BLOCK_START).count(), 1);
+        assert!(second.contains("https://other.example"));
+        assert!(!second.contains("relay.example"));
+        assert!(second.starts_with("[tools]\npython = \"3.12\"\n"));
+
+        // Unchanged values leave the file byte-for-byte the same
+        assert_eq!(upsert_mise_env(&first, &vars()).unwrap(), first);
+    }
+
+    #[test]
+    fn test_upsert_mise_env_refuses_unusual_env() {
+        assert!(upsert_mise_env("env = { FOO = \"1\" }\n", &vars()).is_err());
+    }
+
+    #[test]
+    fn test_write_mise_env_backs_up() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let path = dir.path().join(".mise.toml");
+        fs::write(&path, "[tools]\nnode = \"20\"\n").unwrap();
+
+        write_mise_env(&path, &vars()).unwrap();
+
+        assert_eq!(
+            fs::read_to_string(dir.path().join(".mise.toml.bak")).unwrap(),
+            "[tools]\nnode = \"20\"\n"
+        );
+        assert!(fs::read_to_string(&path).unwrap().contains("node = \"20\""));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_write_mise_env_backup_keeps_permissions() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = tempfile::TempDir::new().unwrap();
+        let path = dir.path().join(".mise.toml");
+        fs::write(&path, "[tools]\nnode = \"20\"\n").unwrap();
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o600)).unwrap();
+
+        write_mise_env(&path, &vars()).unwrap();
+
+        let backup = fs::metadata(dir.path().join(".mise.toml.bak")).unwrap();
+        assert_eq!(backup.permissions().mode() & 0o777, 0o600);
+    }
+}
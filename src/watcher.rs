@@ -1,46 +1,83 @@
 use anyhow::{Context, Result};
-use std::fs;
-use std::path::Path;
+use std::ops::Range;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::thread;
-use std::time::Duration;
+use std::time::{Duration, Instant, SystemTime};
 
-use crate::pattern::{EccePattern, PatternDetector};
+use crate::line_endings::{common_prefix, common_suffix};
+use crate::pattern::{DedupStrategy, EccePattern, PatternDetector, PatternType};
+use crate::remote::{FileStamp, FileTarget};
+
+/// A file modified this recently may change again without its modification time
+/// moving (coarse timestamps on some filesystems), so its stamp isn't trusted yet
+const RACY_STAMP_MARGIN: Duration = Duration::from_secs(2);
+
+/// Files at least this large are rescanned only around what changed since the
+/// last poll; a pattern left unanswered elsewhere is picked up once it is edited
+pub const INCREMENTAL_MIN_BYTES: usize = 512 * 1024;
 
 pub struct FileWatcher {
     last_content: String,
+    /// Stamp of the file when `last_content` was read, if it can be trusted to skip reads
+    last_stamp: Option<FileStamp>,
     detector: PatternDetector,
     poll_interval: Duration,
+    /// How long the file must stay unchanged before it is scanned
+    debounce: Duration,
+    /// Content seen while waiting for the file to settle, and since when
+    settling: Option<(String, Instant)>,
 }
 
 impl FileWatcher {
-    pub fn new<P: AsRef<Path>>(path: P) -> Result<Self> {
-        Self::with_interval(path, 500)
+    pub fn new(target: &FileTarget) -> Result<Self> {
+        Self::with_interval(target, 500)
     }
 
-    pub fn with_interval<P: AsRef<Path>>(path: P, interval_ms: u64) -> Result<Self> {
-        let initial_content =
-            fs::read_to_string(&path).context("Failed to read initial file content")?;
+    pub fn with_interval(target: &FileTarget, interval_ms: u64) -> Result<Self> {
+        let last_stamp = trusted_stamp(target);
+        let initial_content = target
+            .read_to_string()
+            .context("Failed to read initial file content")?;
 
         Ok(Self {
             last_content: initial_content,
+            last_stamp,
             detector: PatternDetector::new(),
             poll_interval: Duration::from_millis(interval_ms),
+            debounce: Duration::ZERO,
+            settling: None,
         })
     }
 
-    pub fn watch<P: AsRef<Path>>(&mut self, _path: P) -> Result<()> {
+    pub fn watch(&mut self, _target: &FileTarget) -> Result<()> {
         // No-op: we don't need to set up watching, we'll poll directly
         Ok(())
     }
 
     /// Wait for file changes and return new patterns found
-    pub fn wait_for_changes<P: AsRef<Path>>(&mut self, path: P) -> Result<Vec<EccePattern>> {
+    pub fn wait_for_changes(&mut self, target: &FileTarget) -> Result<Vec<EccePattern>> {
+        self.wait_for_changes_until(target, &AtomicBool::new(false))
+    }
+
+    /// Like `wait_for_changes`, but return no patterns once `stop` is set
+    pub fn wait_for_changes_until(
+        &mut self,
+        target: &FileTarget,
+        stop: &AtomicBool,
+    ) -> Result<Vec<EccePattern>> {
         loop {
             // Sleep for the poll interval
             thread::sleep(self.poll_interval);
+            if stop.load(Ordering::Relaxed) {
+                return Ok(Vec::new());
+            }
+
+            if !self.settled(target)? {
+                continue;
+            }
 
             // Check for new patterns
-            if let Some(patterns) = self.check_for_new_patterns(&path)? {
+            if let Some(patterns) = self.check_for_new_patterns(target)? {
                 if !patterns.is_empty() {
                     return Ok(patterns);
                 }
@@ -49,19 +86,26 @@ impl FileWatcher {
     }
 
     /// Check for new patterns in file
-    fn check_for_new_patterns<P: AsRef<Path>>(
-        &mut self,
-        path: P,
-    ) -> Result<Option<Vec<EccePattern>>> {
-        let current_content = fs::read_to_string(&path).context("Failed to read file content")?;
+    fn check_for_new_patterns(&mut self, target: &FileTarget) -> Result<Option<Vec<EccePattern>>> {
+        // Same modification time and size: skip reading the file at all
+        if self.last_stamp.is_some() && target.stamp() == self.last_stamp {
+            return Ok(None);
+        }
+        self.last_stamp = trusted_stamp(target);
+        let current_content = target.read_to_string().context("Failed to read file content")?;
 
         // If content is identical, skip
         if current_content == self.last_content {
             return Ok(None);
         }
 
-        // Check the entire file for new patterns
-        let patterns = self.detector.detect_new_patterns(&current_content);
+        let patterns = if current_content.len() >= INCREMENTAL_MIN_BYTES {
+            let changed = changed_range(&self.last_content, &current_content);
+            self.detector.detect_patterns_near(&current_content, changed)
+        } else {
+            // Check the entire file for new patterns
+            self.detector.detect_new_patterns(&current_content)
+        };
 
         // Update last content
         self.last_content = current_content;
@@ -73,14 +117,71 @@ impl FileWatcher {
         }
     }
 
+    /// Wait until the file has stayed unchanged for `debounce` before scanning
+    /// it, so patterns are answered once the editor stops saving
+    pub fn set_debounce(&mut self, debounce: Duration) {
+        self.debounce = debounce;
+    }
+
+    /// Whether the file has been unchanged for the debounce period (always, without one)
+    fn settled(&mut self, target: &FileTarget) -> Result<bool> {
+        if self.debounce.is_zero() || (self.last_stamp.is_some() && target.stamp() == self.last_stamp) {
+            return Ok(true);
+        }
+        let content = target.read_to_string().context("Failed to read file content")?;
+        if content == self.last_content {
+            self.settling = None;
+            return Ok(true);
+        }
+        match self.settling {
+            Some((ref seen, since)) if *seen == content => {
+                let settled = since.elapsed() >= self.debounce;
+                if settled {
+                    self.settling = None;
+                }
+                Ok(settled)
+            }
+            _ => {
+                self.settling = Some((content, Instant::now()));
+                Ok(false)
+            }
+        }
+    }
+
+    /// Trigger on these keywords instead of `ecce` (the default when empty)
+    pub fn set_keywords(&mut self, keywords: &[String]) -> Result<()> {
+        self.detector.set_keywords(keywords)
+    }
+
+    /// Only report the given pattern types (all of them when empty)
+    pub fn set_pattern_types(&mut self, types: Vec<PatternType>) {
+        self.detector.set_pattern_types(types);
+    }
+
+    /// How answered patterns are recognized
+    pub fn set_dedup(&mut self, dedup: DedupStrategy) {
+        self.detector.set_dedup(dedup);
+    }
+
     /// Mark a pattern as processed
-    pub fn mark_processed(&mut self, content: &str) {
-        self.detector.mark_processed(content);
+    pub fn mark_processed(&mut self, pattern: &EccePattern) {
+        self.detector.mark_processed(pattern);
+    }
+
+    /// Keys of the patterns answered so far, for saving the session
+    pub fn processed_keys(&self) -> Vec<String> {
+        self.detector.processed_keys()
+    }
+
+    /// Skip patterns answered in a saved session
+    pub fn restore_processed(&mut self, keys: Vec<String>) {
+        self.detector.restore_processed(keys);
     }
 
     /// Update the watcher's content to match the current file
-    pub fn update_content<P: AsRef<Path>>(&mut self, path: P) -> Result<()> {
-        let current_content = fs::read_to_string(&path).context("Failed to read file content")?;
+    pub fn update_content(&mut self, target: &FileTarget) -> Result<()> {
+        self.last_stamp = trusted_stamp(target);
+        let current_content = target.read_to_string().context("Failed to read file content")?;
         self.last_content = current_content;
         Ok(())
     }
@@ -89,5 +190,106 @@ impl FileWatcher {
     pub fn current_content(&self) -> &str {
         &self.last_content
     }
+
+    /// Patterns in the current content not yet answered, without waiting for a change
+    pub fn unanswered_patterns(&self) -> Vec<EccePattern> {
+        self.detector.detect_new_patterns(&self.last_content)
+    }
 }
 
+
+/// The file's stamp, unless it was modified too recently to rely on; taken before
+/// reading so a write during the read shows up as a change next time
+fn trusted_stamp(target: &FileTarget) -> Option<FileStamp> {
+    let stamp = target.stamp()?;
+    let age = SystemTime::now().duration_since(stamp.modified).ok()?;
+    (age >= RACY_STAMP_MARGIN).then_some(stamp)
+}
+
+/// Byte range of `new` that differs from `old`, between their common prefix and suffix
+pub fn changed_range(old: &str, new: &str) -> Range<usize> {
+    let prefix = common_prefix(old, new);
+    let suffix = common_suffix(&old[prefix..], &new[prefix..]);
+    prefix..new.len() - suffix
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_changed_range() {
+        assert_eq!(changed_range("abc def", "abc XX def"), 4..7);
+        assert_eq!(changed_range("abc", "abc"), 3..3);
+        assert_eq!(changed_range("日本", "日本語"), 6..9);
+    }
+
+    #[test]
+    fn test_debounce_waits_for_the_file_to_settle() {
+        let temp = TempDir::new().unwrap();
+        let path = temp.path().join("slides.md");
+        std::fs::write(&path, "# Notes\n").unwrap();
+        let target = FileTarget::from(path.clone());
+        let mut watcher = FileWatcher::new(&target).unwrap();
+        assert!(watcher.settled(&target).unwrap());
+
+        watcher.set_debounce(Duration::from_millis(200));
+        assert!(watcher.settled(&target).unwrap());
+        std::fs::write(&path, "# Notes\n\necce What ecce\n").unwrap();
+        assert!(!watcher.settled(&target).unwrap());
+        std::thread::sleep(Duration::from_millis(120));
+        // Still typing: the wait starts over
+        std::fs::write(&path, "# Notes\n\necce What is ownership? ecce\n").unwrap();
+        assert!(!watcher.settled(&target).unwrap());
+        std::thread::sleep(Duration::from_millis(120));
+        assert!(!watcher.settled(&target).unwrap());
+        std::thread::sleep(Duration::from_millis(120));
+        assert!(watcher.settled(&target).unwrap());
+        let patterns = watcher.check_for_new_patterns(&target).unwrap().unwrap();
+        assert_eq!(patterns[0].question(), "What is ownership?");
+    }
+
+    #[test]
+    fn test_unchanged_stamp_skips_read() {
+        let temp = TempDir::new().unwrap();
+        let path = temp.path().join("slides.md");
+        std::fs::write(&path, "ecce old ecce").unwrap();
+        let old = SystemTime::now() - Duration::from_secs(60);
+        std::fs::File::options().write(true).open(&path).unwrap().set_modified(old).unwrap();
+        let target = FileTarget::from(path.clone());
+        let mut watcher = FileWatcher::new(&target).unwrap();
+        assert!(watcher.last_stamp.is_some());
+
+        // Same size and time: the new pattern isn't seen, since the file isn't read
+        std::fs::write(&path, "ecce new ecce").unwrap();
+        std::fs::File::options().write(true).open(&path).unwrap().set_modified(old).unwrap();
+        assert!(watcher.check_for_new_patterns(&target).unwrap().is_none());
+
+        // A fresh modification time is noticed, and not trusted until it ages
+        std::fs::write(&path, "ecce newer ecce").unwrap();
+        let patterns = watcher.check_for_new_patterns(&target).unwrap().unwrap();
+        assert_eq!(patterns[0].content, "newer");
+        assert!(watcher.last_stamp.is_none());
+    }
+
+    #[test]
+    fn test_incremental_detection_in_large_file() {
+        let temp = TempDir::new().unwrap();
+        let path = temp.path().join("notes.md");
+        let filler = "Some lecture notes.\n\n".repeat(INCREMENTAL_MIN_BYTES / 20);
+        let old = format!("{}ecce left alone ecce\n\n{}", filler, filler);
+        std::fs::write(&path, &old).unwrap();
+        let target = FileTarget::from(path.clone());
+        let mut watcher = FileWatcher::new(&target).unwrap();
+
+        let new = old.replacen("Some lecture notes.", "ecce new one? ecce", 1);
+        std::fs::write(&path, &new).unwrap();
+        let patterns = watcher.check_for_new_patterns(&target).unwrap().unwrap();
+
+        // Only the edited paragraph is scanned
+        assert_eq!(patterns.len(), 1);
+        assert_eq!(patterns[0].content, "new one?");
+        assert_eq!(patterns[0].start_pos, 0);
+    }
+}
@@ -0,0 +1,538 @@
+// Runs the ecce binary against a fake claude (ecce::testing), from pattern
+// detection through the placeholder to the answer written back
+#![cfg(unix)]
+
+use ecce::pending::PLACEHOLDER_TEXT;
+use ecce::provenance::Provenance;
+use ecce::testing::FakeClaude;
+use ecce::Config;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::time::{Duration, Instant};
+use tempfile::TempDir;
+
+/// A home directory whose ecce config points at `claude`
+fn home_with(claude: &FakeClaude) -> TempDir {
+    let home = TempDir::new().unwrap();
+    let mut config = Config::default();
+    claude.install(&mut config);
+    let dir = home.path().join(".config").join("ecce");
+    fs::create_dir_all(&dir).unwrap();
+    fs::write(dir.join("config.json"), serde_json::to_string_pretty(&config).unwrap()).unwrap();
+    home
+}
+
+fn ecce(home: &Path) -> Command {
+    let mut command = Command::new(env!("CARGO_BIN_EXE_ecce"));
+    command.env("HOME", home).env("NO_COLOR", "1").stdin(Stdio::null());
+    command
+}
+
+/// Wait until the file satisfies `done`, failing after a while
+fn wait_for(path: &PathBuf, what: &str, done: impl Fn(&str) -> bool) -> String {
+    let start = Instant::now();
+    loop {
+        let content = fs::read_to_string(path).unwrap_or_default();
+        if done(&content) {
+            return content;
+        }
+        assert!(start.elapsed() < Duration::from_secs(30), "timed out waiting for {}: {:?}", what, content);
+        std::thread::sleep(Duration::from_millis(50));
+    }
+}
+
+/// Block until a watcher started with `home` has read the file and is polling:
+/// its session autosave appears once patterns already in the file are set aside
+fn wait_until_watching(home: &Path) {
+    let autosaves = home.join(".config/ecce/sessions/autosave");
+    let start = Instant::now();
+    while fs::read_dir(&autosaves).map_or(true, |mut dir| dir.next().is_none()) {
+        assert!(start.elapsed() < Duration::from_secs(30), "timed out waiting for the watcher to start");
+        std::thread::sleep(Duration::from_millis(20));
+    }
+}
+
+#[test]
+fn test_answers_patterns_in_place() {
+    let claude = FakeClaude::builder()
+        .answer_when("ownership", "Ownership moves values.")
+        .build()
+        .unwrap();
+    let home = home_with(&claude);
+    let slides = home.path().join("slides.md");
+    fs::write(&slides, "# Rust\n\necce What is ownership? ecce\n\nThe end.\n").unwrap();
+
+    let output = ecce(home.path()).args(["hooks", "run", "--file"]).arg(&slides).output().unwrap();
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+
+    let content = fs::read_to_string(&slides).unwrap();
+    assert!(content.starts_with("# Rust\n\n"));
+    assert!(content.contains("Ownership moves values."));
+    assert!(content.ends_with("The end.\n"));
+    assert!(!content.contains("ecce What") && !content.contains(PLACEHOLDER_TEXT));
+
+    let calls = claude.calls();
+    assert_eq!(calls.len(), 1);
+    assert!(calls[0].stdin.contains("What is ownership?"));
+}
+
+#[test]
+fn test_hooks_run_uses_the_project_keywords() {
+    let claude = FakeClaude::answering("Ownership moves values.").unwrap();
+    let home = home_with(&claude);
+    let slides = home.path().join("slides.md");
+    fs::write(home.path().join(".ecce.json"), r#"{"keywords": ["ai!"]}"#).unwrap();
+    fs::write(&slides, "ai! What is ownership? ai!\n\necce Not a question here. ecce\n").unwrap();
+
+    let output = ecce(home.path()).args(["hooks", "run", "--file"]).arg(&slides).output().unwrap();
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+
+    let content = fs::read_to_string(&slides).unwrap();
+    assert_eq!(content, "Ownership moves values.\n\necce Not a question here. ecce\n");
+    assert_eq!(claude.calls().len(), 1);
+}
+
+#[test]
+fn test_notebook_questions_reach_the_agent_unescaped() {
+    let claude = FakeClaude::answering("Because \"moves\" are cheap.").unwrap();
+    let home = home_with(&claude);
+    let notebook = home.path().join("analysis.ipynb");
+    fs::write(&notebook, r#"{"cells": [{"cell_type": "markdown", "source": ["ecce Why \"borrow\"?\nBriefly. ecce"]}]}"#).unwrap();
+
+    let output = ecce(home.path()).args(["hooks", "run", "--file"]).arg(&notebook).output().unwrap();
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+
+    assert!(claude.calls()[0].stdin.contains("Why \"borrow\"?\nBriefly."));
+    let cells: serde_json::Value = serde_json::from_str(&fs::read_to_string(&notebook).unwrap()).unwrap();
+    assert_eq!(cells["cells"][0]["source"][0], "Because \"moves\" are cheap.");
+}
+
+#[test]
+fn test_translates_answers_for_the_project() {
+    let claude = FakeClaude::builder()
+        .answer_when("Translate this into Japanese", "所有権は値を移動します。")
+        .answer("Ownership moves values.")
+        .build()
+        .unwrap();
+    let home = home_with(&claude);
+    let slides = home.path().join("slides.md");
+    fs::write(home.path().join(".ecce.json"), r#"{"translate_to": {"lang": "ja", "bilingual": true}}"#).unwrap();
+    fs::write(&slides, "ecce What is ownership? ecce\n").unwrap();
+
+    let output = ecce(home.path()).args(["hooks", "run", "--file"]).arg(&slides).output().unwrap();
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+
+    let content = fs::read_to_string(&slides).unwrap();
+    assert!(content.contains("Ownership moves values.\n\n<!-- ecce-translation ja -->\n\n所有権は値を移動します。"));
+    let calls = claude.calls();
+    assert_eq!(calls.len(), 2);
+    assert!(calls[1].stdin.contains("Ownership moves values."));
+}
+
+#[test]
+fn test_failed_generation_keeps_a_recoverable_placeholder() {
+    let claude = FakeClaude::builder().fail_on_call(1, 1, "overloaded").build().unwrap();
+    let home = home_with(&claude);
+    let slides = home.path().join("slides.md");
+    fs::write(&slides, "ecce What is borrowing? ecce\n").unwrap();
+
+    let output = ecce(home.path()).args(["hooks", "run", "--file"]).arg(&slides).output().unwrap();
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("overloaded"));
+    // The placeholder stays, with a pending record, for `ecce pending` to retry
+    let content = fs::read_to_string(&slides).unwrap();
+    assert!(content.contains(PLACEHOLDER_TEXT) && content.contains("ecce:pending"));
+    assert_eq!(claude.calls().len(), 1);
+}
+
+#[test]
+fn test_watcher_shows_placeholder_then_answer() {
+    let claude = FakeClaude::builder()
+        .answer("Lifetimes bound references.")
+        .delay(Duration::from_millis(800))
+        .build()
+        .unwrap();
+    let home = home_with(&claude);
+    let slides = home.path().join("slides.md");
+    fs::write(&slides, "# Notes\n").unwrap();
+
+    let mut watcher = ecce(home.path())
+        .arg("homo")
+        .arg(&slides)
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+        .unwrap();
+    // Patterns already in the file when watching starts are left alone, so add one once it runs
+    wait_until_watching(home.path());
+    fs::write(&slides, "# Notes\n\necce What are lifetimes? ecce\n").unwrap();
+
+    wait_for(&slides, "the placeholder", |c| c.contains(PLACEHOLDER_TEXT));
+    let content = wait_for(&slides, "the answer", |c| c.contains("Lifetimes bound references."));
+    watcher.kill().ok();
+    watcher.wait().ok();
+
+    assert!(content.starts_with("# Notes\n"));
+    assert!(!content.contains(PLACEHOLDER_TEXT));
+    assert!(claude.calls()[0].stdin.contains("What are lifetimes?"));
+}
+
+#[test]
+fn test_fit_slide_continues_long_answers() {
+    let claude = FakeClaude::builder()
+        .answer("First point.\n\nSecond point.\n\nThird point.")
+        .build()
+        .unwrap();
+    let home = home_with(&claude);
+    let slides = home.path().join("slides.md");
+    fs::write(&slides, "# Traits\n").unwrap();
+
+    let mut watcher = ecce(home.path())
+        .arg("homo")
+        .arg(&slides)
+        .args(["--fit-slide", "4"])
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+        .unwrap();
+    wait_until_watching(home.path());
+    fs::write(&slides, "# Traits\n\necce Why traits? ecce\n\n---\n\n# Next\n").unwrap();
+
+    let content = wait_for(&slides, "the answer", |c| c.contains("Third point."));
+    watcher.kill().ok();
+    watcher.wait().ok();
+
+    // "# Traits" leaves room for three lines: two points, then a new slide
+    assert_eq!(
+        content,
+        "# Traits\n\nFirst point.\n\nSecond point.\n\n---\n\nThird point.\n\n---\n\n# Next\n"
+    );
+    assert!(claude.calls()[0].stdin.contains("room for about 3 more lines"));
+}
+
+#[test]
+fn test_queue_answers_open_items() {
+    let claude = FakeClaude::builder()
+        .answer_when("trait", "A set of methods.")
+        .answer_when("borrow", "Lend without moving.")
+        .build()
+        .unwrap();
+    let home = home_with(&claude);
+    let queue = home.path().join("questions.md");
+    fs::write(&queue, "# Questions\n\n- [ ] What is a trait?\n- [x] Old news\n").unwrap();
+
+    let mut watcher = ecce(home.path())
+        .arg("homo")
+        .arg(&queue)
+        .arg("--queue")
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+        .unwrap();
+    // Open items are answered at start, and new ones as they are added
+    let content = wait_for(&queue, "the first answer", |c| c.contains("A set of methods."));
+    fs::write(&queue, format!("{}- [ ] Why borrow?\n", content)).unwrap();
+    let content = wait_for(&queue, "the second answer", |c| c.contains("Lend without moving."));
+    watcher.kill().ok();
+    watcher.wait().ok();
+
+    assert_eq!(
+        content,
+        "# Questions\n\n- [x] What is a trait?\n\n  A set of methods.\n- [x] Old news\n- [x] Why borrow?\n\n  Lend without moving.\n"
+    );
+    assert_eq!(claude.calls().len(), 2);
+}
+
+#[test]
+fn test_queue_to_stdout_ticks_items() {
+    let claude = FakeClaude::answering("A set of methods.").unwrap();
+    let home = home_with(&claude);
+    let queue = home.path().join("questions.md");
+    fs::write(&queue, "- [ ] What is a trait?\n").unwrap();
+
+    let mut watcher = ecce(home.path())
+        .arg("homo")
+        .arg(&queue)
+        .args(["--queue", "--stdout"])
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+        .unwrap();
+    // Answered on stdout, but ticked so a restart doesn't ask again
+    let content = wait_for(&queue, "the item ticked", |c| c.contains("[x]"));
+    watcher.kill().ok();
+    watcher.wait().ok();
+
+    assert_eq!(content, "- [x] What is a trait?\n");
+    assert_eq!(claude.calls().len(), 1);
+}
+
+#[test]
+fn test_reuse_answers_repeated_questions() {
+    let claude = FakeClaude::answering("A set of methods.").unwrap();
+    let home = home_with(&claude);
+    let slides = home.path().join("slides.md");
+    fs::write(&slides, "# Traits\n").unwrap();
+
+    let mut watcher = ecce(home.path())
+        .arg("homo")
+        .arg(&slides)
+        .arg("--reuse")
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+        .unwrap();
+    wait_until_watching(home.path());
+    fs::write(&slides, "# Traits\n\necce What is a trait? ecce\n").unwrap();
+    let content = wait_for(&slides, "the first answer", |c| c.contains("A set of methods."));
+    fs::write(&slides, format!("{}\n---\n\necce what is a trait ecce\n", content)).unwrap();
+    // Without a terminal to ask, the earlier answer is reused
+    let content = wait_for(&slides, "the reused answer", |c| c.matches("A set of methods.").count() == 2);
+    watcher.kill().ok();
+    watcher.wait().ok();
+
+    assert!(!content.contains("ecce what"));
+    assert_eq!(claude.calls().len(), 1);
+}
+
+#[test]
+fn test_stdin_answers_each_line() {
+    use std::io::Write;
+
+    let claude = FakeClaude::builder()
+        .answer_when("trait", "A set of methods.")
+        .answer_when("borrow", "Lend without moving.")
+        .build()
+        .unwrap();
+    let home = home_with(&claude);
+    let mut child = ecce(home.path())
+        .args(["homo", "--stdin"])
+        .current_dir(home.path())
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(b"What is a trait?\n\nWhy borrow?\n")
+        .unwrap();
+    let output = child.wait_with_output().unwrap();
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout),
+        "## What is a trait?\n\nA set of methods.\n\n## Why borrow?\n\nLend without moving.\n\n"
+    );
+    assert_eq!(claude.calls().len(), 2);
+}
+
+#[test]
+fn test_chat_streams_saves_and_resumes() {
+    use std::io::Write;
+
+    let claude = FakeClaude::builder()
+        .answer_when("trait", "A set of methods.")
+        .answer_when("borrow", "Lend without moving.")
+        .build()
+        .unwrap();
+    let home = home_with(&claude);
+    let slides = home.path().join("slides.md");
+    fs::write(&slides, "# Traits\n").unwrap();
+    let chat = |input: &[u8]| {
+        let mut child = ecce(home.path())
+            .arg("chat")
+            .arg(&slides)
+            .current_dir(home.path())
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .unwrap();
+        child.stdin.take().unwrap().write_all(input).unwrap();
+        let output = child.wait_with_output().unwrap();
+        assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+        String::from_utf8_lossy(&output.stdout).to_string()
+    };
+
+    let stdout = chat(b"What is a trait?\n/save-to\n/save-to notes.md\n");
+    assert!(stdout.contains("A set of methods."), "{}", stdout);
+    // A deck gets the exchange as a slide of its own
+    assert_eq!(
+        fs::read_to_string(&slides).unwrap(),
+        "# Traits\n\n---\n\n### What is a trait?\n\nA set of methods.\n"
+    );
+    assert!(fs::read_to_string(home.path().join("notes.md"))
+        .unwrap()
+        .contains("A set of methods."));
+
+    // A second chat on the file continues the conversation
+    let stdout = chat(b"Why borrow?\n");
+    assert!(stdout.contains("Continuing the conversation"), "{}", stdout);
+    assert!(stdout.contains("Lend without moving."), "{}", stdout);
+    let calls = claude.calls();
+    assert_eq!(calls.len(), 2);
+    assert!(calls[0].args.iter().any(|a| a == "stream-json"));
+    assert!(calls[1].args.iter().any(|a| a == "--resume"));
+}
+
+#[test]
+fn test_chat_asks_before_saving_what_the_guards_block() {
+    use std::io::Write;
+
+    let claude = FakeClaude::answering("Project bluebird ships in May.").unwrap();
+    let home = home_with(&claude);
+    fs::write(
+        home.path().join(".ecce.json"),
+        r#"{"guards": [{"name": "codenames", "words": ["bluebird"], "action": "block"}]}"#,
+    )
+    .unwrap();
+    let mut child = ecce(home.path())
+        .arg("chat")
+        .current_dir(home.path())
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    // Declined once, then overridden
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(b"When does it ship?\n/save-to notes.md\nn\n/save-to notes.md\ny\n")
+        .unwrap();
+    let output = child.wait_with_output().unwrap();
+
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("Response blocked by guard: codenames"), "{}\n{}", stderr, String::from_utf8_lossy(&output.stdout));
+    let notes = fs::read_to_string(home.path().join("notes.md")).unwrap();
+    assert_eq!(notes.matches("Project bluebird ships in May.").count(), 1);
+}
+
+#[test]
+fn test_regen_keeps_the_answer_a_guard_blocks() {
+    let claude = FakeClaude::answering("Project bluebird ships in May.").unwrap();
+    let home = home_with(&claude);
+    fs::write(
+        home.path().join(".ecce.json"),
+        r#"{"guards": [{"name": "codenames", "words": ["bluebird"], "action": "block"}]}"#,
+    )
+    .unwrap();
+    let slides = home.path().join("slides.md");
+    let answer = Provenance::new("a1".to_string(), "default".to_string(), None, "When does it ship?".to_string(), "Soon.")
+        .wrap("Soon.");
+    fs::write(&slides, format!("# Plan\n\n{}\n", answer)).unwrap();
+
+    let output = ecce(home.path()).arg("regen").arg(&slides).output().unwrap();
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("Response blocked by guard: codenames"), "{}", stderr);
+    assert_eq!(fs::read_to_string(&slides).unwrap(), format!("# Plan\n\n{}\n", answer));
+    assert_eq!(claude.calls().len(), 1);
+}
+
+#[test]
+fn test_chat_ratings_are_exported() {
+    use std::io::Write;
+
+    let claude = FakeClaude::answering("A set of methods.").unwrap();
+    let home = home_with(&claude);
+    let mut child = ecce(home.path())
+        .arg("chat")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(b"What is a trait?\n/bad too terse\nWhy borrow?\n")
+        .unwrap();
+    let output = child.wait_with_output().unwrap();
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+
+    let output = ecce(home.path())
+        .args(["history", "export", "--format", "markdown", "--feedback", "down"])
+        .output()
+        .unwrap();
+    let exported = String::from_utf8_lossy(&output.stdout);
+    assert!(exported.contains("What is a trait?"), "{}", exported);
+    assert!(exported.contains("- Feedback: 👎 too terse"));
+    assert!(!exported.contains("Why borrow?"));
+}
+
+#[test]
+fn test_ctrl_c_lets_the_generation_finish() {
+    use std::os::unix::process::CommandExt;
+
+    let claude = FakeClaude::builder()
+        .answer("Lifetimes bound references.")
+        .delay(Duration::from_millis(1500))
+        .build()
+        .unwrap();
+    let home = home_with(&claude);
+    let slides = home.path().join("slides.md");
+    fs::write(&slides, "# Notes\n").unwrap();
+
+    // A group of its own, like a shell job, so the interrupt can go to the whole group
+    let mut watcher = ecce(home.path())
+        .arg("homo")
+        .arg(&slides)
+        .process_group(0)
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+        .unwrap();
+    wait_until_watching(home.path());
+    fs::write(&slides, "# Notes\n\necce What are lifetimes? ecce\n").unwrap();
+    wait_for(&slides, "the placeholder", |c| c.contains(PLACEHOLDER_TEXT));
+
+    // What a terminal does on Ctrl+C: interrupt every process in the foreground group
+    Command::new("kill")
+        .args(["-INT", "--", &format!("-{}", watcher.id())])
+        .status()
+        .unwrap();
+    let status = watcher.wait().unwrap();
+
+    assert!(status.success());
+    let content = fs::read_to_string(&slides).unwrap();
+    assert!(content.contains("Lifetimes bound references."), "{}", content);
+}
+
+#[test]
+fn test_env_facts_reach_the_answer() {
+    use std::io::Write;
+
+    let claude = FakeClaude::answering("Fixed OPS-42 in ecce-slides.").unwrap();
+    let home = TempDir::new().unwrap();
+    let mut config = Config::default();
+    claude.install(&mut config);
+    let writer = config.agents.get_mut("writer").unwrap();
+    writer.env.insert("TICKET".to_string(), "OPS-42".to_string());
+    writer.env.insert("PROJECT".to_string(), "ecce-slides".to_string());
+    writer.env.insert("API_TOKEN".to_string(), "ecce-slides-token".to_string());
+    config.context_env = vec!["TICKET".to_string(), "PROJECT".to_string()];
+    let dir = home.path().join(".config").join("ecce");
+    fs::create_dir_all(&dir).unwrap();
+    fs::write(dir.join("config.json"), serde_json::to_string_pretty(&config).unwrap()).unwrap();
+
+    let mut child = ecce(home.path())
+        .args(["homo", "--stdin"])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child.stdin.take().unwrap().write_all(b"What changed?\n").unwrap();
+    let output = child.wait_with_output().unwrap();
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+
+    // Facts an agent is given are not secrets; only its token is
+    assert!(String::from_utf8_lossy(&output.stdout).contains("Fixed OPS-42 in ecce-slides."));
+}
@@ -1,5 +1,7 @@
+// Exercises the full CLI-side library
+#![cfg(feature = "cli")]
+
 use std::fs;
-use std::path::PathBuf;
 use tempfile::TempDir;
 
 // Integration tests for ecce package
@@ -18,10 +20,13 @@ fn test_config_persistence() {
         url: "https://api.test.com".to_string(),
         key: "test-key-123".to_string(),
         service: "claude-code".to_string(),
+        ..Default::default()
     };
 
     config.profiles.push(profile);
-    config.active_profile = Some("test-profile".to_string());
+    config
+        .active_profiles
+        .insert("claude-code".to_string(), "test-profile".to_string());
 
     // Save config
     let json = serde_json::to_string_pretty(&config).unwrap();
@@ -34,7 +39,10 @@ fn test_config_persistence() {
     // Verify
     assert_eq!(loaded_config.profiles.len(), 1);
     assert_eq!(loaded_config.profiles[0].name, "test-profile");
-    assert_eq!(loaded_config.active_profile, Some("test-profile".to_string()));
+    assert_eq!(
+        loaded_config.get_active_profile("claude-code").unwrap().name,
+        "test-profile"
+    );
 }
 
 #[test]
@@ -52,6 +60,7 @@ fn test_agent_export_import_roundtrip() {
         context_files: vec![],
         tools: Some(vec!["Read".to_string(), "Write".to_string()]),
         model: Some("sonnet".to_string()),
+        ..Default::default()
     };
 
     // Export agent to file
@@ -122,7 +131,7 @@ fn test_pattern_deduplication() {
     assert_eq!(patterns1.len(), 1);
 
     // Mark as processed
-    detector.mark_processed(&patterns1[0].content);
+    detector.mark_processed(&patterns1[0]);
 
     // Second detection - should be empty
     let patterns2 = detector.detect_patterns(text2);
@@ -141,6 +150,7 @@ fn test_config_with_multiple_entities() {
         url: "https://api1.com".to_string(),
         key: "key1".to_string(),
         service: "claude-code".to_string(),
+        ..Default::default()
     });
 
     config.profiles.push(Profile {
@@ -148,6 +158,7 @@ fn test_config_with_multiple_entities() {
         url: "https://api2.com".to_string(),
         key: "key2".to_string(),
         service: "claude-code".to_string(),
+        ..Default::default()
     });
 
     // Add agents
@@ -160,6 +171,7 @@ fn test_config_with_multiple_entities() {
             context_files: vec![],
             tools: None,
             model: None,
+            ..Default::default()
         },
     );
 
@@ -169,6 +181,7 @@ fn test_config_with_multiple_entities() {
         Task {
             name: "task1".to_string(),
             template: "Task 1 template".to_string(),
+            ..Default::default()
         },
     );
 
@@ -205,6 +218,7 @@ fn test_profile_switching() {
         url: "https://dev.api.com".to_string(),
         key: "dev-key".to_string(),
         service: "claude-code".to_string(),
+        ..Default::default()
     });
 
     config.profiles.push(Profile {
@@ -212,15 +226,20 @@ fn test_profile_switching() {
         url: "https://prod.api.com".to_string(),
         key: "prod-key".to_string(),
         service: "claude-code".to_string(),
+        ..Default::default()
     });
 
     // Set active profile
-    config.active_profile = Some("dev".to_string());
-    assert_eq!(config.get_active_profile().unwrap().name, "dev");
+    config
+        .active_profiles
+        .insert("claude-code".to_string(), "dev".to_string());
+    assert_eq!(config.get_active_profile("claude-code").unwrap().name, "dev");
 
     // Switch profile
-    config.active_profile = Some("prod".to_string());
-    assert_eq!(config.get_active_profile().unwrap().name, "prod");
+    config
+        .active_profiles
+        .insert("claude-code".to_string(), "prod".to_string());
+    assert_eq!(config.get_active_profile("claude-code").unwrap().name, "prod");
 }
 
 #[test]
@@ -237,6 +256,7 @@ fn test_agent_with_context_files() {
         ],
         tools: Some(vec!["Read".to_string()]),
         model: Some("opus".to_string()),
+        ..Default::default()
     };
 
     assert_eq!(agent.context_files.len(), 2);
@@ -250,17 +270,17 @@ fn test_pattern_hash_consistency() {
     let mut detector1 = PatternDetector::new();
     let mut detector2 = PatternDetector::new();
 
-    let content = "test pattern content";
+    let pattern = detector1.detect_patterns("ecce test pattern content ecce").remove(0);
 
     // Mark in first detector
-    detector1.mark_processed(content);
+    detector1.mark_processed(&pattern);
 
     // Mark in second detector
-    detector2.mark_processed(content);
+    detector2.mark_processed(&pattern);
 
     // Both should recognize it as processed
-    assert!(detector1.is_processed(content));
-    assert!(detector2.is_processed(content));
+    assert!(detector1.is_processed(&pattern));
+    assert!(detector2.is_processed(&pattern));
 }
 
 #[test]
@@ -270,7 +290,7 @@ fn test_config_default_values() {
     let config = Config::default();
 
     assert!(config.profiles.is_empty());
-    assert!(config.active_profile.is_none());
+    assert!(config.active_profiles.is_empty());
     assert!(config.default_profile.is_none());
     assert!(config.agents.is_empty());
     assert!(config.tasks.is_empty());